@@ -0,0 +1,135 @@
+//! Request and response bodies for the wabba-server HTTP API, shared by the
+//! server and wabba-tools.
+
+use serde::{Deserialize, Serialize};
+
+use crate::archive_state::ArchiveState;
+
+/// Response header carrying the xxHash64 (base64) of the whole file on
+/// mod and modlist downloads, so clients can check what they received.
+pub const CONTENT_HASH_HEADER: &str = "X-Content-Hash";
+
+/// Request header naming the machine or user a client runs for, e.g. a
+/// hostname, so the server can attribute transfers to it.
+pub const CLIENT_HEADER: &str = "X-Wabba-Client";
+
+/// Response header on the paged listings (`GET /api/modlists`,
+/// `GET /api/mods`) carrying the cursor to pass as `after` for the next
+/// page. Absent on the last page.
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+/// Which store an archive belongs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveKind {
+    Modlist,
+    Mod,
+}
+
+impl ArchiveKind {
+    /// `.wabbajack` files are modlists; everything else is a mod.
+    pub fn for_filename(filename: &str) -> Self {
+        if filename.to_lowercase().ends_with(".wabbajack") {
+            Self::Modlist
+        } else {
+            Self::Mod
+        }
+    }
+
+    /// The path segment used by `/check/{kind}` and `/submit/{kind}/...`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Modlist => "modlist",
+            Self::Mod => "mod",
+        }
+    }
+}
+
+/// Body of `POST /check`: asks about many hashes in one round trip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct HashCheckRequest {
+    pub kind: ArchiveKind,
+    /// xxHash64 values, base64, as produced by `Hash::compute`.
+    pub hashes: Vec<String>,
+    /// Sizes in bytes of the files, in the order of `hashes`. When given, a
+    /// stored file only counts as available if its size matches as well.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sizes: Vec<u64>,
+}
+
+/// Response to `POST /check`. Every requested hash appears in exactly one of
+/// the two lists.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct AvailabilityReport {
+    /// Hashes the server already holds a file for.
+    pub available: Vec<String>,
+    /// Hashes the server would accept an upload for.
+    pub missing: Vec<String>,
+}
+
+/// Response to a successful `POST /submit/{kind}/{filename}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct UploadResult {
+    pub kind: ArchiveKind,
+    /// The name the file was stored under. Differs from the requested name
+    /// when another file already had it.
+    pub filename: String,
+    pub hash: String,
+    pub size: u64,
+}
+
+/// An entry in `GET /api/modlists`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ModlistSummary {
+    pub id: u64,
+    pub name: String,
+    pub version: String,
+    pub filename: String,
+    pub size: u64,
+    pub hash: String,
+    /// Whether the server holds the `.wabbajack` file itself.
+    pub available: bool,
+    pub muted: bool,
+    /// Required (non-optional) archives.
+    pub mods_total: u64,
+    /// Required archives the server holds.
+    pub mods_available: u64,
+}
+
+/// An entry in `GET /api/mods`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ModSummary {
+    pub id: u64,
+    /// The name a modlist gives the archive, or the stored filename for
+    /// mods no modlist references.
+    pub filename: String,
+    pub name: Option<String>,
+    pub size: u64,
+    pub hash: String,
+    pub available: bool,
+    pub lost_forever: bool,
+}
+
+/// An entry in `GET /api/modlists/{id}/archives`: one archive the modlist
+/// lists, as recorded when it was uploaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ModlistArchive {
+    /// The name the modlist gives the archive.
+    pub filename: String,
+    pub size: u64,
+    pub hash: String,
+    /// Where the archive downloads from, as in the `.wabbajack` file.
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub state: ArchiveState,
+    /// The archive's `Meta` ini, when the modlist has one.
+    pub meta: Option<String>,
+    /// Marked optional on the server, so left out of availability counts.
+    pub optional: bool,
+}
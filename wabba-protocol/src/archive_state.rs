@@ -1,6 +1,22 @@
 #![allow(unused)]
 use serde::{Deserialize, Serialize};
 
+/// Short, stable identifiers for each downloader type paired with a display
+/// label. The identifier is what the server stores in its indexed
+/// `source_type` column, so these must not change once released.
+pub const SOURCE_TYPES: &[(&str, &str)] = &[
+    ("nexus", "Nexus Mods"),
+    ("manual", "Manual"),
+    ("cdn", "Wabbajack CDN"),
+    ("http", "HTTP"),
+    ("gdrive", "Google Drive"),
+    ("mega", "MEGA"),
+    ("mediafire", "MediaFire"),
+    ("loverslab", "LoversLab"),
+    ("gamefile", "Game File"),
+    ("unknown", "Unknown"),
+];
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "$type")]
 pub enum ArchiveState {
@@ -131,4 +147,109 @@ impl ArchiveState {
             | ArchiveState::UnknownDownloader => None,
         }
     }
+
+    /// The human-readable instructions Wabbajack shows for archives that must
+    /// be fetched by hand, along with the page the user is sent to.
+    pub fn manual_prompt(&self) -> Option<(String, String)> {
+        match self {
+            ArchiveState::ManualDownloader { prompt, url } => Some((prompt.clone(), url.clone())),
+            _ => None,
+        }
+    }
+
+    /// The contents of the `.meta` file Wabbajack (and Mod Organizer) keep
+    /// next to a downloaded archive, which is how Wabbajack recognizes an
+    /// existing download as belonging to this source. `None` for sources
+    /// that don't have one.
+    pub fn meta_ini(&self) -> Option<String> {
+        let fields = match self {
+            ArchiveState::NexusDownloader {
+                game_name,
+                mod_id,
+                file_id,
+                ..
+            } => vec![
+                ("gameName", game_name.clone()),
+                ("modID", mod_id.to_string()),
+                ("fileID", file_id.to_string()),
+            ],
+            ArchiveState::HttpDownloader { url, .. }
+            | ArchiveState::WabbajackCDNDownloader { url }
+            | ArchiveState::MegaDownloader { url }
+            | ArchiveState::MediaFireDownloader { url } => vec![("directURL", url.clone())],
+            ArchiveState::GoogleDriveDownloader { id } => vec![(
+                "directURL",
+                format!("https://drive.google.com/uc?id={}&export=download", id),
+            )],
+            ArchiveState::ManualDownloader { url, .. } => vec![("manualURL", url.clone())],
+            ArchiveState::LoversLabOAuthDownloader {
+                ips4_mod,
+                ips4_file,
+                ..
+            } => {
+                let mut fields = vec![
+                    ("ips4Site", "Lovers Lab".to_string()),
+                    ("ips4Mod", ips4_mod.to_string()),
+                ];
+                if let Some(file) = ips4_file {
+                    fields.push(("ips4File", file.clone()));
+                }
+                fields
+            }
+            ArchiveState::GameFileSourceDownloader { .. } | ArchiveState::UnknownDownloader => {
+                return None;
+            }
+        };
+
+        let mut ini = String::from("[General]\n");
+        for (key, value) in fields {
+            ini.push_str(&format!("{}={}\n", key, value));
+        }
+        ini.push_str("installed=false\n");
+        Some(ini)
+    }
+
+    /// The page or file a person can download the archive from, for
+    /// sources that have one.
+    pub fn source_url(&self) -> Option<String> {
+        match self {
+            ArchiveState::NexusDownloader {
+                game_name,
+                mod_id,
+                file_id,
+                ..
+            } => Some(format!(
+                "https://www.nexusmods.com/{}/mods/{}?tab=files&file_id={}",
+                game_name.to_lowercase().replace(' ', ""),
+                mod_id,
+                file_id
+            )),
+            ArchiveState::HttpDownloader { url, .. }
+            | ArchiveState::WabbajackCDNDownloader { url }
+            | ArchiveState::ManualDownloader { url, .. }
+            | ArchiveState::MegaDownloader { url }
+            | ArchiveState::MediaFireDownloader { url } => Some(url.clone()),
+            ArchiveState::GoogleDriveDownloader { id } => {
+                Some(format!("https://drive.google.com/file/d/{}", id))
+            }
+            ArchiveState::LoversLabOAuthDownloader { ips4_url, .. } => Some(ips4_url.clone()),
+            ArchiveState::GameFileSourceDownloader { .. } | ArchiveState::UnknownDownloader => None,
+        }
+    }
+
+    /// The short identifier for this downloader type (see [`SOURCE_TYPES`]).
+    pub fn source_type(&self) -> &'static str {
+        match self {
+            ArchiveState::NexusDownloader { .. } => "nexus",
+            ArchiveState::HttpDownloader { .. } => "http",
+            ArchiveState::GameFileSourceDownloader { .. } => "gamefile",
+            ArchiveState::WabbajackCDNDownloader { .. } => "cdn",
+            ArchiveState::ManualDownloader { .. } => "manual",
+            ArchiveState::MegaDownloader { .. } => "mega",
+            ArchiveState::GoogleDriveDownloader { .. } => "gdrive",
+            ArchiveState::MediaFireDownloader { .. } => "mediafire",
+            ArchiveState::LoversLabOAuthDownloader { .. } => "loverslab",
+            ArchiveState::UnknownDownloader => "unknown",
+        }
+    }
 }
@@ -0,0 +1,67 @@
+use std::path::{Component, Path, PathBuf};
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalizes a file name to Unicode NFC.
+///
+/// macOS tends to hand out decomposed (NFD) names while Windows and most
+/// Linux tools produce composed (NFC) ones, so the same visible name can
+/// arrive as two different byte sequences. Compare and store normalized
+/// names; only use the raw name to actually open the file.
+pub fn normalize_filename(name: &str) -> String {
+    name.nfc().collect()
+}
+
+/// The decomposed (NFD) spelling of `name`, for matching raw names that
+/// were stored before normalization.
+pub fn decomposed_filename(name: &str) -> String {
+    name.nfd().collect()
+}
+
+/// Whether two file names are the same once normalized.
+pub fn filenames_match(a: &str, b: &str) -> bool {
+    a == b || normalize_filename(a) == normalize_filename(b)
+}
+
+/// Joins an archive file name taken from a modlist onto `dir`.
+///
+/// Modlists are downloaded from anywhere, so a name that could land outside
+/// `dir` is rejected: path separators (either kind, whatever the platform),
+/// `.`, `..`, NUL bytes and absolute or drive-relative names.
+pub fn safe_join(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    if name.contains(['/', '\\', '\0']) {
+        return Err(format!("Unsafe archive filename {:?}", name));
+    }
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(dir.join(name)),
+        _ => Err(format!("Unsafe archive filename {:?}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_join_keeps_archives_inside_the_directory() {
+        let dir = Path::new("/downloads");
+        assert_eq!(
+            safe_join(dir, "Mod 1.0.7z"),
+            Ok(PathBuf::from("/downloads/Mod 1.0.7z"))
+        );
+        for name in [
+            "",
+            ".",
+            "..",
+            "../evil.7z",
+            "/etc/passwd",
+            "nested/Mod.7z",
+            "..\\evil.7z",
+            "C:\\Windows\\evil.dll",
+            "Mod\0.7z",
+        ] {
+            assert!(safe_join(dir, name).is_err(), "{:?} was accepted", name);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! The modlist metadata Wabbajack's gallery reads: a repository is a JSON
+//! array of `ModlistMetadata`, one per modlist.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModlistMetadata {
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub maintainers: Vec<String>,
+    /// A Wabbajack game name, such as `SkyrimSpecialEdition`.
+    pub game: String,
+    pub official: bool,
+    pub tags: Vec<String>,
+    pub nsfw: bool,
+    pub utility_list: bool,
+    pub image_contains_title: bool,
+    #[serde(rename = "DisplayVersionOnlyInInstallerView")]
+    pub display_version_only_in_installer_view: bool,
+    pub force_down: bool,
+    pub links: Links,
+    pub download_metadata: DownloadMetadata,
+    pub version: String,
+    /// RFC 3339.
+    #[serde(rename = "dateCreated")]
+    pub date_created: String,
+    /// RFC 3339.
+    #[serde(rename = "dateUpdated")]
+    pub date_updated: String,
+    #[serde(rename = "repositoryName")]
+    pub repository_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Links {
+    pub image: String,
+    pub readme: String,
+    pub download: String,
+    /// Identifies the modlist within its repository.
+    #[serde(rename = "machineURL")]
+    pub machine_url: String,
+    #[serde(rename = "discordURL")]
+    pub discord_url: String,
+    #[serde(rename = "websiteURL")]
+    pub website_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DownloadMetadata {
+    /// xxHash64 of the .wabbajack file, base64.
+    pub hash: String,
+    pub size: u64,
+    pub number_of_archives: u64,
+    pub size_of_archives: u64,
+    pub number_of_installed_files: u64,
+    pub size_of_installed_files: u64,
+}
+
+/// A `machineURL`: lowercase letters, digits and underscores.
+pub fn machine_name(name: &str) -> String {
+    let mut machine = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            machine.push(c.to_ascii_lowercase());
+        } else if !machine.ends_with('_') {
+            machine.push('_');
+        }
+    }
+    machine.trim_matches('_').to_string()
+}
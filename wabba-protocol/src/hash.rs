@@ -35,3 +35,31 @@ impl Hash {
         Ok(BASE64_STANDARD.encode(hash_bytes))
     }
 }
+
+/// Hashes data as it arrives, for checking a download while it streams.
+/// Produces the same base64 output as `Hash::compute`.
+pub struct StreamingHash {
+    hasher: Xxh64,
+}
+
+impl StreamingHash {
+    pub fn new() -> Self {
+        StreamingHash {
+            hasher: Xxh64::new(0),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    pub fn finish(&self) -> String {
+        BASE64_STANDARD.encode(self.hasher.digest().to_le_bytes())
+    }
+}
+
+impl Default for StreamingHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,9 +1,11 @@
 // Protocol definitions for Wabba communication
 
+pub mod api;
 pub mod archive_state;
+pub mod filename;
+pub mod gallery;
 pub mod hash;
+pub mod mega;
+#[cfg(feature = "test_util")]
+pub mod test_util;
 pub mod wabbajack;
-
-pub mod protocol {
-    // Protocol types and structures will go here
-}
@@ -0,0 +1,104 @@
+use aes::cipher::KeyIvInit;
+use base64::prelude::*;
+
+/// MEGA's public API endpoint.
+pub const MEGA_API_URL: &str = "https://g.api.mega.co.nz/cs";
+
+/// AES-128-CTR, which MEGA encrypts file contents with.
+pub type MegaCipher = ctr::Ctr128BE<aes::Aes128>;
+
+/// A public MEGA file link: the node handle plus the file key from the URL
+/// fragment, which never reaches MEGA's servers.
+#[derive(Debug, Clone)]
+pub struct MegaLink {
+    pub handle: String,
+    key: [u8; 16],
+    iv: [u8; 16],
+}
+
+impl MegaLink {
+    /// Accepts both `https://mega.nz/file/<handle>#<key>` and the older
+    /// `https://mega.nz/#!<handle>!<key>` form.
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (handle, key) = if let Some((_, rest)) = url.split_once("/file/") {
+            rest.split_once('#')
+                .ok_or_else(|| "MEGA link has no decryption key".to_string())?
+        } else if let Some((_, rest)) = url.split_once("#!") {
+            rest.split_once('!')
+                .ok_or_else(|| "MEGA link has no decryption key".to_string())?
+        } else {
+            return Err(format!("Unrecognised MEGA link: {}", url));
+        };
+        // Folder-relative links append more after the key.
+        let key = key.split(['/', '?', '&']).next().unwrap_or(key);
+
+        let raw = BASE64_URL_SAFE_NO_PAD
+            .decode(key.trim_end_matches('='))
+            .map_err(|e| format!("Invalid MEGA key: {}", e))?;
+        if raw.len() != 32 {
+            return Err(format!("MEGA file key is {} bytes, expected 32", raw.len()));
+        }
+
+        // The 256-bit node key folds into the AES key; its third quarter is
+        // the CTR nonce.
+        let mut aes_key = [0u8; 16];
+        for (i, byte) in aes_key.iter_mut().enumerate() {
+            *byte = raw[i] ^ raw[i + 16];
+        }
+        let mut iv = [0u8; 16];
+        iv[..8].copy_from_slice(&raw[16..24]);
+
+        Ok(MegaLink {
+            handle: handle.to_string(),
+            key: aes_key,
+            iv,
+        })
+    }
+
+    pub fn cipher(&self) -> MegaCipher {
+        MegaCipher::new(&self.key.into(), &self.iv.into())
+    }
+
+    /// The API command asking for a temporary download URL, to POST to
+    /// [`MEGA_API_URL`] with `id=0` (and `sid=<session>` to use an
+    /// account's quota instead of the anonymous per-IP one).
+    pub fn download_request(&self) -> serde_json::Value {
+        serde_json::json!([{ "a": "g", "g": 1, "p": self.handle }])
+    }
+}
+
+/// The download URL from MEGA's answer to [`MegaLink::download_request`].
+/// The data served from it is still encrypted; run it through
+/// [`MegaLink::cipher`].
+pub fn download_url_from_response(body: &serde_json::Value) -> Result<String, String> {
+    // Errors come back as a bare negative number, either alone or as
+    // the only element of the result array.
+    let result = body.get(0).unwrap_or(body);
+    if let Some(code) = result.as_i64() {
+        return Err(describe_error(code));
+    }
+    if let Some(code) = result.get("e").and_then(|e| e.as_i64()) {
+        return Err(describe_error(code));
+    }
+    result
+        .get("g")
+        .and_then(|g| g.as_str())
+        .map(|g| g.to_string())
+        .ok_or_else(|| format!("MEGA API response has no download URL: {}", body))
+}
+
+fn describe_error(code: i64) -> String {
+    let reason = match code {
+        -2 => "invalid request",
+        -3 => "server busy, try again later",
+        -4 | -6 => "rate limited, try again later",
+        -9 => "file not found",
+        -11 => "access denied",
+        -15 => "session expired, set a new MEGA session ID",
+        -16 => "file taken down",
+        -17 => "transfer quota exceeded",
+        -18 => "temporarily unavailable",
+        _ => "unknown error",
+    };
+    format!("MEGA error {}: {}", code, reason)
+}
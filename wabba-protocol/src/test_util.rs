@@ -0,0 +1,155 @@
+//! Builders for synthetic .wabbajack files, for tests and demo data. Only
+//! built with the `test_util` feature.
+
+use std::io::Write;
+use std::path::Path;
+
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::archive_state::ArchiveState;
+use crate::hash::Hash;
+
+/// A mod archive a synthetic modlist refers to: its file name, its bytes and
+/// where Wabbajack would download it from.
+#[derive(Debug, Clone)]
+pub struct TestArchive {
+    pub filename: String,
+    pub contents: Vec<u8>,
+    pub state: ArchiveState,
+    /// The archive's `Meta` ini; empty unless set.
+    pub meta: String,
+}
+
+impl TestArchive {
+    /// An archive downloaded over plain HTTP from example.com.
+    pub fn new(filename: &str, contents: &[u8]) -> Self {
+        TestArchive {
+            filename: filename.to_string(),
+            contents: contents.to_vec(),
+            state: ArchiveState::HttpDownloader {
+                url: format!("https://example.com/{}", filename),
+                headers: serde_json::json!([]),
+            },
+            meta: String::new(),
+        }
+    }
+
+    pub fn with_state(mut self, state: ArchiveState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn with_meta(mut self, meta: &str) -> Self {
+        self.meta = meta.to_string();
+        self
+    }
+
+    pub fn hash(&self) -> String {
+        Hash::compute(&self.contents)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.contents.len() as u64
+    }
+}
+
+/// Builds a .wabbajack zip holding just the `modlist` entry, which is all
+/// `WabbajackMetadata::load` reads.
+#[derive(Debug, Clone)]
+pub struct ModlistBuilder {
+    name: String,
+    version: String,
+    author: String,
+    game_type: String,
+    is_nsfw: bool,
+    archives: Vec<TestArchive>,
+}
+
+impl ModlistBuilder {
+    pub fn new(name: &str) -> Self {
+        ModlistBuilder {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            author: "wabba-tools".to_string(),
+            game_type: "SkyrimSpecialEdition".to_string(),
+            is_nsfw: false,
+            archives: Vec::new(),
+        }
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> Self {
+        self.author = author.to_string();
+        self
+    }
+
+    pub fn game_type(mut self, game_type: &str) -> Self {
+        self.game_type = game_type.to_string();
+        self
+    }
+
+    pub fn nsfw(mut self, is_nsfw: bool) -> Self {
+        self.is_nsfw = is_nsfw;
+        self
+    }
+
+    pub fn archive(mut self, archive: TestArchive) -> Self {
+        self.archives.push(archive);
+        self
+    }
+
+    pub fn archives(mut self, archives: impl IntoIterator<Item = TestArchive>) -> Self {
+        self.archives.extend(archives);
+        self
+    }
+
+    /// The JSON stored in the `modlist` entry.
+    pub fn metadata(&self) -> serde_json::Value {
+        let archives: Vec<serde_json::Value> = self
+            .archives
+            .iter()
+            .map(|archive| {
+                serde_json::json!({
+                    "Hash": archive.hash(),
+                    "Meta": archive.meta,
+                    "Name": archive.filename,
+                    "Size": archive.size(),
+                    "State": archive.state,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "Archives": archives,
+            "Author": self.author,
+            "Description": format!("Synthetic modlist {}", self.name),
+            "Directives": [],
+            "Version": self.version,
+            "GameType": self.game_type,
+            "Image": "",
+            "Name": self.name,
+            "Readme": "",
+            "WabbajackVersion": "4.0.0.0",
+            "Website": "",
+            "IsNSFW": self.is_nsfw,
+        })
+    }
+
+    /// The .wabbajack file's bytes.
+    pub fn build(&self) -> Vec<u8> {
+        let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("modlist", SimpleFileOptions::default())
+            .expect("Failed to start zip entry");
+        zip.write_all(self.metadata().to_string().as_bytes())
+            .expect("Failed to write zip entry");
+        zip.finish().expect("Failed to finish zip").into_inner()
+    }
+
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.build())
+    }
+}
@@ -1,8 +1,10 @@
 #![allow(unused)]
 
 use serde::Deserialize;
+use std::io::Write;
 use std::{fs, path::PathBuf};
-use zip::ZipArchive;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
 
 use crate::archive_state::ArchiveState;
 
@@ -25,6 +27,17 @@ impl Archive {
     pub fn version(&self) -> Option<String> {
         self.state.version()
     }
+
+    /// The `.meta` file to write next to this archive. Prefers the modlist's
+    /// own `Meta`, since Wabbajack compares against those exact contents,
+    /// and falls back to one built from the source.
+    pub fn meta_ini(&self) -> Option<String> {
+        if self.meta.trim().is_empty() {
+            self.state.meta_ini()
+        } else {
+            Some(self.meta.clone())
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -47,10 +60,7 @@ pub struct WabbajackMetadata {
 
 impl WabbajackMetadata {
     pub fn load(path: &PathBuf) -> Result<WabbajackMetadata, Box<dyn std::error::Error>> {
-        let mut zip = ZipArchive::new(fs::File::open(path)?)?;
-        let mut file = zip.by_name("modlist")?;
-        let mut contents = String::new();
-        std::io::Read::read_to_string(&mut file, &mut contents)?;
+        let contents = String::from_utf8(read_entry(path, "modlist")?)?;
 
         let raw_value: serde_json::Value = serde_json::from_str(&contents)?;
         let formatted_value = serde_json::to_string_pretty(&raw_value)?;
@@ -62,6 +72,22 @@ impl WabbajackMetadata {
         Ok(metadata)
     }
 
+    /// Like `load`, without echoing the metadata to stdout.
+    pub fn load_quietly(path: &PathBuf) -> Result<WabbajackMetadata, Box<dyn std::error::Error>> {
+        let contents = read_entry(path, "modlist")?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    /// Installed file count and total installed size, from the directives.
+    pub fn installed_files(&self) -> (u64, u64) {
+        let size = self
+            .directives
+            .iter()
+            .filter_map(|directive| directive.get("Size").and_then(|size| size.as_u64()))
+            .sum();
+        (self.directives.len() as u64, size)
+    }
+
     pub fn files_from_unknown_downloaders(&self) -> Vec<String> {
         self.archives
             .iter()
@@ -85,6 +111,51 @@ impl WabbajackMetadata {
     }
 }
 
+/// Writes a copy of the .wabbajack file at `input` to `output`, replacing
+/// the state of every archive `patch` returns a new one for. Every other
+/// entry is copied byte for byte. Returns how many archives were patched.
+pub fn patch_archive_states(
+    input: &PathBuf,
+    output: &PathBuf,
+    mut patch: impl FnMut(&Archive) -> Option<ArchiveState>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut modlist: serde_json::Value = serde_json::from_slice(&read_entry(input, "modlist")?)?;
+    let mut patched = 0;
+    if let Some(archives) = modlist.get_mut("Archives").and_then(|a| a.as_array_mut()) {
+        for entry in archives {
+            let archive: Archive = serde_json::from_value(entry.clone())?;
+            if let Some(state) = patch(&archive) {
+                entry["State"] = serde_json::to_value(state)?;
+                patched += 1;
+            }
+        }
+    }
+
+    let mut source = ZipArchive::new(fs::File::open(input)?)?;
+    let mut zip = ZipWriter::new(fs::File::create(output)?);
+    for i in 0..source.len() {
+        let file = source.by_index_raw(i)?;
+        if file.name() == "modlist" {
+            continue;
+        }
+        zip.raw_copy_file(file)?;
+    }
+    zip.start_file("modlist", SimpleFileOptions::default())?;
+    zip.write_all(serde_json::to_string(&modlist)?.as_bytes())?;
+    zip.finish()?;
+    Ok(patched)
+}
+
+/// Reads one entry, such as `modlist` or the image it names, out of a
+/// .wabbajack file.
+pub fn read_entry(path: &PathBuf, name: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut zip = ZipArchive::new(fs::File::open(path)?)?;
+    let mut file = zip.by_name(name)?;
+    let mut contents = Vec::new();
+    std::io::Read::read_to_end(&mut file, &mut contents)?;
+    Ok(contents)
+}
+
 fn print_with_line_numbers(text: &str) {
     let lines = text.lines();
     for (i, line) in lines.enumerate() {
@@ -0,0 +1,225 @@
+use actix_web::middleware::from_fn;
+use actix_web::web::{self, Data, ServiceConfig};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+use wabba_server::serve_static_file;
+
+use crate::bandwidth::BandwidthLimiter;
+use crate::cold_storage::ColdStorage;
+use crate::config::{
+    DigestConfig, DownloaderConfig, MetadataRefreshConfig, OidcConfig, ServeFiles, UiMode,
+    VerificationPolicy,
+};
+use crate::credentials::CredentialVault;
+use crate::data_dir::DataDir;
+use crate::ignore::IgnorePatterns;
+use crate::jobs::JobRegistry;
+use crate::logging::LogControl;
+use crate::notifications::Notifier;
+use crate::openapi::ApiDoc;
+use crate::resources::backup::backup_database;
+use crate::resources::bootstrap::{bootstrap, bootstrap_modlists, bootstrap_mods};
+use crate::resources::digest::generate_digest;
+use crate::resources::downloader::{release_download_source, run_downloads};
+use crate::resources::downloads_import::import_downloads;
+use crate::resources::gallery::{gallery_repository, modlist_gallery_metadata, modlist_image};
+use crate::resources::inventory::{list_modlists, list_mods, mod_meta, modlist_archives};
+use crate::resources::jobs_api::{get_job, list_jobs};
+use crate::resources::loverslab::refresh_metadata;
+use crate::resources::offload::offload_mod;
+use crate::resources::reconciliation::reconcile;
+use crate::resources::reparse::{retry_all_modlist_parses, retry_modlist_parse};
+use crate::resources::resumable_upload::{
+    cancel_upload, create_upload, upload_chunk, upload_status,
+};
+use crate::resources::share_bundle::share_bundle;
+use crate::resources::storage_migration::migrate_storage;
+use crate::resources::verification::verify_mods;
+use crate::resources::wayback::lookup_wayback;
+use crate::resources::{
+    check_hashes, check_mod, check_modlist, hello_world, upload_mod, upload_modlist,
+};
+use crate::web::admin_credentials::{
+    admin_credentials_page, clear_credential, confirm_clear_credential, set_credential,
+};
+use crate::web::admin_jobs::{
+    admin_job_details_page, admin_jobs_page, cancel_job, confirm_job, confirm_job_page, retry_job,
+};
+use crate::web::auth::{callback, login, logout, require_admin};
+use crate::web::details_page::{
+    confirm_delete_mod, confirm_delete_modlist, confirm_toggle_lost_forever, delete_mod,
+    delete_modlist, details_page, download_mod, download_mod_by_hash, download_modlist,
+    mod_details_page, mod_image, rename_modlist, set_readiness_rule, toggle_lost_forever,
+    toggle_muted, toggle_optional,
+};
+use crate::web::diagnostics_page::{
+    diagnostics_page, raise_log_level, rebuild_modlist_counts, reset_log_levels,
+};
+use crate::web::fragments::modlist_availability_fragment;
+use crate::web::i18n::set_language;
+use crate::web::listing_page::{listing_page, mods_listing_page, muted_modlists_page};
+use crate::web::preferences::set_columns;
+use crate::web::stats_page::{download_digest_html, download_digest_json, stats_page};
+use crate::web::upload_page::{upload_page, upload_post};
+
+/// Everything the handlers pull out of `web::Data`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: Pool<SqliteConnectionManager>,
+    pub data_dir: DataDir,
+    pub jobs: JobRegistry,
+    pub ignore: IgnorePatterns,
+    pub verification: VerificationPolicy,
+    pub cold_storage: ColdStorage,
+    pub limiter: BandwidthLimiter,
+    pub downloader: DownloaderConfig,
+    pub vault: CredentialVault,
+    pub metadata_refresh: MetadataRefreshConfig,
+    pub digest: DigestConfig,
+    pub notifier: Notifier,
+    pub oidc: Option<OidcConfig>,
+    pub logging: LogControl,
+}
+
+/// Registers the shared state and every route. Used by the real server and
+/// by the in-process test server.
+pub fn configure(cfg: &mut ServiceConfig, state: &AppState) {
+    register_state(cfg, state, UiMode::Admin, ServeFiles(true));
+    register_read_only_routes(cfg);
+    register_file_routes(cfg);
+
+    cfg.service(login).service(callback).service(logout);
+
+    // Everything the routes above didn't match lands in this scope, so
+    // signed-in viewers are turned away from all of it at once.
+    cfg.service(
+        web::scope("")
+            .wrap(from_fn(require_admin))
+            .service(upload_modlist)
+            .service(upload_mod)
+            .service(confirm_toggle_lost_forever)
+            .service(toggle_lost_forever)
+            .service(toggle_muted)
+            .service(toggle_optional)
+            .service(set_readiness_rule)
+            .service(rename_modlist)
+            .service(confirm_delete_mod)
+            .service(delete_mod)
+            .service(confirm_delete_modlist)
+            .service(delete_modlist)
+            .service(offload_mod)
+            .service(migrate_storage)
+            .service(import_downloads)
+            .service(bootstrap)
+            .service(bootstrap_modlists)
+            .service(bootstrap_mods)
+            .service(verify_mods)
+            .service(reconcile)
+            .service(run_downloads)
+            .service(release_download_source)
+            .service(lookup_wayback)
+            .service(refresh_metadata)
+            .service(generate_digest)
+            .service(backup_database)
+            .service(admin_jobs_page)
+            .service(admin_job_details_page)
+            .service(cancel_job)
+            .service(retry_job)
+            .service(confirm_job_page)
+            .service(confirm_job)
+            .service(diagnostics_page)
+            .service(rebuild_modlist_counts)
+            .service(raise_log_level)
+            .service(reset_log_levels)
+            .service(retry_modlist_parse)
+            .service(retry_all_modlist_parses)
+            .service(list_jobs)
+            .service(get_job)
+            .service(SwaggerUi::new("/api/docs/{_:.*}").url("/api/openapi.json", ApiDoc::openapi()))
+            .service(admin_credentials_page)
+            .service(set_credential)
+            .service(confirm_clear_credential)
+            .service(clear_credential)
+            .service(upload_page)
+            .service(upload_post)
+            .service(create_upload)
+            .service(upload_status)
+            .service(upload_chunk)
+            .service(cancel_upload),
+    );
+}
+
+/// Registers the read-only pages for the public listener: no uploads,
+/// toggles, jobs or admin pages. Downloads are left off unless
+/// `serve_files` is set.
+pub fn configure_public(cfg: &mut ServiceConfig, state: &AppState, serve_files: bool) {
+    register_state(cfg, state, UiMode::Public, ServeFiles(serve_files));
+    register_read_only_routes(cfg);
+    if serve_files {
+        register_file_routes(cfg);
+    }
+}
+
+fn register_state(cfg: &mut ServiceConfig, state: &AppState, mode: UiMode, files: ServeFiles) {
+    cfg.app_data(Data::new(state.pool.clone()))
+        .app_data(Data::new(state.data_dir.clone()))
+        .app_data(Data::new(state.jobs.clone()))
+        .app_data(Data::new(state.ignore.clone()))
+        .app_data(Data::new(state.verification.clone()))
+        .app_data(Data::new(state.cold_storage.clone()))
+        .app_data(Data::new(state.limiter.clone()))
+        .app_data(Data::new(state.downloader.clone()))
+        .app_data(Data::new(state.vault.clone()))
+        .app_data(Data::new(state.metadata_refresh.clone()))
+        .app_data(Data::new(state.digest.clone()))
+        .app_data(Data::new(state.notifier.clone()))
+        .app_data(Data::new(state.oidc.clone()))
+        .app_data(Data::new(state.logging.clone()))
+        .app_data(Data::new(mode))
+        .app_data(Data::new(files));
+}
+
+/// Routes that never change anything, shared by both listeners.
+fn register_read_only_routes(cfg: &mut ServiceConfig) {
+    cfg.service(hello_world)
+        .service(check_modlist)
+        .service(check_mod)
+        .service(check_hashes)
+        .service(listing_page)
+        .service(mods_listing_page)
+        .service(muted_modlists_page)
+        .service(set_language)
+        .service(set_columns)
+        .service(details_page)
+        .service(mod_details_page)
+        .service(mod_image)
+        .service(modlist_image)
+        .service(list_modlists)
+        .service(list_mods)
+        .service(mod_meta)
+        .service(modlist_archives)
+        .service(stats_page)
+        .service(download_digest_json)
+        .service(download_digest_html)
+        .service(modlist_availability_fragment)
+        .service(serve_static_file!("htmx.min.js"))
+        .service(serve_static_file!("idiomorph.min.js"))
+        .service(serve_static_file!("idiomorph-ext.min.js"))
+        .service(serve_static_file!("styles.css"))
+        .service(serve_static_file!("resumable-upload.js"))
+        .service(serve_static_file!("confirm-dialog.js"))
+        .service(serve_static_file!("copy-hash.js"));
+}
+
+/// Routes handing out the archives themselves. Always on the admin listener;
+/// on the public one only when `PUBLIC_DOWNLOADS` is set.
+fn register_file_routes(cfg: &mut ServiceConfig) {
+    cfg.service(download_mod)
+        .service(download_mod_by_hash)
+        .service(download_modlist)
+        .service(modlist_gallery_metadata)
+        .service(share_bundle)
+        .service(gallery_repository);
+}
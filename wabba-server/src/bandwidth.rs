@@ -0,0 +1,45 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::config::BandwidthSchedule;
+
+/// Shared rate limiter for background transfers. Every worker throttles
+/// through the same instance, so the schedule caps their combined rate.
+#[derive(Debug, Clone)]
+pub struct BandwidthLimiter {
+    schedule: Arc<BandwidthSchedule>,
+    /// When the bytes reserved so far will have been "paid for".
+    next_free: Arc<Mutex<Instant>>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(schedule: BandwidthSchedule) -> Self {
+        BandwidthLimiter {
+            schedule: Arc::new(schedule),
+            next_free: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Bytes per second allowed right now; `None` means unlimited.
+    pub fn current_limit(&self) -> Option<u64> {
+        self.schedule.limit_at(chrono::Local::now().time())
+    }
+
+    /// Waits until `bytes` more can be sent without exceeding the current
+    /// limit. Call before sending each chunk.
+    pub async fn throttle(&self, bytes: usize) {
+        let Some(limit) = self.current_limit() else {
+            return;
+        };
+        let cost = Duration::from_secs_f64(bytes as f64 / limit as f64);
+        let wait_until = {
+            let mut next_free = self.next_free.lock().unwrap();
+            let start = (*next_free).max(Instant::now());
+            *next_free = start + cost;
+            start
+        };
+        tokio::time::sleep_until(wait_until).await;
+    }
+}
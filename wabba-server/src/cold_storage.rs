@@ -0,0 +1,185 @@
+use std::path::Path;
+use std::pin::Pin;
+
+use actix_web::web::Bytes;
+use futures_util::{Stream, StreamExt};
+use tokio::io::AsyncReadExt;
+
+use crate::bandwidth::BandwidthLimiter;
+
+/// Size of the chunks archives are streamed to and from cold storage in.
+const STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+
+pub type ColdStorageStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+
+/// Optional second tier for archives that don't need to stay on the NAS.
+///
+/// Offloaded archives are pushed to the backend and only a content
+/// reference is kept locally. Downloads stream them back through, keeping
+/// a copy in Downloads; those cached copies are evicted least recently
+/// used first once they add up to more than `cache_limit_bytes`.
+#[derive(Debug, Clone)]
+pub struct ColdStorage {
+    backend: Backend,
+    pub cache_limit_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    Disabled,
+    /// A Kubo (go-ipfs) node, reached through its RPC API. Archives are
+    /// pinned on add so the node's garbage collector leaves them alone.
+    Ipfs {
+        api_url: String,
+        client: reqwest::Client,
+    },
+}
+
+impl ColdStorage {
+    /// Reads the backend from the environment:
+    ///
+    /// - `COLD_STORAGE_IPFS_API` (e.g. `http://127.0.0.1:5001`) enables the
+    ///   IPFS backend; otherwise cold storage is disabled
+    /// - `COLD_STORAGE_CACHE_MB` caps the space used by copies fetched back
+    ///   from cold storage (default: unlimited)
+    pub fn from_env() -> Result<Self, String> {
+        let backend = match std::env::var("COLD_STORAGE_IPFS_API") {
+            Ok(api_url) if !api_url.trim().is_empty() => Backend::Ipfs {
+                api_url: api_url.trim().trim_end_matches('/').to_string(),
+                client: reqwest::Client::new(),
+            },
+            _ => Backend::Disabled,
+        };
+        let cache_limit_bytes = match std::env::var("COLD_STORAGE_CACHE_MB") {
+            Ok(value) => Some(
+                value
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| "COLD_STORAGE_CACHE_MB must be a non-negative integer")?
+                    * 1024
+                    * 1024,
+            ),
+            Err(_) => None,
+        };
+        Ok(ColdStorage {
+            backend,
+            cache_limit_bytes,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self.backend, Backend::Disabled)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.backend {
+            Backend::Disabled => "Disabled",
+            Backend::Ipfs { .. } => "IPFS",
+        }
+    }
+
+    /// Uploads the file at `path` and returns the reference to fetch it by.
+    /// This runs in the background, so it is paced by `limiter`.
+    pub async fn store(&self, path: &Path, limiter: &BandwidthLimiter) -> Result<String, String> {
+        match &self.backend {
+            Backend::Disabled => Err("Cold storage is not configured".to_string()),
+            Backend::Ipfs { api_url, client } => {
+                let file = tokio::fs::File::open(path)
+                    .await
+                    .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().replace('"', "_"))
+                    .unwrap_or_else(|| "archive".to_string());
+
+                // The add endpoint only takes multipart bodies. Build one by
+                // hand around a streamed file so multi-gigabyte archives
+                // never sit in memory.
+                let boundary = format!(
+                    "wabba-{}",
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_nanos())
+                        .unwrap_or(0)
+                );
+                let head = format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                    boundary, file_name
+                );
+                let tail = format!("\r\n--{}--\r\n", boundary);
+                let body = futures_util::stream::once(async move { Ok(head.into_bytes()) })
+                    .chain(file_chunks(file, limiter.clone()))
+                    .chain(futures_util::stream::once(
+                        async move { Ok(tail.into_bytes()) },
+                    ));
+
+                let response = client
+                    .post(format!("{}/api/v0/add?pin=true&cid-version=1", api_url))
+                    .header(
+                        "Content-Type",
+                        format!("multipart/form-data; boundary={}", boundary),
+                    )
+                    .body(reqwest::Body::wrap_stream(body))
+                    .send()
+                    .await
+                    .map_err(|e| format!("IPFS add failed: {}", e))?;
+                if !response.status().is_success() {
+                    return Err(format!(
+                        "IPFS add failed with {}: {}",
+                        response.status(),
+                        response.text().await.unwrap_or_default()
+                    ));
+                }
+
+                let added: serde_json::Value = response
+                    .json()
+                    .await
+                    .map_err(|e| format!("Unexpected IPFS add response: {}", e))?;
+                added["Hash"]
+                    .as_str()
+                    .map(|cid| cid.to_string())
+                    .ok_or_else(|| format!("IPFS add response has no Hash: {}", added))
+            }
+        }
+    }
+
+    /// Starts reading `reference` back from the backend.
+    pub async fn open(&self, reference: &str) -> Result<ColdStorageStream, String> {
+        match &self.backend {
+            Backend::Disabled => Err("Cold storage is not configured".to_string()),
+            Backend::Ipfs { api_url, client } => {
+                let response = client
+                    .post(format!("{}/api/v0/cat", api_url))
+                    .query(&[("arg", reference)])
+                    .send()
+                    .await
+                    .map_err(|e| format!("IPFS cat failed: {}", e))?;
+                if !response.status().is_success() {
+                    return Err(format!(
+                        "IPFS cat failed with {}: {}",
+                        response.status(),
+                        response.text().await.unwrap_or_default()
+                    ));
+                }
+
+                Ok(Box::pin(response.bytes_stream()))
+            }
+        }
+    }
+}
+
+fn file_chunks(
+    file: tokio::fs::File,
+    limiter: BandwidthLimiter,
+) -> impl Stream<Item = Result<Vec<u8>, std::io::Error>> {
+    futures_util::stream::try_unfold((file, limiter), |(mut file, limiter)| async move {
+        let mut buffer = vec![0; STREAM_CHUNK_SIZE];
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.truncate(read);
+        limiter.throttle(read).await;
+        Ok(Some((buffer, (file, limiter))))
+    })
+}
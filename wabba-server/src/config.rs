@@ -0,0 +1,463 @@
+use std::time::Duration;
+
+/// Which mods the periodic integrity re-hash job looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationStrategy {
+    /// Never run on a schedule; verification can still be started by hand.
+    Off,
+    /// Re-hash every available mod.
+    FullSweep,
+    /// Re-hash a random sample of available mods.
+    RandomSample,
+    /// Re-hash mods not verified in the last `stale_after_days` days,
+    /// oldest first.
+    Stale,
+}
+
+#[derive(Debug, Clone)]
+pub struct VerificationPolicy {
+    pub strategy: VerificationStrategy,
+    /// Time between scheduled runs.
+    pub interval: Duration,
+    /// Mods per run for `RandomSample` and `Stale`.
+    pub batch_size: u64,
+    pub stale_after_days: u64,
+}
+
+impl VerificationPolicy {
+    /// Reads the policy from the environment:
+    ///
+    /// - `VERIFY_STRATEGY`: `off`, `full`, `sample` or `stale` (default `stale`)
+    /// - `VERIFY_INTERVAL_HOURS`: defaults to 720 for `full`, 24 otherwise;
+    ///   0 means never, as with `off`
+    /// - `VERIFY_BATCH_SIZE`: default 200
+    /// - `VERIFY_STALE_DAYS`: default 30
+    pub fn from_env() -> Result<Self, String> {
+        let strategy = match std::env::var("VERIFY_STRATEGY")
+            .unwrap_or_else(|_| "stale".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "off" => VerificationStrategy::Off,
+            "full" => VerificationStrategy::FullSweep,
+            "sample" => VerificationStrategy::RandomSample,
+            "stale" => VerificationStrategy::Stale,
+            other => return Err(format!("Unknown VERIFY_STRATEGY: {}", other)),
+        };
+        let default_interval_hours = match strategy {
+            VerificationStrategy::FullSweep => 24 * 30,
+            _ => 24,
+        };
+        let interval_hours = env_u64("VERIFY_INTERVAL_HOURS", default_interval_hours)?;
+        Ok(VerificationPolicy {
+            strategy: if interval_hours > 0 {
+                strategy
+            } else {
+                VerificationStrategy::Off
+            },
+            interval: Duration::from_secs(interval_hours * 60 * 60),
+            batch_size: env_u64("VERIFY_BATCH_SIZE", 200)?,
+            stale_after_days: env_u64("VERIFY_STALE_DAYS", 30)?,
+        })
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> Result<u64, String> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} must be a non-negative integer", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// A window of the day with its own transfer rate. `start` may be after
+/// `end` for windows that wrap past midnight.
+#[derive(Debug, Clone)]
+pub struct BandwidthWindow {
+    pub start: chrono::NaiveTime,
+    pub end: chrono::NaiveTime,
+    /// Bytes per second; `None` means unlimited.
+    pub limit: Option<u64>,
+}
+
+impl BandwidthWindow {
+    fn contains(&self, time: chrono::NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Rate limits for background transfers (currently cold storage offloads).
+#[derive(Debug, Clone)]
+pub struct BandwidthSchedule {
+    /// Bytes per second outside every window; `None` means unlimited.
+    pub default_limit: Option<u64>,
+    pub windows: Vec<BandwidthWindow>,
+}
+
+impl BandwidthSchedule {
+    /// Reads the schedule from the environment:
+    ///
+    /// - `BANDWIDTH_LIMIT_KBPS`: limit outside any window (default unlimited)
+    /// - `BANDWIDTH_SCHEDULE`: comma-separated `HH:MM-HH:MM=<KB/s>` windows in
+    ///   server local time, where the rate may be `unlimited`, e.g.
+    ///   `01:00-07:00=unlimited,18:00-23:00=512`
+    pub fn from_env() -> Result<Self, String> {
+        let default_limit = match std::env::var("BANDWIDTH_LIMIT_KBPS") {
+            Ok(value) => parse_rate(&value)
+                .ok_or_else(|| format!("Invalid BANDWIDTH_LIMIT_KBPS: {}", value))?,
+            Err(_) => None,
+        };
+        let windows = std::env::var("BANDWIDTH_SCHEDULE")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|window| !window.is_empty())
+            .map(|window| {
+                parse_window(window)
+                    .ok_or_else(|| format!("Invalid BANDWIDTH_SCHEDULE window: {}", window))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BandwidthSchedule {
+            default_limit,
+            windows,
+        })
+    }
+
+    /// The limit in force at `time`. The first matching window wins.
+    pub fn limit_at(&self, time: chrono::NaiveTime) -> Option<u64> {
+        self.windows
+            .iter()
+            .find(|window| window.contains(time))
+            .map_or(self.default_limit, |window| window.limit)
+    }
+}
+
+/// `Some(None)` for unlimited, `Some(Some(bytes_per_second))` for a rate in
+/// KB/s, `None` if unparseable. Zero also means unlimited.
+fn parse_rate(value: &str) -> Option<Option<u64>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("unlimited") {
+        return Some(None);
+    }
+    let kbps: u64 = value.parse().ok()?;
+    Some((kbps > 0).then_some(kbps * 1024))
+}
+
+fn parse_window(window: &str) -> Option<BandwidthWindow> {
+    let (range, rate) = window.split_once('=')?;
+    let (start, end) = range.split_once('-')?;
+    Some(BandwidthWindow {
+        start: chrono::NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?,
+        end: chrono::NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?,
+        limit: parse_rate(rate)?,
+    })
+}
+
+/// How hard the background downloader tries a source before giving up on it.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Consecutive failures after which the source is quarantined and no
+    /// longer retried automatically.
+    pub max_attempts: u32,
+    /// Wait after the first failure; doubled after each further failure.
+    pub base_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            base_backoff: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Longest wait between attempts, however many failures there were.
+    const MAX_BACKOFF: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+    /// Wait before the next attempt after `failures` consecutive failures.
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.base_backoff
+            .saturating_mul(factor)
+            .min(Self::MAX_BACKOFF)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloaderConfig {
+    /// Time between scheduled runs; `None` leaves the downloader to be run
+    /// by hand.
+    pub interval: Option<Duration>,
+    pub default_policy: RetryPolicy,
+    /// Overrides keyed by source type (see `wabba_protocol::archive_state::SOURCE_TYPES`).
+    pub policies: Vec<(String, RetryPolicy)>,
+}
+
+impl DownloaderConfig {
+    /// Reads the downloader settings from the environment:
+    ///
+    /// - `DOWNLOAD_INTERVAL_HOURS`: time between scheduled runs (default 0,
+    ///   meaning never)
+    /// - `DOWNLOAD_RETRY_POLICIES`: comma-separated
+    ///   `<source>=<max attempts>/<backoff minutes>` overrides, e.g.
+    ///   `http=8/30,gdrive=3/360`; `*` sets the default (5/60)
+    pub fn from_env() -> Result<Self, String> {
+        let interval_hours = env_u64("DOWNLOAD_INTERVAL_HOURS", 0)?;
+        let mut config = DownloaderConfig {
+            interval: (interval_hours > 0).then(|| Duration::from_secs(interval_hours * 60 * 60)),
+            default_policy: RetryPolicy::default(),
+            policies: Vec::new(),
+        };
+        for entry in std::env::var("DOWNLOAD_RETRY_POLICIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let invalid = || format!("Invalid DOWNLOAD_RETRY_POLICIES entry: {}", entry);
+            let (source, policy) = entry.split_once('=').ok_or_else(invalid)?;
+            let (attempts, minutes) = policy.split_once('/').ok_or_else(invalid)?;
+            let policy = RetryPolicy {
+                max_attempts: attempts.trim().parse().map_err(|_| invalid())?,
+                base_backoff: Duration::from_secs(
+                    minutes.trim().parse::<u64>().map_err(|_| invalid())? * 60,
+                ),
+            };
+            match source.trim() {
+                "*" => config.default_policy = policy,
+                source => config.policies.push((source.to_string(), policy)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn policy_for(&self, source_type: &str) -> RetryPolicy {
+        self.policies
+            .iter()
+            .find(|(source, _)| source == source_type)
+            .map_or(self.default_policy, |(_, policy)| *policy)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataRefreshConfig {
+    /// Time between scheduled runs; `None` leaves the refresh to be run by
+    /// hand.
+    pub interval: Option<Duration>,
+    /// OAuth client the stored LoversLab tokens were issued to. Needed to
+    /// renew an expired access token.
+    pub loverslab_client_id: Option<String>,
+}
+
+impl MetadataRefreshConfig {
+    /// Reads the metadata refresh settings from the environment:
+    ///
+    /// - `METADATA_REFRESH_INTERVAL_HOURS`: time between scheduled runs
+    ///   (default 168, 0 turns the schedule off)
+    /// - `LOVERSLAB_OAUTH_CLIENT_ID`: the OAuth client ID used to refresh
+    ///   LoversLab tokens
+    pub fn from_env() -> Result<Self, String> {
+        let interval_hours = env_u64("METADATA_REFRESH_INTERVAL_HOURS", 24 * 7)?;
+        Ok(MetadataRefreshConfig {
+            interval: (interval_hours > 0).then(|| Duration::from_secs(interval_hours * 60 * 60)),
+            loverslab_client_id: std::env::var("LOVERSLAB_OAUTH_CLIENT_ID")
+                .ok()
+                .filter(|id| !id.trim().is_empty()),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DigestConfig {
+    /// Time between scheduled digests; `None` leaves them to be generated
+    /// by hand.
+    pub interval: Option<Duration>,
+}
+
+impl DigestConfig {
+    /// Reads `DIGEST_INTERVAL_HOURS`: time between scheduled digests
+    /// (default 168, 0 turns the schedule off).
+    pub fn from_env() -> Result<Self, String> {
+        let interval_hours = env_u64("DIGEST_INTERVAL_HOURS", 24 * 7)?;
+        Ok(DigestConfig {
+            interval: (interval_hours > 0).then(|| Duration::from_secs(interval_hours * 60 * 60)),
+        })
+    }
+
+    /// How far back the first digest looks.
+    pub fn period(&self) -> Duration {
+        self.interval
+            .unwrap_or(Duration::from_secs(7 * 24 * 60 * 60))
+    }
+}
+
+/// Which version of the web UI a listener serves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiMode {
+    /// Every page, button and route.
+    Admin,
+    /// Read-only pages for sharing the archive's status. Mutating routes
+    /// aren't registered and the buttons that would call them are hidden.
+    Public,
+}
+
+impl UiMode {
+    pub fn is_admin(&self) -> bool {
+        *self == UiMode::Admin
+    }
+}
+
+/// Whether a listener serves the archives themselves: mod and modlist
+/// downloads, share bundles and the gallery feed pointing Wabbajack at them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServeFiles(pub bool);
+
+#[derive(Debug, Clone)]
+pub struct ListenConfig {
+    pub admin_addr: String,
+    /// `None` leaves the public UI off.
+    pub public_addr: Option<String>,
+    /// Lets the public UI serve files too. The admin UI always does.
+    pub public_downloads: bool,
+}
+
+impl ListenConfig {
+    /// Reads the listener addresses from the environment:
+    ///
+    /// - `LISTEN_ADDR`: the full admin UI and API (default `0.0.0.0:8080`)
+    /// - `PUBLIC_LISTEN_ADDR`: a read-only copy of the pages, e.g.
+    ///   `0.0.0.0:8081` (default off)
+    /// - `PUBLIC_DOWNLOADS`: `true` to serve downloads on the public UI
+    ///   (default `false`)
+    pub fn from_env() -> Result<Self, String> {
+        let admin_addr = std::env::var("LISTEN_ADDR")
+            .ok()
+            .filter(|addr| !addr.trim().is_empty())
+            .unwrap_or_else(|| "0.0.0.0:8080".to_string());
+        let public_addr = std::env::var("PUBLIC_LISTEN_ADDR")
+            .ok()
+            .filter(|addr| !addr.trim().is_empty());
+        if public_addr.as_ref() == Some(&admin_addr) {
+            return Err("PUBLIC_LISTEN_ADDR must differ from LISTEN_ADDR".to_string());
+        }
+        let public_downloads = match std::env::var("PUBLIC_DOWNLOADS")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "false" => false,
+            "true" => true,
+            other => {
+                return Err(format!(
+                    "PUBLIC_DOWNLOADS must be true or false, not {}",
+                    other
+                ));
+            }
+        };
+        Ok(ListenConfig {
+            admin_addr,
+            public_addr,
+            public_downloads,
+        })
+    }
+}
+
+/// Signing in to the web UI through an OpenID Connect provider such as
+/// Authelia or Keycloak.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// The provider's issuer URL; its discovery document is read from
+    /// `{issuer_url}/.well-known/openid-configuration`.
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Where the provider sends the browser back to; must end in
+    /// `/auth/callback` and be registered with the provider.
+    pub redirect_url: String,
+    /// The userinfo claim listing the user's groups.
+    pub groups_claim: String,
+    /// Members of any of these groups get the full admin UI.
+    pub admin_groups: Vec<String>,
+    /// Members of any of these groups get the read-only UI. Empty lets
+    /// every user the provider vouches for in as a viewer.
+    pub viewer_groups: Vec<String>,
+    /// Signs the session cookie.
+    pub session_secret: String,
+    /// Sent by wabba-tools as `Authorization: Bearer <token>` in place of
+    /// an admin session. `None` leaves uploads to signed-in admins.
+    pub api_token: Option<String>,
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl OidcConfig {
+    /// Reads the OIDC settings from the environment. Sign-in is off unless
+    /// `OIDC_ISSUER_URL` is set, in which case the rest are required:
+    ///
+    /// - `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET`
+    /// - `OIDC_REDIRECT_URL`, e.g. `https://wabba.example.com/auth/callback`
+    /// - `OIDC_ADMIN_GROUPS`: comma-separated
+    /// - `OIDC_VIEWER_GROUPS`: comma-separated (default: anyone)
+    /// - `OIDC_GROUPS_CLAIM`: default `groups`
+    /// - `SESSION_SECRET`: at least 32 characters
+    /// - `API_TOKEN`: at least 32 characters, for wabba-tools (default off)
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(issuer_url) = std::env::var("OIDC_ISSUER_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+        let required = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .ok_or_else(|| format!("{} is required when OIDC_ISSUER_URL is set", name))
+        };
+        let session_secret = required("SESSION_SECRET")?;
+        if session_secret.len() < 32 {
+            return Err("SESSION_SECRET must be at least 32 characters".to_string());
+        }
+        let api_token = std::env::var("API_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+        if api_token.as_ref().is_some_and(|token| token.len() < 32) {
+            return Err("API_TOKEN must be at least 32 characters".to_string());
+        }
+        let admin_groups = env_list("OIDC_ADMIN_GROUPS");
+        if admin_groups.is_empty() {
+            return Err("OIDC_ADMIN_GROUPS is required when OIDC_ISSUER_URL is set".to_string());
+        }
+        Ok(Some(OidcConfig {
+            issuer_url: issuer_url.trim_end_matches('/').to_string(),
+            client_id: required("OIDC_CLIENT_ID")?,
+            client_secret: required("OIDC_CLIENT_SECRET")?,
+            redirect_url: required("OIDC_REDIRECT_URL")?,
+            groups_claim: std::env::var("OIDC_GROUPS_CLAIM")
+                .ok()
+                .filter(|claim| !claim.trim().is_empty())
+                .unwrap_or_else(|| "groups".to_string()),
+            admin_groups,
+            viewer_groups: env_list("OIDC_VIEWER_GROUPS"),
+            session_secret,
+            api_token,
+        }))
+    }
+}
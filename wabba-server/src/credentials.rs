@@ -0,0 +1,170 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use sha2::{Digest, Sha256};
+
+use crate::db::credential::StoredCredential;
+
+/// Credentials the server knows how to use: stored name, label and what it
+/// is used for.
+pub const CREDENTIAL_KINDS: &[(&str, &str, &str)] = &[
+    (
+        NEXUS_API_KEY,
+        "Nexus Mods API key",
+        "Lets the downloader fetch Nexus archives (requires a Premium account).",
+    ),
+    (
+        LOVERSLAB_ACCESS_TOKEN,
+        "LoversLab OAuth access token",
+        "Used to refresh LoversLab file metadata.",
+    ),
+    (
+        LOVERSLAB_REFRESH_TOKEN,
+        "LoversLab OAuth refresh token",
+        "Used to renew the access token when it expires.",
+    ),
+    (
+        MEGA_SESSION,
+        "MEGA session ID",
+        "Sent with MEGA downloads so they count against your account's transfer quota.",
+    ),
+];
+
+pub const NEXUS_API_KEY: &str = "nexus_api_key";
+pub const LOVERSLAB_ACCESS_TOKEN: &str = "loverslab_access_token";
+pub const LOVERSLAB_REFRESH_TOKEN: &str = "loverslab_refresh_token";
+pub const MEGA_SESSION: &str = "mega_session";
+
+/// Encrypts third-party credentials at rest with AES-256-GCM. The key is
+/// derived from `CREDENTIALS_KEY`; without it nothing can be stored or
+/// read, and everything that needs a credential behaves as if it were
+/// unset.
+///
+/// The key is a single unsalted SHA-256 of `CREDENTIALS_KEY`, with no
+/// stretching, so a guessable passphrase falls to a dictionary attack on
+/// a copy of the database. It has to be a long random string, such as the
+/// output of `openssl rand -hex 32`.
+#[derive(Clone)]
+pub struct CredentialVault {
+    cipher: Option<Aes256Gcm>,
+}
+
+impl std::fmt::Debug for CredentialVault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialVault")
+            .field("unlocked", &self.is_unlocked())
+            .finish()
+    }
+}
+
+impl CredentialVault {
+    /// Reads the passphrase from `CREDENTIALS_KEY`. Changing it makes
+    /// existing credentials unreadable; they have to be set again.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("CREDENTIALS_KEY").ok().as_deref())
+    }
+
+    /// A vault keyed with `passphrase`, or a locked one without it.
+    pub fn new(passphrase: Option<&str>) -> Self {
+        let cipher = passphrase.filter(|key| !key.is_empty()).map(|key| {
+            let key = Sha256::digest(key.as_bytes());
+            Aes256Gcm::new(&key)
+        });
+        CredentialVault { cipher }
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    fn cipher(&self) -> Result<&Aes256Gcm, String> {
+        self.cipher
+            .as_ref()
+            .ok_or_else(|| "CREDENTIALS_KEY is not set".to_string())
+    }
+
+    /// Stores `value` under `name`, replacing any previous value.
+    pub fn set(
+        &self,
+        name: &str,
+        value: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), String> {
+        let cipher = self.cipher()?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        // The name is bound in as associated data so a row can't be copied
+        // over another credential.
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                aes_gcm::aead::Payload {
+                    msg: value.as_bytes(),
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| "Failed to encrypt credential".to_string())?;
+        StoredCredential::upsert(name, nonce.as_slice(), &ciphertext, conn)
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// The decrypted value of `name`, if set. A value that fails to decrypt
+    /// (for example after `CREDENTIALS_KEY` changed) is an error rather
+    /// than silently missing.
+    pub fn get(
+        &self,
+        name: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Option<String>, String> {
+        let Some(stored) = StoredCredential::get_by_name(name, conn)
+            .map_err(|e| format!("Database error: {}", e))?
+        else {
+            return Ok(None);
+        };
+        let cipher = self.cipher()?;
+        if stored.nonce.len() != 12 {
+            return Err(format!("Stored credential {} is corrupt", name));
+        }
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&stored.nonce),
+                aes_gcm::aead::Payload {
+                    msg: &stored.ciphertext,
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| {
+                format!(
+                    "Could not decrypt credential {}; was CREDENTIALS_KEY changed?",
+                    name
+                )
+            })?;
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|_| format!("Stored credential {} is corrupt", name))
+    }
+
+    /// Like `get`, but logs and swallows errors, for callers that can carry
+    /// on without the credential.
+    pub fn get_or_warn(
+        &self,
+        name: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Option<String> {
+        if !self.is_unlocked() {
+            return None;
+        }
+        self.get(name, conn).unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            None
+        })
+    }
+
+    pub fn clear(
+        &self,
+        name: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), String> {
+        StoredCredential::delete(name, conn).map_err(|e| format!("Database error: {}", e))
+    }
+}
@@ -15,6 +15,7 @@ impl DataDir {
 
         std::fs::create_dir_all(path.join("Modlists")).unwrap();
         std::fs::create_dir_all(path.join("Downloads")).unwrap();
+        std::fs::create_dir_all(path.join("Uploads")).unwrap();
 
         Ok(DataDir(path))
     }
@@ -35,13 +36,23 @@ impl DataDir {
         self.0.join("Downloads")
     }
 
+    /// Partial files for in-progress resumable uploads.
+    pub fn get_resumable_upload_dir(&self) -> PathBuf {
+        self.0.join("Uploads")
+    }
+
     #[allow(dead_code)]
     pub fn get_modlist_path(&self, modlist_filename: &str) -> PathBuf {
         self.get_modlist_dir().join(modlist_filename)
     }
 
+    /// `mod_filename` may be a `/`-separated path relative to Downloads for
+    /// mods kept in subfolders.
     #[allow(dead_code)]
     pub fn get_mod_path(&self, mod_filename: &str) -> PathBuf {
-        self.get_mod_dir().join(mod_filename)
+        mod_filename
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+            .fold(self.get_mod_dir(), |path, segment| path.join(segment))
     }
 }
@@ -0,0 +1,78 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+
+/// An encrypted third-party credential. Only `CredentialVault` can make
+/// sense of `ciphertext`.
+#[derive(Debug, Clone)]
+pub struct StoredCredential {
+    pub name: String,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub updated_at: i64,
+}
+
+impl StoredCredential {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(StoredCredential {
+            name: row.get(0)?,
+            nonce: row.get(1)?,
+            ciphertext: row.get(2)?,
+            updated_at: row.get(3)?,
+        })
+    }
+
+    pub fn get_by_name(
+        name: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Option<Self>, rusqlite::Error> {
+        let credential = conn
+            .prepare("SELECT name, nonce, ciphertext, updated_at FROM credential WHERE name = ?1")?
+            .query_row(params![name], |row| Ok(StoredCredential::from_row(row)))
+            .optional()?
+            .transpose()?;
+
+        Ok(credential)
+    }
+
+    pub fn get_all(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn
+            .prepare("SELECT name, nonce, ciphertext, updated_at FROM credential ORDER BY name")?;
+        let credentials = stmt
+            .query_map([], StoredCredential::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(credentials)
+    }
+
+    pub fn upsert(
+        name: &str,
+        nonce: &[u8],
+        ciphertext: &[u8],
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO credential (name, nonce, ciphertext, updated_at)
+             VALUES (?1, ?2, ?3, unixepoch())
+             ON CONFLICT(name) DO UPDATE SET
+                 nonce = excluded.nonce,
+                 ciphertext = excluded.ciphertext,
+                 updated_at = excluded.updated_at",
+        )?
+        .execute(params![name, nonce, ciphertext])?;
+
+        Ok(())
+    }
+
+    pub fn delete(
+        name: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare("DELETE FROM credential WHERE name = ?1")?
+            .execute(params![name])?;
+
+        Ok(())
+    }
+}
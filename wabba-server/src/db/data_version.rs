@@ -0,0 +1,29 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+
+/// Global counter bumped by triggers on every write to `modlist`, `mod` or
+/// `mod_association`. Used to validate cached copies of rendered pages.
+#[derive(Debug, Clone, Copy)]
+pub struct DataVersion {
+    pub version: u64,
+    pub updated_at: i64,
+}
+
+impl DataVersion {
+    pub fn get(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Self, rusqlite::Error> {
+        conn.query_row(
+            "SELECT version, updated_at FROM data_version WHERE id = 1",
+            [],
+            |row| {
+                Ok(DataVersion {
+                    version: row.get(0)?,
+                    updated_at: row.get(1)?,
+                })
+            },
+        )
+    }
+
+    pub fn last_modified(&self) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(self.updated_at.max(0) as u64)
+    }
+}
@@ -0,0 +1,304 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+
+/// How many past digests feed the disk usage trend.
+const DISK_TREND_POINTS: u32 = 12;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModlistAvailability {
+    pub modlist_id: u64,
+    pub name: String,
+    pub available: u64,
+    pub total: u64,
+}
+
+/// Availability and disk usage at one point in time. Each digest keeps the
+/// snapshot it was built from so the next one can tell what changed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AvailabilitySnapshot {
+    pub taken_at: i64,
+    pub mods_total: u64,
+    pub mods_available: u64,
+    /// Bytes of mods and modlists held locally.
+    pub disk_bytes: u64,
+    pub modlists: Vec<ModlistAvailability>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestModlist {
+    pub id: u64,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AvailabilityChange {
+    pub modlist_id: u64,
+    pub name: String,
+    /// `None` when the modlist is new since the previous digest.
+    pub available_before: Option<u64>,
+    pub available_after: u64,
+    pub total: u64,
+}
+
+/// A source the background downloader gave up on during the period.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeadLink {
+    pub mod_id: u64,
+    pub filename: String,
+    pub source_type: String,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiskUsagePoint {
+    pub taken_at: i64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Digest {
+    pub period_start: i64,
+    pub period_end: i64,
+    pub new_modlists: Vec<DigestModlist>,
+    /// Mods first seen during the period that arrived with their file.
+    pub new_mods: u64,
+    pub new_mods_bytes: u64,
+    pub availability_changes: Vec<AvailabilityChange>,
+    pub dead_links: Vec<DeadLink>,
+    /// Oldest first, ending with this digest's snapshot.
+    pub disk_usage: Vec<DiskUsagePoint>,
+    pub snapshot: AvailabilitySnapshot,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredDigest {
+    pub id: u64,
+    pub digest: Digest,
+}
+
+impl AvailabilitySnapshot {
+    pub fn take(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Self, rusqlite::Error> {
+        let (mods_total, mods_available, mod_bytes): (u64, u64, u64) = conn
+            .prepare(
+                "SELECT COUNT(*),
+                        COUNT(m.disk_filename),
+                        COALESCE(SUM(CASE WHEN m.disk_filename IS NOT NULL AND s.evicted IS NOT TRUE THEN m.size END), 0)
+                   FROM \"mod\" m
+                   LEFT JOIN mod_disk_state s ON s.mod_id = m.id",
+            )?
+            .query_row([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        let modlist_bytes: u64 = conn
+            .prepare("SELECT COALESCE(SUM(size), 0) FROM modlist WHERE available")?
+            .query_row([], |row| row.get(0))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT ml.id, ml.name, COUNT(m.disk_filename), COUNT(a.mod_id)
+               FROM modlist ml
+               LEFT JOIN mod_association a ON a.modlist_id = ml.id AND NOT a.optional
+               LEFT JOIN \"mod\" m ON m.id = a.mod_id
+              WHERE ml.parse_error IS NULL
+              GROUP BY ml.id
+              ORDER BY ml.name",
+        )?;
+        let modlists = stmt
+            .query_map([], |row| {
+                Ok(ModlistAvailability {
+                    modlist_id: row.get(0)?,
+                    name: row.get(1)?,
+                    available: row.get(2)?,
+                    total: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AvailabilitySnapshot {
+            taken_at: chrono::Utc::now().timestamp(),
+            mods_total,
+            mods_available,
+            disk_bytes: mod_bytes + modlist_bytes,
+            modlists,
+        })
+    }
+}
+
+impl Digest {
+    /// Builds the digest for everything since `previous` (or the last
+    /// `fallback_period_secs` if there is no previous digest).
+    pub fn build(
+        previous: Option<&Digest>,
+        fallback_period_secs: i64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Self, rusqlite::Error> {
+        let snapshot = AvailabilitySnapshot::take(conn)?;
+        let period_end = snapshot.taken_at;
+        let period_start = previous.map_or(period_end - fallback_period_secs, |digest| {
+            digest.period_end
+        });
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, version FROM modlist
+              WHERE created_at >= ?1 AND parse_error IS NULL
+              ORDER BY created_at",
+        )?;
+        let new_modlists = stmt
+            .query_map(params![period_start], |row| {
+                Ok(DigestModlist {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    version: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (new_mods, new_mods_bytes): (u64, u64) = conn
+            .prepare(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM \"mod\"
+                  WHERE created_at >= ?1 AND disk_filename IS NOT NULL",
+            )?
+            .query_row(params![period_start], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let availability_changes = snapshot
+            .modlists
+            .iter()
+            .filter_map(|current| {
+                let before = previous.and_then(|digest| {
+                    digest
+                        .snapshot
+                        .modlists
+                        .iter()
+                        .find(|m| m.modlist_id == current.modlist_id)
+                        .map(|m| m.available)
+                });
+                (before != Some(current.available)).then(|| AvailabilityChange {
+                    modlist_id: current.modlist_id,
+                    name: current.name.clone(),
+                    available_before: before,
+                    available_after: current.available,
+                    total: current.total,
+                })
+            })
+            .collect();
+
+        let mut stmt = conn.prepare(
+            "SELECT s.mod_id,
+                    COALESCE((SELECT a.filename FROM mod_association a WHERE a.mod_id = s.mod_id LIMIT 1), ''),
+                    s.source_type,
+                    s.consecutive_failures
+               FROM download_source_state s
+              WHERE s.quarantined
+                AND EXISTS (
+                    SELECT 1 FROM download_attempt d
+                     WHERE d.mod_id = s.mod_id AND d.source_type = s.source_type
+                       AND NOT d.ok AND d.attempted_at >= ?1
+                )
+              ORDER BY s.mod_id",
+        )?;
+        let dead_links = stmt
+            .query_map(params![period_start], |row| {
+                Ok(DeadLink {
+                    mod_id: row.get(0)?,
+                    filename: row.get(1)?,
+                    source_type: row.get(2)?,
+                    consecutive_failures: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut disk_usage: Vec<DiskUsagePoint> =
+            StoredDigest::get_recent(DISK_TREND_POINTS - 1, conn)?
+                .into_iter()
+                .rev()
+                .map(|stored| DiskUsagePoint {
+                    taken_at: stored.digest.snapshot.taken_at,
+                    bytes: stored.digest.snapshot.disk_bytes,
+                })
+                .collect();
+        disk_usage.push(DiskUsagePoint {
+            taken_at: snapshot.taken_at,
+            bytes: snapshot.disk_bytes,
+        });
+
+        Ok(Digest {
+            period_start,
+            period_end,
+            new_modlists,
+            new_mods,
+            new_mods_bytes,
+            availability_changes,
+            dead_links,
+            disk_usage,
+            snapshot,
+        })
+    }
+}
+
+impl StoredDigest {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        let payload: String = row.get(1)?;
+        let digest: Digest = serde_json::from_str(&payload).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                1,
+                rusqlite::types::Type::Text,
+                Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Failed to parse digest: {}", e),
+                )),
+            )
+        })?;
+
+        Ok(StoredDigest {
+            id: row.get(0)?,
+            digest,
+        })
+    }
+
+    pub fn get_by_id(
+        id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Option<Self>, rusqlite::Error> {
+        let digest = conn
+            .prepare("SELECT id, payload FROM digest WHERE id = ?1")?
+            .query_row(params![id], |row| Ok(StoredDigest::from_row(row)))
+            .optional()?
+            .transpose()?;
+
+        Ok(digest)
+    }
+
+    /// Newest first.
+    pub fn get_recent(
+        limit: u32,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn
+            .prepare("SELECT id, payload FROM digest ORDER BY period_end DESC, id DESC LIMIT ?1")?;
+        let digests = stmt
+            .query_map(params![limit], StoredDigest::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(digests)
+    }
+
+    pub fn create(
+        digest: Digest,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Self, rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO digest (period_start, period_end, payload)
+             VALUES (?1, ?2, ?3)",
+        )?
+        .execute(params![
+            digest.period_start,
+            digest.period_end,
+            serde_json::to_string(&digest).unwrap()
+        ])?;
+
+        Ok(StoredDigest {
+            id: conn.last_insert_rowid() as u64,
+            digest,
+        })
+    }
+}
@@ -0,0 +1,240 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use wabba_protocol::archive_state::ArchiveState;
+
+use crate::config::RetryPolicy;
+use crate::db::mod_data::Mod;
+
+/// One try by the background downloader to fetch a mod from a source.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadAttempt {
+    pub id: u64,
+    pub mod_id: u64,
+    pub source_type: String,
+    pub attempted_at: i64,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// Retry bookkeeping for a mod's source. A row only exists while the source
+/// is failing; a successful download removes it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadSourceState {
+    pub mod_id: u64,
+    pub source_type: String,
+    pub consecutive_failures: u32,
+    pub next_attempt_at: i64,
+    /// Failed too often; skipped until released by hand.
+    pub quarantined: bool,
+}
+
+/// A missing mod and one of the sources it could be downloaded from.
+#[derive(Debug, Clone)]
+pub struct DownloadCandidate {
+    pub mod_item: Mod,
+    pub source: ArchiveState,
+    pub filename: String,
+}
+
+/// How many attempts are shown on a mod's page.
+const ATTEMPT_HISTORY_LIMIT: u32 = 50;
+
+impl DownloadAttempt {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(DownloadAttempt {
+            id: row.get(0)?,
+            mod_id: row.get(1)?,
+            source_type: row.get(2)?,
+            attempted_at: row.get(3)?,
+            ok: row.get(4)?,
+            error: row.get(5)?,
+        })
+    }
+
+    pub fn record(
+        mod_id: u64,
+        source_type: &str,
+        error: Option<&str>,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO download_attempt (mod_id, source_type, attempted_at, ok, error)
+             VALUES (?1, ?2, unixepoch(), ?3, ?4)",
+        )?
+        .execute(params![mod_id, source_type, error.is_none(), error])?;
+
+        Ok(())
+    }
+
+    /// Most recent attempts first.
+    pub fn get_by_mod_id(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT id, mod_id, source_type, attempted_at, ok, error
+             FROM download_attempt
+             WHERE mod_id = ?1
+             ORDER BY attempted_at DESC, id DESC
+             LIMIT ?2",
+        )?;
+        let attempts = stmt
+            .query_map(
+                params![mod_id, ATTEMPT_HISTORY_LIMIT],
+                DownloadAttempt::from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(attempts)
+    }
+}
+
+impl DownloadSourceState {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(DownloadSourceState {
+            mod_id: row.get(0)?,
+            source_type: row.get(1)?,
+            consecutive_failures: row.get(2)?,
+            next_attempt_at: row.get(3)?,
+            quarantined: row.get(4)?,
+        })
+    }
+
+    pub fn get_by_mod_id(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT mod_id, source_type, consecutive_failures, next_attempt_at, quarantined
+             FROM download_source_state
+             WHERE mod_id = ?1
+             ORDER BY source_type",
+        )?;
+        let states = stmt
+            .query_map(params![mod_id], DownloadSourceState::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(states)
+    }
+
+    pub fn record_success(
+        mod_id: u64,
+        source_type: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare("DELETE FROM download_source_state WHERE mod_id = ?1 AND source_type = ?2")?
+            .execute(params![mod_id, source_type])?;
+
+        Ok(())
+    }
+
+    /// Counts a failure, pushing the next attempt out by the policy's
+    /// backoff and quarantining the source once it runs out of attempts.
+    /// Returns the updated state.
+    pub fn record_failure(
+        mod_id: u64,
+        source_type: &str,
+        policy: &RetryPolicy,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Self, rusqlite::Error> {
+        let failures: u32 = conn
+            .prepare(
+                "SELECT consecutive_failures FROM download_source_state
+                 WHERE mod_id = ?1 AND source_type = ?2",
+            )?
+            .query_row(params![mod_id, source_type], |row| row.get(0))
+            .optional()?
+            .unwrap_or(0)
+            + 1;
+        let quarantined = failures >= policy.max_attempts;
+        let backoff = policy.backoff(failures).as_secs() as i64;
+
+        conn.prepare(
+            "INSERT INTO download_source_state (mod_id, source_type, consecutive_failures, next_attempt_at, quarantined)
+             VALUES (?1, ?2, ?3, unixepoch() + ?4, ?5)
+             ON CONFLICT(mod_id, source_type) DO UPDATE SET
+                 consecutive_failures = excluded.consecutive_failures,
+                 next_attempt_at = excluded.next_attempt_at,
+                 quarantined = excluded.quarantined",
+        )?
+        .execute(params![mod_id, source_type, failures, backoff, quarantined])?;
+
+        conn.prepare(
+            "SELECT mod_id, source_type, consecutive_failures, next_attempt_at, quarantined
+             FROM download_source_state
+             WHERE mod_id = ?1 AND source_type = ?2",
+        )?
+        .query_row(params![mod_id, source_type], DownloadSourceState::from_row)
+    }
+
+    /// Takes a source out of quarantine and makes it due immediately. The
+    /// failure count is reset so it gets a full set of attempts again.
+    pub fn release(
+        mod_id: u64,
+        source_type: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<bool, rusqlite::Error> {
+        let updated = conn
+            .prepare(
+                "UPDATE download_source_state
+                    SET quarantined = FALSE, consecutive_failures = 0, next_attempt_at = unixepoch()
+                  WHERE mod_id = ?1 AND source_type = ?2",
+            )?
+            .execute(params![mod_id, source_type])?;
+
+        Ok(updated > 0)
+    }
+
+    /// Missing, recoverable mods with an association of one of
+    /// `source_types` that is neither quarantined nor backing off. Each
+    /// (mod, source type) pair appears once.
+    pub fn select_due_candidates(
+        source_types: &[&str],
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<DownloadCandidate>, rusqlite::Error> {
+        if source_types.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; source_types.len()].join(", ");
+        let sql = format!(
+            "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever, a.source, a.filename
+               FROM \"mod\" m
+               JOIN mod_association a ON a.mod_id = m.id
+               LEFT JOIN download_source_state d
+                      ON d.mod_id = m.id AND d.source_type = a.source_type
+              WHERE m.disk_filename IS NULL
+                AND NOT m.lost_forever
+                AND a.source_type IN ({})
+                AND (d.mod_id IS NULL OR (NOT d.quarantined AND d.next_attempt_at <= unixepoch()))
+              GROUP BY m.id, a.source_type
+              ORDER BY m.id",
+            placeholders
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let candidates = stmt
+            .query_map(rusqlite::params_from_iter(source_types.iter()), |row| {
+                let source_str: String = row.get(5)?;
+                let source: ArchiveState = serde_json::from_str(&source_str).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        5,
+                        rusqlite::types::Type::Text,
+                        Box::new(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("Failed to parse ArchiveState: {}", e),
+                        )),
+                    )
+                })?;
+                Ok(DownloadCandidate {
+                    mod_item: Mod::from_row(row)?,
+                    source,
+                    filename: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(candidates)
+    }
+}
@@ -1,8 +1,10 @@
 use crate::prelude::*;
 use indoc::indoc;
 
-pub fn migrate(mut conn: PooledConnection<SqliteConnectionManager>) -> Result<()> {
-    let migrations: Migrations = Migrations::new(vec![
+/// Every schema change, oldest first. Existing databases only run the ones
+/// they haven't yet, so fixing up old rows takes a migration of its own.
+pub fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
         M::up(indoc! { r#"
           CREATE TABLE modlist (
               id INTEGER PRIMARY KEY NOT NULL,
@@ -49,7 +51,336 @@ pub fn migrate(mut conn: PooledConnection<SqliteConnectionManager>) -> Result<()
         M::up(indoc! { r#"
           CREATE INDEX mod_association_name_idx ON mod_association(name);
       "#}),
-    ]);
+        M::up(indoc! { r#"
+          ALTER TABLE modlist ADD COLUMN has_unknown_downloaders BOOLEAN NOT NULL DEFAULT FALSE;
+      "#}),
+        M::up(indoc! { r#"
+          ALTER TABLE mod_association ADD COLUMN source_type TEXT NOT NULL DEFAULT 'unknown';
+          UPDATE mod_association SET source_type = CASE json_extract(source, '$."$type"')
+              WHEN 'NexusDownloader, Wabbajack.Lib' THEN 'nexus'
+              WHEN 'HttpDownloader, Wabbajack.Lib' THEN 'http'
+              WHEN 'GameFileSourceDownloader, Wabbajack.Lib' THEN 'gamefile'
+              WHEN 'WabbajackCDNDownloader+State, Wabbajack.Lib' THEN 'cdn'
+              WHEN 'ManualDownloader, Wabbajack.Lib' THEN 'manual'
+              WHEN 'MegaDownloader, Wabbajack.Lib' THEN 'mega'
+              WHEN 'GoogleDriveDownloader, Wabbajack.Lib' THEN 'gdrive'
+              WHEN 'MediaFireDownloader+State, Wabbajack.Lib' THEN 'mediafire'
+              WHEN 'LoversLabOAuthDownloader, Wabbajack.Lib' THEN 'loverslab'
+              ELSE 'unknown'
+          END;
+          CREATE INDEX mod_association_source_type_idx ON mod_association(source_type);
+      "#}),
+        M::up(indoc! { r#"
+          ALTER TABLE modlist ADD COLUMN game_type TEXT;
+          CREATE INDEX mod_size_idx ON "mod"(size);
+      "#}),
+        M::up(indoc! { r#"
+          CREATE INDEX mod_listing_order_idx ON "mod"(COALESCE(disk_filename, ''), id);
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE data_version (
+              id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+              version INTEGER NOT NULL,
+              updated_at TIMESTAMP NOT NULL DEFAULT (unixepoch())
+          );
+          INSERT INTO data_version (id, version) VALUES (1, 1);
+
+          CREATE TRIGGER modlist_insert_data_version AFTER INSERT ON modlist
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER modlist_update_data_version AFTER UPDATE ON modlist
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER modlist_delete_data_version AFTER DELETE ON modlist
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER mod_insert_data_version AFTER INSERT ON "mod"
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER mod_update_data_version AFTER UPDATE ON "mod"
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER mod_delete_data_version AFTER DELETE ON "mod"
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER mod_association_insert_data_version AFTER INSERT ON mod_association
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER mod_association_update_data_version AFTER UPDATE ON mod_association
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER mod_association_delete_data_version AFTER DELETE ON mod_association
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE mod_disk_state (
+              mod_id INTEGER PRIMARY KEY NOT NULL,
+              last_verified_at TIMESTAMP,
+              last_verify_ok BOOLEAN,
+
+              FOREIGN KEY(mod_id) REFERENCES "mod"(id)
+          );
+          CREATE INDEX mod_disk_state_last_verified_at_idx ON mod_disk_state(last_verified_at);
+      "#}),
+        M::up(indoc! { r#"
+          ALTER TABLE mod_disk_state ADD COLUMN file_size INTEGER;
+          ALTER TABLE mod_disk_state ADD COLUMN file_mtime_ns INTEGER;
+          ALTER TABLE mod_disk_state ADD COLUMN needs_rehash BOOLEAN NOT NULL DEFAULT FALSE;
+      "#}),
+        M::up(indoc! { r#"
+          ALTER TABLE mod_disk_state ADD COLUMN cold_storage_ref TEXT;
+          ALTER TABLE mod_disk_state ADD COLUMN evicted BOOLEAN NOT NULL DEFAULT FALSE;
+      "#}),
+        M::up(indoc! { r#"
+          ALTER TABLE mod_disk_state ADD COLUMN last_accessed_at TIMESTAMP;
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE download_attempt (
+              id INTEGER PRIMARY KEY NOT NULL,
+              mod_id INTEGER NOT NULL,
+              source_type TEXT NOT NULL,
+              attempted_at TIMESTAMP NOT NULL,
+              ok BOOLEAN NOT NULL,
+              error TEXT,
+
+              FOREIGN KEY(mod_id) REFERENCES "mod"(id)
+          );
+          CREATE INDEX download_attempt_mod_id_idx ON download_attempt(mod_id, attempted_at);
+
+          CREATE TABLE download_source_state (
+              mod_id INTEGER NOT NULL,
+              source_type TEXT NOT NULL,
+              consecutive_failures INTEGER NOT NULL DEFAULT 0,
+              next_attempt_at TIMESTAMP NOT NULL,
+              quarantined BOOLEAN NOT NULL DEFAULT FALSE,
+
+              PRIMARY KEY(mod_id, source_type),
+              FOREIGN KEY(mod_id) REFERENCES "mod"(id)
+          );
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE credential (
+              name TEXT PRIMARY KEY NOT NULL,
+              nonce BLOB NOT NULL,
+              ciphertext BLOB NOT NULL,
+              updated_at TIMESTAMP NOT NULL
+          );
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE mod_image_cache (
+              mod_id INTEGER PRIMARY KEY NOT NULL,
+              image_url TEXT NOT NULL,
+              content_type TEXT NOT NULL,
+              data BLOB NOT NULL,
+              fetched_at TIMESTAMP NOT NULL,
+
+              FOREIGN KEY(mod_id) REFERENCES "mod"(id)
+          );
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE wayback_lookup (
+              mod_id INTEGER NOT NULL,
+              url TEXT NOT NULL,
+              looked_up_at TIMESTAMP NOT NULL,
+
+              PRIMARY KEY(mod_id, url),
+              FOREIGN KEY(mod_id) REFERENCES "mod"(id)
+          );
+          CREATE TABLE wayback_capture (
+              mod_id INTEGER NOT NULL,
+              url TEXT NOT NULL,
+              timestamp TEXT NOT NULL,
+              mime_type TEXT,
+
+              PRIMARY KEY(mod_id, url, timestamp),
+              FOREIGN KEY(mod_id) REFERENCES "mod"(id)
+          );
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE digest (
+              id INTEGER PRIMARY KEY NOT NULL,
+              period_start TIMESTAMP NOT NULL,
+              period_end TIMESTAMP NOT NULL,
+              payload TEXT NOT NULL
+          );
+          CREATE INDEX digest_period_end_idx ON digest(period_end);
+      "#}),
+        M::up(indoc! { r#"
+          ALTER TABLE modlist ADD COLUMN parse_error TEXT;
+      "#}),
+        M::up(indoc! { r#"
+          CREATE TABLE modlist_readiness_rule (
+              modlist_id INTEGER PRIMARY KEY NOT NULL,
+              min_available_percent REAL NOT NULL DEFAULT 100,
+              ignore_nsfw BOOLEAN NOT NULL DEFAULT FALSE,
+              FOREIGN KEY(modlist_id) REFERENCES modlist(id)
+          );
+          CREATE TRIGGER modlist_readiness_rule_insert_data_version AFTER INSERT ON modlist_readiness_rule
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER modlist_readiness_rule_update_data_version AFTER UPDATE ON modlist_readiness_rule
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+          CREATE TRIGGER modlist_readiness_rule_delete_data_version AFTER DELETE ON modlist_readiness_rule
+          BEGIN
+              UPDATE data_version SET version = version + 1, updated_at = unixepoch() WHERE id = 1;
+          END;
+      "#}),
+        M::up(indoc! { r#"
+          ALTER TABLE mod_association ADD COLUMN optional BOOLEAN NOT NULL DEFAULT FALSE;
+        "# }),
+        M::up(indoc! { r#"
+          ALTER TABLE mod_association ADD COLUMN meta TEXT;
+        "# }),
+        M::up(indoc! { r#"
+          ALTER TABLE modlist ADD COLUMN author TEXT;
+        "# }),
+        M::up(indoc! { r#"
+          CREATE TABLE modlist_counts (
+              modlist_id INTEGER PRIMARY KEY NOT NULL,
+              mods_total INTEGER NOT NULL DEFAULT 0,
+              mods_available INTEGER NOT NULL DEFAULT 0,
+              lost_forever INTEGER NOT NULL DEFAULT 0,
+              nsfw_total INTEGER NOT NULL DEFAULT 0,
+              nsfw_available INTEGER NOT NULL DEFAULT 0,
+              nsfw_lost_forever INTEGER NOT NULL DEFAULT 0
+          );
+          INSERT INTO modlist_counts
+              (modlist_id, mods_total, mods_available, lost_forever, nsfw_total, nsfw_available, nsfw_lost_forever)
+          SELECT modlist_id, COUNT(*), SUM(available), SUM(lost_forever),
+                 SUM(nsfw), SUM(nsfw AND available), SUM(nsfw AND lost_forever)
+            FROM (
+                SELECT a.modlist_id,
+                       m.disk_filename IS NOT NULL AS available,
+                       m.lost_forever AS lost_forever,
+                       COALESCE(json_extract(a.source, '$.IsNSFW'), 0) AS nsfw
+                  FROM mod_association a
+                 INNER JOIN "mod" m ON m.id = a.mod_id
+                 WHERE NOT a.optional
+            )
+           GROUP BY modlist_id;
+
+          CREATE TRIGGER modlist_counts_association_insert AFTER INSERT ON mod_association
+          WHEN NOT NEW.optional
+          BEGIN
+              INSERT OR IGNORE INTO modlist_counts (modlist_id) VALUES (NEW.modlist_id);
+              UPDATE modlist_counts SET
+                  mods_total = mods_total + 1,
+                  mods_available = mods_available + COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = NEW.mod_id), 0),
+                  lost_forever = lost_forever + COALESCE((SELECT lost_forever FROM "mod" WHERE id = NEW.mod_id), 0),
+                  nsfw_total = nsfw_total + COALESCE(json_extract(NEW.source, '$.IsNSFW'), 0),
+                  nsfw_available = nsfw_available + COALESCE(json_extract(NEW.source, '$.IsNSFW'), 0) * COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = NEW.mod_id), 0),
+                  nsfw_lost_forever = nsfw_lost_forever + COALESCE(json_extract(NEW.source, '$.IsNSFW'), 0) * COALESCE((SELECT lost_forever FROM "mod" WHERE id = NEW.mod_id), 0)
+              WHERE modlist_id = NEW.modlist_id;
+          END;
+          CREATE TRIGGER modlist_counts_association_delete AFTER DELETE ON mod_association
+          WHEN NOT OLD.optional
+          BEGIN
+              UPDATE modlist_counts SET
+                  mods_total = mods_total - 1,
+                  mods_available = mods_available - COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = OLD.mod_id), 0),
+                  lost_forever = lost_forever - COALESCE((SELECT lost_forever FROM "mod" WHERE id = OLD.mod_id), 0),
+                  nsfw_total = nsfw_total - COALESCE(json_extract(OLD.source, '$.IsNSFW'), 0),
+                  nsfw_available = nsfw_available - COALESCE(json_extract(OLD.source, '$.IsNSFW'), 0) * COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = OLD.mod_id), 0),
+                  nsfw_lost_forever = nsfw_lost_forever - COALESCE(json_extract(OLD.source, '$.IsNSFW'), 0) * COALESCE((SELECT lost_forever FROM "mod" WHERE id = OLD.mod_id), 0)
+              WHERE modlist_id = OLD.modlist_id;
+          END;
+          CREATE TRIGGER modlist_counts_association_update_old AFTER UPDATE OF modlist_id, mod_id, source, optional ON mod_association
+          WHEN NOT OLD.optional
+          BEGIN
+              UPDATE modlist_counts SET
+                  mods_total = mods_total - 1,
+                  mods_available = mods_available - COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = OLD.mod_id), 0),
+                  lost_forever = lost_forever - COALESCE((SELECT lost_forever FROM "mod" WHERE id = OLD.mod_id), 0),
+                  nsfw_total = nsfw_total - COALESCE(json_extract(OLD.source, '$.IsNSFW'), 0),
+                  nsfw_available = nsfw_available - COALESCE(json_extract(OLD.source, '$.IsNSFW'), 0) * COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = OLD.mod_id), 0),
+                  nsfw_lost_forever = nsfw_lost_forever - COALESCE(json_extract(OLD.source, '$.IsNSFW'), 0) * COALESCE((SELECT lost_forever FROM "mod" WHERE id = OLD.mod_id), 0)
+              WHERE modlist_id = OLD.modlist_id;
+          END;
+          CREATE TRIGGER modlist_counts_association_update_new AFTER UPDATE OF modlist_id, mod_id, source, optional ON mod_association
+          WHEN NOT NEW.optional
+          BEGIN
+              INSERT OR IGNORE INTO modlist_counts (modlist_id) VALUES (NEW.modlist_id);
+              UPDATE modlist_counts SET
+                  mods_total = mods_total + 1,
+                  mods_available = mods_available + COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = NEW.mod_id), 0),
+                  lost_forever = lost_forever + COALESCE((SELECT lost_forever FROM "mod" WHERE id = NEW.mod_id), 0),
+                  nsfw_total = nsfw_total + COALESCE(json_extract(NEW.source, '$.IsNSFW'), 0),
+                  nsfw_available = nsfw_available + COALESCE(json_extract(NEW.source, '$.IsNSFW'), 0) * COALESCE((SELECT disk_filename IS NOT NULL FROM "mod" WHERE id = NEW.mod_id), 0),
+                  nsfw_lost_forever = nsfw_lost_forever + COALESCE(json_extract(NEW.source, '$.IsNSFW'), 0) * COALESCE((SELECT lost_forever FROM "mod" WHERE id = NEW.mod_id), 0)
+              WHERE modlist_id = NEW.modlist_id;
+          END;
+          CREATE TRIGGER modlist_counts_mod_update AFTER UPDATE OF disk_filename, lost_forever ON "mod"
+          BEGIN
+              UPDATE modlist_counts SET
+                  mods_available = mods_available
+                      + ((NEW.disk_filename IS NOT NULL) - (OLD.disk_filename IS NOT NULL)) * (
+                          SELECT COUNT(*) FROM mod_association a
+                           WHERE a.modlist_id = modlist_counts.modlist_id AND a.mod_id = NEW.id AND NOT a.optional),
+                  lost_forever = lost_forever
+                      + (NEW.lost_forever - OLD.lost_forever) * (
+                          SELECT COUNT(*) FROM mod_association a
+                           WHERE a.modlist_id = modlist_counts.modlist_id AND a.mod_id = NEW.id AND NOT a.optional),
+                  nsfw_available = nsfw_available
+                      + ((NEW.disk_filename IS NOT NULL) - (OLD.disk_filename IS NOT NULL)) * (
+                          SELECT COALESCE(SUM(COALESCE(json_extract(a.source, '$.IsNSFW'), 0)), 0) FROM mod_association a
+                           WHERE a.modlist_id = modlist_counts.modlist_id AND a.mod_id = NEW.id AND NOT a.optional),
+                  nsfw_lost_forever = nsfw_lost_forever
+                      + (NEW.lost_forever - OLD.lost_forever) * (
+                          SELECT COALESCE(SUM(COALESCE(json_extract(a.source, '$.IsNSFW'), 0)), 0) FROM mod_association a
+                           WHERE a.modlist_id = modlist_counts.modlist_id AND a.mod_id = NEW.id AND NOT a.optional)
+              WHERE modlist_id IN (SELECT modlist_id FROM mod_association WHERE mod_id = NEW.id);
+          END;
+          CREATE TRIGGER modlist_counts_modlist_delete AFTER DELETE ON modlist
+          BEGIN
+              DELETE FROM modlist_counts WHERE modlist_id = OLD.id;
+          END;
+        "# }),
+        M::up(indoc! { r#"
+          CREATE TABLE transfer_stats (
+              day TEXT NOT NULL,
+              client TEXT NOT NULL,
+              uploaded_bytes INTEGER NOT NULL DEFAULT 0,
+              downloaded_bytes INTEGER NOT NULL DEFAULT 0,
+              PRIMARY KEY (day, client)
+          );
+        "# }),
+        M::up(indoc! { r#"
+          UPDATE modlist SET has_unknown_downloaders = EXISTS (
+              SELECT 1 FROM mod_association a
+               WHERE a.modlist_id = modlist.id AND a.source_type = 'unknown'
+          );
+        "# }),
+        M::up(indoc! { r#"
+          -- The game isn't stored anywhere else, so guess it from the Nexus
+          -- archives until the next bootstrap reads it from the modlist.
+          UPDATE modlist SET game_type = (
+              SELECT json_extract(a.source, '$.GameName') FROM mod_association a
+               WHERE a.modlist_id = modlist.id AND a.source_type = 'nexus'
+               GROUP BY 1
+               ORDER BY COUNT(*) DESC
+               LIMIT 1
+          )
+          WHERE game_type IS NULL AND parse_error IS NULL;
+          CREATE INDEX modlist_game_type_idx ON modlist(game_type);
+        "# }),
+    ])
+}
+
+pub fn migrate(mut conn: PooledConnection<SqliteConnectionManager>) -> Result<()> {
+    let migrations = migrations();
 
     conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
         .unwrap();
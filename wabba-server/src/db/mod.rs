@@ -1,4 +1,14 @@
+pub mod credential;
+pub mod data_version;
+pub mod digest;
+pub mod download_state;
 pub mod migrations;
 pub mod mod_association;
 pub mod mod_data;
+pub mod mod_disk_state;
+pub mod mod_image_cache;
 pub mod modlist;
+pub mod modlist_counts;
+pub mod readiness;
+pub mod transfer_stats;
+pub mod wayback;
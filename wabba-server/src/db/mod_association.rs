@@ -3,6 +3,7 @@ use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{OptionalExtension, params};
 use serde::{Deserialize, Serialize};
 use wabba_protocol::archive_state::ArchiveState;
+use wabba_protocol::filename::{decomposed_filename, normalize_filename};
 
 use crate::db::mod_data::Mod;
 use crate::db::modlist::Modlist;
@@ -15,6 +16,11 @@ pub struct ModAssociation {
     pub filename: String,
     pub name: Option<String>,
     pub version: Option<String>,
+    /// Left out of availability counts and missing mod reports.
+    pub optional: bool,
+    /// The archive's `Meta` ini exactly as the modlist has it. Wabbajack
+    /// compares `.meta` files against this in some flows.
+    pub meta: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,6 +31,15 @@ pub struct ModAssociationEgg {
     pub filename: String,
     pub name: Option<String>,
     pub version: Option<String>,
+    pub meta: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ModlistArchiveCounts {
+    pub required: u64,
+    pub optional: u64,
+    /// Required archives with no file on disk.
+    pub missing: u64,
 }
 
 impl ModAssociation {
@@ -48,6 +63,8 @@ impl ModAssociation {
             filename: row.get(3)?,
             name: row.get::<_, Option<String>>(4)?,
             version: row.get::<_, Option<String>>(5)?,
+            optional: row.get(6)?,
+            meta: row.get(7)?,
         })
     }
 
@@ -58,7 +75,7 @@ impl ModAssociation {
     ) -> Result<Option<Self>, rusqlite::Error> {
         let association = conn
             .prepare(
-                "SELECT modlist_id, mod_id, source, filename, name, version
+                "SELECT modlist_id, mod_id, source, filename, name, version, optional, meta
                  FROM mod_association
                  WHERE modlist_id = ?1 AND mod_id = ?2",
             )?
@@ -76,7 +93,7 @@ impl ModAssociation {
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Vec<Self>, rusqlite::Error> {
         let mut stmt = conn.prepare(
-            "SELECT modlist_id, mod_id, source, filename, name, version
+            "SELECT modlist_id, mod_id, source, filename, name, version, optional, meta
              FROM mod_association
              WHERE modlist_id = ?1
              ORDER BY filename",
@@ -88,12 +105,129 @@ impl ModAssociation {
         Ok(associations)
     }
 
+    /// One page of a modlist's archives in filename order, each with its
+    /// mod row. `missing_only` keeps just the required archives that aren't
+    /// on disk.
+    pub fn get_page_by_modlist_id(
+        modlist_id: u64,
+        missing_only: bool,
+        limit: u64,
+        offset: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<(Self, Mod)>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT a.modlist_id, a.mod_id, a.source, a.filename, a.name, a.version, a.optional, a.meta,
+                    m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever
+             FROM mod_association a
+             INNER JOIN \"mod\" m ON m.id = a.mod_id
+             WHERE a.modlist_id = ?1
+               AND (?2 = 0 OR (m.disk_filename IS NULL AND a.optional = 0))
+             ORDER BY a.filename, a.mod_id
+             LIMIT ?3 OFFSET ?4",
+        )?;
+        let rows = stmt
+            .query_map(params![modlist_id, missing_only, limit, offset], |row| {
+                Ok((ModAssociation::from_row(row)?, mod_after_association(row)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    /// Other mods that some modlist names `name`, each with its first
+    /// association, in one query on the name index.
+    pub fn get_other_mods_named(
+        name: &str,
+        exclude_mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<(Mod, Option<Self>)>, rusqlite::Error> {
+        Self::get_mods_with_first_association(
+            "m.id IN (SELECT mod_id FROM mod_association WHERE name = ?1) AND m.id != ?2",
+            params![name, exclude_mod_id],
+            conn,
+        )
+    }
+
+    /// Other mods stored under `disk_filename`, each with its first
+    /// association. Matches both the composed (NFC) and decomposed (NFD)
+    /// spelling, since disk names are stored exactly as found.
+    pub fn get_other_mods_with_disk_filename(
+        disk_filename: &str,
+        exclude_mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<(Mod, Option<Self>)>, rusqlite::Error> {
+        Self::get_mods_with_first_association(
+            "m.disk_filename IN (?1, ?2) AND m.id != ?3",
+            params![
+                normalize_filename(disk_filename),
+                decomposed_filename(disk_filename),
+                exclude_mod_id
+            ],
+            conn,
+        )
+    }
+
+    /// Mods matching `condition` (over `"mod" m`) joined with the association
+    /// of the lowest modlist id using each, if any.
+    fn get_mods_with_first_association(
+        condition: &str,
+        params: impl rusqlite::Params,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<(Mod, Option<Self>)>, rusqlite::Error> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT a.modlist_id, a.mod_id, a.source, a.filename, a.name, a.version, a.optional, a.meta,
+                    m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever
+             FROM \"mod\" m
+             LEFT JOIN mod_association a ON a.mod_id = m.id
+               AND a.modlist_id = (SELECT MIN(modlist_id) FROM mod_association WHERE mod_id = m.id)
+             WHERE {}
+             ORDER BY m.id",
+            condition
+        ))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                let association = match row.get::<_, Option<u64>>(0)? {
+                    Some(_) => Some(ModAssociation::from_row(row)?),
+                    None => None,
+                };
+                Ok((mod_after_association(row)?, association))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    /// How many of a modlist's archives are required, optional, and required
+    /// but missing, for sizing the details page tables.
+    pub fn count_by_modlist_id(
+        modlist_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<ModlistArchiveCounts, rusqlite::Error> {
+        conn.prepare(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(a.optional), 0),
+                    COALESCE(SUM(m.disk_filename IS NULL AND a.optional = 0), 0)
+             FROM mod_association a
+             INNER JOIN \"mod\" m ON m.id = a.mod_id
+             WHERE a.modlist_id = ?1",
+        )?
+        .query_row(params![modlist_id], |row| {
+            let total: u64 = row.get(0)?;
+            let optional: u64 = row.get(1)?;
+            Ok(ModlistArchiveCounts {
+                required: total - optional,
+                optional,
+                missing: row.get(2)?,
+            })
+        })
+    }
+
     pub fn get_by_mod_id(
         mod_id: u64,
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Vec<Self>, rusqlite::Error> {
         let mut stmt = conn.prepare(
-            "SELECT modlist_id, mod_id, source, filename, name, version
+            "SELECT modlist_id, mod_id, source, filename, name, version, optional, meta
              FROM mod_association
              WHERE mod_id = ?1
              ORDER BY modlist_id",
@@ -105,6 +239,71 @@ impl ModAssociation {
         Ok(associations)
     }
 
+    pub fn get_by_source_type(
+        source_type: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT modlist_id, mod_id, source, filename, name, version, optional, meta
+             FROM mod_association
+             WHERE source_type = ?1
+             ORDER BY mod_id, modlist_id",
+        )?;
+        let associations = stmt
+            .query_map(params![source_type], ModAssociation::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(associations)
+    }
+
+    /// The `.meta` file Wabbajack expects next to this archive: the
+    /// modlist's own copy when it has one, otherwise one built from the
+    /// source.
+    pub fn meta_ini(&self) -> Option<String> {
+        self.meta
+            .clone()
+            .filter(|meta| !meta.trim().is_empty())
+            .or_else(|| self.source.meta_ini())
+    }
+
+    /// Replaces the source and the name/version derived from it. Only
+    /// writes when something changed, so unchanged rows don't bump the
+    /// data version.
+    pub fn update_source(
+        &self,
+        source: &ArchiveState,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<bool, rusqlite::Error> {
+        let updated = conn
+            .prepare(
+                "UPDATE mod_association SET source = ?3, name = ?4, version = ?5
+                 WHERE modlist_id = ?1 AND mod_id = ?2
+                   AND (source IS NOT ?3 OR name IS NOT ?4 OR version IS NOT ?5)",
+            )?
+            .execute(params![
+                self.modlist_id,
+                self.mod_id,
+                serde_json::to_string(source).unwrap(),
+                source.name(),
+                source.version(),
+            ])?;
+
+        Ok(updated > 0)
+    }
+
+    pub fn set_optional(
+        &self,
+        optional: bool,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "UPDATE mod_association SET optional = ?3 WHERE modlist_id = ?1 AND mod_id = ?2",
+        )?
+        .execute(params![self.modlist_id, self.mod_id, optional])?;
+
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn get_mod_with_association(
         &self,
@@ -127,8 +326,12 @@ impl ModAssociation {
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<(), rusqlite::Error> {
         conn.prepare(
-            "INSERT OR REPLACE INTO mod_association (modlist_id, mod_id, source, filename, name, version)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            "INSERT INTO mod_association (modlist_id, mod_id, source, filename, name, version, source_type, optional, meta)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(modlist_id, mod_id) DO UPDATE SET
+                 source = excluded.source, filename = excluded.filename, name = excluded.name,
+                 version = excluded.version, source_type = excluded.source_type,
+                 optional = excluded.optional, meta = excluded.meta"
         )?
         .execute(params![
             self.modlist_id,
@@ -136,7 +339,10 @@ impl ModAssociation {
             serde_json::to_string(&self.source).unwrap(),
             self.filename,
             self.name,
-            self.version
+            self.version,
+            self.source.source_type(),
+            self.optional,
+            self.meta
         ])?;
 
         Ok(())
@@ -160,8 +366,8 @@ impl ModAssociationEgg {
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<ModAssociation, rusqlite::Error> {
         conn.prepare(
-            "INSERT INTO mod_association (modlist_id, mod_id, source, filename, name, version)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO mod_association (modlist_id, mod_id, source, filename, name, version, source_type, meta)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
         )?
         .execute(params![
             self.modlist_id,
@@ -169,7 +375,9 @@ impl ModAssociationEgg {
             serde_json::to_string(&self.source).unwrap(),
             self.filename,
             self.name,
-            self.version
+            self.version,
+            self.source.source_type(),
+            self.meta
         ])?;
 
         Ok(ModAssociation {
@@ -179,6 +387,20 @@ impl ModAssociationEgg {
             filename: self.filename.clone(),
             name: self.name.clone(),
             version: self.version.clone(),
+            optional: false,
+            meta: self.meta.clone(),
         })
     }
 }
+
+/// Reads the `"mod"` columns that follow the eight association columns in
+/// the joined queries above.
+fn mod_after_association(row: &rusqlite::Row) -> Result<Mod, rusqlite::Error> {
+    Ok(Mod {
+        id: row.get(8)?,
+        disk_filename: row.get(9)?,
+        size: row.get(10)?,
+        xxhash64: row.get(11)?,
+        lost_forever: row.get(12)?,
+    })
+}
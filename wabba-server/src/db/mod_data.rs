@@ -38,6 +38,17 @@ pub struct Mod {
     pub lost_forever: bool,
 }
 
+/// Filters for the `/mods` listing. Every field is optional and they are
+/// combined with AND in a single query.
+#[derive(Debug, Default, Clone)]
+pub struct ModListingFilter {
+    pub unavailable_only: bool,
+    pub source_type: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub game: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModEgg {
     pub disk_filename: Option<String>,
@@ -170,7 +181,9 @@ impl Mod {
         &self,
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<(), rusqlite::Error> {
-        conn.prepare("INSERT OR REPLACE INTO \"mod\" (id, disk_filename, size, xxhash64, lost_forever) VALUES (?1, ?2, ?3, ?4, ?5)")?
+        conn.prepare("INSERT INTO \"mod\" (id, disk_filename, size, xxhash64, lost_forever) VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(id) DO UPDATE SET disk_filename = excluded.disk_filename, size = excluded.size,
+                xxhash64 = excluded.xxhash64, lost_forever = excluded.lost_forever")?
         .execute(params![self.id, self.disk_filename, self.size, self.xxhash64, self.lost_forever])?;
 
         Ok(())
@@ -210,7 +223,7 @@ impl Mod {
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Vec<Modlist>, rusqlite::Error> {
         let mut stmt = conn.prepare(
-            "SELECT modlist.id, modlist.filename, modlist.name, modlist.version, modlist.size, modlist.xxhash64, modlist.available, modlist.muted
+            "SELECT modlist.id, modlist.filename, modlist.name, modlist.version, modlist.size, modlist.xxhash64, modlist.available, modlist.muted, modlist.has_unknown_downloaders, modlist.game_type, modlist.parse_error, modlist.author, modlist.created_at
              FROM modlist
              INNER JOIN mod_association ON modlist.id = mod_association.modlist_id
              WHERE mod_association.mod_id = ?1
@@ -235,56 +248,88 @@ impl Mod {
         Ok(count as u64)
     }
 
-    pub fn get_by_disk_filename_all(
-        disk_filename: &str,
-        exclude_id: u64,
-        conn: &PooledConnection<SqliteConnectionManager>,
-    ) -> Result<Vec<Self>, rusqlite::Error> {
-        let mut stmt = conn.prepare(
-            "SELECT id, disk_filename, size, xxhash64, lost_forever
-             FROM \"mod\"
-             WHERE disk_filename = ?1 AND id != ?2
-             ORDER BY id",
-        )?;
-        let mods = stmt
-            .query_map(params![disk_filename, exclude_id], Mod::from_row)?
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(mods)
-    }
-
-    /// Returns every mod along with its association count and first
-    /// association (lowest modlist_id) in a single query. Replaces the
-    /// N+1 pattern of calling `count_modlists` + `ModAssociation::get_by_mod_id`
-    /// for each row on the `/mods` listing page.
+    /// Returns up to `limit` mods matching `filter` along with their
+    /// association count and first association (lowest modlist_id) in a
+    /// single query, ordered by disk filename then id. `after` is the
+    /// `(disk_filename, id)` of the last row on the previous page; rows
+    /// strictly after it are returned.
     pub fn get_all_for_listing(
-        unavailable_only: bool,
+        filter: &ModListingFilter,
+        after: Option<(String, u64)>,
+        limit: u64,
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Vec<(Mod, u64, Option<ModAssociation>)>, rusqlite::Error> {
-        let filter = if unavailable_only {
-            "WHERE m.disk_filename IS NULL"
+        let mut conditions = Vec::new();
+        let mut params: Vec<rusqlite::types::Value> = Vec::new();
+        if filter.unavailable_only {
+            conditions.push("m.disk_filename IS NULL".to_string());
+            // Mods only ever referenced as optional aren't reported missing.
+            conditions.push(
+                "(EXISTS (SELECT 1 FROM mod_association ra WHERE ra.mod_id = m.id AND NOT ra.optional)
+                  OR NOT EXISTS (SELECT 1 FROM mod_association oa WHERE oa.mod_id = m.id))"
+                    .to_string(),
+            );
+        }
+        if let Some(source_type) = &filter.source_type {
+            params.push(source_type.clone().into());
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM mod_association sa WHERE sa.mod_id = m.id AND sa.source_type = ?{})",
+                params.len()
+            ));
+        }
+        if let Some(min_size) = filter.min_size {
+            params.push((min_size as i64).into());
+            conditions.push(format!("m.size >= ?{}", params.len()));
+        }
+        if let Some(max_size) = filter.max_size {
+            params.push((max_size as i64).into());
+            conditions.push(format!("m.size <= ?{}", params.len()));
+        }
+        if let Some(game) = &filter.game {
+            params.push(game.clone().into());
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM mod_association ga
+                           INNER JOIN modlist gm ON gm.id = ga.modlist_id
+                          WHERE ga.mod_id = m.id AND gm.game_type = ?{})",
+                params.len()
+            ));
+        }
+        if let Some((after_name, after_id)) = after {
+            params.push(after_name.into());
+            params.push((after_id as i64).into());
+            conditions.push(format!(
+                "(COALESCE(m.disk_filename, ''), m.id) > (?{}, ?{})",
+                params.len() - 1,
+                params.len()
+            ));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
         } else {
-            ""
+            format!("WHERE {}", conditions.join(" AND "))
         };
+        params.push((limit as i64).into());
+        // Counts and the first association are correlated per row so SQLite
+        // can walk mod_listing_order_idx and stop after `limit` rows instead
+        // of aggregating the whole association table up front.
         let sql = format!(
             "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever,
-                    COALESCE(counts.c, 0) AS modlist_count,
-                    a.modlist_id, a.source, a.filename, a.name, a.version
+                    (SELECT COUNT(*) FROM mod_association c WHERE c.mod_id = m.id) AS modlist_count,
+                    a.modlist_id, a.source, a.filename, a.name, a.version, a.optional, a.meta
                FROM \"mod\" m
-               LEFT JOIN (
-                 SELECT mod_id, COUNT(*) AS c, MIN(modlist_id) AS first_modlist_id
-                   FROM mod_association GROUP BY mod_id
-               ) counts ON counts.mod_id = m.id
                LEFT JOIN mod_association a
-                   ON a.mod_id = m.id AND a.modlist_id = counts.first_modlist_id
+                   ON a.mod_id = m.id
+                  AND a.modlist_id = (SELECT MIN(f.modlist_id) FROM mod_association f WHERE f.mod_id = m.id)
              {}
-             ORDER BY m.disk_filename",
-            filter
+             ORDER BY COALESCE(m.disk_filename, ''), m.id
+             LIMIT ?{}",
+            filter,
+            params.len()
         );
 
         let mut stmt = conn.prepare(&sql)?;
         let rows = stmt
-            .query_map([], |row| {
+            .query_map(rusqlite::params_from_iter(params.iter()), |row| {
                 let mod_item = Mod {
                     id: row.get(0)?,
                     disk_filename: row.get(1)?,
@@ -315,6 +360,8 @@ impl Mod {
                             filename: row.get(8)?,
                             name: row.get::<_, Option<String>>(9)?,
                             version: row.get::<_, Option<String>>(10)?,
+                            optional: row.get(11)?,
+                            meta: row.get(12)?,
                         })
                     }
                     None => None,
@@ -0,0 +1,351 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{VerificationPolicy, VerificationStrategy};
+use crate::db::mod_data::Mod;
+
+/// What we last observed about a mod's file on disk. Kept out of `mod` so
+/// the catalogue row isn't rewritten every time a file is checked.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModDiskState {
+    pub mod_id: u64,
+    pub last_verified_at: Option<i64>,
+    pub last_verify_ok: Option<bool>,
+    /// Size and mtime (nanoseconds since the epoch) of the file as of the
+    /// last ingest or successful verification.
+    pub file_size: Option<u64>,
+    pub file_mtime_ns: Option<i64>,
+    /// Set by reconciliation when the file changed since it was hashed.
+    pub needs_rehash: bool,
+    /// Where the archive lives in cold storage (an IPFS CID), once offloaded.
+    pub cold_storage_ref: Option<String>,
+    /// The local copy was removed after offloading; the next download
+    /// fetches it back from cold storage.
+    pub evicted: bool,
+    /// Last download, used to pick which cached copies to evict first.
+    pub last_accessed_at: Option<i64>,
+}
+
+/// Size and mtime of a file, used to spot changes without re-hashing.
+pub fn file_stat(path: &std::path::Path) -> std::io::Result<(u64, i64)> {
+    let metadata = std::fs::metadata(path)?;
+    let mtime_ns = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime_ns))
+}
+
+impl ModDiskState {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(ModDiskState {
+            mod_id: row.get(0)?,
+            last_verified_at: row.get(1)?,
+            last_verify_ok: row.get(2)?,
+            file_size: row.get(3)?,
+            file_mtime_ns: row.get(4)?,
+            needs_rehash: row.get(5)?,
+            cold_storage_ref: row.get(6)?,
+            evicted: row.get(7)?,
+            last_accessed_at: row.get(8)?,
+        })
+    }
+
+    pub fn get_by_mod_id(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Option<Self>, rusqlite::Error> {
+        let state = conn
+            .prepare(
+                "SELECT mod_id, last_verified_at, last_verify_ok, file_size, file_mtime_ns, needs_rehash,
+                        cold_storage_ref, evicted, last_accessed_at
+                 FROM mod_disk_state WHERE mod_id = ?1",
+            )?
+            .query_row(params![mod_id], |row| Ok(ModDiskState::from_row(row)))
+            .optional()?
+            .transpose()?;
+
+        Ok(state)
+    }
+
+    /// Records a re-hash. `stat` is the file's size and mtime at hashing
+    /// time; a passing check makes it the new baseline and clears
+    /// `needs_rehash`.
+    pub fn record_verification(
+        mod_id: u64,
+        ok: bool,
+        stat: Option<(u64, i64)>,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        let (size, mtime_ns) = match stat {
+            Some((size, mtime_ns)) if ok => (Some(size as i64), Some(mtime_ns)),
+            _ => (None, None),
+        };
+        conn.prepare(
+            "INSERT INTO mod_disk_state (mod_id, last_verified_at, last_verify_ok, file_size, file_mtime_ns, needs_rehash)
+             VALUES (?1, unixepoch(), ?2, ?3, ?4, NOT ?2)
+             ON CONFLICT(mod_id) DO UPDATE SET
+                 last_verified_at = excluded.last_verified_at,
+                 last_verify_ok = excluded.last_verify_ok,
+                 file_size = COALESCE(excluded.file_size, file_size),
+                 file_mtime_ns = COALESCE(excluded.file_mtime_ns, file_mtime_ns),
+                 needs_rehash = excluded.needs_rehash",
+        )?
+        .execute(params![mod_id, ok, size, mtime_ns])?;
+
+        Ok(())
+    }
+
+    /// Records the size and mtime of a freshly ingested (and therefore just
+    /// hashed) file.
+    pub fn record_ingest(
+        mod_id: u64,
+        size: u64,
+        mtime_ns: i64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO mod_disk_state (mod_id, file_size, file_mtime_ns, needs_rehash)
+             VALUES (?1, ?2, ?3, FALSE)
+             ON CONFLICT(mod_id) DO UPDATE SET
+                 file_size = excluded.file_size,
+                 file_mtime_ns = excluded.file_mtime_ns,
+                 needs_rehash = FALSE",
+        )?
+        .execute(params![mod_id, size as i64, mtime_ns])?;
+
+        Ok(())
+    }
+
+    pub fn set_needs_rehash(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO mod_disk_state (mod_id, needs_rehash) VALUES (?1, TRUE)
+             ON CONFLICT(mod_id) DO UPDATE SET needs_rehash = TRUE",
+        )?
+        .execute(params![mod_id])?;
+
+        Ok(())
+    }
+
+    /// Records that the archive was stored in cold storage under
+    /// `reference` while its local copy is still in place.
+    pub fn record_cold_copy(
+        mod_id: u64,
+        reference: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO mod_disk_state (mod_id, cold_storage_ref) VALUES (?1, ?2)
+             ON CONFLICT(mod_id) DO UPDATE SET cold_storage_ref = excluded.cold_storage_ref",
+        )?
+        .execute(params![mod_id, reference])?;
+
+        Ok(())
+    }
+
+    /// Records that the archive was stored in cold storage under `reference`
+    /// and its local copy removed.
+    pub fn record_eviction(
+        mod_id: u64,
+        reference: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO mod_disk_state (mod_id, cold_storage_ref, evicted) VALUES (?1, ?2, TRUE)
+             ON CONFLICT(mod_id) DO UPDATE SET
+                 cold_storage_ref = excluded.cold_storage_ref,
+                 evicted = TRUE",
+        )?
+        .execute(params![mod_id, reference])?;
+
+        Ok(())
+    }
+
+    /// Records that an evicted archive was fetched back and hash-checked.
+    /// The cold storage reference is kept so it can be evicted again for
+    /// free.
+    pub fn record_restore(
+        mod_id: u64,
+        size: u64,
+        mtime_ns: i64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "UPDATE mod_disk_state
+                SET evicted = FALSE, file_size = ?2, file_mtime_ns = ?3, needs_rehash = FALSE
+              WHERE mod_id = ?1",
+        )?
+        .execute(params![mod_id, size as i64, mtime_ns])?;
+
+        Ok(())
+    }
+
+    pub fn record_access(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO mod_disk_state (mod_id, last_accessed_at) VALUES (?1, unixepoch())
+             ON CONFLICT(mod_id) DO UPDATE SET last_accessed_at = excluded.last_accessed_at",
+        )?
+        .execute(params![mod_id])?;
+
+        Ok(())
+    }
+
+    /// Local copies of mods that are also in cold storage, least recently
+    /// downloaded first. These can be dropped at any time and fetched back.
+    pub fn get_cached(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<(Mod, ModDiskState)>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever,
+                    s.mod_id, s.last_verified_at, s.last_verify_ok, s.file_size, s.file_mtime_ns, s.needs_rehash,
+                    s.cold_storage_ref, s.evicted, s.last_accessed_at
+               FROM \"mod\" m
+               JOIN mod_disk_state s ON s.mod_id = m.id
+              WHERE m.disk_filename IS NOT NULL
+                AND s.cold_storage_ref IS NOT NULL
+                AND NOT s.evicted
+              ORDER BY s.last_accessed_at IS NOT NULL, s.last_accessed_at, m.id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    Mod::from_row(row)?,
+                    ModDiskState {
+                        mod_id: row.get(5)?,
+                        last_verified_at: row.get(6)?,
+                        last_verify_ok: row.get(7)?,
+                        file_size: row.get(8)?,
+                        file_mtime_ns: row.get(9)?,
+                        needs_rehash: row.get(10)?,
+                        cold_storage_ref: row.get(11)?,
+                        evicted: row.get(12)?,
+                        last_accessed_at: row.get(13)?,
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    /// Mods whose only copy is in cold storage, with their reference.
+    pub fn get_evicted(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<(Mod, String)>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever, s.cold_storage_ref
+               FROM \"mod\" m
+               JOIN mod_disk_state s ON s.mod_id = m.id
+              WHERE m.disk_filename IS NOT NULL
+                AND s.evicted
+                AND s.cold_storage_ref IS NOT NULL
+              ORDER BY m.id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((Mod::from_row(row)?, row.get(5)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    /// Every available mod with a local copy, with its recorded disk state
+    /// if any.
+    pub fn get_all_available(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<(Mod, Option<ModDiskState>)>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever,
+                    s.mod_id, s.last_verified_at, s.last_verify_ok, s.file_size, s.file_mtime_ns, s.needs_rehash,
+                    s.cold_storage_ref, s.evicted, s.last_accessed_at
+               FROM \"mod\" m
+               LEFT JOIN mod_disk_state s ON s.mod_id = m.id
+              WHERE m.disk_filename IS NOT NULL
+                AND s.evicted IS NOT TRUE
+              ORDER BY m.id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                let mod_item = Mod::from_row(row)?;
+                let state = match row.get::<_, Option<u64>>(5)? {
+                    Some(mod_id) => Some(ModDiskState {
+                        mod_id,
+                        last_verified_at: row.get(6)?,
+                        last_verify_ok: row.get(7)?,
+                        file_size: row.get(8)?,
+                        file_mtime_ns: row.get(9)?,
+                        needs_rehash: row.get(10)?,
+                        cold_storage_ref: row.get(11)?,
+                        evicted: row.get(12)?,
+                        last_accessed_at: row.get(13)?,
+                    }),
+                    None => None,
+                };
+                Ok((mod_item, state))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    /// Available mods the verification job should re-hash under `policy`.
+    /// Mods evicted to cold storage have nothing local to hash and are
+    /// skipped.
+    pub fn select_mods_for_verification(
+        policy: &VerificationPolicy,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Mod>, rusqlite::Error> {
+        let (sql, params): (&str, Vec<i64>) = match policy.strategy {
+            VerificationStrategy::Off | VerificationStrategy::FullSweep => (
+                "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever
+                   FROM \"mod\" m
+                   LEFT JOIN mod_disk_state s ON s.mod_id = m.id
+                  WHERE m.disk_filename IS NOT NULL
+                    AND s.evicted IS NOT TRUE
+                  ORDER BY m.id",
+                vec![],
+            ),
+            VerificationStrategy::RandomSample => (
+                "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever
+                   FROM \"mod\" m
+                   LEFT JOIN mod_disk_state s ON s.mod_id = m.id
+                  WHERE m.disk_filename IS NOT NULL
+                    AND s.evicted IS NOT TRUE
+                  ORDER BY RANDOM()
+                  LIMIT ?1",
+                vec![policy.batch_size as i64],
+            ),
+            VerificationStrategy::Stale => (
+                "SELECT m.id, m.disk_filename, m.size, m.xxhash64, m.lost_forever
+                   FROM \"mod\" m
+                   LEFT JOIN mod_disk_state s ON s.mod_id = m.id
+                  WHERE m.disk_filename IS NOT NULL
+                    AND s.evicted IS NOT TRUE
+                    AND (s.last_verified_at IS NULL
+                         OR s.last_verified_at < unixepoch() - ?1
+                         OR s.needs_rehash)
+                  ORDER BY s.needs_rehash IS NOT TRUE, s.last_verified_at IS NOT NULL, s.last_verified_at, m.id
+                  LIMIT ?2",
+                vec![
+                    (policy.stale_after_days * 24 * 60 * 60) as i64,
+                    policy.batch_size as i64,
+                ],
+            ),
+        };
+
+        let mut stmt = conn.prepare(sql)?;
+        let mods = stmt
+            .query_map(rusqlite::params_from_iter(params.iter()), Mod::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(mods)
+    }
+}
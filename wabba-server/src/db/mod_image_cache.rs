@@ -0,0 +1,61 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+
+/// A local copy of a mod's preview image, so pages don't depend on the
+/// source site being up.
+#[derive(Debug, Clone)]
+pub struct CachedModImage {
+    /// The upstream URL this copy was fetched from.
+    pub image_url: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl CachedModImage {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(CachedModImage {
+            image_url: row.get(0)?,
+            content_type: row.get(1)?,
+            data: row.get(2)?,
+        })
+    }
+
+    pub fn get_by_mod_id(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Option<Self>, rusqlite::Error> {
+        let image = conn
+            .prepare(
+                "SELECT image_url, content_type, data
+                 FROM mod_image_cache
+                 WHERE mod_id = ?1",
+            )?
+            .query_row(params![mod_id], |row| Ok(CachedModImage::from_row(row)))
+            .optional()?
+            .transpose()?;
+
+        Ok(image)
+    }
+
+    pub fn store(
+        mod_id: u64,
+        image_url: &str,
+        content_type: &str,
+        data: &[u8],
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare(
+            "INSERT INTO mod_image_cache (mod_id, image_url, content_type, data, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, unixepoch())
+             ON CONFLICT(mod_id) DO UPDATE SET
+                 image_url = excluded.image_url,
+                 content_type = excluded.content_type,
+                 data = excluded.data,
+                 fetched_at = excluded.fetched_at",
+        )?
+        .execute(params![mod_id, image_url, content_type, data])?;
+
+        Ok(())
+    }
+}
@@ -15,6 +15,14 @@ pub struct Modlist {
     pub xxhash64: String,
     pub available: bool,
     pub muted: bool,
+    pub has_unknown_downloaders: bool,
+    pub game_type: Option<String>,
+    /// Set when the file's metadata could not be parsed. The file is kept,
+    /// but the modlist has no mods until a retry succeeds.
+    pub parse_error: Option<String>,
+    pub author: Option<String>,
+    /// Unix time the modlist was first stored.
+    pub created_at: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,6 +33,10 @@ pub struct ModlistEgg {
     pub size: u64,
     pub xxhash64: String,
     pub available: bool,
+    pub has_unknown_downloaders: bool,
+    pub game_type: Option<String>,
+    pub parse_error: Option<String>,
+    pub author: Option<String>,
 }
 
 impl Modlist {
@@ -37,7 +49,12 @@ impl Modlist {
             size: row.get(4)?,
             xxhash64: row.get(5)?,
             available: row.get(6)?,
-            muted: row.get(7).unwrap_or(false),
+            muted: row.get(7)?,
+            has_unknown_downloaders: row.get(8)?,
+            game_type: row.get(9)?,
+            parse_error: row.get(10)?,
+            author: row.get(11)?,
+            created_at: row.get(12)?,
         })
     }
 
@@ -45,7 +62,7 @@ impl Modlist {
         filename: &str,
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Option<Self>, rusqlite::Error> {
-        let archive = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted FROM modlist WHERE filename = ?1")?
+        let archive = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at FROM modlist WHERE filename = ?1")?
         .query_row(params![filename], |row| {
           Ok(Modlist::from_row(row))
         })
@@ -60,7 +77,7 @@ impl Modlist {
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Option<Self>, rusqlite::Error> {
         let archive = conn
-            .prepare("SELECT id, filename, name, version, size, xxhash64, available, muted FROM modlist WHERE xxhash64 = ?1")?
+            .prepare("SELECT id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at FROM modlist WHERE xxhash64 = ?1")?
             .query_row(params![hash], |row| Ok(Modlist::from_row(row)))
             .optional()?
             .transpose()?;
@@ -72,7 +89,7 @@ impl Modlist {
         id: u64,
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Option<Self>, rusqlite::Error> {
-        let archive = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted FROM modlist WHERE id = ?1")?
+        let archive = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at FROM modlist WHERE id = ?1")?
             .query_row(params![id], |row| {
                 Ok(Modlist::from_row(row))
             })
@@ -85,7 +102,7 @@ impl Modlist {
     pub fn get_all(
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Vec<Self>, rusqlite::Error> {
-        let mut stmt = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted FROM modlist ORDER BY name, version DESC")?;
+        let mut stmt = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at FROM modlist WHERE parse_error IS NULL ORDER BY name, version DESC")?;
         let archives = stmt
             .query_map([], Modlist::from_row)?
             .collect::<Result<Vec<_>, _>>()?;
@@ -93,10 +110,32 @@ impl Modlist {
         Ok(archives)
     }
 
+    /// Up to `limit` modlists in name order, starting after the
+    /// `(name, id)` cursor, for the paged `/api/modlists`.
+    pub fn get_page(
+        after: Option<(String, u64)>,
+        limit: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let (after_name, after_id) = match after {
+            Some((name, id)) => (Some(name), id as i64),
+            None => (None, 0),
+        };
+        let mut stmt = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at FROM modlist WHERE parse_error IS NULL AND (?1 IS NULL OR (name, id) > (?1, ?2)) ORDER BY name, id LIMIT ?3")?;
+        let archives = stmt
+            .query_map(
+                params![after_name, after_id, limit as i64],
+                Modlist::from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(archives)
+    }
+
     pub fn get_muted(
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Vec<Self>, rusqlite::Error> {
-        let mut stmt = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted FROM modlist WHERE muted = TRUE ORDER BY name, version DESC")?;
+        let mut stmt = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at FROM modlist WHERE muted = TRUE AND parse_error IS NULL ORDER BY name, version DESC")?;
         let archives = stmt
             .query_map([], Modlist::from_row)?
             .collect::<Result<Vec<_>, _>>()?;
@@ -104,40 +143,54 @@ impl Modlist {
         Ok(archives)
     }
 
-    pub fn update(
-        &self,
+    /// Modlists whose metadata failed to parse, for the diagnostics page.
+    pub fn get_parse_failed(
         conn: &PooledConnection<SqliteConnectionManager>,
-    ) -> Result<(), rusqlite::Error> {
-        conn.prepare("INSERT OR REPLACE INTO modlist (id, filename, name, version, size, xxhash64, available, muted) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?
-        .execute(params![self.id, self.filename, self.name, self.version, self.size, self.xxhash64, self.available, self.muted])?;
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare("SELECT id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at FROM modlist WHERE parse_error IS NOT NULL ORDER BY filename")?;
+        let archives = stmt
+            .query_map([], Modlist::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(())
+        Ok(archives)
     }
 
-    pub fn count_mods_total(
-        &self,
+    /// Distinct game types across all modlists, for filter dropdowns.
+    pub fn get_game_types(
         conn: &PooledConnection<SqliteConnectionManager>,
-    ) -> Result<u64, rusqlite::Error> {
-        let count: i64 = conn
-            .prepare("SELECT COUNT(*) FROM mod_association WHERE modlist_id = ?1")?
-            .query_row(params![self.id], |row| row.get(0))?;
+    ) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT game_type FROM modlist WHERE game_type IS NOT NULL ORDER BY game_type",
+        )?;
+        let game_types = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        Ok(game_types)
+    }
+
+    /// Distinct authors across all modlists, for filter dropdowns.
+    pub fn get_authors(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT author FROM modlist WHERE author IS NOT NULL ORDER BY author",
+        )?;
+        let authors = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
 
-        Ok(count as u64)
+        Ok(authors)
     }
 
-    pub fn count_mods_available(
+    pub fn update(
         &self,
         conn: &PooledConnection<SqliteConnectionManager>,
-    ) -> Result<u64, rusqlite::Error> {
-        let count: i64 = conn
-            .prepare(
-                "SELECT COUNT(*) FROM mod_association
-             INNER JOIN \"mod\" ON mod_association.mod_id = \"mod\".id
-             WHERE mod_association.modlist_id = ?1 AND \"mod\".disk_filename IS NOT NULL",
-            )?
-            .query_row(params![self.id], |row| row.get(0))?;
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare("INSERT OR REPLACE INTO modlist (id, filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)")?
+        .execute(params![self.id, self.filename, self.name, self.version, self.size, self.xxhash64, self.available, self.muted, self.has_unknown_downloaders, self.game_type, self.parse_error, self.author, self.created_at])?;
 
-        Ok(count as u64)
+        Ok(())
     }
 
     pub fn has_lost_forever_mods(
@@ -148,13 +201,27 @@ impl Modlist {
             .prepare(
                 "SELECT COUNT(*) FROM mod_association
              INNER JOIN \"mod\" ON mod_association.mod_id = \"mod\".id
-             WHERE mod_association.modlist_id = ?1 AND \"mod\".lost_forever = TRUE",
+             WHERE mod_association.modlist_id = ?1 AND NOT mod_association.optional
+               AND \"mod\".lost_forever = TRUE",
             )?
             .query_row(params![self.id], |row| row.get(0))?;
 
         Ok(count > 0)
     }
 
+    /// Number and total size of the archives the modlist downloads.
+    pub fn archive_totals(
+        &self,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(u64, u64), rusqlite::Error> {
+        conn.prepare(
+            "SELECT COUNT(*), COALESCE(SUM(\"mod\".size), 0) FROM mod_association
+             INNER JOIN \"mod\" ON mod_association.mod_id = \"mod\".id
+             WHERE mod_association.modlist_id = ?1",
+        )?
+        .query_row(params![self.id], |row| Ok((row.get(0)?, row.get(1)?)))
+    }
+
     #[allow(dead_code)]
     pub fn get_mod_associations(
         &self,
@@ -180,11 +247,15 @@ impl ModlistEgg {
         &self,
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Modlist, rusqlite::Error> {
-        conn.prepare("INSERT INTO modlist (filename, name, version, size, xxhash64, available, muted) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?
-          .execute(params![self.filename, self.name, self.version, self.size, self.xxhash64, self.available, false])?;
+        conn.prepare("INSERT INTO modlist (filename, name, version, size, xxhash64, available, muted, has_unknown_downloaders, game_type, parse_error, author) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)")?
+          .execute(params![self.filename, self.name, self.version, self.size, self.xxhash64, self.available, false, self.has_unknown_downloaders, self.game_type, self.parse_error, self.author])?;
+        let id = conn.last_insert_rowid() as u64;
+        let created_at = conn
+            .prepare("SELECT created_at FROM modlist WHERE id = ?1")?
+            .query_row(params![id], |row| row.get(0))?;
 
         Ok(Modlist {
-            id: conn.last_insert_rowid() as u64,
+            id,
             filename: self.filename.clone(),
             name: self.name.clone(),
             version: self.version.clone(),
@@ -192,6 +263,11 @@ impl ModlistEgg {
             xxhash64: self.xxhash64.clone(),
             available: self.available,
             muted: false,
+            has_unknown_downloaders: self.has_unknown_downloaders,
+            game_type: self.game_type.clone(),
+            parse_error: self.parse_error.clone(),
+            author: self.author.clone(),
+            created_at,
         })
     }
 }
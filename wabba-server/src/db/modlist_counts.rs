@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// A modlist's required-mod counters, stored in `modlist_counts` and kept
+/// current by triggers on `mod` and `mod_association`. NSFW counts are
+/// kept apart so readiness rules that ignore NSFW archives can subtract
+/// them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ModlistCounters {
+    pub mods_total: u64,
+    pub mods_available: u64,
+    pub lost_forever: u64,
+    pub nsfw_total: u64,
+    pub nsfw_available: u64,
+    pub nsfw_lost_forever: u64,
+}
+
+/// The same counters as the triggers maintain, computed from scratch.
+const RECOMPUTE_QUERY: &str = "SELECT modlist_id, COUNT(*), SUM(available), SUM(lost_forever),
+           SUM(nsfw), SUM(nsfw AND available), SUM(nsfw AND lost_forever)
+      FROM (
+          SELECT a.modlist_id,
+                 m.disk_filename IS NOT NULL AS available,
+                 m.lost_forever AS lost_forever,
+                 COALESCE(json_extract(a.source, '$.IsNSFW'), 0) AS nsfw
+            FROM mod_association a
+           INNER JOIN \"mod\" m ON m.id = a.mod_id
+           WHERE NOT a.optional
+      )
+     GROUP BY modlist_id";
+
+/// A modlist whose stored counters disagree with a recount.
+#[derive(Debug, Clone, Serialize)]
+pub struct CounterMismatch {
+    pub modlist_id: u64,
+    pub stored: ModlistCounters,
+    pub actual: ModlistCounters,
+}
+
+impl ModlistCounters {
+    fn query(
+        sql: &str,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<HashMap<u64, Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(sql)?;
+        let counters = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    ModlistCounters {
+                        mods_total: row.get(1)?,
+                        mods_available: row.get(2)?,
+                        lost_forever: row.get(3)?,
+                        nsfw_total: row.get(4)?,
+                        nsfw_available: row.get(5)?,
+                        nsfw_lost_forever: row.get(6)?,
+                    },
+                ))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(counters)
+    }
+
+    /// The stored counters of every modlist with required mods.
+    pub fn get_all(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<HashMap<u64, Self>, rusqlite::Error> {
+        ModlistCounters::query(
+            "SELECT modlist_id, mods_total, mods_available, lost_forever,
+                    nsfw_total, nsfw_available, nsfw_lost_forever
+               FROM modlist_counts",
+            conn,
+        )
+    }
+
+    /// The stored counters of one modlist; all zero when it has no
+    /// required mods.
+    pub fn get(
+        modlist_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Self, rusqlite::Error> {
+        let counters = conn
+            .prepare(
+                "SELECT mods_total, mods_available, lost_forever,
+                        nsfw_total, nsfw_available, nsfw_lost_forever
+                   FROM modlist_counts
+                  WHERE modlist_id = ?1",
+            )?
+            .query_row([modlist_id], |row| {
+                Ok(ModlistCounters {
+                    mods_total: row.get(0)?,
+                    mods_available: row.get(1)?,
+                    lost_forever: row.get(2)?,
+                    nsfw_total: row.get(3)?,
+                    nsfw_available: row.get(4)?,
+                    nsfw_lost_forever: row.get(5)?,
+                })
+            })
+            .optional()?;
+
+        Ok(counters.unwrap_or_default())
+    }
+
+    /// Modlists whose stored counters differ from a full recount, for the
+    /// diagnostics page. Empty when the triggers have kept up.
+    pub fn find_mismatches(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<CounterMismatch>, rusqlite::Error> {
+        let stored = ModlistCounters::get_all(conn)?;
+        let actual = ModlistCounters::query(RECOMPUTE_QUERY, conn)?;
+        let mut modlist_ids: Vec<u64> = stored.keys().chain(actual.keys()).copied().collect();
+        modlist_ids.sort();
+        modlist_ids.dedup();
+
+        Ok(modlist_ids
+            .into_iter()
+            .filter_map(|modlist_id| {
+                let stored = stored.get(&modlist_id).copied().unwrap_or_default();
+                let actual = actual.get(&modlist_id).copied().unwrap_or_default();
+                (stored != actual).then_some(CounterMismatch {
+                    modlist_id,
+                    stored,
+                    actual,
+                })
+            })
+            .collect())
+    }
+
+    /// Replaces every stored counter with a full recount.
+    pub fn rebuild(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM modlist_counts", [])?;
+        tx.execute(
+            &format!(
+                "INSERT INTO modlist_counts
+                     (modlist_id, mods_total, mods_available, lost_forever,
+                      nsfw_total, nsfw_available, nsfw_lost_forever)
+                 {}",
+                RECOMPUTE_QUERY
+            ),
+            [],
+        )?;
+        tx.commit()
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+use serde::Serialize;
+
+use crate::db::modlist_counts::ModlistCounters;
+
+/// When a modlist that is missing some archives still counts as
+/// installable. Modlists without a stored rule need every archive.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessRule {
+    pub modlist_id: u64,
+    /// Share of counted archives that must be available, 0–100.
+    pub min_available_percent: f64,
+    /// Leave archives flagged NSFW by their source out of every count.
+    pub ignore_nsfw: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModlistStatus {
+    Ready,
+    /// Missing archives, but within the modlist's readiness rule.
+    InstallableEnough,
+    MissingFiles,
+    /// A counted archive is lost forever.
+    Uninstallable,
+}
+
+impl ModlistStatus {
+    /// The `status-badge` modifier class.
+    pub fn badge_class(&self) -> &'static str {
+        match self {
+            ModlistStatus::Ready => "available",
+            ModlistStatus::InstallableEnough => "warning",
+            ModlistStatus::MissingFiles | ModlistStatus::Uninstallable => "missing",
+        }
+    }
+}
+
+/// Archive counts for one modlist under its readiness rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModlistReadiness {
+    pub counted: u64,
+    pub available: u64,
+    pub lost_forever: u64,
+    /// Archives the rule leaves out.
+    pub ignored: u64,
+    pub status: ModlistStatus,
+}
+
+impl ReadinessRule {
+    pub fn default_for(modlist_id: u64) -> Self {
+        ReadinessRule {
+            modlist_id,
+            min_available_percent: 100.0,
+            ignore_nsfw: false,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.min_available_percent >= 100.0 && !self.ignore_nsfw
+    }
+
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(ReadinessRule {
+            modlist_id: row.get(0)?,
+            min_available_percent: row.get(1)?,
+            ignore_nsfw: row.get(2)?,
+        })
+    }
+
+    /// The stored rule, or the default if there is none.
+    pub fn get_by_modlist_id(
+        modlist_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Self, rusqlite::Error> {
+        let rule = conn
+            .prepare(
+                "SELECT modlist_id, min_available_percent, ignore_nsfw
+                   FROM modlist_readiness_rule WHERE modlist_id = ?1",
+            )?
+            .query_row(params![modlist_id], |row| Ok(ReadinessRule::from_row(row)))
+            .optional()?
+            .transpose()?;
+
+        Ok(rule.unwrap_or_else(|| ReadinessRule::default_for(modlist_id)))
+    }
+
+    /// Every stored rule, keyed by modlist id.
+    pub fn get_all(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<HashMap<u64, Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT modlist_id, min_available_percent, ignore_nsfw FROM modlist_readiness_rule",
+        )?;
+        let rules = stmt
+            .query_map([], ReadinessRule::from_row)?
+            .map(|rule| rule.map(|rule| (rule.modlist_id, rule)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(rules)
+    }
+
+    /// Stores the rule, or removes it if it is the default.
+    pub fn save(
+        &self,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        if self.is_default() {
+            conn.prepare("DELETE FROM modlist_readiness_rule WHERE modlist_id = ?1")?
+                .execute(params![self.modlist_id])?;
+        } else {
+            conn.prepare(
+                "INSERT OR REPLACE INTO modlist_readiness_rule (modlist_id, min_available_percent, ignore_nsfw)
+                 VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![
+                self.modlist_id,
+                self.min_available_percent,
+                self.ignore_nsfw
+            ])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ModlistReadiness {
+    pub fn compute(
+        rule: &ReadinessRule,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Self, rusqlite::Error> {
+        let (counted, available, lost_forever, ignored): (u64, u64, u64, u64) = conn
+            .prepare(
+                "SELECT COALESCE(SUM(NOT ignored), 0),
+                        COALESCE(SUM(NOT ignored AND available), 0),
+                        COALESCE(SUM(NOT ignored AND lost_forever), 0),
+                        COALESCE(SUM(ignored), 0)
+                   FROM (
+                       SELECT (?2 AND COALESCE(json_extract(a.source, '$.IsNSFW'), 0)) AS ignored,
+                              m.disk_filename IS NOT NULL AS available,
+                              m.lost_forever AS lost_forever
+                         FROM mod_association a
+                        INNER JOIN \"mod\" m ON m.id = a.mod_id
+                        WHERE a.modlist_id = ?1 AND NOT a.optional
+                   )",
+            )?
+            .query_row(params![rule.modlist_id, rule.ignore_nsfw], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+
+        Ok(ModlistReadiness::from_counts(
+            rule.min_available_percent,
+            counted,
+            available,
+            lost_forever,
+            ignored,
+        ))
+    }
+
+    fn from_counts(
+        min_available_percent: f64,
+        counted: u64,
+        available: u64,
+        lost_forever: u64,
+        ignored: u64,
+    ) -> Self {
+        let status = if lost_forever > 0 {
+            ModlistStatus::Uninstallable
+        } else if available == counted {
+            ModlistStatus::Ready
+        } else if available as f64 * 100.0 >= min_available_percent * counted as f64 {
+            ModlistStatus::InstallableEnough
+        } else {
+            ModlistStatus::MissingFiles
+        };
+
+        ModlistReadiness {
+            counted,
+            available,
+            lost_forever,
+            ignored,
+            status,
+        }
+    }
+}
+
+/// Everything the modlist listings show about one modlist's mods.
+#[derive(Debug, Clone)]
+pub struct ModlistCounts {
+    /// Required mods, ignoring the readiness rule.
+    pub mods_total: u64,
+    pub mods_available: u64,
+    pub readiness: ModlistReadiness,
+}
+
+impl Default for ModlistCounts {
+    /// A modlist without required mods.
+    fn default() -> Self {
+        ModlistCounts {
+            mods_total: 0,
+            mods_available: 0,
+            readiness: ModlistReadiness::from_counts(100.0, 0, 0, 0, 0),
+        }
+    }
+}
+
+impl ModlistCounts {
+    /// Counts and readiness of every modlist with required mods, keyed by
+    /// modlist id, from the stored counters and readiness rules.
+    pub fn get_all(
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<HashMap<u64, Self>, rusqlite::Error> {
+        let rules = ReadinessRule::get_all(conn)?;
+        let counts = ModlistCounters::get_all(conn)?
+            .into_iter()
+            .map(|(modlist_id, counters)| {
+                let rule = rules
+                    .get(&modlist_id)
+                    .cloned()
+                    .unwrap_or_else(|| ReadinessRule::default_for(modlist_id));
+                // Counters can't go negative; saturate rather than panic if
+                // they have drifted until the diagnostics page rebuilds them.
+                let (ignored, ignored_available, ignored_lost_forever) = if rule.ignore_nsfw {
+                    (
+                        counters.nsfw_total,
+                        counters.nsfw_available,
+                        counters.nsfw_lost_forever,
+                    )
+                } else {
+                    (0, 0, 0)
+                };
+                let readiness = ModlistReadiness::from_counts(
+                    rule.min_available_percent,
+                    counters.mods_total.saturating_sub(ignored),
+                    counters.mods_available.saturating_sub(ignored_available),
+                    counters.lost_forever.saturating_sub(ignored_lost_forever),
+                    ignored,
+                );
+                (
+                    modlist_id,
+                    ModlistCounts {
+                        mods_total: counters.mods_total,
+                        mods_available: counters.mods_available,
+                        readiness,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(counts)
+    }
+}
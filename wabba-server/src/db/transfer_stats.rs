@@ -0,0 +1,92 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use serde::Serialize;
+
+/// Bytes one client sent and received on one day (UTC).
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyTransfer {
+    /// `YYYY-MM-DD`.
+    pub day: String,
+    pub client: String,
+    pub uploaded_bytes: u64,
+    pub downloaded_bytes: u64,
+}
+
+impl DailyTransfer {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(DailyTransfer {
+            day: row.get(0)?,
+            client: row.get(1)?,
+            uploaded_bytes: row.get(2)?,
+            downloaded_bytes: row.get(3)?,
+        })
+    }
+
+    /// Adds to today's totals for `client`.
+    pub fn record(
+        client: &str,
+        uploaded_bytes: u64,
+        downloaded_bytes: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "INSERT INTO transfer_stats (day, client, uploaded_bytes, downloaded_bytes)
+             VALUES (date('now'), ?1, ?2, ?3)
+             ON CONFLICT(day, client) DO UPDATE SET
+                 uploaded_bytes = uploaded_bytes + excluded.uploaded_bytes,
+                 downloaded_bytes = downloaded_bytes + excluded.downloaded_bytes",
+            params![client, uploaded_bytes, downloaded_bytes],
+        )?;
+        Ok(())
+    }
+
+    /// Every client's totals for each of the last `days` days, newest day
+    /// first and the busiest client first within a day.
+    pub fn get_recent(
+        days: u32,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT day, client, uploaded_bytes, downloaded_bytes
+               FROM transfer_stats
+              WHERE day > date('now', '-' || ?1 || ' days')
+              ORDER BY day DESC, uploaded_bytes + downloaded_bytes DESC, client",
+        )?;
+        stmt.query_map(params![days], Self::from_row)?.collect()
+    }
+}
+
+/// One client's totals over a period.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientTransfer {
+    pub client: String,
+    pub uploaded_bytes: u64,
+    pub downloaded_bytes: u64,
+}
+
+impl ClientTransfer {
+    /// Sums `daily` per client, busiest client first.
+    pub fn totals(daily: &[DailyTransfer]) -> Vec<Self> {
+        let mut totals: Vec<ClientTransfer> = Vec::new();
+        for day in daily {
+            match totals.iter_mut().find(|total| total.client == day.client) {
+                Some(total) => {
+                    total.uploaded_bytes += day.uploaded_bytes;
+                    total.downloaded_bytes += day.downloaded_bytes;
+                }
+                None => totals.push(ClientTransfer {
+                    client: day.client.clone(),
+                    uploaded_bytes: day.uploaded_bytes,
+                    downloaded_bytes: day.downloaded_bytes,
+                }),
+            }
+        }
+        totals.sort_by(|a, b| {
+            (b.uploaded_bytes + b.downloaded_bytes)
+                .cmp(&(a.uploaded_bytes + a.downloaded_bytes))
+                .then_with(|| a.client.cmp(&b.client))
+        });
+        totals
+    }
+}
@@ -0,0 +1,115 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// A Wayback Machine capture of a mod's dead HTTP source, offered as an
+/// alternate place to get the file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WaybackCapture {
+    pub mod_id: u64,
+    /// The original source URL.
+    pub url: String,
+    /// The capture's `YYYYMMDDhhmmss` timestamp.
+    pub timestamp: String,
+    pub mime_type: Option<String>,
+}
+
+/// When a source URL was last looked up, whether or not anything was found.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WaybackLookup {
+    pub mod_id: u64,
+    pub url: String,
+    pub looked_up_at: i64,
+}
+
+impl WaybackCapture {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(WaybackCapture {
+            mod_id: row.get(0)?,
+            url: row.get(1)?,
+            timestamp: row.get(2)?,
+            mime_type: row.get(3)?,
+        })
+    }
+
+    /// Link to the archived bytes themselves rather than the Wayback
+    /// viewer page.
+    pub fn download_url(&self) -> String {
+        format!(
+            "https://web.archive.org/web/{}id_/{}",
+            self.timestamp, self.url
+        )
+    }
+
+    /// Newest captures first.
+    pub fn get_by_mod_id(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT mod_id, url, timestamp, mime_type
+             FROM wayback_capture
+             WHERE mod_id = ?1
+             ORDER BY timestamp DESC",
+        )?;
+        let captures = stmt
+            .query_map(params![mod_id], WaybackCapture::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(captures)
+    }
+}
+
+impl WaybackLookup {
+    pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
+        Ok(WaybackLookup {
+            mod_id: row.get(0)?,
+            url: row.get(1)?,
+            looked_up_at: row.get(2)?,
+        })
+    }
+
+    pub fn get_by_mod_id(
+        mod_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT mod_id, url, looked_up_at
+             FROM wayback_lookup
+             WHERE mod_id = ?1
+             ORDER BY url",
+        )?;
+        let lookups = stmt
+            .query_map(params![mod_id], WaybackLookup::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(lookups)
+    }
+
+    /// Replaces the captures known for `url` with `captures`.
+    pub fn record(
+        mod_id: u64,
+        url: &str,
+        captures: &[(String, Option<String>)],
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), rusqlite::Error> {
+        conn.prepare("DELETE FROM wayback_capture WHERE mod_id = ?1 AND url = ?2")?
+            .execute(params![mod_id, url])?;
+        let mut insert = conn.prepare(
+            "INSERT OR IGNORE INTO wayback_capture (mod_id, url, timestamp, mime_type)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (timestamp, mime_type) in captures {
+            insert.execute(params![mod_id, url, timestamp, mime_type])?;
+        }
+        conn.prepare(
+            "INSERT INTO wayback_lookup (mod_id, url, looked_up_at)
+             VALUES (?1, ?2, unixepoch())
+             ON CONFLICT(mod_id, url) DO UPDATE SET looked_up_at = excluded.looked_up_at",
+        )?
+        .execute(params![mod_id, url])?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use regex::Regex;
+
+/// Patterns skipped no matter what is configured.
+const DEFAULT_PATTERNS: &[&str] = &["*.meta", "upload_*.tmp"];
+
+/// Glob patterns for files that ingest should never pick up.
+///
+/// Patterns containing a `/` are matched against the path relative to the
+/// directory being scanned (e.g. `nxm_links/*`, `__MACOSX/**`); patterns
+/// without one are matched against the file name alone (e.g. `*.part`).
+/// `*` matches within a single path segment, `**` across segments and `?`
+/// a single character.
+#[derive(Debug, Clone)]
+pub struct IgnorePatterns {
+    patterns: Vec<(String, Regex, bool)>,
+}
+
+impl IgnorePatterns {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, regex::Error> {
+        let patterns = DEFAULT_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .chain(
+                patterns
+                    .iter()
+                    .map(|p| p.as_ref().trim().to_string())
+                    .filter(|p| !p.is_empty()),
+            )
+            .map(|pattern| {
+                let regex = glob_to_regex(&pattern)?;
+                let match_path = pattern.contains('/');
+                Ok((pattern, regex, match_path))
+            })
+            .collect::<Result<Vec<_>, regex::Error>>()?;
+        Ok(IgnorePatterns { patterns })
+    }
+
+    /// Reads a comma-separated list from `INGEST_IGNORE_PATTERNS`.
+    pub fn from_env() -> Result<Self, regex::Error> {
+        let configured = std::env::var("INGEST_IGNORE_PATTERNS").unwrap_or_default();
+        let patterns: Vec<&str> = configured.split(',').collect();
+        Self::new(&patterns)
+    }
+
+    /// `relative_path` is relative to the scanned directory and uses `/`
+    /// separators.
+    pub fn is_ignored(&self, relative_path: &str) -> bool {
+        let file_name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+        self.patterns.iter().any(|(_, regex, match_path)| {
+            if *match_path {
+                regex.is_match(relative_path)
+            } else {
+                regex.is_match(file_name)
+            }
+        })
+    }
+
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.patterns.iter().map(|(pattern, _, _)| pattern.as_str())
+    }
+}
+
+fn glob_to_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut regex = String::from("^");
+    let mut chars = glob.trim_start_matches('/').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex)
+}
@@ -0,0 +1,445 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::slow_log::for_job;
+
+/// How many finished jobs are kept around for the admin page.
+const FINISHED_JOBS_RETAINED: usize = 200;
+
+/// Cap on verbose log lines kept per job.
+const JOB_LOG_LINES_RETAINED: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Bootstrap,
+    BootstrapModlists,
+    BootstrapMods,
+    Verify,
+    Reconcile,
+    Offload,
+    Download,
+    MetadataRefresh,
+    Digest,
+    ReparseModlists,
+    StorageMigration,
+    ImportDownloads,
+    ExpireUploads,
+}
+
+impl JobKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobKind::Bootstrap => "Bootstrap",
+            JobKind::BootstrapModlists => "Bootstrap modlists",
+            JobKind::BootstrapMods => "Bootstrap mods",
+            JobKind::Verify => "Verify mod hashes",
+            JobKind::Reconcile => "Reconcile disk state",
+            JobKind::Offload => "Offload to cold storage",
+            JobKind::Download => "Download missing mods",
+            JobKind::MetadataRefresh => "Refresh LoversLab metadata",
+            JobKind::Digest => "Generate digest",
+            JobKind::ReparseModlists => "Retry unparsed modlists",
+            JobKind::StorageMigration => "Migrate storage",
+            JobKind::ImportDownloads => "Import downloads folder",
+            JobKind::ExpireUploads => "Expire abandoned uploads",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    /// A dry run finished; its plan is waiting to be confirmed or discarded.
+    AwaitingConfirmation,
+    Succeeded,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "Queued",
+            JobStatus::Running => "Running",
+            JobStatus::AwaitingConfirmation => "Awaiting confirmation",
+            JobStatus::Succeeded => "Succeeded",
+            JobStatus::Failed(_) => "Failed",
+            JobStatus::Cancelled => "Cancelled",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded | JobStatus::Failed(_) | JobStatus::Cancelled
+        )
+    }
+}
+
+/// A non-fatal failure recorded by a job while it kept going.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobError {
+    /// What the error is about, usually a file name.
+    pub item: String,
+    pub message: String,
+}
+
+/// One step a destructive job intends to take, recorded during a dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedAction {
+    /// Short verb, e.g. "delete" or "move".
+    pub action: String,
+    /// What the action applies to, usually a file name or row id.
+    pub target: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub progress: Option<String>,
+    pub errors: Vec<JobError>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The job this one was retried from, if any.
+    pub retry_of: Option<u64>,
+    /// The dry run whose plan this job is executing, if any.
+    pub confirmed_from: Option<u64>,
+    pub dry_run: bool,
+    pub verbose: bool,
+    /// Actions computed by a dry run.
+    pub plan: Option<Vec<PlannedAction>>,
+    /// Lines written with `JobContext::log` when the job is verbose.
+    pub log: Vec<String>,
+}
+
+/// Body of a job. Runs on the blocking thread pool and should check
+/// `JobContext::is_cancelled` between units of work.
+pub type JobFn = Arc<dyn Fn(&JobContext) -> Result<(), String> + Send + Sync>;
+
+/// Dry-run half of a destructive job: works out what would be done without
+/// touching anything.
+pub type PlanFn = Arc<dyn Fn(&JobContext) -> Result<Vec<PlannedAction>, String> + Send + Sync>;
+
+/// Execute half of a destructive job: applies a confirmed plan. Should
+/// re-check each action, since things may have changed since the dry run.
+pub type ExecuteFn = Arc<dyn Fn(&JobContext, &[PlannedAction]) -> Result<(), String> + Send + Sync>;
+
+#[derive(Clone, Default)]
+struct JobOptions {
+    retry_of: Option<u64>,
+    confirmed_from: Option<u64>,
+    verbose: bool,
+    /// Set for dry runs; run once the plan is confirmed.
+    execute: Option<ExecuteFn>,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    run: JobFn,
+    options: JobOptions,
+    cancel: Arc<AtomicBool>,
+}
+
+struct Inner {
+    next_id: u64,
+    jobs: BTreeMap<u64, JobEntry>,
+}
+
+/// Handle passed to a running job.
+pub struct JobContext {
+    id: u64,
+    verbose: bool,
+    cancel: Arc<AtomicBool>,
+    registry: JobRegistry,
+}
+
+impl JobContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Free-form progress line shown on the admin page.
+    pub fn set_progress(&self, progress: impl Into<String>) {
+        let progress = progress.into();
+        self.registry
+            .update(self.id, |info| info.progress = Some(progress));
+    }
+
+    /// Records a per-item failure; the job carries on with the next item.
+    pub fn record_error(&self, item: impl Into<String>, message: impl Into<String>) {
+        let error = JobError {
+            item: item.into(),
+            message: message.into(),
+        };
+        log::warn!("Job {}: {}: {}", self.id, error.item, error.message);
+        self.registry
+            .update(self.id, |info| info.errors.push(error));
+    }
+
+    /// Detailed log line, kept on the job when it was started in verbose
+    /// mode and otherwise only sent to the debug log.
+    pub fn log(&self, line: impl Into<String>) {
+        let line = line.into();
+        log::debug!("Job {}: {}", self.id, line);
+        if self.verbose {
+            self.registry.update(self.id, |info| {
+                if info.log.len() < JOB_LOG_LINES_RETAINED {
+                    info.log.push(line);
+                }
+            });
+        }
+    }
+
+    fn set_plan(&self, plan: Vec<PlannedAction>) {
+        self.registry.update(self.id, |info| info.plan = Some(plan));
+    }
+}
+
+/// In-process registry and runner for background jobs. Jobs run one at a
+/// time, in submission order, on the blocking thread pool.
+#[derive(Clone)]
+pub struct JobRegistry {
+    inner: Arc<Mutex<Inner>>,
+    slots: Arc<Semaphore>,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        JobRegistry {
+            inner: Arc::new(Mutex::new(Inner {
+                next_id: 1,
+                jobs: BTreeMap::new(),
+            })),
+            slots: Arc::new(Semaphore::new(1)),
+        }
+    }
+
+    pub fn submit(&self, kind: JobKind, run: JobFn) -> u64 {
+        self.submit_inner(kind, run, JobOptions::default())
+    }
+
+    /// Queues a dry run of a destructive job. `plan` runs now and its actions
+    /// are stored on the job; `execute` only runs once the plan is confirmed
+    /// with [`JobRegistry::confirm`].
+    pub fn submit_dry_run(
+        &self,
+        kind: JobKind,
+        plan: PlanFn,
+        execute: ExecuteFn,
+        verbose: bool,
+    ) -> u64 {
+        let run: JobFn = Arc::new(move |job: &JobContext| {
+            let actions = plan(job)?;
+            job.set_plan(actions);
+            Ok(())
+        });
+        self.submit_inner(
+            kind,
+            run,
+            JobOptions {
+                verbose,
+                execute: Some(execute),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Executes the plan of a dry run awaiting confirmation, returning the id
+    /// of the new job.
+    pub fn confirm(&self, id: u64) -> Option<u64> {
+        let (kind, plan, execute, verbose) = {
+            let mut inner = self.inner.lock().unwrap();
+            let entry = inner.jobs.get_mut(&id)?;
+            if entry.info.status != JobStatus::AwaitingConfirmation {
+                return None;
+            }
+            let plan = entry.info.plan.clone().unwrap_or_default();
+            let execute = entry.options.execute.clone()?;
+            entry.info.status = JobStatus::Succeeded;
+            entry.info.finished_at = Some(Utc::now());
+            (entry.info.kind, plan, execute, entry.options.verbose)
+        };
+        let run: JobFn = Arc::new(move |job: &JobContext| execute(job, &plan));
+        Some(self.submit_inner(
+            kind,
+            run,
+            JobOptions {
+                confirmed_from: Some(id),
+                verbose,
+                ..Default::default()
+            },
+        ))
+    }
+
+    fn submit_inner(&self, kind: JobKind, run: JobFn, options: JobOptions) -> u64 {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let id = {
+            let mut inner = self.inner.lock().unwrap();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            inner.jobs.insert(
+                id,
+                JobEntry {
+                    info: JobInfo {
+                        id,
+                        kind,
+                        status: JobStatus::Queued,
+                        progress: None,
+                        errors: Vec::new(),
+                        created_at: Utc::now(),
+                        started_at: None,
+                        finished_at: None,
+                        retry_of: options.retry_of,
+                        confirmed_from: options.confirmed_from,
+                        dry_run: options.execute.is_some(),
+                        verbose: options.verbose,
+                        plan: None,
+                        log: Vec::new(),
+                    },
+                    run: run.clone(),
+                    options: options.clone(),
+                    cancel: cancel.clone(),
+                },
+            );
+            Self::prune(&mut inner);
+            id
+        };
+        log::info!("Queued job {} ({})", id, kind.label());
+        let dry_run = options.execute.is_some();
+
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let _permit = registry.slots.clone().acquire_owned().await;
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            registry.update(id, |info| {
+                info.status = JobStatus::Running;
+                info.started_at = Some(Utc::now());
+            });
+            log::info!("Started job {} ({})", id, kind.label());
+
+            let context = JobContext {
+                id,
+                verbose: options.verbose,
+                cancel: cancel.clone(),
+                registry: registry.clone(),
+            };
+            let origin = format!("job {} ({})", id, kind.label());
+            let result = tokio::task::spawn_blocking(move || for_job(origin, || run(&context)))
+                .await
+                .unwrap_or_else(|e| Err(format!("Job panicked: {}", e)));
+
+            let status = match result {
+                _ if cancel.load(Ordering::Relaxed) => JobStatus::Cancelled,
+                Ok(()) if dry_run => JobStatus::AwaitingConfirmation,
+                Ok(()) => JobStatus::Succeeded,
+                Err(e) => JobStatus::Failed(e),
+            };
+            match &status {
+                JobStatus::Failed(e) => log::error!("Job {} ({}) failed: {}", id, kind.label(), e),
+                other => log::info!("Job {} ({}) {}", id, kind.label(), other.label()),
+            }
+            registry.update(id, |info| {
+                if status != JobStatus::AwaitingConfirmation {
+                    info.finished_at = Some(Utc::now());
+                }
+                info.status = status;
+            });
+        });
+
+        id
+    }
+
+    /// Requests cancellation. Queued jobs are cancelled immediately, as are
+    /// dry runs awaiting confirmation (discarding their plan); running jobs
+    /// stop at their next cancellation check.
+    pub fn cancel(&self, id: u64) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.jobs.get_mut(&id) {
+            Some(entry) if !entry.info.status.is_finished() => {
+                entry.cancel.store(true, Ordering::Relaxed);
+                if matches!(
+                    entry.info.status,
+                    JobStatus::Queued | JobStatus::AwaitingConfirmation
+                ) {
+                    entry.info.status = JobStatus::Cancelled;
+                    entry.info.finished_at = Some(Utc::now());
+                }
+                log::info!("Cancellation requested for job {}", id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Re-queues a failed or cancelled job, returning the id of the new job.
+    /// Retrying a dry run produces a fresh plan.
+    pub fn retry(&self, id: u64) -> Option<u64> {
+        let (kind, run, options) = {
+            let inner = self.inner.lock().unwrap();
+            let entry = inner.jobs.get(&id)?;
+            match entry.info.status {
+                JobStatus::Failed(_) | JobStatus::Cancelled => {}
+                _ => return None,
+            }
+            (entry.info.kind, entry.run.clone(), entry.options.clone())
+        };
+        Some(self.submit_inner(
+            kind,
+            run,
+            JobOptions {
+                retry_of: Some(id),
+                ..options
+            },
+        ))
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobInfo> {
+        let inner = self.inner.lock().unwrap();
+        inner.jobs.get(&id).map(|e| e.info.clone())
+    }
+
+    /// All known jobs, newest first.
+    pub fn list(&self) -> Vec<JobInfo> {
+        let inner = self.inner.lock().unwrap();
+        inner.jobs.values().rev().map(|e| e.info.clone()).collect()
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut JobInfo)) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(entry) = inner.jobs.get_mut(&id) {
+            f(&mut entry.info);
+        }
+    }
+
+    fn prune(inner: &mut Inner) {
+        let finished: Vec<u64> = inner
+            .jobs
+            .values()
+            .filter(|e| e.info.status.is_finished())
+            .map(|e| e.info.id)
+            .collect();
+        if finished.len() > FINISHED_JOBS_RETAINED {
+            for id in &finished[..finished.len() - FINISHED_JOBS_RETAINED] {
+                inner.jobs.remove(id);
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! The server's logger. Levels come from the environment; on top of those,
+//! admins can raise the level of some modules for a while from the
+//! diagnostics page, to see what an upload or ingest is doing without
+//! restarting the server in the middle of it.
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// The modules behind uploads and ingest, offered as the default on the
+/// diagnostics page.
+pub const UPLOAD_MODULES: &[&str] = &[
+    concat!(env!("CARGO_CRATE_NAME"), "::resources"),
+    concat!(env!("CARGO_CRATE_NAME"), "::web::upload_page"),
+];
+
+/// A level raised at runtime for `module` and the modules under it.
+#[derive(Debug, Clone)]
+pub struct LogOverride {
+    pub module: String,
+    pub level: LevelFilter,
+    pub until: Instant,
+}
+
+/// The runtime overrides of the installed logger. Clones share them.
+#[derive(Clone)]
+pub struct LogControl {
+    overrides: Arc<RwLock<Vec<LogOverride>>>,
+    /// The most verbose level the environment asks for, restored when the
+    /// overrides are cleared.
+    base_max: LevelFilter,
+}
+
+impl Default for LogControl {
+    fn default() -> Self {
+        LogControl {
+            overrides: Arc::default(),
+            base_max: LevelFilter::Info,
+        }
+    }
+}
+
+impl LogControl {
+    /// Logs `modules` at `level` for the next `duration`, replacing any
+    /// earlier override of the same module.
+    pub fn raise(&self, modules: &[String], level: LevelFilter, duration: Duration) {
+        let until = Instant::now() + duration;
+        {
+            let mut overrides = self.overrides.write().unwrap();
+            overrides.retain(|o| o.until > Instant::now() && !modules.contains(&o.module));
+            overrides.extend(modules.iter().map(|module| LogOverride {
+                module: module.clone(),
+                level,
+                until,
+            }));
+        }
+        if level > log::max_level() {
+            log::set_max_level(level);
+        }
+        // Only once the lock is released: logging reads the overrides.
+        log::info!(
+            "Logging {} at {} for {} minutes",
+            modules.join(", "),
+            level,
+            duration.as_secs() / 60
+        );
+    }
+
+    /// Drops every override, back to the levels from the environment.
+    pub fn reset(&self) {
+        self.overrides.write().unwrap().clear();
+        log::set_max_level(self.base_max);
+        log::info!("Cleared runtime log levels");
+    }
+
+    /// The overrides that haven't run out yet.
+    pub fn active(&self) -> Vec<LogOverride> {
+        let now = Instant::now();
+        self.overrides
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|o| o.until > now)
+            .cloned()
+            .collect()
+    }
+
+    /// The level of the most specific live override covering `target`.
+    fn level_for(&self, target: &str) -> Option<LevelFilter> {
+        let overrides = self.overrides.read().unwrap();
+        if overrides.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        overrides
+            .iter()
+            .filter(|o| o.until > now && target.starts_with(&o.module))
+            .max_by_key(|o| o.module.len())
+            .map(|o| o.level)
+    }
+}
+
+struct Logger {
+    base: Filter,
+    output: env_logger::Logger,
+    control: LogControl,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match self.control.level_for(metadata.target()) {
+            Some(level) => metadata.level() <= level,
+            None => self.base.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.output.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.output.flush();
+    }
+}
+
+/// Installs the logger, configured from the environment:
+///
+/// - `LOG_LEVEL`: the default level (default `info`)
+/// - `LOG_FILTERS`: levels for some modules, comma separated, as in
+///   `wabba_server::resources::ingest=debug,actix_web=warn`
+/// - `RUST_LOG`: still honoured, and wins over both
+///
+/// Request logs from actix are kept at `warn` unless one of these says
+/// otherwise, as it logs every request at `info`.
+pub fn init_from_env() -> Result<LogControl, String> {
+    let level = match std::env::var("LOG_LEVEL") {
+        Ok(value) if !value.trim().is_empty() => value
+            .trim()
+            .parse::<LevelFilter>()
+            .map_err(|_| format!("Invalid LOG_LEVEL: {}", value))?,
+        _ => LevelFilter::Info,
+    };
+    let mut filter = FilterBuilder::new();
+    filter
+        .filter_level(level)
+        .filter_module("actix_web::middleware::logger", LevelFilter::Warn);
+    for name in ["LOG_FILTERS", "RUST_LOG"] {
+        if let Ok(filters) = std::env::var(name) {
+            filter.parse(&filters);
+        }
+    }
+    let base = filter.build();
+
+    let control = LogControl {
+        overrides: Arc::default(),
+        base_max: base.filter(),
+    };
+    let logger = Logger {
+        output: env_logger::Builder::new()
+            .filter_level(LevelFilter::Trace)
+            .build(),
+        base,
+        control: control.clone(),
+    };
+    log::set_boxed_logger(Box::new(logger)).map_err(|e| e.to_string())?;
+    log::set_max_level(control.base_max);
+    Ok(control)
+}
@@ -23,83 +23,108 @@ pub mod prelude {
     pub use std::time::{SystemTime, UNIX_EPOCH};
 }
 
+mod app;
+mod bandwidth;
+mod cold_storage;
+mod config;
+mod credentials;
 mod data_dir;
 mod db;
+mod ignore;
+mod jobs;
+mod logging;
+mod notifications;
+mod openapi;
 mod resources;
+mod slow_log;
+#[cfg(test)]
+mod test_support;
+#[cfg(test)]
+mod tests;
+mod transfer_stats;
 mod web;
 use std::path::PathBuf;
 
+use crate::app::AppState;
+use crate::bandwidth::BandwidthLimiter;
+use crate::cold_storage::ColdStorage;
+use crate::config::{
+    BandwidthSchedule, DigestConfig, DownloaderConfig, ListenConfig, MetadataRefreshConfig,
+    OidcConfig, VerificationPolicy,
+};
+use crate::credentials::CredentialVault;
 use crate::data_dir::DataDir;
 use crate::db::migrations::migrate;
+use crate::ignore::IgnorePatterns;
+use crate::jobs::JobRegistry;
+use crate::notifications::Notifier;
 use crate::prelude::*;
-use crate::resources::bootstrap::{bootstrap, bootstrap_modlists, bootstrap_mods};
-use crate::resources::{check_mod, check_modlist, hello_world, upload_mod, upload_modlist};
-use crate::web::details_page::{
-    delete_mod, delete_modlist, details_page, download_mod, download_modlist, mod_details_page,
-    mod_image, rename_modlist, toggle_lost_forever, toggle_muted,
-};
-use crate::web::listing_page::{listing_page, mods_listing_page, muted_modlists_page};
-use crate::web::upload_page::{upload_page, upload_post};
-use wabba_server::serve_static_file;
+use crate::resources::digest::spawn_digest_schedule;
+use crate::resources::downloader::spawn_download_schedule;
+use crate::resources::loverslab::spawn_metadata_refresh_schedule;
+use crate::resources::resumable_upload::spawn_upload_expiry_schedule;
+use crate::resources::verification::spawn_verification_schedule;
+use crate::slow_log::log_slow_requests;
+use crate::transfer_stats::record_transfers;
+use crate::web::auth::require_login;
 
-async fn start_http(
-    pool: Pool<SqliteConnectionManager>,
-    data_dir: DataDir,
-) -> Result<(), std::io::Error> {
-    log::info!("Starting HTTP server at http://localhost:8080/api");
+async fn start_http(state: AppState, listen: ListenConfig) -> Result<(), std::io::Error> {
+    log::info!("Starting HTTP server at http://{}/api", listen.admin_addr);
 
-    HttpServer::new(move || {
+    let admin_state = state.clone();
+    let session_key = state.oidc.as_ref().map_or(Key::from(&[0; 64]), |oidc| {
+        Key::derive_from(oidc.session_secret.as_bytes())
+    });
+    let public_session_key = session_key.clone();
+    let admin = HttpServer::new(move || {
         App::new()
+            .wrap(middleware::from_fn(record_transfers))
+            .wrap(middleware::from_fn(require_login))
             .wrap(
-                SessionMiddleware::builder(CookieSessionStore::default(), Key::from(&[0; 64]))
+                SessionMiddleware::builder(CookieSessionStore::default(), session_key.clone())
                     .cookie_secure(false)
                     .build(),
             )
-            .app_data(Data::new(pool.clone()))
-            .app_data(Data::new(data_dir.clone()))
             .wrap(middleware::Logger::default())
-            .service(hello_world)
-            .service(upload_modlist)
-            .service(upload_mod)
-            .service(check_modlist)
-            .service(check_mod)
-            .service(listing_page)
-            .service(mods_listing_page)
-            .service(muted_modlists_page)
-            .service(details_page)
-            .service(mod_details_page)
-            .service(mod_image)
-            .service(download_mod)
-            .service(download_modlist)
-            .service(toggle_lost_forever)
-            .service(toggle_muted)
-            .service(rename_modlist)
-            .service(delete_mod)
-            .service(delete_modlist)
-            .service(bootstrap)
-            .service(bootstrap_modlists)
-            .service(bootstrap_mods)
-            .service(upload_page)
-            .service(upload_post)
-            .service(serve_static_file!("htmx.min.js"))
-            .service(serve_static_file!("idiomorph.min.js"))
-            .service(serve_static_file!("idiomorph-ext.min.js"))
-            .service(serve_static_file!("styles.css"))
+            .wrap(middleware::from_fn(log_slow_requests))
+            .configure(|cfg| app::configure(cfg, &admin_state))
     })
-    .bind(("0.0.0.0", 8080))?
-    .run()
-    .await
+    .bind(&listen.admin_addr)?
+    .run();
+
+    let Some(public_addr) = listen.public_addr else {
+        return admin.await;
+    };
+    let public_downloads = listen.public_downloads;
+    log::info!("Starting read-only HTTP server at http://{}/", public_addr);
+    let public = HttpServer::new(move || {
+        // Only holds the language picked on the public pages.
+        App::new()
+            .wrap(middleware::from_fn(record_transfers))
+            .wrap(
+                SessionMiddleware::builder(
+                    CookieSessionStore::default(),
+                    public_session_key.clone(),
+                )
+                .cookie_secure(false)
+                .build(),
+            )
+            .wrap(middleware::Logger::default())
+            .wrap(middleware::from_fn(log_slow_requests))
+            .configure(|cfg| app::configure_public(cfg, &state, public_downloads))
+    })
+    .bind(&public_addr)?
+    .run();
+
+    futures_util::future::try_join(admin, public)
+        .await
+        .map(|_| ())
 }
 
 #[actix_web::main]
 #[allow(clippy::expect_used)]
 async fn main() -> std::io::Result<()> {
-    // Configure logger with custom filter to prioritize Discord logs
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info) // Set default level to Info for most modules
-        .filter_module("actix_web::middleware::logger", log::LevelFilter::Warn) // Actix web middleware logs every request at info
-        .parse_default_env()
-        .init();
+    let logging = logging::init_from_env().expect("Invalid logging settings");
 
     let data_dir = DataDir::new(&PathBuf::from(
         std::env::var("DATA_DIR").expect("DATA_DIR environment variable is not set"),
@@ -109,14 +134,105 @@ async fn main() -> std::io::Result<()> {
     log::info!("Data directory: {:?}", data_dir.get_path());
 
     // connect to SQLite DB
-    let manager = SqliteConnectionManager::file(data_dir.get_db_path());
+    slow_log::init_from_env().expect("Invalid slow log settings");
+    let manager = SqliteConnectionManager::file(data_dir.get_db_path()).with_init(|conn| {
+        slow_log::profile_connection(conn);
+        Ok(())
+    });
     let pool = Pool::new(manager).expect("Failed to create database pool");
     {
         let conn = pool.get().expect("Failed to get database connection");
         migrate(conn).expect("Failed to run database migrations");
     }
 
-    start_http(pool.clone(), data_dir).await?;
+    let ignore = IgnorePatterns::from_env().expect("Invalid INGEST_IGNORE_PATTERNS");
+    log::info!(
+        "Ingest ignore patterns: {}",
+        ignore.patterns().collect::<Vec<_>>().join(", ")
+    );
+
+    let verification = VerificationPolicy::from_env().expect("Invalid verification settings");
+    let jobs = JobRegistry::new();
+    spawn_verification_schedule(
+        pool.clone(),
+        data_dir.clone(),
+        jobs.clone(),
+        verification.clone(),
+    );
+
+    spawn_upload_expiry_schedule(data_dir.clone(), jobs.clone());
+
+    let cold_storage = ColdStorage::from_env().expect("Invalid cold storage settings");
+    log::info!("Cold storage: {}", cold_storage.label());
+
+    let bandwidth = BandwidthSchedule::from_env().expect("Invalid bandwidth settings");
+    log::info!(
+        "Background bandwidth: default {}, {} scheduled window(s)",
+        bandwidth
+            .default_limit
+            .map_or("unlimited".to_string(), |limit| format!(
+                "{} KB/s",
+                limit / 1024
+            )),
+        bandwidth.windows.len()
+    );
+    let limiter = BandwidthLimiter::new(bandwidth);
+
+    let vault = CredentialVault::from_env();
+    if !vault.is_unlocked() {
+        log::warn!("CREDENTIALS_KEY is not set; third-party credentials are unavailable");
+    }
+
+    let downloader = DownloaderConfig::from_env().expect("Invalid downloader settings");
+    spawn_download_schedule(
+        pool.clone(),
+        data_dir.clone(),
+        jobs.clone(),
+        downloader.clone(),
+        limiter.clone(),
+        vault.clone(),
+    );
+
+    let metadata_refresh =
+        MetadataRefreshConfig::from_env().expect("Invalid metadata refresh settings");
+    spawn_metadata_refresh_schedule(
+        pool.clone(),
+        jobs.clone(),
+        vault.clone(),
+        metadata_refresh.clone(),
+    );
+
+    let notifier = Notifier::from_env();
+    let digest = DigestConfig::from_env().expect("Invalid digest settings");
+    spawn_digest_schedule(pool.clone(), jobs.clone(), digest.clone(), notifier.clone());
+
+    let listen = ListenConfig::from_env().expect("Invalid listener settings");
+    let oidc = OidcConfig::from_env().expect("Invalid OIDC settings");
+    match &oidc {
+        Some(oidc) => log::info!("Web UI sign-in through {}", oidc.issuer_url),
+        None => log::info!("OIDC_ISSUER_URL is not set; the web UI is open to anyone"),
+    }
+
+    start_http(
+        AppState {
+            pool,
+            data_dir,
+            jobs,
+            ignore,
+            verification,
+            cold_storage,
+            limiter,
+            downloader,
+            vault,
+            metadata_refresh,
+            digest,
+            notifier,
+            oidc,
+            logging,
+        },
+        listen,
+    )
+    .await?;
 
     Ok(())
 }
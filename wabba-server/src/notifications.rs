@@ -0,0 +1,113 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Unix time the payload was signed at; part of the signed message so a
+/// captured request can't be replayed later.
+pub const TIMESTAMP_HEADER: &str = "X-Wabba-Timestamp";
+/// `sha256=` followed by the hex HMAC-SHA256 of `{timestamp}.{body}`.
+pub const SIGNATURE_HEADER: &str = "X-Wabba-Signature";
+
+/// Posts events to the webhooks configured in `NOTIFY_WEBHOOK_URLS`.
+///
+/// Discord webhook URLs get a `content` message with the summary; every
+/// other URL gets the full event as JSON:
+/// `{"event": "...", "summary": "...", "data": {...}}`.
+///
+/// With `NOTIFY_WEBHOOK_SECRET` set, every request also carries
+/// [`TIMESTAMP_HEADER`] and [`SIGNATURE_HEADER`] so receivers can check it
+/// came from this server.
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    webhook_urls: Vec<String>,
+    secret: Option<String>,
+}
+
+/// Discord rejects messages longer than this.
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+fn is_discord(url: &str) -> bool {
+    url.starts_with("https://discord.com/api/webhooks/")
+        || url.starts_with("https://discordapp.com/api/webhooks/")
+}
+
+/// The [`SIGNATURE_HEADER`] value for `body` sent at `timestamp`.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    format!(
+        "sha256={}",
+        digest
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+    )
+}
+
+impl Notifier {
+    /// Reads `NOTIFY_WEBHOOK_URLS`, a comma-separated list of URLs, and
+    /// the optional signing key `NOTIFY_WEBHOOK_SECRET`.
+    pub fn from_env() -> Self {
+        Notifier {
+            webhook_urls: std::env::var("NOTIFY_WEBHOOK_URLS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(|url| url.to_string())
+                .collect(),
+            secret: std::env::var("NOTIFY_WEBHOOK_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.webhook_urls.is_empty()
+    }
+
+    /// Sends `event` to every webhook. Failures are collected rather than
+    /// stopping delivery to the remaining hooks.
+    pub async fn notify(
+        &self,
+        event: &str,
+        summary: &str,
+        data: &serde_json::Value,
+    ) -> Result<(), String> {
+        let client = reqwest::Client::new();
+        let mut errors = Vec::new();
+        for url in &self.webhook_urls {
+            let body = if is_discord(url) {
+                let content: String = summary.chars().take(DISCORD_MESSAGE_LIMIT).collect();
+                serde_json::json!({ "content": content })
+            } else {
+                serde_json::json!({ "event": event, "summary": summary, "data": data })
+            };
+            let body = serde_json::to_vec(&body).map_err(|e| e.to_string())?;
+            let mut request = client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json");
+            if let Some(secret) = &self.secret {
+                let timestamp = chrono::Utc::now().timestamp();
+                request = request
+                    .header(TIMESTAMP_HEADER, timestamp.to_string())
+                    .header(SIGNATURE_HEADER, sign(secret, timestamp, &body));
+            }
+            match request.body(body).send().await {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => {
+                    errors.push(format!("Webhook responded with {}", response.status()))
+                }
+                Err(e) => errors.push(format!("Webhook request failed: {}", e)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
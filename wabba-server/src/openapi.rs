@@ -0,0 +1,62 @@
+use utoipa::OpenApi;
+
+use wabba_protocol::api::{
+    ArchiveKind, AvailabilityReport, HashCheckRequest, ModSummary, ModlistArchive, ModlistSummary,
+    UploadResult,
+};
+
+use crate::resources::jobs_api::JobSummary;
+
+/// The machine-facing part of the server: uploads, downloads, hash lookups,
+/// inventory and jobs. Served at `/api/openapi.json`, with Swagger UI at
+/// `/api/docs/`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "wabba-server"),
+    paths(
+        crate::resources::hello_world,
+        crate::resources::check_modlist,
+        crate::resources::check_mod,
+        crate::resources::check_hashes,
+        crate::resources::upload_modlist,
+        crate::resources::upload_mod,
+        crate::resources::resumable_upload::create_upload,
+        crate::resources::resumable_upload::upload_status,
+        crate::resources::resumable_upload::upload_chunk,
+        crate::resources::resumable_upload::cancel_upload,
+        crate::web::details_page::download_mod,
+        crate::web::details_page::download_mod_by_hash,
+        crate::web::details_page::download_modlist,
+        crate::resources::inventory::list_modlists,
+        crate::resources::inventory::list_mods,
+        crate::resources::inventory::modlist_archives,
+        crate::resources::inventory::mod_meta,
+        crate::resources::jobs_api::list_jobs,
+        crate::resources::jobs_api::get_job,
+        crate::resources::bootstrap::bootstrap,
+        crate::resources::verification::verify_mods,
+        crate::resources::reconciliation::reconcile,
+        crate::resources::downloader::run_downloads,
+        crate::resources::loverslab::refresh_metadata,
+        crate::resources::backup::backup_database,
+    ),
+    components(schemas(
+        ArchiveKind,
+        HashCheckRequest,
+        AvailabilityReport,
+        UploadResult,
+        ModlistSummary,
+        ModSummary,
+        ModlistArchive,
+        JobSummary
+    )),
+    tags(
+        (name = "uploads", description = "Submitting modlists and mods"),
+        (name = "downloads", description = "Fetching stored files"),
+        (name = "lookups", description = "Asking whether the server already has a file"),
+        (name = "inventory", description = "Listing what the server knows about"),
+        (name = "jobs", description = "Starting and inspecting background jobs"),
+        (name = "maintenance", description = "Looking after the server itself"),
+    )
+)]
+pub struct ApiDoc;
@@ -0,0 +1,41 @@
+// Confirmation dialogs for destructive admin actions.
+//
+// Confirm buttons load a <dialog class="confirm-dialog"> at the end of the
+// page with htmx; this opens it as a modal, so focus stays inside it and
+// Escape cancels. Confirmations that ask for typed text keep their action
+// disabled until the text matches. The server checks it again.
+(function () {
+  "use strict";
+
+  function wireTypedConfirmation(container) {
+    var input = container.querySelector("input[data-confirm-text]");
+    var submit = container.querySelector("button.danger[type=submit]");
+    if (!input || !submit) {
+      return;
+    }
+    var update = function () {
+      submit.disabled = input.value.trim() !== input.dataset.confirmText;
+    };
+    input.addEventListener("input", update);
+    update();
+  }
+
+  document.addEventListener("htmx:load", function (event) {
+    var dialog = event.detail.elt;
+    if (!dialog.matches || !dialog.matches("dialog.confirm-dialog")) {
+      return;
+    }
+    wireTypedConfirmation(dialog);
+    dialog.addEventListener("close", function () {
+      dialog.remove();
+    });
+    dialog.showModal();
+  });
+
+  document.addEventListener("DOMContentLoaded", function () {
+    var page = document.querySelector(".confirm-page");
+    if (page) {
+      wireTypedConfirmation(page);
+    }
+  });
+})();
@@ -0,0 +1,50 @@
+// Copy buttons next to shortened hashes.
+//
+// Pages only show the start of a hash; the button copies the full value
+// from its data-copy attribute. The listener is on the document so rows
+// added later by htmx work too.
+(function () {
+  "use strict";
+
+  function flash(button, text) {
+    var original = button.dataset.label || button.textContent;
+    button.dataset.label = original;
+    button.textContent = text;
+    setTimeout(function () {
+      button.textContent = original;
+    }, 1200);
+  }
+
+  // For pages served over plain HTTP, where navigator.clipboard is missing.
+  function copyWithSelection(text) {
+    var area = document.createElement("textarea");
+    area.value = text;
+    area.setAttribute("readonly", "");
+    area.style.position = "fixed";
+    area.style.opacity = "0";
+    document.body.appendChild(area);
+    area.select();
+    var copied = document.execCommand("copy");
+    area.remove();
+    return copied ? Promise.resolve() : Promise.reject();
+  }
+
+  document.addEventListener("click", function (event) {
+    var button = event.target.closest && event.target.closest("button.copy-hash");
+    if (!button) {
+      return;
+    }
+    var text = button.dataset.copy;
+    var copy = navigator.clipboard
+      ? navigator.clipboard.writeText(text)
+      : copyWithSelection(text);
+    copy.then(
+      function () {
+        flash(button, "✓");
+      },
+      function () {
+        flash(button, "✗");
+      }
+    );
+  });
+})();
@@ -0,0 +1,203 @@
+// Resumable uploads for the /upload page.
+//
+// Speaks the tus-style protocol served under /uploads: reserve an upload,
+// ask the server how much it already has, then PATCH the rest in chunks.
+// Each chunk carries a CRC32 checksum which the server verifies before
+// writing. The upload URL is remembered in localStorage so a reload (or a
+// dropped Wi-Fi connection) picks up where it left off.
+(function () {
+  "use strict";
+
+  var CHUNK_SIZE = 8 * 1024 * 1024;
+  var MAX_RETRIES = 10;
+
+  var CRC_TABLE = (function () {
+    var table = new Uint32Array(256);
+    for (var n = 0; n < 256; n++) {
+      var c = n;
+      for (var k = 0; k < 8; k++) {
+        c = c & 1 ? 0xedb88320 ^ (c >>> 1) : c >>> 1;
+      }
+      table[n] = c >>> 0;
+    }
+    return table;
+  })();
+
+  function crc32(bytes) {
+    var crc = 0xffffffff;
+    for (var i = 0; i < bytes.length; i++) {
+      crc = CRC_TABLE[(crc ^ bytes[i]) & 0xff] ^ (crc >>> 8);
+    }
+    return (crc ^ 0xffffffff) >>> 0;
+  }
+
+  function crc32Header(bytes) {
+    var crc = crc32(bytes);
+    var raw = String.fromCharCode(
+      (crc >>> 24) & 0xff,
+      (crc >>> 16) & 0xff,
+      (crc >>> 8) & 0xff,
+      crc & 0xff
+    );
+    return "crc32 " + btoa(raw);
+  }
+
+  function utf8Base64(text) {
+    var bytes = new TextEncoder().encode(text);
+    var raw = "";
+    for (var i = 0; i < bytes.length; i++) {
+      raw += String.fromCharCode(bytes[i]);
+    }
+    return btoa(raw);
+  }
+
+  function storageKey(file) {
+    return (
+      "wabba-upload:" + file.name + ":" + file.size + ":" + file.lastModified
+    );
+  }
+
+  function sleep(ms) {
+    return new Promise(function (resolve) {
+      setTimeout(resolve, ms);
+    });
+  }
+
+  async function createUpload(file) {
+    var response = await fetch("/uploads", {
+      method: "POST",
+      headers: {
+        "Upload-Length": String(file.size),
+        "Upload-Metadata": "filename " + utf8Base64(file.name),
+      },
+    });
+    if (response.status !== 201) {
+      throw new Error("Could not start upload: " + (await response.text()));
+    }
+    return response.headers.get("Location");
+  }
+
+  async function currentOffset(url) {
+    var response = await fetch(url, { method: "HEAD", cache: "no-store" });
+    if (response.status === 404) {
+      return null;
+    }
+    if (!response.ok) {
+      throw new Error("Could not query upload status (" + response.status + ")");
+    }
+    return parseInt(response.headers.get("Upload-Offset"), 10);
+  }
+
+  async function sendChunk(url, file, offset) {
+    var blob = file.slice(offset, Math.min(offset + CHUNK_SIZE, file.size));
+    var bytes = new Uint8Array(await blob.arrayBuffer());
+    return fetch(url, {
+      method: "PATCH",
+      headers: {
+        "Content-Type": "application/offset+octet-stream",
+        "Upload-Offset": String(offset),
+        "Upload-Checksum": crc32Header(bytes),
+      },
+      body: bytes,
+    });
+  }
+
+  async function upload(file, onProgress) {
+    var key = storageKey(file);
+    var url = localStorage.getItem(key);
+    var offset = url ? await currentOffset(url).catch(function () {
+      return null;
+    }) : null;
+    if (offset === null) {
+      url = await createUpload(file);
+      localStorage.setItem(key, url);
+      offset = 0;
+    }
+
+    var failures = 0;
+    while (true) {
+      onProgress(offset, file.size);
+      var response;
+      try {
+        response = await sendChunk(url, file, offset);
+      } catch (err) {
+        response = null;
+      }
+
+      if (response && (response.status === 204 || response.status === 200)) {
+        failures = 0;
+        offset = parseInt(response.headers.get("Upload-Offset"), 10);
+        var location = response.headers.get("Location");
+        if (offset >= file.size) {
+          localStorage.removeItem(key);
+          onProgress(offset, file.size);
+          return location;
+        }
+        continue;
+      }
+
+      if (response && response.status === 404) {
+        // The server forgot about this upload; start over.
+        localStorage.removeItem(key);
+        throw new Error("Upload expired on the server, please retry");
+      }
+      if (response && response.status >= 400 && response.status < 500 &&
+          response.status !== 409 && response.status !== 460) {
+        throw new Error(await response.text());
+      }
+
+      failures += 1;
+      if (failures > MAX_RETRIES) {
+        throw new Error("Giving up after " + MAX_RETRIES + " failed attempts");
+      }
+      await sleep(Math.min(30000, 1000 * Math.pow(2, failures)));
+      // Re-sync with whatever the server actually stored before retrying.
+      var synced = await currentOffset(url).catch(function () {
+        return null;
+      });
+      if (synced !== null) {
+        offset = synced;
+      }
+    }
+  }
+
+  function formatMb(bytes) {
+    return (bytes / 1024 / 1024).toFixed(1) + " MB";
+  }
+
+  document.addEventListener("DOMContentLoaded", function () {
+    var form = document.querySelector("form[data-resumable]");
+    if (!form || !window.fetch || !window.TextEncoder || !Blob.prototype.arrayBuffer) {
+      return;
+    }
+    var input = form.querySelector("input[type=file]");
+    var status = document.getElementById("upload-status");
+    var bar = document.getElementById("upload-progress");
+
+    form.addEventListener("submit", async function (event) {
+      event.preventDefault();
+      var file = input.files[0];
+      if (!file) {
+        return;
+      }
+      form.querySelector("button").disabled = true;
+      bar.hidden = false;
+      try {
+        var location = await upload(file, function (done, total) {
+          bar.max = total;
+          bar.value = done;
+          status.textContent = formatMb(done) + " of " + formatMb(total) + " uploaded";
+        });
+        status.textContent = "Upload complete.";
+        if (location) {
+          window.location = location;
+        }
+      } catch (err) {
+        status.textContent = "Upload failed: " + err.message +
+          " — resubmit the same file to resume.";
+      } finally {
+        form.querySelector("button").disabled = false;
+      }
+    });
+  });
+})();
@@ -0,0 +1,83 @@
+//! A consistent copy of the live database, for backups taken while the
+//! server runs. Copying `db.db` directly can catch it mid-write and misses
+//! whatever still sits in the WAL; `VACUUM INTO` writes a snapshot as of one
+//! transaction instead.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::http::header;
+use actix_web::{HttpResponse, get, web};
+use futures_util::StreamExt;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use tokio_util::io::ReaderStream;
+
+use crate::data_dir::DataDir;
+
+/// The snapshot staged in the data directory. Removed when dropped, which
+/// for a sent backup is once the response body is done with it, whether it
+/// went out in full or the client went away.
+struct Snapshot(PathBuf);
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            log::warn!("Failed to remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "maintenance",
+    responses((status = 200, description = "A snapshot of the database as a SQLite file", content_type = "application/vnd.sqlite3"))
+)]
+#[get("/api/backup")]
+pub async fn backup_database(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let snapshot = Snapshot(data_dir.get_path().join(format!("db.backup-{}.db", nanos)));
+    let path = snapshot.0.clone();
+    let pool = pool.into_inner();
+    web::block(move || -> Result<(), String> {
+        let conn = pool.get().map_err(|e| e.to_string())?;
+        conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Failed to back up database: {}", e))
+    })?;
+
+    // Streamed rather than read into memory; the database can run to
+    // gigabytes.
+    let file = tokio::fs::File::open(&snapshot.0)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .len();
+    let body = ReaderStream::new(file).map(move |chunk| {
+        let _snapshot = &snapshot;
+        chunk
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/vnd.sqlite3")
+        .insert_header(header::ContentDisposition {
+            disposition: header::DispositionType::Attachment,
+            parameters: vec![header::DispositionParam::Filename("db.db".to_string())],
+        })
+        .no_chunking(size)
+        .streaming(body))
+}
@@ -1,3 +1,6 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use actix_web::{HttpResponse, post, web};
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
@@ -5,122 +8,253 @@ use wabba_protocol::hash::Hash;
 
 use crate::{
     data_dir::DataDir,
+    ignore::IgnorePatterns,
+    jobs::{JobContext, JobFn, JobKind, JobRegistry},
     resources::ingest::{ingest_mod, ingest_modlist},
 };
 
 fn bootstrap_modlists_impl(
     conn: &PooledConnection<SqliteConnectionManager>,
     data_dir: &DataDir,
-) -> Result<(), actix_web::Error> {
+    ignore: &IgnorePatterns,
+    job: &JobContext,
+) -> Result<(), String> {
     // Read all modlist files in the modlist directory
-    let modlist_files = std::fs::read_dir(data_dir.get_modlist_dir()).unwrap();
-    for modlist_file in modlist_files.filter_map(Result::ok) {
+    let modlist_files = std::fs::read_dir(data_dir.get_modlist_dir())
+        .map_err(|e| format!("Failed to read modlist directory: {}", e))?;
+    for modlist_file in modlist_files {
+        if job.is_cancelled() {
+            return Ok(());
+        }
+        let modlist_file = match modlist_file {
+            Ok(entry) => entry,
+            Err(e) => {
+                job.record_error("Modlists", format!("Failed to read directory entry: {}", e));
+                continue;
+            }
+        };
         let path = modlist_file.path();
+        if ignore.is_ignored(&modlist_file.file_name().to_string_lossy()) {
+            log::info!("Skipping ignored file: {:?}", path.file_name());
+            continue;
+        }
         if path.extension().unwrap_or_default() != "wabbajack" {
             log::info!("Skipping non-wabbajack file: {:?}", path);
             continue;
         }
         log::info!("Processing modlist file: {:?}", path.file_name());
         let file_name_os = modlist_file.file_name();
-        let filename = file_name_os.to_str().unwrap();
-        let hash = Hash::compute(&std::fs::read(&path).unwrap());
-        ingest_modlist(filename, &hash, &path, conn)?;
+        let Some(filename) = file_name_os.to_str() else {
+            job.record_error(
+                file_name_os.to_string_lossy(),
+                "File name is not valid UTF-8",
+            );
+            continue;
+        };
+        job.set_progress(format!("Modlist {}", filename));
+        let hash = match Hash::compute_file(&path) {
+            Ok(hash) => hash,
+            Err(e) => {
+                job.record_error(filename, format!("Failed to hash file: {}", e));
+                continue;
+            }
+        };
+        match ingest_modlist(filename, &hash, &path, conn) {
+            Ok(modlist) => {
+                if let Some(error) = modlist.parse_error {
+                    job.record_error(filename, format!("Failed to parse metadata: {}", error));
+                }
+            }
+            Err(e) => job.record_error(filename, format!("Failed to ingest modlist: {}", e)),
+        }
     }
 
     Ok(())
 }
 
+/// Recursively lists files under `root`, returning each file's path
+/// relative to `root` (with `/` separators) alongside its full path.
+/// Ignored files and directories are skipped, as are symlinked
+/// directories; unreadable directories are recorded on the job and skipped.
+pub fn walk_files(
+    root: &Path,
+    ignore: &IgnorePatterns,
+    job: &JobContext,
+) -> Result<Vec<(String, PathBuf)>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![String::new()];
+    while let Some(relative_dir) = pending.pop() {
+        if job.is_cancelled() {
+            break;
+        }
+        let dir = root.join(&relative_dir);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if relative_dir.is_empty() => {
+                return Err(format!("Failed to read {:?}: {}", root, e));
+            }
+            Err(e) => {
+                job.record_error(&relative_dir, format!("Failed to read directory: {}", e));
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    job.record_error(
+                        relative_dir.clone(),
+                        format!("Failed to read directory entry: {}", e),
+                    );
+                    continue;
+                }
+            };
+            let file_name_os = entry.file_name();
+            let Some(file_name) = file_name_os.to_str() else {
+                job.record_error(
+                    file_name_os.to_string_lossy(),
+                    "File name is not valid UTF-8",
+                );
+                continue;
+            };
+            let relative_path = if relative_dir.is_empty() {
+                file_name.to_string()
+            } else {
+                format!("{}/{}", relative_dir, file_name)
+            };
+            if ignore.is_ignored(&relative_path) {
+                log::info!("Skipping ignored path: {}", relative_path);
+                continue;
+            }
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    job.record_error(relative_path, format!("Failed to read file type: {}", e));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending.push(relative_path);
+            } else if file_type.is_symlink() && path.is_dir() {
+                // Following these could loop forever or pull in files from
+                // outside the tree.
+                log::info!("Skipping symlinked directory: {}", relative_path);
+            } else {
+                files.push((relative_path, path));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
 fn bootstrap_mods_impl(
     conn: &PooledConnection<SqliteConnectionManager>,
     data_dir: &DataDir,
-) -> Result<(), actix_web::Error> {
-    // Read all mod files in the mod directory
-    let mod_files = std::fs::read_dir(data_dir.get_mod_dir()).unwrap();
-    for mod_file in mod_files.filter_map(Result::ok) {
-        let path = mod_file.path();
-        if path.extension().unwrap_or_default() == "meta" {
-            log::info!("Skipping meta file: {:?}", path.file_name());
-            continue;
-        }
-        if path.is_dir() {
-            log::info!("Skipping directory: {:?}", path.file_name());
-            continue;
+    ignore: &IgnorePatterns,
+    job: &JobContext,
+) -> Result<(), String> {
+    // Read all mod files in the mod directory, including subfolders. Mods in
+    // subfolders are stored with their relative path as the disk filename.
+    let mod_files = walk_files(&data_dir.get_mod_dir(), ignore, job)?;
+    for (filename, path) in mod_files {
+        if job.is_cancelled() {
+            return Ok(());
         }
-        let file_name_os = mod_file.file_name();
-        let filename = file_name_os
-            .to_str()
-            .expect("Failed to convert file name to string");
         log::info!("Processing mod file: {:?}", filename);
-        let hash = Hash::compute(&std::fs::read(&path).expect("Failed to read mod file"));
-        ingest_mod(filename, &hash, &path, conn)?;
+        job.set_progress(format!("Mod {}", filename));
+        let hash = match Hash::compute_file(&path) {
+            Ok(hash) => hash,
+            Err(e) => {
+                job.record_error(filename, format!("Failed to hash file: {}", e));
+                continue;
+            }
+        };
+        if let Err(e) = ingest_mod(&filename, &hash, &path, conn) {
+            job.record_error(filename, format!("Failed to ingest mod: {}", e));
+        }
     }
 
     Ok(())
 }
 
-#[post("/bootstrap/modlists")]
-pub async fn bootstrap_modlists(
+/// Wraps a bootstrap body as a job, checking out a connection when it runs.
+fn bootstrap_job(
     pool: web::Data<Pool<SqliteConnectionManager>>,
     data_dir: web::Data<DataDir>,
-) -> Result<HttpResponse, actix_web::Error> {
-    tokio::task::spawn_blocking(move || {
-        let conn = pool.into_inner().get().unwrap();
-        let data_dir = data_dir.into_inner();
+    ignore: web::Data<IgnorePatterns>,
+    modlists: bool,
+    mods: bool,
+) -> JobFn {
+    Arc::new(move |job: &JobContext| {
+        let conn = pool.get().map_err(|e| e.to_string())?;
 
         log::info!(
-            "Bootstrapping modlists from data directory: {:?}",
+            "Bootstrapping from data directory: {:?}",
             data_dir.get_path()
         );
 
-        bootstrap_modlists_impl(&conn, &data_dir).expect("Failed to bootstrap modlists");
+        if modlists {
+            bootstrap_modlists_impl(&conn, &data_dir, &ignore, job)?;
+        }
+        if mods {
+            bootstrap_mods_impl(&conn, &data_dir, &ignore, job)?;
+        }
+
+        log::info!("Bootstrap complete");
+        Ok(())
+    })
+}
 
-        log::info!("Modlists bootstrap complete");
-    });
+fn redirect_to_jobs() -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish()
+}
 
-    Ok(HttpResponse::Ok().body("modlists bootstrap started"))
+#[post("/bootstrap/modlists")]
+pub async fn bootstrap_modlists(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    ignore: web::Data<IgnorePatterns>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    jobs.submit(
+        JobKind::BootstrapModlists,
+        bootstrap_job(pool, data_dir, ignore, true, false),
+    );
+    Ok(redirect_to_jobs())
 }
 
 #[post("/bootstrap/mods")]
 pub async fn bootstrap_mods(
     pool: web::Data<Pool<SqliteConnectionManager>>,
     data_dir: web::Data<DataDir>,
+    ignore: web::Data<IgnorePatterns>,
+    jobs: web::Data<JobRegistry>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    tokio::task::spawn_blocking(move || {
-        let conn = pool.into_inner().get().unwrap();
-        let data_dir = data_dir.into_inner();
-
-        log::info!(
-            "Bootstrapping mods from data directory: {:?}",
-            data_dir.get_path()
-        );
-
-        bootstrap_mods_impl(&conn, &data_dir).expect("Failed to bootstrap mods");
-
-        log::info!("Mods bootstrap complete");
-    });
-
-    Ok(HttpResponse::Ok().body("mods bootstrap started"))
+    jobs.submit(
+        JobKind::BootstrapMods,
+        bootstrap_job(pool, data_dir, ignore, false, true),
+    );
+    Ok(redirect_to_jobs())
 }
 
+#[utoipa::path(
+    tag = "jobs",
+    responses((status = 303, description = "Queued a full bootstrap; redirects to the jobs page"))
+)]
 #[post("/bootstrap")]
 pub async fn bootstrap(
     pool: web::Data<Pool<SqliteConnectionManager>>,
     data_dir: web::Data<DataDir>,
+    ignore: web::Data<IgnorePatterns>,
+    jobs: web::Data<JobRegistry>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    tokio::task::spawn_blocking(move || {
-        let conn = pool.into_inner().get().unwrap();
-        let data_dir = data_dir.into_inner();
-
-        log::info!(
-            "Bootstrapping all from data directory: {:?}",
-            data_dir.get_path()
-        );
-
-        bootstrap_modlists_impl(&conn, &data_dir).expect("Failed to bootstrap modlists");
-        bootstrap_mods_impl(&conn, &data_dir).expect("Failed to bootstrap mods");
-
-        log::info!("Bootstrapping complete");
-    });
-
-    Ok(HttpResponse::Ok().body("bootstrap started"))
+    jobs.submit(
+        JobKind::Bootstrap,
+        bootstrap_job(pool, data_dir, ignore, true, true),
+    );
+    Ok(redirect_to_jobs())
 }
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::{
+    config::DigestConfig,
+    db::digest::{Digest, StoredDigest},
+    jobs::{JobContext, JobFn, JobKind, JobRegistry},
+    notifications::Notifier,
+};
+
+/// A few lines of plain text for chat-style notifications.
+fn summarize(digest: &Digest) -> String {
+    let mut lines = vec![format!(
+        "wabba-server digest: {} modlists, {} of {} mods available",
+        digest.snapshot.modlists.len(),
+        digest.snapshot.mods_available,
+        digest.snapshot.mods_total
+    )];
+    if !digest.new_modlists.is_empty() {
+        lines.push(format!(
+            "New modlists: {}",
+            digest
+                .new_modlists
+                .iter()
+                .map(|m| format!("{} {}", m.name, m.version))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if digest.new_mods > 0 {
+        lines.push(format!(
+            "New mods: {} ({:.2} GB)",
+            digest.new_mods,
+            digest.new_mods_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+        ));
+    }
+    for change in &digest.availability_changes {
+        lines.push(match change.available_before {
+            Some(before) => format!(
+                "{}: {} -> {} of {} available",
+                change.name, before, change.available_after, change.total
+            ),
+            None => format!(
+                "{}: {} of {} available",
+                change.name, change.available_after, change.total
+            ),
+        });
+    }
+    if !digest.dead_links.is_empty() {
+        lines.push(format!("Newly dead links: {}", digest.dead_links.len()));
+    }
+    lines.push(format!(
+        "Disk usage: {:.2} GB",
+        digest.snapshot.disk_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+    ));
+    lines.join("\n")
+}
+
+fn generate_digest_impl(
+    pool: &Pool<SqliteConnectionManager>,
+    config: &DigestConfig,
+    notifier: &Notifier,
+    job: &JobContext,
+) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let previous = StoredDigest::get_recent(1, &conn)
+        .map_err(|e| format!("Database error: {}", e))?
+        .into_iter()
+        .next();
+
+    job.set_progress("Collecting statistics".to_string());
+    let digest = Digest::build(
+        previous.as_ref().map(|stored| &stored.digest),
+        config.period().as_secs() as i64,
+        &conn,
+    )
+    .map_err(|e| format!("Database error: {}", e))?;
+    let stored =
+        StoredDigest::create(digest, &conn).map_err(|e| format!("Database error: {}", e))?;
+    log::info!("Generated digest {}", stored.id);
+
+    if notifier.is_enabled() {
+        job.set_progress("Sending notifications".to_string());
+        let data = serde_json::to_value(&stored.digest).map_err(|e| e.to_string())?;
+        if let Err(e) = tokio::runtime::Handle::current().block_on(notifier.notify(
+            "digest",
+            &summarize(&stored.digest),
+            &data,
+        )) {
+            job.record_error("notifications", e);
+        }
+    }
+    Ok(())
+}
+
+fn digest_job(
+    pool: Pool<SqliteConnectionManager>,
+    config: DigestConfig,
+    notifier: Notifier,
+) -> JobFn {
+    Arc::new(move |job: &JobContext| generate_digest_impl(&pool, &config, &notifier, job))
+}
+
+/// Submits a digest every `config.interval`, if one is set.
+pub fn spawn_digest_schedule(
+    pool: Pool<SqliteConnectionManager>,
+    jobs: JobRegistry,
+    config: DigestConfig,
+    notifier: Notifier,
+) {
+    let Some(period) = config.interval else {
+        log::info!("Scheduled digests are off");
+        return;
+    };
+    log::info!("Scheduling digests every {} hours", period.as_secs() / 3600);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick fires immediately; wait a full interval after
+        // startup instead.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            jobs.submit(
+                JobKind::Digest,
+                digest_job(pool.clone(), config.clone(), notifier.clone()),
+            );
+        }
+    });
+}
+
+#[post("/stats/digests")]
+pub async fn generate_digest(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    config: web::Data<DigestConfig>,
+    notifier: web::Data<Notifier>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    jobs.submit(
+        JobKind::Digest,
+        digest_job(
+            pool.get_ref().clone(),
+            config.get_ref().clone(),
+            notifier.get_ref().clone(),
+        ),
+    );
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
@@ -0,0 +1,547 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use aes::cipher::StreamCipher;
+use futures_util::StreamExt;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use regex::Regex;
+use tokio::io::AsyncWriteExt;
+use wabba_protocol::archive_state::ArchiveState;
+use wabba_protocol::hash::Hash;
+
+use crate::{
+    bandwidth::BandwidthLimiter,
+    config::DownloaderConfig,
+    credentials::{CredentialVault, MEGA_SESSION, NEXUS_API_KEY},
+    data_dir::DataDir,
+    db::{
+        download_state::{DownloadAttempt, DownloadCandidate, DownloadSourceState},
+        mod_data::Mod,
+    },
+    jobs::{JobContext, JobFn, JobKind, JobRegistry},
+    resources::{
+        base64_to_base64url, determine_final_filename,
+        filename_policy::sanitize_filename,
+        ingest::ingest_mod,
+        mega::{self, MegaCipher, MegaLink},
+        wayback::lookup_archived_source,
+    },
+};
+
+/// Source types the background downloader knows how to fetch.
+pub const SUPPORTED_SOURCES: &[&str] = &["http", "gdrive", "mediafire", "mega", "nexus"];
+
+/// Google Drive's direct download host.
+const GOOGLE_DRIVE_DOWNLOAD_URL: &str = "https://drive.usercontent.google.com/download";
+
+/// Nexus Mods' public API.
+const NEXUS_API_URL: &str = "https://api.nexusmods.com/v1";
+
+/// Credentials from the vault, looked up once per download run.
+struct SourceCredentials {
+    nexus_api_key: Option<String>,
+    mega_session: Option<String>,
+}
+
+impl SourceCredentials {
+    fn load(vault: &CredentialVault, conn: &PooledConnection<SqliteConnectionManager>) -> Self {
+        SourceCredentials {
+            nexus_api_key: vault.get_or_warn(NEXUS_API_KEY, conn),
+            mega_session: vault.get_or_warn(MEGA_SESSION, conn),
+        }
+    }
+}
+
+/// First capture group of `pattern` in `html`.
+fn extract(html: &str, pattern: &str) -> Option<String> {
+    Regex::new(pattern)
+        .ok()?
+        .captures(html)?
+        .get(1)
+        .map(|m| m.as_str().replace("&amp;", "&"))
+}
+
+fn is_html(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"))
+}
+
+/// Sends `request`, treating non-success statuses as errors.
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Server responded with {}", response.status()));
+    }
+    Ok(response)
+}
+
+/// A response whose body is the archive, plus the cipher to decrypt it
+/// with for hosts that serve encrypted data.
+struct OpenedSource {
+    response: reqwest::Response,
+    cipher: Option<MegaCipher>,
+}
+
+impl From<reqwest::Response> for OpenedSource {
+    fn from(response: reqwest::Response) -> Self {
+        OpenedSource {
+            response,
+            cipher: None,
+        }
+    }
+}
+
+/// Performs whatever handshake `source` needs and returns the response
+/// whose body is the archive itself.
+async fn open_source(
+    client: &reqwest::Client,
+    source: &ArchiveState,
+    credentials: &SourceCredentials,
+) -> Result<OpenedSource, String> {
+    match source {
+        ArchiveState::HttpDownloader { url, headers } => {
+            // Wabbajack stores extra headers as "Name: value" strings.
+            let mut request = client.get(url);
+            for header in headers.as_array().into_iter().flatten() {
+                if let Some((name, value)) = header.as_str().and_then(|h| h.split_once(':')) {
+                    request = request.header(name.trim(), value.trim());
+                }
+            }
+            send(request).await.map(OpenedSource::from)
+        }
+        ArchiveState::GoogleDriveDownloader { id } => {
+            let request = client
+                .get(GOOGLE_DRIVE_DOWNLOAD_URL)
+                .query(&[("id", id.as_str()), ("export", "download")]);
+            let response = send(request).await?;
+            if !is_html(&response) {
+                return Ok(response.into());
+            }
+
+            // Files too large to virus-scan get an interstitial whose form
+            // carries the tokens needed to skip the scan.
+            let page = response
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read Google Drive page: {}", e))?;
+            let confirm = extract(&page, r#"name="confirm"\s+value="([^"]+)""#).ok_or_else(|| {
+                "Google Drive returned a page without a download link; the file is gone or over its download quota".to_string()
+            })?;
+            let mut query = vec![
+                ("id", id.clone()),
+                ("export", "download".to_string()),
+                ("confirm", confirm),
+            ];
+            if let Some(uuid) = extract(&page, r#"name="uuid"\s+value="([^"]+)""#) {
+                query.push(("uuid", uuid));
+            }
+            let response = send(client.get(GOOGLE_DRIVE_DOWNLOAD_URL).query(&query)).await?;
+            if is_html(&response) {
+                return Err("Google Drive did not accept the confirmation token".to_string());
+            }
+            Ok(response.into())
+        }
+        ArchiveState::MediaFireDownloader { url } => {
+            let response = send(client.get(url)).await?;
+            if !is_html(&response) {
+                return Ok(response.into());
+            }
+
+            // The file page links the real download from its download button.
+            let page = response
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read MediaFire page: {}", e))?;
+            let direct_url = extract(
+                &page,
+                r#"href="(https?://download[0-9]*\.mediafire\.com/[^"]+)""#,
+            )
+            .ok_or_else(|| {
+                "MediaFire page has no download link; the file has probably been removed"
+                    .to_string()
+            })?;
+            let response = send(client.get(direct_url)).await?;
+            if is_html(&response) {
+                return Err("MediaFire served a page instead of the file".to_string());
+            }
+            Ok(response.into())
+        }
+        ArchiveState::MegaDownloader { url } => {
+            let link = MegaLink::parse(url)?;
+            let download_url =
+                mega::download_url(&link, client, credentials.mega_session.as_deref()).await?;
+            Ok(OpenedSource {
+                response: send(client.get(download_url)).await?,
+                cipher: Some(link.cipher()),
+            })
+        }
+        ArchiveState::NexusDownloader {
+            game_name,
+            mod_id,
+            file_id,
+            ..
+        } => {
+            let api_key = credentials
+                .nexus_api_key
+                .as_deref()
+                .ok_or_else(|| "Nexus API key not configured".to_string())?;
+            // Wabbajack's game names match the Nexus domain once lowercased
+            // for every game it supports downloading from Nexus.
+            let request = client
+                .get(format!(
+                    "{}/games/{}/mods/{}/files/{}/download_link.json",
+                    NEXUS_API_URL,
+                    game_name.to_lowercase(),
+                    mod_id,
+                    file_id
+                ))
+                .header("apikey", api_key);
+            let response = request
+                .send()
+                .await
+                .map_err(|e| format!("Nexus API request failed: {}", e))?;
+            match response.status().as_u16() {
+                200 => {}
+                401 => return Err("Nexus rejected the API key".to_string()),
+                403 => {
+                    return Err(
+                        "Nexus only serves direct downloads to Premium accounts".to_string()
+                    );
+                }
+                404 => return Err("File no longer exists on Nexus".to_string()),
+                429 => return Err("Nexus API rate limit reached".to_string()),
+                status => return Err(format!("Nexus API responded with {}", status)),
+            }
+            let links: serde_json::Value = response
+                .json()
+                .await
+                .map_err(|e| format!("Unexpected Nexus API response: {}", e))?;
+            let uri = links
+                .get(0)
+                .and_then(|link| link.get("URI"))
+                .and_then(|uri| uri.as_str())
+                .ok_or_else(|| "Nexus API returned no download links".to_string())?;
+            send(client.get(uri)).await.map(OpenedSource::from)
+        }
+        other => Err(format!(
+            "Downloading from {} sources is not supported",
+            other.source_type()
+        )),
+    }
+}
+
+/// Fetches `candidate` into Downloads, checks it against the expected size
+/// and hash, and ingests it.
+async fn download_candidate(
+    candidate: &DownloadCandidate,
+    client: &reqwest::Client,
+    data_dir: &DataDir,
+    limiter: &BandwidthLimiter,
+    credentials: &SourceCredentials,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<(), String> {
+    let mod_item = &candidate.mod_item;
+    let OpenedSource {
+        response,
+        mut cipher,
+    } = open_source(client, &candidate.source, credentials).await?;
+    if let Some(length) = response.content_length()
+        && length != mod_item.size
+    {
+        return Err(format!(
+            "Server offered {} bytes, expected {}",
+            length, mod_item.size
+        ));
+    }
+
+    let downloads_dir = data_dir.get_mod_dir();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    // Named like an upload temp file so bootstrap skips it.
+    let temp_path = downloads_dir.join(format!("upload_{}.tmp", timestamp));
+    let result = async {
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .await
+            .map_err(|e| format!("Failed to create temp file: {}", e))?;
+        let mut written: u64 = 0;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| format!("Transfer failed: {}", e))?;
+            written += chunk.len() as u64;
+            if written > mod_item.size {
+                return Err(format!("Server sent more than {} bytes", mod_item.size));
+            }
+            limiter.throttle(chunk.len()).await;
+            let chunk = match cipher.as_mut() {
+                Some(cipher) => {
+                    let mut plain = chunk.to_vec();
+                    cipher.apply_keystream(&mut plain);
+                    plain.into()
+                }
+                None => chunk,
+            };
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| format!("Failed to write temp file: {}", e))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+        if written != mod_item.size {
+            return Err(format!(
+                "Transfer ended after {} of {} bytes",
+                written, mod_item.size
+            ));
+        }
+
+        let hash_path = temp_path.clone();
+        let hash = web::block(move || Hash::compute_file(&hash_path))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to hash file: {}", e))?;
+        if hash != mod_item.xxhash64 {
+            return Err(format!(
+                "Hash mismatch: expected {}, found {}",
+                mod_item.xxhash64, hash
+            ));
+        }
+
+        // Association filenames come from the modlist; never let one pick a
+        // directory.
+        let requested = candidate
+            .filename
+            .rsplit(['/', '\\'])
+            .next()
+            .and_then(|name| sanitize_filename(name).ok())
+            .unwrap_or_else(|| "download".to_string());
+        let final_filename =
+            determine_final_filename(&requested, &base64_to_base64url(&hash), &downloads_dir);
+        let final_path = downloads_dir.join(&final_filename);
+        std::fs::rename(&temp_path, &final_path)
+            .map_err(|e| format!("Failed to move file to final location: {}", e))?;
+        log::info!("Downloaded mod {} as {}", mod_item.id, final_filename);
+
+        ingest_mod(&final_filename, &hash, &final_path, conn)
+            .map_err(|e| format!("Failed to ingest mod: {}", e))
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn download_missing_impl(
+    pool: &Pool<SqliteConnectionManager>,
+    data_dir: &DataDir,
+    config: &DownloaderConfig,
+    limiter: &BandwidthLimiter,
+    vault: &CredentialVault,
+    job: &JobContext,
+) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let candidates = DownloadSourceState::select_due_candidates(SUPPORTED_SOURCES, &conn)
+        .map_err(|e| format!("Database error: {}", e))?;
+    log::info!(
+        "{} missing mods are due a download attempt",
+        candidates.len()
+    );
+
+    let credentials = SourceCredentials::load(vault, &conn);
+    let client = reqwest::Client::new();
+    let runtime = tokio::runtime::Handle::current();
+    let total = candidates.len();
+    let mut downloaded = 0;
+    for (index, candidate) in candidates.iter().enumerate() {
+        if job.is_cancelled() {
+            return Ok(());
+        }
+        let mod_id = candidate.mod_item.id;
+        let source_type = candidate.source.source_type();
+
+        // Another source may have already come through this run.
+        if Mod::get_by_id(mod_id, &conn)
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some_and(|m| m.is_available())
+        {
+            continue;
+        }
+
+        job.set_progress(format!(
+            "{}/{}: {} ({})",
+            index + 1,
+            total,
+            candidate.filename,
+            source_type
+        ));
+        let result = runtime.block_on(download_candidate(
+            candidate,
+            &client,
+            data_dir,
+            limiter,
+            &credentials,
+            &conn,
+        ));
+
+        DownloadAttempt::record(
+            mod_id,
+            source_type,
+            result.as_ref().err().map(|e| e.as_str()),
+            &conn,
+        )
+        .map_err(|e| format!("Database error: {}", e))?;
+        match result {
+            Ok(()) => {
+                downloaded += 1;
+                DownloadSourceState::record_success(mod_id, source_type, &conn)
+                    .map_err(|e| format!("Database error: {}", e))?;
+            }
+            Err(error) => {
+                let state = DownloadSourceState::record_failure(
+                    mod_id,
+                    source_type,
+                    &config.policy_for(source_type),
+                    &conn,
+                )
+                .map_err(|e| format!("Database error: {}", e))?;
+                // A quarantined HTTP source is as good as dead; see whether
+                // the Wayback Machine kept a copy.
+                if state.quarantined
+                    && let ArchiveState::HttpDownloader { url, .. } = &candidate.source
+                    && let Err(e) =
+                        runtime.block_on(lookup_archived_source(mod_id, url, &client, &conn))
+                {
+                    job.record_error(&candidate.filename, e);
+                }
+                let message = if state.quarantined {
+                    format!(
+                        "{} (quarantined after {} failures)",
+                        error, state.consecutive_failures
+                    )
+                } else {
+                    error
+                };
+                job.record_error(&candidate.filename, message);
+            }
+        }
+    }
+
+    log::info!(
+        "Download run complete: {} of {} attempts succeeded",
+        downloaded,
+        total
+    );
+    Ok(())
+}
+
+fn download_job(
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    config: DownloaderConfig,
+    limiter: BandwidthLimiter,
+    vault: CredentialVault,
+) -> JobFn {
+    Arc::new(move |job: &JobContext| {
+        download_missing_impl(&pool, &data_dir, &config, &limiter, &vault, job)
+    })
+}
+
+/// Submits a download run every `config.interval`, if one is set.
+pub fn spawn_download_schedule(
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    jobs: JobRegistry,
+    config: DownloaderConfig,
+    limiter: BandwidthLimiter,
+    vault: CredentialVault,
+) {
+    let Some(period) = config.interval else {
+        log::info!("Scheduled downloads are off");
+        return;
+    };
+    log::info!(
+        "Scheduling missing mod downloads every {} hours",
+        period.as_secs() / 3600
+    );
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick fires immediately; wait a full interval after
+        // startup instead.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            jobs.submit(
+                JobKind::Download,
+                download_job(
+                    pool.clone(),
+                    data_dir.clone(),
+                    config.clone(),
+                    limiter.clone(),
+                    vault.clone(),
+                ),
+            );
+        }
+    });
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    responses((status = 303, description = "Queued a missing mod download run; redirects to the jobs page"))
+)]
+#[post("/downloads/run")]
+pub async fn run_downloads(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    config: web::Data<DownloaderConfig>,
+    limiter: web::Data<BandwidthLimiter>,
+    vault: web::Data<CredentialVault>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    jobs.submit(
+        JobKind::Download,
+        download_job(
+            pool.get_ref().clone(),
+            data_dir.get_ref().clone(),
+            config.get_ref().clone(),
+            limiter.get_ref().clone(),
+            vault.get_ref().clone(),
+        ),
+    );
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
+
+#[post("/mod/{id}/downloads/{source_type}/release")]
+pub async fn release_download_source(
+    path: web::Path<(u64, String)>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (mod_id, source_type) = path.into_inner();
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !DownloadSourceState::release(mod_id, &source_type, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    {
+        return Err(actix_web::error::ErrorNotFound(
+            "No retry state for this source",
+        ));
+    }
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/mod/{}", mod_id)))
+        .finish())
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use actix_web::{HttpResponse, post, web};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::Deserialize;
+use wabba_protocol::hash::Hash;
+
+use crate::{
+    data_dir::DataDir,
+    db::{
+        digest::AvailabilitySnapshot,
+        mod_data::Mod,
+        mod_disk_state::{ModDiskState, file_stat},
+    },
+    ignore::IgnorePatterns,
+    jobs::{ExecuteFn, JobContext, JobKind, JobRegistry, PlanFn, PlannedAction},
+    resources::{bootstrap::walk_files, ingest::ingest_mod},
+};
+
+/// Size, mtime and hash of a file as the dry run saw it, so confirming the
+/// plan doesn't hash terabytes a second time.
+type HashedFiles = Arc<Mutex<HashMap<String, (u64, i64, String)>>>;
+
+/// Brings an existing Wabbajack downloads folder on the same filesystem
+/// into Downloads by hard-linking each archive, so nothing is copied and
+/// the original folder keeps working.
+#[derive(Clone)]
+struct DownloadsImport {
+    source: PathBuf,
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    ignore: IgnorePatterns,
+    hashed: HashedFiles,
+}
+
+impl DownloadsImport {
+    /// One "link" per archive in the folder that isn't already available
+    /// here. `.meta` files are Wabbajack's own bookkeeping and are skipped.
+    fn plan(&self, job: &JobContext) -> Result<Vec<PlannedAction>, String> {
+        let conn = self.pool.get().map_err(|e| e.to_string())?;
+        let files = walk_files(&self.source, &self.ignore, job)?;
+        let total = files.len();
+        let mut actions = Vec::new();
+        for (index, (relative_path, path)) in files.into_iter().enumerate() {
+            if job.is_cancelled() {
+                return Ok(actions);
+            }
+            if relative_path.ends_with(".meta") {
+                continue;
+            }
+            job.set_progress(format!("Hashing {}/{}: {}", index + 1, total, relative_path));
+            let hashed = file_stat(&path).and_then(|(size, mtime_ns)| {
+                Hash::compute_file(&path).map(|hash| (size, mtime_ns, hash))
+            });
+            let (size, mtime_ns, hash) = match hashed {
+                Ok(hashed) => hashed,
+                Err(e) => {
+                    job.record_error(&relative_path, format!("Failed to hash file: {}", e));
+                    continue;
+                }
+            };
+
+            let known = Mod::get_by_size_and_hash(size, &hash, &conn)
+                .map_err(|e| format!("Database error: {}", e))?;
+            let detail = match &known {
+                Some(mod_item) if mod_item.is_available() => {
+                    job.log(format!("{} is already available; skipped", relative_path));
+                    continue;
+                }
+                Some(mod_item) => {
+                    let modlists = mod_item
+                        .get_associated_modlists(&conn)
+                        .map_err(|e| format!("Database error: {}", e))?;
+                    if modlists.is_empty() {
+                        "not needed by any modlist".to_string()
+                    } else {
+                        format!(
+                            "needed by {}",
+                            modlists
+                                .iter()
+                                .map(|modlist| format!("{} {}", modlist.name, modlist.version))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )
+                    }
+                }
+                None => "not needed by any modlist".to_string(),
+            };
+            if self.data_dir.get_mod_path(&relative_path).exists() {
+                job.record_error(
+                    &relative_path,
+                    "A different file with this name is already in Downloads",
+                );
+                continue;
+            }
+
+            self.hashed
+                .lock()
+                .unwrap()
+                .insert(relative_path.clone(), (size, mtime_ns, hash));
+            actions.push(PlannedAction {
+                action: "link".to_string(),
+                target: relative_path,
+                detail: Some(detail),
+            });
+        }
+        Ok(actions)
+    }
+
+    fn execute(&self, job: &JobContext, plan: &[PlannedAction]) -> Result<(), String> {
+        let conn = self.pool.get().map_err(|e| e.to_string())?;
+        let before =
+            AvailabilitySnapshot::take(&conn).map_err(|e| format!("Database error: {}", e))?;
+
+        let total = plan.len();
+        let mut linked = 0;
+        for (index, action) in plan.iter().enumerate() {
+            if job.is_cancelled() {
+                break;
+            }
+            job.set_progress(format!("{}/{}: {}", index + 1, total, action.target));
+            match self.link(&action.target, &conn) {
+                Ok(()) => {
+                    linked += 1;
+                    job.log(format!("Linked {}", action.target));
+                }
+                Err(e) => job.record_error(&action.target, e),
+            }
+        }
+
+        let after =
+            AvailabilitySnapshot::take(&conn).map_err(|e| format!("Database error: {}", e))?;
+        let mut unlocked = Vec::new();
+        for modlist in &after.modlists {
+            let available_before = before
+                .modlists
+                .iter()
+                .find(|m| m.modlist_id == modlist.modlist_id)
+                .map_or(0, |m| m.available);
+            if modlist.available > available_before {
+                let line = format!(
+                    "{}: {} → {} of {} mods",
+                    modlist.name, available_before, modlist.available, modlist.total
+                );
+                job.log(line.clone());
+                unlocked.push(line);
+            }
+        }
+        log::info!(
+            "Imported {} of {} files from {:?}",
+            linked,
+            total,
+            self.source
+        );
+        job.set_progress(if unlocked.is_empty() {
+            format!("Linked {} files; no modlist gained mods", linked)
+        } else {
+            format!("Linked {} files; {}", linked, unlocked.join("; "))
+        });
+        Ok(())
+    }
+
+    /// Hard-links one file into Downloads under the same relative path and
+    /// ingests it. The hash from the dry run is reused only if the file is
+    /// untouched since.
+    fn link(
+        &self,
+        relative_path: &str,
+        conn: &r2d2::PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), String> {
+        let source = relative_path
+            .split('/')
+            .fold(self.source.clone(), |path, segment| path.join(segment));
+        let destination = self.data_dir.get_mod_path(relative_path);
+        if destination.exists() {
+            return Err("A file with this name is already in Downloads".to_string());
+        }
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        std::fs::hard_link(&source, &destination).map_err(|e| {
+            format!(
+                "Failed to link into Downloads (it must be on the same filesystem): {}",
+                e
+            )
+        })?;
+
+        let stat = file_stat(&destination).map_err(|e| e.to_string())?;
+        let planned = self.hashed.lock().unwrap().remove(relative_path);
+        let hash = match planned {
+            Some((size, mtime_ns, hash)) if (size, mtime_ns) == stat => hash,
+            _ => Hash::compute_file(&destination).map_err(|e| {
+                let _ = std::fs::remove_file(&destination);
+                format!("Failed to hash file: {}", e)
+            })?,
+        };
+        if let Err(e) = ingest_mod(relative_path, &hash, &destination, conn) {
+            let _ = std::fs::remove_file(&destination);
+            return Err(format!("Failed to ingest mod: {}", e));
+        }
+        // A mod that was offloaded has its local copy back.
+        if let Some(mod_item) = Mod::get_by_disk_filename(relative_path, conn)
+            .map_err(|e| format!("Database error: {}", e))?
+            && ModDiskState::get_by_mod_id(mod_item.id, conn)
+                .map_err(|e| format!("Database error: {}", e))?
+                .is_some_and(|s| s.evicted)
+        {
+            ModDiskState::record_restore(mod_item.id, stat.0, stat.1, conn)
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportDownloadsForm {
+    path: String,
+    /// Keep a line per file on the job, for reviewing what it did.
+    #[serde(default)]
+    verbose: bool,
+}
+
+/// Queues a dry run hashing every file in an existing downloads folder
+/// and listing what linking it in would add, per modlist. Nothing is
+/// linked until the plan is confirmed on the job page.
+#[post("/import/downloads")]
+pub async fn import_downloads(
+    form: web::Form<ImportDownloadsForm>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    ignore: web::Data<IgnorePatterns>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ImportDownloadsForm { path, verbose } = form.into_inner();
+    let source = PathBuf::from(path.trim());
+    if !source.is_absolute() || !source.is_dir() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Give the absolute path of a directory on the server",
+        ));
+    }
+    let source = source
+        .canonicalize()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let downloads = data_dir
+        .get_mod_dir()
+        .canonicalize()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if source.starts_with(&downloads) || downloads.starts_with(&source) {
+        return Err(actix_web::error::ErrorBadRequest(
+            "That directory overlaps Downloads; run a mods bootstrap instead",
+        ));
+    }
+
+    let import = DownloadsImport {
+        source,
+        pool: pool.get_ref().clone(),
+        data_dir: data_dir.get_ref().clone(),
+        ignore: ignore.get_ref().clone(),
+        hashed: HashedFiles::default(),
+    };
+    let planner = import.clone();
+    let plan: PlanFn = Arc::new(move |job: &JobContext| planner.plan(job));
+    let execute: ExecuteFn =
+        Arc::new(move |job: &JobContext, plan: &[PlannedAction]| import.execute(job, plan));
+    let id = jobs.submit_dry_run(JobKind::ImportDownloads, plan, execute, verbose);
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/admin/jobs/{}", id)))
+        .finish())
+}
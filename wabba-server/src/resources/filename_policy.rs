@@ -0,0 +1,40 @@
+/// Characters Windows refuses in filenames. Archives are served back to
+/// Wabbajack on Windows, so they are replaced even though Linux allows them.
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+/// Checks a client-supplied filename before it is joined onto a data
+/// directory.
+///
+/// Anything that could escape the directory (path separators, `.`, `..`) is
+/// rejected outright rather than rewritten, since it is never an honest
+/// archive name. Characters that are merely unportable are replaced with `_`,
+/// and trailing dots and spaces, which Windows silently drops, are trimmed.
+pub fn sanitize_filename(requested: &str) -> Result<String, String> {
+    if requested.contains(['/', '\\']) {
+        return Err(format!(
+            "Filename {:?} contains a path separator",
+            requested
+        ));
+    }
+    if requested.contains('\0') {
+        return Err("Filename contains a NUL byte".to_string());
+    }
+
+    let sanitized: String = requested
+        .chars()
+        .map(|c| {
+            if c.is_control() || ILLEGAL_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_end_matches(['.', ' ']);
+
+    // `.` and `..` end up here too, having lost their trailing dots.
+    if sanitized.is_empty() {
+        return Err(format!("Filename {:?} is not a valid filename", requested));
+    }
+    Ok(sanitized.to_string())
+}
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use wabba_protocol::gallery::{DownloadMetadata, Links, ModlistMetadata, machine_name};
+use wabba_protocol::wabbajack::{WabbajackMetadata, read_entry};
+
+use crate::data_dir::DataDir;
+use crate::db::modlist::Modlist;
+
+/// Shown as the repository in Wabbajack's gallery.
+fn repository_name() -> String {
+    std::env::var("GALLERY_REPOSITORY_NAME").unwrap_or_else(|_| "wabba-server".to_string())
+}
+
+fn format_timestamp(unix_seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(unix_seconds, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// Wabbajack needs absolute links, so they are built from the host the
+/// request came in on.
+pub fn base_url(req: &HttpRequest) -> String {
+    let info = req.connection_info();
+    format!("{}://{}", info.scheme(), info.host())
+}
+
+fn modlist_file(modlist: &Modlist, data_dir: &DataDir) -> Result<PathBuf, actix_web::Error> {
+    let path = data_dir.get_modlist_path(&modlist.filename);
+    if !modlist.available || !path.is_file() {
+        return Err(actix_web::error::ErrorNotFound(
+            "Modlist file missing on disk",
+        ));
+    }
+    Ok(path)
+}
+
+fn gallery_entry(
+    modlist: &Modlist,
+    base_url: &str,
+    data_dir: &DataDir,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<ModlistMetadata, actix_web::Error> {
+    let path = modlist_file(modlist, data_dir)?;
+    let metadata = WabbajackMetadata::load_quietly(&path).map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Failed to read modlist: {}", e))
+    })?;
+    let (number_of_archives, size_of_archives) = modlist
+        .archive_totals(conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let (number_of_installed_files, size_of_installed_files) = metadata.installed_files();
+
+    Ok(ModlistMetadata {
+        title: metadata.name.clone(),
+        description: metadata.description.clone(),
+        author: metadata.author.clone(),
+        maintainers: vec![metadata.author.clone()],
+        game: metadata.game_type.clone(),
+        official: false,
+        tags: Vec::new(),
+        nsfw: metadata.is_nsfw,
+        utility_list: false,
+        image_contains_title: false,
+        display_version_only_in_installer_view: false,
+        force_down: false,
+        links: Links {
+            image: if metadata.image.is_empty() {
+                String::new()
+            } else {
+                format!("{}/modlists/{}/image", base_url, modlist.id)
+            },
+            readme: metadata.readme.clone(),
+            download: format!("{}/modlists/{}/download", base_url, modlist.id),
+            machine_url: format!("{}_{}", machine_name(&modlist.name), modlist.id),
+            discord_url: String::new(),
+            website_url: metadata.website.clone(),
+        },
+        download_metadata: DownloadMetadata {
+            hash: modlist.xxhash64.clone(),
+            size: modlist.size,
+            number_of_archives,
+            size_of_archives,
+            number_of_installed_files,
+            size_of_installed_files,
+        },
+        version: metadata.version.clone(),
+        date_created: format_timestamp(modlist.created_at),
+        date_updated: format_timestamp(modlist.created_at),
+        repository_name: repository_name(),
+    })
+}
+
+/// The gallery entry for one modlist.
+#[get("/modlists/{id}/wabbajack.metadata")]
+pub async fn modlist_gallery_metadata(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let modlist = Modlist::get_by_id(id.into_inner(), &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+    let entry = gallery_entry(&modlist, &base_url(&req), &data_dir, &conn)?;
+
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+/// Every unmuted, available modlist as a Wabbajack repository. Point
+/// Wabbajack's repository list at this URL to browse them in its gallery.
+#[get("/wabbajack/modlists.json")]
+pub async fn gallery_repository(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let base_url = base_url(&req);
+    let modlists = Modlist::get_all(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let mut entries = Vec::new();
+    for modlist in modlists.iter().filter(|m| m.available && !m.muted) {
+        match gallery_entry(modlist, &base_url, &data_dir, &conn) {
+            Ok(entry) => entries.push(entry),
+            // One unreadable file shouldn't hide the rest of the gallery.
+            Err(e) => log::warn!("Leaving {} out of the gallery: {}", modlist.filename, e),
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+/// The image bundled in the .wabbajack file.
+#[get("/modlists/{id}/image")]
+pub async fn modlist_image(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let modlist = Modlist::get_by_id(id.into_inner(), &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+    let path = modlist_file(&modlist, &data_dir)?;
+    let metadata = WabbajackMetadata::load_quietly(&path).map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Failed to read modlist: {}", e))
+    })?;
+    if metadata.image.is_empty() {
+        return Err(actix_web::error::ErrorNotFound("Modlist has no image"));
+    }
+    let image = read_entry(&path, &metadata.image)
+        .map_err(|_| actix_web::error::ErrorNotFound("Modlist image missing from file"))?;
+
+    let content_type = if metadata.image.to_lowercase().ends_with(".png") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    };
+    Ok(HttpResponse::Ok().content_type(content_type).body(image))
+}
@@ -2,11 +2,13 @@ use std::path::{Path, PathBuf};
 
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
+use wabba_protocol::filename::normalize_filename;
 use wabba_protocol::wabbajack::WabbajackMetadata;
 
 use crate::db::{
     mod_association::{ModAssociation, ModAssociationEgg},
     mod_data::{Mod, ModEgg},
+    mod_disk_state::{ModDiskState, file_stat},
     modlist::{Modlist, ModlistEgg},
 };
 
@@ -16,10 +18,12 @@ pub fn ingest_mod(
     path: &Path,
     conn: &PooledConnection<SqliteConnectionManager>,
 ) -> Result<(), actix_web::Error> {
-    let size = std::fs::metadata(path).unwrap().len() as u64;
+    let (size, mtime_ns) = file_stat(path).map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Failed to stat {:?}: {}", path, e))
+    })?;
 
     // Check if file was in DB but unavailable - if so, mark as available; otherwise create new
-    match Mod::get_by_size_and_hash(size, hash, conn)
+    let mod_id = match Mod::get_by_size_and_hash(size, hash, conn)
         .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Database error: {}", e)))?
     {
         Some(stored_mod) => {
@@ -27,6 +31,7 @@ pub fn ingest_mod(
             stored_mod.set_disk_filename(filename, conn).map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
             })?;
+            stored_mod.id
         }
 
         None => {
@@ -37,23 +42,103 @@ pub fn ingest_mod(
                 size,
             };
 
-            mod_egg.create(conn).map_err(|e| {
+            mod_egg
+                .create(conn)
+                .map_err(|e| {
+                    actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
+                })?
+                .id
+        }
+    };
+
+    // The hash was just checked against this exact file, so its size and
+    // mtime become the baseline reconciliation compares against.
+    ModDiskState::record_ingest(mod_id, size, mtime_ns, conn).map_err(|e| {
+        actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
+    })?;
+
+    Ok(())
+}
+
+/// Stores a modlist whose metadata could not be parsed, so the file stays
+/// tracked and can be retried from the diagnostics page.
+fn record_parse_failure(
+    filename: &str,
+    hash: &str,
+    size: u64,
+    error: String,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Modlist, actix_web::Error> {
+    log::warn!(
+        "Failed to parse metadata of modlist {}: {}",
+        filename,
+        error
+    );
+
+    match Modlist::get_by_filename(filename, conn)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Database error: {}", e)))?
+    {
+        Some(existing) => {
+            let updated = Modlist {
+                xxhash64: hash.to_string(),
+                size,
+                available: true,
+                parse_error: Some(error),
+                ..existing
+            };
+            updated.update(conn).map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
             })?;
+            Ok(updated)
+        }
+        None => {
+            let name = filename
+                .strip_suffix(".wabbajack")
+                .unwrap_or(filename)
+                .to_string();
+            let modlist_egg = ModlistEgg {
+                filename: filename.to_string(),
+                name,
+                version: String::new(),
+                xxhash64: hash.to_string(),
+                size,
+                available: true,
+                has_unknown_downloaders: false,
+                game_type: None,
+                parse_error: Some(error),
+                author: None,
+            };
+            modlist_egg.create(conn).map_err(|e| {
+                actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
+            })
         }
     }
-
-    Ok(())
 }
 
+/// Records the modlist at `path` and the mods it requires. A file whose
+/// metadata can't be parsed is still recorded, with `parse_error` set.
 pub fn ingest_modlist(
     filename: &str,
     hash: &str,
     path: &PathBuf,
     conn: &PooledConnection<SqliteConnectionManager>,
-) -> Result<(), actix_web::Error> {
-    let size = std::fs::metadata(path).unwrap().len() as u64;
-    let metadata = WabbajackMetadata::load(path).expect("Failed to load Wabbajack metadata");
+) -> Result<Modlist, actix_web::Error> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| {
+            actix_web::error::ErrorInternalServerError(format!("Failed to stat {:?}: {}", path, e))
+        })?
+        .len() as u64;
+    let metadata = match WabbajackMetadata::load(path) {
+        Ok(metadata) => metadata,
+        Err(e) => return record_parse_failure(filename, hash, size, e.to_string(), conn),
+    };
+    let has_unknown_downloaders = !metadata.files_from_unknown_downloaders().is_empty();
+    if has_unknown_downloaders {
+        log::warn!(
+            "Modlist {} contains archives with unknown downloaders; availability numbers may be incomplete",
+            filename
+        );
+    }
 
     // Check if modlist already exists - update if needed, otherwise create new
     let modlist = match Modlist::get_by_filename(filename, conn)
@@ -71,6 +156,11 @@ pub fn ingest_modlist(
                 size,
                 available: true,
                 muted: existing.muted,
+                has_unknown_downloaders,
+                game_type: Some(metadata.game_type.clone()),
+                parse_error: None,
+                author: Some(metadata.author.clone()),
+                created_at: existing.created_at,
             };
             updated.update(conn).map_err(|e| {
                 actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
@@ -87,6 +177,10 @@ pub fn ingest_modlist(
                 xxhash64: hash.to_string(),
                 size,
                 available: true,
+                has_unknown_downloaders,
+                game_type: Some(metadata.game_type.clone()),
+                parse_error: None,
+                author: Some(metadata.author.clone()),
             };
 
             modlist_egg.create(conn).map_err(|e| {
@@ -131,9 +225,10 @@ pub fn ingest_modlist(
             Some(mut existing_assoc) => {
                 // Update existing association with latest metadata
                 existing_assoc.source = archive.state.clone();
-                existing_assoc.filename = archive.filename.clone();
+                existing_assoc.filename = normalize_filename(&archive.filename);
                 existing_assoc.name = archive.name();
                 existing_assoc.version = archive.version();
+                existing_assoc.meta = Some(archive.meta.clone());
                 existing_assoc.update(conn).map_err(|e| {
                     actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
                 })?;
@@ -144,9 +239,10 @@ pub fn ingest_modlist(
                     modlist_id: modlist.id,
                     mod_id: mod_to_associate.id,
                     source: archive.state.clone(),
-                    filename: archive.filename.clone(),
+                    filename: normalize_filename(&archive.filename),
                     name: archive.name(),
                     version: archive.version(),
+                    meta: Some(archive.meta.clone()),
                 };
 
                 // Create new association
@@ -158,5 +254,5 @@ pub fn ingest_modlist(
         }
     }
 
-    Ok(())
+    Ok(modlist)
 }
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use actix_web::{HttpResponse, get, web};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use wabba_protocol::api::{ModSummary, ModlistArchive, ModlistSummary, NEXT_CURSOR_HEADER};
+
+use crate::db::mod_association::ModAssociation;
+use crate::db::mod_data::{Mod, ModListingFilter};
+use crate::db::modlist::Modlist;
+use crate::db::modlist_counts::ModlistCounters;
+
+/// The most entries a page of `/api/modlists` or `/api/mods` holds, and
+/// how many it holds unless `limit` asks for fewer.
+const MAX_PAGE_SIZE: u64 = 1000;
+
+fn page_size(limit: Option<u64>) -> u64 {
+    limit.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// The cursor after an entry: its id, then its sort name in base64 so the
+/// cursor fits in a header whatever the name holds.
+fn encode_cursor(name: &str, id: u64) -> String {
+    format!("{}:{}", id, URL_SAFE_NO_PAD.encode(name))
+}
+
+fn decode_cursor(cursor: &str) -> Result<(String, u64), actix_web::Error> {
+    cursor
+        .split_once(':')
+        .and_then(|(id, name)| {
+            let name = String::from_utf8(URL_SAFE_NO_PAD.decode(name).ok()?).ok()?;
+            Some((name, id.parse().ok()?))
+        })
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("Invalid cursor"))
+}
+
+/// `items` with the next-page cursor header when there is a next page.
+fn paged<T: Serialize>(items: &T, next: Option<String>) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    if let Some(next) = next {
+        response.insert_header((NEXT_CURSOR_HEADER, next));
+    }
+    response.json(items)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Entries per page, at most 1000.
+    limit: Option<u64>,
+    /// Where to continue from: the `X-Next-Cursor` of the previous page.
+    after: Option<String>,
+}
+
+fn mod_summary(mod_item: &Mod, assoc: Option<&ModAssociation>) -> ModSummary {
+    ModSummary {
+        id: mod_item.id,
+        filename: assoc
+            .map(|assoc| assoc.filename.clone())
+            .or_else(|| mod_item.disk_filename.clone())
+            .unwrap_or_default(),
+        name: assoc.and_then(|assoc| assoc.name.clone()),
+        size: mod_item.size,
+        hash: mod_item.xxhash64.clone(),
+        available: mod_item.is_available(),
+        lost_forever: mod_item.lost_forever,
+    }
+}
+
+#[utoipa::path(
+    tag = "inventory",
+    params(PageQuery),
+    responses(
+        (status = 200, description = "A page of modlists, by name", body = [ModlistSummary],
+            headers(("X-Next-Cursor" = String, description = "`after` for the next page; absent on the last"))),
+        (status = 400, description = "Invalid cursor"),
+    )
+)]
+#[get("/api/modlists")]
+pub async fn list_modlists(
+    query: web::Query<PageQuery>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let after = query.after.as_deref().map(decode_cursor).transpose()?;
+    let limit = page_size(query.limit);
+    let mut modlists = Modlist::get_page(after, limit + 1, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let next = if modlists.len() > limit as usize {
+        modlists.truncate(limit as usize);
+        modlists
+            .last()
+            .map(|last| encode_cursor(&last.name, last.id))
+    } else {
+        None
+    };
+    let counters =
+        ModlistCounters::get_all(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let summaries: Vec<ModlistSummary> = modlists
+        .into_iter()
+        .map(|modlist| {
+            let counters = counters.get(&modlist.id).copied().unwrap_or_default();
+            ModlistSummary {
+                mods_total: counters.mods_total,
+                mods_available: counters.mods_available,
+                id: modlist.id,
+                name: modlist.name,
+                version: modlist.version,
+                filename: modlist.filename,
+                size: modlist.size,
+                hash: modlist.xxhash64,
+                available: modlist.available,
+                muted: modlist.muted,
+            }
+        })
+        .collect();
+    Ok(paged(&summaries, next))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ModsQuery {
+    /// Only mods the server doesn't hold. With `modlist`, optional archives
+    /// are left out.
+    #[serde(default)]
+    missing: bool,
+    /// Only the archives this modlist uses. These come in one response,
+    /// however many there are; `limit` and `after` don't apply.
+    modlist: Option<u64>,
+    /// Mods per page, at most 1000.
+    limit: Option<u64>,
+    /// Where to continue from: the `X-Next-Cursor` of the previous page.
+    after: Option<String>,
+}
+
+#[utoipa::path(
+    tag = "inventory",
+    params(ModsQuery),
+    responses(
+        (status = 200, description = "A page of matching mods, by filename", body = [ModSummary],
+            headers(("X-Next-Cursor" = String, description = "`after` for the next page; absent on the last"))),
+        (status = 400, description = "Invalid cursor"),
+        (status = 404, description = "Unknown modlist"),
+    )
+)]
+#[get("/api/mods")]
+pub async fn list_mods(
+    query: web::Query<ModsQuery>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let (summaries, next): (Vec<ModSummary>, Option<String>) = match query.modlist {
+        Some(modlist_id) => {
+            let modlist = Modlist::get_by_id(modlist_id, &conn)
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+            let associations: HashMap<u64, ModAssociation> = modlist
+                .get_mod_associations(&conn)
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .into_iter()
+                .map(|assoc| (assoc.mod_id, assoc))
+                .collect();
+            let summaries = Mod::get_by_modlist_id(modlist_id, &conn)
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .iter()
+                .filter(|mod_item| {
+                    !query.missing
+                        || (!mod_item.is_available()
+                            && !associations
+                                .get(&mod_item.id)
+                                .is_some_and(|assoc| assoc.optional))
+                })
+                .map(|mod_item| mod_summary(mod_item, associations.get(&mod_item.id)))
+                .collect();
+            (summaries, None)
+        }
+        None => {
+            let filter = ModListingFilter {
+                unavailable_only: query.missing,
+                ..Default::default()
+            };
+            let after = query.after.as_deref().map(decode_cursor).transpose()?;
+            let limit = page_size(query.limit);
+            let mut mods = Mod::get_all_for_listing(&filter, after, limit + 1, &conn)
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            let next = if mods.len() > limit as usize {
+                mods.truncate(limit as usize);
+                mods.last().map(|(last, _, _)| {
+                    encode_cursor(last.disk_filename.as_deref().unwrap_or(""), last.id)
+                })
+            } else {
+                None
+            };
+            let summaries = mods
+                .iter()
+                .map(|(mod_item, _, assoc)| mod_summary(mod_item, assoc.as_ref()))
+                .collect();
+            (summaries, next)
+        }
+    };
+    Ok(paged(&summaries, next))
+}
+
+#[utoipa::path(
+    tag = "inventory",
+    params(("id" = u64, Path, description = "Modlist id")),
+    responses(
+        (status = 200, description = "Every archive the modlist lists, by filename", body = [ModlistArchive]),
+        (status = 404, description = "Unknown modlist"),
+    )
+)]
+#[get("/api/modlists/{id}/archives")]
+pub async fn modlist_archives(
+    path: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let modlist_id = path.into_inner();
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let modlist = Modlist::get_by_id(modlist_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+    let mods: HashMap<u64, Mod> = Mod::get_by_modlist_id(modlist.id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|mod_item| (mod_item.id, mod_item))
+        .collect();
+    let archives: Vec<ModlistArchive> = modlist
+        .get_mod_associations(&conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .filter_map(|assoc| {
+            let mod_item = mods.get(&assoc.mod_id)?;
+            Some(ModlistArchive {
+                filename: assoc.filename,
+                size: mod_item.size,
+                hash: mod_item.xxhash64.clone(),
+                state: assoc.source,
+                meta: assoc.meta,
+                optional: assoc.optional,
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(archives))
+}
+
+#[utoipa::path(
+    tag = "inventory",
+    params(
+        ("id" = u64, Path, description = "Modlist id"),
+        ("mod_id" = u64, Path, description = "Mod id"),
+    ),
+    responses(
+        (status = 200, description = "The `.meta` file Wabbajack expects next to this archive", body = String, content_type = "text/plain"),
+        (status = 404, description = "The modlist doesn't use this mod, or its source has no `.meta`"),
+    )
+)]
+#[get("/api/modlists/{id}/mods/{mod_id}/meta")]
+pub async fn mod_meta(
+    path: web::Path<(u64, u64)>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (modlist_id, mod_id) = path.into_inner();
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let association = ModAssociation::get_by_modlist_and_mod(modlist_id, mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("The modlist doesn't use this mod"))?;
+    let meta = association
+        .meta_ini()
+        .ok_or_else(|| actix_web::error::ErrorNotFound("This source has no .meta file"))?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(meta))
+}
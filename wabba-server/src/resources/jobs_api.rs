@@ -0,0 +1,75 @@
+use actix_web::{HttpResponse, get, web};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::jobs::{JobInfo, JobRegistry, JobStatus};
+
+/// A background job as reported by the JSON API.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobSummary {
+    pub id: u64,
+    /// Human-readable job type, as shown on the jobs page.
+    pub kind: String,
+    /// One of Queued, Running, Awaiting confirmation, Succeeded, Failed or
+    /// Cancelled.
+    pub status: String,
+    /// Why the job failed, when it did.
+    pub failure: Option<String>,
+    pub progress: Option<String>,
+    /// Number of per-item errors the job recorded while carrying on.
+    pub error_count: usize,
+    pub dry_run: bool,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<&JobInfo> for JobSummary {
+    fn from(info: &JobInfo) -> Self {
+        JobSummary {
+            id: info.id,
+            kind: info.kind.label().to_string(),
+            status: info.status.label().to_string(),
+            failure: match &info.status {
+                JobStatus::Failed(error) => Some(error.clone()),
+                _ => None,
+            },
+            progress: info.progress.clone(),
+            error_count: info.errors.len(),
+            dry_run: info.dry_run,
+            created_at: info.created_at,
+            started_at: info.started_at,
+            finished_at: info.finished_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    responses((status = 200, description = "Jobs since the server started, newest first", body = [JobSummary]))
+)]
+#[get("/api/jobs")]
+pub async fn list_jobs(jobs: web::Data<JobRegistry>) -> HttpResponse {
+    let summaries: Vec<JobSummary> = jobs.list().iter().map(JobSummary::from).collect();
+    HttpResponse::Ok().json(summaries)
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    params(("id" = u64, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "The job", body = JobSummary),
+        (status = 404, description = "Unknown job"),
+    )
+)]
+#[get("/api/jobs/{id}")]
+pub async fn get_job(
+    id: web::Path<u64>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let info = jobs
+        .get(id.into_inner())
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Job not found"))?;
+    Ok(HttpResponse::Ok().json(JobSummary::from(&info)))
+}
@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use regex::Regex;
+use serde::Deserialize;
+use wabba_protocol::archive_state::ArchiveState;
+
+use crate::{
+    config::MetadataRefreshConfig,
+    credentials::{CredentialVault, LOVERSLAB_ACCESS_TOKEN, LOVERSLAB_REFRESH_TOKEN},
+    db::{mod_association::ModAssociation, mod_image_cache::CachedModImage},
+    jobs::{JobContext, JobFn, JobKind, JobRegistry},
+};
+
+/// LoversLab's Invision Community REST API.
+const LOVERSLAB_API_URL: &str = "https://www.loverslab.com/api";
+const LOVERSLAB_TOKEN_URL: &str = "https://www.loverslab.com/oauth/token/";
+
+/// The parts of an Invision `downloads/files/{id}` response we keep.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IpsFile {
+    title: String,
+    version: Option<String>,
+    description: Option<String>,
+    author: Option<IpsMember>,
+    primary_screenshot: Option<IpsImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IpsMember {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IpsImage {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+enum FetchError {
+    /// The tokens are no good; nothing else in this run will work either.
+    Unauthorized(String),
+    Other(String),
+}
+
+/// Fetches an image, returning its content type and bytes.
+pub async fn fetch_image(client: &reqwest::Client, url: &str) -> Result<(String, Vec<u8>), String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch image from {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Image server responded with {} for {}",
+            response.status(),
+            url
+        ));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "image/jpeg".to_string());
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read image from {}: {}", url, e))?;
+    Ok((content_type, bytes.to_vec()))
+}
+
+/// Invision descriptions are HTML; associations carry plain text.
+fn strip_html(html: &str) -> String {
+    let text = Regex::new(r"<[^>]*>")
+        .map(|tags| tags.replace_all(html, " ").into_owned())
+        .unwrap_or_else(|_| html.to_string());
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+struct LoversLabClient<'a> {
+    client: reqwest::Client,
+    vault: &'a CredentialVault,
+    client_id: Option<&'a str>,
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+impl LoversLabClient<'_> {
+    async fn get_file(
+        &mut self,
+        file_id: u64,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<IpsFile, FetchError> {
+        let mut refreshed = false;
+        loop {
+            let response = self
+                .client
+                .get(format!("{}/downloads/files/{}", LOVERSLAB_API_URL, file_id))
+                .bearer_auth(&self.access_token)
+                .send()
+                .await
+                .map_err(|e| FetchError::Other(format!("LoversLab API request failed: {}", e)))?;
+            match response.status().as_u16() {
+                200 => {
+                    return response.json().await.map_err(|e| {
+                        FetchError::Other(format!("Unexpected LoversLab API response: {}", e))
+                    });
+                }
+                401 if !refreshed => {
+                    self.refresh(conn).await.map_err(FetchError::Unauthorized)?;
+                    refreshed = true;
+                }
+                401 => {
+                    return Err(FetchError::Unauthorized(
+                        "LoversLab rejected the refreshed access token".to_string(),
+                    ));
+                }
+                404 => return Err(FetchError::Other("File no longer exists".to_string())),
+                status => {
+                    return Err(FetchError::Other(format!(
+                        "LoversLab API responded with {}",
+                        status
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Trades the refresh token for a new access token and stores both.
+    async fn refresh(
+        &mut self,
+        conn: &PooledConnection<SqliteConnectionManager>,
+    ) -> Result<(), String> {
+        let refresh_token = self.refresh_token.clone().ok_or_else(|| {
+            "LoversLab access token expired and no refresh token is set".to_string()
+        })?;
+        let client_id = self.client_id.ok_or_else(|| {
+            "LoversLab access token expired; set LOVERSLAB_OAUTH_CLIENT_ID to renew it".to_string()
+        })?;
+        let response = self
+            .client
+            .post(LOVERSLAB_TOKEN_URL)
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token.as_str()),
+                ("client_id", client_id),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("LoversLab token request failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "LoversLab refused to refresh the access token ({})",
+                response.status()
+            ));
+        }
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Unexpected LoversLab token response: {}", e))?;
+
+        self.vault
+            .set(LOVERSLAB_ACCESS_TOKEN, &tokens.access_token, conn)?;
+        if let Some(refresh_token) = &tokens.refresh_token {
+            self.vault
+                .set(LOVERSLAB_REFRESH_TOKEN, refresh_token, conn)?;
+        }
+        log::info!("Refreshed LoversLab access token");
+        self.access_token = tokens.access_token;
+        self.refresh_token = tokens.refresh_token.or(Some(refresh_token));
+        Ok(())
+    }
+}
+
+/// `source` with its metadata replaced by what LoversLab currently says.
+fn with_metadata(source: &ArchiveState, file: &IpsFile) -> ArchiveState {
+    let mut source = source.clone();
+    if let ArchiveState::LoversLabOAuthDownloader {
+        author,
+        description,
+        image_url,
+        name,
+        version,
+        ..
+    } = &mut source
+    {
+        *name = Some(file.title.clone());
+        if file.version.is_some() {
+            version.clone_from(&file.version);
+        }
+        if let Some(text) = file.description.as_deref().map(strip_html) {
+            *description = Some(text);
+        }
+        if let Some(member) = &file.author {
+            *author = Some(member.name.clone());
+        }
+        if let Some(screenshot) = &file.primary_screenshot {
+            *image_url = Some(screenshot.url.clone());
+        }
+    }
+    source
+}
+
+fn refresh_metadata_impl(
+    pool: &Pool<SqliteConnectionManager>,
+    vault: &CredentialVault,
+    config: &MetadataRefreshConfig,
+    job: &JobContext,
+) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let access_token = vault
+        .get(LOVERSLAB_ACCESS_TOKEN, &conn)?
+        .ok_or_else(|| "LoversLab access token not configured".to_string())?;
+    let mut client = LoversLabClient {
+        client: reqwest::Client::new(),
+        vault,
+        client_id: config.loverslab_client_id.as_deref(),
+        access_token,
+        refresh_token: vault.get_or_warn(LOVERSLAB_REFRESH_TOKEN, &conn),
+    };
+
+    // The same LoversLab file usually appears in several modlists; ask
+    // about each one once.
+    let mut by_file: BTreeMap<u64, Vec<ModAssociation>> = BTreeMap::new();
+    for association in ModAssociation::get_by_source_type("loverslab", &conn)
+        .map_err(|e| format!("Database error: {}", e))?
+    {
+        if let ArchiveState::LoversLabOAuthDownloader { ips4_mod, .. } = &association.source {
+            by_file.entry(*ips4_mod).or_default().push(association);
+        }
+    }
+    log::info!("Refreshing metadata for {} LoversLab files", by_file.len());
+
+    let runtime = tokio::runtime::Handle::current();
+    let total = by_file.len();
+    let mut updated = 0;
+    for (index, (file_id, associations)) in by_file.iter().enumerate() {
+        if job.is_cancelled() {
+            return Ok(());
+        }
+        job.set_progress(format!(
+            "{}/{}: LoversLab file {}",
+            index + 1,
+            total,
+            file_id
+        ));
+
+        let file = match runtime.block_on(client.get_file(*file_id, &conn)) {
+            Ok(file) => file,
+            Err(FetchError::Unauthorized(message)) => return Err(message),
+            Err(FetchError::Other(message)) => {
+                job.record_error(format!("LoversLab file {}", file_id), message);
+                continue;
+            }
+        };
+
+        for association in associations {
+            if association
+                .update_source(&with_metadata(&association.source, &file), &conn)
+                .map_err(|e| format!("Database error: {}", e))?
+            {
+                updated += 1;
+            }
+        }
+
+        let Some(image_url) = file.primary_screenshot.as_ref().map(|s| s.url.as_str()) else {
+            continue;
+        };
+        let mut mod_ids: Vec<u64> = associations.iter().map(|a| a.mod_id).collect();
+        mod_ids.dedup();
+        let mut stale = Vec::new();
+        for mod_id in mod_ids {
+            let cached = CachedModImage::get_by_mod_id(mod_id, &conn)
+                .map_err(|e| format!("Database error: {}", e))?;
+            if cached.is_none_or(|image| image.image_url != image_url) {
+                stale.push(mod_id);
+            }
+        }
+        if stale.is_empty() {
+            continue;
+        }
+        match runtime.block_on(fetch_image(&client.client, image_url)) {
+            Ok((content_type, data)) => {
+                for mod_id in stale {
+                    CachedModImage::store(mod_id, image_url, &content_type, &data, &conn)
+                        .map_err(|e| format!("Database error: {}", e))?;
+                }
+            }
+            Err(e) => job.record_error(format!("LoversLab file {}", file_id), e),
+        }
+    }
+
+    log::info!(
+        "LoversLab metadata refresh complete: {} associations updated",
+        updated
+    );
+    Ok(())
+}
+
+fn refresh_metadata_job(
+    pool: Pool<SqliteConnectionManager>,
+    vault: CredentialVault,
+    config: MetadataRefreshConfig,
+) -> JobFn {
+    Arc::new(move |job: &JobContext| refresh_metadata_impl(&pool, &vault, &config, job))
+}
+
+/// Submits a metadata refresh every `config.interval`, if one is set and
+/// the vault can hold the LoversLab tokens.
+pub fn spawn_metadata_refresh_schedule(
+    pool: Pool<SqliteConnectionManager>,
+    jobs: JobRegistry,
+    vault: CredentialVault,
+    config: MetadataRefreshConfig,
+) {
+    let Some(period) = config.interval else {
+        log::info!("Scheduled metadata refresh is off");
+        return;
+    };
+    if !vault.is_unlocked() {
+        log::info!("Scheduled metadata refresh is off: CREDENTIALS_KEY is not set");
+        return;
+    }
+    log::info!(
+        "Scheduling LoversLab metadata refresh every {} hours",
+        period.as_secs() / 3600
+    );
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick fires immediately; wait a full interval after
+        // startup instead.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            jobs.submit(
+                JobKind::MetadataRefresh,
+                refresh_metadata_job(pool.clone(), vault.clone(), config.clone()),
+            );
+        }
+    });
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    responses((status = 303, description = "Queued a LoversLab metadata refresh; redirects to the jobs page"))
+)]
+#[post("/metadata/refresh")]
+pub async fn refresh_metadata(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    vault: web::Data<CredentialVault>,
+    config: web::Data<MetadataRefreshConfig>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    jobs.submit(
+        JobKind::MetadataRefresh,
+        refresh_metadata_job(
+            pool.get_ref().clone(),
+            vault.get_ref().clone(),
+            config.get_ref().clone(),
+        ),
+    );
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
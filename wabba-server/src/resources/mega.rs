@@ -0,0 +1,32 @@
+use wabba_protocol::mega::{MEGA_API_URL, download_url_from_response};
+
+pub use wabba_protocol::mega::{MegaCipher, MegaLink};
+
+/// Asks MEGA for a temporary download URL for `link`. With a `session` the
+/// download counts against that account's quota instead of the anonymous
+/// per-IP one.
+pub async fn download_url(
+    link: &MegaLink,
+    client: &reqwest::Client,
+    session: Option<&str>,
+) -> Result<String, String> {
+    let mut query = vec![("id", "0")];
+    if let Some(session) = session {
+        query.push(("sid", session));
+    }
+    let response = client
+        .post(MEGA_API_URL)
+        .query(&query)
+        .json(&link.download_request())
+        .send()
+        .await
+        .map_err(|e| format!("MEGA API request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("MEGA API responded with {}", response.status()));
+    }
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected MEGA API response: {}", e))?;
+    download_url_from_response(&body)
+}
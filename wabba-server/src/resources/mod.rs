@@ -1,12 +1,31 @@
+pub mod backup;
 pub mod bootstrap;
+pub mod digest;
+pub mod downloader;
+pub mod downloads_import;
+pub mod filename_policy;
+pub mod gallery;
 pub mod ingest;
+pub mod inventory;
+pub mod jobs_api;
+pub mod loverslab;
+pub mod mega;
+pub mod offload;
+pub mod reconciliation;
+pub mod reparse;
+pub mod resumable_upload;
+pub mod share_bundle;
+pub mod storage_migration;
 pub mod upload_validation;
+pub mod verification;
+pub mod wayback;
 
 use actix_web::HttpRequest;
 use std::path::{Path, PathBuf};
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
 use tokio::io::BufWriter;
+use wabba_protocol::api::{ArchiveKind, AvailabilityReport, HashCheckRequest, UploadResult};
 use wabba_protocol::hash::Hash;
 
 use actix_web::{HttpResponse, Responder, get, post, web};
@@ -18,6 +37,7 @@ use r2d2_sqlite::SqliteConnectionManager;
 use crate::data_dir::DataDir;
 use crate::db::mod_data::Mod;
 use crate::db::modlist::Modlist;
+use crate::resources::filename_policy::sanitize_filename;
 use crate::resources::ingest::{ingest_mod, ingest_modlist};
 use crate::resources::upload_validation::{
     ArchiveType, UploadValidationResult, validate_upload_request,
@@ -133,6 +153,10 @@ async fn stream_upload_to_temp_file(
     Ok((temp_path, total_written))
 }
 
+#[utoipa::path(
+    tag = "lookups",
+    responses((status = 200, description = "The server is up", content_type = "text/html"))
+)]
 #[get("/hello")]
 pub async fn hello_world() -> impl Responder {
     html! {
@@ -167,6 +191,15 @@ fn check_hash<A: ArchiveType>(
     }
 }
 
+#[utoipa::path(
+    tag = "lookups",
+    params(("If-None-Match" = String, Header, description = "xxHash64 of the modlist, base64")),
+    responses(
+        (status = 200, description = "The server does not have this modlist; upload it"),
+        (status = 304, description = "The server already has this modlist"),
+        (status = 400, description = "No hash given"),
+    )
+)]
 #[get("/check/modlist")]
 pub async fn check_modlist(
     req: HttpRequest,
@@ -178,6 +211,15 @@ pub async fn check_modlist(
     check_hash::<Modlist>(&req, &conn)
 }
 
+#[utoipa::path(
+    tag = "lookups",
+    params(("If-None-Match" = String, Header, description = "xxHash64 of the mod, base64")),
+    responses(
+        (status = 200, description = "The server does not have this mod; upload it"),
+        (status = 304, description = "The server already has this mod"),
+        (status = 400, description = "No hash given"),
+    )
+)]
 #[get("/check/mod")]
 pub async fn check_mod(
     req: HttpRequest,
@@ -189,6 +231,72 @@ pub async fn check_mod(
     check_hash::<Mod>(&req, &conn)
 }
 
+/// Whether a file with `hash` is stored, and has `size` bytes if given.
+fn is_hash_available<A: ArchiveType>(
+    hash: &str,
+    size: Option<u64>,
+    conn: &r2d2::PooledConnection<SqliteConnectionManager>,
+) -> Result<bool, rusqlite::Error> {
+    Ok(A::get_by_hash(hash, conn)?.is_some_and(|archive| {
+        archive.is_available() && size.is_none_or(|size| archive.size() == size)
+    }))
+}
+
+#[utoipa::path(
+    tag = "lookups",
+    request_body = HashCheckRequest,
+    responses(
+        (status = 200, description = "Which of the hashes the server already has", body = AvailabilityReport),
+        (status = 400, description = "`sizes` is given but not one per hash")
+    )
+)]
+#[post("/check")]
+pub async fn check_hashes(
+    request: web::Json<HashCheckRequest>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let request = request.into_inner();
+    if !request.sizes.is_empty() && request.sizes.len() != request.hashes.len() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "sizes must have one entry per hash",
+        ));
+    }
+
+    let mut report = AvailabilityReport::default();
+    for (idx, hash) in request.hashes.into_iter().enumerate() {
+        let size = request.sizes.get(idx).copied();
+        let available = match request.kind {
+            ArchiveKind::Modlist => is_hash_available::<Modlist>(&hash, size, &conn),
+            ArchiveKind::Mod => is_hash_available::<Mod>(&hash, size, &conn),
+        }
+        .map_err(|e| {
+            actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
+        })?;
+        if available {
+            report.available.push(hash);
+        } else {
+            report.missing.push(hash);
+        }
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[utoipa::path(
+    tag = "uploads",
+    params(
+        ("filename" = String, Path, description = "Name to store the modlist under"),
+        ("If-None-Match" = String, Header, description = "xxHash64 of the body, base64"),
+    ),
+    request_body(content = [u8], content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Stored and ingested", body = UploadResult),
+        (status = 304, description = "The server already has this modlist; the body was not read"),
+        (status = 400, description = "Missing hash, unusable filename, or the body did not match it"),
+    )
+)]
 #[post("/submit/modlist/{filename}")]
 pub async fn upload_modlist(
     filename: web::Path<String>,
@@ -198,7 +306,8 @@ pub async fn upload_modlist(
     body: web::Payload,
 ) -> Result<HttpResponse, actix_web::Error> {
     let conn = pool.into_inner().get().unwrap();
-    let requested_filename = filename.into_inner();
+    let requested_filename =
+        sanitize_filename(&filename.into_inner()).map_err(actix_web::error::ErrorBadRequest)?;
     let data_dir = data_dir.into_inner();
 
     log::info!("Request to upload modlist file {}", requested_filename);
@@ -231,7 +340,7 @@ pub async fn upload_modlist(
 
     // Upload to temporary file
     let modlist_dir = data_dir.get_modlist_dir();
-    let (temp_path, _size) = stream_upload_to_temp_file(&modlist_dir, body).await?;
+    let (temp_path, size) = stream_upload_to_temp_file(&modlist_dir, body).await?;
 
     // Compute hash from uploaded file
     let computed_hash = Hash::compute(&std::fs::read(&temp_path).map_err(|e| {
@@ -270,9 +379,27 @@ pub async fn upload_modlist(
         actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
     })?;
 
-    Ok(HttpResponse::Ok().body("ok"))
+    Ok(HttpResponse::Ok().json(UploadResult {
+        kind: ArchiveKind::Modlist,
+        filename: final_filename,
+        hash: if_none_match.to_string(),
+        size: size as u64,
+    }))
 }
 
+#[utoipa::path(
+    tag = "uploads",
+    params(
+        ("filename" = String, Path, description = "Name to store the mod under"),
+        ("If-None-Match" = String, Header, description = "xxHash64 of the body, base64"),
+    ),
+    request_body(content = [u8], content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Stored and ingested", body = UploadResult),
+        (status = 304, description = "The server already has this mod; the body was not read"),
+        (status = 400, description = "Missing hash, unusable filename, or the body did not match it"),
+    )
+)]
 #[post("/submit/mod/{filename}")]
 pub async fn upload_mod(
     filename: web::Path<String>,
@@ -282,7 +409,8 @@ pub async fn upload_mod(
     body: web::Payload,
 ) -> Result<HttpResponse, actix_web::Error> {
     let conn = pool.into_inner().get().unwrap();
-    let requested_filename = filename.into_inner();
+    let requested_filename =
+        sanitize_filename(&filename.into_inner()).map_err(actix_web::error::ErrorBadRequest)?;
     let data_dir = data_dir.into_inner();
 
     log::info!("Request to upload mod file {}", requested_filename);
@@ -316,7 +444,7 @@ pub async fn upload_mod(
 
     // Upload to temporary file
     let downloads_dir = data_dir.get_mod_dir();
-    let (temp_path, _size) = stream_upload_to_temp_file(&downloads_dir, body).await?;
+    let (temp_path, size) = stream_upload_to_temp_file(&downloads_dir, body).await?;
 
     // Compute hash from uploaded file
     let computed_hash = Hash::compute(&std::fs::read(&temp_path).map_err(|e| {
@@ -355,5 +483,10 @@ pub async fn upload_mod(
         actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
     })?;
 
-    Ok(HttpResponse::Ok().body("ok"))
+    Ok(HttpResponse::Ok().json(UploadResult {
+        kind: ArchiveKind::Mod,
+        filename: final_filename,
+        hash: if_none_match.to_string(),
+        size: size as u64,
+    }))
 }
@@ -0,0 +1,376 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web, web::Bytes};
+use futures_util::StreamExt;
+use futures_util::stream::LocalBoxStream;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use tokio::io::AsyncWriteExt;
+use wabba_protocol::hash::Hash;
+
+use crate::{
+    bandwidth::BandwidthLimiter,
+    cold_storage::{ColdStorage, ColdStorageStream},
+    data_dir::DataDir,
+    db::{
+        mod_data::Mod,
+        mod_disk_state::{ModDiskState, file_stat},
+    },
+    jobs::{ExecuteFn, JobContext, JobKind, JobRegistry, PlanFn, PlannedAction},
+};
+
+/// The dry run of an offload: the one local copy it would remove, and
+/// whether it still has to be uploaded first.
+fn plan_offload(
+    mod_id: u64,
+    pool: &Pool<SqliteConnectionManager>,
+    cold_storage: &ColdStorage,
+    job: &JobContext,
+) -> Result<Vec<PlannedAction>, String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let mod_item = Mod::get_by_id(mod_id, &conn)
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Mod {} not found", mod_id))?;
+    let disk_filename = mod_item
+        .disk_filename
+        .ok_or_else(|| "Mod is not available on disk".to_string())?;
+    let state =
+        ModDiskState::get_by_mod_id(mod_id, &conn).map_err(|e| format!("Database error: {}", e))?;
+    if state.as_ref().is_some_and(|s| s.evicted) {
+        return Err("Mod is already in cold storage".to_string());
+    }
+    let detail = match state.and_then(|s| s.cold_storage_ref) {
+        Some(_) => "already uploaded; delete the local copy".to_string(),
+        None => format!(
+            "upload {} bytes to {}, then delete the local copy",
+            mod_item.size,
+            cold_storage.label()
+        ),
+    };
+    job.log(format!("{} would be offloaded: {}", disk_filename, detail));
+    Ok(vec![PlannedAction {
+        action: "offload".to_string(),
+        target: disk_filename,
+        detail: Some(detail),
+    }])
+}
+
+fn offload_mod_impl(
+    mod_id: u64,
+    pool: &Pool<SqliteConnectionManager>,
+    data_dir: &DataDir,
+    cold_storage: &ColdStorage,
+    limiter: &BandwidthLimiter,
+    job: &JobContext,
+) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let mod_item = Mod::get_by_id(mod_id, &conn)
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Mod {} not found", mod_id))?;
+    let disk_filename = mod_item
+        .disk_filename
+        .as_ref()
+        .ok_or_else(|| "Mod is not available on disk".to_string())?;
+    let state =
+        ModDiskState::get_by_mod_id(mod_id, &conn).map_err(|e| format!("Database error: {}", e))?;
+    if state.as_ref().is_some_and(|s| s.evicted) {
+        return Err("Mod is already in cold storage".to_string());
+    }
+
+    // Don't push a corrupted copy somewhere we'll only read it back from.
+    let path = data_dir.get_mod_path(disk_filename);
+    job.set_progress(format!("Hashing {}", disk_filename));
+    let hash = Hash::compute_file(&path).map_err(|e| format!("Failed to hash file: {}", e))?;
+    if hash != mod_item.xxhash64 {
+        return Err(format!(
+            "Hash mismatch: expected {}, found {}; not offloading",
+            mod_item.xxhash64, hash
+        ));
+    }
+    if job.is_cancelled() {
+        return Ok(());
+    }
+
+    let reference = match state.and_then(|s| s.cold_storage_ref) {
+        // Fetched back earlier and unchanged since: the copy in cold
+        // storage is still good.
+        Some(reference) => reference,
+        None => {
+            job.set_progress(format!(
+                "Uploading {} to {}",
+                disk_filename,
+                cold_storage.label()
+            ));
+            tokio::runtime::Handle::current().block_on(cold_storage.store(&path, limiter))?
+        }
+    };
+    log::info!(
+        "Offloaded {} to cold storage as {}",
+        disk_filename,
+        reference
+    );
+
+    // Keep the reference even if the local copy can't be removed, but only
+    // call the mod evicted once it's gone.
+    ModDiskState::record_cold_copy(mod_id, &reference, &conn)
+        .map_err(|e| format!("Database error: {}", e))?;
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to remove local copy: {}", e))?;
+    ModDiskState::record_eviction(mod_id, &reference, &conn)
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(())
+}
+
+#[post("/mod/{id}/offload")]
+pub async fn offload_mod(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    cold_storage: web::Data<ColdStorage>,
+    limiter: web::Data<BandwidthLimiter>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !cold_storage.is_enabled() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Cold storage is not configured",
+        ));
+    }
+    let mod_id = id.into_inner();
+    let pool = pool.get_ref().clone();
+    let data_dir = data_dir.get_ref().clone();
+    let cold_storage = cold_storage.get_ref().clone();
+    let limiter = limiter.get_ref().clone();
+    let (plan_pool, plan_cold_storage) = (pool.clone(), cold_storage.clone());
+    let plan: PlanFn =
+        Arc::new(move |job: &JobContext| plan_offload(mod_id, &plan_pool, &plan_cold_storage, job));
+    let execute: ExecuteFn = Arc::new(move |job: &JobContext, plan: &[PlannedAction]| {
+        for action in plan {
+            // Renamed or removed since the dry run: not what was confirmed.
+            let conn = pool.get().map_err(|e| e.to_string())?;
+            let current = Mod::get_by_id(mod_id, &conn)
+                .map_err(|e| format!("Database error: {}", e))?
+                .and_then(|mod_item| mod_item.disk_filename);
+            if current.as_deref() != Some(action.target.as_str()) {
+                job.record_error(&action.target, "Changed since the dry run; skipped");
+                continue;
+            }
+            job.log(format!("Offloading {}", action.target));
+            offload_mod_impl(mod_id, &pool, &data_dir, &cold_storage, &limiter, job)?;
+            job.log(format!("Removed the local copy of {}", action.target));
+        }
+        Ok(())
+    });
+    let id = jobs.submit_dry_run(JobKind::Offload, plan, execute, false);
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/admin/jobs/{}", id)))
+        .finish())
+}
+
+/// Removes the partially written cache file unless it was moved into place.
+struct PendingCacheFile {
+    path: PathBuf,
+    file: Option<tokio::fs::File>,
+}
+
+impl Drop for PendingCacheFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// State carried through a cold storage download.
+struct CacheTee {
+    upstream: ColdStorageStream,
+    pending: PendingCacheFile,
+    mod_item: Mod,
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    cold_storage: ColdStorage,
+}
+
+/// Streams an evicted mod from cold storage straight to the client while
+/// writing a copy next to where it belongs in Downloads. Once the transfer
+/// completes the copy is hash-checked and moved into place, so the next
+/// download is served locally.
+pub async fn stream_from_cold_storage(
+    mod_item: Mod,
+    reference: &str,
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    cold_storage: ColdStorage,
+) -> Result<LocalBoxStream<'static, Result<Bytes, actix_web::Error>>, actix_web::Error> {
+    let disk_filename = mod_item
+        .disk_filename
+        .clone()
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Mod is not available on disk"))?;
+    let path = data_dir.get_mod_path(&disk_filename);
+    let dir = path
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_else(|| data_dir.get_mod_dir());
+
+    log::info!("Streaming {} from cold storage", disk_filename);
+    let upstream = cold_storage
+        .open(reference)
+        .await
+        .map_err(actix_web::error::ErrorBadGateway)?;
+
+    // Named like an upload temp file so a bootstrap running meanwhile
+    // skips it. A failure here only costs us the cached copy.
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let temp_path = dir.join(format!("upload_{}.tmp", timestamp));
+    let file = match std::fs::create_dir_all(&dir) {
+        Ok(()) => tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .await
+            .ok(),
+        Err(_) => None,
+    };
+    if file.is_none() {
+        log::warn!(
+            "Could not create {:?}; serving {} without caching it",
+            temp_path,
+            disk_filename
+        );
+    }
+    let pending = PendingCacheFile {
+        path: temp_path,
+        file,
+    };
+
+    let tee = CacheTee {
+        upstream,
+        pending,
+        mod_item,
+        pool,
+        data_dir,
+        cold_storage,
+    };
+    Ok(futures_util::stream::unfold(Some(tee), |tee| async move {
+        let mut tee = tee?;
+        match tee.upstream.next().await {
+            Some(Ok(chunk)) => {
+                if let Some(file) = tee.pending.file.as_mut()
+                    && let Err(e) = file.write_all(&chunk).await
+                {
+                    log::warn!("Stopped caching {:?}: {}", tee.pending.path, e);
+                    tee.pending.file = None;
+                }
+                Some((Ok(chunk), Some(tee)))
+            }
+            Some(Err(e)) => Some((
+                Err(actix_web::error::ErrorBadGateway(format!(
+                    "Cold storage transfer failed: {}",
+                    e
+                ))),
+                None,
+            )),
+            None => {
+                if let Some(mut file) = tee.pending.file.take()
+                    && file.flush().await.is_ok()
+                {
+                    drop(file);
+                    // The client already has everything; finish caching
+                    // without holding up the response.
+                    actix_web::rt::spawn(async move {
+                        let mod_id = tee.mod_item.id;
+                        if let Err(e) = install_cached_copy(tee).await {
+                            log::warn!("Failed to cache mod {}: {}", mod_id, e);
+                        }
+                    });
+                }
+                None
+            }
+        }
+    })
+    .boxed_local())
+}
+
+/// Hash-checks a copy fetched from cold storage, moves it into place and
+/// then trims the cache back under its limit.
+async fn install_cached_copy(tee: CacheTee) -> Result<(), String> {
+    let CacheTee {
+        pending,
+        mod_item,
+        pool,
+        data_dir,
+        cold_storage,
+        ..
+    } = tee;
+    let disk_filename = mod_item
+        .disk_filename
+        .as_ref()
+        .ok_or_else(|| "Mod is no longer available".to_string())?;
+    let hash_path = pending.path.clone();
+    let hash = web::block(move || Hash::compute_file(&hash_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to hash file: {}", e))?;
+    if hash != mod_item.xxhash64 {
+        return Err(format!(
+            "Cold storage returned the wrong file: expected {}, found {}",
+            mod_item.xxhash64, hash
+        ));
+    }
+
+    let path = data_dir.get_mod_path(disk_filename);
+    std::fs::rename(&pending.path, &path)
+        .map_err(|e| format!("Failed to move file to final location: {}", e))?;
+    let (size, mtime_ns) = file_stat(&path).map_err(|e| e.to_string())?;
+
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    ModDiskState::record_restore(mod_item.id, size, mtime_ns, &conn)
+        .map_err(|e| format!("Database error: {}", e))?;
+    log::info!("Cached {} from cold storage", disk_filename);
+
+    if let Some(limit) = cold_storage.cache_limit_bytes {
+        evict_least_recently_used(limit, mod_item.id, &conn, &data_dir)
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Drops cached copies, least recently downloaded first, until the ones
+/// left fit in `limit` bytes. `keep` (the copy just fetched) is never
+/// evicted.
+fn evict_least_recently_used(
+    limit: u64,
+    keep: u64,
+    conn: &PooledConnection<SqliteConnectionManager>,
+    data_dir: &DataDir,
+) -> Result<(), rusqlite::Error> {
+    let cached = ModDiskState::get_cached(conn)?;
+    let mut total: u64 = cached.iter().map(|(mod_item, _)| mod_item.size).sum();
+    for (mod_item, state) in cached {
+        if total <= limit {
+            break;
+        }
+        if mod_item.id == keep {
+            continue;
+        }
+        let (Some(disk_filename), Some(reference)) =
+            (&mod_item.disk_filename, &state.cold_storage_ref)
+        else {
+            continue;
+        };
+        let path = data_dir.get_mod_path(disk_filename);
+        if let Err(e) = std::fs::remove_file(&path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            log::warn!("Failed to evict cached copy {:?}: {}", path, e);
+            continue;
+        }
+        ModDiskState::record_eviction(mod_item.id, reference, conn)?;
+        log::info!("Evicted cached copy of {}", disk_filename);
+        total = total.saturating_sub(mod_item.size);
+    }
+
+    Ok(())
+}
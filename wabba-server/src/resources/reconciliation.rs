@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::{
+    data_dir::DataDir,
+    db::mod_disk_state::{ModDiskState, file_stat},
+    jobs::{JobContext, JobKind, JobRegistry},
+};
+
+/// Compares every available mod's file against the size and mtime recorded
+/// when it was last hashed. Anything that changed behind our back (another
+/// tool overwriting a file in the shared Downloads folder, say) is flagged
+/// as needing a re-hash; the next verification run picks those up first.
+fn reconcile_impl(
+    pool: &Pool<SqliteConnectionManager>,
+    data_dir: &DataDir,
+    job: &JobContext,
+) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let mods =
+        ModDiskState::get_all_available(&conn).map_err(|e| format!("Database error: {}", e))?;
+
+    let total = mods.len();
+    let mut changed = 0;
+    for (index, (mod_item, state)) in mods.into_iter().enumerate() {
+        if job.is_cancelled() {
+            return Ok(());
+        }
+        let Some(disk_filename) = &mod_item.disk_filename else {
+            continue;
+        };
+        job.set_progress(format!("{}/{}: {}", index + 1, total, disk_filename));
+
+        let (size, mtime_ns) = match file_stat(&data_dir.get_mod_path(disk_filename)) {
+            Ok(stat) => stat,
+            Err(e) => {
+                job.record_error(disk_filename, format!("Failed to stat file: {}", e));
+                continue;
+            }
+        };
+
+        let baseline = state
+            .as_ref()
+            .and_then(|s| s.file_size.zip(s.file_mtime_ns));
+        match baseline {
+            // Ingested before sizes and mtimes were tracked. Without a hash
+            // we can't vouch for the file, so use what is there now as the
+            // baseline and leave checking it to verification.
+            None if size == mod_item.size => {
+                ModDiskState::record_ingest(mod_item.id, size, mtime_ns, &conn)
+                    .map_err(|e| format!("Database error: {}", e))?;
+            }
+            Some((recorded_size, recorded_mtime))
+                if recorded_size == size && recorded_mtime == mtime_ns => {}
+            _ => {
+                changed += 1;
+                job.record_error(
+                    disk_filename,
+                    format!(
+                        "File changed since it was hashed (size {} bytes, expected {})",
+                        size, mod_item.size
+                    ),
+                );
+                ModDiskState::set_needs_rehash(mod_item.id, &conn)
+                    .map_err(|e| format!("Database error: {}", e))?;
+            }
+        }
+    }
+
+    log::info!(
+        "Reconciliation complete: {} checked, {} changed on disk",
+        total,
+        changed
+    );
+    Ok(())
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    responses((status = 303, description = "Queued a disk reconciliation run; redirects to the jobs page"))
+)]
+#[post("/reconcile")]
+pub async fn reconcile(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let pool = pool.get_ref().clone();
+    let data_dir = data_dir.get_ref().clone();
+    jobs.submit(
+        JobKind::Reconcile,
+        Arc::new(move |job: &JobContext| reconcile_impl(&pool, &data_dir, job)),
+    );
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::{
+    data_dir::DataDir,
+    db::modlist::Modlist,
+    jobs::{JobContext, JobKind, JobRegistry},
+    resources::ingest::ingest_modlist,
+};
+
+/// Parses a stored modlist file again, e.g. after a protocol upgrade taught
+/// us a new downloader. Returns the modlist as re-ingested, which still has
+/// `parse_error` set if the file is still unreadable.
+fn reparse_modlist(
+    modlist: &Modlist,
+    data_dir: &DataDir,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Modlist, String> {
+    let path = data_dir.get_modlist_path(&modlist.filename);
+    if !path.exists() {
+        return Err("File is missing from the data directory".to_string());
+    }
+    ingest_modlist(&modlist.filename, &modlist.xxhash64, &path, conn).map_err(|e| e.to_string())
+}
+
+fn reparse_modlists_impl(
+    pool: &Pool<SqliteConnectionManager>,
+    data_dir: &DataDir,
+    job: &JobContext,
+) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let modlists =
+        Modlist::get_parse_failed(&conn).map_err(|e| format!("Database error: {}", e))?;
+
+    let total = modlists.len();
+    let mut fixed = 0;
+    for (index, modlist) in modlists.iter().enumerate() {
+        if job.is_cancelled() {
+            return Ok(());
+        }
+        job.set_progress(format!("{}/{}: {}", index + 1, total, modlist.filename));
+        match reparse_modlist(modlist, data_dir, &conn) {
+            Ok(reparsed) => match reparsed.parse_error {
+                Some(error) => job.record_error(&modlist.filename, error),
+                None => fixed += 1,
+            },
+            Err(e) => job.record_error(&modlist.filename, e),
+        }
+    }
+    log::info!("Re-parsed {} of {} modlists", fixed, total);
+    Ok(())
+}
+
+fn redirect_to_diagnostics() -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/diagnostics"))
+        .finish()
+}
+
+#[post("/admin/diagnostics/modlists/{id}/retry")]
+pub async fn retry_modlist_parse(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let modlist = Modlist::get_by_id(id.into_inner(), &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+
+    let reparsed = reparse_modlist(&modlist, &data_dir, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if reparsed.parse_error.is_none() {
+        log::info!("Modlist {} parsed on retry", reparsed.filename);
+        return Ok(HttpResponse::SeeOther()
+            .append_header(("Location", format!("/modlists/{}", reparsed.id)))
+            .finish());
+    }
+    Ok(redirect_to_diagnostics())
+}
+
+#[post("/admin/diagnostics/retry")]
+pub async fn retry_all_modlist_parses(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let pool = pool.get_ref().clone();
+    let data_dir = data_dir.get_ref().clone();
+    jobs.submit(
+        JobKind::ReparseModlists,
+        Arc::new(move |job: &JobContext| reparse_modlists_impl(&pool, &data_dir, job)),
+    );
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
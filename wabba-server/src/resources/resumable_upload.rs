@@ -0,0 +1,518 @@
+//! Resumable, chunked uploads for the browser upload page.
+//!
+//! Loosely follows the tus protocol: `POST /uploads` reserves an upload and
+//! returns its URL, `HEAD` reports how many bytes the server already has, and
+//! each `PATCH` appends one chunk at the reported offset. Every chunk carries
+//! an `Upload-Checksum: crc32 <base64>` header which is verified before the
+//! bytes are written. Partial uploads live on disk so they survive both
+//! dropped connections and server restarts, until they have sat idle for
+//! `MAX_IDLE`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use actix_web::{HttpRequest, HttpResponse, delete, head, http::StatusCode, patch, post, web};
+use base64::prelude::*;
+use futures_util::StreamExt;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use wabba_protocol::hash::Hash;
+
+use crate::data_dir::DataDir;
+use crate::db::mod_data::Mod;
+use crate::db::modlist::Modlist;
+use crate::jobs::{JobContext, JobFn, JobKind, JobRegistry};
+use crate::resources::filename_policy::sanitize_filename;
+use crate::resources::ingest::{ingest_mod, ingest_modlist};
+use crate::resources::{base64_to_base64url, determine_final_filename};
+
+/// Largest chunk accepted by a single PATCH.
+pub const MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Uploads that haven't received a chunk for this long are deleted, so
+/// abandoned partial files don't pile up in the data directory.
+pub const MAX_IDLE: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// How often abandoned uploads are looked for.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// One lock per upload being patched, so two PATCHes sent at the same
+/// offset can't both pass the offset check and interleave their writes.
+static UPLOAD_LOCKS: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Mutex::default);
+
+fn upload_lock(id: &str) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = UPLOAD_LOCKS.lock().unwrap();
+    // Forget the uploads nobody is patching right now.
+    locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+    locks.entry(id.to_string()).or_default().clone()
+}
+
+fn part_length(part_path: &Path) -> Result<u64, actix_web::Error> {
+    Ok(std::fs::metadata(part_path)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .len())
+}
+
+fn offset_mismatch(offset: u64, current: u64) -> HttpResponse {
+    HttpResponse::Conflict()
+        .append_header(("Upload-Offset", current.to_string()))
+        .body(format!(
+            "Offset mismatch: client sent {}, server has {}",
+            offset, current
+        ))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingUpload {
+    filename: String,
+    length: u64,
+    /// Unix seconds of the reservation or the last chunk stored. Missing
+    /// from uploads reserved by older versions, which go by the partial
+    /// file's modification time instead.
+    #[serde(default)]
+    last_activity: i64,
+}
+
+impl PendingUpload {
+    fn idle_since(&self, part_path: &Path) -> i64 {
+        if self.last_activity > 0 {
+            return self.last_activity;
+        }
+        std::fs::metadata(part_path)
+            .and_then(|metadata| metadata.modified())
+            .map_or(0, |modified| {
+                chrono::DateTime::<chrono::Utc>::from(modified).timestamp()
+            })
+    }
+}
+
+fn save_upload(meta_path: &Path, upload: &PendingUpload) -> Result<(), actix_web::Error> {
+    let meta = serde_json::to_string(upload).map_err(actix_web::error::ErrorInternalServerError)?;
+    std::fs::write(meta_path, meta).map_err(actix_web::error::ErrorInternalServerError)
+}
+
+fn upload_paths(data_dir: &DataDir, id: &str) -> Result<(PathBuf, PathBuf), actix_web::Error> {
+    // Ids are generated by us as lowercase hex; refuse anything else so the
+    // id can't escape the uploads directory.
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(actix_web::error::ErrorNotFound("Unknown upload"));
+    }
+    let dir = data_dir.get_resumable_upload_dir();
+    Ok((
+        dir.join(format!("{}.json", id)),
+        dir.join(format!("{}.part", id)),
+    ))
+}
+
+fn load_upload(
+    data_dir: &DataDir,
+    id: &str,
+) -> Result<(PendingUpload, PathBuf, PathBuf), actix_web::Error> {
+    let (meta_path, part_path) = upload_paths(data_dir, id)?;
+    let meta = std::fs::read_to_string(&meta_path)
+        .map_err(|_| actix_web::error::ErrorNotFound("Unknown upload"))?;
+    let upload: PendingUpload =
+        serde_json::from_str(&meta).map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok((upload, meta_path, part_path))
+}
+
+fn header_u64(req: &HttpRequest, name: &str) -> Result<u64, actix_web::Error> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .ok_or_else(|| actix_web::error::ErrorBadRequest(format!("{} header is required", name)))
+}
+
+/// Extracts `filename` from a tus `Upload-Metadata` header
+/// (`key base64value, key base64value`).
+fn metadata_filename(req: &HttpRequest) -> Option<String> {
+    let metadata = req.headers().get("Upload-Metadata")?.to_str().ok()?;
+    metadata.split(',').find_map(|pair| {
+        let mut parts = pair.trim().splitn(2, ' ');
+        if parts.next()? != "filename" {
+            return None;
+        }
+        let decoded = BASE64_STANDARD.decode(parts.next()?.trim()).ok()?;
+        String::from_utf8(decoded).ok()
+    })
+}
+
+#[utoipa::path(
+    tag = "uploads",
+    params(
+        ("Upload-Length" = u64, Header, description = "Total size of the file in bytes"),
+        ("Upload-Metadata" = String, Header, description = "tus metadata; must include `filename`"),
+    ),
+    responses(
+        (status = 201, description = "Upload reserved; `Location` is its URL"),
+        (status = 400, description = "Missing length or filename"),
+    )
+)]
+#[post("/uploads")]
+pub async fn create_upload(
+    data_dir: web::Data<DataDir>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let length = header_u64(&req, "Upload-Length")?;
+    let filename = metadata_filename(&req)
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("Upload-Metadata filename is required"))?;
+
+    // Keep only the final path component of whatever the browser sent.
+    let filename = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let filename = sanitize_filename(filename).map_err(actix_web::error::ErrorBadRequest)?;
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let id = format!(
+        "{:x}",
+        md5::compute(format!("{}:{}:{}", filename, length, nanos))
+    );
+    let (meta_path, part_path) = upload_paths(&data_dir, &id)?;
+
+    std::fs::File::create(&part_path).map_err(actix_web::error::ErrorInternalServerError)?;
+    save_upload(
+        &meta_path,
+        &PendingUpload {
+            filename: filename.clone(),
+            length,
+            last_activity: chrono::Utc::now().timestamp(),
+        },
+    )?;
+
+    log::info!(
+        "Created resumable upload {} for {} ({} bytes)",
+        id,
+        filename,
+        length
+    );
+
+    Ok(HttpResponse::Created()
+        .append_header(("Location", format!("/uploads/{}", id)))
+        .append_header(("Upload-Offset", "0"))
+        .finish())
+}
+
+#[utoipa::path(
+    tag = "uploads",
+    params(("id" = String, Path, description = "Upload id")),
+    responses(
+        (status = 200, description = "`Upload-Offset` and `Upload-Length` report progress"),
+        (status = 404, description = "Unknown upload"),
+    )
+)]
+#[head("/uploads/{id}")]
+pub async fn upload_status(
+    id: web::Path<String>,
+    data_dir: web::Data<DataDir>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (upload, _, part_path) = load_upload(&data_dir, &id)?;
+    let offset = part_length(&part_path)?;
+
+    Ok(HttpResponse::Ok()
+        .append_header(("Upload-Offset", offset.to_string()))
+        .append_header(("Upload-Length", upload.length.to_string()))
+        .append_header(("Cache-Control", "no-store"))
+        .finish())
+}
+
+#[utoipa::path(
+    tag = "uploads",
+    params(
+        ("id" = String, Path, description = "Upload id"),
+        ("Upload-Offset" = u64, Header, description = "Offset this chunk starts at"),
+        ("Upload-Checksum" = String, Header, description = "`crc32 <base64>` of the chunk"),
+    ),
+    request_body(content = [u8], content_type = "application/offset+octet-stream"),
+    responses(
+        (status = 204, description = "Chunk stored. Once the last chunk lands, `Location` is the ingested item's page"),
+        (status = 409, description = "Offset does not match what the server has"),
+        (status = 460, description = "Checksum mismatch"),
+    )
+)]
+#[patch("/uploads/{id}")]
+pub async fn upload_chunk(
+    id: web::Path<String>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    req: HttpRequest,
+    mut body: web::Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (upload, _, part_path) = load_upload(&data_dir, &id)?;
+    let offset = header_u64(&req, "Upload-Offset")?;
+    // Turn away a stale offset before reading the chunk; it is checked
+    // again under the lock before anything is written.
+    let current = part_length(&part_path)?;
+    if offset != current {
+        return Ok(offset_mismatch(offset, current));
+    }
+
+    let expected_checksum = req
+        .headers()
+        .get("Upload-Checksum")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().strip_prefix("crc32 "))
+        .map(|v| v.trim().to_string())
+        .ok_or_else(|| {
+            actix_web::error::ErrorBadRequest("Upload-Checksum: crc32 <base64> header is required")
+        })?;
+
+    let mut chunk = web::BytesMut::new();
+    while let Some(bytes) = body.next().await {
+        let bytes = bytes?;
+        if chunk.len() + bytes.len() > MAX_CHUNK_SIZE {
+            return Err(actix_web::error::ErrorPayloadTooLarge(format!(
+                "Chunks may be at most {} bytes",
+                MAX_CHUNK_SIZE
+            )));
+        }
+        chunk.extend_from_slice(&bytes);
+    }
+
+    if offset + chunk.len() as u64 > upload.length {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Chunk extends past the declared Upload-Length",
+        ));
+    }
+
+    let actual_checksum = BASE64_STANDARD.encode(crc32fast::hash(&chunk).to_be_bytes());
+    if actual_checksum != expected_checksum {
+        log::warn!(
+            "Checksum mismatch on upload {} at offset {}: expected {}, got {}",
+            id,
+            offset,
+            expected_checksum,
+            actual_checksum
+        );
+        // 460 is the tus "Checksum Mismatch" status; the client resends the chunk.
+        return Ok(HttpResponse::build(StatusCode::from_u16(460).unwrap())
+            .append_header(("Upload-Offset", current.to_string()))
+            .body("Checksum mismatch"));
+    }
+
+    let lock = upload_lock(&id);
+    let _guard = lock.lock().await;
+    // A PATCH that held the lock before us may have finished the upload.
+    let (mut upload, meta_path, part_path) = load_upload(&data_dir, &id)?;
+    let current = part_length(&part_path)?;
+    if offset != current {
+        return Ok(offset_mismatch(offset, current));
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(&part_path)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    file.write_all(&chunk)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    file.flush()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let new_offset = offset + chunk.len() as u64;
+
+    if new_offset < upload.length {
+        upload.last_activity = chrono::Utc::now().timestamp();
+        save_upload(&meta_path, &upload)?;
+        return Ok(HttpResponse::NoContent()
+            .append_header(("Upload-Offset", new_offset.to_string()))
+            .finish());
+    }
+
+    log::info!("Resumable upload {} complete, finalizing", id);
+    let id = id.into_inner();
+    let pool = pool.get_ref().clone();
+    let data_dir = data_dir.get_ref().clone();
+    let location = web::block(move || {
+        let location = finalize_upload(&upload, &part_path, &pool, &data_dir);
+        match &location {
+            Ok(_) => {
+                let _ = std::fs::remove_file(&meta_path);
+                let _ = std::fs::remove_file(&part_path);
+            }
+            // Keep the received bytes: an empty PATCH at the final offset
+            // retries the finalize, and the idle sweep clears them otherwise.
+            Err(e) if part_path.exists() => {
+                log::error!(
+                    "Failed to finalize upload {}, keeping it for a retry: {}",
+                    id,
+                    e
+                );
+                upload.last_activity = chrono::Utc::now().timestamp();
+                let _ = save_upload(&meta_path, &upload);
+            }
+            // The file already reached its final location; there is nothing
+            // left to retry.
+            Err(e) => {
+                log::error!("Failed to finalize upload {}: {}", id, e);
+                let _ = std::fs::remove_file(&meta_path);
+            }
+        }
+        location
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::NoContent()
+        .append_header(("Upload-Offset", new_offset.to_string()))
+        .append_header(("Location", location))
+        .finish())
+}
+
+#[utoipa::path(
+    tag = "uploads",
+    params(("id" = String, Path, description = "Upload id")),
+    responses(
+        (status = 204, description = "Upload discarded"),
+        (status = 404, description = "Unknown upload"),
+    )
+)]
+#[delete("/uploads/{id}")]
+pub async fn cancel_upload(
+    id: web::Path<String>,
+    data_dir: web::Data<DataDir>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (_, meta_path, part_path) = load_upload(&data_dir, &id)?;
+    let _ = std::fs::remove_file(&meta_path);
+    let _ = std::fs::remove_file(&part_path);
+    log::info!("Cancelled resumable upload {}", id);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Deletes the uploads that have been idle for longer than `max_idle`.
+/// Uploads a PATCH is writing to right now are left alone.
+fn expire_uploads(data_dir: &DataDir, max_idle: Duration, job: &JobContext) -> Result<(), String> {
+    let dir = data_dir.get_resumable_upload_dir();
+    let cutoff = chrono::Utc::now().timestamp() - max_idle.as_secs() as i64;
+    let entries =
+        std::fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut expired = 0;
+    for entry in entries {
+        if job.is_cancelled() {
+            return Ok(());
+        }
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let lock = upload_lock(id);
+        let Ok(_guard) = lock.try_lock() else {
+            continue;
+        };
+        let (upload, meta_path, part_path) = match load_upload(data_dir, id) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                job.record_error(id, e.to_string());
+                continue;
+            }
+        };
+        if upload.idle_since(&part_path) > cutoff {
+            continue;
+        }
+        for path in [&part_path, &meta_path] {
+            if let Err(e) = std::fs::remove_file(path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                job.record_error(id, format!("Failed to delete {}: {}", path.display(), e));
+            }
+        }
+        log::info!(
+            "Expired resumable upload {} of {} after {} hours idle",
+            id,
+            upload.filename,
+            max_idle.as_secs() / 3600
+        );
+        expired += 1;
+    }
+    job.set_progress(format!("Expired {} abandoned uploads", expired));
+    Ok(())
+}
+
+pub fn expire_uploads_job(data_dir: DataDir, max_idle: Duration) -> JobFn {
+    Arc::new(move |job: &JobContext| expire_uploads(&data_dir, max_idle, job))
+}
+
+/// Looks for abandoned uploads at startup and every `EXPIRY_INTERVAL`.
+pub fn spawn_upload_expiry_schedule(data_dir: DataDir, jobs: JobRegistry) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            jobs.submit(
+                JobKind::ExpireUploads,
+                expire_uploads_job(data_dir.clone(), MAX_IDLE),
+            );
+        }
+    });
+}
+
+/// Hashes the assembled file, moves it into place and ingests it. Returns the
+/// page the browser should land on. Blocks; run it off the async workers.
+fn finalize_upload(
+    upload: &PendingUpload,
+    part_path: &Path,
+    pool: &Pool<SqliteConnectionManager>,
+    data_dir: &DataDir,
+) -> Result<String, String> {
+    let hash = Hash::compute_file(part_path).map_err(|e| format!("Failed to hash file: {}", e))?;
+    log::info!(
+        "Computed hash {} for uploaded file {}",
+        hash,
+        upload.filename
+    );
+
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let is_modlist = upload.filename.to_lowercase().ends_with(".wabbajack");
+
+    if is_modlist {
+        if let Some(existing) =
+            Modlist::get_by_hash(&hash, &conn).map_err(|e| format!("Database error: {}", e))?
+            && existing.available
+        {
+            return Ok(format!("/modlists/{}", existing.id));
+        }
+    } else if let Some(existing) = Mod::get_by_size_and_hash(upload.length, &hash, &conn)
+        .map_err(|e| format!("Database error: {}", e))?
+        && existing.is_available()
+    {
+        return Ok(format!("/mod/{}", existing.id));
+    }
+
+    let dir = if is_modlist {
+        data_dir.get_modlist_dir()
+    } else {
+        data_dir.get_mod_dir()
+    };
+    let final_filename =
+        determine_final_filename(&upload.filename, &base64_to_base64url(&hash), &dir);
+    let final_path = dir.join(&final_filename);
+    std::fs::rename(part_path, &final_path)
+        .map_err(|e| format!("Failed to move file to final location: {}", e))?;
+    log::info!("File moved to final location: {}", final_filename);
+
+    if is_modlist {
+        ingest_modlist(&final_filename, &hash, &final_path, &conn).map_err(|e| e.to_string())?;
+        let modlist = Modlist::get_by_filename(&final_filename, &conn)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Modlist not found after ingest".to_string())?;
+        Ok(format!("/modlists/{}", modlist.id))
+    } else {
+        ingest_mod(&final_filename, &hash, &final_path, &conn).map_err(|e| e.to_string())?;
+        let mod_item = Mod::get_by_disk_filename(&final_filename, &conn)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Mod not found after ingest".to_string())?;
+        Ok(format!("/mod/{}", mod_item.id))
+    }
+}
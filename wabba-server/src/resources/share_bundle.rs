@@ -0,0 +1,137 @@
+//! Share bundles: everything needed to install a modlist on another
+//! machine as one download queue. A bundle is an aria2 input file listing
+//! the `.wabbajack`, every archive of it the server holds and each
+//! archive's `.meta` file, saved under the names Wabbajack expects.
+//! `aria2c -c` resumes interrupted files, so a transfer of hundreds of GB
+//! can be stopped and picked up again; the downloads support ranges.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::db::mod_data::Mod;
+use crate::db::modlist::Modlist;
+use crate::resources::gallery::base_url;
+
+/// One download in the queue, saved as `out` in the directory aria2 is
+/// told to use.
+fn queue_entry(bundle: &mut String, url: &str, out: &str) {
+    let _ = writeln!(bundle, "{}\n  out={}", url, out);
+}
+
+/// The share bundle of a modlist, as `{modlist}.bundle.txt`. Archives the
+/// server doesn't have are listed in comments at the end, so whoever gets
+/// the bundle knows what they still need to find.
+#[get("/modlists/{id}/bundle")]
+pub async fn share_bundle(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let modlist = Modlist::get_by_id(id.into_inner(), &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+    if !modlist.available {
+        return Err(actix_web::error::ErrorNotFound("Modlist is not available"));
+    }
+    let mods: HashMap<u64, Mod> = Mod::get_by_modlist_id(modlist.id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|mod_item| (mod_item.id, mod_item))
+        .collect();
+    let associations = modlist
+        .get_mod_associations(&conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let base_url = base_url(&req);
+    let (available, missing): (Vec<_>, Vec<_>) = associations.iter().partition(|assoc| {
+        mods.get(&assoc.mod_id)
+            .is_some_and(|mod_item| mod_item.is_available())
+    });
+    let archive_size: u64 = available
+        .iter()
+        .filter_map(|assoc| mods.get(&assoc.mod_id))
+        .map(|mod_item| mod_item.size)
+        .sum();
+
+    let mut bundle = String::new();
+    let _ = writeln!(bundle, "# {} {}", modlist.name, modlist.version);
+    let _ = writeln!(
+        bundle,
+        "# {} and {} of {} archives ({} bytes), from {}",
+        modlist.filename,
+        available.len(),
+        associations.len(),
+        modlist.size + archive_size,
+        base_url
+    );
+    let _ = writeln!(
+        bundle,
+        "# Download into the Wabbajack downloads folder with: aria2c -c -d <folder> -i <this file>"
+    );
+    let _ = writeln!(bundle);
+    queue_entry(
+        &mut bundle,
+        &format!("{}/modlists/{}/download", base_url, modlist.id),
+        &modlist.filename,
+    );
+    for assoc in &available {
+        let Some(mod_item) = mods.get(&assoc.mod_id) else {
+            continue;
+        };
+        // By hash rather than id: that route is open to machines when
+        // logins are required, so the bundle works without a session.
+        queue_entry(
+            &mut bundle,
+            &format!(
+                "{}/mods/by-hash/{}/download",
+                base_url,
+                mod_item
+                    .xxhash64
+                    .replace('+', "-")
+                    .replace('/', "_")
+                    .trim_end_matches('=')
+            ),
+            &assoc.filename,
+        );
+        if assoc.meta_ini().is_some() {
+            queue_entry(
+                &mut bundle,
+                &format!(
+                    "{}/api/modlists/{}/mods/{}/meta",
+                    base_url, modlist.id, assoc.mod_id
+                ),
+                &format!("{}.meta", assoc.filename),
+            );
+        }
+    }
+    if !missing.is_empty() {
+        let _ = writeln!(bundle);
+        let _ = writeln!(bundle, "# Not on this server:");
+        for assoc in &missing {
+            let _ = writeln!(bundle, "#   {}", assoc.filename);
+        }
+    }
+
+    let stem = modlist
+        .filename
+        .strip_suffix(".wabbajack")
+        .unwrap_or(&modlist.filename);
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header(header::ContentDisposition {
+            disposition: header::DispositionType::Attachment,
+            parameters: vec![header::DispositionParam::Filename(format!(
+                "{}.bundle.txt",
+                stem
+            ))],
+        })
+        .body(bundle))
+}
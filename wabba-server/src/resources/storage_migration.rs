@@ -0,0 +1,401 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use futures_util::StreamExt;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::Deserialize;
+use wabba_protocol::hash::{Hash, StreamingHash};
+
+use crate::{
+    bandwidth::BandwidthLimiter,
+    cold_storage::ColdStorage,
+    data_dir::DataDir,
+    db::{
+        mod_data::Mod,
+        mod_disk_state::{ModDiskState, file_stat},
+    },
+    jobs::{ExecuteFn, JobContext, JobKind, JobRegistry, PlanFn, PlannedAction},
+};
+
+/// Where a storage migration moves mods to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageTarget {
+    /// Every local copy directly in Downloads, out of any subfolders.
+    Flat,
+    /// Every mod kept locally, fetching back anything in cold storage.
+    Local,
+    /// Every mod offloaded to cold storage.
+    ColdStorage,
+}
+
+impl StorageTarget {
+    fn action(&self) -> &'static str {
+        match self {
+            StorageTarget::Flat => "move",
+            StorageTarget::Local => "restore",
+            StorageTarget::ColdStorage => "offload",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Migration {
+    target: StorageTarget,
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    cold_storage: ColdStorage,
+    limiter: BandwidthLimiter,
+}
+
+type Conn = PooledConnection<SqliteConnectionManager>;
+
+/// Bare file name of a `/`-separated `disk_filename`.
+fn flat_filename(disk_filename: &str) -> &str {
+    disk_filename.rsplit('/').next().unwrap_or(disk_filename)
+}
+
+impl Migration {
+    /// One action per mod that isn't where `target` wants it yet. Actions
+    /// target the mod's `disk_filename`, which execution looks up again.
+    fn plan(&self, job: &JobContext) -> Result<Vec<PlannedAction>, String> {
+        let conn = self.pool.get().map_err(|e| e.to_string())?;
+        let mut actions = Vec::new();
+        match self.target {
+            StorageTarget::Flat => {
+                let mods = ModDiskState::get_all_available(&conn)
+                    .map_err(|e| format!("Database error: {}", e))?;
+                for (mod_item, _) in mods {
+                    let Some(disk_filename) = mod_item.disk_filename else {
+                        continue;
+                    };
+                    let flat = flat_filename(&disk_filename);
+                    if flat == disk_filename {
+                        continue;
+                    }
+                    match self.flat_conflict(flat, &conn)? {
+                        Some(reason) => job.record_error(&disk_filename, reason),
+                        None => {
+                            job.log(format!("{} would move to {}", disk_filename, flat));
+                            actions.push(PlannedAction {
+                                action: self.target.action().to_string(),
+                                detail: Some(format!("to {}", flat)),
+                                target: disk_filename,
+                            });
+                        }
+                    }
+                }
+            }
+            StorageTarget::Local => {
+                let mods = ModDiskState::get_evicted(&conn)
+                    .map_err(|e| format!("Database error: {}", e))?;
+                for (mod_item, reference) in mods {
+                    let Some(disk_filename) = mod_item.disk_filename else {
+                        continue;
+                    };
+                    actions.push(PlannedAction {
+                        action: self.target.action().to_string(),
+                        target: disk_filename,
+                        detail: Some(format!("from {} ({} bytes)", reference, mod_item.size)),
+                    });
+                }
+            }
+            StorageTarget::ColdStorage => {
+                if !self.cold_storage.is_enabled() {
+                    return Err("Cold storage is not configured".to_string());
+                }
+                let mods = ModDiskState::get_all_available(&conn)
+                    .map_err(|e| format!("Database error: {}", e))?;
+                for (mod_item, state) in mods {
+                    let Some(disk_filename) = mod_item.disk_filename else {
+                        continue;
+                    };
+                    let detail = match state.and_then(|s| s.cold_storage_ref) {
+                        Some(_) => "already uploaded; drop the local copy".to_string(),
+                        None => format!("upload {} bytes", mod_item.size),
+                    };
+                    job.log(format!("{} would be offloaded: {}", disk_filename, detail));
+                    actions.push(PlannedAction {
+                        action: self.target.action().to_string(),
+                        target: disk_filename,
+                        detail: Some(detail),
+                    });
+                }
+            }
+        }
+        Ok(actions)
+    }
+
+    fn execute(&self, job: &JobContext, plan: &[PlannedAction]) -> Result<(), String> {
+        let total = plan.len();
+        let mut migrated = 0;
+        for (index, action) in plan.iter().enumerate() {
+            if job.is_cancelled() {
+                return Ok(());
+            }
+            job.set_progress(format!("{}/{}: {}", index + 1, total, action.target));
+            let conn = self.pool.get().map_err(|e| e.to_string())?;
+            let mod_item = match Mod::get_by_disk_filename(&action.target, &conn)
+                .map_err(|e| format!("Database error: {}", e))?
+            {
+                Some(mod_item) => mod_item,
+                None => {
+                    job.record_error(&action.target, "No longer in the database; skipped");
+                    continue;
+                }
+            };
+            job.log(format!("Starting to {} {}", action.action, action.target));
+            let result = match self.target {
+                StorageTarget::Flat => self.move_to_flat(&mod_item, &action.target, &conn),
+                StorageTarget::Local => self.restore(&mod_item, &action.target, &conn),
+                StorageTarget::ColdStorage => self.offload(&mod_item, &action.target, job, &conn),
+            };
+            match result {
+                Ok(()) => {
+                    migrated += 1;
+                    job.log(format!("{} {}", action.action, action.target));
+                }
+                Err(e) => job.record_error(&action.target, e),
+            }
+        }
+        log::info!(
+            "Storage migration to {:?} complete: {} of {} mods migrated",
+            self.target,
+            migrated,
+            total
+        );
+        Ok(())
+    }
+
+    /// Why `flat` can't be used as a mod's new name, if it can't.
+    fn flat_conflict(&self, flat: &str, conn: &Conn) -> Result<Option<String>, String> {
+        if Mod::get_by_disk_filename(flat, conn)
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some()
+        {
+            return Ok(Some(format!("Another mod is already stored as {}", flat)));
+        }
+        if self.data_dir.get_mod_path(flat).exists() {
+            return Ok(Some(format!("{} already exists in Downloads", flat)));
+        }
+        Ok(None)
+    }
+
+    /// Renames within Downloads. Across filesystems (a subfolder that is a
+    /// mount point) the file is copied and the copy hash-checked before the
+    /// original is removed.
+    fn move_to_flat(&self, mod_item: &Mod, disk_filename: &str, conn: &Conn) -> Result<(), String> {
+        let flat = flat_filename(disk_filename);
+        if let Some(reason) = self.flat_conflict(flat, conn)? {
+            return Err(reason);
+        }
+        let from = self.data_dir.get_mod_path(disk_filename);
+        let to = self.data_dir.get_mod_path(flat);
+        let evicted = ModDiskState::get_by_mod_id(mod_item.id, conn)
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some_and(|s| s.evicted);
+
+        // Evicted mods have no local file; only the name they come back
+        // under changes.
+        if !evicted && std::fs::rename(&from, &to).is_err() {
+            std::fs::copy(&from, &to).map_err(|e| format!("Failed to copy file: {}", e))?;
+            let hash =
+                Hash::compute_file(&to).map_err(|e| format!("Failed to hash copy: {}", e))?;
+            if hash != mod_item.xxhash64 {
+                let _ = std::fs::remove_file(&to);
+                return Err(format!(
+                    "Copy does not match: expected {}, found {}; original kept",
+                    mod_item.xxhash64, hash
+                ));
+            }
+            mod_item
+                .set_disk_filename(flat, conn)
+                .map_err(|e| format!("Database error: {}", e))?;
+            std::fs::remove_file(&from).map_err(|e| format!("Failed to remove original: {}", e))?;
+            return Ok(());
+        }
+        mod_item
+            .set_disk_filename(flat, conn)
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Fetches an evicted mod back into Downloads and hash-checks it before
+    /// it replaces anything. The cold storage copy is kept.
+    fn restore(&self, mod_item: &Mod, disk_filename: &str, conn: &Conn) -> Result<(), String> {
+        let state = ModDiskState::get_by_mod_id(mod_item.id, conn)
+            .map_err(|e| format!("Database error: {}", e))?;
+        let Some(reference) = state.filter(|s| s.evicted).and_then(|s| s.cold_storage_ref) else {
+            return Err("No longer in cold storage; skipped".to_string());
+        };
+
+        let path = self.data_dir.get_mod_path(disk_filename);
+        let dir = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.data_dir.get_mod_dir());
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        // Named like an upload temp file so a bootstrap running meanwhile
+        // skips it.
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let temp_path = dir.join(format!("upload_{}.tmp", timestamp));
+
+        let result = self
+            .read_back(&reference, Some(&temp_path))
+            .and_then(|hash| {
+                if hash != mod_item.xxhash64 {
+                    return Err(format!(
+                        "Cold storage returned the wrong file: expected {}, found {}",
+                        mod_item.xxhash64, hash
+                    ));
+                }
+                std::fs::rename(&temp_path, &path)
+                    .map_err(|e| format!("Failed to move file to final location: {}", e))
+            });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result?;
+
+        let (size, mtime_ns) = file_stat(&path).map_err(|e| e.to_string())?;
+        ModDiskState::record_restore(mod_item.id, size, mtime_ns, conn)
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Writes `reference` to `path` (or nowhere, if `None`), returning the
+    /// hash of what was read.
+    fn read_back(&self, reference: &str, path: Option<&Path>) -> Result<String, String> {
+        tokio::runtime::Handle::current().block_on(async {
+            let mut stream = self.cold_storage.open(reference).await?;
+            let mut file = match path {
+                Some(path) => Some(
+                    std::fs::File::create_new(path)
+                        .map_err(|e| format!("Failed to create {:?}: {}", path, e))?,
+                ),
+                None => None,
+            };
+            let mut hasher = StreamingHash::new();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| format!("Cold storage transfer failed: {}", e))?;
+                self.limiter.throttle(chunk.len()).await;
+                hasher.update(&chunk);
+                if let Some(file) = file.as_mut() {
+                    file.write_all(&chunk)
+                        .map_err(|e| format!("Failed to write file: {}", e))?;
+                }
+            }
+            if let Some(file) = file.as_mut() {
+                file.flush()
+                    .map_err(|e| format!("Failed to write file: {}", e))?;
+            }
+            Ok(hasher.finish())
+        })
+    }
+
+    /// Uploads a verified local copy, reads it back to check cold storage
+    /// has it intact, and only then removes the local file.
+    fn offload(
+        &self,
+        mod_item: &Mod,
+        disk_filename: &str,
+        job: &JobContext,
+        conn: &Conn,
+    ) -> Result<(), String> {
+        let state = ModDiskState::get_by_mod_id(mod_item.id, conn)
+            .map_err(|e| format!("Database error: {}", e))?;
+        if state.as_ref().is_some_and(|s| s.evicted) {
+            return Err("Already in cold storage; skipped".to_string());
+        }
+
+        let path = self.data_dir.get_mod_path(disk_filename);
+        job.set_progress(format!("Hashing {}", disk_filename));
+        let hash = Hash::compute_file(&path).map_err(|e| format!("Failed to hash file: {}", e))?;
+        if hash != mod_item.xxhash64 {
+            return Err(format!(
+                "Hash mismatch: expected {}, found {}; not offloading",
+                mod_item.xxhash64, hash
+            ));
+        }
+
+        let reference = match state.and_then(|s| s.cold_storage_ref) {
+            Some(reference) => reference,
+            None => {
+                job.set_progress(format!(
+                    "Uploading {} to {}",
+                    disk_filename,
+                    self.cold_storage.label()
+                ));
+                tokio::runtime::Handle::current()
+                    .block_on(self.cold_storage.store(&path, &self.limiter))?
+            }
+        };
+
+        job.set_progress(format!("Checking {} in cold storage", disk_filename));
+        let stored_hash = self.read_back(&reference, None)?;
+        if stored_hash != mod_item.xxhash64 {
+            return Err(format!(
+                "Cold storage copy does not match: expected {}, found {}; local copy kept",
+                mod_item.xxhash64, stored_hash
+            ));
+        }
+
+        ModDiskState::record_cold_copy(mod_item.id, &reference, conn)
+            .map_err(|e| format!("Database error: {}", e))?;
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to remove local copy: {}", e))?;
+        ModDiskState::record_eviction(mod_item.id, &reference, conn)
+            .map_err(|e| format!("Database error: {}", e))?;
+        log::info!(
+            "Migrated {} to cold storage as {}",
+            disk_filename,
+            reference
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MigrateStorageForm {
+    target: StorageTarget,
+    /// Keep a line per step on the job, for reviewing what it did.
+    #[serde(default)]
+    verbose: bool,
+}
+
+/// Queues a dry run listing every mod that would move to `target`. Nothing
+/// is touched until the plan is confirmed on the job page.
+#[post("/storage/migrate")]
+pub async fn migrate_storage(
+    form: web::Form<MigrateStorageForm>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    cold_storage: web::Data<ColdStorage>,
+    limiter: web::Data<BandwidthLimiter>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let MigrateStorageForm { target, verbose } = form.into_inner();
+    if target == StorageTarget::ColdStorage && !cold_storage.is_enabled() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Cold storage is not configured",
+        ));
+    }
+    let migration = Migration {
+        target,
+        pool: pool.get_ref().clone(),
+        data_dir: data_dir.get_ref().clone(),
+        cold_storage: cold_storage.get_ref().clone(),
+        limiter: limiter.get_ref().clone(),
+    };
+    let planner = migration.clone();
+    let plan: PlanFn = Arc::new(move |job: &JobContext| planner.plan(job));
+    let execute: ExecuteFn =
+        Arc::new(move |job: &JobContext, plan: &[PlannedAction]| migration.execute(job, plan));
+    let id = jobs.submit_dry_run(JobKind::StorageMigration, plan, execute, verbose);
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/admin/jobs/{}", id)))
+        .finish())
+}
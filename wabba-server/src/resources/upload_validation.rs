@@ -18,6 +18,7 @@ pub trait ArchiveType: Clone {
         conn: &PooledConnection<SqliteConnectionManager>,
     ) -> Result<Option<Self>, rusqlite::Error>;
     fn is_available(&self) -> bool;
+    fn size(&self) -> u64;
 }
 
 impl ArchiveType for Mod {
@@ -31,6 +32,10 @@ impl ArchiveType for Mod {
     fn is_available(&self) -> bool {
         self.is_available()
     }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
 }
 
 impl ArchiveType for Modlist {
@@ -44,6 +49,10 @@ impl ArchiveType for Modlist {
     fn is_available(&self) -> bool {
         self.available
     }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
 }
 
 pub fn validate_upload_request<A: ArchiveType>(
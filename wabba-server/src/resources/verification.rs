@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use wabba_protocol::hash::Hash;
+
+use crate::{
+    config::{VerificationPolicy, VerificationStrategy},
+    data_dir::DataDir,
+    db::mod_disk_state::{ModDiskState, file_stat},
+    jobs::{JobContext, JobFn, JobKind, JobRegistry},
+};
+
+fn verify_mods_impl(
+    pool: &Pool<SqliteConnectionManager>,
+    data_dir: &DataDir,
+    policy: &VerificationPolicy,
+    job: &JobContext,
+) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let mods = ModDiskState::select_mods_for_verification(policy, &conn)
+        .map_err(|e| format!("Database error: {}", e))?;
+    log::info!(
+        "Verifying {} mods ({:?} strategy)",
+        mods.len(),
+        policy.strategy
+    );
+
+    let total = mods.len();
+    let mut mismatches = 0;
+    for (index, mod_item) in mods.into_iter().enumerate() {
+        if job.is_cancelled() {
+            return Ok(());
+        }
+        let Some(disk_filename) = &mod_item.disk_filename else {
+            continue;
+        };
+        job.set_progress(format!("{}/{}: {}", index + 1, total, disk_filename));
+
+        let path = data_dir.get_mod_path(disk_filename);
+        // Stat before hashing so a write during the hash shows up as a change
+        // on the next reconciliation rather than being blessed.
+        let stat = file_stat(&path).ok();
+        let hash = match Hash::compute_file(&path) {
+            Ok(hash) => hash,
+            Err(e) => {
+                job.record_error(disk_filename, format!("Failed to hash file: {}", e));
+                continue;
+            }
+        };
+
+        let ok = hash == mod_item.xxhash64;
+        if !ok {
+            mismatches += 1;
+            job.record_error(
+                disk_filename,
+                format!(
+                    "Hash mismatch: expected {}, found {}",
+                    mod_item.xxhash64, hash
+                ),
+            );
+        }
+        ModDiskState::record_verification(mod_item.id, ok, stat, &conn)
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    log::info!(
+        "Verification complete: {} checked, {} mismatched",
+        total,
+        mismatches
+    );
+    Ok(())
+}
+
+fn verification_job(
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    policy: VerificationPolicy,
+) -> JobFn {
+    Arc::new(move |job: &JobContext| verify_mods_impl(&pool, &data_dir, &policy, job))
+}
+
+/// Submits a verification job on `policy.interval` for as long as the
+/// server runs.
+pub fn spawn_verification_schedule(
+    pool: Pool<SqliteConnectionManager>,
+    data_dir: DataDir,
+    jobs: JobRegistry,
+    policy: VerificationPolicy,
+) {
+    if policy.strategy == VerificationStrategy::Off {
+        log::info!("Scheduled verification is off");
+        return;
+    }
+    log::info!(
+        "Scheduling {:?} verification every {} hours",
+        policy.strategy,
+        policy.interval.as_secs() / 3600
+    );
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(policy.interval);
+        // The first tick fires immediately; wait a full interval after
+        // startup instead.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            jobs.submit(
+                JobKind::Verify,
+                verification_job(pool.clone(), data_dir.clone(), policy.clone()),
+            );
+        }
+    });
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    responses((status = 303, description = "Queued a hash verification run; redirects to the jobs page"))
+)]
+#[post("/verify")]
+pub async fn verify_mods(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    policy: web::Data<VerificationPolicy>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // A manual run with the schedule turned off still needs something to do.
+    let mut policy = policy.get_ref().clone();
+    if policy.strategy == VerificationStrategy::Off {
+        policy.strategy = VerificationStrategy::Stale;
+    }
+    jobs.submit(
+        JobKind::Verify,
+        verification_job(pool.get_ref().clone(), data_dir.get_ref().clone(), policy),
+    );
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
@@ -0,0 +1,112 @@
+use actix_web::{HttpResponse, post, web};
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use wabba_protocol::archive_state::ArchiveState;
+
+use crate::db::{mod_association::ModAssociation, wayback::WaybackLookup};
+
+/// The Wayback Machine's capture index.
+const WAYBACK_CDX_URL: &str = "https://web.archive.org/cdx/search/cdx";
+
+/// How many of the newest distinct captures are kept per URL.
+const MAX_CAPTURES: i32 = 10;
+
+/// Successful captures of `url`, newest last, as (timestamp, MIME type).
+/// Captures with identical content are collapsed into one.
+async fn find_captures(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Vec<(String, Option<String>)>, String> {
+    let response = client
+        .get(WAYBACK_CDX_URL)
+        .query(&[
+            ("url", url),
+            ("output", "json"),
+            ("fl", "timestamp,mimetype"),
+            ("filter", "statuscode:200"),
+            ("collapse", "digest"),
+            ("limit", &(-MAX_CAPTURES).to_string()),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Wayback Machine request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Wayback Machine responded with {}",
+            response.status()
+        ));
+    }
+    // A header row followed by one row per capture, or nothing at all.
+    let rows: Vec<Vec<String>> = response
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected Wayback Machine response: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .skip(1)
+        .filter_map(|row| {
+            let mut fields = row.into_iter();
+            let timestamp = fields.next()?;
+            Some((timestamp, fields.next().filter(|mime| mime != "-")))
+        })
+        .collect())
+}
+
+/// Looks `url` up in the Wayback Machine and stores what it finds against
+/// `mod_id`. Returns the number of captures.
+pub async fn lookup_archived_source(
+    mod_id: u64,
+    url: &str,
+    client: &reqwest::Client,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<usize, String> {
+    let captures = find_captures(client, url).await?;
+    WaybackLookup::record(mod_id, url, &captures, conn)
+        .map_err(|e| format!("Database error: {}", e))?;
+    log::info!(
+        "Found {} Wayback Machine captures of {} for mod {}",
+        captures.len(),
+        url,
+        mod_id
+    );
+    Ok(captures.len())
+}
+
+#[post("/mod/{id}/wayback")]
+pub async fn lookup_wayback(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_id = id.into_inner();
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let associations = ModAssociation::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let mut urls: Vec<&str> = associations
+        .iter()
+        .filter_map(|association| match &association.source {
+            ArchiveState::HttpDownloader { url, .. } => Some(url.as_str()),
+            _ => None,
+        })
+        .collect();
+    urls.sort();
+    urls.dedup();
+    if urls.is_empty() {
+        return Err(actix_web::error::ErrorNotFound(
+            "This mod has no HTTP sources",
+        ));
+    }
+
+    let client = reqwest::Client::new();
+    for url in urls {
+        lookup_archived_source(mod_id, url, &client, &conn)
+            .await
+            .map_err(actix_web::error::ErrorBadGateway)?;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/mod/{}", mod_id)))
+        .finish())
+}
@@ -0,0 +1,161 @@
+//! Logs database queries and requests that take longer than a threshold,
+//! each with the request or job it ran for, to find what makes the server
+//! sluggish on a large database.
+//!
+//! - `SLOW_QUERY_MS`: queries slower than this are logged (default 250)
+//! - `SLOW_REQUEST_MS`: requests slower than this are logged (default 2000)
+//!
+//! `0` turns either off. String literals in the SQL and secrets in
+//! request query strings are masked.
+//!
+//! Bound SQL parameters aren't logged, only the SQL with its placeholders:
+//! rusqlite's profile hook is handed the statement text alone, and getting
+//! at the bound values needs `sqlite3_trace_v2`, which this rusqlite
+//! version doesn't wrap. The request or job a slow query ran for is logged
+//! instead; its path (e.g. `GET /modlists/3`) usually names the modlist or
+//! hash behind it.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Uri;
+use actix_web::middleware::Next;
+use rusqlite::Connection;
+
+static SLOW_QUERY_MS: AtomicU64 = AtomicU64::new(250);
+static SLOW_REQUEST_MS: AtomicU64 = AtomicU64::new(2000);
+
+/// Query string keys whose values never make it into the log.
+const SECRET_KEYS: &[&str] = &["code", "state", "token", "password", "secret", "key"];
+
+tokio::task_local! {
+    /// What the running code works for: a request as `GET /path?query`, or
+    /// a job as `job 12 (Verify mods)`.
+    static ORIGIN: String;
+}
+
+/// Reads the thresholds from the environment.
+pub fn init_from_env() -> Result<(), String> {
+    for (name, threshold) in [
+        ("SLOW_QUERY_MS", &SLOW_QUERY_MS),
+        ("SLOW_REQUEST_MS", &SLOW_REQUEST_MS),
+    ] {
+        if let Ok(value) = std::env::var(name) {
+            let ms = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{} must be a non-negative integer", name))?;
+            threshold.store(ms, Ordering::Relaxed);
+        }
+    }
+    Ok(())
+}
+
+fn is_slow(threshold: &AtomicU64, elapsed: Duration) -> bool {
+    let ms = threshold.load(Ordering::Relaxed);
+    ms != 0 && elapsed >= Duration::from_millis(ms)
+}
+
+fn origin() -> String {
+    ORIGIN
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| "background work".to_string())
+}
+
+/// Times every statement run on `conn`. Set on each pooled connection.
+pub fn profile_connection(conn: &mut Connection) {
+    conn.profile(Some(log_slow_query));
+}
+
+fn log_slow_query(sql: &str, elapsed: Duration) {
+    if is_slow(&SLOW_QUERY_MS, elapsed) {
+        log::warn!(
+            "Slow query ({} ms) for {}: {}",
+            elapsed.as_millis(),
+            origin(),
+            mask_sql(sql)
+        );
+    }
+}
+
+/// `sql` on one line, with string literals replaced by `'***'`.
+pub fn mask_sql(sql: &str) -> String {
+    let mut masked = String::with_capacity(sql.len());
+    let mut in_literal = false;
+    for c in sql.chars() {
+        match c {
+            '\'' if in_literal => in_literal = false,
+            '\'' => {
+                in_literal = true;
+                masked.push_str("'***'");
+            }
+            _ if in_literal => {}
+            c if c.is_whitespace() => {
+                if !masked.ends_with(' ') {
+                    masked.push(' ');
+                }
+            }
+            c => masked.push(c),
+        }
+    }
+    masked.trim().to_string()
+}
+
+/// The path and query of `uri`, with the values of secret-looking query
+/// parameters (OIDC codes, tokens) replaced by `***`.
+pub fn mask_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.path().to_string();
+    };
+    let mut masked = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        let lower = key.to_lowercase();
+        if SECRET_KEYS.iter().any(|secret| lower.contains(secret)) {
+            masked.append_pair(&key, "***");
+        } else {
+            masked.append_pair(&key, &value);
+        }
+    }
+    format!("{}?{}", uri.path(), masked.finish())
+}
+
+/// Runs the job `f` with `origin` as what its queries are logged for.
+/// Jobs run on a blocking thread of their own, outside any request.
+pub fn for_job<T>(origin: String, f: impl FnOnce() -> T) -> T {
+    ORIGIN.sync_scope(origin, f)
+}
+
+/// Wraps `f` to run with the current request or job as its origin, for
+/// work a handler hands to a blocking thread.
+pub fn carry_origin<T>(f: impl FnOnce() -> T + Send) -> impl FnOnce() -> T + Send {
+    let origin = origin();
+    move || ORIGIN.sync_scope(origin, f)
+}
+
+/// Middleware that logs requests slower than `SLOW_REQUEST_MS` and tags
+/// the queries made while handling them. Times the handler up to the
+/// response; streaming the body of a download afterwards doesn't count.
+pub async fn log_slow_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let origin = format!("{} {}", req.method(), mask_uri(req.uri()));
+    let started = Instant::now();
+    let response = ORIGIN.scope(origin.clone(), next.call(req)).await;
+    let elapsed = started.elapsed();
+    if is_slow(&SLOW_REQUEST_MS, elapsed) {
+        let status = match &response {
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+        log::warn!(
+            "Slow request ({} ms): {} -> {}",
+            elapsed.as_millis(),
+            origin,
+            status
+        );
+    }
+    response
+}
@@ -0,0 +1,199 @@
+//! An in-process server for end-to-end tests: the real routes and handlers,
+//! backed by a temporary data directory and an in-memory database.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use actix_http::Request;
+use actix_session::SessionMiddleware;
+use actix_session::storage::CookieSessionStore;
+use actix_web::cookie::Key;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::middleware::from_fn;
+use actix_web::{App, test};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use tempfile::TempDir;
+
+use crate::app::{self, AppState};
+use crate::bandwidth::BandwidthLimiter;
+use crate::cold_storage::ColdStorage;
+use crate::config::{
+    BandwidthSchedule, DigestConfig, DownloaderConfig, MetadataRefreshConfig, VerificationPolicy,
+};
+use crate::credentials::CredentialVault;
+use crate::data_dir::DataDir;
+use crate::db::migrations::migrate;
+use crate::ignore::IgnorePatterns;
+use crate::jobs::{JobInfo, JobRegistry, JobStatus};
+use crate::logging::LogControl;
+use crate::notifications::Notifier;
+use crate::transfer_stats::record_transfers;
+use crate::web::auth::require_login;
+
+/// Gives each test its own shared-cache in-memory database.
+static NEXT_DATABASE: AtomicUsize = AtomicUsize::new(0);
+
+/// How long `wait_for_jobs` waits before failing the test.
+const JOB_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct TestServer {
+    pub state: AppState,
+    // Dropped with the server, which removes the data directory.
+    _root: TempDir,
+}
+
+impl TestServer {
+    pub fn new() -> Self {
+        let root = tempfile::tempdir().expect("Failed to create temp dir");
+        let data_dir =
+            DataDir::new(&root.path().to_path_buf()).expect("Failed to open data directory");
+
+        // Every pooled connection opens the same named in-memory database,
+        // which lives as long as the pool keeps a connection open.
+        let manager = SqliteConnectionManager::file(format!(
+            "file:wabba-test-{}?mode=memory&cache=shared",
+            NEXT_DATABASE.fetch_add(1, Ordering::SeqCst)
+        ));
+        let pool = Pool::new(manager).expect("Failed to create database pool");
+        migrate(pool.get().expect("Failed to get database connection"))
+            .expect("Failed to run database migrations");
+
+        let state = AppState {
+            pool,
+            data_dir,
+            jobs: JobRegistry::new(),
+            ignore: IgnorePatterns::from_env().expect("Invalid INGEST_IGNORE_PATTERNS"),
+            verification: VerificationPolicy::from_env().expect("Invalid verification settings"),
+            cold_storage: ColdStorage::from_env().expect("Invalid cold storage settings"),
+            limiter: BandwidthLimiter::new(
+                BandwidthSchedule::from_env().expect("Invalid bandwidth settings"),
+            ),
+            downloader: DownloaderConfig::from_env().expect("Invalid downloader settings"),
+            vault: CredentialVault::from_env(),
+            metadata_refresh: MetadataRefreshConfig::from_env()
+                .expect("Invalid metadata refresh settings"),
+            digest: DigestConfig::from_env().expect("Invalid digest settings"),
+            notifier: Notifier::default(),
+            oidc: None,
+            logging: LogControl::default(),
+        };
+
+        TestServer { state, _root: root }
+    }
+
+    /// The app as `main` builds it, minus the session and logger middleware.
+    pub async fn app(
+        &self,
+    ) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+        test::init_service(
+            App::new()
+                .wrap(from_fn(record_transfers))
+                .configure(|cfg| app::configure(cfg, &self.state)),
+        )
+        .await
+    }
+
+    /// The app with the sign-in check and session `main` wraps it in, for
+    /// tests of who may reach which routes once OIDC is configured.
+    pub async fn app_with_login(
+        &self,
+    ) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+        test::init_service(
+            App::new()
+                .wrap(from_fn(record_transfers))
+                .wrap(from_fn(require_login))
+                .wrap(
+                    SessionMiddleware::builder(CookieSessionStore::default(), Key::from(&[0; 64]))
+                        .cookie_secure(false)
+                        .build(),
+                )
+                .configure(|cfg| app::configure(cfg, &self.state)),
+        )
+        .await
+    }
+
+    /// The read-only app served on `PUBLIC_LISTEN_ADDR`.
+    pub async fn public_app(
+        &self,
+    ) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+        test::init_service(
+            App::new()
+                .wrap(from_fn(record_transfers))
+                .configure(|cfg| app::configure_public(cfg, &self.state, false)),
+        )
+        .await
+    }
+
+    /// The read-only app with the session cookie `main` gives it, for
+    /// tests of what a visitor's session remembers.
+    pub async fn public_app_with_session(
+        &self,
+    ) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+        test::init_service(
+            App::new()
+                .wrap(from_fn(record_transfers))
+                .wrap(
+                    SessionMiddleware::builder(CookieSessionStore::default(), Key::from(&[0; 64]))
+                        .cookie_secure(false)
+                        .build(),
+                )
+                .configure(|cfg| app::configure_public(cfg, &self.state, false)),
+        )
+        .await
+    }
+
+    pub fn data_dir(&self) -> &DataDir {
+        &self.state.data_dir
+    }
+
+    /// Blocks until every submitted job has finished, failing the test if any
+    /// of them failed or recorded errors.
+    pub async fn wait_for_jobs(&self) {
+        let deadline = tokio::time::Instant::now() + JOB_TIMEOUT;
+        loop {
+            let jobs = self.state.jobs.list();
+            if jobs.iter().all(|job| job.status.is_finished()) {
+                for job in jobs {
+                    assert_eq!(job.status, JobStatus::Succeeded, "{} job", job.kind.label());
+                    assert!(
+                        job.errors.is_empty(),
+                        "{} job recorded errors: {:?}",
+                        job.kind.label(),
+                        job.errors
+                    );
+                }
+                return;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "Timed out waiting for jobs"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// Blocks until the dry run `id` has its plan ready for confirmation.
+    pub async fn wait_for_plan(&self, id: u64) -> JobInfo {
+        let deadline = tokio::time::Instant::now() + JOB_TIMEOUT;
+        loop {
+            let job = self.state.jobs.get(id).expect("No such job");
+            match job.status {
+                JobStatus::AwaitingConfirmation => return job,
+                JobStatus::Queued | JobStatus::Running => {}
+                other => panic!("Dry run {} ended as {:?}", id, other),
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "Timed out waiting for the plan"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+}
+
+/// Writes `contents` to `dir/filename`, as if it had been copied in by hand.
+pub fn place_file(dir: &Path, filename: &str, contents: &[u8]) {
+    std::fs::write(dir.join(filename), contents).expect("Failed to write fixture file");
+}
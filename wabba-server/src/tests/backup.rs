@@ -0,0 +1,58 @@
+use actix_web::http::StatusCode;
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::test;
+use wabba_protocol::test_util::TestArchive;
+
+use crate::test_support::TestServer;
+
+#[actix_web::test]
+async fn backup_is_a_sqlite_snapshot_of_the_live_database() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archive = TestArchive::new("Some Mod-1-0.7z", b"some mod contents");
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Some%20Mod-1-0.7z")
+        .insert_header(("If-None-Match", archive.hash()))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let request = test::TestRequest::get().uri("/api/backup").to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let length: usize = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let snapshot = test::read_body(response).await;
+    assert_eq!(snapshot.len(), length);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.db");
+    std::fs::write(&path, &snapshot).unwrap();
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let hash: String = conn
+        .query_row("SELECT xxhash64 FROM \"mod\"", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(hash, archive.hash());
+
+    // The snapshot is only staged in the data directory while it is sent.
+    let leftovers: Vec<_> = std::fs::read_dir(server.data_dir().get_path())
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("db.backup-")
+        })
+        .collect();
+    assert!(leftovers.is_empty());
+}
@@ -0,0 +1,95 @@
+use actix_web::http::StatusCode;
+use actix_web::test;
+use wabba_protocol::api::{ArchiveKind, AvailabilityReport, HashCheckRequest};
+use wabba_protocol::hash::Hash;
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::test_support::{TestServer, place_file};
+
+#[actix_web::test]
+async fn bootstrap_ingests_files_already_on_disk() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("Present.7z", b"present archive"),
+        TestArchive::new("Absent.7z", b"absent archive"),
+    ];
+    let [present, absent] = &archives;
+    let modlist = ModlistBuilder::new("Bootstrapped")
+        .version("2.0.0")
+        .archives(archives.clone())
+        .build();
+    let modlist_hash = Hash::compute(&modlist);
+    place_file(
+        &server.data_dir().get_modlist_dir(),
+        "Bootstrapped.wabbajack",
+        &modlist,
+    );
+    place_file(
+        &server.data_dir().get_mod_dir(),
+        &present.filename,
+        &present.contents,
+    );
+
+    let request = test::TestRequest::post().uri("/bootstrap").to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+    server.wait_for_jobs().await;
+
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Mod,
+            hashes: vec![present.hash(), absent.hash()],
+            sizes: Vec::new(),
+        })
+        .to_request();
+    let report: AvailabilityReport = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(report.available, vec![present.hash()]);
+    assert_eq!(report.missing, vec![absent.hash()]);
+
+    let request = test::TestRequest::get()
+        .uri("/check/modlist")
+        .insert_header(("If-None-Match", modlist_hash))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::NOT_MODIFIED
+    );
+}
+
+#[cfg(unix)]
+#[actix_web::test]
+async fn bootstrap_skips_symlinked_directories() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let inside = TestArchive::new("Inside.7z", b"inside archive");
+    let outside = TestArchive::new("Outside.7z", b"outside archive");
+    let mod_dir = server.data_dir().get_mod_dir();
+    place_file(&mod_dir, &inside.filename, &inside.contents);
+    let elsewhere = tempfile::tempdir().unwrap();
+    place_file(elsewhere.path(), &outside.filename, &outside.contents);
+    std::os::unix::fs::symlink("..", mod_dir.join("Loop")).unwrap();
+    std::os::unix::fs::symlink(elsewhere.path(), mod_dir.join("Elsewhere")).unwrap();
+
+    let request = test::TestRequest::post().uri("/bootstrap").to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+    server.wait_for_jobs().await;
+
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Mod,
+            hashes: vec![inside.hash(), outside.hash()],
+            sizes: Vec::new(),
+        })
+        .to_request();
+    let report: AvailabilityReport = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(report.available, vec![inside.hash()]);
+    assert_eq!(report.missing, vec![outside.hash()]);
+}
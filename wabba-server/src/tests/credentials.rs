@@ -0,0 +1,68 @@
+use crate::credentials::{CredentialVault, LOVERSLAB_ACCESS_TOKEN, NEXUS_API_KEY};
+use crate::db::credential::StoredCredential;
+use crate::test_support::TestServer;
+
+const PASSPHRASE: &str = "4f1c9a0e7b2d8c6f3a5e1b9d7c0f2a4e";
+
+#[test]
+fn stored_credentials_read_back() {
+    let server = TestServer::new();
+    let conn = server.state.pool.get().unwrap();
+    let vault = CredentialVault::new(Some(PASSPHRASE));
+
+    assert_eq!(vault.get(NEXUS_API_KEY, &conn).unwrap(), None);
+    vault.set(NEXUS_API_KEY, "first key", &conn).unwrap();
+    vault.set(NEXUS_API_KEY, "second key", &conn).unwrap();
+    assert_eq!(
+        vault.get(NEXUS_API_KEY, &conn).unwrap().as_deref(),
+        Some("second key")
+    );
+    let stored = StoredCredential::get_by_name(NEXUS_API_KEY, &conn)
+        .unwrap()
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&stored.ciphertext).contains("second key"));
+
+    assert!(
+        CredentialVault::new(None)
+            .get(NEXUS_API_KEY, &conn)
+            .is_err()
+    );
+}
+
+#[test]
+fn another_key_cannot_read_credentials() {
+    let server = TestServer::new();
+    let conn = server.state.pool.get().unwrap();
+    CredentialVault::new(Some(PASSPHRASE))
+        .set(NEXUS_API_KEY, "secret", &conn)
+        .unwrap();
+
+    let error = CredentialVault::new(Some("a different passphrase"))
+        .get(NEXUS_API_KEY, &conn)
+        .unwrap_err();
+    assert!(error.contains("was CREDENTIALS_KEY changed?"), "{}", error);
+}
+
+#[test]
+fn a_credential_copied_under_another_name_does_not_decrypt() {
+    let server = TestServer::new();
+    let conn = server.state.pool.get().unwrap();
+    let vault = CredentialVault::new(Some(PASSPHRASE));
+    vault.set(NEXUS_API_KEY, "secret", &conn).unwrap();
+
+    let stored = StoredCredential::get_by_name(NEXUS_API_KEY, &conn)
+        .unwrap()
+        .unwrap();
+    StoredCredential::upsert(
+        LOVERSLAB_ACCESS_TOKEN,
+        &stored.nonce,
+        &stored.ciphertext,
+        &conn,
+    )
+    .unwrap();
+    assert!(vault.get(LOVERSLAB_ACCESS_TOKEN, &conn).is_err());
+    assert_eq!(
+        vault.get(NEXUS_API_KEY, &conn).unwrap().as_deref(),
+        Some("secret")
+    );
+}
@@ -0,0 +1,82 @@
+use actix_web::http::{StatusCode, header};
+use actix_web::test;
+use wabba_protocol::api::{ArchiveKind, AvailabilityReport, HashCheckRequest};
+use wabba_protocol::hash::Hash;
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::test_support::{TestServer, place_file};
+
+#[actix_web::test]
+async fn importing_a_downloads_folder_links_archives_in_place() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("Wanted.7z", b"wanted archive"),
+        TestArchive::new("Elsewhere.7z", b"archive nobody has"),
+    ];
+    let [wanted, elsewhere] = &archives;
+    let modlist = ModlistBuilder::new("Imported")
+        .archives(archives.clone())
+        .build();
+    place_file(
+        &server.data_dir().get_modlist_dir(),
+        "Imported.wabbajack",
+        &modlist,
+    );
+    let request = test::TestRequest::post().uri("/bootstrap").to_request();
+    test::call_service(&app, request).await;
+    server.wait_for_jobs().await;
+
+    // An existing Wabbajack install, next to the data directory.
+    let install = server.data_dir().get_path().join("Wabbajack");
+    std::fs::create_dir_all(&install).unwrap();
+    place_file(&install, &wanted.filename, &wanted.contents);
+    place_file(&install, "Wanted.7z.meta", b"[General]\ninstalled=false\n");
+
+    let request = test::TestRequest::post()
+        .uri("/import/downloads")
+        .set_form([("path", install.to_str().unwrap())])
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let id: u64 = location.rsplit('/').next().unwrap().parse().unwrap();
+
+    let plan = server.wait_for_plan(id).await.plan.unwrap();
+    assert_eq!(plan.len(), 1);
+    assert_eq!(plan[0].target, wanted.filename);
+    assert!(
+        plan[0]
+            .detail
+            .as_deref()
+            .is_some_and(|detail| detail.contains("Imported"))
+    );
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/admin/jobs/{}/confirm", id))
+        .to_request();
+    test::call_service(&app, request).await;
+    server.wait_for_jobs().await;
+
+    // The original stays where it was.
+    assert!(install.join(&wanted.filename).exists());
+    let linked = server.data_dir().get_mod_path(&wanted.filename);
+    assert_eq!(Hash::compute_file(&linked).unwrap(), wanted.hash());
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Mod,
+            hashes: vec![wanted.hash(), elsewhere.hash()],
+            sizes: Vec::new(),
+        })
+        .to_request();
+    let report: AvailabilityReport = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(report.available, vec![wanted.hash()]);
+    assert_eq!(report.missing, vec![elsewhere.hash()]);
+}
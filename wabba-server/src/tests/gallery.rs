@@ -0,0 +1,102 @@
+use actix_web::test;
+use wabba_protocol::gallery::ModlistMetadata;
+use wabba_protocol::hash::Hash;
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::db::modlist::Modlist;
+use crate::test_support::TestServer;
+
+#[actix_web::test]
+async fn repository_lists_stored_modlists() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let modlist = ModlistBuilder::new("Gallery Test")
+        .version("1.2.0")
+        .author("Someone")
+        .archive(TestArchive::new("Only.7z", b"only archive"))
+        .build();
+    let hash = Hash::compute(&modlist);
+
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Gallery%20Test.wabbajack")
+        .insert_header(("If-None-Match", hash.clone()))
+        .set_payload(modlist.clone())
+        .to_request();
+    test::call_service(&app, request).await;
+
+    let request = test::TestRequest::get()
+        .uri("/wabbajack/modlists.json")
+        .to_request();
+    let entries: Vec<ModlistMetadata> = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.title, "Gallery Test");
+    assert_eq!(entry.author, "Someone");
+    assert_eq!(entry.version, "1.2.0");
+    assert_eq!(entry.download_metadata.hash, hash);
+    assert_eq!(entry.download_metadata.size, modlist.len() as u64);
+    assert_eq!(entry.download_metadata.number_of_archives, 1);
+    assert!(entry.links.download.ends_with("/download"));
+    assert!(entry.links.machine_url.starts_with("gallery_test_"));
+}
+
+#[actix_web::test]
+async fn share_bundle_queues_the_modlist_and_its_stored_archives() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let stored = TestArchive::new("Stored.7z", b"stored archive");
+    let absent = TestArchive::new("Absent.7z", b"absent archive");
+    let modlist = ModlistBuilder::new("Bundle")
+        .archives([stored.clone(), absent.clone()])
+        .build();
+
+    for (uri, hash, contents) in [
+        (
+            "/submit/modlist/Bundle.wabbajack",
+            Hash::compute(&modlist),
+            modlist.clone(),
+        ),
+        (
+            "/submit/mod/Stored.7z",
+            stored.hash(),
+            stored.contents.clone(),
+        ),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("If-None-Match", hash))
+            .set_payload(contents)
+            .to_request();
+        test::call_service(&app, request).await;
+    }
+    let modlist_id = Modlist::get_all(&server.state.pool.get().unwrap()).unwrap()[0].id;
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/modlists/{}/bundle", modlist_id))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert!(
+        response
+            .headers()
+            .get("Content-Disposition")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("Bundle.bundle.txt"))
+    );
+    let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    assert!(body.contains(&format!(
+        "/modlists/{}/download\n  out=Bundle.wabbajack",
+        modlist_id
+    )));
+    assert!(body.contains("\n  out=Stored.7z\n"));
+    let request = test::TestRequest::get()
+        .uri(
+            body.lines()
+                .find_map(|line| line.find("/mods/by-hash/").map(|start| &line[start..]))
+                .unwrap(),
+        )
+        .to_request();
+    let downloaded = test::call_and_read_body(&app, request).await;
+    assert_eq!(downloaded, stored.contents);
+    assert!(body.contains("#   Absent.7z"));
+    assert!(!body.contains("out=Absent.7z"));
+}
@@ -0,0 +1,314 @@
+use actix_web::test;
+use serde_json::Value;
+use wabba_protocol::api::{ModSummary, ModlistArchive, ModlistSummary, NEXT_CURSOR_HEADER};
+use wabba_protocol::hash::Hash;
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::test_support::TestServer;
+
+#[actix_web::test]
+async fn inventory_lists_modlists_and_their_missing_mods() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("Present.7z", b"present archive"),
+        TestArchive::new("Absent.7z", b"absent archive"),
+    ];
+    let [present, absent] = &archives;
+    let modlist = ModlistBuilder::new("Inventory")
+        .archives(archives.clone())
+        .build();
+
+    for (uri, hash, contents) in [
+        (
+            "/submit/modlist/Inventory.wabbajack",
+            Hash::compute(&modlist),
+            modlist.clone(),
+        ),
+        (
+            "/submit/mod/Present.7z",
+            present.hash(),
+            present.contents.clone(),
+        ),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("If-None-Match", hash))
+            .set_payload(contents)
+            .to_request();
+        test::call_service(&app, request).await;
+    }
+
+    let request = test::TestRequest::get().uri("/api/modlists").to_request();
+    let modlists: Vec<ModlistSummary> = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(modlists.len(), 1);
+    assert_eq!(modlists[0].name, "Inventory");
+    assert_eq!(modlists[0].mods_total, 2);
+    assert_eq!(modlists[0].mods_available, 1);
+
+    let request = test::TestRequest::get()
+        .uri(&format!(
+            "/api/mods?missing=true&modlist={}",
+            modlists[0].id
+        ))
+        .to_request();
+    let mods: Vec<ModSummary> = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(mods.len(), 1);
+    assert_eq!(mods[0].filename, absent.filename);
+    assert_eq!(mods[0].hash, absent.hash());
+    assert!(!mods[0].available);
+
+    let request = test::TestRequest::get().uri("/api/mods").to_request();
+    let mods: Vec<ModSummary> = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(mods.len(), 2);
+
+    // Pages follow the cursor in the header until it runs out.
+    let request = test::TestRequest::get()
+        .uri("/api/mods?limit=1")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    let next = response
+        .headers()
+        .get(NEXT_CURSOR_HEADER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let first: Vec<ModSummary> = test::read_body_json(response).await;
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/mods?limit=1&after={}", next))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert!(!response.headers().contains_key(NEXT_CURSOR_HEADER));
+    let second: Vec<ModSummary> = test::read_body_json(response).await;
+    assert_eq!(
+        first
+            .iter()
+            .chain(&second)
+            .map(|m| m.id)
+            .collect::<Vec<_>>(),
+        mods.iter().map(|m| m.id).collect::<Vec<_>>()
+    );
+
+    let request = test::TestRequest::get()
+        .uri("/api/modlists?after=nonsense")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        actix_web::http::StatusCode::BAD_REQUEST
+    );
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/modlists/{}/archives", modlists[0].id))
+        .to_request();
+    let listed: Vec<ModlistArchive> = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(
+        listed
+            .iter()
+            .map(|archive| (archive.filename.as_str(), archive.hash.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (absent.filename.as_str(), absent.hash()),
+            (present.filename.as_str(), present.hash()),
+        ]
+    );
+    assert!(
+        listed
+            .iter()
+            .all(|archive| archive.state.requires_download())
+    );
+
+    let request = test::TestRequest::get()
+        .uri("/api/modlists/999/archives")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        actix_web::http::StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn meta_files_prefer_the_modlists_own_meta() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let modlist_meta = "[General]\ndirectURL=https://mirror.example.com/Kept.7z\n";
+    let archives = [
+        TestArchive::new("Kept.7z", b"archive with meta").with_meta(modlist_meta),
+        TestArchive::new("Built.7z", b"archive without meta"),
+    ];
+    let modlist = ModlistBuilder::new("Metas")
+        .archives(archives.clone())
+        .build();
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Metas.wabbajack")
+        .insert_header(("If-None-Match", Hash::compute(&modlist)))
+        .set_payload(modlist)
+        .to_request();
+    test::call_service(&app, request).await;
+
+    let request = test::TestRequest::get().uri("/api/modlists").to_request();
+    let modlists: Vec<ModlistSummary> = test::call_and_read_body_json(&app, request).await;
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/mods?modlist={}", modlists[0].id))
+        .to_request();
+    let mods: Vec<ModSummary> = test::call_and_read_body_json(&app, request).await;
+    let meta_of = |filename: &str| {
+        let mod_id = mods.iter().find(|m| m.filename == filename).unwrap().id;
+        format!("/api/modlists/{}/mods/{}/meta", modlists[0].id, mod_id)
+    };
+
+    let request = test::TestRequest::get()
+        .uri(&meta_of("Kept.7z"))
+        .to_request();
+    let body = test::call_and_read_body(&app, request).await;
+    assert_eq!(body, modlist_meta.as_bytes());
+
+    let request = test::TestRequest::get()
+        .uri(&meta_of("Built.7z"))
+        .to_request();
+    let body = test::call_and_read_body(&app, request).await;
+    assert_eq!(
+        body,
+        "[General]\ndirectURL=https://example.com/Built.7z\ninstalled=false\n".as_bytes()
+    );
+}
+
+#[actix_web::test]
+async fn pages_answer_with_json_when_asked() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("Present.7z", b"present archive"),
+        TestArchive::new("Absent.7z", b"absent archive"),
+    ];
+    let [present, absent] = &archives;
+    let modlist = ModlistBuilder::new("Negotiated")
+        .archives(archives.clone())
+        .build();
+    for (uri, hash, contents) in [
+        (
+            "/submit/modlist/Negotiated.wabbajack",
+            Hash::compute(&modlist),
+            modlist.clone(),
+        ),
+        (
+            "/submit/mod/Present.7z",
+            present.hash(),
+            present.contents.clone(),
+        ),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("If-None-Match", hash))
+            .set_payload(contents)
+            .to_request();
+        test::call_service(&app, request).await;
+    }
+    let get_json = |uri: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Accept", "application/json"))
+            .to_request()
+    };
+
+    let modlists: Value = test::call_and_read_body_json(&app, get_json("/")).await;
+    assert_eq!(modlists[0]["name"], "Negotiated");
+    assert_eq!(modlists[0]["mods_total"], 2);
+    assert_eq!(modlists[0]["mods_available"], 1);
+    assert_eq!(modlists[0]["readiness"]["status"], "missing_files");
+
+    // Browsers accept anything at a lower weight and keep getting HTML,
+    // under a different entity tag.
+    let json = test::call_service(&app, get_json("/")).await;
+    let request = test::TestRequest::get()
+        .uri("/")
+        .insert_header((
+            "Accept",
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        ))
+        .to_request();
+    let html = test::call_service(&app, request).await;
+    assert!(
+        html.headers()
+            .get("Content-Type")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+    assert_ne!(html.headers().get("ETag"), json.headers().get("ETag"));
+
+    let details: Value =
+        test::call_and_read_body_json(&app, get_json(&format!("/modlists/{}", modlists[0]["id"])))
+            .await;
+    assert_eq!(details["counts"]["required"], 2);
+    assert_eq!(details["counts"]["missing"], 1);
+    assert_eq!(details["archives"]["archives"].as_array().unwrap().len(), 2);
+    let missing = &details["missing_archives"]["archives"][0];
+    assert_eq!(missing["filename"], absent.filename.as_str());
+    assert_eq!(missing["available"], false);
+
+    let mods: Value = test::call_and_read_body_json(&app, get_json("/mods")).await;
+    assert_eq!(mods["mods"].as_array().unwrap().len(), 2);
+    assert!(mods["next_page"].is_null());
+
+    let present_row = details["archives"]["archives"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|archive| archive["filename"] == present.filename.as_str())
+        .unwrap();
+    let mod_details: Value = test::call_and_read_body_json(
+        &app,
+        get_json(&format!("/mod/{}", present_row["mod"]["id"])),
+    )
+    .await;
+    assert_eq!(mod_details["available"], true);
+    assert_eq!(mod_details["modlists"][0]["modlist"]["name"], "Negotiated");
+    assert_eq!(
+        mod_details["modlists"][0]["association"]["filename"],
+        present.filename.as_str()
+    );
+}
+
+#[actix_web::test]
+async fn admin_and_stats_pages_answer_with_json_when_asked() {
+    let server = TestServer::new();
+    let app = server.app().await;
+
+    for uri in [
+        "/stats",
+        "/admin/jobs",
+        "/admin/diagnostics",
+        "/admin/credentials",
+        "/upload",
+    ] {
+        let request = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Accept", "application/json"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert!(response.status().is_success(), "{}", uri);
+        assert_eq!(
+            response.headers().get("Vary").unwrap(),
+            "Accept, Accept-Language, Cookie",
+            "{}",
+            uri
+        );
+        let body: Value = test::read_body_json(response).await;
+        assert!(body.is_object(), "{}", uri);
+    }
+
+    let request = test::TestRequest::get()
+        .uri("/admin/credentials")
+        .insert_header(("Accept", "application/json"))
+        .to_request();
+    let credentials: Value = test::call_and_read_body_json(&app, request).await;
+    assert!(
+        credentials["credentials"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|credential| credential["updated_at"].is_null())
+    );
+}
@@ -0,0 +1,87 @@
+use actix_web::http::{StatusCode, Uri};
+use actix_web::test;
+use log::LevelFilter;
+
+use crate::slow_log::{mask_sql, mask_uri};
+use crate::test_support::TestServer;
+
+#[actix_web::test]
+async fn log_levels_can_be_raised_for_a_while_and_reset() {
+    let server = TestServer::new();
+    let app = server.app().await;
+
+    let request = test::TestRequest::post()
+        .uri("/admin/diagnostics/logging")
+        .set_form([
+            (
+                "modules",
+                "wabba_server::resources::ingest, wabba_server::web",
+            ),
+            ("level", "debug"),
+            ("minutes", "15"),
+        ])
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+    let active = server.state.logging.active();
+    assert_eq!(
+        active
+            .iter()
+            .map(|o| (o.module.as_str(), o.level))
+            .collect::<Vec<_>>(),
+        vec![
+            ("wabba_server::resources::ingest", LevelFilter::Debug),
+            ("wabba_server::web", LevelFilter::Debug),
+        ]
+    );
+
+    let request = test::TestRequest::get()
+        .uri("/admin/diagnostics")
+        .to_request();
+    let body = test::call_and_read_body(&app, request).await;
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("wabba_server::resources::ingest"));
+
+    let request = test::TestRequest::post()
+        .uri("/admin/diagnostics/logging")
+        .set_form([
+            ("modules", "wabba_server"),
+            ("level", "loud"),
+            ("minutes", "15"),
+        ])
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let request = test::TestRequest::post()
+        .uri("/admin/diagnostics/logging/reset")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+    assert!(server.state.logging.active().is_empty());
+}
+
+#[actix_web::test]
+async fn slow_logs_leave_out_secrets_and_literals() {
+    let uri: Uri = "/auth/callback?code=abc123&state=xyz&return_to=%2Fmodlists"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        mask_uri(&uri),
+        "/auth/callback?code=***&state=***&return_to=%2Fmodlists"
+    );
+    assert_eq!(mask_uri(&"/modlists/3".parse().unwrap()), "/modlists/3");
+
+    assert_eq!(
+        mask_sql(
+            "SELECT id\n    FROM \"mod\"\n    WHERE disk_filename = 'secret.7z' AND size > ?1"
+        ),
+        "SELECT id FROM \"mod\" WHERE disk_filename = '***' AND size > ?1"
+    );
+}
@@ -0,0 +1,91 @@
+use rusqlite::Connection;
+
+use crate::db::migrations::migrations;
+
+/// The last migration before the backfills below, which existing databases
+/// had already run.
+const BEFORE_BACKFILLS: usize = 23;
+
+/// A modlist as an older server stored it, with one archive of the given
+/// source.
+fn insert_modlist(conn: &Connection, id: i64, source: &str, source_type: &str) {
+    conn.execute(
+        "INSERT INTO modlist (id, filename, name, version, size, xxhash64)
+         VALUES (?1, ?2, ?2, '1.0', 1, 'modlist-hash')",
+        rusqlite::params![id, format!("List{}.wabbajack", id)],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO \"mod\" (id, size, xxhash64) VALUES (?1, 1, 'mod-hash')",
+        [id],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO mod_association (modlist_id, mod_id, source, filename, source_type)
+         VALUES (?1, ?1, ?2, 'Archive.7z', ?3)",
+        rusqlite::params![id, source, source_type],
+    )
+    .unwrap();
+}
+
+#[test]
+fn existing_modlists_are_flagged_for_unknown_downloaders() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations()
+        .to_version(&mut conn, BEFORE_BACKFILLS)
+        .unwrap();
+    insert_modlist(
+        &conn,
+        1,
+        r#"{"$type":"SomeNewDownloader, Wabbajack.Lib"}"#,
+        "unknown",
+    );
+    insert_modlist(
+        &conn,
+        2,
+        r#"{"$type":"HttpDownloader, Wabbajack.Lib"}"#,
+        "http",
+    );
+
+    migrations().to_latest(&mut conn).unwrap();
+
+    let flagged: Vec<i64> = conn
+        .prepare("SELECT id FROM modlist WHERE has_unknown_downloaders ORDER BY id")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(flagged, vec![1]);
+}
+
+#[test]
+fn existing_modlists_get_the_game_of_their_nexus_archives() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrations()
+        .to_version(&mut conn, BEFORE_BACKFILLS)
+        .unwrap();
+    insert_modlist(
+        &conn,
+        1,
+        r#"{"$type":"NexusDownloader, Wabbajack.Lib","GameName":"FalloutNewVegas"}"#,
+        "nexus",
+    );
+    insert_modlist(
+        &conn,
+        2,
+        r#"{"$type":"HttpDownloader, Wabbajack.Lib"}"#,
+        "http",
+    );
+
+    migrations().to_latest(&mut conn).unwrap();
+
+    let games: Vec<Option<String>> = conn
+        .prepare("SELECT game_type FROM modlist ORDER BY id")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(games, vec![Some("FalloutNewVegas".to_string()), None]);
+}
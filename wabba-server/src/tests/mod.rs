@@ -0,0 +1,14 @@
+mod backup;
+mod bootstrap;
+mod credentials;
+mod downloads_import;
+mod gallery;
+mod inventory;
+mod logging;
+mod migrations;
+mod notifications;
+mod public_ui;
+mod readiness;
+mod storage_migration;
+mod transfer_stats;
+mod upload;
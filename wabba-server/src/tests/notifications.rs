@@ -0,0 +1,18 @@
+use crate::notifications::sign;
+
+/// Receivers recompute this to check a webhook came from the server:
+/// `sha256=` and the hex HMAC-SHA256, keyed with `NOTIFY_WEBHOOK_SECRET`,
+/// of the timestamp header, a `.`, and the raw body.
+#[test]
+fn signatures_are_the_hmac_of_timestamp_dot_body() {
+    let body = br#"{"text":"Hello, World!"}"#;
+    assert_eq!(
+        sign("It's a Secret to Everybody", 1700000000, body),
+        "sha256=fece4bea7fd5f8da056096ed9a44fa787bbdf6b78a5a9be7c29b9e55ea328d89"
+    );
+    // A replayed body with a new timestamp doesn't carry over the signature.
+    assert_ne!(
+        sign("It's a Secret to Everybody", 1700000001, body),
+        sign("It's a Secret to Everybody", 1700000000, body)
+    );
+}
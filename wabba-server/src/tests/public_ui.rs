@@ -0,0 +1,701 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::StatusCode;
+use actix_web::test;
+use wabba_protocol::archive_state::ArchiveState;
+use wabba_protocol::hash::Hash;
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::config::OidcConfig;
+use crate::db::mod_association::ModAssociation;
+use crate::db::mod_data::Mod;
+use crate::db::modlist::Modlist;
+use crate::test_support::TestServer;
+use crate::web::i18n::{LANGUAGES, Lang};
+
+#[actix_web::test]
+async fn public_ui_is_read_only() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let public = server.public_app().await;
+    let archive = TestArchive::new("Core.7z", b"core archive");
+    let modlist = ModlistBuilder::new("Shared")
+        .archives([archive.clone()])
+        .build();
+
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Shared.wabbajack")
+        .insert_header(("If-None-Match", Hash::compute(&modlist)))
+        .set_payload(modlist.clone())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let modlist_id = Modlist::get_all(&server.state.pool.get().unwrap()).unwrap()[0].id;
+
+    let request = test::TestRequest::get().uri("/").to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(body.contains("Shared"));
+    assert!(!body.contains("/bootstrap"));
+    assert!(!body.contains("/upload"));
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/modlists/{}", modlist_id))
+        .to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(!body.contains("method=\"post\""));
+
+    for uri in [
+        "/bootstrap".to_string(),
+        format!("/modlists/{}/toggle-muted", modlist_id),
+        "/submit/mod/Core.7z".to_string(),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(("If-None-Match", archive.hash()))
+            .set_payload(archive.contents.clone())
+            .to_request();
+        assert_eq!(
+            test::call_service(&public, request).await.status(),
+            StatusCode::NOT_FOUND,
+            "{} should not be served publicly",
+            uri
+        );
+    }
+}
+
+#[actix_web::test]
+async fn public_ui_does_not_serve_files() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let public = server.public_app().await;
+    let archive = TestArchive::new("Core.7z", b"core archive");
+    let modlist = ModlistBuilder::new("Shared")
+        .archives([archive.clone()])
+        .build();
+
+    for (uri, payload, hash) in [
+        (
+            "/submit/modlist/Shared.wabbajack",
+            modlist.clone(),
+            Hash::compute(&modlist),
+        ),
+        (
+            "/submit/mod/Core.7z",
+            archive.contents.clone(),
+            archive.hash(),
+        ),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("If-None-Match", hash))
+            .set_payload(payload)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    let conn = server.state.pool.get().unwrap();
+    let modlist_id = Modlist::get_all(&conn).unwrap()[0].id;
+    let mod_id = Mod::get_all(&conn).unwrap()[0].id;
+
+    let url_hash = archive
+        .hash()
+        .replace('+', "-")
+        .replace('/', "_")
+        .trim_end_matches('=')
+        .to_string();
+    let uris = [
+        format!("/mod/{}/download", mod_id),
+        format!("/mods/by-hash/{}/download", url_hash),
+        format!("/modlists/{}/download", modlist_id),
+        format!("/modlists/{}/bundle", modlist_id),
+        format!("/modlists/{}/wabbajack.metadata", modlist_id),
+        "/wabbajack/modlists.json".to_string(),
+    ];
+    for uri in &uris {
+        let request = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(
+            test::call_service(&public, request).await.status(),
+            StatusCode::NOT_FOUND,
+            "{} should not be served publicly",
+            uri
+        );
+        let request = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK,
+            "{} should still be served on the admin listener",
+            uri
+        );
+    }
+
+    for uri in ["/".to_string(), format!("/modlists/{}", modlist_id)] {
+        let request = test::TestRequest::get().uri(&uri).to_request();
+        let body =
+            String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+        assert!(!body.contains("/download\""), "{} links a download", uri);
+        assert!(!body.contains("/bundle\""), "{} links a bundle", uri);
+    }
+}
+
+pub(super) const API_TOKEN: &str = "fedcba9876543210fedcba9876543210";
+
+pub(super) fn oidc_config() -> OidcConfig {
+    OidcConfig {
+        issuer_url: "https://auth.example.com".to_string(),
+        client_id: "wabba".to_string(),
+        client_secret: "secret".to_string(),
+        redirect_url: "https://wabba.example.com/auth/callback".to_string(),
+        groups_claim: "groups".to_string(),
+        admin_groups: vec!["admins".to_string()],
+        viewer_groups: Vec::new(),
+        session_secret: "0123456789abcdef0123456789abcdef".to_string(),
+        api_token: Some(API_TOKEN.to_string()),
+    }
+}
+
+#[actix_web::test]
+async fn viewers_without_an_admin_session_get_the_read_only_ui() {
+    let mut server = TestServer::new();
+    server.state.oidc = Some(oidc_config());
+    let app = server.app().await;
+
+    let request = test::TestRequest::get().uri("/").to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(!body.contains("/bootstrap"));
+
+    let request = test::TestRequest::post().uri("/bootstrap").to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    let archive = TestArchive::new("Core.7z", b"core archive");
+    for (token, status) in [
+        (None, StatusCode::FORBIDDEN),
+        (Some(API_TOKEN), StatusCode::OK),
+    ] {
+        let mut request = test::TestRequest::post()
+            .uri("/submit/mod/Core.7z")
+            .insert_header(("If-None-Match", archive.hash()))
+            .set_payload(archive.contents.clone());
+        if let Some(token) = token {
+            request = request.insert_header(("Authorization", format!("Bearer {}", token)));
+        }
+        assert_eq!(
+            test::call_service(&app, request.to_request())
+                .await
+                .status(),
+            status,
+            "uploading with token {:?}",
+            token
+        );
+    }
+}
+
+#[actix_web::test]
+async fn only_wabbajack_routes_skip_sign_in() {
+    let mut server = TestServer::new();
+    server.state.oidc = Some(oidc_config());
+    let app = server.app_with_login().await;
+    let archive = TestArchive::new("x", b"anonymous upload");
+
+    for (token, status) in [
+        (None, StatusCode::UNAUTHORIZED),
+        (Some("not the token"), StatusCode::UNAUTHORIZED),
+        (Some(API_TOKEN), StatusCode::OK),
+    ] {
+        let mut request = test::TestRequest::post()
+            .uri("/submit/mod/x")
+            .insert_header(("If-None-Match", archive.hash()))
+            .set_payload(archive.contents.clone());
+        if let Some(token) = token {
+            request = request.insert_header(("Authorization", format!("Bearer {}", token)));
+        }
+        assert_eq!(
+            test::call_service(&app, request.to_request())
+                .await
+                .status(),
+            status,
+            "uploading with token {:?}",
+            token
+        );
+    }
+
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(serde_json::json!({"kind": "mod", "hashes": [archive.hash()]}))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    for uri in ["/api/mods", "/wabbajack/modlists.json", "/res/styles.css"] {
+        let request = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK,
+            "{} should stay open",
+            uri
+        );
+    }
+
+    let request = test::TestRequest::get().uri("/api/jobs").to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::FOUND
+    );
+}
+
+#[actix_web::test]
+async fn modlist_listing_shows_and_filters_by_game_and_author() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    for (filename, modlist) in [
+        (
+            "NorthernLights.wabbajack",
+            ModlistBuilder::new("Northern Lights")
+                .author("Alice")
+                .game_type("SkyrimSpecialEdition"),
+        ),
+        (
+            "WastelandNights.wabbajack",
+            ModlistBuilder::new("Wasteland Nights")
+                .author("Bob")
+                .game_type("FalloutNewVegas"),
+        ),
+    ] {
+        let contents = modlist.build();
+        let request = test::TestRequest::post()
+            .uri(&format!("/submit/modlist/{}", filename))
+            .insert_header(("If-None-Match", Hash::compute(&contents)))
+            .set_payload(contents)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    let request = test::TestRequest::get().uri("/").to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("Alice"));
+    assert!(body.contains("FalloutNewVegas"));
+
+    let request = test::TestRequest::get()
+        .uri("/?game=FalloutNewVegas")
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("Wasteland Nights"));
+    assert!(!body.contains("Northern Lights"));
+
+    let request = test::TestRequest::get()
+        .uri("/?author=Alice&added_after=2000-01-01")
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("Northern Lights"));
+    assert!(!body.contains("Wasteland Nights"));
+}
+
+#[actix_web::test]
+async fn modlist_listing_has_a_grid_view() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let modlist = ModlistBuilder::new("Shared").build();
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Shared.wabbajack")
+        .insert_header(("If-None-Match", Hash::compute(&modlist)))
+        .set_payload(modlist)
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let modlist_id = Modlist::get_all(&server.state.pool.get().unwrap()).unwrap()[0].id;
+
+    let request = test::TestRequest::get().uri("/?view=grid").to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("modlist-card"));
+    assert!(body.contains(&format!("/modlists/{}/image", modlist_id)));
+    assert!(body.contains(&format!("/modlists/{}/download", modlist_id)));
+    assert!(!body.contains("modlist-table"));
+
+    let request = test::TestRequest::get().uri("/").to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("modlist-table"));
+    assert!(body.contains(&format!("/modlists/{}/download", modlist_id)));
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/modlists/{}/download", modlist_id))
+        .insert_header(("Range", "bytes=0-1"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(test::read_body(response).await, b"PK"[..]);
+}
+
+#[actix_web::test]
+async fn modlist_details_pages_large_archive_tables() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = (0..205).map(|i| {
+        TestArchive::new(
+            &format!("Archive{:03}.7z", i),
+            format!("archive {}", i).as_bytes(),
+        )
+    });
+    let modlist = ModlistBuilder::new("Huge").archives(archives).build();
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Huge.wabbajack")
+        .insert_header(("If-None-Match", Hash::compute(&modlist)))
+        .set_payload(modlist)
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let modlist_id = Modlist::get_all(&server.state.pool.get().unwrap()).unwrap()[0].id;
+
+    // Everything is missing, yet the missing section is still shown
+    let request = test::TestRequest::get()
+        .uri(&format!("/modlists/{}", modlist_id))
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("Missing Mods"));
+    assert!(body.contains("205 required mods have no file on disk."));
+    assert!(body.contains("Archive199.7z"));
+    assert!(!body.contains("Archive200.7z"));
+    assert!(body.contains("Page 1 of 2"));
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/modlists/{}?missing_page=2", modlist_id))
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    // Two tables: the missing one on its last page, the full one on its first
+    assert_eq!(body.matches("Archive204.7z").count(), 1);
+    assert_eq!(body.matches("Archive000.7z").count(), 1);
+    assert!(body.contains("Page 2 of 2"));
+}
+
+#[actix_web::test]
+async fn mod_details_lists_other_versions_with_the_same_name() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let skyui = |filename: &str, contents: &[u8], file_id: u64| {
+        TestArchive::new(filename, contents).with_state(ArchiveState::NexusDownloader {
+            author: None,
+            description: String::new(),
+            file_id,
+            game_name: "SkyrimSpecialEdition".to_string(),
+            image_url: None,
+            is_nsfw: false,
+            mod_id: 12604,
+            name: "SkyUI".to_string(),
+            version: file_id.to_string(),
+        })
+    };
+    for (name, archive) in [
+        ("Old", skyui("SkyUI_5_1.7z", b"skyui 5.1", 1)),
+        ("New", skyui("SkyUI_5_2.7z", b"skyui 5.2", 2)),
+    ] {
+        let modlist = ModlistBuilder::new(name).archives([archive]).build();
+        let request = test::TestRequest::post()
+            .uri(&format!("/submit/modlist/{}.wabbajack", name))
+            .insert_header(("If-None-Match", Hash::compute(&modlist)))
+            .set_payload(modlist)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    let conn = server.state.pool.get().unwrap();
+    let mut ids: Vec<(String, u64, u64)> = Modlist::get_all(&conn)
+        .unwrap()
+        .into_iter()
+        .flat_map(|modlist| ModAssociation::get_by_modlist_id(modlist.id, &conn).unwrap())
+        .map(|assoc| (assoc.filename, assoc.mod_id, assoc.modlist_id))
+        .collect();
+    ids.sort();
+    let [(_, old_id, old_modlist_id), (_, new_id, _)] = ids[..] else {
+        panic!("expected two archives, got {:?}", ids);
+    };
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/mod/{}", old_id))
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains(&format!("href=\"/mod/{}\"", new_id)));
+    assert!(body.contains("SkyUI_5_2.7z"));
+
+    assert!(body.contains("← Back to Modlists"));
+
+    // Arriving from a modlist links back to it and highlights its row
+    let request = test::TestRequest::get()
+        .uri(&format!("/mod/{}?from={}", old_id, old_modlist_id))
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("← Back to Old"));
+    assert!(body.contains("current-modlist"));
+}
+
+#[actix_web::test]
+async fn public_pages_follow_the_browser_language() {
+    let server = TestServer::new();
+    let public = server.public_app().await;
+
+    let request = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("Accept-Language", "de-DE,de;q=0.9,en;q=0.5"))
+        .to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(body.contains("lang=\"de\""));
+    assert!(body.contains("Alle Mods anzeigen"));
+
+    let request = test::TestRequest::get()
+        .uri("/stats")
+        .insert_header(("Accept-Language", "de-DE,de;q=0.9,en;q=0.5"))
+        .to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(body.contains("lang=\"de\""));
+    assert!(body.contains("Zusammenfassungen"));
+    assert!(body.contains("Es wurden noch keine Zusammenfassungen erstellt."));
+
+    let request = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("Accept-Language", "ja, fr;q=0.8"))
+        .to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(body.contains("Voir tous les mods"));
+
+    let request = test::TestRequest::get()
+        .uri("/language/es")
+        .insert_header(("Referer", "https://elsewhere.example.com/mods?missing=true"))
+        .to_request();
+    let response = test::call_service(&public, request).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        "/mods?missing=true"
+    );
+
+    let request = test::TestRequest::get().uri("/language/xx").to_request();
+    assert_eq!(
+        test::call_service(&public, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn every_translated_message_exists_in_english() {
+    let english: Vec<&str> = Lang::English.catalog().iter().map(|(id, _)| *id).collect();
+    for lang in LANGUAGES {
+        for (id, _) in lang.catalog() {
+            assert!(
+                english.contains(id),
+                "{} has unknown message {}",
+                lang.code(),
+                id
+            );
+        }
+    }
+}
+
+#[actix_web::test]
+async fn deleting_a_modlist_takes_typing_its_name() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let public = server.public_app().await;
+    let modlist = ModlistBuilder::new("Shared").build();
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Shared.wabbajack")
+        .insert_header(("If-None-Match", Hash::compute(&modlist)))
+        .set_payload(modlist)
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let modlist_id = Modlist::get_all(&server.state.pool.get().unwrap()).unwrap()[0].id;
+    let uri = format!("/modlists/{}/delete", modlist_id);
+
+    // A page of its own without JavaScript, a dialog for htmx.
+    let request = test::TestRequest::get().uri(&uri).to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("data-confirm-text=\"Shared\""));
+    assert!(!body.contains("<dialog"));
+    let request = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(("HX-Request", "true"))
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.starts_with("<dialog"));
+
+    let request = test::TestRequest::get().uri(&uri).to_request();
+    assert_eq!(
+        test::call_service(&public, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let request = test::TestRequest::post()
+        .uri(&uri)
+        .set_form([("confirm", "Shard")])
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        Modlist::get_all(&server.state.pool.get().unwrap())
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let request = test::TestRequest::post()
+        .uri(&uri)
+        .set_form([("confirm", "Shared")])
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+    assert!(
+        Modlist::get_all(&server.state.pool.get().unwrap())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[actix_web::test]
+async fn listings_remember_filters_and_columns_in_the_session() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let public = server.public_app_with_session().await;
+    let modlist = ModlistBuilder::new("Shared")
+        .archives([TestArchive::new("Core.7z", b"core archive")])
+        .build();
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Shared.wabbajack")
+        .insert_header(("If-None-Match", Hash::compute(&modlist)))
+        .set_payload(modlist)
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let session_cookie = |response: &ServiceResponse| {
+        response
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "id")
+            .map(|cookie| cookie.into_owned())
+            .expect("the session is saved")
+    };
+
+    let request = test::TestRequest::get()
+        .uri("/mods?filter=unavailable&source=")
+        .to_request();
+    let response = test::call_service(&public, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response);
+
+    // A bare visit goes back to the filters used last.
+    let request = test::TestRequest::get()
+        .uri("/mods")
+        .cookie(cookie.clone())
+        .to_request();
+    let response = test::call_service(&public, request).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        "/mods?filter=unavailable"
+    );
+
+    let request = test::TestRequest::post()
+        .uri("/preferences/mods/columns")
+        .cookie(cookie)
+        .set_form([("show", "col-name"), ("show", "col-size")])
+        .to_request();
+    let response = test::call_service(&public, request).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let cookie = session_cookie(&response);
+
+    let request = test::TestRequest::get()
+        .uri("/mods?reset")
+        .cookie(cookie)
+        .to_request();
+    let response = test::call_service(&public, request).await;
+    let cookie = session_cookie(&response);
+    let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    assert!(body.contains("Core.7z"));
+    assert!(body.contains("<th>Size</th>"));
+    assert!(!body.contains("<th>Hash</th>"));
+    // The table is streamed after the rest of the page; all of it arrives.
+    assert!(body.trim_end().ends_with("</html>"));
+    assert!(!body.contains("<!--wabba-"));
+    let request = test::TestRequest::get()
+        .uri("/mods")
+        .cookie(cookie)
+        .to_request();
+    assert_eq!(
+        test::call_service(&public, request).await.status(),
+        StatusCode::OK
+    );
+
+    let request = test::TestRequest::get().uri("/?sort=-added").to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(body.contains("Added ▼"));
+    assert!(body.contains("href=\"/?sort=name\""));
+}
+
+#[actix_web::test]
+async fn shortened_hashes_keep_the_full_value_to_copy() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let public = server.public_app().await;
+    let modlist = ModlistBuilder::new("Shared")
+        .archives([TestArchive::new("Core.7z", b"core archive")])
+        .build();
+
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Shared.wabbajack")
+        .insert_header(("If-None-Match", Hash::compute(&modlist)))
+        .set_payload(modlist)
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let modlist = Modlist::get_all(&server.state.pool.get().unwrap())
+        .unwrap()
+        .remove(0);
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/modlists/{}", modlist.id))
+        .to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(body.contains(&format!("data-copy=\"{}\"", modlist.xxhash64)));
+    assert!(body.contains("/res/copy-hash.js"));
+
+    let request = test::TestRequest::get()
+        .uri("/res/copy-hash.js")
+        .to_request();
+    assert_eq!(
+        test::call_service(&public, request).await.status(),
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,213 @@
+use actix_web::http::StatusCode;
+use actix_web::test;
+use wabba_protocol::archive_state::ArchiveState;
+use wabba_protocol::hash::Hash;
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::db::mod_association::ModAssociation;
+use crate::db::modlist::Modlist;
+use crate::db::modlist_counts::ModlistCounters;
+use crate::db::readiness::{ModlistCounts, ModlistReadiness, ModlistStatus, ReadinessRule};
+use crate::test_support::TestServer;
+
+#[actix_web::test]
+async fn ignoring_nsfw_archives_makes_modlist_ready() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("Core.7z", b"core archive"),
+        TestArchive::new("Spicy.7z", b"spicy archive").with_state(ArchiveState::NexusDownloader {
+            author: None,
+            description: String::new(),
+            file_id: 1,
+            game_name: "SkyrimSpecialEdition".to_string(),
+            image_url: None,
+            is_nsfw: true,
+            mod_id: 1,
+            name: "Spicy".to_string(),
+            version: "1.0".to_string(),
+        }),
+    ];
+    let [core, _] = &archives;
+    let modlist = ModlistBuilder::new("Rules")
+        .archives(archives.clone())
+        .build();
+
+    for (uri, hash, contents) in [
+        (
+            "/submit/modlist/Rules.wabbajack",
+            Hash::compute(&modlist),
+            modlist.clone(),
+        ),
+        ("/submit/mod/Core.7z", core.hash(), core.contents.clone()),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("If-None-Match", hash))
+            .set_payload(contents)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    let conn = server.state.pool.get().unwrap();
+    let modlist_id = Modlist::get_all(&conn).unwrap()[0].id;
+    let rule = ReadinessRule::get_by_modlist_id(modlist_id, &conn).unwrap();
+    let readiness = ModlistReadiness::compute(&rule, &conn).unwrap();
+    assert_eq!(readiness.status, ModlistStatus::MissingFiles);
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/modlists/{}/readiness", modlist_id))
+        .set_form([("min_available_percent", "100"), ("ignore_nsfw", "true")])
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+
+    let rule = ReadinessRule::get_by_modlist_id(modlist_id, &conn).unwrap();
+    assert!(rule.ignore_nsfw);
+    let readiness = ModlistReadiness::compute(&rule, &conn).unwrap();
+    assert_eq!(readiness.counted, 1);
+    assert_eq!(readiness.ignored, 1);
+    assert_eq!(readiness.status, ModlistStatus::Ready);
+
+    assert!(ModlistCounters::find_mismatches(&conn).unwrap().is_empty());
+    let counts = &ModlistCounts::get_all(&conn).unwrap()[&modlist_id];
+    assert_eq!((counts.mods_available, counts.mods_total), (1, 2));
+    assert_eq!(counts.readiness.ignored, 1);
+    assert_eq!(counts.readiness.status, ModlistStatus::Ready);
+}
+
+#[actix_web::test]
+async fn optional_archives_are_left_out_of_availability() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("Core.7z", b"core archive"),
+        TestArchive::new("Alternate Textures.7z", b"alternate textures"),
+    ];
+    let [core, alternate] = &archives;
+    let modlist = ModlistBuilder::new("Options")
+        .archives(archives.clone())
+        .build();
+
+    for (uri, hash, contents) in [
+        (
+            "/submit/modlist/Options.wabbajack",
+            Hash::compute(&modlist),
+            modlist.clone(),
+        ),
+        ("/submit/mod/Core.7z", core.hash(), core.contents.clone()),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("If-None-Match", hash))
+            .set_payload(contents)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    let conn = server.state.pool.get().unwrap();
+    let modlist = Modlist::get_all(&conn).unwrap().remove(0);
+    let alternate_id = ModAssociation::get_by_modlist_id(modlist.id, &conn)
+        .unwrap()
+        .into_iter()
+        .find(|assoc| assoc.filename == alternate.filename)
+        .unwrap()
+        .mod_id;
+
+    let request = test::TestRequest::post()
+        .uri(&format!(
+            "/modlists/{}/mods/{}/toggle-optional",
+            modlist.id, alternate_id
+        ))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+
+    let counters = ModlistCounters::get(modlist.id, &conn).unwrap();
+    assert_eq!((counters.mods_available, counters.mods_total), (1, 1));
+    assert!(ModlistCounters::find_mismatches(&conn).unwrap().is_empty());
+    let counts = &ModlistCounts::get_all(&conn).unwrap()[&modlist.id];
+    assert_eq!((counts.mods_available, counts.mods_total), (1, 1));
+    assert_eq!(counts.readiness.status, ModlistStatus::Ready);
+    let rule = ReadinessRule::get_by_modlist_id(modlist.id, &conn).unwrap();
+    let readiness = ModlistReadiness::compute(&rule, &conn).unwrap();
+    assert_eq!(readiness.status, ModlistStatus::Ready);
+}
+
+#[actix_web::test]
+async fn stored_counters_follow_availability_changes() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("Core.7z", b"core archive"),
+        TestArchive::new("Gone.7z", b"gone archive"),
+    ];
+    let [core, _] = &archives;
+    let modlist = ModlistBuilder::new("Counted")
+        .archives(archives.clone())
+        .build();
+    for (uri, hash, contents) in [
+        (
+            "/submit/modlist/Counted.wabbajack",
+            Hash::compute(&modlist),
+            modlist.clone(),
+        ),
+        ("/submit/mod/Core.7z", core.hash(), core.contents.clone()),
+    ] {
+        let request = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("If-None-Match", hash))
+            .set_payload(contents)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    let conn = server.state.pool.get().unwrap();
+    let modlist_id = Modlist::get_all(&conn).unwrap()[0].id;
+    let gone_id = ModAssociation::get_by_modlist_id(modlist_id, &conn)
+        .unwrap()
+        .into_iter()
+        .find(|assoc| assoc.filename == "Gone.7z")
+        .unwrap()
+        .mod_id;
+    let request = test::TestRequest::post()
+        .uri(&format!("/mod/{}/toggle-lost-forever", gone_id))
+        .to_request();
+    test::call_service(&app, request).await;
+
+    let counters = ModlistCounters::get_all(&conn).unwrap()[&modlist_id];
+    assert_eq!(
+        (
+            counters.mods_available,
+            counters.mods_total,
+            counters.lost_forever
+        ),
+        (1, 2, 1)
+    );
+    assert!(ModlistCounters::find_mismatches(&conn).unwrap().is_empty());
+
+    conn.execute("UPDATE modlist_counts SET mods_total = 7", [])
+        .unwrap();
+    assert_eq!(ModlistCounters::find_mismatches(&conn).unwrap().len(), 1);
+    let request = test::TestRequest::post()
+        .uri("/admin/diagnostics/counts/rebuild")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::SEE_OTHER
+    );
+    assert!(ModlistCounters::find_mismatches(&conn).unwrap().is_empty());
+}
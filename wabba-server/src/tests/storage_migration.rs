@@ -0,0 +1,69 @@
+use actix_web::http::{StatusCode, header};
+use actix_web::test;
+use wabba_protocol::api::{ArchiveKind, AvailabilityReport, HashCheckRequest};
+use wabba_protocol::test_util::TestArchive;
+
+use crate::test_support::{TestServer, place_file};
+
+#[actix_web::test]
+async fn flat_migration_moves_mods_out_of_subfolders_once_confirmed() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let nested = TestArchive::new("Nested.7z", b"nested archive");
+    let subfolder = server.data_dir().get_mod_dir().join("Extras");
+    std::fs::create_dir_all(&subfolder).unwrap();
+    place_file(&subfolder, &nested.filename, &nested.contents);
+
+    let request = test::TestRequest::post().uri("/bootstrap/mods").to_request();
+    test::call_service(&app, request).await;
+    server.wait_for_jobs().await;
+
+    let request = test::TestRequest::post()
+        .uri("/storage/migrate")
+        .set_form([("target", "flat"), ("verbose", "true")])
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let id: u64 = location.rsplit('/').next().unwrap().parse().unwrap();
+
+    let job = server.wait_for_plan(id).await;
+    let plan = job.plan.unwrap();
+    assert_eq!(plan.len(), 1);
+    assert_eq!(plan[0].target, "Extras/Nested.7z");
+    // Nothing moves until the plan is confirmed.
+    assert!(subfolder.join(&nested.filename).exists());
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/admin/jobs/{}/confirm", id))
+        .to_request();
+    test::call_service(&app, request).await;
+    server.wait_for_jobs().await;
+
+    assert!(!subfolder.join(&nested.filename).exists());
+    assert!(server.data_dir().get_mod_path(&nested.filename).exists());
+    let executed = server
+        .state
+        .jobs
+        .list()
+        .into_iter()
+        .find(|job| job.confirmed_from == Some(id))
+        .unwrap();
+    assert!(executed.log.contains(&"move Extras/Nested.7z".to_string()));
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Mod,
+            hashes: vec![nested.hash()],
+            sizes: Vec::new(),
+        })
+        .to_request();
+    let report: AvailabilityReport = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(report.available, vec![nested.hash()]);
+}
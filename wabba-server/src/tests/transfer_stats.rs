@@ -0,0 +1,93 @@
+use actix_web::http::StatusCode;
+use actix_web::test;
+use wabba_protocol::api::CLIENT_HEADER;
+use wabba_protocol::test_util::TestArchive;
+
+use super::public_ui::{API_TOKEN, oidc_config};
+use crate::db::transfer_stats::DailyTransfer;
+use crate::test_support::TestServer;
+
+#[actix_web::test]
+async fn transfers_are_summed_per_token_client_and_day() {
+    let mut server = TestServer::new();
+    server.state.oidc = Some(oidc_config());
+    let app = server.app_with_login().await;
+    let archive = TestArchive::new("Tracked.7z", b"tracked archive contents");
+    let bearer = ("Authorization", format!("Bearer {}", API_TOKEN));
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Tracked.7z")
+        .insert_header(("If-None-Match", archive.hash()))
+        .insert_header(bearer.clone())
+        .insert_header((CLIENT_HEADER, "desktop"))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    for _ in 0..2 {
+        let request = test::TestRequest::get()
+            .uri("/mod/1/download")
+            .insert_header(bearer.clone())
+            .to_request();
+        let body = test::call_and_read_body(&app, request).await;
+        assert_eq!(body, archive.contents);
+    }
+
+    let transfers = DailyTransfer::get_recent(1, &server.state.pool.get().unwrap()).unwrap();
+    let of = |client: &str| {
+        transfers
+            .iter()
+            .find(|transfer| transfer.client == client)
+            .unwrap_or_else(|| panic!("no transfers for {}", client))
+    };
+    assert_eq!(of("API token (desktop)").uploaded_bytes, archive.size());
+    assert_eq!(of("API token").uploaded_bytes, 0);
+    assert_eq!(of("API token").downloaded_bytes, 2 * archive.size());
+
+    let request = test::TestRequest::get()
+        .uri("/admin/diagnostics")
+        .insert_header(bearer)
+        .to_request();
+    let body = test::call_and_read_body(&app, request).await;
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("API token (desktop)"));
+}
+
+#[actix_web::test]
+async fn client_names_need_the_token_and_turned_away_requests_count_for_nothing() {
+    let mut server = TestServer::new();
+    server.state.oidc = Some(oidc_config());
+    let app = server.app_with_login().await;
+    let archive = TestArchive::new("Spoofed.7z", b"anonymous upload");
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Spoofed.7z")
+        .insert_header(("If-None-Match", archive.hash()))
+        .insert_header((CLIENT_HEADER, "someone else"))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let conn = server.state.pool.get().unwrap();
+    assert!(DailyTransfer::get_recent(1, &conn).unwrap().is_empty());
+
+    // Wabbajack's open routes count by address, whatever the header says.
+    let request = test::TestRequest::get()
+        .uri("/api/mods")
+        .insert_header((CLIENT_HEADER, "someone else"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let clients: Vec<_> = DailyTransfer::get_recent(1, &conn)
+        .unwrap()
+        .into_iter()
+        .map(|transfer| transfer.client)
+        .collect();
+    assert_eq!(clients, vec!["unknown"]);
+}
@@ -0,0 +1,465 @@
+use actix_web::http::{StatusCode, header};
+use actix_web::test;
+use base64::prelude::*;
+use wabba_protocol::api::{
+    ArchiveKind, AvailabilityReport, CONTENT_HASH_HEADER, HashCheckRequest, UploadResult,
+};
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::db::modlist::Modlist;
+use crate::jobs::JobKind;
+use crate::resources::resumable_upload::{MAX_IDLE, expire_uploads_job};
+use crate::test_support::TestServer;
+
+#[actix_web::test]
+async fn uploaded_mod_is_stored_and_reported_available() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archive = TestArchive::new("Some Mod-1-0.7z", b"some mod contents");
+
+    let request = test::TestRequest::get()
+        .uri("/check/mod")
+        .insert_header(("If-None-Match", archive.hash()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Some%20Mod-1-0.7z")
+        .insert_header(("If-None-Match", archive.hash()))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    let result: UploadResult = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(result.kind, ArchiveKind::Mod);
+    assert_eq!(result.filename, archive.filename);
+    assert_eq!(result.hash, archive.hash());
+    assert_eq!(result.size, archive.size());
+    assert_eq!(
+        std::fs::read(server.data_dir().get_mod_path(&archive.filename)).unwrap(),
+        archive.contents
+    );
+
+    let request = test::TestRequest::get()
+        .uri("/check/mod")
+        .insert_header(("If-None-Match", archive.hash()))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::NOT_MODIFIED
+    );
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Some%20Mod-1-0.7z")
+        .insert_header(("If-None-Match", archive.hash()))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::NOT_MODIFIED
+    );
+}
+
+#[actix_web::test]
+async fn upload_with_wrong_hash_is_rejected() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archive = TestArchive::new("Some Mod.7z", b"some mod contents");
+    let other = TestArchive::new("Other Mod.7z", b"other contents");
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Some%20Mod.7z")
+        .insert_header(("If-None-Match", other.hash()))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert!(!server.data_dir().get_mod_path(&archive.filename).exists());
+}
+
+#[actix_web::test]
+async fn modlist_upload_registers_its_archives() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archives = [
+        TestArchive::new("First.7z", b"first archive"),
+        TestArchive::new("Second.zip", b"second archive"),
+    ];
+    let [first, second] = &archives;
+    let modlist = ModlistBuilder::new("Test List")
+        .archives(archives.clone())
+        .build();
+    let modlist_hash = wabba_protocol::hash::Hash::compute(&modlist);
+
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Test%20List.wabbajack")
+        .insert_header(("If-None-Match", modlist_hash.clone()))
+        .set_payload(modlist)
+        .to_request();
+    let result: UploadResult = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(result.kind, ArchiveKind::Modlist);
+    assert_eq!(result.filename, "Test List.wabbajack");
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/First.7z")
+        .insert_header(("If-None-Match", first.hash()))
+        .set_payload(first.contents.clone())
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Mod,
+            hashes: vec![first.hash(), second.hash()],
+            sizes: Vec::new(),
+        })
+        .to_request();
+    let report: AvailabilityReport = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(report.available, vec![first.hash()]);
+    assert_eq!(report.missing, vec![second.hash()]);
+
+    // With sizes, a stored file only counts if its size matches too.
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Mod,
+            hashes: vec![first.hash(), first.hash()],
+            sizes: vec![first.contents.len() as u64, first.contents.len() as u64 + 1],
+        })
+        .to_request();
+    let report: AvailabilityReport = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(report.available, vec![first.hash()]);
+    assert_eq!(report.missing, vec![first.hash()]);
+
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Mod,
+            hashes: vec![first.hash(), second.hash()],
+            sizes: vec![first.contents.len() as u64],
+        })
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let request = test::TestRequest::post()
+        .uri("/check")
+        .set_json(HashCheckRequest {
+            kind: ArchiveKind::Modlist,
+            hashes: vec![modlist_hash.clone()],
+            sizes: Vec::new(),
+        })
+        .to_request();
+    let report: AvailabilityReport = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(report.available, vec![modlist_hash]);
+}
+
+#[actix_web::test]
+async fn unparseable_modlist_is_kept_for_diagnostics() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let contents = b"not a zip file".to_vec();
+    let hash = wabba_protocol::hash::Hash::compute(&contents);
+
+    let request = test::TestRequest::post()
+        .uri("/submit/modlist/Broken.wabbajack")
+        .insert_header(("If-None-Match", hash))
+        .set_payload(contents)
+        .to_request();
+    let result: UploadResult = test::call_and_read_body_json(&app, request).await;
+    assert!(
+        server
+            .data_dir()
+            .get_modlist_path(&result.filename)
+            .exists()
+    );
+
+    let conn = server.state.pool.get().unwrap();
+    let failed = Modlist::get_parse_failed(&conn).unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].filename, "Broken.wabbajack");
+    assert!(failed[0].parse_error.is_some());
+    assert!(Modlist::get_all(&conn).unwrap().is_empty());
+
+    // Only the admin UI links the banner to the diagnostics page.
+    let public = server.public_app().await;
+    let uri = format!("/modlists/{}", failed[0].id);
+    let request = test::TestRequest::get().uri(&uri).to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+    assert!(body.contains("href=\"/admin/diagnostics\""));
+    let request = test::TestRequest::get().uri(&uri).to_request();
+    let body =
+        String::from_utf8(test::call_and_read_body(&public, request).await.to_vec()).unwrap();
+    assert!(body.contains("Parse failed"));
+    assert!(!body.contains("/admin/diagnostics"));
+}
+
+#[actix_web::test]
+async fn uploaded_mod_can_be_downloaded_by_hash() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archive = TestArchive::new("Fetch Me.7z", b"fetch me");
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Fetch%20Me.7z")
+        .insert_header(("If-None-Match", archive.hash()))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    test::call_service(&app, request).await;
+
+    let hash = archive
+        .hash()
+        .replace('+', "-")
+        .replace('/', "_")
+        .trim_end_matches('=')
+        .to_string();
+    let request = test::TestRequest::get()
+        .uri(&format!("/mods/by-hash/{}/download", hash))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(
+        response.headers().get(CONTENT_HASH_HEADER).unwrap(),
+        archive.hash().as_str()
+    );
+    let body = test::read_body(response).await;
+    assert_eq!(body.as_ref(), archive.contents.as_slice());
+}
+
+#[actix_web::test]
+async fn upload_filenames_are_sanitized() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archive = TestArchive::new("Some Mod.7z", b"some mod contents");
+
+    for filename in ["..", "..%5Csecrets.7z", "%2E%2E"] {
+        let request = test::TestRequest::post()
+            .uri(&format!("/submit/mod/{}", filename))
+            .insert_header(("If-None-Match", archive.hash()))
+            .set_payload(archive.contents.clone())
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::BAD_REQUEST,
+            "{} should be rejected",
+            filename
+        );
+    }
+
+    let request = test::TestRequest::post()
+        .uri("/submit/mod/Some%20Mod%3A%20Part%201%3F.7z")
+        .insert_header(("If-None-Match", archive.hash()))
+        .set_payload(archive.contents.clone())
+        .to_request();
+    let result: UploadResult = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(result.filename, "Some Mod_ Part 1_.7z");
+    assert!(server.data_dir().get_mod_path(&result.filename).exists());
+}
+
+#[actix_web::test]
+async fn resumable_upload_finishes_once_and_refuses_stale_offsets() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archive = TestArchive::new("Chunked.7z", b"chunked upload contents");
+    let (first, second) = archive.contents.split_at(7);
+    let checksum = |chunk: &[u8]| {
+        format!(
+            "crc32 {}",
+            BASE64_STANDARD.encode(crc32fast::hash(chunk).to_be_bytes())
+        )
+    };
+
+    let request = test::TestRequest::post()
+        .uri("/uploads")
+        .insert_header(("Upload-Length", archive.size().to_string()))
+        .insert_header((
+            "Upload-Metadata",
+            format!("filename {}", BASE64_STANDARD.encode(&archive.filename)),
+        ))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let url = response
+        .headers()
+        .get(header::LOCATION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let patch = |offset: usize, chunk: &[u8]| {
+        test::TestRequest::patch()
+            .uri(&url)
+            .insert_header(("Upload-Offset", offset.to_string()))
+            .insert_header(("Upload-Checksum", checksum(chunk)))
+            .set_payload(chunk.to_vec())
+            .to_request()
+    };
+    assert_eq!(
+        test::call_service(&app, patch(0, first)).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    // A retried first chunk is refused instead of appended twice.
+    assert_eq!(
+        test::call_service(&app, patch(0, first)).await.status(),
+        StatusCode::CONFLICT
+    );
+
+    let response = test::call_service(&app, patch(first.len(), second)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(response.headers().contains_key(header::LOCATION));
+    assert_eq!(
+        std::fs::read(server.data_dir().get_mod_path(&archive.filename)).unwrap(),
+        archive.contents
+    );
+    // The finished upload is gone.
+    assert_eq!(
+        test::call_service(&app, patch(first.len(), second))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn failed_finalize_keeps_the_upload_for_a_retry() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let archive = TestArchive::new("Retried.7z", b"fully received contents");
+    let checksum = |chunk: &[u8]| {
+        format!(
+            "crc32 {}",
+            BASE64_STANDARD.encode(crc32fast::hash(chunk).to_be_bytes())
+        )
+    };
+
+    let request = test::TestRequest::post()
+        .uri("/uploads")
+        .insert_header(("Upload-Length", archive.size().to_string()))
+        .insert_header((
+            "Upload-Metadata",
+            format!("filename {}", BASE64_STANDARD.encode(&archive.filename)),
+        ))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let url = response
+        .headers()
+        .get(header::LOCATION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let patch = |offset: u64, chunk: &[u8]| {
+        test::TestRequest::patch()
+            .uri(&url)
+            .insert_header(("Upload-Offset", offset.to_string()))
+            .insert_header(("Upload-Checksum", checksum(chunk)))
+            .set_payload(chunk.to_vec())
+            .to_request()
+    };
+
+    // Without a Downloads directory the finished file can't be moved in.
+    let mod_dir = server.data_dir().get_mod_dir();
+    let moved_aside = mod_dir.with_extension("aside");
+    std::fs::rename(&mod_dir, &moved_aside).unwrap();
+    let response = test::call_service(&app, patch(0, &archive.contents)).await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    std::fs::rename(&moved_aside, &mod_dir).unwrap();
+
+    // Every byte is still there, and an empty PATCH finishes the upload.
+    let request = test::TestRequest::default()
+        .method(actix_web::http::Method::HEAD)
+        .uri(&url)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("Upload-Offset").unwrap(),
+        &archive.size().to_string()
+    );
+    let response = test::call_service(&app, patch(archive.size(), b"")).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(response.headers().contains_key(header::LOCATION));
+    assert_eq!(
+        std::fs::read(server.data_dir().get_mod_path(&archive.filename)).unwrap(),
+        archive.contents
+    );
+}
+
+#[actix_web::test]
+async fn idle_resumable_uploads_expire() {
+    let server = TestServer::new();
+    let app = server.app().await;
+    let reserve = |filename: &str| {
+        test::TestRequest::post()
+            .uri("/uploads")
+            .insert_header(("Upload-Length", "1024"))
+            .insert_header((
+                "Upload-Metadata",
+                format!("filename {}", BASE64_STANDARD.encode(filename)),
+            ))
+            .to_request()
+    };
+    let mut urls = Vec::new();
+    for filename in ["Abandoned.7z", "Active.7z"] {
+        let response = test::call_service(&app, reserve(filename)).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        urls.push(
+            response
+                .headers()
+                .get(header::LOCATION)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string(),
+        );
+    }
+    let [abandoned, active] = &urls[..] else {
+        unreachable!()
+    };
+
+    // Backdate the first upload's last chunk past the idle limit.
+    let meta_path = server.data_dir().get_resumable_upload_dir().join(format!(
+        "{}.json",
+        abandoned.trim_start_matches("/uploads/")
+    ));
+    let mut meta: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&meta_path).unwrap()).unwrap();
+    meta["last_activity"] =
+        (chrono::Utc::now().timestamp() - MAX_IDLE.as_secs() as i64 - 60).into();
+    std::fs::write(&meta_path, meta.to_string()).unwrap();
+
+    server.state.jobs.submit(
+        JobKind::ExpireUploads,
+        expire_uploads_job(server.data_dir().clone(), MAX_IDLE),
+    );
+    server.wait_for_jobs().await;
+
+    let status = |url: &str| {
+        test::TestRequest::default()
+            .method(actix_web::http::Method::HEAD)
+            .uri(url)
+            .to_request()
+    };
+    assert_eq!(
+        test::call_service(&app, status(abandoned)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        test::call_service(&app, status(active)).await.status(),
+        StatusCode::OK
+    );
+    let files: Vec<_> = std::fs::read_dir(server.data_dir().get_resumable_upload_dir())
+        .unwrap()
+        .collect();
+    assert_eq!(files.len(), 2, "only the active upload's files are left");
+}
@@ -0,0 +1,90 @@
+//! Counts the bytes each client uploads and downloads per day, so the
+//! diagnostics page can show which machine or user is behind traffic and
+//! storage growth.
+//!
+//! Clients are told apart by how they authenticated: requests carrying the
+//! API token count as the token, labelled with the `X-Wabba-Client` header
+//! wabba-tools sends with `--client-name`; signed-in users count as
+//! themselves, and anyone else as the address of the connection. The
+//! header is ignored without the token, so nobody can book traffic under
+//! a name of their choosing. Only requests that were let in and succeeded
+//! are counted. Requests count by their `Content-Length` and responses by
+//! the size of their body; bodies of unknown length, such as streamed
+//! pages, count as nothing.
+
+use actix_session::SessionExt;
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use wabba_protocol::api::CLIENT_HEADER;
+
+use crate::db::transfer_stats::DailyTransfer;
+use crate::web::auth::{has_api_token, session_user};
+
+/// Longer client names are cut, so a client can't bloat the table.
+const MAX_CLIENT_LEN: usize = 64;
+
+/// Who `req` is attributed to.
+fn client_name(req: &ServiceRequest) -> String {
+    let name = if has_api_token(req.request()) {
+        let label = req
+            .headers()
+            .get(CLIENT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        match label {
+            Some(label) => format!("API token ({})", label),
+            None => "API token".to_string(),
+        }
+    } else if let Some(user) = session_user(&req.get_session()) {
+        user
+    } else {
+        // The socket's address rather than a forwarded one, which the
+        // client could make up.
+        req.peer_addr()
+            .map_or("unknown".to_string(), |addr| addr.ip().to_string())
+    };
+    name.chars().take(MAX_CLIENT_LEN).collect()
+}
+
+/// Middleware that adds each successful request and response to its
+/// client's totals for the day. Wrap it inside `require_login`, so turned
+/// away requests never reach it.
+pub async fn record_transfers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let client = client_name(&req);
+    let uploaded = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(0);
+    let pool = req
+        .app_data::<Data<Pool<SqliteConnectionManager>>>()
+        .cloned();
+
+    let response = next.call(req).await?.map_into_boxed_body();
+    let downloaded = match response.response().body().size() {
+        BodySize::Sized(size) => size,
+        BodySize::None | BodySize::Stream => 0,
+    };
+    if response.status().is_success()
+        && (uploaded > 0 || downloaded > 0)
+        && let Some(pool) = pool
+    {
+        let recorded = pool.get().map_err(|e| e.to_string()).and_then(|conn| {
+            DailyTransfer::record(&client, uploaded, downloaded, &conn).map_err(|e| e.to_string())
+        });
+        if let Err(e) = recorded {
+            log::warn!("Failed to record transfers for {}: {}", client, e);
+        }
+    }
+    Ok(response)
+}
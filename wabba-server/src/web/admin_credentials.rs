@@ -0,0 +1,229 @@
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use chrono::DateTime;
+use maud::html;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::{Deserialize, Serialize};
+
+use crate::credentials::{CREDENTIAL_KINDS, CredentialVault};
+use crate::db::credential::StoredCredential;
+use crate::web::components::{header_nav, htmx, layout, status_badge};
+use crate::web::confirm::{Confirmation, confirm_button, confirm_script, confirmation_response};
+use crate::web::i18n::Lang;
+use crate::web::negotiate::{Format, html_page, json_page};
+
+fn format_timestamp(unix_seconds: i64) -> String {
+    DateTime::from_timestamp(unix_seconds, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| unix_seconds.to_string())
+}
+
+/// The credential's label, or 404 for names that aren't credentials.
+fn known_credential(name: &str) -> Result<&'static str, actix_web::Error> {
+    CREDENTIAL_KINDS
+        .iter()
+        .find(|(known, _, _)| *known == name)
+        .map(|(_, label, _)| *label)
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown credential"))
+}
+
+fn redirect_to_credentials() -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/credentials"))
+        .finish()
+}
+
+/// A row of `/admin/credentials`, as JSON. Like the page, it only says
+/// whether a value is set.
+#[derive(Serialize)]
+struct CredentialStatus {
+    name: &'static str,
+    label: &'static str,
+    description: &'static str,
+    /// When the value was last saved, or `None` if it isn't set.
+    updated_at: Option<i64>,
+}
+
+/// What `/admin/credentials` shows, for `Accept: application/json`.
+#[derive(Serialize)]
+struct Credentials {
+    /// Whether `CREDENTIALS_KEY` is set, so values can be saved.
+    unlocked: bool,
+    credentials: Vec<CredentialStatus>,
+}
+
+#[get("/admin/credentials")]
+pub async fn admin_credentials_page(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    vault: web::Data<CredentialVault>,
+    format: Format,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    // Only metadata is shown; stored values never leave the server.
+    let stored =
+        StoredCredential::get_all(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if format == Format::Json {
+        return Ok(json_page(&Credentials {
+            unlocked: vault.is_unlocked(),
+            credentials: CREDENTIAL_KINDS
+                .iter()
+                .map(|&(name, label, description)| CredentialStatus {
+                    name,
+                    label,
+                    description,
+                    updated_at: stored
+                        .iter()
+                        .find(|c| c.name == name)
+                        .map(|c| c.updated_at),
+                })
+                .collect(),
+        }));
+    }
+
+    let page = layout(
+        Lang::English,
+        "Credentials",
+        "page-listing",
+        html! {
+            (htmx())
+            (confirm_script())
+        },
+        html! {
+            (header_nav("Credentials", html! {
+                a.nav-link href="/" { "View Modlists" }
+                a.nav-link href="/admin/jobs" { "Jobs" }
+            }))
+            @if !vault.is_unlocked() {
+                p.empty-state {
+                    "Set CREDENTIALS_KEY to a secret passphrase and restart the server to store credentials."
+                }
+            }
+            table.modlist-table.credentials-table {
+                thead {
+                    tr {
+                        th { "Credential" }
+                        th { "Status" }
+                        th { "Actions" }
+                    }
+                }
+                tbody {
+                    @for (name, label, description) in CREDENTIAL_KINDS {
+                        @let current = stored.iter().find(|c| c.name == *name);
+                        tr {
+                            td {
+                                strong { (label) }
+                                br;
+                                span.credential-description { (description) }
+                            }
+                            td.status {
+                                @match current {
+                                    Some(credential) => {
+                                        (status_badge("available", "Set"))
+                                        " " (format_timestamp(credential.updated_at))
+                                    }
+                                    None => (status_badge("unavailable", "Not set")),
+                                }
+                            }
+                            td.actions {
+                                @if vault.is_unlocked() {
+                                    form method="post" action=(format!("/admin/credentials/{}", name)) {
+                                        input type="password" name="value" autocomplete="off" required placeholder=(if current.is_some() { "New value" } else { "Value" });
+                                        button.job-button type="submit" {
+                                            @if current.is_some() { "Rotate" } @else { "Save" }
+                                        }
+                                    }
+                                }
+                                @if current.is_some() {
+                                    (confirm_button(&format!("/admin/credentials/{}/clear", name), "danger", "Clear"))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+    );
+
+    Ok(html_page(page))
+}
+
+#[derive(Deserialize)]
+struct CredentialForm {
+    value: String,
+}
+
+#[post("/admin/credentials/{name}")]
+pub async fn set_credential(
+    name: web::Path<String>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    vault: web::Data<CredentialVault>,
+    form: web::Form<CredentialForm>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = name.into_inner();
+    known_credential(&name)?;
+    let value = form.value.trim();
+    if value.is_empty() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Credential value cannot be empty",
+        ));
+    }
+    if !vault.is_unlocked() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "CREDENTIALS_KEY is not set",
+        ));
+    }
+
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    vault
+        .set(&name, value, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    log::info!("Credential {} updated", name);
+
+    Ok(redirect_to_credentials())
+}
+
+#[get("/admin/credentials/{name}/clear")]
+pub async fn confirm_clear_credential(
+    name: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = name.into_inner();
+    let label = known_credential(&name)?;
+    Ok(confirmation_response(
+        &req,
+        Confirmation {
+            title: format!("Clear the {}?", label),
+            message: html! {
+                p { "The stored value is deleted. Anything that needs it stops working until a new one is saved." }
+            },
+            action: format!("/admin/credentials/{}/clear", name),
+            submit_label: "Clear",
+            back: "/admin/credentials".to_string(),
+            typed: None,
+        },
+    ))
+}
+
+#[post("/admin/credentials/{name}/clear")]
+pub async fn clear_credential(
+    name: web::Path<String>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    vault: web::Data<CredentialVault>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = name.into_inner();
+    known_credential(&name)?;
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    vault
+        .clear(&name, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    log::info!("Credential {} cleared", name);
+
+    Ok(redirect_to_credentials())
+}
@@ -0,0 +1,372 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use chrono::{DateTime, Utc};
+use maud::html;
+use serde::Serialize;
+
+use crate::bandwidth::BandwidthLimiter;
+use crate::jobs::{JobError, JobInfo, JobRegistry, JobStatus, PlannedAction};
+use crate::resources::jobs_api::JobSummary;
+use crate::web::components::{header_nav, htmx, layout, mod_table, status_badge};
+use crate::web::confirm::{
+    ConfirmForm, Confirmation, confirm_button, confirm_script, confirmation_response,
+};
+use crate::web::i18n::Lang;
+use crate::web::negotiate::{Format, html_page, json_page};
+
+fn format_time(time: &Option<DateTime<Utc>>) -> String {
+    match time {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// What `/admin/jobs` shows, for `Accept: application/json`.
+#[derive(Serialize)]
+struct JobsPage {
+    /// Bytes per second, or `None` when unlimited.
+    bandwidth_limit: Option<u64>,
+    jobs: Vec<JobSummary>,
+}
+
+/// What `/admin/jobs/{id}` shows, for `Accept: application/json`.
+#[derive(Serialize)]
+struct JobDetails<'a> {
+    #[serde(flatten)]
+    summary: JobSummary,
+    retry_of: Option<u64>,
+    confirmed_from: Option<u64>,
+    plan: Option<&'a [PlannedAction]>,
+    errors: &'a [JobError],
+    log: &'a [String],
+}
+
+#[get("/admin/jobs")]
+pub async fn admin_jobs_page(
+    jobs: web::Data<JobRegistry>,
+    limiter: web::Data<BandwidthLimiter>,
+    format: Format,
+) -> impl Responder {
+    let jobs = jobs.list();
+    let bandwidth_limit = limiter.current_limit();
+    if format == Format::Json {
+        return json_page(&JobsPage {
+            bandwidth_limit,
+            jobs: jobs.iter().map(JobSummary::from).collect(),
+        });
+    }
+    let has_active = jobs
+        .iter()
+        .any(|job| !job.status.is_finished() && job.status != JobStatus::AwaitingConfirmation);
+
+    let page = layout(
+        Lang::English,
+        "Jobs",
+        "page-listing",
+        html! {
+            @if has_active {
+                meta http-equiv="refresh" content="5";
+            }
+        },
+        html! {
+            (header_nav("Background Jobs", html! {
+                a.nav-link href="/" { "View Modlists" }
+                a.nav-link href="/mods" { "View All Mods" }
+                a.nav-link href="/admin/credentials" { "Credentials" }
+                a.nav-link href="/admin/diagnostics" { "Diagnostics" }
+            }))
+            p {
+                strong { "Background transfer limit: " }
+                @match bandwidth_limit {
+                    Some(limit) => { (limit / 1024) " KB/s" }
+                    None => { "Unlimited" }
+                }
+            }
+            @if jobs.is_empty() {
+                p.empty-state { "No jobs have run since the server started." }
+            } @else {
+                table.modlist-table.jobs-table {
+                    thead {
+                        tr {
+                            th { "ID" }
+                            th { "Job" }
+                            th { "Status" }
+                            th { "Progress" }
+                            th { "Errors" }
+                            th { "Queued" }
+                            th { "Started" }
+                            th { "Finished" }
+                            th { "Actions" }
+                        }
+                    }
+                    tbody {
+                        @for job in &jobs {
+                            tr {
+                                td {
+                                    a href=(format!("/admin/jobs/{}", job.id)) { "#" (job.id) }
+                                }
+                                td {
+                                    (job.kind.label())
+                                    @if job.dry_run {
+                                        span.retry-of { " (dry run)" }
+                                    }
+                                    @if let Some(retry_of) = job.retry_of {
+                                        span.retry-of { " (retry of #" (retry_of) ")" }
+                                    }
+                                }
+                                td.status {
+                                    @match &job.status {
+                                        JobStatus::Queued => (status_badge("warning", "Queued")),
+                                        JobStatus::Running => (status_badge("available", "Running")),
+                                        JobStatus::AwaitingConfirmation => (status_badge("warning", "Awaiting confirmation")),
+                                        JobStatus::Succeeded => (status_badge("available", "Succeeded")),
+                                        JobStatus::Failed(error) => span.status-badge.missing title=(error) { "Failed" },
+                                        JobStatus::Cancelled => (status_badge("unavailable", "Cancelled")),
+                                    }
+                                }
+                                td.progress {
+                                    @if let JobStatus::Failed(error) = &job.status {
+                                        code { (error) }
+                                    } @else if let Some(progress) = &job.progress {
+                                        (progress)
+                                    }
+                                }
+                                td {
+                                    @if job.errors.is_empty() {
+                                        "0"
+                                    } @else {
+                                        a href=(format!("/admin/jobs/{}", job.id)) {
+                                            span.status-badge.missing { (job.errors.len()) }
+                                        }
+                                    }
+                                }
+                                td { (format_time(&Some(job.created_at))) }
+                                td { (format_time(&job.started_at)) }
+                                td { (format_time(&job.finished_at)) }
+                                td.actions {
+                                    @if job.status == JobStatus::AwaitingConfirmation {
+                                        a.job-button href=(format!("/admin/jobs/{}", job.id)) { "Review plan" }
+                                    }
+                                    @if !job.status.is_finished() {
+                                        form method="post" action=(format!("/admin/jobs/{}/cancel", job.id)) {
+                                            button.job-button type="submit" { "Cancel" }
+                                        }
+                                    }
+                                    @if matches!(job.status, JobStatus::Failed(_) | JobStatus::Cancelled) {
+                                        form method="post" action=(format!("/admin/jobs/{}/retry", job.id)) {
+                                            button.job-button type="submit" { "Retry" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+    );
+
+    html_page(page)
+}
+
+#[get("/admin/jobs/{id}")]
+pub async fn admin_job_details_page(
+    id: web::Path<u64>,
+    jobs: web::Data<JobRegistry>,
+    format: Format,
+) -> Result<HttpResponse, actix_web::Error> {
+    let job = jobs
+        .get(id.into_inner())
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Job not found"))?;
+    if format == Format::Json {
+        return Ok(json_page(&JobDetails {
+            summary: JobSummary::from(&job),
+            retry_of: job.retry_of,
+            confirmed_from: job.confirmed_from,
+            plan: job.plan.as_deref(),
+            errors: &job.errors,
+            log: &job.log,
+        }));
+    }
+
+    let page = layout(
+        Lang::English,
+        html! { "Job #" (job.id) },
+        "page-details",
+        html! {
+            @if !job.status.is_finished() && job.status != JobStatus::AwaitingConfirmation {
+                meta http-equiv="refresh" content="5";
+            }
+            @if job.status == JobStatus::AwaitingConfirmation {
+                (htmx())
+                (confirm_script())
+            }
+        },
+        html! {
+            div.header {
+                a.back-link href="/admin/jobs" { "← Back to Jobs" }
+                h1 { "Job #" (job.id) ": " (job.kind.label()) }
+                div.metadata {
+                    p { strong { "Status: " } (job.status.label()) }
+                    @if let JobStatus::Failed(error) = &job.status {
+                        p { strong { "Error: " } code { (error) } }
+                    }
+                    @if let Some(progress) = &job.progress {
+                        p { strong { "Progress: " } (progress) }
+                    }
+                    @if let Some(retry_of) = job.retry_of {
+                        p {
+                            strong { "Retry of: " }
+                            a href=(format!("/admin/jobs/{}", retry_of)) { "#" (retry_of) }
+                        }
+                    }
+                    @if let Some(confirmed_from) = job.confirmed_from {
+                        p {
+                            strong { "Executing plan from: " }
+                            a href=(format!("/admin/jobs/{}", confirmed_from)) { "#" (confirmed_from) }
+                        }
+                    }
+                    @if job.dry_run {
+                        p { strong { "Mode: " } "Dry run" }
+                    }
+                    p { strong { "Queued: " } (format_time(&Some(job.created_at))) }
+                    p { strong { "Started: " } (format_time(&job.started_at)) }
+                    p { strong { "Finished: " } (format_time(&job.finished_at)) }
+                }
+            }
+            @if let Some(plan) = &job.plan {
+                h2 { "Planned actions (" (plan.len()) ")" }
+                @if job.status == JobStatus::AwaitingConfirmation {
+                    div.plan-confirm {
+                        p {
+                            "Nothing has been changed yet. Review the actions below, then confirm to execute them or cancel to discard the plan."
+                        }
+                        (confirm_button(&format!("/admin/jobs/{}/confirm", job.id), "danger", "Confirm and execute"))
+                        form method="post" action=(format!("/admin/jobs/{}/cancel", job.id)) {
+                            button.job-button type="submit" { "Discard plan" }
+                        }
+                    }
+                }
+                @if plan.is_empty() {
+                    p.empty-state { "Nothing to do." }
+                } @else {
+                    (mod_table(&["Action", "Target", "Detail"], html! {
+                        @for action in plan {
+                            tr {
+                                td { (action.action) }
+                                td.filename { (action.target) }
+                                td { (action.detail.as_deref().unwrap_or("")) }
+                            }
+                        }
+                    }))
+                }
+            }
+            h2 { "Errors (" (job.errors.len()) ")" }
+            @if job.errors.is_empty() {
+                p.empty-state { "No errors recorded." }
+            } @else {
+                (mod_table(&["Item", "Error"], html! {
+                    @for error in &job.errors {
+                        tr {
+                            td.filename { (error.item) }
+                            td { (error.message) }
+                        }
+                    }
+                }))
+            }
+            @if job.verbose {
+                h2 { "Log (" (job.log.len()) " lines)" }
+                pre.job-log { @for line in &job.log { (line) "\n" } }
+            }
+        },
+    );
+
+    Ok(html_page(page))
+}
+
+#[post("/admin/jobs/{id}/cancel")]
+pub async fn cancel_job(
+    id: web::Path<u64>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !jobs.cancel(id.into_inner()) {
+        return Err(actix_web::error::ErrorNotFound(
+            "Job not found or already finished",
+        ));
+    }
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
+
+#[post("/admin/jobs/{id}/retry")]
+pub async fn retry_job(
+    id: web::Path<u64>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if jobs.retry(id.into_inner()).is_none() {
+        return Err(actix_web::error::ErrorNotFound(
+            "Job not found or not in a retryable state",
+        ));
+    }
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/jobs"))
+        .finish())
+}
+
+/// Plans of more than one action are bulk changes, so executing them takes
+/// typing how many actions there are.
+fn typed_confirmation(job: &JobInfo) -> Option<String> {
+    let actions = job.plan.as_ref().map_or(0, Vec::len);
+    (actions > 1).then(|| actions.to_string())
+}
+
+#[get("/admin/jobs/{id}/confirm")]
+pub async fn confirm_job_page(
+    id: web::Path<u64>,
+    req: HttpRequest,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let job = jobs
+        .get(id.into_inner())
+        .filter(|job| job.status == JobStatus::AwaitingConfirmation)
+        .ok_or_else(|| {
+            actix_web::error::ErrorNotFound("Job not found or not awaiting confirmation")
+        })?;
+    let actions = job.plan.as_ref().map_or(0, Vec::len);
+    Ok(confirmation_response(
+        &req,
+        Confirmation {
+            title: format!("Execute the plan of job #{}?", job.id),
+            message: html! {
+                p {
+                    (job.kind.label()) " will carry out " (actions) " planned actions. "
+                    "Each one is checked again before it runs, but finished actions aren't undone if the job is cancelled."
+                }
+            },
+            action: format!("/admin/jobs/{}/confirm", job.id),
+            submit_label: "Confirm and execute",
+            back: format!("/admin/jobs/{}", job.id),
+            typed: typed_confirmation(&job),
+        },
+    ))
+}
+
+#[post("/admin/jobs/{id}/confirm")]
+pub async fn confirm_job(
+    id: web::Path<u64>,
+    form: Option<web::Form<ConfirmForm>>,
+    jobs: web::Data<JobRegistry>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let id = id.into_inner();
+    if let Some(expected) = jobs.get(id).as_ref().and_then(typed_confirmation) {
+        form.map(web::Form::into_inner)
+            .unwrap_or_default()
+            .check(&expected)?;
+    }
+    let new_id = jobs.confirm(id).ok_or_else(|| {
+        actix_web::error::ErrorNotFound("Job not found or not awaiting confirmation")
+    })?;
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/admin/jobs/{}", new_id)))
+        .finish())
+}
@@ -0,0 +1,418 @@
+use std::future::{Ready, ready};
+
+use actix_session::{Session, SessionExt};
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::middleware::Next;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, get, http::Method, web};
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use maud::html;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::{OidcConfig, ServeFiles, UiMode};
+use crate::web::components::layout;
+use crate::web::i18n::Lang;
+
+const SESSION_USER: &str = "user";
+const SESSION_ROLE: &str = "role";
+const SESSION_STATE: &str = "oidc_state";
+const SESSION_VERIFIER: &str = "oidc_verifier";
+const SESSION_RETURN_TO: &str = "oidc_return_to";
+
+/// What a signed-in user may do, from their groups at the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Viewer,
+    Admin,
+}
+
+impl Role {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Admin => "admin",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(Role::Viewer),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    /// `None` when the user is in none of the configured groups.
+    fn for_groups(config: &OidcConfig, groups: &[String]) -> Option<Self> {
+        let member_of = |allowed: &[String]| groups.iter().any(|group| allowed.contains(group));
+        if member_of(&config.admin_groups) {
+            Some(Role::Admin)
+        } else if config.viewer_groups.is_empty() || member_of(&config.viewer_groups) {
+            Some(Role::Viewer)
+        } else {
+            None
+        }
+    }
+}
+
+fn oidc_config(req: &HttpRequest) -> Option<&OidcConfig> {
+    req.app_data::<web::Data<Option<OidcConfig>>>()
+        .and_then(|config| config.get_ref().as_ref())
+}
+
+/// Who signed in to this session, as the provider named them.
+pub fn session_user(session: &Session) -> Option<String> {
+    session.get::<String>(SESSION_USER).ok().flatten()
+}
+
+fn session_role(session: &Session) -> Option<Role> {
+    session
+        .get::<String>(SESSION_ROLE)
+        .ok()
+        .flatten()
+        .and_then(|role| Role::parse(&role))
+}
+
+/// The UI the current request should see: the listener's mode, narrowed to
+/// the read-only pages for signed-in viewers.
+impl FromRequest for UiMode {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let listener = req
+            .app_data::<web::Data<UiMode>>()
+            .map_or(UiMode::Admin, |mode| *mode.get_ref());
+        let mode = if listener.is_admin()
+            && oidc_config(req).is_some()
+            && session_role(&req.get_session()) != Some(Role::Admin)
+        {
+            UiMode::Public
+        } else {
+            listener
+        };
+        ready(Ok(mode))
+    }
+}
+
+/// Whether the listener hands out files, so pages only link downloads that
+/// will work.
+impl FromRequest for ServeFiles {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let files = req
+            .app_data::<web::Data<ServeFiles>>()
+            .map_or(ServeFiles(true), |files| *files.get_ref());
+        ready(Ok(files))
+    }
+}
+
+/// Read-only routes Wabbajack fetches directly. It can't follow a browser
+/// sign-in or send a token, so these stay open for `GET`. `*` stands for
+/// one path segment.
+const OPEN_ROUTES: &[&str] = &[
+    "/hello",
+    "/api/modlists",
+    "/api/modlists/*/archives",
+    "/api/modlists/*/mods/*/meta",
+    "/api/mods",
+    "/mods/by-hash/*/download",
+    "/wabbajack/modlists.json",
+    "/modlists/*/wabbajack.metadata",
+    "/modlists/*/image",
+    "/modlists/*/download",
+    "/res/*",
+    "/auth/login",
+    "/auth/callback",
+    "/auth/logout",
+];
+
+fn is_open_route(method: &Method, path: &str) -> bool {
+    if method != Method::GET && method != Method::HEAD {
+        return false;
+    }
+    OPEN_ROUTES.iter().any(|route| {
+        let segments: Vec<_> = path.split('/').collect();
+        let pattern: Vec<_> = route.split('/').collect();
+        segments.len() == pattern.len()
+            && segments
+                .iter()
+                .zip(&pattern)
+                .all(|(segment, expected)| *expected == "*" || segment == expected)
+    })
+}
+
+/// Whether the request carries `API_TOKEN` as a bearer token, which
+/// stands in for an admin session.
+pub fn has_api_token(req: &HttpRequest) -> bool {
+    let Some(expected) = oidc_config(req).and_then(|config| config.api_token.as_deref()) else {
+        return false;
+    };
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // Comparing digests keeps the comparison time independent of how
+        // much of the token a guess got right.
+        .is_some_and(|token| Sha256::digest(token) == Sha256::digest(expected))
+}
+
+/// Sends visitors without a session to the provider when OIDC is
+/// configured. Does nothing otherwise.
+pub async fn require_login(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if oidc_config(req.request()).is_none()
+        || is_open_route(req.method(), req.path())
+        || has_api_token(req.request())
+        || session_role(&req.get_session()).is_some()
+    {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    let response = if req.method() == Method::GET {
+        let return_to = req
+            .uri()
+            .path_and_query()
+            .map_or("/".to_string(), |p| p.to_string());
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("return_to", &return_to);
+        HttpResponse::Found()
+            .append_header(("Location", format!("/auth/login?{}", query.finish())))
+            .finish()
+    } else {
+        HttpResponse::Unauthorized().body("Sign in required")
+    };
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+/// Turns away signed-in viewers. Wraps the routes that change anything.
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if oidc_config(req.request()).is_none()
+        || has_api_token(req.request())
+        || session_role(&req.get_session()) == Some(Role::Admin)
+    {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+    Ok(req
+        .into_response(HttpResponse::Forbidden().body("Admin access required"))
+        .map_into_right_body())
+}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+async fn discover(config: &OidcConfig) -> Result<Discovery, String> {
+    let url = format!("{}/.well-known/openid-configuration", config.issuer_url);
+    reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid discovery document: {}", e))
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn not_configured() -> actix_web::Error {
+    actix_web::error::ErrorNotFound("Single sign-on is not configured")
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginQuery {
+    return_to: Option<String>,
+}
+
+#[get("/auth/login")]
+pub async fn login(
+    query: web::Query<LoginQuery>,
+    config: web::Data<Option<OidcConfig>>,
+    session: Session,
+) -> Result<HttpResponse, actix_web::Error> {
+    let config = config.get_ref().as_ref().ok_or_else(not_configured)?;
+    let discovery = discover(config)
+        .await
+        .map_err(actix_web::error::ErrorBadGateway)?;
+
+    let state = random_token();
+    let verifier = random_token();
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    // Only ever send the user back somewhere on this server.
+    let return_to = query
+        .return_to
+        .clone()
+        .filter(|path| path.starts_with('/') && !path[1..].starts_with(['/', '\\']))
+        .unwrap_or_else(|| "/".to_string());
+    session.insert(SESSION_STATE, &state)?;
+    session.insert(SESSION_VERIFIER, &verifier)?;
+    session.insert(SESSION_RETURN_TO, &return_to)?;
+
+    let mut url = url::Url::parse(&discovery.authorization_endpoint)
+        .map_err(actix_web::error::ErrorBadGateway)?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &config.redirect_url)
+        .append_pair("scope", "openid profile groups")
+        .append_pair("state", &state)
+        .append_pair("code_challenge", &challenge)
+        .append_pair("code_challenge_method", "S256");
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", url.to_string()))
+        .finish())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[get("/auth/callback")]
+pub async fn callback(
+    query: web::Query<CallbackQuery>,
+    config: web::Data<Option<OidcConfig>>,
+    session: Session,
+) -> Result<HttpResponse, actix_web::Error> {
+    let config = config.get_ref().as_ref().ok_or_else(not_configured)?;
+    if let Some(error) = &query.error {
+        return Err(actix_web::error::ErrorUnauthorized(format!(
+            "Sign-in failed: {} {}",
+            error,
+            query.error_description.as_deref().unwrap_or("")
+        )));
+    }
+    let expected_state = session
+        .remove_as::<String>(SESSION_STATE)
+        .and_then(Result::ok);
+    let verifier = session
+        .remove_as::<String>(SESSION_VERIFIER)
+        .and_then(Result::ok);
+    let (Some(code), Some(state), Some(expected_state), Some(verifier)) =
+        (&query.code, &query.state, expected_state, verifier)
+    else {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Sign-in expired or was started elsewhere; try again",
+        ));
+    };
+    if *state != expected_state {
+        return Err(actix_web::error::ErrorBadRequest("Sign-in state mismatch"));
+    }
+
+    let discovery = discover(config)
+        .await
+        .map_err(actix_web::error::ErrorBadGateway)?;
+    let client = reqwest::Client::new();
+    let token: TokenResponse = client
+        .post(&discovery.token_endpoint)
+        .basic_auth(&config.client_id, Some(&config.client_secret))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("code_verifier", verifier.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| actix_web::error::ErrorBadGateway(format!("Token exchange failed: {}", e)))?
+        .json()
+        .await
+        .map_err(actix_web::error::ErrorBadGateway)?;
+    let userinfo: serde_json::Value = client
+        .get(&discovery.userinfo_endpoint)
+        .bearer_auth(&token.access_token)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| actix_web::error::ErrorBadGateway(format!("Userinfo request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(actix_web::error::ErrorBadGateway)?;
+
+    let user = userinfo["preferred_username"]
+        .as_str()
+        .or_else(|| userinfo["sub"].as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    let groups: Vec<String> = userinfo[config.groups_claim.as_str()]
+        .as_array()
+        .map(|groups| {
+            groups
+                .iter()
+                .filter_map(|group| group.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    let Some(role) = Role::for_groups(config, &groups) else {
+        log::info!("Refused sign-in for {}: not in an allowed group", user);
+        return Err(actix_web::error::ErrorForbidden(
+            "Your account is not in a group allowed to use this server",
+        ));
+    };
+
+    let return_to = session
+        .remove_as::<String>(SESSION_RETURN_TO)
+        .and_then(Result::ok)
+        .unwrap_or_else(|| "/".to_string());
+    session.renew();
+    session.insert(SESSION_USER, &user)?;
+    session.insert(SESSION_ROLE, role.as_str())?;
+    log::info!("{} signed in as {}", user, role.as_str());
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", return_to))
+        .finish())
+}
+
+#[get("/auth/logout")]
+pub async fn logout(session: Session) -> HttpResponse {
+    session.purge();
+    let page = layout(
+        Lang::English,
+        "Signed out",
+        "page-listing",
+        html! {},
+        html! {
+            h1 { "Signed out" }
+            a.nav-link href="/auth/login" { "Sign in again" }
+        },
+    );
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page.into_string())
+}
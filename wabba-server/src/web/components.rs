@@ -0,0 +1,152 @@
+//! Pieces of markup and formatting shared by the server-rendered pages.
+
+use maud::{DOCTYPE, Markup, Render, html};
+
+use crate::db::mod_data::Mod;
+use crate::web::i18n::{LANGUAGES, Lang};
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+pub fn format_hash(hash: &str) -> String {
+    if hash.len() > 16 {
+        format!("{}...", &hash[..16])
+    } else {
+        hash.to_string()
+    }
+}
+
+/// A hash shortened by [`format_hash`], with the full value in its
+/// tooltip and a button that copies it (see res/copy-hash.js).
+pub fn hash_code(lang: Lang, hash: &str) -> Markup {
+    html! {
+        code title=(hash) data-hash=(hash) { (format_hash(hash)) }
+        button.copy-hash type="button" data-copy=(hash) title=(lang.t("copy-hash")) aria-label=(lang.t("copy-hash")) { "⧉" }
+    }
+}
+
+/// A whole page: the document head with the shared stylesheet, then
+/// `content` inside the centered container. `lang` is the language the
+/// page is written in. `body_class` picks the page layout from styles.css
+/// (`page-listing` or `page-details`), and `head` adds page-specific tags
+/// such as scripts.
+pub fn layout(
+    lang: Lang,
+    title: impl Render,
+    body_class: &str,
+    head: Markup,
+    content: Markup,
+) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(lang.code()) {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { (title) }
+                link rel="stylesheet" href="/res/styles.css";
+                script src="/res/copy-hash.js" defer {}
+                (head)
+            }
+            body class=(body_class) {
+                div.container {
+                    (content)
+                    (language_picker(lang))
+                }
+            }
+        }
+    }
+}
+
+/// Links to `/language/{code}` for every language but `current`.
+fn language_picker(current: Lang) -> Markup {
+    html! {
+        nav.language-picker aria-label=(current.t("language")) {
+            @for lang in LANGUAGES {
+                @if lang == current {
+                    span.active { (lang.native_name()) }
+                } @else {
+                    a href=(format!("/language/{}", lang.code())) lang=(lang.code()) hreflang=(lang.code()) {
+                        (lang.native_name())
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The htmx script, for pages with polling or partial updates.
+pub fn htmx() -> Markup {
+    html! {
+        script src="/res/htmx.min.js" {}
+    }
+}
+
+/// A page heading with navigation links beside it.
+pub fn header_nav(title: &str, links: Markup) -> Markup {
+    html! {
+        div.header-nav {
+            h1 { (title) }
+            div.nav-links {
+                (links)
+            }
+        }
+    }
+}
+
+/// `class` is one of the badge colors in styles.css: `available`,
+/// `unavailable`, `missing` or `warning`.
+pub fn status_badge(class: &str, label: impl Render) -> Markup {
+    html! {
+        span class=(format!("status-badge {}", class)) { (label) }
+    }
+}
+
+/// Whether a mod is on disk, and if not whether it can still be found.
+pub fn mod_status_badge(lang: Lang, mod_item: &Mod) -> Markup {
+    if mod_item.is_available() {
+        status_badge("available", lang.t("status-available"))
+    } else if mod_item.lost_forever {
+        status_badge("missing", lang.t("status-lost-forever"))
+    } else {
+        status_badge("unavailable", lang.t("status-unavailable"))
+    }
+}
+
+/// A `.mod-table` with `headers` as its columns around `rows`. Tables
+/// that lead with an ID column (which reads "ID" in every language) get
+/// the `.mod-table-with-id` widths.
+pub fn mod_table(headers: &[&str], rows: Markup) -> Markup {
+    let class = if headers.first() == Some(&"ID") {
+        "mod-table mod-table-with-id"
+    } else {
+        "mod-table"
+    };
+    html! {
+        table class=(class) {
+            thead {
+                tr {
+                    @for header in headers {
+                        th { (header) }
+                    }
+                }
+            }
+            tbody {
+                (rows)
+            }
+        }
+    }
+}
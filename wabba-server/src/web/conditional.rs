@@ -0,0 +1,64 @@
+use actix_web::{
+    HttpRequest, HttpResponse, HttpResponseBuilder,
+    http::header::{self, HttpDate},
+};
+
+use crate::db::data_version::DataVersion;
+use crate::web::i18n::Lang;
+use crate::web::negotiate::Format;
+
+/// What every negotiated page varies on: the language comes from the
+/// session cookie or `Accept-Language` and the format from `Accept`.
+pub const VARY: &str = "Accept, Accept-Language, Cookie";
+
+/// The entity tag of `version` rendered in `lang` (or as JSON), so a cached
+/// page in one language isn't revalidated for a visitor reading another.
+fn etag(version: &DataVersion, lang: Lang, format: Format) -> String {
+    match format {
+        Format::Html => format!("\"v{}-{}\"", version.version, lang.code()),
+        Format::Json => format!("\"v{}-json\"", version.version),
+    }
+}
+
+/// Whether the client's cached copy (per `If-None-Match`, falling back to
+/// `If-Modified-Since`) is still current for `version` in `lang`.
+pub fn is_fresh(req: &HttpRequest, version: &DataVersion, lang: Lang, format: Format) -> bool {
+    if let Some(if_none_match) = req.headers().get(header::IF_NONE_MATCH) {
+        let etag = etag(version, lang, format);
+        return if_none_match
+            .to_str()
+            .unwrap_or("")
+            .split(',')
+            .map(|tag| tag.trim())
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+    }
+    if let Some(if_modified_since) = req.headers().get(header::IF_MODIFIED_SINCE)
+        && let Ok(since) = if_modified_since.to_str().unwrap_or("").parse::<HttpDate>()
+    {
+        return std::time::SystemTime::from(since) >= version.last_modified();
+    }
+    false
+}
+
+/// Adds `ETag`/`Last-Modified` and asks clients to revalidate on every use.
+pub fn set_validators(
+    response: &mut HttpResponseBuilder,
+    version: &DataVersion,
+    lang: Lang,
+    format: Format,
+) {
+    response
+        .insert_header((header::ETAG, etag(version, lang, format)))
+        .insert_header((
+            header::LAST_MODIFIED,
+            HttpDate::from(version.last_modified()).to_string(),
+        ))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header((header::VARY, VARY));
+}
+
+pub fn not_modified(version: &DataVersion, lang: Lang, format: Format) -> HttpResponse {
+    let mut response = HttpResponse::NotModified();
+    set_validators(&mut response, version, lang, format);
+    response.finish()
+}
@@ -0,0 +1,118 @@
+//! Confirmation steps for destructive admin actions. The button for such an
+//! action links to a GET route describing what will happen. htmx opens it
+//! as a modal dialog over the current page; without JavaScript it is a page
+//! of its own. Actions that remove a lot at once also ask for some text to
+//! be typed, which the POST handler checks again.
+
+use actix_web::{HttpRequest, HttpResponse};
+use maud::{Markup, Render, html};
+use serde::Deserialize;
+
+use crate::web::components::layout;
+use crate::web::i18n::Lang;
+
+/// What a confirmation step asks.
+pub struct Confirmation {
+    pub title: String,
+    pub message: Markup,
+    /// Where the confirmed form posts.
+    pub action: String,
+    pub submit_label: &'static str,
+    /// Where cancelling goes when the confirmation is a page of its own.
+    pub back: String,
+    /// Text that has to be typed before the action goes ahead.
+    pub typed: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ConfirmForm {
+    #[serde(default)]
+    pub confirm: String,
+}
+
+impl ConfirmForm {
+    pub fn check(&self, expected: &str) -> Result<(), actix_web::Error> {
+        if self.confirm.trim() == expected {
+            Ok(())
+        } else {
+            Err(actix_web::error::ErrorBadRequest(
+                "The confirmation text doesn't match",
+            ))
+        }
+    }
+}
+
+/// The button for a destructive action, opening its confirmation at `url`.
+pub fn confirm_button(url: &str, class: &str, label: impl Render) -> Markup {
+    html! {
+        a class=(format!("job-button {}", class)) href=(url)
+          hx-get=(url) hx-target="body" hx-swap="beforeend" {
+            (label)
+        }
+    }
+}
+
+/// The script that opens confirmation dialogs, for pages with
+/// [`confirm_button`]s. Needs [`crate::web::components::htmx`] too.
+pub fn confirm_script() -> Markup {
+    html! {
+        script src="/res/confirm-dialog.js" {}
+    }
+}
+
+fn confirmation_form(confirmation: &Confirmation, in_dialog: bool) -> Markup {
+    html! {
+        h2 #confirm-title { (confirmation.title) }
+        div.confirm-message { (confirmation.message) }
+        form method="post" action=(confirmation.action) {
+            @if let Some(typed) = &confirmation.typed {
+                label.confirm-typed {
+                    "Type " code { (typed) } " to confirm"
+                    input type="text" name="confirm" required autocomplete="off" autofocus
+                          data-confirm-text=(typed);
+                }
+            }
+            // The action comes first, so Enter in the text field submits
+            // it rather than cancelling.
+            div.confirm-actions {
+                button.job-button.danger type="submit" { (confirmation.submit_label) }
+                @if in_dialog {
+                    button.job-button type="submit" formmethod="dialog" formnovalidate
+                           autofocus[confirmation.typed.is_none()] { "Cancel" }
+                } @else {
+                    a.job-button href=(confirmation.back) { "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Renders `confirmation` as a dialog for htmx requests and as a full page
+/// otherwise.
+pub fn confirmation_response(req: &HttpRequest, confirmation: Confirmation) -> HttpResponse {
+    let markup = if req.headers().contains_key("HX-Request") {
+        html! {
+            dialog.confirm-dialog aria-labelledby="confirm-title" {
+                (confirmation_form(&confirmation, true))
+            }
+        }
+    } else {
+        layout(
+            Lang::English,
+            confirmation.title.as_str(),
+            "page-details",
+            confirm_script(),
+            html! {
+                div.header {
+                    a.back-link href=(confirmation.back) { "← Back" }
+                }
+                div.confirm-page {
+                    (confirmation_form(&confirmation, false))
+                }
+            },
+        )
+    };
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string())
+}
@@ -1,38 +1,45 @@
 use actix_files::NamedFile;
+use actix_web::http::header::TryIntoHeaderPair;
 use actix_web::{HttpRequest, HttpResponse, Responder, get, http::header, post, web};
 use maud::html;
-use r2d2::Pool;
+use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::cold_storage::ColdStorage;
+use crate::config::{ServeFiles, UiMode};
 use crate::data_dir::DataDir;
-use crate::db::mod_association::ModAssociation;
+use crate::db::download_state::{DownloadAttempt, DownloadSourceState};
+use crate::db::mod_association::{ModAssociation, ModlistArchiveCounts};
 use crate::db::mod_data::Mod;
+use crate::db::mod_disk_state::ModDiskState;
+use crate::db::mod_image_cache::CachedModImage;
 use crate::db::modlist::Modlist;
+use crate::db::readiness::{ModlistReadiness, ReadinessRule};
+use crate::db::wayback::{WaybackCapture, WaybackLookup};
+use crate::resources::loverslab::fetch_image;
+use crate::resources::offload::stream_from_cold_storage;
+use crate::web::components::{format_size, hash_code, htmx, layout, mod_status_badge, mod_table};
+use crate::web::confirm::{
+    ConfirmForm, Confirmation, confirm_button, confirm_script, confirmation_response,
+};
+use crate::web::fragments::availability_bar;
+use crate::web::i18n::Lang;
+use crate::web::negotiate::{Format, html_page, json_page};
+use wabba_protocol::api::CONTENT_HASH_HEADER;
 use wabba_protocol::archive_state::ArchiveState;
 
-fn format_size(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} B", bytes)
-    }
+fn format_timestamp(unix_seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(unix_seconds, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| unix_seconds.to_string())
 }
 
-fn format_hash(hash: &str) -> String {
-    if hash.len() > 16 {
-        format!("{}...", &hash[..16])
-    } else {
-        hash.to_string()
-    }
+/// Wayback timestamps are `YYYYMMDDhhmmss`.
+fn format_capture_timestamp(timestamp: &str) -> String {
+    chrono::NaiveDateTime::parse_from_str(timestamp, "%Y%m%d%H%M%S")
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
 }
 
 fn nexus_game_url_slug(game_name: &str) -> String {
@@ -308,11 +315,59 @@ fn render_source(source: &ArchiveState, mod_id: u64) -> maud::Markup {
     }
 }
 
+/// A modlist using the mod, as `/mod/{id}` lists it in JSON.
+#[derive(Serialize)]
+struct ModUse<'a> {
+    modlist: &'a Modlist,
+    association: Option<&'a ModAssociation>,
+}
+
+/// Another mod in the `/mod/{id}` conflict tables, as JSON.
+#[derive(Serialize)]
+struct RelatedMod<'a> {
+    #[serde(rename = "mod")]
+    mod_item: &'a Mod,
+    association: Option<&'a ModAssociation>,
+}
+
+impl<'a> RelatedMod<'a> {
+    fn list(mods: &'a [(Mod, Option<ModAssociation>)]) -> Vec<Self> {
+        mods.iter()
+            .map(|(mod_item, association)| RelatedMod {
+                mod_item,
+                association: association.as_ref(),
+            })
+            .collect()
+    }
+}
+
+/// What `/mod/{id}` shows, for `Accept: application/json`.
+#[derive(Serialize)]
+struct ModDetails<'a> {
+    #[serde(rename = "mod")]
+    mod_item: &'a Mod,
+    available: bool,
+    modlists: Vec<ModUse<'a>>,
+    disk_state: Option<&'a ModDiskState>,
+    download_sources: &'a [DownloadSourceState],
+    download_attempts: &'a [DownloadAttempt],
+    wayback_lookups: &'a [WaybackLookup],
+    wayback_captures: &'a [WaybackCapture],
+    same_filename: Vec<RelatedMod<'a>>,
+    same_name: Vec<RelatedMod<'a>>,
+}
+
 #[get("/mod/{id}")]
+#[allow(clippy::too_many_arguments)]
 pub async fn mod_details_page(
     id: web::Path<u64>,
     query: web::Query<std::collections::HashMap<String, String>>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
+    cold_storage: web::Data<ColdStorage>,
+    ServeFiles(serve_files): ServeFiles,
+    mode: UiMode,
+    lang: Lang,
+    format: Format,
 ) -> Result<impl Responder, actix_web::Error> {
     let conn = pool
         .get()
@@ -324,6 +379,17 @@ pub async fn mod_details_page(
         .map_err(actix_web::error::ErrorInternalServerError)?
         .ok_or_else(|| actix_web::error::ErrorNotFound("Mod not found"))?;
 
+    let disk_state = ModDiskState::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let download_states = DownloadSourceState::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let download_attempts = DownloadAttempt::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let wayback_lookups = WaybackLookup::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let wayback_captures = WaybackCapture::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
     // Get all associations for this mod
     let associations = ModAssociation::get_by_mod_id(mod_id, &conn)
         .map_err(actix_web::error::ErrorInternalServerError)?;
@@ -340,6 +406,13 @@ pub async fn mod_details_page(
         .map(|assoc| (assoc.modlist_id, assoc))
         .collect();
 
+    // The modlist the viewer came from, when it's one that uses this mod,
+    // for the back link and to highlight its row
+    let from_modlist = query
+        .get("from")
+        .and_then(|id| id.parse::<u64>().ok())
+        .and_then(|id| modlists.iter().find(|modlist| modlist.id == id));
+
     // Create tuples with modlists, their associations, and whether they have lost forever mods
     let modlists_with_assocs: Vec<_> = modlists
         .iter()
@@ -352,480 +425,474 @@ pub async fn mod_details_page(
 
     // Get primary association (first one) for display purposes
     let primary_assoc = associations.first();
+    let has_http_source = associations
+        .iter()
+        .any(|assoc| matches!(assoc.source, ArchiveState::HttpDownloader { .. }));
 
-    // Get mods with the same disk filename (excluding current mod)
-    let mods_same_filename = if let Some(ref disk_filename) = mod_item.disk_filename {
-        Mod::get_by_disk_filename_all(disk_filename, mod_item.id, &conn)
-            .map_err(actix_web::error::ErrorInternalServerError)?
-    } else {
-        Vec::new()
+    // Other mods with the same disk filename or association name, each
+    // with its first association, for the conflict tables
+    let mods_same_filename_with_assocs = match &mod_item.disk_filename {
+        Some(disk_filename) => {
+            ModAssociation::get_other_mods_with_disk_filename(disk_filename, mod_item.id, &conn)
+                .map_err(actix_web::error::ErrorInternalServerError)?
+        }
+        None => Vec::new(),
+    };
+    let mods_same_name = match primary_assoc.and_then(|assoc| assoc.name.as_deref()) {
+        Some(name) => ModAssociation::get_other_mods_named(name, mod_id, &conn)
+            .map_err(actix_web::error::ErrorInternalServerError)?,
+        None => Vec::new(),
     };
 
-    // Get associations for mods with same filename
-    let mut mods_same_filename_with_assocs = Vec::new();
-    for related_mod in &mods_same_filename {
-        let related_assocs = ModAssociation::get_by_mod_id(related_mod.id, &conn)
-            .map_err(actix_web::error::ErrorInternalServerError)?;
-        let related_first_assoc = related_assocs.first().cloned();
-        mods_same_filename_with_assocs.push((related_mod, related_first_assoc));
+    if format == Format::Json {
+        let data = ModDetails {
+            mod_item: &mod_item,
+            available: mod_item.is_available(),
+            modlists: modlists_with_assocs
+                .iter()
+                .map(|(modlist, association, _)| ModUse {
+                    modlist,
+                    association: *association,
+                })
+                .collect(),
+            disk_state: disk_state.as_ref(),
+            download_sources: &download_states,
+            download_attempts: &download_attempts,
+            wayback_lookups: &wayback_lookups,
+            wayback_captures: &wayback_captures,
+            same_filename: RelatedMod::list(&mods_same_filename_with_assocs),
+            same_name: RelatedMod::list(&mods_same_name),
+        };
+        return Ok(json_page(&data));
     }
 
-    // Get mods with the same name from associations (excluding current mod).
-    // Use the mod_association.name index to find matching mod ids in a single
-    // query, then fetch each matching mod + its first association. This
-    // replaces a previous O(total_mods) N+1 scan.
-    let mods_same_name = if let Some(assoc) = primary_assoc {
-        if let Some(ref name) = assoc.name {
-            let matching_ids: Vec<u64> = conn
-                .prepare(
-                    "SELECT DISTINCT mod_id FROM mod_association
-                     WHERE name = ?1 AND mod_id != ?2",
-                )
-                .map_err(actix_web::error::ErrorInternalServerError)?
-                .query_map(rusqlite::params![name, mod_id], |row| row.get(0))
-                .map_err(actix_web::error::ErrorInternalServerError)?
-                .collect::<Result<Vec<u64>, _>>()
-                .map_err(actix_web::error::ErrorInternalServerError)?;
-
-            let mut same_name_mods = Vec::with_capacity(matching_ids.len());
-            for other_id in matching_ids {
-                let Some(other_mod) = Mod::get_by_id(other_id, &conn)
-                    .map_err(actix_web::error::ErrorInternalServerError)?
-                else {
-                    continue;
-                };
-                let other_assocs = ModAssociation::get_by_mod_id(other_id, &conn)
-                    .map_err(actix_web::error::ErrorInternalServerError)?;
-                let other_first_assoc = other_assocs.first().cloned();
-                same_name_mods.push((other_mod, other_first_assoc));
+    let mod_name = primary_assoc
+        .map(|assoc| {
+            assoc
+                .name
+                .clone()
+                .or_else(|| mod_item.disk_filename.clone())
+                .unwrap_or_else(|| assoc.filename.clone())
+        })
+        .or_else(|| mod_item.disk_filename.clone())
+        .unwrap_or_else(|| lang.t("unknown-mod").to_string());
+
+    let page = layout(
+        lang,
+        lang.tf("title-mod-details", &[("name", mod_name.clone())]),
+        "page-details",
+        html! {
+            @if mode.is_admin() {
+                (htmx())
+                (confirm_script())
             }
-            same_name_mods
-        } else {
-            Vec::new()
-        }
-    } else {
-        Vec::new()
-    };
-
-    let page = html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                title {
-                    @match primary_assoc {
-                        Some(assoc) => {
-                            @match &assoc.name {
-                                Some(name) => {
-                                    (name.clone())
-                                }
-                                None => {
-                                    @match &mod_item.disk_filename {
-                                        Some(disk_filename) => {
-                                            (disk_filename.clone())
-                                        }
-                                        None => {
-                                            (assoc.filename.clone())
-                                        }
-                                    }
-                                }
+        },
+        html! {
+            div.header {
+                @match from_modlist {
+                    Some(modlist) => {
+                        a.back-link href=(format!("/modlists/{}", modlist.id)) { (lang.tf("back-to", &[("name", modlist.name.clone())])) }
+                    }
+                    None => {
+                        a.back-link href="/" { (lang.t("back-to-modlists")) }
+                    }
+                }
+                h1 { (mod_name) }
+                div.metadata {
+                    p { strong { (lang.t("label-id")) } (mod_item.id) }
+                    p {
+                        strong { (lang.t("label-disk-filename")) }
+                        @match &mod_item.disk_filename {
+                            Some(disk_filename) => {
+                                (disk_filename.clone())
                             }
-                        }
-                        None => {
-                            @match &mod_item.disk_filename {
-                                Some(disk_filename) => {
-                                    (disk_filename.clone())
-                                }
-                                None => {
-                                    "Unknown Mod"
-                                }
+                            None => {
+                                em { (lang.t("not-on-disk")) }
                             }
                         }
                     }
-                    " - Mod Details"
-                }
-                link rel="stylesheet" href="/res/styles.css";
-            }
-            body.page-details {
-                div.container {
-                    div.header {
-                        a.back-link href="/" { "← Back to Modlists" }
-                        h1 {
-                            @match primary_assoc {
-                                Some(assoc) => {
-                                    @match &assoc.name {
-                                        Some(name) => {
-                                            (name.clone())
-                                        }
-                                        None => {
-                                            @match &mod_item.disk_filename {
-                                                Some(disk_filename) => {
-                                                    (disk_filename.clone())
-                                                }
-                                                None => {
-                                                    (assoc.filename.clone())
-                                                }
-                                            }
-                                        }
+                    @if let Some(assoc) = primary_assoc {
+                        p { strong { (lang.t("label-modlist-filename")) } (assoc.filename.clone()) }
+                        @if let Some(name) = &assoc.name {
+                            p { strong { (lang.t("label-name")) } (name.clone()) }
+                        }
+                        @if let Some(version) = &assoc.version {
+                            p { strong { (lang.t("label-version")) } (version.clone()) }
+                        }
+                    }
+                    p { strong { (lang.t("label-size")) } (format_size(mod_item.size)) }
+                    p { strong { (lang.t("label-hash")) } span.hash { (hash_code(lang, &mod_item.xxhash64)) } }
+                    @if mod_item.is_available() {
+                        p {
+                            strong { (lang.t("label-last-verified")) }
+                            @match disk_state.as_ref().and_then(|s| s.last_verified_at.map(|at| (at, s.last_verify_ok))) {
+                                Some((verified_at, ok)) => {
+                                    (format_timestamp(verified_at)) " "
+                                    @if ok == Some(false) {
+                                        span.status-badge.missing { (lang.t("hash-mismatch")) }
+                                    } @else {
+                                        span.status-badge.available { (lang.t("ok")) }
                                     }
                                 }
                                 None => {
-                                    @match &mod_item.disk_filename {
-                                        Some(disk_filename) => {
-                                            (disk_filename.clone())
-                                        }
-                                        None => {
-                                            "Unknown Mod"
-                                        }
-                                    }
+                                    em { (lang.t("never")) }
+                                }
+                            }
+                            @if disk_state.as_ref().is_some_and(|s| s.needs_rehash) {
+                                " "
+                                span.status-badge.warning title="The file's size or modification time changed since it was last hashed" {
+                                    "Needs re-hash"
                                 }
                             }
                         }
-                        div.metadata {
-                            p { strong { "ID: " } (mod_item.id) }
+                        @if cold_storage.is_enabled() || disk_state.as_ref().is_some_and(|s| s.cold_storage_ref.is_some()) {
                             p {
-                                strong { "Disk Filename: " }
-                                @match &mod_item.disk_filename {
-                                    Some(disk_filename) => {
-                                        (disk_filename.clone())
+                                strong { "Cold storage: " }
+                                @match disk_state.as_ref().and_then(|s| s.cold_storage_ref.as_ref().map(|r| (r, s.evicted))) {
+                                    Some((reference, evicted)) => {
+                                        code { (reference) } " "
+                                        @if evicted {
+                                            span.status-badge.warning title="Fetched back from cold storage on the next download" { "Offloaded" }
+                                        } @else {
+                                            span.status-badge.available { "Cached locally" }
+                                        }
                                     }
                                     None => {
-                                        em { "Not available on disk" }
+                                        em { "Not offloaded" }
+                                    }
+                                }
+                                @if mode.is_admin() && cold_storage.is_enabled() && !disk_state.as_ref().is_some_and(|s| s.evicted) {
+                                    " "
+                                    form method="post" action=(format!("/mod/{}/offload", mod_item.id)) style="display: inline-block;" {
+                                        button.job-button type="submit" { "Plan offload to " (cold_storage.label()) }
                                     }
                                 }
                             }
-                            @if let Some(assoc) = primary_assoc {
-                                p { strong { "Modlist Filename: " } (assoc.filename.clone()) }
-                        @if let Some(name) = &assoc.name {
-                            p { strong { "Name: " } (name.clone()) }
                         }
-                        @if let Some(version) = &assoc.version {
-                            p { strong { "Version: " } (version.clone()) }
+                    }
+                    p {
+                        strong { (lang.t("label-status")) }
+                        (mod_status_badge(lang, &mod_item))
+                        @if serve_files && mod_item.is_available() {
+                            a.download-button href=(format!("/mod/{}/download", mod_item.id)) style="display: inline-block; margin-left: 1rem; padding: 0.4rem 0.8rem; border-radius: 4px; background-color: #27ae60; color: white; font-weight: 500; text-decoration: none;" {
+                                (lang.t("download"))
+                            }
                         }
+                    }
+                    @if !mod_item.is_available() {
+                        p {
+                            strong { (lang.t("label-lost-forever")) }
+                            @if mod_item.lost_forever {
+                                span.status-badge.missing { (lang.t("yes")) }
+                            } @else {
+                                span { (lang.t("no")) }
                             }
-                            p { strong { "Size: " } (format_size(mod_item.size)) }
-                            p { strong { "Hash: " } span.hash { code { (format_hash(&mod_item.xxhash64)) } } }
-                            p {
-                                strong { "Status: " }
-                                @if mod_item.is_available() {
-                                    span.status-badge.available { "Available" }
-                                } @else if mod_item.lost_forever {
-                                    span.status-badge.missing { "Lost Forever" }
-                                } @else {
-                                    span.status-badge.unavailable { "Unavailable" }
-                                }
-                                @if mod_item.is_available() {
-                                    a.download-button href=(format!("/mod/{}/download", mod_item.id)) style="display: inline-block; margin-left: 1rem; padding: 0.4rem 0.8rem; border-radius: 4px; background-color: #27ae60; color: white; font-weight: 500; text-decoration: none;" {
-                                        "Download"
-                                    }
+                            @if mode.is_admin() {
+                                " "
+                                (confirm_button(
+                                    &format!("/mod/{}/toggle-lost-forever", mod_item.id),
+                                    if mod_item.lost_forever { "" } else { "danger" },
+                                    if mod_item.lost_forever { "Mark as Recoverable" } else { "Mark as Lost Forever" },
+                                ))
+                            }
+                        }
+                    }
+                    @if show_debug && mode.is_admin() {
+                        p.debug-actions style="margin-top: 1rem; padding-top: 1rem; border-top: 1px dashed #e74c3c;" {
+                            strong { "Debug: " }
+                            (confirm_button(&format!("/mod/{}/delete", mod_item.id), "danger", "Delete Mod"))
+                        }
+                    }
+                }
+            }
+
+            @if let Some(assoc) = primary_assoc {
+                h2 { (lang.t("heading-source")) }
+                div.source-section {
+                    (render_source(&assoc.source, mod_id))
+                }
+            }
+
+            @if !download_states.is_empty() || !download_attempts.is_empty() {
+                h2 { (lang.t("heading-download-attempts")) }
+                @for state in &download_states {
+                    p {
+                        strong { (state.source_type) ": " }
+                        (state.consecutive_failures) " consecutive failures, "
+                        @if state.quarantined {
+                            span.status-badge.missing { "Quarantined" }
+                        } @else {
+                            "next attempt " (format_timestamp(state.next_attempt_at))
+                        }
+                        @if mode.is_admin() {
+                            form method="post" action=(format!("/mod/{}/downloads/{}/release", mod_id, state.source_type)) style="display: inline-block; margin-left: 1rem;" {
+                                button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #3498db; color: white; font-weight: 500;" {
+                                    @if state.quarantined { "Release from quarantine" } @else { "Retry next run" }
                                 }
                             }
-                            @if !mod_item.is_available() {
-                                p {
-                                    strong { "Lost Forever: " }
-                                    @if mod_item.lost_forever {
-                                        span.status-badge.missing { "Yes" }
+                        }
+                    }
+                }
+                @if !download_attempts.is_empty() {
+                    (mod_table(&[lang.t("col-when"), lang.t("col-source"), lang.t("col-result")], html! {
+                        @for attempt in &download_attempts {
+                            tr {
+                                td { (format_timestamp(attempt.attempted_at)) }
+                                td { (attempt.source_type) }
+                                td {
+                                    @if attempt.ok {
+                                        span.status-badge.available { (lang.t("status-downloaded")) }
                                     } @else {
-                                        span { "No" }
-                                    }
-                                    form method="post" action=(format!("/mod/{}/toggle-lost-forever", mod_item.id)) style="display: inline-block;" {
-                                        button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #3498db; color: white; font-weight: 500;" {
-                                            @if mod_item.lost_forever {
-                                                "Mark as Recoverable"
-                                            } @else {
-                                                "Mark as Lost Forever"
-                                            }
-                                        }
+                                        span.status-badge.missing { (lang.t("status-failed")) } " "
+                                        (attempt.error.as_deref().unwrap_or(""))
                                     }
                                 }
                             }
-                            @if show_debug {
-                                p.debug-actions style="margin-top: 1rem; padding-top: 1rem; border-top: 1px dashed #e74c3c;" {
-                                    strong { "Debug: " }
-                                    form method="post"
-                                         action=(format!("/mod/{}/delete", mod_item.id))
-                                         onsubmit="return confirm('Delete this mod permanently?\\n\\nThis removes the DB row, all mod associations, and the file on disk. Cannot be undone.');"
-                                         style="display: inline-block;" {
-                                        button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #e74c3c; color: white; font-weight: 500;" {
-                                            "Delete Mod"
-                                        }
-                                    }
+                        }
+                    }))
+                }
+            }
+
+            @if has_http_source {
+                h2 { (lang.t("heading-archived-copies")) }
+                @if wayback_lookups.is_empty() {
+                    p.empty-state { "Not looked up in the Wayback Machine yet." }
+                } @else if wayback_captures.is_empty() {
+                    p.empty-state { "The Wayback Machine has no captures of this mod's HTTP sources." }
+                } @else {
+                    (mod_table(&[lang.t("col-captured"), lang.t("col-original-url"), lang.t("col-type")], html! {
+                        @for capture in &wayback_captures {
+                            tr {
+                                td {
+                                    a href=(capture.download_url()) target="_blank" { (format_capture_timestamp(&capture.timestamp)) }
                                 }
+                                td { (capture.url) }
+                                td { (capture.mime_type.as_deref().unwrap_or("")) }
                             }
                         }
-                    }
-
-                    @if let Some(assoc) = primary_assoc {
-                        h2 { "Source" }
-                        div.source-section {
-                            (render_source(&assoc.source, mod_id))
+                    }))
+                }
+                @for lookup in &wayback_lookups {
+                    p { "Looked up " (lookup.url) " " (format_timestamp(lookup.looked_up_at)) }
+                }
+                @if mode.is_admin() {
+                    form method="post" action=(format!("/mod/{}/wayback", mod_id)) {
+                        button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #3498db; color: white; font-weight: 500;" {
+                            "Search the Wayback Machine"
                         }
                     }
+                }
+            }
 
-                    h2 { "Conflicts - Mods with Same Filename" }
-                    @if mods_same_filename.is_empty() {
-                        p.empty-state { "No conflicts found." }
-                    } @else {
-                        table.mod-table.mod-table-with-id {
-                            thead {
-                                tr {
-                                    th { "ID" }
-                                    th { "Filename" }
-                                    th { "Name" }
-                                    th { "Version" }
-                                    th { "Size" }
-                                    th { "Hash" }
-                                    th { "Status" }
-                                }
-                            }
-                            tbody {
-                                @for (related_mod, related_first_assoc) in &mods_same_filename_with_assocs {
-                                    tr class=(if related_mod.is_available() { "" } else { "unavailable-row" }) {
-                                        td.id { (related_mod.id) }
-                                        td.filename {
-                                            a href=(format!("/mod/{}", related_mod.id)) {
-                                                @match &related_mod.disk_filename {
-                                                    Some(disk_filename) => {
-                                                        (disk_filename.clone())
-                                                    }
-                                                    None => {
-                                                        @match related_first_assoc {
-                                                            Some(assoc) => {
-                                                                (assoc.filename.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.name {
-                                            a href=(format!("/mod/{}", related_mod.id)) {
-                                                @match related_first_assoc {
-                                                    Some(assoc) => {
-                                                        @match &assoc.name {
-                                                            Some(name) => {
-                                                                (name.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                    None => {
-                                                        em { "Unknown" }
-                                                    }
-                                                }
-                                            }
+            h2 { (lang.t("heading-same-filename")) }
+            @if mods_same_filename_with_assocs.is_empty() {
+                p.empty-state { (lang.t("no-conflicts")) }
+            } @else {
+                (mod_table(&["ID", lang.t("col-filename"), lang.t("col-name"), lang.t("col-version"), lang.t("col-size"), lang.t("col-hash"), lang.t("col-status")], html! {
+                    @for (related_mod, related_first_assoc) in &mods_same_filename_with_assocs {
+                        tr class=(if related_mod.is_available() { "" } else { "unavailable-row" }) {
+                            td.id { (related_mod.id) }
+                            td.filename {
+                                a href=(format!("/mod/{}", related_mod.id)) {
+                                    @match &related_mod.disk_filename {
+                                        Some(disk_filename) => {
+                                            (disk_filename.clone())
                                         }
-                                        td.version {
+                                        None => {
                                             @match related_first_assoc {
                                                 Some(assoc) => {
-                                                    @match &assoc.version {
-                                                        Some(version) => {
-                                                            (version.clone())
-                                                        }
-                                                        None => {
-                                                            em { "-" }
-                                                        }
-                                                    }
+                                                    (assoc.filename.clone())
                                                 }
                                                 None => {
-                                                    em { "-" }
+                                                    em { (lang.t("unknown")) }
                                                 }
                                             }
                                         }
-                                        td.size {
-                                            (format_size(related_mod.size))
+                                    }
+                                }
+                            }
+                            td.name {
+                                a href=(format!("/mod/{}", related_mod.id)) {
+                                    @match related_first_assoc {
+                                        Some(assoc) => {
+                                            @match &assoc.name {
+                                                Some(name) => {
+                                                    (name.clone())
+                                                }
+                                                None => {
+                                                    em { (lang.t("unknown")) }
+                                                }
+                                            }
                                         }
-                                        td.hash {
-                                            code { (format_hash(&related_mod.xxhash64)) }
+                                        None => {
+                                            em { (lang.t("unknown")) }
                                         }
-                                        td.status {
-                                            @if related_mod.is_available() {
-                                                span.status-badge.available { "Available" }
-                                            } @else if related_mod.lost_forever {
-                                                span.status-badge.missing { "Lost Forever" }
-                                            } @else {
-                                                span.status-badge.unavailable { "Unavailable" }
+                                    }
+                                }
+                            }
+                            td.version {
+                                @match related_first_assoc {
+                                    Some(assoc) => {
+                                        @match &assoc.version {
+                                            Some(version) => {
+                                                (version.clone())
+                                            }
+                                            None => {
+                                                em { "-" }
                                             }
                                         }
                                     }
+                                    None => {
+                                        em { "-" }
+                                    }
                                 }
                             }
+                            td.size {
+                                (format_size(related_mod.size))
+                            }
+                            td.hash {
+                                (hash_code(lang, &related_mod.xxhash64))
+                            }
+                            td.status {
+                                (mod_status_badge(lang, related_mod))
+                            }
                         }
                     }
+                }))
+            }
 
-                    h2 { "Associated Modlists" }
-                    @if modlists_with_assocs.is_empty() {
-                        p.empty-state { "This mod is not associated with any modlists." }
-                    } @else {
-                        table.mod-table {
-                            thead {
-                                tr {
-                                    th { "Name" }
-                                    th { "Version" }
-                                    th { "Filename" }
-                                    th { "Size" }
-                                    th { "Hash" }
-                                    th { "Status" }
+            h2 { (lang.t("heading-associated-modlists")) }
+            @if modlists_with_assocs.is_empty() {
+                p.empty-state { (lang.t("no-associated-modlists")) }
+            } @else {
+                (mod_table(&[lang.t("col-name"), lang.t("col-version"), lang.t("col-filename"), lang.t("col-size"), lang.t("col-hash"), lang.t("col-status")], html! {
+                    @for (modlist, assoc, has_lost_forever) in &modlists_with_assocs {
+                        tr class=(if from_modlist.is_some_and(|from| from.id == modlist.id) { "current-modlist" } else { "" }) {
+                            td.name {
+                                a href=(format!("/modlists/{}", modlist.id)) {
+                                    (modlist.name.clone())
                                 }
                             }
-                            tbody {
-                                @for (modlist, assoc, has_lost_forever) in &modlists_with_assocs {
-                                    tr {
-                                        td.name {
-                                            a href=(format!("/modlists/{}", modlist.id)) {
-                                                (modlist.name.clone())
-                                            }
-                                        }
-                                        td.version { (modlist.version.clone()) }
-                                        td.filename {
-                                            @match assoc {
-                                                Some(assoc) => {
-                                                    (assoc.filename.clone())
-                                                }
-                                                None => {
-                                                    @match &mod_item.disk_filename {
-                                                        Some(disk_filename) => {
-                                                            (disk_filename.clone())
-                                                        }
-                                                        None => {
-                                                            em { "Unknown" }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.size { (format_size(mod_item.size)) }
-                                        td.hash {
-                                            span.hash {
-                                                code { (format_hash(&mod_item.xxhash64)) }
+                            td.version { (modlist.version.clone()) }
+                            td.filename {
+                                @match assoc {
+                                    Some(assoc) => {
+                                        (assoc.filename.clone())
+                                    }
+                                    None => {
+                                        @match &mod_item.disk_filename {
+                                            Some(disk_filename) => {
+                                                (disk_filename.clone())
                                             }
-                                        }
-                                        td.status {
-                                            @if *has_lost_forever {
-                                                span.status-badge.missing { "Uninstallable" }
-                                            } @else if mod_item.is_available() {
-                                                span.status-badge.available { "Available" }
-                                            } @else {
-                                                span.status-badge.unavailable { "Unavailable" }
+                                            None => {
+                                                em { (lang.t("unknown")) }
                                             }
                                         }
                                     }
                                 }
                             }
+                            td.size { (format_size(mod_item.size)) }
+                            td.hash {
+                                span.hash {
+                                    (hash_code(lang, &mod_item.xxhash64))
+                                }
+                            }
+                            td.status {
+                                @if *has_lost_forever {
+                                    span.status-badge.missing { (lang.t("status-uninstallable")) }
+                                } @else if mod_item.is_available() {
+                                    span.status-badge.available { (lang.t("status-available")) }
+                                } @else {
+                                    span.status-badge.unavailable { (lang.t("status-unavailable")) }
+                                }
+                            }
                         }
                     }
+                }))
+            }
 
-                    @if primary_assoc.is_some_and(|a| a.name.is_some()) {
-                        h2 { "Other Versions - Mods with Same Name" }
-                        @if mods_same_name.is_empty() {
-                            p.empty-state { "No other versions found." }
-                        } @else {
-                            table.mod-table.mod-table-with-id {
-                                thead {
-                                    tr {
-                                        th { "ID" }
-                                        th { "Filename" }
-                                        th { "Name" }
-                                        th { "Version" }
-                                        th { "Size" }
-                                        th { "Hash" }
-                                        th { "Status" }
-                                    }
-                                }
-                            tbody {
-                                @for (related_mod, related_first_assoc) in &mods_same_name {
-                                        tr class=(if related_mod.is_available() { "" } else { "unavailable-row" }) {
-                                            td.id { (related_mod.id) }
-                                            td.filename {
-                                                a href=(format!("/mod/{}", related_mod.id)) {
-                                                    @match &related_mod.disk_filename {
-                                                        Some(disk_filename) => {
-                                                            (disk_filename.clone())
-                                                        }
-                                                        None => {
-                                                            @match related_first_assoc {
-                                                                Some(assoc) => {
-                                                                    (assoc.filename.clone())
-                                                                }
-                                                                None => {
-                                                                    em { "Unknown" }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                            td.name {
-                                                a href=(format!("/mod/{}", related_mod.id)) {
-                                                    @match related_first_assoc {
-                                                        Some(assoc) => {
-                                                            @match &assoc.name {
-                                                                Some(name) => {
-                                                                    (name.clone())
-                                                                }
-                                                                None => {
-                                                                    em { "Unknown" }
-                                                                }
-                                                            }
-                                                        }
-                                                        None => {
-                                                            em { "Unknown" }
-                                                        }
-                                                    }
-                                                }
+            @if primary_assoc.is_some_and(|a| a.name.is_some()) {
+                h2 { (lang.t("heading-other-versions")) }
+                @if mods_same_name.is_empty() {
+                    p.empty-state { (lang.t("no-other-versions")) }
+                } @else {
+                    (mod_table(&["ID", lang.t("col-filename"), lang.t("col-name"), lang.t("col-version"), lang.t("col-size"), lang.t("col-hash"), lang.t("col-status")], html! {
+                        @for (related_mod, related_first_assoc) in &mods_same_name {
+                            tr class=(if related_mod.is_available() { "" } else { "unavailable-row" }) {
+                                td.id { (related_mod.id) }
+                                td.filename {
+                                    a href=(format!("/mod/{}", related_mod.id)) {
+                                        @match &related_mod.disk_filename {
+                                            Some(disk_filename) => {
+                                                (disk_filename.clone())
                                             }
-                                            td.version {
+                                            None => {
                                                 @match related_first_assoc {
                                                     Some(assoc) => {
-                                                        @match &assoc.version {
-                                                            Some(version) => {
-                                                                (version.clone())
-                                                            }
-                                                            None => {
-                                                                em { "-" }
-                                                            }
-                                                        }
+                                                        (assoc.filename.clone())
                                                     }
                                                     None => {
-                                                        em { "-" }
+                                                        em { (lang.t("unknown")) }
                                                     }
                                                 }
                                             }
-                                            td.size {
-                                                (format_size(related_mod.size))
+                                        }
+                                    }
+                                }
+                                td.name {
+                                    a href=(format!("/mod/{}", related_mod.id)) {
+                                        @match related_first_assoc {
+                                            Some(assoc) => {
+                                                @match &assoc.name {
+                                                    Some(name) => {
+                                                        (name.clone())
+                                                    }
+                                                    None => {
+                                                        em { (lang.t("unknown")) }
+                                                    }
+                                                }
                                             }
-                                            td.hash {
-                                                code { (format_hash(&related_mod.xxhash64)) }
+                                            None => {
+                                                em { (lang.t("unknown")) }
                                             }
-                                            td.status {
-                                                @if related_mod.is_available() {
-                                                    span.status-badge.available { "Available" }
-                                                } @else if related_mod.lost_forever {
-                                                    span.status-badge.missing { "Lost Forever" }
-                                                } @else {
-                                                    span.status-badge.unavailable { "Unavailable" }
+                                        }
+                                    }
+                                }
+                                td.version {
+                                    @match related_first_assoc {
+                                        Some(assoc) => {
+                                            @match &assoc.version {
+                                                Some(version) => {
+                                                    (version.clone())
+                                                }
+                                                None => {
+                                                    em { "-" }
                                                 }
                                             }
                                         }
+                                        None => {
+                                            em { "-" }
+                                        }
                                     }
                                 }
+                                td.size {
+                                    (format_size(related_mod.size))
+                                }
+                                td.hash {
+                                    (hash_code(lang, &related_mod.xxhash64))
+                                }
+                                td.status {
+                                    (mod_status_badge(lang, related_mod))
+                                }
                             }
                         }
-                    }
+                    }))
                 }
             }
-        }
-    };
+        },
+    );
 
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(page.into_string()))
+    Ok(html_page(page))
 }
 
 #[get("/mod-image/{id}")]
@@ -854,73 +921,180 @@ pub async fn mod_image(
         })
         .ok_or_else(|| actix_web::error::ErrorNotFound("Mod image not found"))?;
 
-    // Fetch the image from the upstream URL
-    let client = reqwest::Client::new();
-    let response = client.get(image_url).send().await.map_err(|e| {
-        log::error!("Failed to fetch mod image from {}: {}", image_url, e);
-        actix_web::error::ErrorInternalServerError("Failed to fetch mod image")
-    })?;
-
-    if !response.status().is_success() {
-        return Err(actix_web::error::ErrorNotFound("Mod image not found"));
+    // Serve the cached copy while it still matches the association.
+    if let Some(cached) = CachedModImage::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        && cached.image_url == *image_url
+    {
+        return Ok(HttpResponse::Ok()
+            .content_type(cached.content_type)
+            .body(cached.data));
     }
 
-    // Determine content type from response or default to image/jpeg
-    let content_type = response
-        .headers()
-        .get("content-type")
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| "image/jpeg".to_string());
-
-    // Get the image bytes
-    let image_bytes = response.bytes().await.map_err(|e| {
-        log::error!("Failed to read mod image bytes: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to read mod image")
+    let client = reqwest::Client::new();
+    let (content_type, image_bytes) = fetch_image(&client, image_url).await.map_err(|e| {
+        log::error!("{}", e);
+        actix_web::error::ErrorNotFound("Mod image not found")
     })?;
+    if let Err(e) = CachedModImage::store(mod_id, image_url, &content_type, &image_bytes, &conn) {
+        log::warn!("Failed to cache image for mod {}: {}", mod_id, e);
+    }
 
     Ok(HttpResponse::Ok()
         .content_type(content_type)
         .body(image_bytes))
 }
 
+#[utoipa::path(
+    tag = "downloads",
+    params(("id" = u64, Path, description = "Mod id")),
+    responses(
+        (status = 200, description = "The archive", body = [u8], content_type = "application/octet-stream"),
+        (status = 206, description = "The requested range of the archive", body = [u8], content_type = "application/octet-stream"),
+        (status = 404, description = "Unknown or unavailable mod"),
+    )
+)]
 #[get("/mod/{id}/download")]
 pub async fn download_mod(
     id: web::Path<u64>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
     data_dir: web::Data<DataDir>,
+    cold_storage: web::Data<ColdStorage>,
     req: HttpRequest,
 ) -> Result<HttpResponse, actix_web::Error> {
     let conn = pool
         .get()
         .map_err(actix_web::error::ErrorInternalServerError)?;
-    let mod_id = id.into_inner();
 
-    let mod_item = Mod::get_by_id(mod_id, &conn)
+    let mod_item = Mod::get_by_id(id.into_inner(), &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Mod not found"))?;
+
+    serve_mod(mod_item, &conn, &pool, &data_dir, &cold_storage, &req).await
+}
+
+#[utoipa::path(
+    tag = "downloads",
+    params(("hash" = String, Path, description = "xxHash64 of the archive, base64url without padding")),
+    responses(
+        (status = 200, description = "The archive", body = [u8], content_type = "application/octet-stream"),
+        (status = 206, description = "The requested range of the archive", body = [u8], content_type = "application/octet-stream"),
+        (status = 404, description = "Unknown or unavailable mod"),
+    )
+)]
+#[get("/mods/by-hash/{hash}/download")]
+pub async fn download_mod_by_hash(
+    hash: web::Path<String>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    data_dir: web::Data<DataDir>,
+    cold_storage: web::Data<ColdStorage>,
+    req: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let mod_item = Mod::get_by_hash(&base64url_to_base64(&hash), &conn)
         .map_err(actix_web::error::ErrorInternalServerError)?
         .ok_or_else(|| actix_web::error::ErrorNotFound("Mod not found"))?;
 
+    serve_mod(mod_item, &conn, &pool, &data_dir, &cold_storage, &req).await
+}
+
+/// Hashes are base64, which can contain `/`; URLs carry them as base64url.
+fn base64url_to_base64(hash: &str) -> String {
+    let mut hash = hash.replace('-', "+").replace('_', "/");
+    while !hash.len().is_multiple_of(4) {
+        hash.push('=');
+    }
+    hash
+}
+
+/// Streams a mod's file from the data directory, or from cold storage if
+/// it has been evicted.
+async fn serve_mod(
+    mod_item: Mod,
+    conn: &PooledConnection<SqliteConnectionManager>,
+    pool: &Pool<SqliteConnectionManager>,
+    data_dir: &DataDir,
+    cold_storage: &ColdStorage,
+    req: &HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_id = mod_item.id;
+
     let disk_filename = mod_item
         .disk_filename
         .as_ref()
         .ok_or_else(|| actix_web::error::ErrorNotFound("Mod is not available on disk"))?;
 
+    ModDiskState::record_access(mod_id, conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    // Mods in Downloads subfolders are stored as relative paths; only
+    // offer the file name itself to the browser.
+    let content_disposition = header::ContentDisposition {
+        disposition: header::DispositionType::Attachment,
+        parameters: vec![header::DispositionParam::Filename(
+            disk_filename
+                .rsplit('/')
+                .next()
+                .unwrap_or(disk_filename)
+                .to_string(),
+        )],
+    };
+
     let file_path = data_dir.get_mod_path(disk_filename);
     if !file_path.is_file() {
-        return Err(actix_web::error::ErrorNotFound("Mod file missing on disk"));
+        let reference = ModDiskState::get_by_mod_id(mod_id, conn)
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .filter(|state| state.evicted)
+            .and_then(|state| state.cold_storage_ref)
+            .ok_or_else(|| actix_web::error::ErrorNotFound("Mod file missing on disk"))?;
+        let size = mod_item.size;
+        let hash = mod_item.xxhash64.clone();
+        let stream = stream_from_cold_storage(
+            mod_item,
+            &reference,
+            pool.clone(),
+            data_dir.clone(),
+            cold_storage.clone(),
+        )
+        .await?;
+        return Ok(HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .insert_header(content_disposition)
+            .insert_header((CONTENT_HASH_HEADER, hash))
+            .no_chunking(size)
+            .streaming(stream));
     }
 
     let named_file = NamedFile::open_async(&file_path).await.map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Failed to open mod file: {}", e))
     })?;
-    let named_file = named_file.set_content_disposition(header::ContentDisposition {
-        disposition: header::DispositionType::Attachment,
-        parameters: vec![header::DispositionParam::Filename(disk_filename.clone())],
-    });
+    let named_file = named_file.set_content_disposition(content_disposition);
+
+    Ok(with_content_hash(
+        named_file.into_response(req),
+        &mod_item.xxhash64,
+    ))
+}
 
-    Ok(named_file.into_response(&req))
+fn with_content_hash(mut response: HttpResponse, hash: &str) -> HttpResponse {
+    if let Ok((name, value)) = (CONTENT_HASH_HEADER, hash).try_into_pair() {
+        response.headers_mut().insert(name, value);
+    }
+    response
 }
 
+#[utoipa::path(
+    tag = "downloads",
+    params(("id" = u64, Path, description = "Modlist id")),
+    responses(
+        (status = 200, description = "The .wabbajack file", body = [u8], content_type = "application/octet-stream"),
+        (status = 206, description = "The requested range of the file", body = [u8], content_type = "application/octet-stream"),
+        (status = 404, description = "Unknown or unavailable modlist"),
+    )
+)]
 #[get("/modlists/{id}/download")]
 pub async fn download_modlist(
     id: web::Path<u64>,
@@ -956,11 +1130,53 @@ pub async fn download_modlist(
         parameters: vec![header::DispositionParam::Filename(modlist.filename.clone())],
     });
 
-    Ok(named_file.into_response(&req))
+    Ok(with_content_hash(
+        named_file.into_response(&req),
+        &modlist.xxhash64,
+    ))
 }
 
-#[post("/mod/{id}/delete")]
-pub async fn delete_mod(
+#[get("/mod/{id}/delete")]
+pub async fn confirm_delete_mod(
+    id: web::Path<u64>,
+    req: HttpRequest,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mod_id = id.into_inner();
+    let mod_item = Mod::get_by_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Mod not found"))?;
+    let modlists = ModAssociation::get_by_mod_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .len();
+
+    Ok(confirmation_response(
+        &req,
+        Confirmation {
+            title: "Delete this mod?".to_string(),
+            message: html! {
+                p {
+                    "This removes the mod's row, its associations with "
+                    (modlists) " modlists"
+                    @if let Some(disk_filename) = &mod_item.disk_filename {
+                        ", and " code { (disk_filename) } " on disk"
+                    }
+                    ". It can't be undone."
+                }
+            },
+            action: format!("/mod/{}/delete", mod_id),
+            submit_label: "Delete Mod",
+            back: format!("/mod/{}", mod_id),
+            typed: None,
+        },
+    ))
+}
+
+#[post("/mod/{id}/delete")]
+pub async fn delete_mod(
     id: web::Path<u64>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
     data_dir: web::Data<DataDir>,
@@ -989,6 +1205,36 @@ pub async fn delete_mod(
         .execute(rusqlite::params![mod_id])
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
+    conn.prepare("DELETE FROM mod_disk_state WHERE mod_id = ?1")
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .execute(rusqlite::params![mod_id])
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    conn.prepare("DELETE FROM download_attempt WHERE mod_id = ?1")
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .execute(rusqlite::params![mod_id])
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    conn.prepare("DELETE FROM download_source_state WHERE mod_id = ?1")
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .execute(rusqlite::params![mod_id])
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    conn.prepare("DELETE FROM wayback_capture WHERE mod_id = ?1")
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .execute(rusqlite::params![mod_id])
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    conn.prepare("DELETE FROM wayback_lookup WHERE mod_id = ?1")
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .execute(rusqlite::params![mod_id])
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    conn.prepare("DELETE FROM mod_image_cache WHERE mod_id = ?1")
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .execute(rusqlite::params![mod_id])
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
     conn.prepare("DELETE FROM \"mod\" WHERE id = ?1")
         .map_err(actix_web::error::ErrorInternalServerError)?
         .execute(rusqlite::params![mod_id])
@@ -1001,9 +1247,47 @@ pub async fn delete_mod(
         .finish())
 }
 
+/// Deleting a modlist drops all of its archive associations at once, so the
+/// modlist's name has to be typed.
+#[get("/modlists/{id}/delete")]
+pub async fn confirm_delete_modlist(
+    id: web::Path<u64>,
+    req: HttpRequest,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let modlist_id = id.into_inner();
+    let modlist = Modlist::get_by_id(modlist_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+    let counts = ModAssociation::count_by_modlist_id(modlist_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(confirmation_response(
+        &req,
+        Confirmation {
+            title: format!("Delete {}?", modlist.name),
+            message: html! {
+                p {
+                    "This removes the modlist's row, its "
+                    (counts.required + counts.optional) " archive associations and "
+                    code { (modlist.filename) } " on disk. Mods it shares with no other modlist stay. It can't be undone."
+                }
+            },
+            action: format!("/modlists/{}/delete", modlist_id),
+            submit_label: "Delete Modlist",
+            back: format!("/modlists/{}", modlist_id),
+            typed: Some(modlist.name),
+        },
+    ))
+}
+
 #[post("/modlists/{id}/delete")]
 pub async fn delete_modlist(
     id: web::Path<u64>,
+    form: web::Form<ConfirmForm>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
     data_dir: web::Data<DataDir>,
 ) -> Result<impl Responder, actix_web::Error> {
@@ -1016,6 +1300,7 @@ pub async fn delete_modlist(
     let modlist = Modlist::get_by_id(modlist_id, &conn)
         .map_err(actix_web::error::ErrorInternalServerError)?
         .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+    form.check(&modlist.name)?;
 
     let file_path = data_dir.get_modlist_path(&modlist.filename);
     if file_path.exists()
@@ -1033,6 +1318,11 @@ pub async fn delete_modlist(
         .execute(rusqlite::params![modlist_id])
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
+    conn.prepare("DELETE FROM modlist_readiness_rule WHERE modlist_id = ?1")
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .execute(rusqlite::params![modlist_id])
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
     conn.prepare("DELETE FROM modlist WHERE id = ?1")
         .map_err(actix_web::error::ErrorInternalServerError)?
         .execute(rusqlite::params![modlist_id])
@@ -1045,6 +1335,46 @@ pub async fn delete_modlist(
         .finish())
 }
 
+#[get("/mod/{id}/toggle-lost-forever")]
+pub async fn confirm_toggle_lost_forever(
+    id: web::Path<u64>,
+    req: HttpRequest,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mod_id = id.into_inner();
+    let mod_item = Mod::get_by_id(mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Mod not found"))?;
+
+    let (title, message, submit_label) = if mod_item.lost_forever {
+        (
+            "Mark this mod as recoverable?",
+            "Modlists that need it go back to counting it as missing rather than lost.",
+            "Mark as Recoverable",
+        )
+    } else {
+        (
+            "Mark this mod as lost forever?",
+            "Modlists that need it show as uninstallable until it is marked recoverable again.",
+            "Mark as Lost Forever",
+        )
+    };
+    Ok(confirmation_response(
+        &req,
+        Confirmation {
+            title: title.to_string(),
+            message: html! { p { (message) } },
+            action: format!("/mod/{}/toggle-lost-forever", mod_id),
+            submit_label,
+            back: format!("/mod/{}", mod_id),
+            typed: None,
+        },
+    ))
+}
+
 #[post("/mod/{id}/toggle-lost-forever")]
 pub async fn toggle_lost_forever(
     id: web::Path<u64>,
@@ -1076,6 +1406,37 @@ pub async fn toggle_lost_forever(
         .finish())
 }
 
+#[post("/modlists/{modlist_id}/mods/{mod_id}/toggle-optional")]
+pub async fn toggle_optional(
+    path: web::Path<(u64, u64)>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<impl Responder, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let (modlist_id, mod_id) = path.into_inner();
+
+    let association = ModAssociation::get_by_modlist_and_mod(modlist_id, mod_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Mod is not part of this modlist"))?;
+    association
+        .set_optional(!association.optional, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    // Back to the page of the archive table the toggle was clicked on
+    let page = query
+        .get("page")
+        .and_then(|page| page.parse::<u64>().ok())
+        .unwrap_or(1);
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/modlists/{}?page={}#required-mods", modlist_id, page),
+        ))
+        .finish())
+}
+
 #[post("/modlists/{id}/toggle-muted")]
 pub async fn toggle_muted(
     id: web::Path<u64>,
@@ -1187,6 +1548,11 @@ pub async fn rename_modlist(
         xxhash64: modlist.xxhash64,
         available: modlist.available,
         muted: modlist.muted,
+        has_unknown_downloaders: modlist.has_unknown_downloaders,
+        game_type: modlist.game_type,
+        parse_error: modlist.parse_error,
+        author: modlist.author,
+        created_at: modlist.created_at,
     };
     updated_modlist
         .update(&conn)
@@ -1198,11 +1564,148 @@ pub async fn rename_modlist(
         .finish())
 }
 
+/// Rows per page of the archive tables on the modlist details page.
+const ARCHIVES_PAGE_SIZE: u64 = 200;
+
+/// A 1-based page of one of the details page archive tables, read from
+/// its own query parameter so both tables page independently.
+struct ArchivePage {
+    param: &'static str,
+    number: u64,
+    count: u64,
+}
+
+impl ArchivePage {
+    fn from_query(
+        query: &std::collections::HashMap<String, String>,
+        param: &'static str,
+        rows: u64,
+    ) -> Self {
+        let count = rows.div_ceil(ARCHIVES_PAGE_SIZE).max(1);
+        let number = query
+            .get(param)
+            .and_then(|page| page.parse::<u64>().ok())
+            .unwrap_or(1)
+            .clamp(1, count);
+        ArchivePage {
+            param,
+            number,
+            count,
+        }
+    }
+
+    fn offset(&self) -> u64 {
+        (self.number - 1) * ARCHIVES_PAGE_SIZE
+    }
+
+    /// The current URL's query with this table moved to `number`.
+    fn url(
+        &self,
+        query: &std::collections::HashMap<String, String>,
+        number: u64,
+        anchor: &str,
+    ) -> String {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in query.iter() {
+            if key != self.param {
+                serializer.append_pair(key, value);
+            }
+        }
+        if number > 1 {
+            serializer.append_pair(self.param, &number.to_string());
+        }
+        format!("?{}#{}", serializer.finish(), anchor)
+    }
+}
+
+fn archive_pagination(
+    lang: Lang,
+    query: &std::collections::HashMap<String, String>,
+    page: &ArchivePage,
+    anchor: &str,
+) -> maud::Markup {
+    html! {
+        @if page.count > 1 {
+            div.pagination {
+                span {
+                    @if page.number > 1 {
+                        a href=(page.url(query, 1, anchor)) { (lang.t("page-first")) }
+                        " "
+                        a href=(page.url(query, page.number - 1, anchor)) { (lang.t("page-previous")) }
+                    }
+                }
+                span {
+                    (lang.tf("page-of", &[
+                        ("page", page.number.to_string()),
+                        ("count", page.count.to_string()),
+                    ]))
+                }
+                span {
+                    @if page.number < page.count {
+                        a href=(page.url(query, page.number + 1, anchor)) { (lang.t("page-next")) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// One page of an archive table on `/modlists/{id}`, as JSON.
+#[derive(Serialize)]
+struct ArchiveTable<'a> {
+    page: u64,
+    page_count: u64,
+    archives: Vec<Archive<'a>>,
+}
+
+#[derive(Serialize)]
+struct Archive<'a> {
+    #[serde(flatten)]
+    association: &'a ModAssociation,
+    #[serde(rename = "mod")]
+    mod_item: &'a Mod,
+    available: bool,
+}
+
+impl<'a> ArchiveTable<'a> {
+    fn new(page: &ArchivePage, rows: &'a [(ModAssociation, Mod)]) -> Self {
+        ArchiveTable {
+            page: page.number,
+            page_count: page.count,
+            archives: rows
+                .iter()
+                .map(|(association, mod_item)| Archive {
+                    association,
+                    mod_item,
+                    available: mod_item.is_available(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What `/modlists/{id}` shows, for `Accept: application/json`. The
+/// archive tables are paged like the page's, with `page` and
+/// `missing_page`.
+#[derive(Serialize)]
+struct ModlistDetails<'a> {
+    modlist: &'a Modlist,
+    counts: ModlistArchiveCounts,
+    readiness_rule: &'a ReadinessRule,
+    readiness: &'a ModlistReadiness,
+    archives: ArchiveTable<'a>,
+    missing_archives: ArchiveTable<'a>,
+}
+
 #[get("/modlists/{id}")]
 pub async fn details_page(
     id: web::Path<u64>,
     query: web::Query<std::collections::HashMap<String, String>>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
+    ServeFiles(serve_files): ServeFiles,
+    mode: UiMode,
+    lang: Lang,
+    format: Format,
 ) -> Result<impl Responder, actix_web::Error> {
     let conn = pool
         .get()
@@ -1214,303 +1717,329 @@ pub async fn details_page(
         .map_err(actix_web::error::ErrorInternalServerError)?
         .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
 
-    // Get mods via association table
-    let mods = Mod::get_by_modlist_id(archive_id, &conn)
+    let rule = ReadinessRule::get_by_modlist_id(archive_id, &conn)
         .map_err(actix_web::error::ErrorInternalServerError)?;
-
-    // Get associations for all mods in this modlist
-    let associations = ModAssociation::get_by_modlist_id(archive_id, &conn)
+    let readiness = ModlistReadiness::compute(&rule, &conn)
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    // Create a map from mod_id to association for quick lookup
-    use std::collections::HashMap;
-    let assoc_map: HashMap<u64, &ModAssociation> = associations
-        .iter()
-        .map(|assoc| (assoc.mod_id, assoc))
-        .collect();
-
-    // Separate unavailable mods for the missing mods table
-    let unavailable_mods: Vec<_> = mods.iter().filter(|m| !m.is_available()).cloned().collect();
-    let show_missing_table = !unavailable_mods.is_empty() && unavailable_mods.len() < 25;
-
-    // Create tuples with mods and their associations for rendering
-    let unavailable_mods_with_assocs: Vec<_> = unavailable_mods
-        .iter()
-        .map(|mod_item| {
-            let assoc = assoc_map.get(&mod_item.id).cloned();
-            (mod_item, assoc)
-        })
-        .collect();
+    let counts = ModAssociation::count_by_modlist_id(archive_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let mods_with_assocs: Vec<_> = mods
-        .iter()
-        .map(|mod_item| {
-            let assoc = assoc_map.get(&mod_item.id).cloned();
-            (mod_item, assoc)
-        })
-        .collect();
+    // Both tables are paged in SQL so a list with thousands of archives
+    // renders one page at a time.
+    let required_page = ArchivePage::from_query(&query, "page", counts.required + counts.optional);
+    let missing_page = ArchivePage::from_query(&query, "missing_page", counts.missing);
+    let mods_with_assocs = ModAssociation::get_page_by_modlist_id(
+        archive_id,
+        false,
+        ARCHIVES_PAGE_SIZE,
+        required_page.offset(),
+        &conn,
+    )
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+    let unavailable_mods_with_assocs = ModAssociation::get_page_by_modlist_id(
+        archive_id,
+        true,
+        ARCHIVES_PAGE_SIZE,
+        missing_page.offset(),
+        &conn,
+    )
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if format == Format::Json {
+        let data = ModlistDetails {
+            modlist: &modlist,
+            counts,
+            readiness_rule: &rule,
+            readiness: &readiness,
+            archives: ArchiveTable::new(&required_page, &mods_with_assocs),
+            missing_archives: ArchiveTable::new(&missing_page, &unavailable_mods_with_assocs),
+        };
+        return Ok(json_page(&data));
+    }
 
-    let page = html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                title { (modlist.name.clone()) " - Modlist Details" }
-                link rel="stylesheet" href="/res/styles.css";
+    let page = layout(
+        lang,
+        lang.tf("title-modlist-details", &[("name", modlist.name.clone())]),
+        "page-details",
+        html! {
+            (htmx())
+            @if mode.is_admin() {
+                (confirm_script())
             }
-            body.page-details {
-                div.container {
-                    div.header {
-                        a.back-link href=(if modlist.muted { "/modlists/muted" } else { "/" }) {
-                            @if modlist.muted {
-                                "← Back to Muted Modlists"
-                            } @else {
-                                "← Back to Modlists"
+        },
+        html! {
+            div.header {
+                a.back-link href=(if modlist.muted { "/modlists/muted" } else { "/" }) {
+                    @if modlist.muted {
+                        (lang.t("back-to-muted-modlists"))
+                    } @else {
+                        (lang.t("back-to-modlists"))
+                    }
+                }
+                h1 { (modlist.name.clone()) }
+                @if let Some(error) = &modlist.parse_error {
+                    p.warning-banner {
+                        span.status-badge.missing { (lang.t("parse-failed")) }
+                        (lang.t("parse-failed-detail"))
+                        code { (error) }
+                        @if mode.is_admin() {
+                            " "
+                            a href="/admin/diagnostics" { (lang.t("nav-diagnostics")) }
+                        }
+                    }
+                }
+                @if modlist.has_unknown_downloaders {
+                    p.warning-banner {
+                        span.status-badge.warning { (lang.t("status-unknown-sources")) }
+                        (lang.t("unknown-sources-detail"))
+                    }
+                }
+                div.metadata {
+                    p { strong { (lang.t("label-version")) } (modlist.version.clone()) }
+                    p {
+                        strong { (lang.t("label-filename")) }
+                        (modlist.filename.clone())
+                        @if mode.is_admin() {
+                            form method="post" action=(format!("/modlists/{}/rename", modlist.id)) style="display: inline-block; margin-left: 1rem;" {
+                                input type="text" name="new_filename" value=(modlist.filename.clone()) style="padding: 0.4rem; border: 1px solid #ccc; border-radius: 4px; margin-right: 0.5rem;" required;
+                                button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #27ae60; color: white; font-weight: 500;" {
+                                    "Rename"
+                                }
                             }
                         }
-                        h1 { (modlist.name.clone()) }
-                        div.metadata {
-                            p { strong { "Version: " } (modlist.version.clone()) }
-                            p {
-                                strong { "Filename: " }
-                                (modlist.filename.clone())
-                                form method="post" action=(format!("/modlists/{}/rename", modlist.id)) style="display: inline-block; margin-left: 1rem;" {
-                                    input type="text" name="new_filename" value=(modlist.filename.clone()) style="padding: 0.4rem; border: 1px solid #ccc; border-radius: 4px; margin-right: 0.5rem;" required;
-                                    button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #27ae60; color: white; font-weight: 500;" {
-                                        "Rename"
+                        @if serve_files && modlist.available {
+                            a.download-button href=(format!("/modlists/{}/download", modlist.id)) style="display: inline-block; margin-left: 0.5rem; padding: 0.4rem 0.8rem; border-radius: 4px; background-color: #27ae60; color: white; font-weight: 500; text-decoration: none;" {
+                                (lang.t("download"))
+                            }
+                            a href=(format!("/modlists/{}/wabbajack.metadata", modlist.id)) style="margin-left: 0.5rem;" {
+                                (lang.t("wabbajack-metadata"))
+                            }
+                            a href=(format!("/modlists/{}/bundle", modlist.id)) title=(lang.t("share-bundle-title")) style="margin-left: 0.5rem;" {
+                                (lang.t("share-bundle"))
+                            }
+                        }
+                    }
+                    p { strong { (lang.t("label-size")) } (format_size(modlist.size)) }
+                    p { strong { (lang.t("label-hash")) } span.hash { (hash_code(lang, &modlist.xxhash64)) } }
+                    div style="margin: 1em 0;" {
+                        strong { (lang.t("label-mods-available")) }
+                        (availability_bar(lang, modlist.id, counts.required - counts.missing, counts.required))
+                        @if counts.optional > 0 {
+                            (lang.tf("plus-optional", &[("count", counts.optional.to_string())]))
+                        }
+                    }
+                    p {
+                        strong { (lang.t("label-status")) }
+                        span class=(format!("status-badge {}", readiness.status.badge_class())) { (lang.modlist_status(readiness.status)) }
+                        " "
+                        (lang.tf("readiness-summary", &[
+                            ("available", readiness.available.to_string()),
+                            ("counted", readiness.counted.to_string()),
+                        ]))
+                        @if readiness.ignored > 0 {
+                            (lang.tf("readiness-ignored", &[("count", readiness.ignored.to_string())]))
+                        }
+                        @if readiness.lost_forever > 0 {
+                            (lang.tf("readiness-lost-forever", &[("count", readiness.lost_forever.to_string())]))
+                        }
+                    }
+                    @if mode.is_admin() {
+                        form.readiness-rule method="post" action=(format!("/modlists/{}/readiness", modlist.id)) {
+                            strong { "Ready when: " }
+                            label {
+                                "at least "
+                                input type="number" name="min_available_percent" min="0" max="100" step="0.1" value=(rule.min_available_percent) style="width: 5rem; padding: 0.3rem;";
+                                "% available"
+                            }
+                            label style="margin-left: 1rem;" {
+                                input type="checkbox" name="ignore_nsfw" value="true" checked[rule.ignore_nsfw];
+                                " ignore NSFW archives"
+                            }
+                            button type="submit" style="margin-left: 1rem; padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #3498db; color: white; font-weight: 500;" {
+                                "Save"
+                            }
+                        }
+                    }
+                    p {
+                        strong { (lang.t("label-muted")) }
+                        @if modlist.muted {
+                            span.status-badge.missing { (lang.t("yes")) }
+                        } @else {
+                            span { (lang.t("no")) }
+                        }
+                        @if mode.is_admin() {
+                            form method="post" action=(format!("/modlists/{}/toggle-muted", modlist.id)) style="display: inline-block;" {
+                                button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #3498db; color: white; font-weight: 500;" {
+                                    @if modlist.muted {
+                                        "Unmute Modlist"
+                                    } @else {
+                                        "Mute Modlist"
                                     }
                                 }
-                                @if modlist.available {
-                                    a.download-button href=(format!("/modlists/{}/download", modlist.id)) style="display: inline-block; margin-left: 0.5rem; padding: 0.4rem 0.8rem; border-radius: 4px; background-color: #27ae60; color: white; font-weight: 500; text-decoration: none;" {
-                                        "Download"
+                            }
+                        }
+                    }
+                    @if show_debug && mode.is_admin() {
+                        p.debug-actions style="margin-top: 1rem; padding-top: 1rem; border-top: 1px dashed #e74c3c;" {
+                            strong { "Debug: " }
+                            (confirm_button(&format!("/modlists/{}/delete", modlist.id), "danger", "Delete Modlist"))
+                        }
+                    }
+                }
+            }
+
+            h2 #missing-mods { (lang.t("heading-missing-mods")) }
+            @if counts.missing == 0 {
+                p.empty-state { (lang.t("missing-none")) }
+            } @else {
+                p { (lang.tf("missing-count", &[("count", counts.missing.to_string())])) }
+                (mod_table(&[lang.t("col-filename"), lang.t("col-name"), lang.t("col-version"), lang.t("col-size"), lang.t("col-hash"), lang.t("col-status")], html! {
+                    @for (assoc, mod_item) in &unavailable_mods_with_assocs {
+                        tr {
+                            td.filename {
+                                a href=(format!("/mod/{}?from={}", mod_item.id, modlist.id)) { (assoc.filename.clone()) }
+                            }
+                            td.name {
+                                a href=(format!("/mod/{}?from={}", mod_item.id, modlist.id)) {
+                                    @match &assoc.name {
+                                        Some(name) => { (name.clone()) }
+                                        None => { em { (lang.t("unknown")) } }
                                     }
                                 }
                             }
-                            p { strong { "Size: " } (format_size(modlist.size)) }
-                            p { strong { "Hash: " } span.hash { code { (format_hash(&modlist.xxhash64)) } } }
-                            p {
-                                strong { "Muted: " }
-                                @if modlist.muted {
-                                    span.status-badge.missing { "Yes" }
-                                } @else {
-                                    span { "No" }
+                            td.version {
+                                @match &assoc.version {
+                                    Some(version) => { (version.clone()) }
+                                    None => { em { "-" } }
                                 }
-                                form method="post" action=(format!("/modlists/{}/toggle-muted", modlist.id)) style="display: inline-block;" {
-                                    button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #3498db; color: white; font-weight: 500;" {
-                                        @if modlist.muted {
-                                            "Unmute Modlist"
-                                        } @else {
-                                            "Mute Modlist"
-                                        }
-                                    }
+                            }
+                            td.size {
+                                (format_size(mod_item.size))
+                            }
+                            td.hash {
+                                (hash_code(lang, &mod_item.xxhash64))
+                            }
+                            td.status {
+                                @if mod_item.lost_forever {
+                                    span.status-badge.missing { (lang.t("status-lost-forever")) }
+                                } @else {
+                                    span.status-badge.unavailable { (lang.t("status-unavailable")) }
                                 }
                             }
-                            @if show_debug {
-                                p.debug-actions style="margin-top: 1rem; padding-top: 1rem; border-top: 1px dashed #e74c3c;" {
-                                    strong { "Debug: " }
-                                    form method="post"
-                                         action=(format!("/modlists/{}/delete", modlist.id))
-                                         onsubmit="return confirm('Delete this modlist permanently?\\n\\nThis removes the DB row, all mod associations, and the .wabbajack file on disk. Mods referenced only by this modlist remain. Cannot be undone.');"
-                                         style="display: inline-block;" {
-                                        button type="submit" style="padding: 0.4rem 0.8rem; border-radius: 4px; border: none; cursor: pointer; background-color: #e74c3c; color: white; font-weight: 500;" {
-                                            "Delete Modlist"
-                                        }
-                                    }
+                        }
+                        @if let Some((prompt, url)) = assoc.source.manual_prompt() {
+                            tr.manual-prompt-row {
+                                td colspan="6" {
+                                    strong { (lang.t("manual-download")) }
+                                    span.manual-prompt { (prompt) }
+                                    " "
+                                    a href=(url) target="_blank" { (url) }
                                 }
                             }
                         }
                     }
+                }))
+                (archive_pagination(lang, &query, &missing_page, "missing-mods"))
+            }
 
-                    @if show_missing_table {
-                        h2 { "Missing Mods" }
-                        table.mod-table {
-                            thead {
-                                tr {
-                                    th { "Filename" }
-                                    th { "Name" }
-                                    th { "Version" }
-                                    th { "Size" }
-                                    th { "Hash" }
-                                    th { "Status" }
-                                }
+            h2 #required-mods { (lang.t("heading-required-mods")) }
+            @if mods_with_assocs.is_empty() {
+                p.empty-state { (lang.t("empty-mods")) }
+            } @else {
+                (mod_table(&[lang.t("col-filename"), lang.t("col-name"), lang.t("col-version"), lang.t("col-size"), lang.t("col-hash"), lang.t("col-status"), lang.t("col-optional")], html! {
+                    @for (assoc, mod_item) in &mods_with_assocs {
+                        tr {
+                            td.filename {
+                                a href=(format!("/mod/{}?from={}", mod_item.id, modlist.id)) { (assoc.filename.clone()) }
                             }
-                            tbody {
-                                @for (mod_item, assoc) in &unavailable_mods_with_assocs {
-                                    tr {
-                                        td.filename {
-                                            a href=(format!("/mod/{}", mod_item.id)) {
-                                                @match assoc {
-                                                    Some(assoc) => {
-                                                        (assoc.filename.clone())
-                                                    }
-                                                    None => {
-                                                        @match &mod_item.disk_filename {
-                                                            Some(disk_filename) => {
-                                                                (disk_filename.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.name {
-                                            a href=(format!("/mod/{}", mod_item.id)) {
-                                                @match assoc {
-                                                    Some(assoc) => {
-                                                        @match &assoc.name {
-                                                            Some(name) => {
-                                                                (name.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                    None => {
-                                                        em { "Unknown" }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.version {
-                                            @match assoc {
-                                                Some(assoc) => {
-                                                    @match &assoc.version {
-                                                        Some(version) => {
-                                                            (version.clone())
-                                                        }
-                                                        None => {
-                                                            em { "-" }
-                                                        }
-                                                    }
-                                                }
-                                                None => {
-                                                    em { "-" }
-                                                }
-                                            }
-                                        }
-                                        td.size {
-                                            (format_size(mod_item.size))
-                                        }
-                                        td.hash {
-                                            code { (format_hash(&mod_item.xxhash64)) }
-                                        }
-                                        td.status {
-                                            @if mod_item.lost_forever {
-                                                span.status-badge.missing { "Lost Forever" }
-                                            } @else {
-                                                span.status-badge.unavailable { "Unavailable" }
-                                            }
-                                        }
+                            td.name {
+                                a href=(format!("/mod/{}?from={}", mod_item.id, modlist.id)) {
+                                    @match &assoc.name {
+                                        Some(name) => { (name.clone()) }
+                                        None => { em { (lang.t("unknown")) } }
                                     }
                                 }
                             }
-                        }
-                    }
-
-                    h2 { "Required Mods" }
-                    @if mods.is_empty() {
-                        p.empty-state { "No mods found." }
-                    } @else {
-                        table.mod-table {
-                            thead {
-                                tr {
-                                    th { "Filename" }
-                                    th { "Name" }
-                                    th { "Version" }
-                                    th { "Size" }
-                                    th { "Hash" }
-                                    th { "Status" }
+                            td.version {
+                                @match &assoc.version {
+                                    Some(version) => { (version.clone()) }
+                                    None => { em { "-" } }
                                 }
                             }
-                            tbody {
-                                @for (mod_item, assoc) in &mods_with_assocs {
-                                    tr {
-                                        td.filename {
-                                            a href=(format!("/mod/{}", mod_item.id)) {
-                                                @match assoc {
-                                                    Some(assoc) => {
-                                                        (assoc.filename.clone())
-                                                    }
-                                                    None => {
-                                                        @match &mod_item.disk_filename {
-                                                            Some(disk_filename) => {
-                                                                (disk_filename.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.name {
-                                            a href=(format!("/mod/{}", mod_item.id)) {
-                                                @match assoc {
-                                                    Some(assoc) => {
-                                                        @match &assoc.name {
-                                                            Some(name) => {
-                                                                (name.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                    None => {
-                                                        em { "Unknown" }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.version {
-                                            @match assoc {
-                                                Some(assoc) => {
-                                                    @match &assoc.version {
-                                                        Some(version) => {
-                                                            (version.clone())
-                                                        }
-                                                        None => {
-                                                            em { "-" }
-                                                        }
-                                                    }
-                                                }
-                                                None => {
-                                                    em { "-" }
-                                                }
+                            td.size {
+                                (format_size(mod_item.size))
+                            }
+                            td.hash {
+                                (hash_code(lang, &mod_item.xxhash64))
+                            }
+                            td.status {
+                                (mod_status_badge(lang, mod_item))
+                            }
+                            td.optional {
+                                @if mode.is_admin() {
+                                    form method="post" action=(format!("/modlists/{}/mods/{}/toggle-optional?page={}", modlist.id, mod_item.id, required_page.number)) style="display: inline-block;" {
+                                        @if assoc.optional {
+                                            span.status-badge.warning { (lang.t("status-optional")) } " "
+                                            button type="submit" style="padding: 0.2rem 0.5rem; border-radius: 4px; border: none; cursor: pointer; background-color: #95a5a6; color: white;" {
+                                                "Mark Required"
                                             }
-                                        }
-                                        td.size {
-                                            (format_size(mod_item.size))
-                                        }
-                                        td.hash {
-                                            code { (format_hash(&mod_item.xxhash64)) }
-                                        }
-                                        td.status {
-                                            @if mod_item.is_available() {
-                                                span.status-badge.available { "Available" }
-                                            } @else if mod_item.lost_forever {
-                                                span.status-badge.missing { "Lost Forever" }
-                                            } @else {
-                                                span.status-badge.unavailable { "Unavailable" }
+                                        } @else {
+                                            button type="submit" style="padding: 0.2rem 0.5rem; border-radius: 4px; border: none; cursor: pointer; background-color: #95a5a6; color: white;" {
+                                                "Mark Optional"
                                             }
                                         }
                                     }
+                                } @else if assoc.optional {
+                                    span.status-badge.warning { (lang.t("status-optional")) }
                                 }
                             }
                         }
                     }
-                }
+                }))
+                (archive_pagination(lang, &query, &required_page, "required-mods"))
             }
-        }
-    };
+        },
+    );
 
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(page.into_string()))
+    Ok(html_page(page))
+}
+
+#[derive(Deserialize)]
+pub struct ReadinessRuleForm {
+    min_available_percent: f64,
+    // Unchecked checkboxes are left out of the form entirely.
+    ignore_nsfw: Option<String>,
+}
+
+#[post("/modlists/{id}/readiness")]
+pub async fn set_readiness_rule(
+    id: web::Path<u64>,
+    form: web::Form<ReadinessRuleForm>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<impl Responder, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let modlist_id = id.into_inner();
+    Modlist::get_by_id(modlist_id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+
+    if !(0.0..=100.0).contains(&form.min_available_percent) {
+        return Err(actix_web::error::ErrorBadRequest(
+            "min_available_percent must be between 0 and 100",
+        ));
+    }
+    ReadinessRule {
+        modlist_id,
+        min_available_percent: form.min_available_percent,
+        ignore_nsfw: form.ignore_nsfw.is_some(),
+    }
+    .save(&conn)
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/modlists/{}", modlist_id)))
+        .finish())
 }
@@ -0,0 +1,354 @@
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpResponse, get, post, web};
+use log::LevelFilter;
+use maud::html;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::{Deserialize, Serialize};
+
+use crate::db::modlist::Modlist;
+use crate::db::modlist_counts::{CounterMismatch, ModlistCounters};
+use crate::db::transfer_stats::{ClientTransfer, DailyTransfer};
+use crate::logging::{LogControl, UPLOAD_MODULES};
+use crate::web::components::{format_size, header_nav, layout};
+use crate::web::i18n::Lang;
+use crate::web::negotiate::{Format, html_page, json_page};
+
+/// How many days of transfers the diagnostics page sums and lists.
+const TRANSFER_HISTORY_DAYS: u32 = 30;
+
+/// A modlist with wrong stored counters, as `/admin/diagnostics` lists it
+/// in JSON. `modlist` is `None` once the modlist has been deleted.
+#[derive(Serialize)]
+struct CounterRow<'a> {
+    #[serde(flatten)]
+    mismatch: &'a CounterMismatch,
+    modlist: Option<&'a Modlist>,
+}
+
+/// A raised log level, as JSON.
+#[derive(Serialize)]
+struct LogLevel<'a> {
+    module: &'a str,
+    level: String,
+    minutes_left: u64,
+}
+
+/// What `/admin/diagnostics` shows, for `Accept: application/json`.
+#[derive(Serialize)]
+struct Diagnostics<'a> {
+    parse_failed: &'a [Modlist],
+    counter_mismatches: Vec<CounterRow<'a>>,
+    transfer_history_days: u32,
+    client_transfers: &'a [ClientTransfer],
+    daily_transfers: &'a [DailyTransfer],
+    log_overrides: Vec<LogLevel<'a>>,
+}
+
+#[get("/admin/diagnostics")]
+pub async fn diagnostics_page(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    logging: web::Data<LogControl>,
+    format: Format,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let parse_failed =
+        Modlist::get_parse_failed(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let mismatches = ModlistCounters::find_mismatches(&conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|mismatch| {
+            let modlist = Modlist::get_by_id(mismatch.modlist_id, &conn).ok().flatten();
+            (mismatch, modlist)
+        })
+        .collect::<Vec<_>>();
+    let transfers = DailyTransfer::get_recent(TRANSFER_HISTORY_DAYS, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let client_totals = ClientTransfer::totals(&transfers);
+    let log_overrides = logging.active();
+    let now = Instant::now();
+
+    if format == Format::Json {
+        return Ok(json_page(&Diagnostics {
+            parse_failed: &parse_failed,
+            counter_mismatches: mismatches
+                .iter()
+                .map(|(mismatch, modlist)| CounterRow {
+                    mismatch,
+                    modlist: modlist.as_ref(),
+                })
+                .collect(),
+            transfer_history_days: TRANSFER_HISTORY_DAYS,
+            client_transfers: &client_totals,
+            daily_transfers: &transfers,
+            log_overrides: log_overrides
+                .iter()
+                .map(|o| LogLevel {
+                    module: &o.module,
+                    level: o.level.to_string(),
+                    minutes_left: o.until.saturating_duration_since(now).as_secs().div_ceil(60),
+                })
+                .collect(),
+        }));
+    }
+
+    let page = layout(
+        Lang::English,
+        "Diagnostics",
+        "page-listing",
+        html! {},
+        html! {
+            (header_nav("Diagnostics", html! {
+                a.nav-link href="/" { "View Modlists" }
+                a.nav-link href="/admin/jobs" { "Jobs" }
+            }))
+
+            h2 { "Modlists that failed to parse" }
+            @if parse_failed.is_empty() {
+                p.empty-state { "Every stored modlist parsed successfully." }
+            } @else {
+                p {
+                    "These files are kept but hidden from the modlist listing until their metadata can be read. "
+                    "Retry them after upgrading the server."
+                }
+                form method="post" action="/admin/diagnostics/retry" {
+                    button.bootstrap-button type="submit" { "Retry All" }
+                }
+                table.modlist-table {
+                    thead {
+                        tr {
+                            th { "File" }
+                            th { "Size" }
+                            th { "Error" }
+                            th { "Actions" }
+                        }
+                    }
+                    tbody {
+                        @for modlist in &parse_failed {
+                            tr {
+                                td {
+                                    a href=(format!("/modlists/{}", modlist.id)) { (modlist.filename) }
+                                }
+                                td { (format_size(modlist.size)) }
+                                td { code { (modlist.parse_error.as_deref().unwrap_or_default()) } }
+                                td.actions {
+                                    form method="post" action=(format!("/admin/diagnostics/modlists/{}/retry", modlist.id)) {
+                                        button.job-button type="submit" { "Retry" }
+                                    }
+                                    a href=(format!("/modlists/{}/download", modlist.id)) { "Download" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            h2 { "Modlist counters" }
+            @if mismatches.is_empty() {
+                p.empty-state { "Every modlist's stored mod counts match a recount." }
+            } @else {
+                p {
+                    "The listings read these stored counts, so they are showing wrong numbers for these modlists. "
+                    "Rebuilding recounts every modlist."
+                }
+                form method="post" action="/admin/diagnostics/counts/rebuild" {
+                    button.bootstrap-button type="submit" { "Rebuild Counters" }
+                }
+                table.modlist-table {
+                    thead {
+                        tr {
+                            th { "Modlist" }
+                            th { "Stored (available / total / lost)" }
+                            th { "Actual (available / total / lost)" }
+                        }
+                    }
+                    tbody {
+                        @for (mismatch, modlist) in &mismatches {
+                            tr {
+                                td {
+                                    @match modlist {
+                                        Some(modlist) => {
+                                            a href=(format!("/modlists/{}", modlist.id)) { (modlist.name) " " (modlist.version) }
+                                        }
+                                        None => {
+                                            "Deleted modlist #" (mismatch.modlist_id)
+                                        }
+                                    }
+                                }
+                                td { (mismatch.stored.mods_available) " / " (mismatch.stored.mods_total) " / " (mismatch.stored.lost_forever) }
+                                td { (mismatch.actual.mods_available) " / " (mismatch.actual.mods_total) " / " (mismatch.actual.lost_forever) }
+                            }
+                        }
+                    }
+                }
+            }
+
+            h2 { "Transfers" }
+            @if transfers.is_empty() {
+                p.empty-state { "Nothing was uploaded or downloaded in the last " (TRANSFER_HISTORY_DAYS) " days." }
+            } @else {
+                p {
+                    "Bytes each client sent and received over the last " (TRANSFER_HISTORY_DAYS) " days. "
+                    "Requests with the API token count as the token, labelled with the machine name "
+                    "wabba-tools sends with " code { "--client-name" } "; "
+                    "otherwise a client is the signed-in user or the address it connects from."
+                }
+                table.modlist-table {
+                    thead {
+                        tr {
+                            th { "Client" }
+                            th { "Uploaded" }
+                            th { "Downloaded" }
+                        }
+                    }
+                    tbody {
+                        @for total in &client_totals {
+                            tr {
+                                td { (total.client) }
+                                td { (format_size(total.uploaded_bytes)) }
+                                td { (format_size(total.downloaded_bytes)) }
+                            }
+                        }
+                    }
+                }
+                h3 { "By day" }
+                table.modlist-table {
+                    thead {
+                        tr {
+                            th { "Day (UTC)" }
+                            th { "Client" }
+                            th { "Uploaded" }
+                            th { "Downloaded" }
+                        }
+                    }
+                    tbody {
+                        @for transfer in &transfers {
+                            tr {
+                                td { (transfer.day) }
+                                td { (transfer.client) }
+                                td { (format_size(transfer.uploaded_bytes)) }
+                                td { (format_size(transfer.downloaded_bytes)) }
+                            }
+                        }
+                    }
+                }
+            }
+
+            h2 { "Logging" }
+            p {
+                "Log some modules in more detail for a while, e.g. while an upload that keeps failing is retried. "
+                "Takes effect immediately, for uploads already running too, and runs out on its own."
+            }
+            form method="post" action="/admin/diagnostics/logging" {
+                label {
+                    "Modules "
+                    input type="text" name="modules" size="60" value=(UPLOAD_MODULES.join(", "));
+                }
+                " "
+                select name="level" {
+                    option value="debug" selected { "debug" }
+                    option value="trace" { "trace" }
+                }
+                " for "
+                select name="minutes" {
+                    option value="15" { "15 minutes" }
+                    option value="60" selected { "1 hour" }
+                    option value="240" { "4 hours" }
+                }
+                " "
+                button.bootstrap-button type="submit" { "Raise" }
+            }
+            @if log_overrides.is_empty() {
+                p.empty-state { "Every module logs at the level the environment sets." }
+            } @else {
+                table.modlist-table {
+                    thead {
+                        tr {
+                            th { "Module" }
+                            th { "Level" }
+                            th { "Minutes left" }
+                        }
+                    }
+                    tbody {
+                        @for o in &log_overrides {
+                            tr {
+                                td { code { (o.module) } }
+                                td { (o.level) }
+                                td { (o.until.saturating_duration_since(now).as_secs().div_ceil(60)) }
+                            }
+                        }
+                    }
+                }
+                form method="post" action="/admin/diagnostics/logging/reset" {
+                    button.bootstrap-button type="submit" { "Reset" }
+                }
+            }
+        },
+    );
+
+    Ok(html_page(page))
+}
+
+/// Recounts every modlist's stored mod counters.
+#[post("/admin/diagnostics/counts/rebuild")]
+pub async fn rebuild_modlist_counts(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    ModlistCounters::rebuild(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/diagnostics"))
+        .finish())
+}
+
+#[derive(Deserialize)]
+struct LogLevelForm {
+    /// Module paths, separated by commas or whitespace.
+    modules: String,
+    level: String,
+    minutes: u64,
+}
+
+/// Raises the log level of some modules for a while.
+#[post("/admin/diagnostics/logging")]
+pub async fn raise_log_level(
+    logging: web::Data<LogControl>,
+    form: web::Form<LogLevelForm>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let modules: Vec<String> = form
+        .modules
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|module| !module.is_empty())
+        .map(str::to_string)
+        .collect();
+    if modules.is_empty() {
+        return Err(actix_web::error::ErrorBadRequest("No modules given"));
+    }
+    let level: LevelFilter = form
+        .level
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Unknown log level"))?;
+    if form.minutes == 0 || form.minutes > 24 * 60 {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Minutes must be between 1 and 1440",
+        ));
+    }
+    logging.raise(&modules, level, Duration::from_secs(form.minutes * 60));
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/diagnostics"))
+        .finish())
+}
+
+/// Puts every module back to the level the environment sets.
+#[post("/admin/diagnostics/logging/reset")]
+pub async fn reset_log_levels(logging: web::Data<LogControl>) -> HttpResponse {
+    logging.reset();
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/diagnostics"))
+        .finish()
+}
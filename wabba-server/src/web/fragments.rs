@@ -0,0 +1,75 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, get, web};
+use maud::{Markup, html};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::db::data_version::DataVersion;
+use crate::db::modlist::Modlist;
+use crate::db::modlist_counts::ModlistCounters;
+use crate::web::conditional::{is_fresh, not_modified, set_validators};
+use crate::web::i18n::Lang;
+use crate::web::negotiate::Format;
+
+/// Progress bar of a modlist's available mods. Polls its own fragment
+/// endpoint so it keeps moving while bootstrap or downloads are running.
+pub fn availability_bar(
+    lang: Lang,
+    modlist_id: u64,
+    mods_available: u64,
+    mods_total: u64,
+) -> Markup {
+    // A modlist without mods has nothing missing.
+    let percent = (mods_available * 100)
+        .checked_div(mods_total)
+        .unwrap_or(100);
+    html! {
+        div.availability
+            hx-get=(format!("/fragments/modlists/{}/availability", modlist_id))
+            hx-trigger="every 10s"
+            hx-swap="outerHTML"
+            title=(lang.tf("availability-title", &[
+                ("available", mods_available.to_string()),
+                ("total", mods_total.to_string()),
+            ])) {
+            div.availability-bar {
+                div.availability-bar-fill style=(format!("width: {}%", percent)) {}
+            }
+            span.availability-label { (mods_available) " / " (mods_total) " (" (percent) "%)" }
+        }
+    }
+}
+
+#[get("/fragments/modlists/{id}/availability")]
+pub async fn modlist_availability_fragment(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    lang: Lang,
+    req: HttpRequest,
+) -> Result<impl Responder, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let data_version =
+        DataVersion::get(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    if is_fresh(&req, &data_version, lang, Format::Html) {
+        return Ok(not_modified(&data_version, lang, Format::Html));
+    }
+
+    let modlist = Modlist::get_by_id(id.into_inner(), &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Modlist not found"))?;
+    let counters = ModlistCounters::get(modlist.id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let mut response = HttpResponse::Ok();
+    set_validators(&mut response, &data_version, lang, Format::Html);
+    Ok(response.content_type("text/html; charset=utf-8").body(
+        availability_bar(
+            lang,
+            modlist.id,
+            counters.mods_available,
+            counters.mods_total,
+        )
+        .into_string(),
+    ))
+}
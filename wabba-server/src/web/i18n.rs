@@ -0,0 +1,980 @@
+//! Translations of the public pages. Each language is a catalog of message
+//! ids to text, and messages a catalog lacks fall back to English. Messages
+//! can take `{name}` arguments. The admin-only pages and controls are only
+//! in English.
+
+use std::future::{Ready, ready};
+
+use actix_session::{Session, SessionExt};
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, get, web};
+
+use crate::db::readiness::ModlistStatus;
+
+/// Session key for a language picked with `/language/{code}`, which wins
+/// over the browser's `Accept-Language`.
+const SESSION_LANGUAGE: &str = "language";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+/// Every language, in the order the picker lists them.
+pub const LANGUAGES: [Lang; 4] = [Lang::English, Lang::German, Lang::French, Lang::Spanish];
+
+impl Lang {
+    /// The ISO 639-1 code, for `lang` attributes and `/language/{code}`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::English => "en",
+            Lang::German => "de",
+            Lang::French => "fr",
+            Lang::Spanish => "es",
+        }
+    }
+
+    /// The language's name in itself, for the picker.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Lang::English => "English",
+            Lang::German => "Deutsch",
+            Lang::French => "Français",
+            Lang::Spanish => "Español",
+        }
+    }
+
+    /// Accepts a bare code or a full language tag such as `de-AT`.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?.trim();
+        LANGUAGES
+            .into_iter()
+            .find(|lang| lang.code().eq_ignore_ascii_case(primary))
+    }
+
+    /// The most preferred language of an `Accept-Language` header that
+    /// there is a catalog for.
+    fn from_accept_language(header: &str) -> Option<Self> {
+        let mut ranges: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        // Stable, so equally weighted languages keep the browser's order.
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(tag, _)| Lang::parse(tag))
+    }
+
+    pub(crate) fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::English => EN,
+            Lang::German => DE,
+            Lang::French => FR,
+            Lang::Spanish => ES,
+        }
+    }
+
+    /// The text of message `id`, in English if this language lacks it.
+    pub fn t(self, id: &'static str) -> &'static str {
+        let lookup = |catalog: &'static [(&'static str, &'static str)]| {
+            catalog
+                .iter()
+                .find(|(key, _)| *key == id)
+                .map(|(_, text)| *text)
+        };
+        lookup(self.catalog())
+            .or_else(|| lookup(EN))
+            .unwrap_or_else(|| {
+                log::warn!("No message {} in any catalog", id);
+                id
+            })
+    }
+
+    /// Message `id` with each `{name}` replaced by its argument.
+    pub fn tf(self, id: &'static str, args: &[(&str, String)]) -> String {
+        let mut text = self.t(id).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+
+    pub fn modlist_status(self, status: ModlistStatus) -> &'static str {
+        self.t(match status {
+            ModlistStatus::Ready => "status-ready",
+            ModlistStatus::InstallableEnough => "status-installable-enough",
+            ModlistStatus::MissingFiles => "status-missing-files",
+            ModlistStatus::Uninstallable => "status-uninstallable",
+        })
+    }
+}
+
+/// The language picked for this session, or else the browser's preferred
+/// one, or else English.
+impl FromRequest for Lang {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let picked = req
+            .get_session()
+            .get::<String>(SESSION_LANGUAGE)
+            .ok()
+            .flatten()
+            .and_then(|code| Lang::parse(&code));
+        let lang = picked
+            .or_else(|| {
+                req.headers()
+                    .get(header::ACCEPT_LANGUAGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(Lang::from_accept_language)
+            })
+            .unwrap_or_default();
+        ready(Ok(lang))
+    }
+}
+
+/// Remembers the language for this session and goes back to the page the
+/// picker was clicked on.
+#[get("/language/{code}")]
+pub async fn set_language(
+    code: web::Path<String>,
+    session: Session,
+    req: HttpRequest,
+) -> Result<impl Responder, actix_web::Error> {
+    let lang =
+        Lang::parse(&code).ok_or_else(|| actix_web::error::ErrorNotFound("Unknown language"))?;
+    session.insert(SESSION_LANGUAGE, lang.code())?;
+
+    // Only the path of the referring page, so this can't send anyone to
+    // another site.
+    let back = req
+        .headers()
+        .get(header::REFERER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|referer| url::Url::parse(referer).ok())
+        .map(|referer| match referer.query() {
+            Some(query) => format!("{}?{}", referer.path(), query),
+            None => referer.path().to_string(),
+        })
+        .unwrap_or_else(|| "/".to_string());
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, back))
+        .finish())
+}
+
+const EN: &[(&str, &str)] = &[
+    ("language", "Language"),
+    ("nav-modlists", "View Modlists"),
+    ("nav-all-modlists", "View All Modlists"),
+    ("nav-all-mods", "View All Mods"),
+    ("nav-muted-modlists", "View Muted Modlists"),
+    ("nav-missing-mods", "View Missing Mods"),
+    ("nav-upload", "Upload"),
+    ("nav-stats", "Stats"),
+    ("nav-jobs", "Jobs"),
+    ("nav-diagnostics", "Diagnostics"),
+    ("nav-repository", "Wabbajack Repository"),
+    (
+        "nav-repository-title",
+        "Add this URL to Wabbajack to browse these modlists in its gallery",
+    ),
+    ("back-to-modlists", "← Back to Modlists"),
+    ("back-to-muted-modlists", "← Back to Muted Modlists"),
+    ("back-to", "← Back to {name}"),
+    ("title-modlists", "Modlists"),
+    ("heading-modlists", "Wabbajack Modlists"),
+    ("title-muted-modlists", "Muted Modlists"),
+    ("title-mods", "Mods"),
+    ("title-missing-mods", "Missing Mods"),
+    ("title-modlist-details", "{name} - Modlist Details"),
+    ("title-mod-details", "{name} - Mod Details"),
+    ("filter-game", "Game:"),
+    ("filter-all-games", "All games"),
+    ("filter-author", "Author:"),
+    ("filter-all-authors", "All authors"),
+    ("filter-added-since", "Added since:"),
+    ("filter-missing-only", "Missing only"),
+    ("filter-source", "Source:"),
+    ("filter-all-sources", "All sources"),
+    ("filter-size", "Size:"),
+    ("filter-min-size", "min (e.g. 1GB)"),
+    ("filter-max-size", "max"),
+    ("filter-submit", "Filter"),
+    ("filter-reset", "Reset"),
+    ("columns", "Columns"),
+    ("columns-apply", "Apply"),
+    ("copy-hash", "Copy the full hash"),
+    ("view-table", "Table"),
+    ("view-grid", "Grid"),
+    (
+        "empty-filtered-modlists",
+        "No modlists match these filters.",
+    ),
+    ("empty-modlists", "No modlists found."),
+    ("empty-muted-modlists", "No muted modlists found."),
+    ("empty-mods", "No mods found."),
+    ("empty-missing-mods", "No missing mods found."),
+    ("col-name", "Name"),
+    ("col-version", "Version"),
+    ("col-game", "Game"),
+    ("col-author", "Author"),
+    ("col-added", "Added"),
+    ("col-filename", "Filename"),
+    ("col-size", "Size"),
+    ("col-hash", "Hash"),
+    ("col-mods-total", "Mods total"),
+    ("col-mods-available", "Mods available"),
+    ("col-modlists", "Modlists"),
+    ("col-status", "Status"),
+    ("col-optional", "Optional"),
+    ("col-when", "When"),
+    ("col-source", "Source"),
+    ("col-result", "Result"),
+    ("col-captured", "Captured"),
+    ("col-original-url", "Original URL"),
+    ("col-type", "Type"),
+    ("page-first", "← First page"),
+    ("page-previous", "‹ Previous"),
+    ("page-next", "Next page →"),
+    ("page-of", "Page {page} of {count}"),
+    ("status-available", "Available"),
+    ("status-unavailable", "Unavailable"),
+    ("status-lost-forever", "Lost Forever"),
+    ("status-unknown", "Unknown"),
+    ("status-unknown-sources", "Unknown sources"),
+    (
+        "status-unknown-sources-title",
+        "This modlist contains archives with unknown downloaders; availability numbers may be incomplete",
+    ),
+    ("status-ready", "Ready"),
+    ("status-installable-enough", "Installable enough"),
+    ("status-missing-files", "Missing files"),
+    ("status-uninstallable", "Uninstallable"),
+    ("status-optional", "Optional"),
+    ("status-downloaded", "Downloaded"),
+    ("status-failed", "Failed"),
+    (
+        "readiness-summary",
+        "{available} of {counted} counted archives available",
+    ),
+    ("readiness-ignored", ", {count} ignored"),
+    ("readiness-lost-forever", ", {count} lost forever"),
+    (
+        "availability-title",
+        "{available} of {total} mods available",
+    ),
+    ("unknown", "Unknown"),
+    ("unknown-mod", "Unknown Mod"),
+    ("manual-download", "Manual download: "),
+    ("open-page", "Open page"),
+    ("download", "Download"),
+    ("wabbajack-metadata", "Wabbajack metadata"),
+    ("share-bundle", "Share bundle"),
+    (
+        "share-bundle-title",
+        "A resumable aria2c download list with the modlist, its archives and their .meta files",
+    ),
+    ("yes", "Yes"),
+    ("no", "No"),
+    ("never", "Never"),
+    ("ok", "OK"),
+    ("hash-mismatch", "Hash mismatch"),
+    ("label-id", "ID: "),
+    ("label-version", "Version: "),
+    ("label-name", "Name: "),
+    ("label-filename", "Filename: "),
+    ("label-disk-filename", "Disk Filename: "),
+    ("label-modlist-filename", "Modlist Filename: "),
+    ("label-size", "Size: "),
+    ("label-hash", "Hash: "),
+    ("label-status", "Status: "),
+    ("label-muted", "Muted: "),
+    ("label-mods-available", "Mods available: "),
+    ("label-last-verified", "Last verified: "),
+    ("label-lost-forever", "Lost Forever: "),
+    ("not-on-disk", "Not available on disk"),
+    ("plus-optional", " (plus {count} optional)"),
+    ("parse-failed", "Parse failed"),
+    (
+        "parse-failed-detail",
+        " The metadata in this file could not be read, so its mods are unknown: ",
+    ),
+    (
+        "unknown-sources-detail",
+        " This modlist contains archives with unknown downloaders; availability numbers may be incomplete.",
+    ),
+    ("heading-missing-mods", "Missing Mods"),
+    ("missing-none", "Every required mod is available."),
+    (
+        "missing-count",
+        "{count} required mods have no file on disk.",
+    ),
+    ("heading-required-mods", "Required Mods"),
+    ("heading-source", "Source"),
+    ("heading-download-attempts", "Download Attempts"),
+    ("heading-archived-copies", "Archived Copies"),
+    (
+        "heading-same-filename",
+        "Conflicts - Mods with Same Filename",
+    ),
+    ("no-conflicts", "No conflicts found."),
+    ("heading-associated-modlists", "Associated Modlists"),
+    (
+        "no-associated-modlists",
+        "This mod is not associated with any modlists.",
+    ),
+    (
+        "heading-other-versions",
+        "Other Versions - Mods with Same Name",
+    ),
+    ("no-other-versions", "No other versions found."),
+    ("title-stats", "Stats"),
+    ("label-disk-usage", "Disk usage: "),
+    ("count-of-total", "{count} of {total}"),
+    ("heading-digests", "Digests"),
+    ("empty-digests", "No digests have been generated yet."),
+    ("col-period", "Period"),
+    ("col-new-modlists", "New modlists"),
+    ("col-availability-changes", "Availability changes"),
+    ("col-dead-links", "Dead links"),
+    ("heading-latest-digest", "Latest digest"),
+    ("title-digest", "Digest"),
+    ("digest-period", "From {start} to {end}"),
+    ("heading-new-uploads", "New uploads"),
+    ("digest-nothing-new", "Nothing new."),
+    ("digest-new-mods", "{count} new mods ({size})"),
+    ("heading-availability-changes", "Availability changes"),
+    ("digest-no-changes", "No changes."),
+    ("col-modlist", "Modlist"),
+    ("col-before", "Before"),
+    ("col-after", "After"),
+    ("col-total", "Total"),
+    ("digest-new", "New"),
+    ("heading-dead-links", "Newly dead links"),
+    ("digest-none", "None."),
+    ("col-mod", "Mod"),
+    ("col-failures", "Failures"),
+    ("heading-disk-usage", "Disk usage"),
+];
+
+const DE: &[(&str, &str)] = &[
+    ("language", "Sprache"),
+    ("nav-modlists", "Modlisten anzeigen"),
+    ("nav-all-modlists", "Alle Modlisten anzeigen"),
+    ("nav-all-mods", "Alle Mods anzeigen"),
+    ("nav-muted-modlists", "Stummgeschaltete Modlisten anzeigen"),
+    ("nav-missing-mods", "Fehlende Mods anzeigen"),
+    ("nav-upload", "Hochladen"),
+    ("nav-stats", "Statistik"),
+    ("nav-jobs", "Aufträge"),
+    ("nav-diagnostics", "Diagnose"),
+    ("nav-repository", "Wabbajack-Repository"),
+    (
+        "nav-repository-title",
+        "Diese URL in Wabbajack hinzufügen, um diese Modlisten in der Galerie zu durchsuchen",
+    ),
+    ("back-to-modlists", "← Zurück zu den Modlisten"),
+    (
+        "back-to-muted-modlists",
+        "← Zurück zu den stummgeschalteten Modlisten",
+    ),
+    ("back-to", "← Zurück zu {name}"),
+    ("title-modlists", "Modlisten"),
+    ("heading-modlists", "Wabbajack-Modlisten"),
+    ("title-muted-modlists", "Stummgeschaltete Modlisten"),
+    ("title-mods", "Mods"),
+    ("title-missing-mods", "Fehlende Mods"),
+    ("title-modlist-details", "{name} - Modlisten-Details"),
+    ("title-mod-details", "{name} - Mod-Details"),
+    ("filter-game", "Spiel:"),
+    ("filter-all-games", "Alle Spiele"),
+    ("filter-author", "Autor:"),
+    ("filter-all-authors", "Alle Autoren"),
+    ("filter-added-since", "Hinzugefügt seit:"),
+    ("filter-missing-only", "Nur fehlende"),
+    ("filter-source", "Quelle:"),
+    ("filter-all-sources", "Alle Quellen"),
+    ("filter-size", "Größe:"),
+    ("filter-min-size", "min. (z. B. 1GB)"),
+    ("filter-max-size", "max."),
+    ("filter-submit", "Filtern"),
+    ("filter-reset", "Zurücksetzen"),
+    ("columns", "Spalten"),
+    ("columns-apply", "Übernehmen"),
+    ("copy-hash", "Vollständigen Hash kopieren"),
+    ("view-table", "Tabelle"),
+    ("view-grid", "Raster"),
+    (
+        "empty-filtered-modlists",
+        "Keine Modlisten entsprechen diesen Filtern.",
+    ),
+    ("empty-modlists", "Keine Modlisten gefunden."),
+    (
+        "empty-muted-modlists",
+        "Keine stummgeschalteten Modlisten gefunden.",
+    ),
+    ("empty-mods", "Keine Mods gefunden."),
+    ("empty-missing-mods", "Keine fehlenden Mods gefunden."),
+    ("col-name", "Name"),
+    ("col-version", "Version"),
+    ("col-game", "Spiel"),
+    ("col-author", "Autor"),
+    ("col-added", "Hinzugefügt"),
+    ("col-filename", "Dateiname"),
+    ("col-size", "Größe"),
+    ("col-hash", "Hash"),
+    ("col-mods-total", "Mods gesamt"),
+    ("col-mods-available", "Mods verfügbar"),
+    ("col-modlists", "Modlisten"),
+    ("col-status", "Status"),
+    ("col-optional", "Optional"),
+    ("col-when", "Wann"),
+    ("col-source", "Quelle"),
+    ("col-result", "Ergebnis"),
+    ("col-captured", "Erfasst"),
+    ("col-original-url", "Ursprüngliche URL"),
+    ("col-type", "Typ"),
+    ("page-first", "← Erste Seite"),
+    ("page-previous", "‹ Zurück"),
+    ("page-next", "Nächste Seite →"),
+    ("page-of", "Seite {page} von {count}"),
+    ("status-available", "Verfügbar"),
+    ("status-unavailable", "Nicht verfügbar"),
+    ("status-lost-forever", "Für immer verloren"),
+    ("status-unknown", "Unbekannt"),
+    ("status-unknown-sources", "Unbekannte Quellen"),
+    (
+        "status-unknown-sources-title",
+        "Diese Modliste enthält Archive mit unbekannten Downloadern; die Verfügbarkeit ist möglicherweise unvollständig",
+    ),
+    ("status-ready", "Bereit"),
+    ("status-installable-enough", "Ausreichend installierbar"),
+    ("status-missing-files", "Fehlende Dateien"),
+    ("status-uninstallable", "Nicht installierbar"),
+    ("status-optional", "Optional"),
+    ("status-downloaded", "Heruntergeladen"),
+    ("status-failed", "Fehlgeschlagen"),
+    (
+        "readiness-summary",
+        "{available} von {counted} gezählten Archiven verfügbar",
+    ),
+    ("readiness-ignored", ", {count} ignoriert"),
+    ("readiness-lost-forever", ", {count} für immer verloren"),
+    (
+        "availability-title",
+        "{available} von {total} Mods verfügbar",
+    ),
+    ("unknown", "Unbekannt"),
+    ("unknown-mod", "Unbekannter Mod"),
+    ("manual-download", "Manueller Download: "),
+    ("open-page", "Seite öffnen"),
+    ("download", "Herunterladen"),
+    ("wabbajack-metadata", "Wabbajack-Metadaten"),
+    ("share-bundle", "Weitergabe-Paket"),
+    (
+        "share-bundle-title",
+        "Eine fortsetzbare aria2c-Downloadliste mit der Modliste, ihren Archiven und deren .meta-Dateien",
+    ),
+    ("yes", "Ja"),
+    ("no", "Nein"),
+    ("never", "Nie"),
+    ("ok", "OK"),
+    ("hash-mismatch", "Hash stimmt nicht überein"),
+    ("label-id", "ID: "),
+    ("label-version", "Version: "),
+    ("label-name", "Name: "),
+    ("label-filename", "Dateiname: "),
+    ("label-disk-filename", "Dateiname auf der Festplatte: "),
+    ("label-modlist-filename", "Dateiname in der Modliste: "),
+    ("label-size", "Größe: "),
+    ("label-hash", "Hash: "),
+    ("label-status", "Status: "),
+    ("label-muted", "Stummgeschaltet: "),
+    ("label-mods-available", "Verfügbare Mods: "),
+    ("label-last-verified", "Zuletzt geprüft: "),
+    ("label-lost-forever", "Für immer verloren: "),
+    ("not-on-disk", "Nicht auf der Festplatte vorhanden"),
+    ("plus-optional", " (plus {count} optionale)"),
+    ("parse-failed", "Lesen fehlgeschlagen"),
+    (
+        "parse-failed-detail",
+        " Die Metadaten dieser Datei konnten nicht gelesen werden, daher sind ihre Mods unbekannt: ",
+    ),
+    (
+        "unknown-sources-detail",
+        " Diese Modliste enthält Archive mit unbekannten Downloadern; die Verfügbarkeit ist möglicherweise unvollständig.",
+    ),
+    ("heading-missing-mods", "Fehlende Mods"),
+    ("missing-none", "Alle benötigten Mods sind verfügbar."),
+    (
+        "missing-count",
+        "Für {count} benötigte Mods gibt es keine Datei.",
+    ),
+    ("heading-required-mods", "Benötigte Mods"),
+    ("heading-source", "Quelle"),
+    ("heading-download-attempts", "Downloadversuche"),
+    ("heading-archived-copies", "Archivierte Kopien"),
+    (
+        "heading-same-filename",
+        "Konflikte - Mods mit gleichem Dateinamen",
+    ),
+    ("no-conflicts", "Keine Konflikte gefunden."),
+    ("heading-associated-modlists", "Zugehörige Modlisten"),
+    (
+        "no-associated-modlists",
+        "Dieser Mod gehört zu keiner Modliste.",
+    ),
+    (
+        "heading-other-versions",
+        "Andere Versionen - Mods mit gleichem Namen",
+    ),
+    ("no-other-versions", "Keine anderen Versionen gefunden."),
+    ("title-stats", "Statistik"),
+    ("label-disk-usage", "Speicherbelegung: "),
+    ("count-of-total", "{count} von {total}"),
+    ("heading-digests", "Zusammenfassungen"),
+    (
+        "empty-digests",
+        "Es wurden noch keine Zusammenfassungen erstellt.",
+    ),
+    ("col-period", "Zeitraum"),
+    ("col-new-modlists", "Neue Modlisten"),
+    ("col-availability-changes", "Verfügbarkeitsänderungen"),
+    ("col-dead-links", "Tote Links"),
+    ("heading-latest-digest", "Neueste Zusammenfassung"),
+    ("title-digest", "Zusammenfassung"),
+    ("digest-period", "Von {start} bis {end}"),
+    ("heading-new-uploads", "Neue Uploads"),
+    ("digest-nothing-new", "Nichts Neues."),
+    ("digest-new-mods", "{count} neue Mods ({size})"),
+    ("heading-availability-changes", "Verfügbarkeitsänderungen"),
+    ("digest-no-changes", "Keine Änderungen."),
+    ("col-modlist", "Modliste"),
+    ("col-before", "Vorher"),
+    ("col-after", "Nachher"),
+    ("col-total", "Gesamt"),
+    ("digest-new", "Neu"),
+    ("heading-dead-links", "Neu tote Links"),
+    ("digest-none", "Keine."),
+    ("col-mod", "Mod"),
+    ("col-failures", "Fehlschläge"),
+    ("heading-disk-usage", "Speicherbelegung"),
+];
+
+const FR: &[(&str, &str)] = &[
+    ("language", "Langue"),
+    ("nav-modlists", "Voir les modlists"),
+    ("nav-all-modlists", "Voir toutes les modlists"),
+    ("nav-all-mods", "Voir tous les mods"),
+    ("nav-muted-modlists", "Voir les modlists masquées"),
+    ("nav-missing-mods", "Voir les mods manquants"),
+    ("nav-upload", "Envoyer"),
+    ("nav-stats", "Statistiques"),
+    ("nav-jobs", "Tâches"),
+    ("nav-diagnostics", "Diagnostics"),
+    ("nav-repository", "Dépôt Wabbajack"),
+    (
+        "nav-repository-title",
+        "Ajoutez cette URL à Wabbajack pour parcourir ces modlists dans sa galerie",
+    ),
+    ("back-to-modlists", "← Retour aux modlists"),
+    ("back-to-muted-modlists", "← Retour aux modlists masquées"),
+    ("back-to", "← Retour à {name}"),
+    ("title-modlists", "Modlists"),
+    ("heading-modlists", "Modlists Wabbajack"),
+    ("title-muted-modlists", "Modlists masquées"),
+    ("title-mods", "Mods"),
+    ("title-missing-mods", "Mods manquants"),
+    ("title-modlist-details", "{name} - Détails de la modlist"),
+    ("title-mod-details", "{name} - Détails du mod"),
+    ("filter-game", "Jeu :"),
+    ("filter-all-games", "Tous les jeux"),
+    ("filter-author", "Auteur :"),
+    ("filter-all-authors", "Tous les auteurs"),
+    ("filter-added-since", "Ajoutées depuis :"),
+    ("filter-missing-only", "Manquants uniquement"),
+    ("filter-source", "Source :"),
+    ("filter-all-sources", "Toutes les sources"),
+    ("filter-size", "Taille :"),
+    ("filter-min-size", "min (ex. 1GB)"),
+    ("filter-max-size", "max"),
+    ("filter-submit", "Filtrer"),
+    ("filter-reset", "Réinitialiser"),
+    ("columns", "Colonnes"),
+    ("columns-apply", "Appliquer"),
+    ("copy-hash", "Copier le hash complet"),
+    ("view-table", "Tableau"),
+    ("view-grid", "Grille"),
+    (
+        "empty-filtered-modlists",
+        "Aucune modlist ne correspond à ces filtres.",
+    ),
+    ("empty-modlists", "Aucune modlist trouvée."),
+    ("empty-muted-modlists", "Aucune modlist masquée trouvée."),
+    ("empty-mods", "Aucun mod trouvé."),
+    ("empty-missing-mods", "Aucun mod manquant trouvé."),
+    ("col-name", "Nom"),
+    ("col-version", "Version"),
+    ("col-game", "Jeu"),
+    ("col-author", "Auteur"),
+    ("col-added", "Ajoutée"),
+    ("col-filename", "Nom de fichier"),
+    ("col-size", "Taille"),
+    ("col-hash", "Hash"),
+    ("col-mods-total", "Mods au total"),
+    ("col-mods-available", "Mods disponibles"),
+    ("col-modlists", "Modlists"),
+    ("col-status", "Statut"),
+    ("col-optional", "Facultatif"),
+    ("col-when", "Quand"),
+    ("col-source", "Source"),
+    ("col-result", "Résultat"),
+    ("col-captured", "Capturé"),
+    ("col-original-url", "URL d'origine"),
+    ("col-type", "Type"),
+    ("page-first", "← Première page"),
+    ("page-previous", "‹ Précédente"),
+    ("page-next", "Page suivante →"),
+    ("page-of", "Page {page} sur {count}"),
+    ("status-available", "Disponible"),
+    ("status-unavailable", "Indisponible"),
+    ("status-lost-forever", "Perdu à jamais"),
+    ("status-unknown", "Inconnu"),
+    ("status-unknown-sources", "Sources inconnues"),
+    (
+        "status-unknown-sources-title",
+        "Cette modlist contient des archives aux téléchargeurs inconnus ; la disponibilité peut être incomplète",
+    ),
+    ("status-ready", "Prête"),
+    ("status-installable-enough", "Suffisamment installable"),
+    ("status-missing-files", "Fichiers manquants"),
+    ("status-uninstallable", "Non installable"),
+    ("status-optional", "Facultatif"),
+    ("status-downloaded", "Téléchargé"),
+    ("status-failed", "Échec"),
+    (
+        "readiness-summary",
+        "{available} archives comptées disponibles sur {counted}",
+    ),
+    ("readiness-ignored", ", {count} ignorées"),
+    ("readiness-lost-forever", ", {count} perdues à jamais"),
+    (
+        "availability-title",
+        "{available} mods disponibles sur {total}",
+    ),
+    ("unknown", "Inconnu"),
+    ("unknown-mod", "Mod inconnu"),
+    ("manual-download", "Téléchargement manuel : "),
+    ("open-page", "Ouvrir la page"),
+    ("download", "Télécharger"),
+    ("wabbajack-metadata", "Métadonnées Wabbajack"),
+    ("share-bundle", "Paquet de partage"),
+    (
+        "share-bundle-title",
+        "Une liste de téléchargement aria2c reprenable avec la modlist, ses archives et leurs fichiers .meta",
+    ),
+    ("yes", "Oui"),
+    ("no", "Non"),
+    ("never", "Jamais"),
+    ("ok", "OK"),
+    ("hash-mismatch", "Hash différent"),
+    ("label-id", "ID : "),
+    ("label-version", "Version : "),
+    ("label-name", "Nom : "),
+    ("label-filename", "Nom de fichier : "),
+    ("label-disk-filename", "Nom de fichier sur disque : "),
+    (
+        "label-modlist-filename",
+        "Nom de fichier dans la modlist : ",
+    ),
+    ("label-size", "Taille : "),
+    ("label-hash", "Hash : "),
+    ("label-status", "Statut : "),
+    ("label-muted", "Masquée : "),
+    ("label-mods-available", "Mods disponibles : "),
+    ("label-last-verified", "Dernière vérification : "),
+    ("label-lost-forever", "Perdu à jamais : "),
+    ("not-on-disk", "Absent du disque"),
+    ("plus-optional", " (plus {count} facultatifs)"),
+    ("parse-failed", "Lecture impossible"),
+    (
+        "parse-failed-detail",
+        " Les métadonnées de ce fichier n'ont pas pu être lues, ses mods sont donc inconnus : ",
+    ),
+    (
+        "unknown-sources-detail",
+        " Cette modlist contient des archives aux téléchargeurs inconnus ; la disponibilité peut être incomplète.",
+    ),
+    ("heading-missing-mods", "Mods manquants"),
+    ("missing-none", "Tous les mods requis sont disponibles."),
+    (
+        "missing-count",
+        "{count} mods requis n'ont aucun fichier sur le disque.",
+    ),
+    ("heading-required-mods", "Mods requis"),
+    ("heading-source", "Source"),
+    ("heading-download-attempts", "Tentatives de téléchargement"),
+    ("heading-archived-copies", "Copies archivées"),
+    (
+        "heading-same-filename",
+        "Conflits - Mods avec le même nom de fichier",
+    ),
+    ("no-conflicts", "Aucun conflit trouvé."),
+    ("heading-associated-modlists", "Modlists associées"),
+    (
+        "no-associated-modlists",
+        "Ce mod n'est associé à aucune modlist.",
+    ),
+    (
+        "heading-other-versions",
+        "Autres versions - Mods avec le même nom",
+    ),
+    ("no-other-versions", "Aucune autre version trouvée."),
+    ("title-stats", "Statistiques"),
+    ("label-disk-usage", "Espace disque : "),
+    ("count-of-total", "{count} sur {total}"),
+    ("heading-digests", "Résumés"),
+    ("empty-digests", "Aucun résumé n'a encore été généré."),
+    ("col-period", "Période"),
+    ("col-new-modlists", "Nouvelles modlists"),
+    ("col-availability-changes", "Changements de disponibilité"),
+    ("col-dead-links", "Liens morts"),
+    ("heading-latest-digest", "Dernier résumé"),
+    ("title-digest", "Résumé"),
+    ("digest-period", "Du {start} au {end}"),
+    ("heading-new-uploads", "Nouveaux envois"),
+    ("digest-nothing-new", "Rien de nouveau."),
+    ("digest-new-mods", "{count} nouveaux mods ({size})"),
+    (
+        "heading-availability-changes",
+        "Changements de disponibilité",
+    ),
+    ("digest-no-changes", "Aucun changement."),
+    ("col-modlist", "Modlist"),
+    ("col-before", "Avant"),
+    ("col-after", "Après"),
+    ("col-total", "Total"),
+    ("digest-new", "Nouvelle"),
+    ("heading-dead-links", "Nouveaux liens morts"),
+    ("digest-none", "Aucun."),
+    ("col-mod", "Mod"),
+    ("col-failures", "Échecs"),
+    ("heading-disk-usage", "Espace disque"),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("language", "Idioma"),
+    ("nav-modlists", "Ver modlists"),
+    ("nav-all-modlists", "Ver todas las modlists"),
+    ("nav-all-mods", "Ver todos los mods"),
+    ("nav-muted-modlists", "Ver modlists silenciadas"),
+    ("nav-missing-mods", "Ver mods que faltan"),
+    ("nav-upload", "Subir"),
+    ("nav-stats", "Estadísticas"),
+    ("nav-jobs", "Tareas"),
+    ("nav-diagnostics", "Diagnóstico"),
+    ("nav-repository", "Repositorio de Wabbajack"),
+    (
+        "nav-repository-title",
+        "Añade esta URL a Wabbajack para explorar estas modlists en su galería",
+    ),
+    ("back-to-modlists", "← Volver a las modlists"),
+    (
+        "back-to-muted-modlists",
+        "← Volver a las modlists silenciadas",
+    ),
+    ("back-to", "← Volver a {name}"),
+    ("title-modlists", "Modlists"),
+    ("heading-modlists", "Modlists de Wabbajack"),
+    ("title-muted-modlists", "Modlists silenciadas"),
+    ("title-mods", "Mods"),
+    ("title-missing-mods", "Mods que faltan"),
+    ("title-modlist-details", "{name} - Detalles de la modlist"),
+    ("title-mod-details", "{name} - Detalles del mod"),
+    ("filter-game", "Juego:"),
+    ("filter-all-games", "Todos los juegos"),
+    ("filter-author", "Autor:"),
+    ("filter-all-authors", "Todos los autores"),
+    ("filter-added-since", "Añadidas desde:"),
+    ("filter-missing-only", "Solo los que faltan"),
+    ("filter-source", "Origen:"),
+    ("filter-all-sources", "Todos los orígenes"),
+    ("filter-size", "Tamaño:"),
+    ("filter-min-size", "mín. (p. ej. 1GB)"),
+    ("filter-max-size", "máx."),
+    ("filter-submit", "Filtrar"),
+    ("filter-reset", "Restablecer"),
+    ("columns", "Columnas"),
+    ("columns-apply", "Aplicar"),
+    ("copy-hash", "Copiar el hash completo"),
+    ("view-table", "Tabla"),
+    ("view-grid", "Cuadrícula"),
+    (
+        "empty-filtered-modlists",
+        "Ninguna modlist coincide con estos filtros.",
+    ),
+    ("empty-modlists", "No se encontraron modlists."),
+    (
+        "empty-muted-modlists",
+        "No se encontraron modlists silenciadas.",
+    ),
+    ("empty-mods", "No se encontraron mods."),
+    ("empty-missing-mods", "No falta ningún mod."),
+    ("col-name", "Nombre"),
+    ("col-version", "Versión"),
+    ("col-game", "Juego"),
+    ("col-author", "Autor"),
+    ("col-added", "Añadida"),
+    ("col-filename", "Archivo"),
+    ("col-size", "Tamaño"),
+    ("col-hash", "Hash"),
+    ("col-mods-total", "Mods en total"),
+    ("col-mods-available", "Mods disponibles"),
+    ("col-modlists", "Modlists"),
+    ("col-status", "Estado"),
+    ("col-optional", "Opcional"),
+    ("col-when", "Cuándo"),
+    ("col-source", "Origen"),
+    ("col-result", "Resultado"),
+    ("col-captured", "Capturado"),
+    ("col-original-url", "URL original"),
+    ("col-type", "Tipo"),
+    ("page-first", "← Primera página"),
+    ("page-previous", "‹ Anterior"),
+    ("page-next", "Página siguiente →"),
+    ("page-of", "Página {page} de {count}"),
+    ("status-available", "Disponible"),
+    ("status-unavailable", "No disponible"),
+    ("status-lost-forever", "Perdido para siempre"),
+    ("status-unknown", "Desconocido"),
+    ("status-unknown-sources", "Orígenes desconocidos"),
+    (
+        "status-unknown-sources-title",
+        "Esta modlist contiene archivos con descargadores desconocidos; la disponibilidad puede estar incompleta",
+    ),
+    ("status-ready", "Lista"),
+    ("status-installable-enough", "Suficientemente instalable"),
+    ("status-missing-files", "Faltan archivos"),
+    ("status-uninstallable", "No instalable"),
+    ("status-optional", "Opcional"),
+    ("status-downloaded", "Descargado"),
+    ("status-failed", "Fallido"),
+    (
+        "readiness-summary",
+        "{available} de {counted} archivos contados disponibles",
+    ),
+    ("readiness-ignored", ", {count} ignorados"),
+    ("readiness-lost-forever", ", {count} perdidos para siempre"),
+    (
+        "availability-title",
+        "{available} de {total} mods disponibles",
+    ),
+    ("unknown", "Desconocido"),
+    ("unknown-mod", "Mod desconocido"),
+    ("manual-download", "Descarga manual: "),
+    ("open-page", "Abrir página"),
+    ("download", "Descargar"),
+    ("wabbajack-metadata", "Metadatos de Wabbajack"),
+    ("share-bundle", "Paquete para compartir"),
+    (
+        "share-bundle-title",
+        "Una lista de descargas de aria2c reanudable con la modlist, sus archivos y sus ficheros .meta",
+    ),
+    ("yes", "Sí"),
+    ("no", "No"),
+    ("never", "Nunca"),
+    ("ok", "OK"),
+    ("hash-mismatch", "Hash distinto"),
+    ("label-id", "ID: "),
+    ("label-version", "Versión: "),
+    ("label-name", "Nombre: "),
+    ("label-filename", "Archivo: "),
+    ("label-disk-filename", "Archivo en disco: "),
+    ("label-modlist-filename", "Archivo en la modlist: "),
+    ("label-size", "Tamaño: "),
+    ("label-hash", "Hash: "),
+    ("label-status", "Estado: "),
+    ("label-muted", "Silenciada: "),
+    ("label-mods-available", "Mods disponibles: "),
+    ("label-last-verified", "Última verificación: "),
+    ("label-lost-forever", "Perdido para siempre: "),
+    ("not-on-disk", "No está en el disco"),
+    ("plus-optional", " (más {count} opcionales)"),
+    ("parse-failed", "Error de lectura"),
+    (
+        "parse-failed-detail",
+        " No se pudieron leer los metadatos de este archivo, así que sus mods son desconocidos: ",
+    ),
+    (
+        "unknown-sources-detail",
+        " Esta modlist contiene archivos con descargadores desconocidos; la disponibilidad puede estar incompleta.",
+    ),
+    ("heading-missing-mods", "Mods que faltan"),
+    (
+        "missing-none",
+        "Todos los mods necesarios están disponibles.",
+    ),
+    (
+        "missing-count",
+        "{count} mods necesarios no tienen archivo en el disco.",
+    ),
+    ("heading-required-mods", "Mods necesarios"),
+    ("heading-source", "Origen"),
+    ("heading-download-attempts", "Intentos de descarga"),
+    ("heading-archived-copies", "Copias archivadas"),
+    (
+        "heading-same-filename",
+        "Conflictos - Mods con el mismo nombre de archivo",
+    ),
+    ("no-conflicts", "No se encontraron conflictos."),
+    ("heading-associated-modlists", "Modlists asociadas"),
+    (
+        "no-associated-modlists",
+        "Este mod no está asociado a ninguna modlist.",
+    ),
+    (
+        "heading-other-versions",
+        "Otras versiones - Mods con el mismo nombre",
+    ),
+    ("no-other-versions", "No se encontraron otras versiones."),
+    ("title-stats", "Estadísticas"),
+    ("label-disk-usage", "Uso de disco: "),
+    ("count-of-total", "{count} de {total}"),
+    ("heading-digests", "Resúmenes"),
+    ("empty-digests", "Todavía no se ha generado ningún resumen."),
+    ("col-period", "Periodo"),
+    ("col-new-modlists", "Modlists nuevas"),
+    ("col-availability-changes", "Cambios de disponibilidad"),
+    ("col-dead-links", "Enlaces caídos"),
+    ("heading-latest-digest", "Último resumen"),
+    ("title-digest", "Resumen"),
+    ("digest-period", "Del {start} al {end}"),
+    ("heading-new-uploads", "Nuevas subidas"),
+    ("digest-nothing-new", "Nada nuevo."),
+    ("digest-new-mods", "{count} mods nuevos ({size})"),
+    ("heading-availability-changes", "Cambios de disponibilidad"),
+    ("digest-no-changes", "Sin cambios."),
+    ("col-modlist", "Modlist"),
+    ("col-before", "Antes"),
+    ("col-after", "Después"),
+    ("col-total", "Total"),
+    ("digest-new", "Nueva"),
+    ("heading-dead-links", "Enlaces caídos recientes"),
+    ("digest-none", "Ninguno."),
+    ("col-mod", "Mod"),
+    ("col-failures", "Fallos"),
+    ("heading-disk-usage", "Uso de disco"),
+];
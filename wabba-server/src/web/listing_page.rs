@@ -1,421 +1,1198 @@
-use actix_web::{HttpResponse, Responder, get, web};
-use maud::html;
-use r2d2::Pool;
+use std::collections::HashMap;
+
+use actix_session::Session;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, get, web};
+use futures_util::{StreamExt, stream};
+use maud::{Markup, PreEscaped, html};
+use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
+use serde::Serialize;
+use tokio::sync::mpsc;
 
-use crate::db::mod_data::Mod;
+use crate::config::{ServeFiles, UiMode};
+use crate::db::data_version::DataVersion;
+use crate::db::mod_association::ModAssociation;
+use crate::db::mod_data::{Mod, ModListingFilter};
 use crate::db::modlist::Modlist;
+use crate::db::readiness::{ModlistCounts, ModlistReadiness, ModlistStatus};
+use crate::slow_log::carry_origin;
+use crate::web::components::{format_size, hash_code, header_nav, htmx, layout, mod_status_badge};
+use crate::web::conditional::{is_fresh, not_modified, set_validators};
+use crate::web::fragments::availability_bar;
+use crate::web::i18n::Lang;
+use crate::web::negotiate::Format;
+use crate::web::preferences::{Columns, MODLISTS, MODS, MUTED_MODLISTS};
+use wabba_protocol::archive_state::SOURCE_TYPES;
 
-fn format_size(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} B", bytes)
+/// Rows per page on `/mods`. Pages are addressed with a keyset cursor
+/// (`after_name` + `after_id`) rather than an offset so deep pages stay fast.
+const MODS_PAGE_SIZE: u64 = 500;
+
+/// Rows rendered per streamed body chunk.
+const ROW_CHUNK_SIZE: usize = 100;
+
+/// Placeholders spliced out of rendered markup, so what comes before them
+/// can be sent while what goes in their place is still being queried.
+const RESULTS_MARKER: &str = "<!--wabba-results-->";
+const ROWS_MARKER: &str = "<!--wabba-rows-->";
+
+/// Parses sizes like `1GB`, `500 MB` or `1024` (bytes) using the same
+/// binary units as `format_size`.
+fn parse_size(input: &str) -> Option<u64> {
+    let input = input.trim().to_uppercase();
+    if input.is_empty() {
+        return None;
     }
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit.trim() {
+        "" | "B" => 1u64,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        "T" | "TB" | "TIB" => 1024 * 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
 }
 
-fn format_hash(hash: &str) -> String {
-    if hash.len() > 16 {
-        format!("{}...", &hash[..16])
-    } else {
-        hash.to_string()
+fn format_date(unix_seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(unix_seconds, 0)
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| unix_seconds.to_string())
+}
+
+/// The per-column filters above the modlist tables, read from the query
+/// string. Empty values mean "any".
+struct ModlistColumnFilter {
+    game: Option<String>,
+    author: Option<String>,
+    added_after_input: String,
+    added_after: Option<i64>,
+}
+
+impl ModlistColumnFilter {
+    fn from_query(query: &HashMap<String, String>) -> Self {
+        let value = |key: &str| {
+            query
+                .get(key)
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let added_after_input = value("added_after").unwrap_or_default();
+        let added_after = chrono::NaiveDate::parse_from_str(&added_after_input, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|time| time.and_utc().timestamp());
+        ModlistColumnFilter {
+            game: value("game"),
+            author: value("author"),
+            added_after_input,
+            added_after,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.game.is_some() || self.author.is_some() || self.added_after.is_some()
+    }
+
+    fn matches(&self, modlist: &Modlist) -> bool {
+        self.game
+            .as_ref()
+            .is_none_or(|game| modlist.game_type.as_ref() == Some(game))
+            && self
+                .author
+                .as_ref()
+                .is_none_or(|author| modlist.author.as_ref() == Some(author))
+            && self
+                .added_after
+                .is_none_or(|added_after| modlist.created_at >= added_after)
+    }
+
+    fn form(
+        &self,
+        lang: Lang,
+        action: &str,
+        sort: Option<ModlistSort>,
+        game_types: &[String],
+        authors: &[String],
+    ) -> Markup {
+        html! {
+            form.filter-form method="get" action=(action) {
+                @if let Some(sort) = sort {
+                    input type="hidden" name="sort" value=(sort.param());
+                }
+                label for="game-filter" { (lang.t("filter-game")) }
+                select id="game-filter" name="game" {
+                    option value="" { (lang.t("filter-all-games")) }
+                    @for game_type in game_types {
+                        option value=(game_type) selected[self.game.as_deref() == Some(game_type.as_str())] { (game_type) }
+                    }
+                }
+                label for="author-filter" { (lang.t("filter-author")) }
+                select id="author-filter" name="author" {
+                    option value="" { (lang.t("filter-all-authors")) }
+                    @for author in authors {
+                        option value=(author) selected[self.author.as_deref() == Some(author.as_str())] { (author) }
+                    }
+                }
+                label for="added-after-filter" { (lang.t("filter-added-since")) }
+                input id="added-after-filter" type="date" name="added_after" value=(self.added_after_input);
+                button type="submit" { (lang.t("filter-submit")) }
+                a href=(format!("{}?reset", action)) { (lang.t("filter-reset")) }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Name,
+    Game,
+    Author,
+    Added,
+    Size,
+    Available,
+}
+
+/// The sortable modlist table headings and their `?sort=` keys.
+const MODLIST_SORTS: [(&str, &str, SortColumn); 6] = [
+    ("col-name", "name", SortColumn::Name),
+    ("col-game", "game", SortColumn::Game),
+    ("col-author", "author", SortColumn::Author),
+    ("col-added", "added", SortColumn::Added),
+    ("col-size", "size", SortColumn::Size),
+    ("col-mods-available", "available", SortColumn::Available),
+];
+
+const MODLIST_HEADINGS: [&str; 11] = [
+    "col-name",
+    "col-version",
+    "col-game",
+    "col-author",
+    "col-added",
+    "col-filename",
+    "col-size",
+    "col-hash",
+    "col-mods-total",
+    "col-mods-available",
+    "col-status",
+];
+
+/// A modlist with its mods total, mods available and readiness.
+type ModlistRow<'a> = (&'a Modlist, u64, u64, Option<ModlistReadiness>);
+
+/// A mod on `/mods`: the mod, how many modlists use it, and its first
+/// association for a display name.
+type ModListingRow = (Mod, u64, Option<ModAssociation>);
+
+/// The order of the modlist tables, from `?sort=`: a column's key, with a
+/// leading `-` for descending. Without one they are in name order.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct ModlistSort {
+    column: SortColumn,
+    descending: bool,
+}
+
+impl ModlistSort {
+    fn from_query(query: &HashMap<String, String>) -> Option<Self> {
+        let value = query.get("sort")?.trim();
+        let (key, descending) = match value.strip_prefix('-') {
+            Some(key) => (key, true),
+            None => (value, false),
+        };
+        MODLIST_SORTS
+            .iter()
+            .find(|(_, sort_key, _)| *sort_key == key)
+            .map(|(_, _, column)| ModlistSort {
+                column: *column,
+                descending,
+            })
+    }
+
+    fn param(self) -> String {
+        let (_, key, _) = MODLIST_SORTS
+            .iter()
+            .find(|(_, _, column)| *column == self.column)
+            .expect("every sort column has a key");
+        if self.descending {
+            format!("-{}", key)
+        } else {
+            key.to_string()
+        }
+    }
+
+    fn apply(self, rows: &mut [ModlistRow]) {
+        // Share of required mods available; lists without any count as
+        // complete.
+        let share = |(_, total, available, _): &ModlistRow| {
+            if *total == 0 {
+                1.0
+            } else {
+                *available as f64 / *total as f64
+            }
+        };
+        rows.sort_by(|a, b| {
+            let ordering = match self.column {
+                SortColumn::Name => a.0.name.to_lowercase().cmp(&b.0.name.to_lowercase()),
+                SortColumn::Game => a.0.game_type.cmp(&b.0.game_type),
+                SortColumn::Author => a.0.author.cmp(&b.0.author),
+                SortColumn::Added => a.0.created_at.cmp(&b.0.created_at),
+                SortColumn::Size => a.0.size.cmp(&b.0.size),
+                SortColumn::Available => share(a).total_cmp(&share(b)),
+            };
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+}
+
+/// A modlist table heading, linking to sort by it (or reverse the order)
+/// when the column is sortable.
+fn sort_heading(
+    lang: Lang,
+    heading: &'static str,
+    sort: Option<ModlistSort>,
+    query: &HashMap<String, String>,
+    path: &str,
+) -> Markup {
+    let Some((_, _, column)) = MODLIST_SORTS.iter().find(|(h, _, _)| *h == heading) else {
+        return html! { (lang.t(heading)) };
+    };
+    let current = sort.filter(|sort| sort.column == *column);
+    let next = ModlistSort {
+        column: *column,
+        descending: current.is_some_and(|sort| !sort.descending),
+    };
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in query.iter() {
+        if key != "sort" && key != "reset" {
+            serializer.append_pair(key, value);
+        }
+    }
+    serializer.append_pair("sort", &next.param());
+    html! {
+        a.sort-link href=(format!("{}?{}", path, serializer.finish())) {
+            (lang.t(heading))
+            @if let Some(current) = current {
+                (if current.descending { " ▼" } else { " ▲" })
+            }
+        }
+    }
+}
+
+/// The table of `/` and `/modlists/muted`.
+#[allow(clippy::too_many_arguments)]
+fn modlist_table(
+    lang: Lang,
+    rows: &[ModlistRow],
+    columns: &Columns,
+    sort: Option<ModlistSort>,
+    query: &HashMap<String, String>,
+    path: &str,
+    default_row_class: &'static str,
+    serve_files: bool,
+) -> Markup {
+    html! {
+        table.modlist-table {
+            thead {
+                tr {
+                    @for heading in MODLIST_HEADINGS {
+                        @if columns.shows(heading) {
+                            th { (sort_heading(lang, heading, sort, query, path)) }
+                        }
+                    }
+                }
+            }
+            tbody {
+                @for (modlist, mods_total, mods_available, readiness) in rows {
+                    tr class=(row_class(readiness.as_ref(), default_row_class)) {
+                        td.name {
+                            a href={"/modlists/" (modlist.id)} {
+                                (modlist.name)
+                            }
+                        }
+                        @if columns.shows("col-version") {
+                            td.version { (modlist.version) }
+                        }
+                        @if columns.shows("col-game") {
+                            td.game { (modlist.game_type.as_deref().unwrap_or("-")) }
+                        }
+                        @if columns.shows("col-author") {
+                            td.author { (modlist.author.as_deref().unwrap_or("-")) }
+                        }
+                        @if columns.shows("col-added") {
+                            td.added { (format_date(modlist.created_at)) }
+                        }
+                        @if columns.shows("col-filename") {
+                            td.filename {
+                                @if serve_files && modlist.available {
+                                    a href={"/modlists/" (modlist.id) "/download"} download title=(lang.t("download")) {
+                                        (modlist.filename)
+                                    }
+                                } @else {
+                                    (modlist.filename)
+                                }
+                            }
+                        }
+                        @if columns.shows("col-size") {
+                            td.size { (format_size(modlist.size)) }
+                        }
+                        @if columns.shows("col-hash") {
+                            td.hash {
+                                (hash_code(lang, &modlist.xxhash64))
+                            }
+                        }
+                        @if columns.shows("col-mods-total") {
+                            td { (mods_total) }
+                        }
+                        @if columns.shows("col-mods-available") {
+                            td { (availability_bar(lang, modlist.id, *mods_available, *mods_total)) }
+                        }
+                        @if columns.shows("col-status") {
+                            td.status { (status_cell(lang, modlist, readiness.as_ref())) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A modlist row of `/` and `/modlists/muted`, as JSON.
+#[derive(Serialize)]
+struct ModlistWithCounts<'a> {
+    #[serde(flatten)]
+    modlist: &'a Modlist,
+    mods_total: u64,
+    mods_available: u64,
+    readiness: Option<&'a ModlistReadiness>,
+}
+
+/// A row of `/mods`, as JSON.
+#[derive(Serialize)]
+struct ModWithCounts<'a> {
+    #[serde(flatten)]
+    mod_item: &'a Mod,
+    available: bool,
+    modlists_count: u64,
+    /// The association the name and version shown come from.
+    first_association: Option<&'a ModAssociation>,
+}
+
+#[derive(Serialize)]
+struct ModsPage<'a> {
+    mods: Vec<ModWithCounts<'a>>,
+    next_page: Option<&'a str>,
+}
+
+/// The JSON form of a listing, with the same validators as the page.
+fn json_listing(data_version: &DataVersion, lang: Lang, data: &impl Serialize) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    set_validators(&mut response, data_version, lang, Format::Json);
+    response.json(data)
+}
+
+/// Session key remembering whether `/` shows the table or the grid.
+const SESSION_LISTING_VIEW: &str = "listing_view";
+
+/// How `/` lays out the modlists.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ListingView {
+    Table,
+    Grid,
+}
+
+impl ListingView {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "table" => Some(ListingView::Table),
+            "grid" => Some(ListingView::Grid),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ListingView::Table => "table",
+            ListingView::Grid => "grid",
+        }
+    }
+
+    /// The view asked for with `?view=`, which is then remembered in the
+    /// session, or else the one last remembered.
+    fn resolve(
+        query: &HashMap<String, String>,
+        session: &Session,
+    ) -> Result<Self, actix_web::Error> {
+        if let Some(view) = query.get("view").and_then(|v| ListingView::parse(v)) {
+            session.insert(SESSION_LISTING_VIEW, view.as_str())?;
+            return Ok(view);
+        }
+        Ok(session
+            .get::<String>(SESSION_LISTING_VIEW)?
+            .and_then(|v| ListingView::parse(&v))
+            .unwrap_or(ListingView::Table))
+    }
+}
+
+/// Links switching between the table and grid, keeping the filters.
+fn view_toggle(lang: Lang, current: ListingView, query: &HashMap<String, String>) -> Markup {
+    let url = |view: ListingView| {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in query.iter() {
+            if key != "view" {
+                serializer.append_pair(key, value);
+            }
+        }
+        serializer.append_pair("view", view.as_str());
+        format!("/?{}", serializer.finish())
+    };
+    html! {
+        div.view-toggle {
+            @for (view, label) in [(ListingView::Table, lang.t("view-table")), (ListingView::Grid, lang.t("view-grid"))] {
+                @if view == current {
+                    span.active { (label) }
+                } @else {
+                    a href=(url(view)) { (label) }
+                }
+            }
+        }
+    }
+}
+
+fn modlist_card(
+    lang: Lang,
+    modlist: &Modlist,
+    mods_total: u64,
+    mods_available: u64,
+    readiness: Option<&ModlistReadiness>,
+    serve_files: bool,
+) -> Markup {
+    html! {
+        div.modlist-card {
+            a.modlist-card-image href={"/modlists/" (modlist.id)} {
+                img src={"/modlists/" (modlist.id) "/image"} alt="" loading="lazy" onerror="this.remove()";
+            }
+            div.modlist-card-body {
+                a.name href={"/modlists/" (modlist.id)} { (modlist.name) }
+                div.version { (modlist.version) }
+                (availability_bar(lang, modlist.id, mods_available, mods_total))
+                div.status { (status_cell(lang, modlist, readiness)) }
+                @if serve_files && modlist.available {
+                    a.download-link href={"/modlists/" (modlist.id) "/download"} download {
+                        (lang.t("download")) " (" (format_size(modlist.size)) ")"
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn row_class(readiness: Option<&ModlistReadiness>, default: &'static str) -> &'static str {
+    match readiness.map(|r| r.status) {
+        Some(ModlistStatus::Uninstallable) => "uninstallable-row",
+        Some(ModlistStatus::MissingFiles) => "unavailable-row",
+        _ => default,
+    }
+}
+
+fn status_cell(lang: Lang, modlist: &Modlist, readiness: Option<&ModlistReadiness>) -> Markup {
+    html! {
+        @match readiness {
+            Some(readiness) => {
+                span class=(format!("status-badge {}", readiness.status.badge_class()))
+                    title=(format!(
+                        "{}{}",
+                        lang.tf("readiness-summary", &[
+                            ("available", readiness.available.to_string()),
+                            ("counted", readiness.counted.to_string()),
+                        ]),
+                        if readiness.ignored > 0 {
+                            lang.tf("readiness-ignored", &[("count", readiness.ignored.to_string())])
+                        } else {
+                            String::new()
+                        }
+                    )) {
+                    (lang.modlist_status(readiness.status))
+                }
+            }
+            None => span.status-badge.warning { (lang.t("status-unknown")) },
+        }
+        @if modlist.has_unknown_downloaders {
+            span.status-badge.warning title=(lang.t("status-unknown-sources-title")) { (lang.t("status-unknown-sources")) }
+        }
     }
 }
 
 #[get("/")]
 pub async fn listing_page(
+    query: web::Query<HashMap<String, String>>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
+    mode: UiMode,
+    session: Session,
+    lang: Lang,
+    format: Format,
+    req: HttpRequest,
 ) -> Result<impl Responder, actix_web::Error> {
+    let ServeFiles(serve_files) = ServeFiles::extract(&req).await?;
+    let view = ListingView::resolve(&query, &session)?;
+    if format == Format::Html
+        && let Some(redirect) = MODLISTS.remember_filters(&query, &session)?
+    {
+        return Ok(redirect);
+    }
     let conn = pool
         .get()
         .map_err(actix_web::error::ErrorInternalServerError)?;
+    let data_version =
+        DataVersion::get(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    if is_fresh(&req, &data_version, lang, format) {
+        return Ok(not_modified(&data_version, lang, format));
+    }
     let all_modlists =
         Modlist::get_all(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
 
     // Filter out muted modlists
-    let modlists: Vec<_> = all_modlists.iter().filter(|m| !m.muted).collect();
+    let filter = ModlistColumnFilter::from_query(&query);
+    let modlists: Vec<_> = all_modlists
+        .iter()
+        .filter(|m| !m.muted && filter.matches(m))
+        .collect();
+    let game_types =
+        Modlist::get_game_types(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let authors =
+        Modlist::get_authors(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let counts =
+        ModlistCounts::get_all(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
 
-    // Compute mod counts for each modlist
-    let modlists_with_counts: Vec<_> = modlists
+    let mut modlists_with_counts: Vec<ModlistRow> = modlists
         .iter()
         .map(|modlist| {
-            let mods_total = modlist.count_mods_total(&conn).unwrap_or(0);
-            let mods_available = modlist.count_mods_available(&conn).unwrap_or(0);
-            let has_lost_forever = modlist.has_lost_forever_mods(&conn).unwrap_or(false);
-            (modlist, mods_total, mods_available, has_lost_forever)
+            let counts = counts.get(&modlist.id).cloned().unwrap_or_default();
+            (
+                *modlist,
+                counts.mods_total,
+                counts.mods_available,
+                Some(counts.readiness),
+            )
         })
         .collect();
+    let sort = ModlistSort::from_query(&query);
+    if let Some(sort) = sort {
+        sort.apply(&mut modlists_with_counts);
+    }
+
+    if format == Format::Json {
+        let data: Vec<_> = modlists_with_counts
+            .iter()
+            .map(
+                |(modlist, mods_total, mods_available, readiness)| ModlistWithCounts {
+                    modlist,
+                    mods_total: *mods_total,
+                    mods_available: *mods_available,
+                    readiness: readiness.as_ref(),
+                },
+            )
+            .collect();
+        return Ok(json_listing(&data_version, lang, &data));
+    }
 
-    let page = html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                title { "Modlists" }
-                link rel="stylesheet" href="/res/styles.css";
+    let columns = MODLISTS.columns(&session);
+    let page = layout(
+        lang,
+        lang.t("title-modlists"),
+        "page-listing",
+        htmx(),
+        html! {
+            (header_nav(lang.t("heading-modlists"), html! {
+                a.nav-link href="/mods" { (lang.t("nav-all-mods")) }
+                a.nav-link href="/modlists/muted" { (lang.t("nav-muted-modlists")) }
+                @if mode.is_admin() {
+                    a.nav-link href="/upload" { (lang.t("nav-upload")) }
+                }
+                a.nav-link href="/stats" { (lang.t("nav-stats")) }
+                @if serve_files {
+                    a.nav-link href="/wabbajack/modlists.json" title=(lang.t("nav-repository-title")) { (lang.t("nav-repository")) }
+                }
+                @if mode.is_admin() {
+                    a.nav-link href="/admin/jobs" { (lang.t("nav-jobs")) }
+                }
+            }))
+            (filter.form(lang, "/", sort, &game_types, &authors))
+            @if modlists_with_counts.is_empty() {
+                p.empty-state {
+                    @if filter.is_active() {
+                        (lang.t("empty-filtered-modlists"))
+                    } @else {
+                        (lang.t("empty-modlists"))
+                    }
+                }
+            } @else if view == ListingView::Grid {
+                (view_toggle(lang, view, &query))
+                div.modlist-grid {
+                    @for (modlist, mods_total, mods_available, readiness) in &modlists_with_counts {
+                        (modlist_card(lang, modlist, *mods_total, *mods_available, readiness.as_ref(), serve_files))
+                    }
+                }
+            } @else {
+                (view_toggle(lang, view, &query))
+                (columns.picker(lang))
+                (modlist_table(lang, &modlists_with_counts, &columns, sort, &query, "/", "", serve_files))
             }
-            body.page-listing {
-                div.container {
-                    div.header-nav {
-                        h1 { "Wabbajack Modlists" }
-                        div.nav-links {
-                            a.nav-link href="/mods" { "View All Mods" }
-                            a.nav-link href="/modlists/muted" { "View Muted Modlists" }
-                            a.nav-link href="/upload" { "Upload" }
+            @if mode.is_admin() {
+                div.bootstrap-section {
+                    h2 { "Bootstrap Database" }
+                    p {
+                        "Scan the data directory and update the database with all modlists and mods found on disk."
+                    }
+                    form method="post" action="/bootstrap" {
+                        button.bootstrap-button type="submit" {
+                            "Run Bootstrap"
                         }
                     }
-                    @if modlists_with_counts.is_empty() {
-                        p.empty-state { "No modlists found." }
-                    } @else {
-                        table.modlist-table {
-                            thead {
-                                tr {
-                                    th { "Name" }
-                                    th { "Version" }
-                                    th { "Filename" }
-                                    th { "Size" }
-                                    th { "Hash" }
-                                    th { "Mods total" }
-                                    th { "Mods available" }
-                                    th { "Status" }
-                                }
-                            }
-                            tbody {
-                                @for (modlist, mods_total, mods_available, has_lost_forever) in &modlists_with_counts {
-                                    tr class=(
-                                        if *has_lost_forever {
-                                            "uninstallable-row"
-                                        } else if *mods_total > 0 && *mods_available < *mods_total {
-                                            "unavailable-row"
-                                        } else {
-                                            ""
-                                        }
-                                    ) {
-                                        td.name {
-                                            a href={"/modlists/" (modlist.id)} {
-                                                (modlist.name)
-                                            }
-                                        }
-                                        td.version { (modlist.version) }
-                                        td.filename { (modlist.filename) }
-                                        td.size { (format_size(modlist.size)) }
-                                        td.hash {
-                                            code { (format_hash(&modlist.xxhash64)) }
-                                        }
-                                        td { (mods_total) }
-                                        td { (mods_available) }
-                                        td.status {
-                                            @if *has_lost_forever {
-                                                span.status-badge.missing { "Uninstallable" }
-                                            } @else if *mods_total == 0 || *mods_available == *mods_total {
-                                                span.status-badge.available { "Ready" }
-                                            } @else {
-                                                span.status-badge.missing { "Missing files" }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                    form method="post" action="/bootstrap/modlists" {
+                        button.bootstrap-button type="submit" {
+                            "Run Modlists Bootstrap"
                         }
                     }
-                    div.bootstrap-section {
-                        h2 { "Bootstrap Database" }
-                        p {
-                            "Scan the data directory and update the database with all modlists and mods found on disk."
-                        }
-                        form method="post" action="/bootstrap" {
-                            button.bootstrap-button type="submit" {
-                                "Run Bootstrap"
-                            }
+                    form method="post" action="/bootstrap/mods" {
+                        button.bootstrap-button type="submit" {
+                            "Run Mods Bootstrap"
                         }
-                        form method="post" action="/bootstrap/modlists" {
-                            button.bootstrap-button type="submit" {
-                                "Run Modlists Bootstrap"
-                            }
+                    }
+                    form method="post" action="/verify" {
+                        button.bootstrap-button type="submit" {
+                            "Run Hash Verification"
                         }
-                        form method="post" action="/bootstrap/mods" {
-                            button.bootstrap-button type="submit" {
-                                "Run Mods Bootstrap"
-                            }
+                    }
+                    form method="post" action="/reconcile" {
+                        button.bootstrap-button type="submit" {
+                            "Run Reconciliation"
+                        }
+                    }
+                    form method="post" action="/downloads/run" {
+                        button.bootstrap-button type="submit" {
+                            "Download Missing Mods"
+                        }
+                    }
+                    form method="post" action="/metadata/refresh" {
+                        button.bootstrap-button type="submit" {
+                            "Refresh LoversLab Metadata"
+                        }
+                    }
+                }
+                div.bootstrap-section {
+                    h2 { "Import Downloads Folder" }
+                    p {
+                        "Link in the archives of an existing Wabbajack downloads folder on this machine without copying them. It must be on the same filesystem as the data directory. You will see what each modlist gains before anything is linked."
+                    }
+                    form method="post" action="/import/downloads" {
+                        input type="text" name="path" placeholder="/path/to/Wabbajack/downloads" required;
+                        label {
+                            input type="checkbox" name="verbose" value="true" checked;
+                            " Keep a detailed log"
+                        }
+                        button.bootstrap-button type="submit" {
+                            "Plan Import"
+                        }
+                    }
+                }
+                div.bootstrap-section {
+                    h2 { "Migrate Storage" }
+                    p {
+                        "Move every mod to one storage layout. You will be shown the plan before anything is moved; originals are only removed once their new copy checks out."
+                    }
+                    form method="post" action="/storage/migrate" {
+                        select name="target" {
+                            option value="flat" { "Flat Downloads folder" }
+                            option value="local" { "Local (fetch back from cold storage)" }
+                            option value="cold_storage" { "Cold storage" }
+                        }
+                        label {
+                            input type="checkbox" name="verbose" value="true";
+                            " Keep a detailed log"
+                        }
+                        button.bootstrap-button type="submit" {
+                            "Plan Migration"
                         }
                     }
                 }
             }
-        }
-    };
+        },
+    );
 
-    Ok(HttpResponse::Ok()
+    let mut response = HttpResponse::Ok();
+    set_validators(&mut response, &data_version, lang, format);
+    Ok(response
         .content_type("text/html; charset=utf-8")
         .body(page.into_string()))
 }
 
 #[get("/modlists/muted")]
 pub async fn muted_modlists_page(
+    query: web::Query<HashMap<String, String>>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
+    ServeFiles(serve_files): ServeFiles,
+    session: Session,
+    lang: Lang,
+    format: Format,
+    req: HttpRequest,
 ) -> Result<impl Responder, actix_web::Error> {
+    if format == Format::Html
+        && let Some(redirect) = MUTED_MODLISTS.remember_filters(&query, &session)?
+    {
+        return Ok(redirect);
+    }
     let conn = pool
         .get()
         .map_err(actix_web::error::ErrorInternalServerError)?;
-    let modlists = Modlist::get_muted(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let data_version =
+        DataVersion::get(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    if is_fresh(&req, &data_version, lang, format) {
+        return Ok(not_modified(&data_version, lang, format));
+    }
+    let filter = ModlistColumnFilter::from_query(&query);
+    let modlists: Vec<_> = Modlist::get_muted(&conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .filter(|m| filter.matches(m))
+        .collect();
+    let game_types =
+        Modlist::get_game_types(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let authors =
+        Modlist::get_authors(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let counts =
+        ModlistCounts::get_all(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
 
-    // Compute mod counts for each modlist
-    let modlists_with_counts: Vec<_> = modlists
+    let mut modlists_with_counts: Vec<ModlistRow> = modlists
         .iter()
         .map(|modlist| {
-            let mods_total = modlist.count_mods_total(&conn).unwrap_or(0);
-            let mods_available = modlist.count_mods_available(&conn).unwrap_or(0);
-            let has_lost_forever = modlist.has_lost_forever_mods(&conn).unwrap_or(false);
-            (modlist, mods_total, mods_available, has_lost_forever)
+            let counts = counts.get(&modlist.id).cloned().unwrap_or_default();
+            (
+                modlist,
+                counts.mods_total,
+                counts.mods_available,
+                Some(counts.readiness),
+            )
         })
         .collect();
+    let sort = ModlistSort::from_query(&query);
+    if let Some(sort) = sort {
+        sort.apply(&mut modlists_with_counts);
+    }
 
-    let page = html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                title { "Muted Modlists" }
-                link rel="stylesheet" href="/res/styles.css";
-            }
-            body.page-listing {
-                div.container {
-                    div.header-nav {
-                        h1 { "Muted Modlists" }
-                        div.nav-links {
-                            a.nav-link href="/" { "View All Modlists" }
-                            a.nav-link href="/mods" { "View All Mods" }
-                        }
-                    }
-                    @if modlists_with_counts.is_empty() {
-                        p.empty-state { "No muted modlists found." }
+    if format == Format::Json {
+        let data: Vec<_> = modlists_with_counts
+            .iter()
+            .map(
+                |(modlist, mods_total, mods_available, readiness)| ModlistWithCounts {
+                    modlist,
+                    mods_total: *mods_total,
+                    mods_available: *mods_available,
+                    readiness: readiness.as_ref(),
+                },
+            )
+            .collect();
+        return Ok(json_listing(&data_version, lang, &data));
+    }
+
+    let columns = MUTED_MODLISTS.columns(&session);
+    let page = layout(
+        lang,
+        lang.t("title-muted-modlists"),
+        "page-listing",
+        htmx(),
+        html! {
+            (header_nav(lang.t("title-muted-modlists"), html! {
+                a.nav-link href="/" { (lang.t("nav-all-modlists")) }
+                a.nav-link href="/mods" { (lang.t("nav-all-mods")) }
+            }))
+            (filter.form(lang, "/modlists/muted", sort, &game_types, &authors))
+            @if modlists_with_counts.is_empty() {
+                p.empty-state {
+                    @if filter.is_active() {
+                        (lang.t("empty-filtered-modlists"))
                     } @else {
-                        table.modlist-table {
-                            thead {
-                                tr {
-                                    th { "Name" }
-                                    th { "Version" }
-                                    th { "Filename" }
-                                    th { "Size" }
-                                    th { "Hash" }
-                                    th { "Mods total" }
-                                    th { "Mods available" }
-                                    th { "Status" }
-                                }
-                            }
-                            tbody {
-                                @for (modlist, mods_total, mods_available, has_lost_forever) in &modlists_with_counts {
-                                    tr class=(
-                                        if *has_lost_forever {
-                                            "uninstallable-row"
-                                        } else if *mods_total > 0 && *mods_available < *mods_total {
-                                            "unavailable-row"
-                                        } else {
-                                            "muted-row"
-                                        }
-                                    ) {
-                                        td.name {
-                                            a href={"/modlists/" (modlist.id)} {
-                                                (modlist.name)
-                                            }
-                                        }
-                                        td.version { (modlist.version) }
-                                        td.filename { (modlist.filename) }
-                                        td.size { (format_size(modlist.size)) }
-                                        td.hash {
-                                            code { (format_hash(&modlist.xxhash64)) }
-                                        }
-                                        td { (mods_total) }
-                                        td { (mods_available) }
-                                        td.status {
-                                            @if *has_lost_forever {
-                                                span.status-badge.missing { "Uninstallable" }
-                                            } @else if *mods_total == 0 || *mods_available == *mods_total {
-                                                span.status-badge.available { "Ready" }
-                                            } @else {
-                                                span.status-badge.missing { "Missing files" }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                        (lang.t("empty-muted-modlists"))
                     }
                 }
+            } @else {
+                (columns.picker(lang))
+                (modlist_table(lang, &modlists_with_counts, &columns, sort, &query, "/modlists/muted", "muted-row", serve_files))
             }
-        }
-    };
+        },
+    );
 
-    Ok(HttpResponse::Ok()
+    let mut response = HttpResponse::Ok();
+    set_validators(&mut response, &data_version, lang, format);
+    Ok(response
         .content_type("text/html; charset=utf-8")
         .body(page.into_string()))
 }
 
 #[get("/mods")]
 pub async fn mods_listing_page(
-    query: web::Query<std::collections::HashMap<String, String>>,
+    query: web::Query<HashMap<String, String>>,
     pool: web::Data<Pool<SqliteConnectionManager>>,
+    mode: UiMode,
+    session: Session,
+    lang: Lang,
+    format: Format,
+    req: HttpRequest,
 ) -> Result<impl Responder, actix_web::Error> {
+    if format == Format::Html
+        && let Some(redirect) = MODS.remember_filters(&query, &session)?
+    {
+        return Ok(redirect);
+    }
     let conn = pool
         .get()
         .map_err(actix_web::error::ErrorInternalServerError)?;
+    let data_version =
+        DataVersion::get(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    if is_fresh(&req, &data_version, lang, format) {
+        return Ok(not_modified(&data_version, lang, format));
+    }
 
     let show_unavailable_only = query
         .get("filter")
         .map(|s| s == "unavailable")
         .unwrap_or(false);
 
-    let mods_with_metadata = Mod::get_all_for_listing(show_unavailable_only, &conn)
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+    // Only accept known source identifiers so the filter can't be used to
+    // probe arbitrary values.
+    let source_filter = query
+        .get("source")
+        .map(|s| s.as_str())
+        .filter(|s| SOURCE_TYPES.iter().any(|(key, _)| key == s));
+
+    let min_size_input = query.get("min_size").cloned().unwrap_or_default();
+    let max_size_input = query.get("max_size").cloned().unwrap_or_default();
+    let game_filter = query
+        .get("game")
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    let filter = ModListingFilter {
+        unavailable_only: show_unavailable_only,
+        source_type: source_filter.map(|s| s.to_string()),
+        min_size: parse_size(&min_size_input),
+        max_size: parse_size(&max_size_input),
+        game: game_filter.clone(),
+    };
+
+    let after = match (query.get("after_name"), query.get("after_id")) {
+        (Some(name), Some(id)) => id.parse::<u64>().ok().map(|id| (name.clone(), id)),
+        _ => None,
+    };
+
+    let first_page_url = after.is_some().then(|| {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in query.iter() {
+            if key != "after_name" && key != "after_id" {
+                serializer.append_pair(key, value);
+            }
+        }
+        format!("/mods?{}", serializer.finish())
+    });
+    if format == Format::Json {
+        let (mods_with_metadata, next_page_url) = mods_page(&filter, after, &query, &conn)
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let data = ModsPage {
+            mods: mods_with_metadata
+                .iter()
+                .map(|(mod_item, modlists_count, first_assoc)| ModWithCounts {
+                    mod_item,
+                    available: mod_item.is_available(),
+                    modlists_count: *modlists_count,
+                    first_association: first_assoc.as_ref(),
+                })
+                .collect(),
+            next_page: next_page_url.as_deref(),
+        };
+        return Ok(json_listing(&data_version, lang, &data));
+    }
+    let game_types =
+        Modlist::get_game_types(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let columns = MODS.columns(&session);
 
-    let page = html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                title {
+    let heading = lang.t(if show_unavailable_only {
+        "title-missing-mods"
+    } else {
+        "title-mods"
+    });
+    let page = layout(
+        lang,
+        heading,
+        "page-listing",
+        html! {},
+        html! {
+            (header_nav(heading, html! {
+                a.nav-link href="/" { (lang.t("nav-modlists")) }
+                @if show_unavailable_only {
+                    a.nav-link href="/mods?reset" { (lang.t("nav-all-mods")) }
+                } @else {
+                    a.nav-link href="/mods?filter=unavailable" { (lang.t("nav-missing-mods")) }
+                }
+                @if mode.is_admin() {
+                    a.nav-link href="/upload" { (lang.t("nav-upload")) }
+                }
+            }))
+            form.filter-form method="get" action="/mods" {
+                label {
+                    input type="checkbox" name="filter" value="unavailable" checked[show_unavailable_only];
+                    " " (lang.t("filter-missing-only"))
+                }
+                label for="source-filter" { (lang.t("filter-source")) }
+                select id="source-filter" name="source" {
+                    option value="" { (lang.t("filter-all-sources")) }
+                    @for (key, label) in SOURCE_TYPES {
+                        option value=(key) selected[source_filter == Some(*key)] { (label) }
+                    }
+                }
+                label for="game-filter" { (lang.t("filter-game")) }
+                select id="game-filter" name="game" {
+                    option value="" { (lang.t("filter-all-games")) }
+                    @for game_type in &game_types {
+                        option value=(game_type) selected[game_filter.as_deref() == Some(game_type.as_str())] { (game_type) }
+                    }
+                }
+                label for="min-size-filter" { (lang.t("filter-size")) }
+                input id="min-size-filter" type="text" name="min_size" placeholder=(lang.t("filter-min-size")) value=(min_size_input) size="10";
+                "–"
+                input type="text" name="max_size" placeholder=(lang.t("filter-max-size")) value=(max_size_input) size="10";
+                button type="submit" { (lang.t("filter-submit")) }
+                a href="/mods?reset" { (lang.t("filter-reset")) }
+            }
+            (PreEscaped(RESULTS_MARKER))
+        },
+    );
+
+    // Send everything above the table before the mods have been queried, so
+    // the browser can fetch styles and paint the filters while the query
+    // runs, then send the table in batches of rows as they are rendered.
+    let page = page.into_string();
+    let (head, tail) = page
+        .split_once(RESULTS_MARKER)
+        .map(|(head, tail)| (head.to_string(), tail.to_string()))
+        .unwrap_or((page.clone(), String::new()));
+    let query = query.into_inner();
+    let (sender, receiver) = mpsc::channel::<std::io::Result<web::Bytes>>(4);
+    tokio::task::spawn_blocking(carry_origin(move || {
+        let send = |chunk: String| sender.blocking_send(Ok(web::Bytes::from(chunk))).is_ok();
+        let (mods_with_metadata, next_page_url) = match mods_page(&filter, after, &query, &conn) {
+            Ok(page) => page,
+            Err(e) => {
+                // The status line is already out; cut the response short.
+                log::error!("Failed to list mods: {}", e);
+                let _ = sender.blocking_send(Err(std::io::Error::other(e)));
+                return;
+            }
+        };
+        drop(conn);
+
+        let results = html! {
+            @if mods_with_metadata.is_empty() {
+                p.empty-state {
                     @if show_unavailable_only {
-                        "Missing Mods"
+                        (lang.t("empty-missing-mods"))
                     } @else {
-                        "Mods"
+                        (lang.t("empty-mods"))
+                    }
+                }
+            } @else {
+                (columns.picker(lang))
+                table.modlist-table.mods-table {
+                    thead {
+                        tr {
+                            @for column in ["col-filename", "col-name", "col-version", "col-size", "col-hash", "col-modlists", "col-status"] {
+                                @if columns.shows(column) {
+                                    th { (lang.t(column)) }
+                                }
+                            }
+                        }
                     }
+                    tbody {
+                        (PreEscaped(ROWS_MARKER))
+                    }
+                }
+            }
+            @if first_page_url.is_some() || next_page_url.is_some() {
+                div.pagination {
+                    @if let Some(url) = &first_page_url {
+                        a href=(url) { (lang.t("page-first")) }
+                    }
+                    @if let Some(url) = &next_page_url {
+                        a href=(url) { (lang.t("page-next")) }
+                    }
+                }
+            }
+        }
+        .into_string();
+        let (before_rows, after_rows) = results.split_once(ROWS_MARKER).unwrap_or((&results, ""));
+        if !send(before_rows.to_string()) {
+            return;
+        }
+        for chunk in mods_with_metadata.chunks(ROW_CHUNK_SIZE) {
+            let mut rendered = String::new();
+            for (mod_item, modlists_count, first_assoc) in chunk {
+                rendered.push_str(
+                    &render_mod_row(lang, &columns, mod_item, *modlists_count, first_assoc)
+                        .into_string(),
+                );
+            }
+            // The client went away; stop rendering for nobody.
+            if !send(rendered) {
+                return;
+            }
+        }
+        send(format!("{}{}", after_rows, tail));
+    }));
+    let rest = stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+    let body = stream::once(async move { Ok(web::Bytes::from(head)) }).chain(rest);
+
+    let mut response = HttpResponse::Ok();
+    set_validators(&mut response, &data_version, lang, format);
+    Ok(response
+        .content_type("text/html; charset=utf-8")
+        .streaming(body))
+}
+
+/// One page of `/mods` from `after` on, with the link to the page after it
+/// when there is one.
+fn mods_page(
+    filter: &ModListingFilter,
+    after: Option<(String, u64)>,
+    query: &HashMap<String, String>,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<(Vec<ModListingRow>, Option<String>), rusqlite::Error> {
+    let mut mods_with_metadata = Mod::get_all_for_listing(filter, after, MODS_PAGE_SIZE + 1, conn)?;
+    let has_more = mods_with_metadata.len() > MODS_PAGE_SIZE as usize;
+    mods_with_metadata.truncate(MODS_PAGE_SIZE as usize);
+
+    // Build the "next page" link from the current query plus the sort key of
+    // the last row on this page.
+    let next_page_url = if has_more {
+        mods_with_metadata.last().map(|(last, _, _)| {
+            let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+            for (key, value) in query.iter() {
+                if key != "after_name" && key != "after_id" {
+                    serializer.append_pair(key, value);
                 }
-                link rel="stylesheet" href="/res/styles.css";
             }
-            body.page-listing {
-                div.container {
-                    div.header-nav {
-                        h1 {
-                            @if show_unavailable_only {
-                                "Missing Mods"
-                            } @else {
-                                "Mods"
+            serializer.append_pair("after_name", last.disk_filename.as_deref().unwrap_or(""));
+            serializer.append_pair("after_id", &last.id.to_string());
+            format!("/mods?{}", serializer.finish())
+        })
+    } else {
+        None
+    };
+    Ok((mods_with_metadata, next_page_url))
+}
+
+fn render_mod_row(
+    lang: Lang,
+    columns: &Columns,
+    mod_item: &Mod,
+    modlists_count: u64,
+    first_assoc: &Option<ModAssociation>,
+) -> Markup {
+    html! {
+        tr {
+            td.filename {
+                a href=(format!("/mod/{}", mod_item.id)) {
+                    @match &mod_item.disk_filename {
+                        Some(disk_filename) => {
+                            (disk_filename)
+                        }
+                        None => {
+                            @match first_assoc {
+                                Some(assoc) => {
+                                    (assoc.filename.clone())
+                                }
+                                None => {
+                                    em { (lang.t("unknown")) }
+                                }
                             }
                         }
-                        div.nav-links {
-                            a.nav-link href="/" { "View Modlists" }
-                            @if show_unavailable_only {
-                                a.nav-link href="/mods" { "View All Mods" }
-                            } @else {
-                                a.nav-link href="/mods?filter=unavailable" { "View Missing Mods" }
+                    }
+                }
+            }
+            @if columns.shows("col-name") {
+                td.name {
+                    a href=(format!("/mod/{}", mod_item.id)) {
+                        @match first_assoc {
+                            Some(assoc) => {
+                                @match &assoc.name {
+                                    Some(name) => {
+                                        (name.clone())
+                                    }
+                                    None => {
+                                        em { (lang.t("unknown")) }
+                                    }
+                                }
+                            }
+                            None => {
+                                em { (lang.t("unknown")) }
                             }
-                            a.nav-link href="/upload" { "Upload" }
                         }
                     }
-                    @if mods_with_metadata.is_empty() {
-                        p.empty-state {
-                            @if show_unavailable_only {
-                                "No missing mods found."
-                            } @else {
-                                "No mods found."
+                    @if !mod_item.is_available() {
+                        @if let Some((prompt, url)) = first_assoc.as_ref().and_then(|a| a.source.manual_prompt()) {
+                            div.manual-prompt {
+                                strong { (lang.t("manual-download")) }
+                                (prompt)
+                                " "
+                                a href=(url) target="_blank" { (lang.t("open-page")) }
                             }
                         }
-                    } @else {
-                        table.modlist-table.mods-table {
-                            thead {
-                                tr {
-                                    th { "Filename" }
-                                    th { "Name" }
-                                    th { "Version" }
-                                    th { "Size" }
-                                    th { "Hash" }
-                                    th { "Modlists" }
-                                    th { "Status" }
+                    }
+                }
+            }
+            @if columns.shows("col-version") {
+                td.version {
+                    @match first_assoc {
+                        Some(assoc) => {
+                            @match &assoc.version {
+                                Some(version) => {
+                                    (version.clone())
                                 }
-                            }
-                            tbody {
-                                @for (mod_item, modlists_count, first_assoc) in &mods_with_metadata {
-                                    tr {
-                                        td.filename {
-                                            a href=(format!("/mod/{}", mod_item.id)) {
-                                                @match &mod_item.disk_filename {
-                                                    Some(disk_filename) => {
-                                                        (disk_filename)
-                                                    }
-                                                    None => {
-                                                        @match first_assoc {
-                                                            Some(assoc) => {
-                                                                (assoc.filename.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.name {
-                                            a href=(format!("/mod/{}", mod_item.id)) {
-                                                @match first_assoc {
-                                                    Some(assoc) => {
-                                                        @match &assoc.name {
-                                                            Some(name) => {
-                                                                (name.clone())
-                                                            }
-                                                            None => {
-                                                                em { "Unknown" }
-                                                            }
-                                                        }
-                                                    }
-                                                    None => {
-                                                        em { "Unknown" }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        td.version {
-                                            @match first_assoc {
-                                                Some(assoc) => {
-                                                    @match &assoc.version {
-                                                        Some(version) => {
-                                                            (version.clone())
-                                                        }
-                                                        None => {
-                                                            em { "-" }
-                                                        }
-                                                    }
-                                                }
-                                                None => {
-                                                    em { "-" }
-                                                }
-                                            }
-                                        }
-                                        td.size { (format_size(mod_item.size)) }
-                                        td.hash {
-                                            code { (format_hash(&mod_item.xxhash64)) }
-                                        }
-                                        td { (modlists_count) }
-                                        td.status {
-                                            @if mod_item.is_available() {
-                                                span.status-badge.available { "Available" }
-                                            } @else if mod_item.lost_forever {
-                                                span.status-badge.missing { "Lost Forever" }
-                                            } @else {
-                                                span.status-badge.unavailable { "Unavailable" }
-                                            }
-                                        }
-                                    }
+                                None => {
+                                    em { "-" }
                                 }
                             }
                         }
+                        None => {
+                            em { "-" }
+                        }
                     }
                 }
             }
+            @if columns.shows("col-size") {
+                td.size { (format_size(mod_item.size)) }
+            }
+            @if columns.shows("col-hash") {
+                td.hash {
+                    (hash_code(lang, &mod_item.xxhash64))
+                }
+            }
+            @if columns.shows("col-modlists") {
+                td { (modlists_count) }
+            }
+            @if columns.shows("col-status") {
+                td.status {
+                    (mod_status_badge(lang, mod_item))
+                }
+            }
         }
-    };
-
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(page.into_string()))
+    }
 }
@@ -1,3 +1,15 @@
+pub mod admin_credentials;
+pub mod admin_jobs;
+pub mod auth;
+pub mod components;
+pub mod conditional;
+pub mod confirm;
 pub mod details_page;
+pub mod diagnostics_page;
+pub mod fragments;
+pub mod i18n;
 pub mod listing_page;
+pub mod negotiate;
+pub mod preferences;
+pub mod stats_page;
 pub mod upload_page;
@@ -0,0 +1,79 @@
+//! Content negotiation for the HTML pages. A request that prefers
+//! `application/json` gets the data the page is rendered from instead, so
+//! scripts can use the same URLs as the browser. Responses for both list
+//! `Accept` in `Vary`, along with what picks the language.
+
+use std::future::{Ready, ready};
+
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest, HttpResponse};
+use maud::Markup;
+use serde::Serialize;
+
+use crate::web::conditional::VARY;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Json,
+}
+
+impl Format {
+    /// JSON when an `Accept` header ranks `application/json` above HTML.
+    /// Browsers list `*/*` at a lower weight than `text/html`, so they
+    /// keep getting pages.
+    fn from_accept(header: &str) -> Self {
+        let mut html = 0.0f32;
+        let mut json = 0.0f32;
+        for range in header.split(',') {
+            let mut parts = range.split(';');
+            let media_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+            let Some(quality) = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())
+            else {
+                continue;
+            };
+            match media_type.as_str() {
+                "application/json" => json = json.max(quality),
+                "text/html" | "text/*" | "*/*" => html = html.max(quality),
+                _ => {}
+            }
+        }
+        if json > html {
+            Format::Json
+        } else {
+            Format::Html
+        }
+    }
+}
+
+impl FromRequest for Format {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let format = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .map_or(Format::Html, Format::from_accept);
+        ready(Ok(format))
+    }
+}
+
+/// A rendered page, for `Format::Html`.
+pub fn html_page(page: Markup) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((header::VARY, VARY))
+        .content_type("text/html; charset=utf-8")
+        .body(page.into_string())
+}
+
+/// The data a page is rendered from, for `Format::Json`.
+pub fn json_page(data: &impl Serialize) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((header::VARY, VARY))
+        .json(data)
+}
@@ -0,0 +1,196 @@
+//! Display preferences for the listing tables, kept in the session like
+//! the table/grid choice on `/`: the filters and sort order last used on
+//! each table, and which of its columns are shown. The session is a
+//! cookie, so they follow the browser rather than an account.
+
+use std::collections::HashMap;
+
+use actix_session::Session;
+use actix_web::{HttpResponse, http::header, post, web};
+use maud::{Markup, html};
+
+use crate::web::i18n::Lang;
+
+/// A listing table with remembered preferences.
+pub struct Table {
+    /// Names the table in the session and in its column form's URL.
+    id: &'static str,
+    path: &'static str,
+    /// Query parameters remembered between visits.
+    remembered: &'static [&'static str],
+    /// Columns that can be hidden, by heading message id. The rest are
+    /// always shown.
+    optional_columns: &'static [&'static str],
+}
+
+const MODLIST_COLUMNS: &[&str] = &[
+    "col-version",
+    "col-game",
+    "col-author",
+    "col-added",
+    "col-filename",
+    "col-size",
+    "col-hash",
+    "col-mods-total",
+    "col-mods-available",
+    "col-status",
+];
+
+pub static MODLISTS: Table = Table {
+    id: "modlists",
+    path: "/",
+    remembered: &["game", "author", "added_after", "sort"],
+    optional_columns: MODLIST_COLUMNS,
+};
+
+pub static MUTED_MODLISTS: Table = Table {
+    id: "muted-modlists",
+    path: "/modlists/muted",
+    remembered: &["game", "author", "added_after", "sort"],
+    optional_columns: MODLIST_COLUMNS,
+};
+
+/// `/mods` is always in filename order, since it is paged with a cursor
+/// on the filename.
+pub static MODS: Table = Table {
+    id: "mods",
+    path: "/mods",
+    remembered: &["filter", "source", "game", "min_size", "max_size"],
+    optional_columns: &[
+        "col-name",
+        "col-version",
+        "col-size",
+        "col-hash",
+        "col-modlists",
+        "col-status",
+    ],
+};
+
+static TABLES: [&Table; 3] = [&MODLISTS, &MUTED_MODLISTS, &MODS];
+
+impl Table {
+    fn filters_key(&self) -> String {
+        format!("filters:{}", self.id)
+    }
+
+    fn hidden_columns_key(&self) -> String {
+        format!("hidden_columns:{}", self.id)
+    }
+
+    /// Remembers the filters in `query` when it sets any and forgets them
+    /// on `?reset`. A visit without any query is redirected to the
+    /// remembered filters, if there are some.
+    pub fn remember_filters(
+        &self,
+        query: &HashMap<String, String>,
+        session: &Session,
+    ) -> Result<Option<HttpResponse>, actix_web::Error> {
+        if query.contains_key("reset") {
+            session.remove(&self.filters_key());
+            return Ok(None);
+        }
+        if self.remembered.iter().any(|key| query.contains_key(*key)) {
+            // A filter form submitted with every field empty clears them.
+            let filters: Vec<(&str, &str)> = self
+                .remembered
+                .iter()
+                .filter_map(|key| {
+                    let value = query.get(*key)?.trim();
+                    (!value.is_empty()).then_some((*key, value))
+                })
+                .collect();
+            if filters.is_empty() {
+                session.remove(&self.filters_key());
+            } else {
+                session.insert(self.filters_key(), &filters)?;
+            }
+            return Ok(None);
+        }
+        if !query.is_empty() {
+            return Ok(None);
+        }
+        let Some(filters) = session.get::<Vec<(String, String)>>(&self.filters_key())? else {
+            return Ok(None);
+        };
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        serializer.extend_pairs(&filters);
+        Ok(Some(
+            HttpResponse::SeeOther()
+                .insert_header((
+                    header::LOCATION,
+                    format!("{}?{}", self.path, serializer.finish()),
+                ))
+                .finish(),
+        ))
+    }
+
+    pub fn columns(&'static self, session: &Session) -> Columns {
+        let hidden = session
+            .get::<Vec<String>>(&self.hidden_columns_key())
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        Columns {
+            table: self,
+            hidden,
+        }
+    }
+}
+
+/// The columns of a table this session shows.
+pub struct Columns {
+    table: &'static Table,
+    hidden: Vec<String>,
+}
+
+impl Columns {
+    pub fn shows(&self, heading: &str) -> bool {
+        !self.hidden.iter().any(|hidden| hidden == heading)
+    }
+
+    /// The form for picking the optional columns, folded away by default.
+    pub fn picker(&self, lang: Lang) -> Markup {
+        html! {
+            details.column-picker {
+                summary { (lang.t("columns")) }
+                form method="post" action=(format!("/preferences/{}/columns", self.table.id)) {
+                    @for column in self.table.optional_columns.iter().copied() {
+                        label {
+                            input type="checkbox" name="show" value=(column) checked[self.shows(column)];
+                            " " (lang.t(column))
+                        }
+                    }
+                    button type="submit" { (lang.t("columns-apply")) }
+                }
+            }
+        }
+    }
+}
+
+/// Saves which optional columns of a table to show and goes back to it.
+#[post("/preferences/{table}/columns")]
+pub async fn set_columns(
+    table: web::Path<String>,
+    form: web::Form<Vec<(String, String)>>,
+    session: Session,
+) -> Result<HttpResponse, actix_web::Error> {
+    let table = TABLES
+        .into_iter()
+        .find(|t| t.id == table.as_str())
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown table"))?;
+    let shown: Vec<&str> = form
+        .iter()
+        .filter(|(name, _)| name == "show")
+        .map(|(_, column)| column.as_str())
+        .collect();
+    let hidden: Vec<&str> = table
+        .optional_columns
+        .iter()
+        .copied()
+        .filter(|column| !shown.contains(column))
+        .collect();
+    session.insert(table.hidden_columns_key(), &hidden)?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, table.path))
+        .finish())
+}
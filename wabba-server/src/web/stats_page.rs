@@ -0,0 +1,314 @@
+use actix_web::{HttpResponse, get, web};
+use maud::{Markup, html};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::Serialize;
+
+use crate::config::UiMode;
+use crate::db::digest::{AvailabilitySnapshot, Digest, StoredDigest};
+use crate::web::components::{format_size, header_nav, layout};
+use crate::web::i18n::Lang;
+use crate::web::negotiate::{Format, html_page, json_page};
+
+/// How many past digests are listed on the stats page.
+const DIGEST_HISTORY_LIMIT: u32 = 20;
+
+fn format_timestamp(unix_seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(unix_seconds, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| unix_seconds.to_string())
+}
+
+/// The body of a digest, shared by the stats page and the HTML download.
+fn render_digest(lang: Lang, digest: &Digest) -> Markup {
+    html! {
+        p {
+            (lang.tf("digest-period", &[
+                ("start", format_timestamp(digest.period_start)),
+                ("end", format_timestamp(digest.period_end)),
+            ]))
+        }
+
+        h3 { (lang.t("heading-new-uploads")) }
+        @if digest.new_modlists.is_empty() && digest.new_mods == 0 {
+            p.empty-state { (lang.t("digest-nothing-new")) }
+        } @else {
+            @if digest.new_mods > 0 {
+                p {
+                    (lang.tf("digest-new-mods", &[
+                        ("count", digest.new_mods.to_string()),
+                        ("size", format_size(digest.new_mods_bytes)),
+                    ]))
+                }
+            }
+            @if !digest.new_modlists.is_empty() {
+                ul {
+                    @for modlist in &digest.new_modlists {
+                        li { (modlist.name) " " (modlist.version) }
+                    }
+                }
+            }
+        }
+
+        h3 { (lang.t("heading-availability-changes")) }
+        @if digest.availability_changes.is_empty() {
+            p.empty-state { (lang.t("digest-no-changes")) }
+        } @else {
+            table.modlist-table {
+                thead {
+                    tr {
+                        th { (lang.t("col-modlist")) }
+                        th { (lang.t("col-before")) }
+                        th { (lang.t("col-after")) }
+                        th { (lang.t("col-total")) }
+                    }
+                }
+                tbody {
+                    @for change in &digest.availability_changes {
+                        tr {
+                            td { (change.name) }
+                            td {
+                                @match change.available_before {
+                                    Some(before) => { (before) }
+                                    None => em { (lang.t("digest-new")) },
+                                }
+                            }
+                            td { (change.available_after) }
+                            td { (change.total) }
+                        }
+                    }
+                }
+            }
+        }
+
+        h3 { (lang.t("heading-dead-links")) }
+        @if digest.dead_links.is_empty() {
+            p.empty-state { (lang.t("digest-none")) }
+        } @else {
+            table.modlist-table {
+                thead {
+                    tr {
+                        th { (lang.t("col-mod")) }
+                        th { (lang.t("col-source")) }
+                        th { (lang.t("col-failures")) }
+                    }
+                }
+                tbody {
+                    @for link in &digest.dead_links {
+                        tr {
+                            td { (link.filename) " (#" (link.mod_id) ")" }
+                            td { (link.source_type) }
+                            td { (link.consecutive_failures) }
+                        }
+                    }
+                }
+            }
+        }
+
+        h3 { (lang.t("heading-disk-usage")) }
+        table.modlist-table {
+            thead {
+                tr {
+                    th { (lang.t("col-when")) }
+                    th { (lang.t("col-size")) }
+                }
+            }
+            tbody {
+                @for point in &digest.disk_usage {
+                    tr {
+                        td { (format_timestamp(point.taken_at)) }
+                        td { (format_size(point.bytes)) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A row of the digest table on `/stats`, as JSON.
+#[derive(Serialize)]
+struct DigestSummary {
+    id: u64,
+    period_start: i64,
+    period_end: i64,
+    new_modlists: usize,
+    availability_changes: usize,
+    dead_links: usize,
+}
+
+impl From<&StoredDigest> for DigestSummary {
+    fn from(stored: &StoredDigest) -> Self {
+        DigestSummary {
+            id: stored.id,
+            period_start: stored.digest.period_start,
+            period_end: stored.digest.period_end,
+            new_modlists: stored.digest.new_modlists.len(),
+            availability_changes: stored.digest.availability_changes.len(),
+            dead_links: stored.digest.dead_links.len(),
+        }
+    }
+}
+
+/// What `/stats` shows, for `Accept: application/json`.
+#[derive(Serialize)]
+struct Stats<'a> {
+    current: &'a AvailabilitySnapshot,
+    digests: Vec<DigestSummary>,
+    latest_digest: Option<&'a Digest>,
+}
+
+#[get("/stats")]
+pub async fn stats_page(
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    mode: UiMode,
+    lang: Lang,
+    format: Format,
+) -> Result<HttpResponse, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let current =
+        AvailabilitySnapshot::take(&conn).map_err(actix_web::error::ErrorInternalServerError)?;
+    let digests = StoredDigest::get_recent(DIGEST_HISTORY_LIMIT, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if format == Format::Json {
+        return Ok(json_page(&Stats {
+            current: &current,
+            digests: digests.iter().map(DigestSummary::from).collect(),
+            latest_digest: digests.first().map(|stored| &stored.digest),
+        }));
+    }
+
+    let page = layout(
+        lang,
+        lang.t("title-stats"),
+        "page-listing",
+        html! {},
+        html! {
+            (header_nav(lang.t("title-stats"), html! {
+                a.nav-link href="/" { (lang.t("nav-modlists")) }
+                @if mode.is_admin() {
+                    a.nav-link href="/admin/jobs" { (lang.t("nav-jobs")) }
+                }
+            }))
+            p {
+                strong { (lang.t("label-mods-available")) }
+                (lang.tf("count-of-total", &[
+                    ("count", current.mods_available.to_string()),
+                    ("total", current.mods_total.to_string()),
+                ]))
+            }
+            p {
+                strong { (lang.t("label-disk-usage")) }
+                (format_size(current.disk_bytes))
+            }
+
+            h2 { (lang.t("heading-digests")) }
+            @if mode.is_admin() {
+                form method="post" action="/stats/digests" {
+                    button.bootstrap-button type="submit" { "Generate Digest Now" }
+                }
+            }
+            @if digests.is_empty() {
+                p.empty-state { (lang.t("empty-digests")) }
+            } @else {
+                table.modlist-table {
+                    thead {
+                        tr {
+                            th { (lang.t("col-period")) }
+                            th { (lang.t("col-new-modlists")) }
+                            th { (lang.t("col-availability-changes")) }
+                            th { (lang.t("col-dead-links")) }
+                            th { (lang.t("download")) }
+                        }
+                    }
+                    tbody {
+                        @for stored in &digests {
+                            tr {
+                                td {
+                                    (format_timestamp(stored.digest.period_start))
+                                    " – "
+                                    (format_timestamp(stored.digest.period_end))
+                                }
+                                td { (stored.digest.new_modlists.len()) }
+                                td { (stored.digest.availability_changes.len()) }
+                                td { (stored.digest.dead_links.len()) }
+                                td {
+                                    a href=(format!("/stats/digests/{}.html", stored.id)) { "HTML" }
+                                    " · "
+                                    a href=(format!("/stats/digests/{}.json", stored.id)) { "JSON" }
+                                }
+                            }
+                        }
+                    }
+                }
+                h2 { (lang.t("heading-latest-digest")) }
+                (render_digest(lang, &digests[0].digest))
+            }
+        },
+    );
+
+    Ok(html_page(page))
+}
+
+fn get_digest(
+    id: u64,
+    pool: &Pool<SqliteConnectionManager>,
+) -> Result<StoredDigest, actix_web::Error> {
+    let conn = pool
+        .get()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    StoredDigest::get_by_id(id, &conn)
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Digest not found"))
+}
+
+#[get("/stats/digests/{id}.json")]
+pub async fn download_digest_json(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let id = id.into_inner();
+    let stored = get_digest(id, &pool)?;
+
+    Ok(HttpResponse::Ok()
+        .append_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"digest-{}.json\"", id),
+        ))
+        .json(stored.digest))
+}
+
+#[get("/stats/digests/{id}.html")]
+pub async fn download_digest_html(
+    id: web::Path<u64>,
+    pool: web::Data<Pool<SqliteConnectionManager>>,
+    lang: Lang,
+) -> Result<HttpResponse, actix_web::Error> {
+    let id = id.into_inner();
+    let stored = get_digest(id, &pool)?;
+
+    // Standalone, so it still reads fine when saved or mailed on.
+    let page = html! {
+        (maud::DOCTYPE)
+        html lang=(lang.code()) {
+            head {
+                meta charset="utf-8";
+                title { (lang.t("title-digest")) " " (format_timestamp(stored.digest.period_end)) }
+            }
+            body {
+                h1 { (lang.t("title-digest")) }
+                (render_digest(lang, &stored.digest))
+            }
+        }
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .append_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"digest-{}.html\"", id),
+        ))
+        .body(page.into_string()))
+}
@@ -6,12 +6,17 @@ use futures_util::TryStreamExt;
 use maud::html;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use serde::Serialize;
 use tokio::{
     fs::OpenOptions,
     io::{AsyncWriteExt, BufWriter},
 };
 use wabba_protocol::hash::Hash;
 
+use crate::resources::resumable_upload::MAX_CHUNK_SIZE;
+use crate::web::components::layout;
+use crate::web::i18n::Lang;
+use crate::web::negotiate::{Format, html_page, json_page};
 use crate::{
     data_dir::DataDir,
     db::mod_data::Mod,
@@ -19,47 +24,66 @@ use crate::{
     resources::ingest::{ingest_mod, ingest_modlist},
 };
 
+/// File types the upload form offers to pick.
+const ACCEPTED_EXTENSIONS: [&str; 4] = [".zip", ".7z", ".rar", ".wabbajack"];
+
+/// What `/upload` offers, for `Accept: application/json`: where the form
+/// posts to and where resumable uploads are reserved.
+#[derive(Serialize)]
+struct UploadForm {
+    action: &'static str,
+    resumable_action: &'static str,
+    max_chunk_size: usize,
+    accepted_extensions: [&'static str; 4],
+}
+
 #[get("/upload")]
-pub async fn upload_page() -> impl Responder {
-    let page = html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                title { "Upload File" }
-                link rel="stylesheet" href="/res/styles.css";
+pub async fn upload_page(format: Format) -> impl Responder {
+    if format == Format::Json {
+        return json_page(&UploadForm {
+            action: "/upload",
+            resumable_action: "/uploads",
+            max_chunk_size: MAX_CHUNK_SIZE,
+            accepted_extensions: ACCEPTED_EXTENSIONS,
+        });
+    }
+
+    let page = layout(
+        Lang::English,
+        "Upload File",
+        "page-listing",
+        html! {
+            script src="/res/resumable-upload.js" {}
+        },
+        html! {
+            div.header-nav {
+                h1 { "Upload File" }
+                p { "Upload a modlist or mod file to the server" }
             }
-            body.page-listing {
-                div.container {
-                    div.header-nav {
-                        h1 { "Upload File" }
-                        p { "Upload a modlist or mod file to the server" }
+            div.upload-section {
+                h2 { "Upload a file" }
+                form method="post" action="/upload" enctype="multipart/form-data" data-resumable {
+                    div.form-group {
+                        label for="file-input" {
+                            "Select File:"
+                        }
+                        input type="file" id="file-input" name="file" accept=(ACCEPTED_EXTENSIONS.join(",")) required {}
                     }
-                    div.upload-section {
-                        h2 { "Upload a file" }
-                        form method="post" action="/upload" enctype="multipart/form-data" {
-                            div.form-group {
-                                label for="file-input" {
-                                    "Select File:"
-                                }
-                                input type="file" id="file-input" name="file" accept=".zip,.7z,.rar,.wabbajack" required {}
-                            }
-                            div.form-group {
-                                button.upload-button type="submit" {
-                                    "Upload"
-                                }
-                            }
+                    div.form-group {
+                        button.upload-button type="submit" {
+                            "Upload"
                         }
                     }
+                    div.form-group {
+                        progress id="upload-progress" hidden {}
+                        p id="upload-status" {}
+                    }
                 }
             }
-        }
-    };
+        },
+    );
 
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(page.into_string())) as Result<HttpResponse, actix_web::Error>
+    html_page(page)
 }
 
 #[post("/upload")]
@@ -305,43 +329,36 @@ pub async fn upload_post(
 }
 
 fn render_upload_result(success: bool, message: String, hash: Option<String>) -> HttpResponse {
-    let page = html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                title { "Upload File" }
-                link rel="stylesheet" href="/res/styles.css";
+    let page = layout(
+        Lang::English,
+        "Upload File",
+        "page-listing",
+        html! {},
+        html! {
+            div.header-nav {
+                h1 { "Upload File" }
             }
-            body.page-listing {
-                div.container {
-                    div.header-nav {
-                        h1 { "Upload File" }
+            div.upload-section {
+                h2 { "Result" }
+                @if success {
+                    div.success-message {
+                        p { (message) }
                     }
-                    div.upload-section {
-                        h2 { "Result" }
-                        @if success {
-                            div.success-message {
-                                p { (message) }
-                            }
-                        } @else {
-                            div.error-message {
-                                p { (message) }
-                            }
-                        }
-                        @if let Some(ref hash_value) = hash {
-                            p {
-                                strong { "Hash: " }
-                                code { (hash_value) }
-                            }
-                        }
-
+                } @else {
+                    div.error-message {
+                        p { (message) }
+                    }
+                }
+                @if let Some(ref hash_value) = hash {
+                    p {
+                        strong { "Hash: " }
+                        code { (hash_value) }
                     }
                 }
+
             }
-        }
-    };
+        },
+    );
 
     HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
@@ -0,0 +1,518 @@
+//! Typed client for the wabba-server API, following the operations in its
+//! OpenAPI description (`/api/openapi.json`). Only the operations the CLI
+//! calls are covered.
+
+use futures_util::StreamExt;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue, IF_NONE_MATCH, RANGE};
+use reqwest::{Certificate, Client, Proxy, StatusCode};
+use serde::de::DeserializeOwned;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio_util::codec::{BytesCodec, FramedRead};
+use wabba_protocol::api::{
+    ArchiveKind, AvailabilityReport, CLIENT_HEADER, CONTENT_HASH_HEADER, HashCheckRequest,
+    ModSummary, ModlistArchive, ModlistSummary, NEXT_CURSOR_HEADER, UploadResult,
+};
+use wabba_protocol::hash::{Hash, StreamingHash};
+
+use crate::rate_limit::RateLimiter;
+
+/// Tries per archive before `download_mod` gives up.
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Idle connections kept per host, enough for every `--transfers` worker
+/// to reuse its connection instead of handshaking again.
+const MAX_IDLE_CONNECTIONS: usize = 16;
+
+/// How to reach servers that sit behind a proxy or an internal CA:
+/// `--proxy`, `--ca-cert` and `--insecure`, what to call ourselves
+/// (`--client-name`) and how to authenticate (`--api-token`).
+#[derive(Clone, Default)]
+pub struct ClientOptions {
+    proxy: Option<Proxy>,
+    root_certificates: Vec<Certificate>,
+    insecure: bool,
+    client_name: Option<HeaderValue>,
+    authorization: Option<HeaderValue>,
+}
+
+impl ClientOptions {
+    /// Reads the PEM files in `ca_certs`, each of which can hold several
+    /// certificates. Fails on a file that can't be read or a proxy URL that
+    /// doesn't parse, so a typo doesn't silently bypass either.
+    pub fn new(
+        proxy: Option<&str>,
+        ca_certs: &[PathBuf],
+        insecure: bool,
+        client_name: Option<&str>,
+        api_token: Option<&str>,
+    ) -> Result<Self, String> {
+        let proxy = proxy
+            .map(|url| Proxy::all(url).map_err(|e| format!("Invalid proxy {}: {}", url, e)))
+            .transpose()?;
+        let mut root_certificates = Vec::new();
+        for path in ca_certs {
+            let pem = std::fs::read(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("Invalid certificate in {}: {}", path.display(), e))?;
+            log::debug!(
+                "Trusting {} certificates from {}",
+                certificates.len(),
+                path.display()
+            );
+            root_certificates.extend(certificates);
+        }
+        if insecure {
+            log::warn!("Not checking server certificates (--insecure)");
+        }
+        let client_name = client_name
+            .map(|name| {
+                HeaderValue::from_str(name.trim())
+                    .map_err(|_| format!("Invalid client name {:?}", name))
+            })
+            .transpose()?;
+        let authorization = api_token
+            .map(|token| {
+                let mut value = HeaderValue::from_str(&format!("Bearer {}", token.trim()))
+                    .map_err(|_| "Invalid API token".to_string())?;
+                value.set_sensitive(true);
+                Ok::<_, String>(value)
+            })
+            .transpose()?;
+        Ok(ClientOptions {
+            proxy,
+            root_certificates,
+            insecure,
+            client_name,
+            authorization,
+        })
+    }
+}
+
+/// The one client shared by every request a command makes. HTTP/2 is
+/// negotiated over TLS where the server offers it, multiplexing parallel
+/// transfers over a single connection; plain HTTP servers keep using
+/// pooled HTTP/1.1 connections. Without `--proxy`, the usual
+/// `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` variables apply.
+fn build_client(options: &ClientOptions) -> Result<Client, reqwest::Error> {
+    let mut builder = Client::builder()
+        .pool_max_idle_per_host(MAX_IDLE_CONNECTIONS)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60))
+        .tcp_nodelay(true)
+        .http2_adaptive_window(true)
+        .danger_accept_invalid_certs(options.insecure);
+    if let Some(proxy) = &options.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    for certificate in &options.root_certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    let mut headers = HeaderMap::new();
+    if let Some(name) = &options.client_name {
+        headers.insert(CLIENT_HEADER, name.clone());
+    }
+    if let Some(authorization) = &options.authorization {
+        headers.insert(AUTHORIZATION, authorization.clone());
+    }
+    builder = builder.default_headers(headers);
+    builder.build()
+}
+
+pub enum UploadOutcome {
+    Uploaded(UploadResult),
+    AlreadyPresent,
+    Failed(u16, String),
+}
+
+pub struct ServerClient {
+    client: Client,
+    base_url: String,
+    upload_limit: Option<RateLimiter>,
+}
+
+impl ServerClient {
+    /// Probe the server once (`GET /hello`) and remember the post-redirect
+    /// base URL. Reqwest follows GET redirects transparently but cannot
+    /// replay a streamed POST body, so we resolve any redirect chain (e.g.
+    /// Traefik's HTTP→HTTPS 308) up front and use the resolved base URL for
+    /// the rest of the run.
+    pub async fn connect(server: &str, options: &ClientOptions) -> Result<Self, reqwest::Error> {
+        let client = build_client(options)?;
+        let server = server.trim_end_matches('/');
+        let probe_url = format!("{}/hello", server);
+        let response = client.get(&probe_url).send().await?;
+        let final_url = response.url().as_str();
+        let base_url = final_url
+            .trim_end_matches("/hello")
+            .trim_end_matches('/')
+            .to_string();
+        if base_url != server {
+            log::info!("Resolved server URL {} -> {}", server, base_url);
+        }
+        Ok(ServerClient {
+            client,
+            base_url,
+            upload_limit: None,
+        })
+    }
+
+    /// Throttles streamed uploads to `bytes_per_second`, shared across
+    /// every upload this client makes.
+    pub fn with_upload_limit(mut self, bytes_per_second: Option<u64>) -> Self {
+        self.upload_limit = bytes_per_second.map(RateLimiter::new);
+        self
+    }
+
+    /// The server's URL after following any redirects.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// `POST /check`. Sorts `hashes` into the ones the server already has
+    /// and the ones it still needs.
+    pub async fn check(
+        &self,
+        kind: ArchiveKind,
+        hashes: Vec<String>,
+    ) -> Result<AvailabilityReport, reqwest::Error> {
+        self.post_check(&HashCheckRequest {
+            kind,
+            hashes,
+            sizes: Vec::new(),
+        })
+        .await
+    }
+
+    /// `POST /check` for local files as `(hash, size)`. A file the server
+    /// stores under the hash but with another size counts as missing.
+    pub async fn check_files(
+        &self,
+        kind: ArchiveKind,
+        files: &[(String, u64)],
+    ) -> Result<AvailabilityReport, reqwest::Error> {
+        let (hashes, sizes) = files.iter().cloned().unzip();
+        self.post_check(&HashCheckRequest {
+            kind,
+            hashes,
+            sizes,
+        })
+        .await
+    }
+
+    async fn post_check(
+        &self,
+        request: &HashCheckRequest,
+    ) -> Result<AvailabilityReport, reqwest::Error> {
+        let url = format!("{}/check", self.base_url);
+        self.client
+            .post(&url)
+            .json(request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    /// Every page of a paged listing, following the `X-Next-Cursor` header
+    /// from one page to the next.
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>, reqwest::Error> {
+        let mut items = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let mut request = self.client.get(url).query(query);
+            if let Some(after) = &after {
+                request = request.query(&[("after", after)]);
+            }
+            let response = request.send().await?.error_for_status()?;
+            let next = response
+                .headers()
+                .get(NEXT_CURSOR_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            items.extend(response.json::<Vec<T>>().await?);
+            match next {
+                Some(next) => after = Some(next),
+                None => return Ok(items),
+            }
+        }
+    }
+
+    /// `GET /api/modlists`, every page.
+    pub async fn list_modlists(&self) -> Result<Vec<ModlistSummary>, reqwest::Error> {
+        let url = format!("{}/api/modlists", self.base_url);
+        self.get_all_pages(&url, &[]).await
+    }
+
+    /// `GET /api/modlists/{id}/archives`.
+    pub async fn modlist_archives(&self, id: u64) -> Result<Vec<ModlistArchive>, reqwest::Error> {
+        let url = format!("{}/api/modlists/{}/archives", self.base_url, id);
+        self.client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    /// `GET /api/mods`, every page, optionally only the missing ones and
+    /// only those a modlist uses.
+    pub async fn list_mods(
+        &self,
+        missing: bool,
+        modlist: Option<u64>,
+    ) -> Result<Vec<ModSummary>, reqwest::Error> {
+        let url = format!("{}/api/mods", self.base_url);
+        let mut query = vec![("missing", missing.to_string())];
+        if let Some(modlist) = modlist {
+            query.push(("modlist", modlist.to_string()));
+        }
+        self.get_all_pages(&url, &query).await
+    }
+
+    /// `POST /submit/{modlist,mod}/{filename}`, streaming the file as the
+    /// body. The caller is responsible for deciding whether the upload is
+    /// needed; this will submit the body regardless.
+    pub async fn submit(
+        &self,
+        file: &Path,
+        hash: &str,
+    ) -> Result<UploadOutcome, Box<dyn std::error::Error>> {
+        self.submit_tracked(file, hash, None).await
+    }
+
+    /// Like [`ServerClient::submit`], adding the length of each chunk to
+    /// `sent` as it goes out.
+    pub async fn submit_tracked(
+        &self,
+        file: &Path,
+        hash: &str,
+        sent: Option<Arc<AtomicU64>>,
+    ) -> Result<UploadOutcome, Box<dyn std::error::Error>> {
+        let filename = file
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or("Invalid filename")?;
+
+        let async_file = File::open(file).await?;
+        let stream = FramedRead::new(async_file, BytesCodec::new()).inspect(move |chunk| {
+            if let (Some(sent), Ok(bytes)) = (&sent, chunk) {
+                sent.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+            }
+        });
+        let body = match self.upload_limit.clone() {
+            Some(limiter) => reqwest::Body::wrap_stream(stream.then(move |chunk| {
+                let limiter = limiter.clone();
+                async move {
+                    if let Ok(bytes) = &chunk {
+                        limiter.throttle(bytes.len()).await;
+                    }
+                    chunk
+                }
+            })),
+            None => reqwest::Body::wrap_stream(stream),
+        };
+
+        self.submit_body(filename, hash, body).await
+    }
+
+    /// Like [`ServerClient::submit`], for contents already in memory.
+    pub async fn submit_bytes(
+        &self,
+        filename: &str,
+        contents: Vec<u8>,
+    ) -> Result<UploadOutcome, Box<dyn std::error::Error>> {
+        let hash = Hash::compute(&contents);
+        self.submit_body(filename, &hash, reqwest::Body::from(contents))
+            .await
+    }
+
+    /// Where the server serves the mod with this hash. Hashes are base64,
+    /// which can contain `/`, so the path carries them as base64url.
+    pub fn mod_download_url(&self, hash: &str) -> String {
+        format!(
+            "{}/mods/by-hash/{}/download",
+            self.base_url,
+            hash.replace('+', "-")
+                .replace('/', "_")
+                .trim_end_matches('=')
+        )
+    }
+
+    /// `GET /mods/by-hash/{hash}/download`, writing the archive to `dest`.
+    /// The body goes to a `.part` file next to `dest` and is hashed as it
+    /// arrives; it is only renamed into place once size and hash match.
+    /// An interrupted transfer leaves the `.part` file behind and is
+    /// resumed with a Range request, by the next attempt or the next run.
+    /// Corrupt transfers are discarded and retried.
+    pub async fn download_mod(
+        &self,
+        hash: &str,
+        size: u64,
+        dest: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let url = self.mod_download_url(hash);
+        let mut part_name = dest.file_name().ok_or("Invalid filename")?.to_os_string();
+        part_name.push(".part");
+        let part = dest.with_file_name(part_name);
+
+        for attempt in 1..=DOWNLOAD_ATTEMPTS {
+            log::debug!("GET {} (attempt {})", url, attempt);
+            match self.download_attempt(&url, hash, size, &part).await {
+                Ok(()) => {
+                    std::fs::rename(&part, dest)?;
+                    return Ok(());
+                }
+                Err(e) if attempt == DOWNLOAD_ATTEMPTS => return Err(e),
+                Err(e) => log::warn!("Retrying {}: {}", dest.display(), e),
+            }
+        }
+        unreachable!("the last attempt always returns")
+    }
+
+    /// One pass at filling `part`. Removes `part` itself when what it holds
+    /// turns out to be wrong, so only sound prefixes are ever resumed.
+    async fn download_attempt(
+        &self,
+        url: &str,
+        hash: &str,
+        size: u64,
+        part: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let discard = |message: String| -> Box<dyn std::error::Error> {
+            let _ = std::fs::remove_file(part);
+            message.into()
+        };
+
+        let mut hasher = StreamingHash::new();
+        let mut received = match std::fs::metadata(part) {
+            Ok(metadata) if metadata.len() <= size => {
+                let mut existing = std::fs::File::open(part)?;
+                let mut buf = vec![0u8; 64 * 1024];
+                loop {
+                    let n = existing.read(&mut buf)?;
+                    if n == 0 {
+                        break;
+                    }
+                    hasher.update(&buf[..n]);
+                }
+                metadata.len()
+            }
+            Ok(_) => {
+                return Err(discard(
+                    "Partial file is larger than the archive".to_string(),
+                ));
+            }
+            Err(_) => 0,
+        };
+
+        if received < size {
+            let mut request = self.client.get(url);
+            if received > 0 {
+                log::info!("Resuming {} at byte {}", part.display(), received);
+                request = request.header(RANGE, format!("bytes={}-", received));
+            }
+            let mut response = request.send().await?;
+            if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+                return Err(discard("Server refused to resume".to_string()));
+            }
+            response.error_for_status_ref()?;
+            if let Some(advertised) = response.headers().get(CONTENT_HASH_HEADER)
+                && advertised.to_str().ok() != Some(hash)
+            {
+                return Err(
+                    format!("Server is serving {:?} instead of {}", advertised, hash).into(),
+                );
+            }
+
+            let mut file = if response.status() == StatusCode::PARTIAL_CONTENT {
+                std::fs::OpenOptions::new().append(true).open(part)?
+            } else {
+                // The server ignored the range; start over.
+                hasher = StreamingHash::new();
+                received = 0;
+                std::fs::File::create(part)?
+            };
+            while let Some(chunk) = response.chunk().await? {
+                received += chunk.len() as u64;
+                if received > size {
+                    return Err(discard(format!(
+                        "Received more than the expected {} bytes",
+                        size
+                    )));
+                }
+                hasher.update(&chunk);
+                file.write_all(&chunk)?;
+            }
+            file.flush()?;
+        }
+
+        if received != size {
+            return Err(format!("Received {} of {} bytes", received, size).into());
+        }
+        let received_hash = hasher.finish();
+        if received_hash != hash {
+            return Err(discard(format!(
+                "Hash mismatch: expected {}, got {}",
+                hash, received_hash
+            )));
+        }
+        Ok(())
+    }
+
+    /// `GET /api/backup`, writing the database snapshot to `dest`. Returns
+    /// the number of bytes written.
+    pub async fn backup_database(&self, dest: &Path) -> Result<u64, Box<dyn std::error::Error>> {
+        let url = format!("{}/api/backup", self.base_url);
+        log::info!("GET {}", url);
+        let mut response = self.client.get(&url).send().await?.error_for_status()?;
+        let mut file = std::fs::File::create(dest)?;
+        let mut written = 0u64;
+        while let Some(chunk) = response.chunk().await? {
+            written += chunk.len() as u64;
+            file.write_all(&chunk)?;
+        }
+        file.sync_all()?;
+        Ok(written)
+    }
+
+    async fn submit_body(
+        &self,
+        filename: &str,
+        hash: &str,
+        body: reqwest::Body,
+    ) -> Result<UploadOutcome, Box<dyn std::error::Error>> {
+        let kind = ArchiveKind::for_filename(filename);
+        let url = format!("{}/submit/{}/{}", self.base_url, kind.as_str(), filename);
+
+        log::info!("POST {}", url);
+        let response = self
+            .client
+            .post(&url)
+            .header(IF_NONE_MATCH, hash)
+            .body(body)
+            .send()
+            .await?;
+
+        let code = response.status().as_u16();
+        match code {
+            200 => Ok(UploadOutcome::Uploaded(response.json().await?)),
+            304 => Ok(UploadOutcome::AlreadyPresent),
+            _ => {
+                let body = response.text().await.unwrap_or_default();
+                Ok(UploadOutcome::Failed(code, body))
+            }
+        }
+    }
+}
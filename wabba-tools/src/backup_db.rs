@@ -0,0 +1,139 @@
+//! `backup-db`: a copy of the server's database that is consistent even
+//! while the server writes to it. Copying `db.db` with `cp` can catch a
+//! half-written page and leaves out whatever is still in the WAL.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::backup::{Backup, Progress};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::api_client::{ClientOptions, ServerClient};
+
+/// Pages copied per backup step. Between steps the server can write; a
+/// write restarts the copy, so steps are large enough to finish quickly.
+const PAGES_PER_STEP: std::os::raw::c_int = 1024;
+
+/// How every SQLite database file starts.
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+fn staging_path(output: &Path) -> PathBuf {
+    let mut staging = output.as_os_str().to_os_string();
+    staging.push(".part");
+    PathBuf::from(staging)
+}
+
+fn log_progress(progress: Progress) {
+    log::debug!(
+        "{} of {} pages left to copy",
+        progress.remaining,
+        progress.pagecount
+    );
+}
+
+/// Checks that `path` holds a readable SQLite database and returns how many
+/// modlists and mods it records.
+fn check_backup(path: &Path) -> Result<(u64, u64), String> {
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|_| "not a SQLite database".to_string())?;
+    if header != SQLITE_HEADER {
+        return Err("not a SQLite database".to_string());
+    }
+    // Not read-only: a copy of a WAL database is opened in WAL mode, which
+    // needs its `-shm` file created.
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    let count = |table: &str| -> Result<u64, String> {
+        conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| {
+            row.get(0)
+        })
+        .map_err(|e| e.to_string())
+    };
+    Ok((count("modlist")?, count("mod")?))
+}
+
+/// Copies the live database at `db` with SQLite's online backup API, which
+/// reads a consistent snapshot, WAL included, while other connections keep
+/// writing.
+fn backup_local(db: &Path, staging: &Path) -> Result<(), String> {
+    if !db.is_file() {
+        return Err(format!("{} does not exist", db.display()));
+    }
+    let source = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", db.display(), e))?;
+    source
+        .busy_timeout(Duration::from_secs(30))
+        .map_err(|e| e.to_string())?;
+    let mut dest = Connection::open(staging)
+        .map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+    let backup = Backup::new(&source, &mut dest).map_err(|e| e.to_string())?;
+    backup
+        .run_to_completion(
+            PAGES_PER_STEP,
+            Duration::from_millis(50),
+            Some(log_progress),
+        )
+        .map_err(|e| format!("Backup failed: {}", e))
+}
+
+/// Backs up the database of `server`, or the file `db` on this machine, to
+/// `output`. The copy is made next to `output` and only renamed over it once
+/// it opens as a database, so a failed run never replaces a good backup.
+pub async fn backup_database(
+    server: Option<&str>,
+    client: &ClientOptions,
+    db: Option<&Path>,
+    output: &Path,
+) -> Result<(), String> {
+    let staging = staging_path(output);
+    let _ = std::fs::remove_file(&staging);
+    let result = match (server, db) {
+        (None, Some(db)) => {
+            log::info!("Backing up {}", db.display());
+            let db = db.to_path_buf();
+            let staging = staging.clone();
+            tokio::task::spawn_blocking(move || backup_local(&db, &staging))
+                .await
+                .expect("blocking backup task panicked")
+        }
+        (Some(server), None) => match ServerClient::connect(server, client).await {
+            Ok(server) => server
+                .backup_database(&staging)
+                .await
+                .map(|size| log::info!("Received {} bytes", size))
+                .map_err(|e| format!("Failed to download the backup: {}", e)),
+            Err(e) => Err(format!("Failed to reach server: {}", e)),
+        },
+        (Some(_), Some(_)) => Err("Give either --server or --db, not both".to_string()),
+        (None, None) => Err("Either --server or --db is required".to_string()),
+    }
+    .and_then(|()| {
+        // A server that requires logins answers with its login page.
+        check_backup(&staging).map_err(|e| {
+            format!(
+                "The backup is unusable ({}); if the server requires logins, pass its --api-token or run with --db on its machine",
+                e
+            )
+        })
+    });
+
+    match result {
+        Ok((modlists, mods)) => {
+            std::fs::rename(&staging, output)
+                .map_err(|e| format!("Failed to move backup to {}: {}", output.display(), e))?;
+            log::info!(
+                "Saved {} ({} modlists, {} mods)",
+                output.display(),
+                modlists,
+                mods
+            );
+            Ok(())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&staging);
+            Err(e)
+        }
+    }
+}
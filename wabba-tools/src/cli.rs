@@ -2,12 +2,71 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
+use crate::hashsum::HashFormat;
+use crate::rate_limit::parse_rate;
+use crate::report::OutputFormat;
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
     #[arg(short='v', long="verbose", action = clap::ArgAction::Count)]
     pub debug: u8,
 
+    /// Show a desktop notification when the command finishes
+    #[arg(long = "notify", global = true)]
+    pub notify: bool,
+
+    /// Print what would be copied, moved, deleted, written or uploaded
+    /// without doing it. Servers are still asked which files they have.
+    #[arg(long = "dry-run", global = true)]
+    pub dry_run: bool,
+
+    /// Cap the combined upload rate, e.g. `10MiB` (per second)
+    #[arg(long = "limit-rate", value_name = "RATE", global = true, value_parser = parse_rate)]
+    pub limit_rate: Option<u64>,
+
+    /// Reach the server through this proxy, e.g. `http://proxy:3128`.
+    /// Without it, `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` apply.
+    #[arg(long = "proxy", value_name = "URL", env = "WABBA_PROXY", global = true)]
+    pub proxy: Option<String>,
+
+    /// Also trust the certificate authorities in this PEM file, such as an
+    /// internal CA the server's reverse proxy uses. Can be repeated.
+    #[arg(
+        long = "ca-cert",
+        value_name = "PATH",
+        env = "WABBA_CA_CERT",
+        global = true
+    )]
+    pub ca_certs: Vec<PathBuf>,
+
+    /// Don't verify the server's TLS certificate. Anyone on the way can
+    /// then read and alter the transfer; prefer `--ca-cert`.
+    #[arg(long = "insecure", global = true)]
+    pub insecure: bool,
+
+    /// Name sent to servers with every request, e.g. this machine's
+    /// hostname, so their transfer statistics can tell clients sharing the
+    /// API token apart. Servers ignore it on requests without the token.
+    #[arg(
+        long = "client-name",
+        value_name = "NAME",
+        env = "WABBA_CLIENT_NAME",
+        global = true
+    )]
+    pub client_name: Option<String>,
+
+    /// The server's `API_TOKEN`, needed to upload or back up once it
+    /// signs users in
+    #[arg(
+        long = "api-token",
+        value_name = "TOKEN",
+        env = "WABBA_API_TOKEN",
+        hide_env_values = true,
+        global = true
+    )]
+    pub api_token: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -16,20 +75,98 @@ pub struct Cli {
 pub enum Commands {
     /// Validates that the required files are available
     Validate {
-        /// Path to the Wabbajack file
-        #[arg(value_name = "WABBJACK_FILE")]
-        wabbajack_file: PathBuf,
+        /// Path to the Wabbajack file. Left out with `--modlist-id` or
+        /// `--modlist-hash`, when every path is a download directory.
+        #[arg(
+            value_name = "WABBJACK_FILE",
+            required_unless_present_any = ["modlist_id", "modlist_hash"]
+        )]
+        wabbajack_file: Option<PathBuf>,
 
-        /// Path to the download directory
-        #[arg(value_name = "DOWNLOAD_DIRS")]
+        /// Paths to the download directories. Later directories are treated
+        /// as overflow or backup locations for archives the earlier ones lack.
+        #[arg(
+            value_name = "DOWNLOAD_DIRS",
+            required_unless_present_any = ["against_server", "modlist_id", "modlist_hash"]
+        )]
         download_dirs: Vec<PathBuf>,
+
+        /// Read the required archives of this modlist from the server's
+        /// database instead of from a local Wabbajack file. Needs
+        /// `--server` or `--against-server`.
+        #[arg(
+            long = "modlist-id",
+            value_name = "ID",
+            conflicts_with = "modlist_hash"
+        )]
+        modlist_id: Option<u64>,
+
+        /// Like `--modlist-id`, picking the modlist by the xxhash64 of its
+        /// `.wabbajack` file
+        #[arg(long = "modlist-hash", value_name = "HASH")]
+        modlist_hash: Option<String>,
+
+        /// Base URL of the server to read the modlist from
+        #[arg(long = "server", value_name = "URL")]
+        server: Option<String>,
+
+        /// Check the required archives against what this wabba-server has,
+        /// instead of against download directories
+        #[arg(
+            long = "against-server",
+            value_name = "URL",
+            conflicts_with_all = ["download_dirs", "verify_hashes", "fix_from"]
+        )]
+        against_server: Option<String>,
+
+        /// Also check the size and xxhash64 of every file that is present,
+        /// reporting files that don't match separately from missing ones.
+        #[arg(long = "verify-hashes")]
+        verify_hashes: bool,
+
+        /// Number of files to hash in parallel with `--verify-hashes`.
+        /// Defaults to 1 for spinning disks; see `sync --parallel`.
+        #[arg(long = "parallel", short = 'p', value_name = "N", default_value_t = 1)]
+        parallel: usize,
+
+        /// With `--verify-hashes`, skip the hash cache `sync` shares and
+        /// rehash every file. Otherwise files whose size and modification
+        /// time haven't changed reuse their cached hash.
+        #[arg(long = "no-cache")]
+        no_cache: bool,
+
+        /// Look for missing archives in this directory (by name, then by
+        /// hash) and link or copy them into the first download directory.
+        /// Can be given more than once.
+        #[arg(long = "fix-from", value_name = "DIR")]
+        fix_from: Vec<PathBuf>,
+
+        /// After reporting, keep watching the download directories and
+        /// show how many archives are there until none are missing
+        #[arg(long = "watch", conflicts_with = "against_server")]
+        watch: bool,
+
+        /// How to report the results
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Log)]
+        format: OutputFormat,
+
+        /// Also write the filename, size, hash and source of each missing
+        /// archive to this file, as CSV (`.csv`) or a Markdown table (`.md`)
+        #[arg(long = "report", value_name = "PATH")]
+        report: Option<PathBuf>,
     },
 
-    /// Hash a file using xxhash64
+    /// Hash files using xxhash64, as base64 like modlists and the server
+    /// use. Exits with an error if any file couldn't be hashed.
     Hash {
-        /// Path to the file to hash
-        #[arg(value_name = "FILE")]
-        file: PathBuf,
+        /// Paths to the files to hash. `-` reads stdin, and glob patterns
+        /// are expanded when the shell doesn't.
+        #[arg(value_name = "FILES", required = true)]
+        files: Vec<String>,
+
+        /// How to print the hashes
+        #[arg(long = "format", value_enum, default_value_t = HashFormat::Log)]
+        format: HashFormat,
     },
 
     /// Upload a modlist file or mod file to the server
@@ -48,13 +185,18 @@ pub enum Commands {
     /// considered; subdirectories and `.meta` files are ignored. Files are
     /// never downloaded from the server.
     Sync {
-        /// Base URL of the server to upload to
+        /// Base URL of the server to upload to, as in `sync SERVER
+        /// DIRECTORY`. With `--server`, the only argument is the directory.
         #[arg(value_name = "SERVER")]
-        server: String,
+        server_or_directory: String,
 
         /// Path to the directory to sync
-        #[arg(value_name = "DIRECTORY")]
-        directory: PathBuf,
+        #[arg(value_name = "DIRECTORY", required_unless_present = "server")]
+        directory: Option<PathBuf>,
+
+        /// Base URL of the server to upload to
+        #[arg(long = "server", value_name = "URL", conflicts_with = "directory")]
+        server: Option<String>,
 
         /// Skip the local hash cache and rehash every file.
         #[arg(long = "no-cache")]
@@ -66,5 +208,407 @@ pub enum Commands {
         /// (~4–8) or NVMe (~8–16) sources.
         #[arg(long = "parallel", short = 'p', value_name = "N", default_value_t = 1)]
         parallel: usize,
+
+        /// Number of uploads to run at once.
+        #[arg(
+            long = "transfers",
+            visible_alias = "jobs",
+            short = 'j',
+            value_name = "N",
+            default_value_t = 4
+        )]
+        transfers: usize,
+
+        /// Also write the summary as JSON to this file.
+        #[arg(long = "report-file", value_name = "PATH")]
+        report_file: Option<PathBuf>,
+    },
+
+    /// Upload every archive under a directory, including its
+    /// subdirectories, that the server doesn't already have. `.meta` files
+    /// are skipped. Unlike `sync`, no hash cache is kept.
+    UploadDir {
+        /// Path to the directory to upload
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Base URL of the server to upload to
+        #[arg(long = "server", value_name = "URL")]
+        server: String,
+
+        /// Number of files to hash in parallel. Keep at 1 for spinning disks.
+        #[arg(long = "parallel", short = 'p', value_name = "N", default_value_t = 1)]
+        parallel: usize,
+
+        /// Number of uploads to run at once.
+        #[arg(
+            long = "transfers",
+            visible_alias = "jobs",
+            short = 'j',
+            value_name = "N",
+            default_value_t = 4
+        )]
+        transfers: usize,
+
+        /// Also write the summary as JSON to this file.
+        #[arg(long = "report-file", value_name = "PATH")]
+        report_file: Option<PathBuf>,
+    },
+
+    /// Upload a modlist and every archive it needs that the server doesn't
+    /// have yet, taking the archives from the download directories, then
+    /// list what the server is still missing. Archives are sent with the
+    /// hashes the modlist lists, so a wrong file of the right name is
+    /// rejected by the server.
+    Publish {
+        /// Base URL of the server to upload to
+        #[arg(long = "server", value_name = "URL")]
+        server: String,
+
+        /// Path to the Wabbajack file
+        #[arg(value_name = "WABBAJACK_FILE")]
+        wabbajack_file: PathBuf,
+
+        /// Paths to the download directories. Earlier directories win when
+        /// more than one has an archive.
+        #[arg(value_name = "DOWNLOAD_DIRS", required = true)]
+        download_dirs: Vec<PathBuf>,
+
+        /// Number of uploads to run at once.
+        #[arg(
+            long = "transfers",
+            visible_alias = "jobs",
+            short = 'j',
+            value_name = "N",
+            default_value_t = 4
+        )]
+        transfers: usize,
+
+        /// Also write the summary as JSON to this file.
+        #[arg(long = "report-file", value_name = "PATH")]
+        report_file: Option<PathBuf>,
+    },
+
+    /// Download the archives a modlist needs from the server into a
+    /// download directory. Archives already in the directory, or that the
+    /// server doesn't have, are skipped. Interrupted downloads are kept as
+    /// `.part` files and resumed on the next run. Each downloaded archive
+    /// gets the `.meta` file Wabbajack expects next to it.
+    Download {
+        /// Base URL of the server to download from
+        #[arg(long = "server", value_name = "URL")]
+        server: String,
+
+        /// Path to the Wabbajack file
+        #[arg(value_name = "WABBAJACK_FILE")]
+        wabbajack_file: PathBuf,
+
+        /// Path to the download directory
+        #[arg(value_name = "DOWNLOAD_DIR")]
+        download_dir: PathBuf,
+
+        /// Number of downloads to run at once.
+        #[arg(long = "transfers", short = 'j', value_name = "N", default_value_t = 4)]
+        transfers: usize,
+
+        /// Also write the summary as JSON to this file.
+        #[arg(long = "report-file", value_name = "PATH")]
+        report_file: Option<PathBuf>,
+    },
+
+    /// Download a modlist's missing archives straight from their original
+    /// sources into a download directory. Direct HTTP, Google Drive,
+    /// MediaFire and MEGA links are always fetched; Nexus Mods archives need
+    /// the API key of a Premium account.
+    /// Each archive's size and xxhash64 are checked before it is kept, and
+    /// gets the `.meta` file Wabbajack expects next to it.
+    Fetch {
+        /// Path to the Wabbajack file
+        #[arg(value_name = "WABBAJACK_FILE")]
+        wabbajack_file: PathBuf,
+
+        /// Path to the download directory
+        #[arg(value_name = "DOWNLOAD_DIR")]
+        download_dir: PathBuf,
+
+        /// Nexus Mods API key, from the account's API access settings.
+        /// Without one, Nexus archives are skipped.
+        #[arg(
+            long = "nexus-api-key",
+            value_name = "KEY",
+            env = "NEXUS_API_KEY",
+            hide_env_values = true
+        )]
+        nexus_api_key: Option<String>,
+
+        /// Number of downloads to run at once.
+        #[arg(long = "transfers", short = 'j', value_name = "N", default_value_t = 2)]
+        transfers: usize,
+
+        /// Also write the summary as JSON to this file.
+        #[arg(long = "report-file", value_name = "PATH")]
+        report_file: Option<PathBuf>,
+    },
+
+    /// Write a copy of a modlist whose archives download from the server
+    /// instead of their original sources. Only archives the server has are
+    /// redirected; the rest keep their original source.
+    Mirror {
+        /// Base URL of the server to point the archives at
+        #[arg(long = "server", value_name = "URL")]
+        server: String,
+
+        /// Path to the Wabbajack file
+        #[arg(value_name = "WABBAJACK_FILE")]
+        wabbajack_file: PathBuf,
+
+        /// Where to write the patched Wabbajack file
+        #[arg(value_name = "OUTPUT")]
+        output: PathBuf,
+
+        /// Also write the summary as JSON to this file.
+        #[arg(long = "report-file", value_name = "PATH")]
+        report_file: Option<PathBuf>,
+    },
+
+    /// Write the `.meta` file Wabbajack uses to recognize a download next to
+    /// every archive of a modlist found in a download directory
+    Meta {
+        /// Path to the Wabbajack file
+        #[arg(value_name = "WABBAJACK_FILE")]
+        wabbajack_file: PathBuf,
+
+        /// Path to the download directory
+        #[arg(value_name = "DOWNLOAD_DIR")]
+        download_dir: PathBuf,
+
+        /// Replace `.meta` files that already exist.
+        #[arg(long = "overwrite")]
+        overwrite: bool,
+    },
+
+    /// Move archives that none of the given modlists need out of a download
+    /// directory, or delete them
+    Prune {
+        /// Path to the download directory
+        #[arg(value_name = "DOWNLOAD_DIR")]
+        download_dir: PathBuf,
+
+        /// The modlists whose archives are kept
+        #[arg(value_name = "WABBAJACK_FILES", required = true)]
+        wabbajack_files: Vec<PathBuf>,
+
+        /// Where to move pruned files. Defaults to `wabba-trash` inside the
+        /// download directory. A name already in the trash gets a ` (2)`,
+        /// ` (3)`, ... suffix instead of being replaced.
+        #[arg(long = "trash", value_name = "DIR", conflicts_with = "delete")]
+        trash: Option<PathBuf>,
+
+        /// Delete pruned files instead of moving them to the trash
+        #[arg(long = "delete")]
+        delete: bool,
+
+        /// Leave the `.meta` files of pruned archives where they are
+        #[arg(long = "keep-meta")]
+        keep_meta: bool,
+
+        /// Don't ask for confirmation
+        #[arg(long = "yes", short = 'y')]
+        yes: bool,
+    },
+
+    /// Find archives that are in more than one download directory (same
+    /// size and xxhash64) and replace the copies with links to one of them,
+    /// the one in the earliest directory given. The copies are listed with
+    /// the space they free before anything is changed.
+    Dedupe {
+        /// Paths to the download directories
+        #[arg(value_name = "DIRS", required = true)]
+        dirs: Vec<PathBuf>,
+
+        /// Which kind of link replaces a copy. Hard links need the copies
+        /// on the same filesystem; copies on another one are left alone.
+        #[arg(long = "link", value_enum, default_value_t = LinkKind::Hard)]
+        link: LinkKind,
+
+        /// Number of files to hash in parallel. Keep at 1 for spinning disks.
+        #[arg(long = "parallel", short = 'p', value_name = "N", default_value_t = 1)]
+        parallel: usize,
+
+        /// Skip the hash cache `sync` keeps in each directory and rehash
+        /// every file
+        #[arg(long = "no-cache")]
+        no_cache: bool,
+
+        /// Don't ask for confirmation
+        #[arg(long = "yes", short = 'y')]
+        yes: bool,
+    },
+
+    /// Save a consistent copy of a server's database, safe to take while
+    /// the server is running. Either downloads a snapshot from the server
+    /// or, on the machine it runs on, copies `db.db` with SQLite's backup
+    /// API. Copying the file directly can catch it mid-write.
+    BackupDb {
+        /// Base URL of the server to back up. The backup is an admin route,
+        /// so a server that requires logins also needs `--api-token`.
+        #[arg(
+            long = "server",
+            value_name = "URL",
+            required_unless_present = "db",
+            conflicts_with = "db"
+        )]
+        server: Option<String>,
+
+        /// The live database, `db.db` in the server's data directory
+        #[arg(long = "db", value_name = "PATH", conflicts_with = "server")]
+        db: Option<PathBuf>,
+
+        /// Where to save the backup. Only replaced once the new copy is
+        /// complete and opens as a database.
+        #[arg(value_name = "OUTPUT")]
+        output: PathBuf,
     },
+
+    /// Rehash every archive in a download directory and report the ones
+    /// that are corrupted or truncated. Files are checked against a
+    /// manifest from an earlier `verify --write-manifest`, or against the
+    /// archives of the same name a server has. Exits with an error if any
+    /// file is damaged, missing or couldn't be read.
+    Verify {
+        /// Path to the download directory
+        #[arg(value_name = "DOWNLOAD_DIR")]
+        download_dir: PathBuf,
+
+        /// The manifest to check against. Defaults to
+        /// `.wabba-manifest.json` inside the download directory.
+        #[arg(long = "manifest", value_name = "PATH", conflicts_with = "server")]
+        manifest: Option<PathBuf>,
+
+        /// Record the files as they are now in the manifest, creating it if
+        /// needed. Corrupted and truncated files keep their old entry.
+        #[arg(long = "write-manifest", conflicts_with = "server")]
+        write_manifest: bool,
+
+        /// Check against the sizes and hashes this wabba-server has
+        /// recorded, instead of a manifest
+        #[arg(long = "server", value_name = "URL")]
+        server: Option<String>,
+
+        /// Number of files to hash in parallel. Keep at 1 for spinning disks.
+        #[arg(long = "parallel", short = 'p', value_name = "N", default_value_t = 1)]
+        parallel: usize,
+
+        /// How to report the results
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Log)]
+        format: OutputFormat,
+    },
+
+    /// Browse modlists' archives full-screen: see which are present,
+    /// missing or corrupt in the download directories and which the server
+    /// has, recover, upload or download them one at a time or all at once,
+    /// and watch the transfers as they run
+    Tui {
+        /// Paths to the Wabbajack files
+        #[arg(value_name = "WABBAJACK_FILES", required = true)]
+        wabbajack_files: Vec<PathBuf>,
+
+        /// Path to a download directory. Can be given more than once; later
+        /// directories are treated as overflow or backup locations, and the
+        /// first one receives downloads and recovered archives.
+        #[arg(long = "download-dir", value_name = "DIR", required = true)]
+        download_dirs: Vec<PathBuf>,
+
+        /// Base URL of the server to upload to and download from
+        #[arg(long = "server", value_name = "URL")]
+        server: Option<String>,
+
+        /// Where to look for missing archives when recovering them (by
+        /// name, then by hash). Can be given more than once.
+        #[arg(long = "fix-from", value_name = "DIR")]
+        fix_from: Vec<PathBuf>,
+
+        /// Number of files to hash in parallel. Keep at 1 for spinning disks.
+        #[arg(long = "parallel", short = 'p', value_name = "N", default_value_t = 1)]
+        parallel: usize,
+
+        /// Number of uploads and downloads to run at once.
+        #[arg(long = "transfers", short = 'j', value_name = "N", default_value_t = 4)]
+        transfers: usize,
+    },
+
+    /// Print a modlist's details, download size and a breakdown of its
+    /// archives by download source
+    Inspect {
+        /// Path to the Wabbajack file
+        #[arg(value_name = "WABBAJACK_FILE")]
+        wabbajack_file: PathBuf,
+
+        /// Also list every archive
+        #[arg(long = "archives")]
+        archives: bool,
+
+        /// How to print the results
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Show which archives were added, removed or changed between two
+    /// versions of a modlist, and how much an update would download
+    Diff {
+        /// Path to the old Wabbajack file
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// Path to the new Wabbajack file
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+
+        /// How to print the results
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// List the modlists or mods a server knows about
+    List {
+        /// What to list
+        #[arg(value_enum, value_name = "WHAT")]
+        what: ListKind,
+
+        /// Base URL of the server to query
+        #[arg(long = "server", value_name = "URL")]
+        server: String,
+
+        /// Only list mods the server doesn't have
+        #[arg(long = "missing")]
+        missing: bool,
+
+        /// Only list the mods this modlist uses, given by ID or name
+        #[arg(long = "modlist", value_name = "MODLIST")]
+        modlist: Option<String>,
+
+        /// How to print the results
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Seed a fresh server with a few synthetic modlists and some of their
+    /// archives, for trying out the web UI
+    DemoData {
+        /// Base URL of the server to seed
+        #[arg(value_name = "SERVER")]
+        server: String,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListKind {
+    Modlists,
+    Mods,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    Hard,
+    Sym,
 }
@@ -0,0 +1,305 @@
+//! `dedupe`: finds the same archive downloaded into several download
+//! directories and replaces the copies with links to one of them.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+use futures_util::{StreamExt, stream};
+use wabba_protocol::hash::Hash;
+
+use crate::cli::LinkKind;
+use crate::download_dir::DownloadDirectory;
+use crate::sync_cache::{SyncCache, file_fingerprint};
+
+/// Files with the same size and xxhash64. `keep` is the one found first,
+/// in the order the directories were given; the `copies` are replaced.
+pub struct DuplicateSet {
+    pub keep: PathBuf,
+    pub copies: Vec<PathBuf>,
+    pub size: u64,
+}
+
+/// Identifies the file behind a path, so paths that are already hard links
+/// to each other aren't counted as copies.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Whether `a` and `b` can be hard linked, i.e. are on the same device.
+#[cfg(unix)]
+pub fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+/// Assumed where the platform doesn't say; linking then fails and is
+/// reported like any other error.
+#[cfg(not(unix))]
+pub fn same_filesystem(_a: &Path, _b: &Path) -> bool {
+    true
+}
+
+struct Candidate {
+    path: PathBuf,
+    dir: PathBuf,
+    size: u64,
+    mtime_nanos: i128,
+}
+
+/// Hashes the top-level files of `dirs` that share a size with another one
+/// and groups those with the same hash. Symlinks, `.part` files and paths
+/// already hard linked to a file seen earlier are left out. Hashes are
+/// taken from and added to the cache `sync` keeps in each directory unless
+/// `use_cache` is off; the caches are written back with `save_cache`.
+pub async fn find_duplicates(
+    dirs: &[PathBuf],
+    parallel: usize,
+    use_cache: bool,
+    save_cache: bool,
+) -> Vec<DuplicateSet> {
+    let mut seen_ids = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<Candidate>> = HashMap::new();
+    for dir in dirs {
+        if !dir.is_dir() {
+            log::warn!("{} is not a directory, skipping it", dir.display());
+            continue;
+        }
+        let Ok(download_directory) = DownloadDirectory::new(dir) else {
+            continue;
+        };
+        for path in download_directory.file_paths() {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if name.ends_with(".part") {
+                continue;
+            }
+            let (Ok(link_metadata), Ok(metadata)) =
+                (std::fs::symlink_metadata(&path), std::fs::metadata(&path))
+            else {
+                continue;
+            };
+            if link_metadata.is_symlink() {
+                continue;
+            }
+            if let Some(id) = file_id(&metadata)
+                && !seen_ids.insert(id)
+            {
+                log::debug!("{} is already linked", path.display());
+                continue;
+            }
+            let (size, mtime_nanos) = file_fingerprint(&metadata);
+            by_size.entry(size).or_default().push(Candidate {
+                path,
+                dir: dir.clone(),
+                size,
+                mtime_nanos,
+            });
+        }
+    }
+
+    let mut caches: HashMap<PathBuf, SyncCache> = HashMap::new();
+    if use_cache {
+        for dir in dirs {
+            caches
+                .entry(dir.clone())
+                .or_insert_with(|| SyncCache::load(dir));
+        }
+    }
+
+    // Only a file of the same size can be a copy, so most files are never
+    // read. Empty files aren't worth a link.
+    let mut to_hash = Vec::new();
+    let mut hashes: Vec<(Candidate, String)> = Vec::new();
+    for candidates in by_size.into_values().filter(|c| c.len() > 1) {
+        for candidate in candidates {
+            if candidate.size == 0 {
+                continue;
+            }
+            let filename = candidate
+                .path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            let cached = caches
+                .get(&candidate.dir)
+                .and_then(|cache| cache.lookup(filename, candidate.size, candidate.mtime_nanos));
+            match cached {
+                Some(hash) => hashes.push((candidate, hash)),
+                None => to_hash.push(candidate),
+            }
+        }
+    }
+    let total = to_hash.len();
+    log::info!(
+        "Hashing {} files with parallelism={} ({} hashes from the cache)",
+        total,
+        parallel.max(1),
+        hashes.len()
+    );
+    let mut hashing = stream::iter(to_hash)
+        .map(|candidate| {
+            tokio::task::spawn_blocking(move || {
+                let hash = Hash::compute_file(&candidate.path);
+                (candidate, hash)
+            })
+        })
+        .buffer_unordered(parallel.max(1));
+    let mut completed = 0usize;
+    while let Some(joined) = hashing.next().await {
+        let (candidate, hash) = joined.expect("blocking hash task panicked");
+        completed += 1;
+        match hash {
+            Ok(hash) => {
+                log::info!(
+                    "[{}/{}] Hashed {}",
+                    completed,
+                    total,
+                    candidate.path.display()
+                );
+                if let Some(cache) = caches.get_mut(&candidate.dir)
+                    && let Some(filename) = candidate.path.file_name().and_then(|n| n.to_str())
+                {
+                    cache.insert(
+                        filename.to_string(),
+                        candidate.size,
+                        candidate.mtime_nanos,
+                        hash.clone(),
+                    );
+                }
+                hashes.push((candidate, hash));
+            }
+            Err(e) => log::error!("Failed to hash {}: {}", candidate.path.display(), e),
+        }
+    }
+    if save_cache {
+        for (dir, cache) in &caches {
+            if let Err(e) = cache.save(dir) {
+                log::warn!("Failed to save hash cache in {}: {}", dir.display(), e);
+            }
+        }
+    }
+
+    // Keep the copy in the earliest directory given, then by name, so the
+    // same files are kept from run to run.
+    let dir_order = |dir: &Path| dirs.iter().position(|d| d == dir).unwrap_or(usize::MAX);
+    hashes
+        .sort_by(|(a, _), (b, _)| (dir_order(&a.dir), &a.path).cmp(&(dir_order(&b.dir), &b.path)));
+    let mut groups: HashMap<(u64, String), Vec<PathBuf>> = HashMap::new();
+    for (candidate, hash) in hashes {
+        groups
+            .entry((candidate.size, hash))
+            .or_default()
+            .push(candidate.path);
+    }
+    let mut sets: Vec<DuplicateSet> = groups
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((size, _), mut paths)| {
+            let keep = paths.remove(0);
+            DuplicateSet {
+                keep,
+                copies: paths,
+                size,
+            }
+        })
+        .collect();
+    sets.sort_by(|a, b| a.keep.cmp(&b.keep));
+    sets
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+/// Creates `link` pointing at `original`. Symlinks point at the absolute
+/// path, so they keep working if the linking directory moves.
+fn make_link(kind: LinkKind, original: &Path, link: &Path) -> std::io::Result<()> {
+    match kind {
+        LinkKind::Hard => std::fs::hard_link(original, link),
+        LinkKind::Sym => symlink(&std::fs::canonicalize(original)?, link),
+    }
+}
+
+/// Replaces `copy` with a link to `keep`. The link is made next to the copy
+/// and renamed over it, so the copy is never gone without the link in its
+/// place.
+pub fn replace_with_link(kind: LinkKind, keep: &Path, copy: &Path) -> std::io::Result<()> {
+    let mut staging = copy.as_os_str().to_os_string();
+    staging.push(".wabba-link");
+    let staging = PathBuf::from(staging);
+    make_link(kind, keep, &staging)?;
+    if let Err(e) = std::fs::rename(&staging, copy) {
+        let _ = std::fs::remove_file(&staging);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn groups_copies_and_keeps_the_first_directory() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::write(first.path().join("Mod.7z"), b"same bytes").unwrap();
+        std::fs::write(second.path().join("Mod (1).7z"), b"same bytes").unwrap();
+        std::fs::write(second.path().join("Other.7z"), b"diff bytes").unwrap();
+        std::fs::write(second.path().join("Mod.7z.part"), b"same bytes").unwrap();
+        std::fs::write(first.path().join("Empty.7z"), b"").unwrap();
+        std::fs::write(second.path().join("Empty.7z"), b"").unwrap();
+        let dirs = [first.path().to_path_buf(), second.path().to_path_buf()];
+
+        let sets = find_duplicates(&dirs, 2, false, false).await;
+
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].keep, first.path().join("Mod.7z"));
+        assert_eq!(sets[0].copies, vec![second.path().join("Mod (1).7z")]);
+        assert_eq!(sets[0].size, 10);
+    }
+
+    #[tokio::test]
+    async fn files_already_linked_are_not_copies() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::write(first.path().join("Mod.7z"), b"same bytes").unwrap();
+        std::fs::hard_link(first.path().join("Mod.7z"), second.path().join("Mod.7z")).unwrap();
+        let dirs = [first.path().to_path_buf(), second.path().to_path_buf()];
+
+        assert!(find_duplicates(&dirs, 1, false, false).await.is_empty());
+    }
+
+    #[test]
+    fn replacing_a_copy_leaves_a_link_in_its_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let keep = dir.path().join("Mod.7z");
+        let copy = dir.path().join("Mod (1).7z");
+        std::fs::write(&keep, b"same bytes").unwrap();
+        std::fs::write(&copy, b"same bytes").unwrap();
+
+        replace_with_link(LinkKind::Sym, &keep, &copy).unwrap();
+
+        assert!(std::fs::symlink_metadata(&copy).unwrap().is_symlink());
+        assert_eq!(std::fs::read(&copy).unwrap(), b"same bytes");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
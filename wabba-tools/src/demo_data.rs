@@ -0,0 +1,108 @@
+use wabba_protocol::archive_state::ArchiveState;
+use wabba_protocol::test_util::{ModlistBuilder, TestArchive};
+
+use crate::api_client::{ServerClient, UploadOutcome};
+
+/// Filler for a demo archive. Distinct per file name so every archive gets
+/// its own hash.
+fn demo_contents(filename: &str) -> Vec<u8> {
+    format!("wabba-tools demo archive: {}\n", filename)
+        .repeat(64)
+        .into_bytes()
+}
+
+fn http_archive(filename: &str) -> TestArchive {
+    TestArchive::new(filename, &demo_contents(filename))
+}
+
+fn nexus_archive(filename: &str, name: &str, mod_id: u64, file_id: u64) -> TestArchive {
+    http_archive(filename).with_state(ArchiveState::NexusDownloader {
+        author: Some("Demo Author".to_string()),
+        description: format!("{} (demo data)", name),
+        file_id,
+        game_name: "SkyrimSpecialEdition".to_string(),
+        image_url: None,
+        is_nsfw: false,
+        mod_id,
+        name: name.to_string(),
+        version: "1.0".to_string(),
+    })
+}
+
+fn manual_archive(filename: &str) -> TestArchive {
+    http_archive(filename).with_state(ArchiveState::ManualDownloader {
+        prompt: "Download the main file".to_string(),
+        url: format!("https://example.com/manual/{}", filename),
+    })
+}
+
+/// A few modlists sharing some archives, plus which archives to upload.
+/// Some archives are left out so the server shows partly available lists.
+fn demo_content() -> (Vec<(String, ModlistBuilder)>, Vec<TestArchive>) {
+    let shared = nexus_archive("SkyUI_5_2_SE-12604-5-2SE.7z", "SkyUI", 12604, 35407);
+    let unofficial = nexus_archive(
+        "Unofficial Skyrim Special Edition Patch-266-4-3-2.7z",
+        "Unofficial Skyrim Special Edition Patch",
+        266,
+        480394,
+    );
+    let textures = http_archive("Demo Landscape Textures 2K.zip");
+    let manual = manual_archive("Demo Manual Download.7z");
+    let lost = http_archive("Demo Lost Forever.rar");
+
+    let modlists = vec![
+        (
+            "Demo Essentials.wabbajack".to_string(),
+            ModlistBuilder::new("Demo Essentials")
+                .version("1.2.0")
+                .author("Demo Author")
+                .archives([shared.clone(), unofficial.clone()]),
+        ),
+        (
+            "Demo Visuals.wabbajack".to_string(),
+            ModlistBuilder::new("Demo Visuals")
+                .version("0.9.1")
+                .archives([shared.clone(), textures.clone(), manual.clone()]),
+        ),
+        (
+            "Demo Incomplete.wabbajack".to_string(),
+            ModlistBuilder::new("Demo Incomplete")
+                .version("3.0.0")
+                .game_type("Fallout4")
+                .archives([unofficial.clone(), lost]),
+        ),
+    ];
+    (modlists, vec![shared, unofficial, textures])
+}
+
+/// Uploads the demo modlists and some of their archives. Safe to run twice;
+/// anything the server already has is skipped.
+pub async fn seed(server: &ServerClient) -> Result<(), Box<dyn std::error::Error>> {
+    let (modlists, archives) = demo_content();
+
+    for archive in archives {
+        report(
+            &archive.filename,
+            server
+                .submit_bytes(&archive.filename, archive.contents.clone())
+                .await?,
+        );
+    }
+    for (filename, modlist) in modlists {
+        report(
+            &filename,
+            server.submit_bytes(&filename, modlist.build()).await?,
+        );
+    }
+    Ok(())
+}
+
+fn report(filename: &str, outcome: UploadOutcome) {
+    match outcome {
+        UploadOutcome::Uploaded(result) => log::info!("Uploaded {}", result.filename),
+        UploadOutcome::AlreadyPresent => log::info!("Server already has {}", filename),
+        UploadOutcome::Failed(code, body) => {
+            log::error!("Upload of {} failed: {} — {}", filename, code, body)
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! `diff`: what changed between two versions of a modlist.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use wabba_protocol::{
+    archive_state::ArchiveState, filename::normalize_filename, wabbajack::Archive,
+};
+
+use crate::report::{OutputFormat, print_table};
+
+#[derive(Serialize, Debug)]
+pub struct DiffEntry {
+    pub filename: String,
+    pub version: Option<String>,
+    pub size: u64,
+    pub hash: String,
+}
+
+impl DiffEntry {
+    fn new(archive: &Archive) -> Self {
+        DiffEntry {
+            filename: archive.filename.clone(),
+            version: archive.version(),
+            size: archive.size,
+            hash: archive.hash.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChangedArchive {
+    pub old: DiffEntry,
+    pub new: DiffEntry,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ModlistDiff {
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    /// The same source (Nexus mod, or filename otherwise) with new contents.
+    pub changed: Vec<ChangedArchive>,
+    pub unchanged: usize,
+    /// Bytes to fetch to update: every added archive plus the new side of
+    /// every changed one.
+    pub download_bytes: u64,
+}
+
+/// What identifies "the same archive" across versions when its hash
+/// changed. Nexus files keep their mod ID across updates while their
+/// filenames usually carry the version.
+fn identity(archive: &Archive) -> String {
+    match &archive.state {
+        ArchiveState::NexusDownloader {
+            game_name, mod_id, ..
+        } => format!("nexus:{}:{}", game_name.to_lowercase(), mod_id),
+        _ => normalize_filename(&archive.filename),
+    }
+}
+
+impl ModlistDiff {
+    pub fn compute(old: &[&Archive], new: &[&Archive]) -> Self {
+        let old_hashes: HashSet<&str> = old.iter().map(|a| a.hash.as_str()).collect();
+        let new_hashes: HashSet<&str> = new.iter().map(|a| a.hash.as_str()).collect();
+
+        let mut removed_by_identity: HashMap<String, Vec<&Archive>> = HashMap::new();
+        for archive in old.iter().filter(|a| !new_hashes.contains(a.hash.as_str())) {
+            removed_by_identity
+                .entry(identity(archive))
+                .or_default()
+                .push(archive);
+        }
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        for archive in new.iter().filter(|a| !old_hashes.contains(a.hash.as_str())) {
+            let previous = removed_by_identity
+                .get_mut(&identity(archive))
+                .and_then(|candidates| candidates.pop());
+            match previous {
+                Some(previous) => changed.push(ChangedArchive {
+                    old: DiffEntry::new(previous),
+                    new: DiffEntry::new(archive),
+                }),
+                None => added.push(DiffEntry::new(archive)),
+            }
+        }
+        let mut removed: Vec<DiffEntry> = removed_by_identity
+            .into_values()
+            .flatten()
+            .map(DiffEntry::new)
+            .collect();
+
+        added.sort_by(|a, b| a.filename.cmp(&b.filename));
+        removed.sort_by(|a, b| a.filename.cmp(&b.filename));
+        changed.sort_by(|a, b| a.new.filename.cmp(&b.new.filename));
+
+        let download_bytes = added.iter().map(|a| a.size).sum::<u64>()
+            + changed.iter().map(|c| c.new.size).sum::<u64>();
+        ModlistDiff {
+            unchanged: new
+                .iter()
+                .filter(|a| old_hashes.contains(a.hash.as_str()))
+                .count(),
+            added,
+            removed,
+            changed,
+            download_bytes,
+        }
+    }
+
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(self).expect("Failed to serialize diff")
+            ),
+            OutputFormat::Log | OutputFormat::Table => self.print_table(),
+        }
+    }
+
+    fn print_table(&self) {
+        let version = |entry: &DiffEntry| entry.version.clone().unwrap_or_default();
+        let mut rows = vec![["CHANGE", "FILENAME", "VERSION", "SIZE"].map(str::to_string)];
+        for entry in &self.added {
+            rows.push([
+                "added".to_string(),
+                entry.filename.clone(),
+                version(entry),
+                entry.size.to_string(),
+            ]);
+        }
+        for change in &self.changed {
+            rows.push([
+                "changed".to_string(),
+                format!("{} -> {}", change.old.filename, change.new.filename),
+                format!("{} -> {}", version(&change.old), version(&change.new)),
+                format!("{} -> {}", change.old.size, change.new.size),
+            ]);
+        }
+        for entry in &self.removed {
+            rows.push([
+                "removed".to_string(),
+                entry.filename.clone(),
+                version(entry),
+                entry.size.to_string(),
+            ]);
+        }
+        print_table(&rows);
+        println!();
+        println!(
+            "{} added, {} changed, {} removed, {} unchanged; {:.2} GB to download",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len(),
+            self.unchanged,
+            self.download_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wabba_protocol::test_util::TestArchive;
+
+    fn archive(test: TestArchive) -> Archive {
+        Archive {
+            hash: test.hash(),
+            meta: test.meta.clone(),
+            filename: test.filename.clone(),
+            size: test.size(),
+            state: test.state,
+        }
+    }
+
+    fn nexus(filename: &str, contents: &[u8], mod_id: u64) -> Archive {
+        archive(
+            TestArchive::new(filename, contents).with_state(ArchiveState::NexusDownloader {
+                author: None,
+                description: String::new(),
+                file_id: 1,
+                game_name: "SkyrimSpecialEdition".to_string(),
+                image_url: None,
+                is_nsfw: false,
+                mod_id,
+                name: filename.to_string(),
+                version: "1.0".to_string(),
+            }),
+        )
+    }
+
+    #[test]
+    fn matches_updated_nexus_files_by_mod_id() {
+        let kept = archive(TestArchive::new("Kept.7z", b"kept"));
+        let old_mod = nexus("Mod-1.0.7z", b"old version", 42);
+        let new_mod = nexus("Mod-1.1.7z", b"new version!", 42);
+        let dropped = archive(TestArchive::new("Dropped.7z", b"dropped"));
+        let added = archive(TestArchive::new("Added.7z", b"added"));
+
+        let diff = ModlistDiff::compute(&[&kept, &old_mod, &dropped], &[&kept, &new_mod, &added]);
+
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].old.filename, "Mod-1.0.7z");
+        assert_eq!(diff.changed[0].new.filename, "Mod-1.1.7z");
+        let names = |entries: &[DiffEntry]| -> Vec<String> {
+            entries.iter().map(|e| e.filename.clone()).collect()
+        };
+        assert_eq!(names(&diff.added), vec!["Added.7z"]);
+        assert_eq!(names(&diff.removed), vec!["Dropped.7z"]);
+        assert_eq!(diff.download_bytes, added.size + new_mod.size);
+    }
+
+    #[test]
+    fn other_archives_are_matched_by_normalized_filename() {
+        let old = archive(TestArchive::new("Cafe\u{301}.7z", b"old"));
+        let new = archive(TestArchive::new("Caf\u{e9}.7z", b"new"));
+
+        let diff = ModlistDiff::compute(&[&old], &[&new]);
+
+        assert_eq!(diff.changed.len(), 1);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+    }
+}
@@ -1,5 +1,15 @@
 use std::{fs, path::PathBuf};
 
+use crate::sync_cache::CACHE_FILENAME;
+use crate::verify::MANIFEST_FILENAME;
+
+/// Whether `name` is one of the files wabba-tools keeps in a download
+/// directory for itself (the hash cache, the verify manifest, or a
+/// half-written `.tmp` of either), rather than an archive.
+pub fn is_tool_state(name: &str) -> bool {
+    name.starts_with(CACHE_FILENAME) || name.starts_with(MANIFEST_FILENAME)
+}
+
 pub struct DownloadDirectory {
     path: PathBuf,
 }
@@ -19,7 +29,7 @@ impl DownloadDirectory {
                     .to_string_lossy()
                     .to_string()
             })
-            .filter(|x| !x.ends_with(".meta"))
+            .filter(|x| !x.ends_with(".meta") && !is_tool_state(x))
             .collect::<Vec<String>>()
     }
 
@@ -33,11 +43,82 @@ impl DownloadDirectory {
                     return None;
                 }
                 let name = entry.file_name().to_string_lossy().to_string();
-                if name.ends_with(".meta") {
+                if name.ends_with(".meta") || is_tool_state(&name) {
                     return None;
                 }
                 Some(path)
             })
             .collect()
     }
+
+    /// Every file under the directory, descending into subdirectories.
+    /// `.meta` files and tool state are skipped, as in
+    /// [`DownloadDirectory::file_paths`].
+    pub fn file_paths_recursive(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        let mut pending = vec![self.path.clone()];
+        while let Some(dir) = pending.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::warn!("Failed to read {}: {}", dir.display(), e);
+                    continue;
+                }
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.is_file() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if !name.ends_with(".meta") && !is_tool_state(&name) {
+                        paths.push(path);
+                    }
+                }
+            }
+        }
+        paths.sort();
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_meta_files_and_only_recurses_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Mod.7z"), b"mod").unwrap();
+        std::fs::write(dir.path().join("Mod.7z.meta"), b"[General]").unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("nested").join("Deep.7z"), b"deep").unwrap();
+        let directory = DownloadDirectory::new(&dir.path().to_path_buf()).unwrap();
+
+        assert_eq!(directory.file_paths(), vec![dir.path().join("Mod.7z")]);
+        assert_eq!(
+            directory.file_paths_recursive(),
+            vec![
+                dir.path().join("Mod.7z"),
+                dir.path().join("nested").join("Deep.7z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_the_hash_cache_and_verify_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Mod.7z"), b"mod").unwrap();
+        std::fs::write(dir.path().join(CACHE_FILENAME), b"{}").unwrap();
+        std::fs::write(dir.path().join(MANIFEST_FILENAME), b"{}").unwrap();
+        std::fs::write(dir.path().join(format!("{}.tmp", MANIFEST_FILENAME)), b"{").unwrap();
+        let directory = DownloadDirectory::new(&dir.path().to_path_buf()).unwrap();
+
+        assert_eq!(directory.files(), vec!["Mod.7z".to_string()]);
+        assert_eq!(directory.file_paths(), vec![dir.path().join("Mod.7z")]);
+        assert_eq!(
+            directory.file_paths_recursive(),
+            vec![dir.path().join("Mod.7z")]
+        );
+    }
 }
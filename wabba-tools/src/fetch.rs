@@ -0,0 +1,377 @@
+//! Downloads missing archives straight from their original sources for the
+//! `fetch` command. Direct HTTP, Google Drive, MediaFire and MEGA links need
+//! nothing but the modlist; Nexus Mods archives need a Premium API key.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+
+use aes::cipher::StreamCipher;
+use futures_util::{StreamExt, stream};
+use regex::Regex;
+use reqwest::{Client, RequestBuilder, Response, redirect};
+use wabba_protocol::archive_state::ArchiveState;
+use wabba_protocol::filename::{normalize_filename, safe_join};
+use wabba_protocol::hash::StreamingHash;
+use wabba_protocol::mega::{MEGA_API_URL, MegaCipher, MegaLink, download_url_from_response};
+use wabba_protocol::wabbajack::{Archive, WabbajackMetadata};
+
+use crate::download_dir::DownloadDirectory;
+use crate::nexus::NexusClient;
+use crate::report::{TransferReport, format_size};
+
+/// Google Drive's direct download host.
+const GOOGLE_DRIVE_DOWNLOAD_URL: &str = "https://drive.usercontent.google.com/download";
+
+/// Redirects followed per download, enough for mirrors that bounce through
+/// a CDN or two.
+const MAX_REDIRECTS: usize = 10;
+
+pub struct Fetcher {
+    client: Client,
+    nexus: Option<NexusClient>,
+}
+
+impl Fetcher {
+    pub fn new(nexus_api_key: Option<String>) -> Result<Self, reqwest::Error> {
+        let client = Client::builder()
+            .user_agent(concat!("wabba-tools/", env!("CARGO_PKG_VERSION")))
+            .redirect(redirect::Policy::limited(MAX_REDIRECTS))
+            .build()?;
+        let nexus = nexus_api_key.map(|key| NexusClient::new(client.clone(), key));
+        Ok(Fetcher { client, nexus })
+    }
+
+    /// Whether this fetcher can download from `state` at all, so archives
+    /// it can't are reported up front instead of failing one by one.
+    fn supports(&self, state: &ArchiveState) -> bool {
+        match state {
+            ArchiveState::HttpDownloader { .. }
+            | ArchiveState::GoogleDriveDownloader { .. }
+            | ArchiveState::MediaFireDownloader { .. }
+            | ArchiveState::MegaDownloader { .. } => true,
+            ArchiveState::NexusDownloader { .. } => self.nexus.is_some(),
+            _ => false,
+        }
+    }
+
+    /// Performs whatever handshake `state` needs and starts the download,
+    /// following redirects. MEGA serves encrypted data, so its downloads
+    /// come with the cipher to decrypt them.
+    async fn open(&self, state: &ArchiveState) -> Result<(Response, Option<MegaCipher>), String> {
+        let request = match state {
+            ArchiveState::HttpDownloader { url, headers } => {
+                // Wabbajack stores extra headers as "Name: value" strings.
+                let mut request = self.client.get(url);
+                for header in headers.as_array().into_iter().flatten() {
+                    if let Some((name, value)) = header.as_str().and_then(|h| h.split_once(':')) {
+                        request = request.header(name.trim(), value.trim());
+                    }
+                }
+                request
+            }
+            ArchiveState::GoogleDriveDownloader { id } => {
+                return self
+                    .open_google_drive(id)
+                    .await
+                    .map(|response| (response, None));
+            }
+            ArchiveState::MediaFireDownloader { url } => {
+                return self
+                    .open_mediafire(url)
+                    .await
+                    .map(|response| (response, None));
+            }
+            ArchiveState::MegaDownloader { url } => {
+                let link = MegaLink::parse(url)?;
+                let response = send(
+                    self.client
+                        .post(MEGA_API_URL)
+                        .query(&[("id", "0")])
+                        .json(&link.download_request()),
+                )
+                .await?;
+                let body: serde_json::Value = response
+                    .json()
+                    .await
+                    .map_err(|e| format!("Unexpected MEGA API response: {}", e))?;
+                let download_url = download_url_from_response(&body)?;
+                let response = send(self.client.get(download_url)).await?;
+                return Ok((response, Some(link.cipher())));
+            }
+            ArchiveState::NexusDownloader {
+                game_name,
+                mod_id,
+                file_id,
+                ..
+            } => {
+                let nexus = self
+                    .nexus
+                    .as_ref()
+                    .ok_or_else(|| "No Nexus API key given".to_string())?;
+                let uri = nexus.download_link(game_name, *mod_id, *file_id).await?;
+                self.client.get(uri)
+            }
+            other => {
+                return Err(format!(
+                    "Downloading from {} sources is not supported",
+                    other.source_type()
+                ));
+            }
+        };
+        send(request).await.map(|response| (response, None))
+    }
+
+    async fn open_google_drive(&self, id: &str) -> Result<Response, String> {
+        let request = self
+            .client
+            .get(GOOGLE_DRIVE_DOWNLOAD_URL)
+            .query(&[("id", id), ("export", "download")]);
+        let response = send(request).await?;
+        if !is_html(&response) {
+            return Ok(response);
+        }
+
+        // Files too large to virus-scan get an interstitial whose form
+        // carries the tokens needed to skip the scan.
+        let page = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read Google Drive page: {}", e))?;
+        let confirm = extract(&page, r#"name="confirm"\s+value="([^"]+)""#).ok_or_else(|| {
+            "Google Drive returned a page without a download link; the file is gone or over its download quota".to_string()
+        })?;
+        let mut query = vec![
+            ("id", id.to_string()),
+            ("export", "download".to_string()),
+            ("confirm", confirm),
+        ];
+        if let Some(uuid) = extract(&page, r#"name="uuid"\s+value="([^"]+)""#) {
+            query.push(("uuid", uuid));
+        }
+        let response = send(self.client.get(GOOGLE_DRIVE_DOWNLOAD_URL).query(&query)).await?;
+        if is_html(&response) {
+            return Err("Google Drive did not accept the confirmation token".to_string());
+        }
+        Ok(response)
+    }
+
+    async fn open_mediafire(&self, url: &str) -> Result<Response, String> {
+        let response = send(self.client.get(url)).await?;
+        if !is_html(&response) {
+            return Ok(response);
+        }
+
+        // The file page links the real download from its download button.
+        let page = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read MediaFire page: {}", e))?;
+        let direct_url = extract(
+            &page,
+            r#"href="(https?://download[0-9]*\.mediafire\.com/[^"]+)""#,
+        )
+        .ok_or_else(|| {
+            "MediaFire page has no download link; the file has probably been removed".to_string()
+        })?;
+        let response = send(self.client.get(direct_url)).await?;
+        if is_html(&response) {
+            return Err("MediaFire served a page instead of the file".to_string());
+        }
+        Ok(response)
+    }
+
+    /// Downloads `archive` to `dest` through a `.part` file, which is only
+    /// renamed into place once its size and xxhash64 match the modlist.
+    async fn download(&self, archive: &Archive, dest: &Path) -> Result<(), String> {
+        let (mut response, mut cipher) = self.open(&archive.state).await?;
+        if let Some(length) = response.content_length()
+            && length != archive.size
+        {
+            return Err(format!(
+                "Source offered {} bytes, expected {}",
+                length, archive.size
+            ));
+        }
+
+        let mut part_name = dest.file_name().ok_or("Invalid filename")?.to_os_string();
+        part_name.push(".part");
+        let part = dest.with_file_name(part_name);
+        let discard = |message: String| {
+            let _ = std::fs::remove_file(&part);
+            message
+        };
+
+        let mut file =
+            std::fs::File::create(&part).map_err(|e| format!("Failed to create .part: {}", e))?;
+        let mut hasher = StreamingHash::new();
+        let mut received = 0u64;
+        loop {
+            let mut chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk.to_vec(),
+                Ok(None) => break,
+                Err(e) => return Err(discard(format!("Download interrupted: {}", e))),
+            };
+            if let Some(cipher) = cipher.as_mut() {
+                cipher.apply_keystream(&mut chunk);
+            }
+            received += chunk.len() as u64;
+            if received > archive.size {
+                return Err(discard(format!(
+                    "Received more than the expected {} bytes",
+                    archive.size
+                )));
+            }
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .map_err(|e| discard(format!("Failed to write .part: {}", e)))?;
+        }
+        file.flush()
+            .map_err(|e| discard(format!("Failed to write .part: {}", e)))?;
+
+        if received != archive.size {
+            return Err(discard(format!(
+                "Received {} of {} bytes",
+                received, archive.size
+            )));
+        }
+        let received_hash = hasher.finish();
+        if received_hash != archive.hash {
+            return Err(discard(format!(
+                "Hash mismatch: expected {}, got {}",
+                archive.hash, received_hash
+            )));
+        }
+        std::fs::rename(&part, dest).map_err(|e| format!("Failed to move into place: {}", e))
+    }
+}
+
+/// First capture group of `pattern` in `html`.
+fn extract(html: &str, pattern: &str) -> Option<String> {
+    Regex::new(pattern)
+        .ok()?
+        .captures(html)?
+        .get(1)
+        .map(|m| m.as_str().replace("&amp;", "&"))
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"))
+}
+
+/// Sends `request`, treating non-success statuses as errors.
+async fn send(request: RequestBuilder) -> Result<Response, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Source responded with {}", response.status()));
+    }
+    Ok(response)
+}
+
+/// `fetch`: downloads every archive of `metadata` that `download_dir`
+/// lacks and `fetcher` can get, `transfers` at a time, writing the `.meta`
+/// Wabbajack expects next to each. The rest are reported as failed.
+pub async fn fetch_missing(
+    fetcher: &Fetcher,
+    metadata: &WabbajackMetadata,
+    download_dir: &Path,
+    transfers: usize,
+    dry_run: bool,
+) -> TransferReport {
+    let mut report = TransferReport::new("fetch");
+    let present: HashSet<String> = if download_dir.is_dir() {
+        DownloadDirectory::new(&download_dir.to_path_buf())
+            .expect("Failed to open directory")
+            .files()
+            .iter()
+            .map(|name| normalize_filename(name))
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let mut pending = Vec::new();
+    for archive in metadata.required_archives() {
+        if present.contains(&normalize_filename(&archive.filename)) {
+            report.skipped(&archive.filename);
+        } else if fetcher.supports(&archive.state) {
+            match safe_join(download_dir, &archive.filename) {
+                Ok(path) => pending.push((archive, path)),
+                Err(e) => {
+                    log::warn!("Not fetching {}: {}", archive.filename, e);
+                    report.failed(&archive.filename, e);
+                }
+            }
+        } else {
+            log::warn!(
+                "Can't fetch {} from {} sources",
+                archive.filename,
+                archive.state.source_type()
+            );
+            report.failed(
+                &archive.filename,
+                format!("{} source not supported", archive.state.source_type()),
+            );
+        }
+    }
+    log::info!(
+        "{} archives to fetch ({}); {} can't be fetched",
+        pending.len(),
+        format_size(pending.iter().map(|(archive, _)| archive.size).sum()),
+        report.failed.len()
+    );
+
+    if dry_run {
+        for (archive, path) in &pending {
+            println!(
+                "Would fetch {} ({})",
+                path.display(),
+                format_size(archive.size)
+            );
+        }
+        return report;
+    }
+    if pending.is_empty() {
+        return report;
+    }
+    if let Err(e) = std::fs::create_dir_all(download_dir) {
+        log::error!("Failed to create {}: {}", download_dir.display(), e);
+        return report;
+    }
+
+    let total = pending.len();
+    let mut downloads = stream::iter(pending.into_iter().enumerate())
+        .map(|(idx, (archive, path))| async move {
+            log::info!("[{}/{}] Fetching {}", idx + 1, total, archive.filename);
+            let result = fetcher.download(archive, &path).await;
+            (archive, path, result)
+        })
+        .buffer_unordered(transfers.max(1));
+    while let Some((archive, path, result)) = downloads.next().await {
+        match result {
+            Ok(()) => {
+                // Lets Wabbajack recognize the download as its own.
+                if let Some(ini) = archive.meta_ini() {
+                    let mut meta_path = path.into_os_string();
+                    meta_path.push(".meta");
+                    if let Err(e) = std::fs::write(&meta_path, ini) {
+                        log::warn!("Failed to write .meta for {}: {}", archive.filename, e);
+                    }
+                }
+                report.transferred(&archive.filename, archive.size);
+            }
+            Err(e) => {
+                log::error!("Fetch of {} failed: {}", archive.filename, e);
+                report.failed(&archive.filename, e);
+            }
+        }
+    }
+    report
+}
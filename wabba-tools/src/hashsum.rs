@@ -0,0 +1,132 @@
+//! `hash`: the xxhash64 (base64) that modlists and the server use, of any
+//! number of files or of stdin, in a form other tools can read.
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use wabba_protocol::hash::{Hash, StreamingHash};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashFormat {
+    /// Log each hash (the default)
+    Log,
+    /// Print one JSON object per line to stdout
+    Json,
+    /// Print `hash size filename` per line to stdout, like BSD `cksum`
+    Bsd,
+}
+
+#[derive(Serialize, Debug)]
+struct HashedInput {
+    hash: String,
+    size: u64,
+    filename: String,
+}
+
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+/// `-` is stdin. Arguments with glob characters that don't name a file are
+/// expanded here, for shells that leave them alone (cmd.exe, PowerShell).
+fn expand(args: &[String]) -> Vec<Result<Input, String>> {
+    let mut inputs = Vec::new();
+    for arg in args {
+        if arg == "-" {
+            inputs.push(Ok(Input::Stdin));
+            continue;
+        }
+        let path = PathBuf::from(arg);
+        if path.exists() || !arg.contains(['*', '?', '[']) {
+            inputs.push(Ok(Input::File(path)));
+            continue;
+        }
+        match glob::glob(arg) {
+            Ok(paths) => {
+                let before = inputs.len();
+                for entry in paths {
+                    match entry {
+                        Ok(path) if path.is_file() => inputs.push(Ok(Input::File(path))),
+                        Ok(_) => {}
+                        Err(e) => inputs.push(Err(e.to_string())),
+                    }
+                }
+                if inputs.len() == before {
+                    inputs.push(Err(format!("{}: no matching files", arg)));
+                }
+            }
+            Err(e) => inputs.push(Err(format!("{}: {}", arg, e))),
+        }
+    }
+    inputs
+}
+
+fn hash_stdin() -> std::io::Result<(String, u64)> {
+    let mut hasher = StreamingHash::new();
+    let mut stdin = std::io::stdin().lock();
+    let mut buf = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = stdin.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((hasher.finish(), size))
+}
+
+fn hash_input(input: &Input) -> Result<HashedInput, String> {
+    match input {
+        Input::Stdin => {
+            let (hash, size) = hash_stdin().map_err(|e| format!("-: {}", e))?;
+            Ok(HashedInput {
+                hash,
+                size,
+                filename: "-".to_string(),
+            })
+        }
+        Input::File(path) => {
+            let fail = |e: std::io::Error| format!("{}: {}", path.display(), e);
+            let size = std::fs::metadata(path).map_err(fail)?.len();
+            let hash = Hash::compute_file(path).map_err(fail)?;
+            Ok(HashedInput {
+                hash,
+                size,
+                filename: path.display().to_string(),
+            })
+        }
+    }
+}
+
+/// Hashes every input in `args` and prints them in `format` as they are
+/// done. Inputs that can't be read are logged and skipped; returns whether
+/// all of them could be.
+pub fn hash_files(args: &[String], format: HashFormat) -> bool {
+    let inputs = expand(args);
+    let single = inputs.len() == 1;
+    let mut all_ok = true;
+    for input in inputs {
+        let hashed = match input.and_then(|input| hash_input(&input)) {
+            Ok(hashed) => hashed,
+            Err(e) => {
+                log::error!("Failed to hash {}", e);
+                all_ok = false;
+                continue;
+            }
+        };
+        match format {
+            HashFormat::Log if single => log::info!("Hash: {}", hashed.hash),
+            HashFormat::Log => log::info!("{}: {}", hashed.filename, hashed.hash),
+            HashFormat::Json => println!(
+                "{}",
+                serde_json::to_string(&hashed).expect("hash serializes")
+            ),
+            HashFormat::Bsd => println!("{} {} {}", hashed.hash, hashed.size, hashed.filename),
+        }
+    }
+    all_ok
+}
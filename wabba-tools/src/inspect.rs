@@ -0,0 +1,142 @@
+//! `inspect`: what a modlist is and what it takes to download.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use wabba_protocol::wabbajack::WabbajackMetadata;
+
+use crate::report::{ArchiveEntry, OutputFormat, format_size, print_table};
+
+#[derive(Serialize, Debug)]
+pub struct SourceBreakdown {
+    pub source_type: String,
+    pub archives: usize,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ModlistInspection {
+    pub name: String,
+    pub author: String,
+    pub version: String,
+    pub game_type: String,
+    pub wabbajack_version: String,
+    pub is_nsfw: bool,
+    pub archives: usize,
+    /// Bytes to fetch for every archive that has to be downloaded (game
+    /// files are already on the user's machine).
+    pub download_bytes: u64,
+    pub installed_files: u64,
+    pub installed_bytes: u64,
+    /// One row per downloader type, largest first.
+    pub sources: Vec<SourceBreakdown>,
+    /// Every archive, when asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_list: Option<Vec<ArchiveEntry>>,
+}
+
+impl ModlistInspection {
+    pub fn new(metadata: &WabbajackMetadata, with_archives: bool) -> Self {
+        let mut sources: BTreeMap<&'static str, SourceBreakdown> = BTreeMap::new();
+        for archive in &metadata.archives {
+            let source_type = archive.state.source_type();
+            let entry = sources.entry(source_type).or_insert(SourceBreakdown {
+                source_type: source_type.to_string(),
+                archives: 0,
+                bytes: 0,
+            });
+            entry.archives += 1;
+            entry.bytes += archive.size;
+        }
+        let mut sources: Vec<SourceBreakdown> = sources.into_values().collect();
+        sources.sort_by_key(|source| std::cmp::Reverse(source.bytes));
+
+        let (installed_files, installed_bytes) = metadata.installed_files();
+        ModlistInspection {
+            name: metadata.name.clone(),
+            author: metadata.author.clone(),
+            version: metadata.version.clone(),
+            game_type: metadata.game_type.clone(),
+            wabbajack_version: metadata.wabbajack_version.clone(),
+            is_nsfw: metadata.is_nsfw,
+            archives: metadata.archives.len(),
+            download_bytes: metadata.required_archives().iter().map(|a| a.size).sum(),
+            installed_files,
+            installed_bytes,
+            sources,
+            archive_list: with_archives.then(|| {
+                metadata
+                    .archives
+                    .iter()
+                    .map(|archive| ArchiveEntry {
+                        filename: archive.filename.clone(),
+                        size: archive.size,
+                        hash: archive.hash.clone(),
+                        source_type: archive.state.source_type().to_string(),
+                        source_url: archive.state.source_url(),
+                        prompt: archive.state.manual_prompt().map(|(prompt, _)| prompt),
+                        directory: None,
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(self).expect("Failed to serialize inspection")
+            ),
+            OutputFormat::Log | OutputFormat::Table => self.print_table(),
+        }
+    }
+
+    fn print_table(&self) {
+        print_table(&[
+            ["Name".to_string(), self.name.clone()],
+            ["Author".to_string(), self.author.clone()],
+            ["Version".to_string(), self.version.clone()],
+            ["Game".to_string(), self.game_type.clone()],
+            ["Wabbajack".to_string(), self.wabbajack_version.clone()],
+            ["NSFW".to_string(), self.is_nsfw.to_string()],
+            ["Archives".to_string(), self.archives.to_string()],
+            [
+                "Download size".to_string(),
+                format_size(self.download_bytes),
+            ],
+            [
+                "Installed".to_string(),
+                format!(
+                    "{} files, {}",
+                    self.installed_files,
+                    format_size(self.installed_bytes)
+                ),
+            ],
+        ]);
+
+        println!();
+        let mut rows = vec![["SOURCE", "ARCHIVES", "SIZE"].map(str::to_string)];
+        rows.extend(self.sources.iter().map(|source| {
+            [
+                source.source_type.clone(),
+                source.archives.to_string(),
+                format_size(source.bytes),
+            ]
+        }));
+        print_table(&rows);
+
+        if let Some(archives) = &self.archive_list {
+            println!();
+            let mut rows = vec![["FILENAME", "SOURCE", "SIZE", "HASH"].map(str::to_string)];
+            rows.extend(archives.iter().map(|archive| {
+                [
+                    archive.filename.clone(),
+                    archive.source_type.clone(),
+                    archive.size.to_string(),
+                    archive.hash.clone(),
+                ]
+            }));
+            print_table(&rows);
+        }
+    }
+}
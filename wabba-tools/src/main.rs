@@ -1,19 +1,69 @@
+use crate::api_client::{ClientOptions, ServerClient, UploadOutcome};
+use crate::backup_db::backup_database;
+use crate::cli::{LinkKind, ListKind};
+use crate::dedupe::{find_duplicates, replace_with_link, same_filesystem};
+use crate::diff::ModlistDiff;
 use crate::download_dir::DownloadDirectory;
-use crate::sync_cache::{CACHE_FILENAME, SyncCache, file_fingerprint};
-use clap::Parser;
+use crate::fetch::{Fetcher, fetch_missing};
+use crate::hashsum::hash_files;
+use crate::inspect::ModlistInspection;
+use crate::notify::{desktop_notification, notify_failure, notify_on_panic};
+use crate::prune::{confirm, prunable, prune};
+use crate::publish::publish;
+use crate::recovery::recover_missing;
+use crate::report::{
+    ArchiveEntry, ExtraneousFile, OutputFormat, TransferReport, ValidationReport, format_size,
+    print_table,
+};
+use crate::sync_cache::{SyncCache, file_fingerprint};
+use crate::tui::{LogBuffer, TuiOptions};
+use crate::upload_dir::{CHECK_BATCH_SIZE, upload_candidates, upload_directory};
+use crate::verify::{MANIFEST_FILENAME, Manifest, Reference, verify_directory};
+use crate::watch::watch_download_dirs;
+use clap::{CommandFactory, FromArgMatches};
+use futures_util::stream::FuturesUnordered;
+use futures_util::{StreamExt, stream};
+mod api_client;
+mod backup_db;
 mod cli;
+mod dedupe;
+mod demo_data;
+mod diff;
 mod download_dir;
+mod fetch;
+mod hashsum;
+mod inspect;
+mod nexus;
+mod notify;
+mod prune;
+mod publish;
+mod rate_limit;
+mod recovery;
+mod report;
 mod sync_cache;
+mod tui;
+mod upload_dir;
+mod verify;
+mod watch;
 use env_logger::Builder;
-use reqwest::Client;
-use reqwest::header::IF_NONE_MATCH;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::fs::File;
+use std::time::Duration;
 use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
-use tokio_util::codec::{BytesCodec, FramedRead};
-use wabba_protocol::{hash::Hash, wabbajack::WabbajackMetadata};
+use tokio::time::{Instant, MissedTickBehavior, interval_at};
+use wabba_protocol::{
+    api::{ArchiveKind, AvailabilityReport},
+    archive_state::ArchiveState,
+    filename::{normalize_filename, safe_join},
+    hash::Hash,
+    wabbajack::{Archive, WabbajackMetadata, patch_archive_states},
+};
+
+/// A file hashed by sync: its hash and size, or why it couldn't be hashed.
+type HashedFile = (PathBuf, Result<(String, u64), String>);
 
 #[derive(Debug)]
 struct FileComparisonResult {
@@ -22,115 +72,6 @@ struct FileComparisonResult {
     extraneous_files: Vec<String>,
 }
 
-#[derive(Clone, Copy)]
-enum UploadType {
-    Modlist,
-    Mod,
-}
-
-impl UploadType {
-    fn from_extension(extension: &str) -> Self {
-        match extension {
-            "wabbajack" => Self::Modlist,
-            _ => Self::Mod,
-        }
-    }
-
-    fn as_str(&self) -> &str {
-        match self {
-            Self::Modlist => "modlist",
-            Self::Mod => "mod",
-        }
-    }
-}
-
-fn upload_type_for(path: &Path) -> UploadType {
-    UploadType::from_extension(
-        path.extension()
-            .unwrap_or_default()
-            .to_str()
-            .unwrap_or_default(),
-    )
-}
-
-enum UploadOutcome {
-    Uploaded,
-    AlreadyPresent,
-    Failed(u16, String),
-}
-
-/// Probe the server once and return the post-redirect base URL. Reqwest
-/// follows GET redirects transparently but cannot replay a streamed POST
-/// body, so we resolve any redirect chain (e.g. Traefik's HTTP→HTTPS 308)
-/// up front and use the resolved base URL for the rest of the run.
-async fn resolve_base_url(client: &Client, server: &str) -> Result<String, reqwest::Error> {
-    let server = server.trim_end_matches('/');
-    let probe_url = format!("{}/hello", server);
-    let response = client.get(&probe_url).send().await?;
-    let final_url = response.url().as_str();
-    let resolved = final_url
-        .trim_end_matches("/hello")
-        .trim_end_matches('/')
-        .to_string();
-    if resolved != server {
-        log::info!("Resolved server URL {} -> {}", server, resolved);
-    }
-    Ok(resolved)
-}
-
-/// Ask the server whether it already has a file with the given hash. Returns
-/// true when the server reports the hash is already available (304), false
-/// when the server needs the upload (200).
-async fn server_has_hash(
-    client: &Client,
-    server: &str,
-    upload_type: UploadType,
-    hash: &str,
-) -> Result<bool, reqwest::Error> {
-    let url = format!("{}/check/{}", server, upload_type.as_str());
-    let response = client.get(&url).header(IF_NONE_MATCH, hash).send().await?;
-    Ok(response.status().as_u16() == 304)
-}
-
-/// Stream a single file up to the server. The caller is responsible for
-/// deciding whether the upload is needed; this function will submit the body
-/// regardless.
-async fn upload_file(
-    client: &Client,
-    server: &str,
-    file: &Path,
-    hash: &str,
-) -> Result<UploadOutcome, Box<dyn std::error::Error>> {
-    let upload_type = upload_type_for(file);
-    let filename = file
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or("Invalid filename")?;
-    let url = format!("{}/submit/{}/{}", server, upload_type.as_str(), filename);
-
-    let async_file = File::open(file).await?;
-    let stream = FramedRead::new(async_file, BytesCodec::new());
-    let body = reqwest::Body::wrap_stream(stream);
-
-    log::info!("POST {}", url);
-    let response = client
-        .post(&url)
-        .header(IF_NONE_MATCH, hash)
-        .body(body)
-        .send()
-        .await?;
-
-    let code = response.status().as_u16();
-    match code {
-        200 => Ok(UploadOutcome::Uploaded),
-        304 => Ok(UploadOutcome::AlreadyPresent),
-        _ => {
-            let body = response.text().await.unwrap_or_default();
-            Ok(UploadOutcome::Failed(code, body))
-        }
-    }
-}
-
 // Compare two lists of files and return:
 // - A list of files that are missing
 // - A list of files that are satisfied
@@ -145,14 +86,25 @@ fn compare_file_lists(
         extraneous_files: Vec::new(),
     };
 
+    // Compare NFC-normalized names so the same file downloaded on macOS and
+    // Windows isn't reported as both missing and extraneous.
+    let required: HashSet<String> = required_files
+        .iter()
+        .map(|f| normalize_filename(f))
+        .collect();
+    let present: HashSet<String> = files_in_download_dir
+        .iter()
+        .map(|f| normalize_filename(f))
+        .collect();
+
     for file in files_in_download_dir {
-        if !required_files.contains(file) {
+        if !required.contains(&normalize_filename(file)) {
             result.extraneous_files.push(file.clone());
         }
     }
 
     for file in required_files {
-        if files_in_download_dir.contains(file) {
+        if present.contains(&normalize_filename(file)) {
             result.satisfied_files.push(file.clone());
         } else {
             result.missing_files.push(file.clone());
@@ -162,26 +114,317 @@ fn compare_file_lists(
     result
 }
 
+/// What `sync` did with one file.
+enum SyncStep {
+    /// The server already had it.
+    Present,
+    /// `--dry-run`: the server lacks it, so it would be uploaded.
+    WouldUpload,
+    Submitted(UploadOutcome),
+}
+
+/// Asks the server whether it has `hash` at `size` bytes and uploads `file`
+/// if not, adding what is sent to `sent`. `checked` skips asking, for files
+/// the bulk check up front already found missing.
+async fn sync_file(
+    server: &ServerClient,
+    file: PathBuf,
+    filename: String,
+    (hash, size): (String, u64),
+    checked: bool,
+    sent: Arc<AtomicU64>,
+    dry_run: bool,
+) -> (String, Result<SyncStep, String>) {
+    let kind = ArchiveKind::for_filename(&filename);
+    let check = if checked {
+        Ok(AvailabilityReport::default())
+    } else {
+        server.check_files(kind, &[(hash.clone(), size)]).await
+    };
+    let result = match check {
+        Ok(report) if report.available.contains(&hash) => Ok(SyncStep::Present),
+        Ok(_) if dry_run => {
+            println!("Would upload {}", file.display());
+            Ok(SyncStep::WouldUpload)
+        }
+        Ok(_) => {
+            log::info!("Uploading {}", filename);
+            server
+                .submit_tracked(&file, &hash, Some(sent))
+                .await
+                .map(SyncStep::Submitted)
+                .map_err(|e| e.to_string())
+        }
+        Err(e) => Err(format!("hash check failed: {}", e)),
+    };
+    (filename, result)
+}
+
+/// `validate --modlist-id` / `--modlist-hash`: the modlist as the server
+/// recorded it when it was uploaded, for when the `.wabbajack` file isn't
+/// kept locally. Only the archives are filled in. Archives marked optional
+/// on the server are left out, as in its availability counts.
+async fn modlist_from_server(
+    server: &str,
+    client: &ClientOptions,
+    id: Option<u64>,
+    hash: Option<&str>,
+) -> Result<WabbajackMetadata, String> {
+    let server = ServerClient::connect(server, client)
+        .await
+        .map_err(|e| format!("Failed to reach server: {}", e))?;
+    let modlists = server
+        .list_modlists()
+        .await
+        .map_err(|e| format!("Failed to list modlists: {}", e))?;
+    let summary = modlists
+        .into_iter()
+        .find(|modlist| Some(modlist.id) == id || Some(modlist.hash.as_str()) == hash)
+        .ok_or_else(|| format!("{} has no such modlist", server.base_url()))?;
+    let archives = server
+        .modlist_archives(summary.id)
+        .await
+        .map_err(|e| format!("Failed to fetch the archives of {}: {}", summary.name, e))?;
+    log::info!(
+        "Validating {} {} ({}) as recorded on {}",
+        summary.name,
+        summary.version,
+        summary.filename,
+        server.base_url()
+    );
+    Ok(WabbajackMetadata {
+        archives: archives
+            .into_iter()
+            .filter(|archive| !archive.optional)
+            .map(|archive| Archive {
+                hash: archive.hash,
+                meta: archive.meta.unwrap_or_default(),
+                filename: archive.filename,
+                size: archive.size,
+                state: archive.state,
+            })
+            .collect(),
+        author: String::new(),
+        description: String::new(),
+        directives: Vec::new(),
+        version: summary.version,
+        game_type: String::new(),
+        image: String::new(),
+        name: summary.name,
+        readme: String::new(),
+        wabbajack_version: String::new(),
+        website: String::new(),
+        is_nsfw: false,
+    })
+}
+
+/// `validate --against-server`: sorts the required archives by whether the
+/// server has them, so it's clear whether it can serve the whole modlist.
+async fn validate_against_server(
+    server: &str,
+    client: &ClientOptions,
+    metadata: &WabbajackMetadata,
+    format: OutputFormat,
+    report_path: Option<&Path>,
+    notify: bool,
+) {
+    let server = match ServerClient::connect(server, client).await {
+        Ok(s) => s,
+        Err(e) => {
+            notify_failure(
+                notify,
+                "validate",
+                &format!("Failed to reach server: {}", e),
+            );
+            return;
+        }
+    };
+    let required = metadata.required_archives();
+    let available: HashSet<String> = match server
+        .check(
+            ArchiveKind::Mod,
+            required
+                .iter()
+                .map(|archive| archive.hash.clone())
+                .collect(),
+        )
+        .await
+    {
+        Ok(report) => report.available.into_iter().collect(),
+        Err(e) => {
+            notify_failure(notify, "validate", &format!("Hash check failed: {}", e));
+            return;
+        }
+    };
+
+    let mut report = ValidationReport {
+        missing: Vec::new(),
+        satisfied: Vec::new(),
+        extraneous: Vec::new(),
+        mismatched: Vec::new(),
+    };
+    for archive in required {
+        let on_server = available.contains(&archive.hash);
+        let entry = ArchiveEntry {
+            filename: archive.filename.clone(),
+            size: archive.size,
+            hash: archive.hash.clone(),
+            source_type: archive.state.source_type().to_string(),
+            source_url: archive.state.source_url(),
+            prompt: archive.state.manual_prompt().map(|(prompt, _)| prompt),
+            directory: on_server.then(|| server.base_url().to_string()),
+        };
+        if on_server {
+            report.satisfied.push(entry);
+        } else {
+            report.missing.push(entry);
+        }
+    }
+
+    log::info!(
+        "{} of {} required archives are on {}",
+        report.satisfied.len(),
+        report.satisfied.len() + report.missing.len(),
+        server.base_url()
+    );
+    if !report.missing.is_empty() {
+        log::info!(
+            "Missing from the server: {:#?}",
+            report
+                .missing
+                .iter()
+                .map(|archive| &archive.filename)
+                .collect::<Vec<_>>()
+        );
+    }
+    report.print(format);
+    if let Some(path) = report_path {
+        report.write_missing(path);
+    }
+    if notify {
+        desktop_notification(
+            "wabba-tools validate finished",
+            &format!(
+                "{} missing from the server, {} available",
+                report.missing.len(),
+                report.satisfied.len()
+            ),
+        );
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    let cli = cli::Cli::parse();
+    let matches = cli::Cli::command().get_matches();
+    let cli = cli::Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let command = matches.subcommand_name().unwrap_or_default().to_string();
+    if cli.notify {
+        notify_on_panic(command.clone());
+    }
+
+    let mut builder = Builder::from_default_env();
+    builder.filter_level(match cli.debug {
+        0 => log::LevelFilter::Info,
+        1 => log::LevelFilter::Debug,
+        2 => log::LevelFilter::Trace,
+        _ => log::LevelFilter::Trace,
+    });
+    // The full-screen interface shows the log in a pane of its own.
+    let log_buffer = LogBuffer::default();
+    if matches!(cli.command, cli::Commands::Tui { .. }) {
+        builder.target(env_logger::Target::Pipe(Box::new(log_buffer.clone())));
+    }
+    builder.init();
 
-    Builder::from_default_env()
-        .filter_level(match cli.debug {
-            0 => log::LevelFilter::Info,
-            1 => log::LevelFilter::Debug,
-            2 => log::LevelFilter::Trace,
-            _ => log::LevelFilter::Trace,
-        })
-        .init();
+    let client = match ClientOptions::new(
+        cli.proxy.as_deref(),
+        &cli.ca_certs,
+        cli.insecure,
+        cli.client_name.as_deref(),
+        cli.api_token.as_deref(),
+    ) {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(2);
+        }
+    };
 
     match &cli.command {
         cli::Commands::Validate {
             wabbajack_file,
             download_dirs,
+            modlist_id,
+            modlist_hash,
+            server,
+            against_server,
+            verify_hashes,
+            parallel,
+            no_cache,
+            fix_from,
+            watch,
+            format,
+            report: report_path,
         } => {
-            let metadata =
-                WabbajackMetadata::load(wabbajack_file).expect("Failed to load Wabbajack metadata");
+            let from_server = modlist_id.is_some() || modlist_hash.is_some();
+            // Without a Wabbajack file, the first path is a download
+            // directory too.
+            let download_dirs: Vec<PathBuf> = if from_server {
+                wabbajack_file
+                    .iter()
+                    .chain(download_dirs)
+                    .cloned()
+                    .collect()
+            } else {
+                download_dirs.clone()
+            };
+            let metadata = if from_server {
+                let Some(url) = server.as_deref().or(against_server.as_deref()) else {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        "--modlist-id and --modlist-hash need --server",
+                    );
+                    return;
+                };
+                match modlist_from_server(url, &client, *modlist_id, modlist_hash.as_deref()).await
+                {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        notify_failure(cli.notify, &command, &e);
+                        return;
+                    }
+                }
+            } else {
+                let wabbajack_file = wabbajack_file
+                    .as_ref()
+                    .expect("clap requires a Wabbajack file without --modlist-id");
+                // `load` echoes the metadata to stdout, which would get
+                // mixed into machine-readable output.
+                if *format == OutputFormat::Log && against_server.is_none() {
+                    WabbajackMetadata::load(wabbajack_file)
+                } else {
+                    WabbajackMetadata::load_quietly(wabbajack_file)
+                }
+                .expect("Failed to load Wabbajack metadata")
+            };
+
+            if let Some(server) = against_server {
+                validate_against_server(
+                    server,
+                    &client,
+                    &metadata,
+                    *format,
+                    report_path.as_deref(),
+                    cli.notify,
+                )
+                .await;
+                return;
+            }
+            if download_dirs.is_empty() {
+                notify_failure(cli.notify, &command, "No download directories given");
+                return;
+            }
 
             log::info!("Required archives: {:#?}", metadata.required_archives());
 
@@ -196,67 +439,337 @@ async fn main() {
             }
 
             let required_files = metadata.required_files();
-            let download_directory = DownloadDirectory::new(&download_dirs[0])
-                .expect("Failed to create download directory");
 
-            let result = compare_file_lists(&required_files, &download_directory.files());
+            // Earlier directories win; later ones are overflow or backup
+            // locations that only fill in what the earlier ones lack.
+            let mut paths: HashMap<String, PathBuf> = HashMap::new();
+            for dir in &download_dirs {
+                let download_directory =
+                    DownloadDirectory::new(dir).expect("Failed to create download directory");
+                for path in download_directory.file_paths() {
+                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                        paths.entry(normalize_filename(name)).or_insert(path);
+                    }
+                }
+            }
+            if !fix_from.is_empty() {
+                let recovered = recover_missing(
+                    &metadata.required_archives(),
+                    &mut paths,
+                    fix_from,
+                    &download_dirs[0],
+                    *verify_hashes,
+                    cli.dry_run,
+                );
+                if cli.dry_run {
+                    log::info!("Would recover {} missing archives", recovered.len());
+                } else {
+                    log::info!("Recovered {} missing archives", recovered.len());
+                }
+            }
+
+            let files_in_download_dirs: Vec<String> = paths
+                .values()
+                .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+                .collect();
+
+            let result = compare_file_lists(&required_files, &files_in_download_dirs);
+
+            let directory_of = |filename: &str| {
+                paths
+                    .get(&normalize_filename(filename))
+                    .and_then(|path| path.parent())
+                    .map(|dir| dir.display().to_string())
+            };
+            let entry = |archive: &Archive| ArchiveEntry {
+                filename: archive.filename.clone(),
+                size: archive.size,
+                hash: archive.hash.clone(),
+                source_type: archive.state.source_type().to_string(),
+                source_url: archive.state.source_url(),
+                prompt: archive.state.manual_prompt().map(|(prompt, _)| prompt),
+                directory: directory_of(&archive.filename),
+            };
+            let mut report = ValidationReport {
+                missing: Vec::new(),
+                satisfied: Vec::new(),
+                extraneous: result
+                    .extraneous_files
+                    .iter()
+                    .map(|filename| ExtraneousFile {
+                        filename: filename.clone(),
+                        size: paths
+                            .get(&normalize_filename(filename))
+                            .and_then(|path| std::fs::metadata(path).ok())
+                            .map_or(0, |m| m.len()),
+                        directory: directory_of(filename),
+                    })
+                    .collect(),
+                mismatched: Vec::new(),
+            };
+            for archive in metadata.required_archives() {
+                if result.missing_files.contains(&archive.filename) {
+                    report.missing.push(entry(archive));
+                } else {
+                    report.satisfied.push(entry(archive));
+                }
+            }
 
             log::info!("Missing files: {:#?}", result.missing_files);
+
+            if download_dirs.len() > 1 {
+                for archive in &report.satisfied {
+                    if let Some(dir) = &archive.directory {
+                        log::info!("{} found in {}", archive.filename, dir);
+                    }
+                }
+            }
+
+            if *verify_hashes {
+                let parallelism = (*parallel).max(1);
+                let present: Vec<(&Archive, PathBuf)> = metadata
+                    .required_archives()
+                    .into_iter()
+                    .filter_map(|archive| {
+                        let path = paths.get(&normalize_filename(&archive.filename))?;
+                        Some((archive, path.clone()))
+                    })
+                    .collect();
+                log::info!(
+                    "Verifying {} files with parallelism={}",
+                    present.len(),
+                    parallelism
+                );
+
+                // The same per-directory cache `sync` keeps, so files that
+                // haven't changed since either command last hashed them
+                // aren't read again. A dry run reads it but doesn't save.
+                let use_cache = !no_cache;
+                let save_cache = use_cache && !cli.dry_run;
+                let mut caches: HashMap<PathBuf, Arc<Mutex<SyncCache>>> = HashMap::new();
+                for (_, path) in &present {
+                    if let Some(dir) = path.parent() {
+                        caches.entry(dir.to_path_buf()).or_insert_with(|| {
+                            Arc::new(Mutex::new(if use_cache {
+                                SyncCache::load(dir)
+                            } else {
+                                SyncCache::default()
+                            }))
+                        });
+                    }
+                }
+                let cache_hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+                // `buffered` yields results in modlist order however the
+                // hashes finish, so the report stays stable between runs.
+                let results: Vec<bool> = stream::iter(present.iter().map(|(archive, path)| {
+                    let path = path.clone();
+                    let size = archive.size;
+                    let hash = archive.hash.clone();
+                    let cache = path.parent().and_then(|dir| caches.get(dir)).cloned();
+                    let cache_hits = Arc::clone(&cache_hits);
+                    log::debug!("Verifying {}", archive.filename);
+                    tokio::task::spawn_blocking(move || {
+                        let Ok(metadata) = std::fs::metadata(&path) else {
+                            return false;
+                        };
+                        if metadata.len() != size {
+                            return false;
+                        }
+                        let (size, mtime_nanos) = file_fingerprint(&metadata);
+                        let filename = path
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or_default()
+                            .to_string();
+                        let cached = cache.as_ref().and_then(|cache| {
+                            cache.lock().unwrap().lookup(&filename, size, mtime_nanos)
+                        });
+                        let actual = match cached {
+                            Some(cached) => {
+                                cache_hits.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                                cached
+                            }
+                            None => match Hash::compute_file(&path) {
+                                Ok(actual) => {
+                                    if let Some(cache) = &cache {
+                                        cache.lock().unwrap().insert(
+                                            filename,
+                                            size,
+                                            mtime_nanos,
+                                            actual.clone(),
+                                        );
+                                    }
+                                    actual
+                                }
+                                Err(_) => return false,
+                            },
+                        };
+                        actual == hash
+                    })
+                }))
+                .buffered(parallelism)
+                .map(|result| result.expect("blocking hash task panicked"))
+                .collect()
+                .await;
+                if use_cache {
+                    log::info!(
+                        "{} of {} hashes came from the cache",
+                        cache_hits.load(std::sync::atomic::Ordering::Relaxed),
+                        present.len()
+                    );
+                }
+                if save_cache {
+                    for (dir, cache) in &caches {
+                        if let Err(e) = cache.lock().unwrap().save(dir) {
+                            log::warn!("Failed to save hash cache in {}: {}", dir.display(), e);
+                        }
+                    }
+                }
+                for ((archive, _), matches) in present.iter().zip(results) {
+                    if !matches {
+                        report.mismatched.push(entry(archive));
+                    }
+                }
+                log::info!(
+                    "Mismatched files: {:#?}",
+                    report
+                        .mismatched
+                        .iter()
+                        .map(|archive| &archive.filename)
+                        .collect::<Vec<_>>()
+                );
+            }
+
+            for archive in metadata.required_archives() {
+                if result.missing_files.contains(&archive.filename)
+                    && let Some((prompt, url)) = archive.state.manual_prompt()
+                {
+                    log::warn!(
+                        "Manual download required for {}: {} ({})",
+                        archive.filename,
+                        prompt,
+                        url
+                    );
+                }
+            }
+
+            report.print(*format);
+            if let Some(path) = report_path {
+                report.write_missing(path);
+            }
+            if *watch && !report.missing.is_empty() {
+                watch_download_dirs(&metadata.required_archives(), &download_dirs, cli.notify)
+                    .await;
+            } else if cli.notify {
+                desktop_notification(
+                    "wabba-tools validate finished",
+                    &format!(
+                        "{} missing, {} satisfied, {} mismatched",
+                        report.missing.len(),
+                        report.satisfied.len(),
+                        report.mismatched.len()
+                    ),
+                );
+            }
         }
 
-        cli::Commands::Hash { file } => {
-            let hash = Hash::compute(&std::fs::read(file).expect("Failed to read file"));
-            log::info!("Hash: {}", hash);
+        cli::Commands::Hash { files, format } => {
+            if !hash_files(files, *format) {
+                std::process::exit(1);
+            }
         }
 
         cli::Commands::Upload { server, file } => {
             log::info!("Computing hash for {}", file.display());
-            let hash = Hash::compute(&std::fs::read(file).expect("Failed to read file"));
+            let hash = Hash::compute_file(file).expect("Failed to hash file");
+            if cli.dry_run {
+                println!("Would upload {} ({}) to {}", file.display(), hash, server);
+                return;
+            }
 
-            let client = Client::new();
-            let server = match resolve_base_url(&client, server).await {
-                Ok(s) => s,
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s.with_upload_limit(cli.limit_rate),
                 Err(e) => {
-                    log::error!("Failed to reach server: {}", e);
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
                     return;
                 }
             };
-            let server = server.as_str();
-            match upload_file(&client, server, file, &hash).await {
-                Ok(UploadOutcome::Uploaded) => log::info!("Upload successful"),
-                Ok(UploadOutcome::AlreadyPresent) => log::info!("File already exists"),
+            // Asking first saves starting a transfer the server would turn
+            // away. If the check fails, the upload's If-None-Match still
+            // guards against sending a file twice.
+            let kind = ArchiveKind::for_filename(&file.to_string_lossy());
+            let size = std::fs::metadata(file).expect("Failed to stat file").len();
+            match server.check_files(kind, &[(hash.clone(), size)]).await {
+                Ok(report) if report.available.contains(&hash) => {
+                    log::info!("Server already has {} — skipping", file.display());
+                    if cli.notify {
+                        desktop_notification(
+                            "wabba-tools upload finished",
+                            "The server already has this file",
+                        );
+                    }
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Hash check failed, uploading anyway: {}", e),
+            }
+            let outcome = match server.submit(file, &hash).await {
+                Ok(UploadOutcome::Uploaded(result)) => {
+                    log::info!("Upload successful, stored as {}", result.filename);
+                    format!("Stored as {}", result.filename)
+                }
+                Ok(UploadOutcome::AlreadyPresent) => {
+                    log::info!("File already exists");
+                    "The server already has this file".to_string()
+                }
                 Ok(UploadOutcome::Failed(code, body)) => {
                     log::error!("Upload failed: {}", code);
                     log::error!("Response body: {}", body);
+                    format!("Upload failed: {}", code)
+                }
+                Err(e) => {
+                    log::error!("Upload error: {}", e);
+                    format!("Upload error: {}", e)
                 }
-                Err(e) => log::error!("Upload error: {}", e),
+            };
+            if cli.notify {
+                desktop_notification("wabba-tools upload finished", &outcome);
             }
         }
 
         cli::Commands::Sync {
-            server,
+            server_or_directory,
             directory,
+            server,
             no_cache,
             parallel,
+            transfers,
+            report_file,
         } => {
-            let client = Client::new();
-            let server = match resolve_base_url(&client, server).await {
-                Ok(s) => s,
+            let (server, directory) = match (server, directory) {
+                (Some(server), _) => (server, PathBuf::from(server_or_directory)),
+                (None, Some(directory)) => (server_or_directory, directory.clone()),
+                (None, None) => unreachable!("clap requires DIRECTORY without --server"),
+            };
+            let directory = &directory;
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s.with_upload_limit(cli.limit_rate),
                 Err(e) => {
-                    log::error!("Failed to reach server: {}", e);
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
                     return;
                 }
             };
-            let server = server.as_str();
-
-            let download_directory =
-                DownloadDirectory::new(directory).expect("Failed to open directory");
 
-            let files: Vec<PathBuf> = download_directory
-                .file_paths()
-                .into_iter()
-                .filter(|p| p.file_name().and_then(|n| n.to_str()) != Some(CACHE_FILENAME))
-                .collect();
+            let files = upload_candidates(directory, false);
             log::info!(
                 "Found {} candidate files in {}",
                 files.len(),
@@ -265,6 +778,8 @@ async fn main() {
 
             let parallelism = (*parallel).max(1);
             let use_cache = !no_cache;
+            // A dry run still reads the cache but leaves it as it was.
+            let save_cache = use_cache && !cli.dry_run;
 
             let old_cache = Arc::new(if use_cache {
                 SyncCache::load(directory)
@@ -282,6 +797,65 @@ async fn main() {
             }
             let new_cache = Arc::new(Mutex::new(SyncCache::default()));
 
+            // Files whose hash is cached are checked in bulk before anything
+            // is hashed or sent: the ones the server has are done, and the
+            // rest don't need asking about one by one.
+            let mut report = TransferReport::new("sync");
+            let mut cached = Vec::new();
+            for file in &files {
+                let Some(filename) = file.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                let Ok(metadata) = std::fs::metadata(file) else {
+                    continue;
+                };
+                let (size, mtime_nanos) = file_fingerprint(&metadata);
+                if let Some(hash) = old_cache.lookup(filename, size, mtime_nanos) {
+                    cached.push((file.clone(), filename.to_string(), size, mtime_nanos, hash));
+                }
+            }
+            let mut present = HashSet::new();
+            let mut checked = HashSet::new();
+            for kind in [ArchiveKind::Modlist, ArchiveKind::Mod] {
+                let hashes: Vec<(String, u64)> = cached
+                    .iter()
+                    .filter(|(_, filename, ..)| ArchiveKind::for_filename(filename) == kind)
+                    .map(|(_, _, size, _, hash)| (hash.clone(), *size))
+                    .collect();
+                for batch in hashes.chunks(CHECK_BATCH_SIZE) {
+                    match server.check_files(kind, batch).await {
+                        Ok(batch_report) => {
+                            present.extend(batch_report.available);
+                            checked.extend(batch_report.missing);
+                        }
+                        Err(e) => log::warn!("Bulk hash check failed: {}", e),
+                    }
+                }
+            }
+            let mut done = HashSet::new();
+            for (file, filename, size, mtime_nanos, hash) in cached {
+                if present.contains(&hash) {
+                    log::info!("Server already has {} — skipping", filename);
+                    report.skipped(&filename);
+                    new_cache
+                        .lock()
+                        .unwrap()
+                        .insert(filename, size, mtime_nanos, hash);
+                    done.insert(file);
+                }
+            }
+            let files: Vec<PathBuf> = files
+                .into_iter()
+                .filter(|file| !done.contains(file))
+                .collect();
+            if !done.is_empty() {
+                log::info!(
+                    "{} files are already on the server; {} left to hash or upload",
+                    done.len(),
+                    files.len()
+                );
+            }
+
             log::info!(
                 "Hashing {} files with parallelism={}",
                 files.len(),
@@ -289,7 +863,7 @@ async fn main() {
             );
 
             let sem = Arc::new(Semaphore::new(parallelism));
-            let mut set: JoinSet<(PathBuf, Result<String, String>)> = JoinSet::new();
+            let mut set: JoinSet<HashedFile> = JoinSet::new();
             let total = files.len();
 
             // Spawn every task up front so the `for` loop returns immediately
@@ -309,7 +883,7 @@ async fn main() {
                             .and_then(|n| n.to_str())
                             .unwrap_or_default()
                             .to_string();
-                        let result = (|| -> Result<String, String> {
+                        let result = (|| -> Result<(String, u64), String> {
                             let metadata =
                                 std::fs::metadata(&file).map_err(|e| format!("stat: {}", e))?;
                             let (size, mtime_nanos) = file_fingerprint(&metadata);
@@ -322,7 +896,7 @@ async fn main() {
                                     mtime_nanos,
                                     cached.clone(),
                                 );
-                                return Ok(cached);
+                                return Ok((cached, size));
                             }
 
                             let hash =
@@ -333,7 +907,7 @@ async fn main() {
                                 mtime_nanos,
                                 hash.clone(),
                             );
-                            Ok(hash)
+                            Ok((hash, size))
                         })();
                         (file, result)
                     })
@@ -347,47 +921,110 @@ async fn main() {
             // save() keeps the on-disk file always consistent.
             const CACHE_FLUSH_INTERVAL: usize = 50;
 
-            let mut hashed: Vec<(PathBuf, String)> = Vec::with_capacity(total);
-            let mut failed = 0usize;
+            // Files are checked and uploaded as soon as they are hashed, so
+            // transfers overlap with hashing the rest of the directory. At
+            // most `transfers` files are in flight; while that many are,
+            // finished hashes wait in the join set.
+            let transfers = (*transfers).max(1);
+            let server = &server;
+            let mut uploads = FuturesUnordered::new();
+
+            // One line for all uploads in flight rather than one per file,
+            // so running several at once stays readable.
+            const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+            let sent = Arc::new(AtomicU64::new(0));
+            let mut last_sent = 0u64;
+            let mut progress = interval_at(Instant::now() + PROGRESS_INTERVAL, PROGRESS_INTERVAL);
+            progress.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
             let mut completed = 0usize;
-            while let Some(joined) = set.join_next().await {
-                let (file, result) = joined.expect("hash task panicked");
-                completed += 1;
-                let filename = file
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("<unknown>")
-                    .to_string();
-                match result {
-                    Ok(hash) => {
-                        log::info!("[{}/{}] Hashed {}", completed, total, filename);
-                        hashed.push((file, hash));
+            let mut would_upload = 0usize;
+            while !set.is_empty() || !uploads.is_empty() {
+                tokio::select! {
+                    Some(joined) = set.join_next(), if uploads.len() < transfers => {
+                        let (file, result) = joined.expect("hash task panicked");
+                        completed += 1;
+                        let filename = file
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or("<unknown>")
+                            .to_string();
+                        match result {
+                            Ok(hashed) => {
+                                log::info!("[{}/{}] Hashed {}", completed, total, filename);
+                                let checked = checked.contains(&hashed.0);
+                                uploads.push(sync_file(
+                                    server,
+                                    file,
+                                    filename,
+                                    hashed,
+                                    checked,
+                                    Arc::clone(&sent),
+                                    cli.dry_run,
+                                ));
+                            }
+                            Err(e) => {
+                                log::error!(
+                                    "[{}/{}] Failed to hash {}: {}",
+                                    completed,
+                                    total,
+                                    filename,
+                                    e
+                                );
+                                report.failed(&filename, e);
+                            }
+                        }
+
+                        if save_cache && completed.is_multiple_of(CACHE_FLUSH_INTERVAL) {
+                            let snapshot = new_cache.lock().unwrap().clone();
+                            if let Err(e) = snapshot.save(directory) {
+                                log::warn!("Cache flush failed at {} entries: {}", completed, e);
+                            } else {
+                                log::debug!("Flushed cache ({}/{} files hashed)", completed, total);
+                            }
+                        }
                     }
-                    Err(e) => {
-                        log::error!(
-                            "[{}/{}] Failed to hash {}: {}",
-                            completed,
-                            total,
-                            filename,
-                            e
+                    Some((filename, outcome)) = uploads.next() => match outcome {
+                        Ok(SyncStep::Present) => {
+                            log::info!("Server already has {} — skipping", filename);
+                            report.skipped(&filename);
+                        }
+                        Ok(SyncStep::WouldUpload) => would_upload += 1,
+                        Ok(SyncStep::Submitted(UploadOutcome::Uploaded(result))) => {
+                            log::info!("Uploaded {} as {}", filename, result.filename);
+                            report.transferred(&filename, result.size);
+                        }
+                        Ok(SyncStep::Submitted(UploadOutcome::AlreadyPresent)) => {
+                            log::info!("Server reported {} already present", filename);
+                            report.skipped(&filename);
+                        }
+                        Ok(SyncStep::Submitted(UploadOutcome::Failed(code, body))) => {
+                            log::error!("Upload of {} failed: {} — {}", filename, code, body);
+                            report.failed(&filename, format!("{}: {}", code, body));
+                        }
+                        Err(e) => {
+                            log::error!("Upload error for {}: {}", filename, e);
+                            report.failed(&filename, e);
+                        }
+                    },
+                    _ = progress.tick(), if !uploads.is_empty() && !cli.dry_run => {
+                        let total_sent = sent.load(Ordering::Relaxed);
+                        let rate = (total_sent - last_sent) / PROGRESS_INTERVAL.as_secs();
+                        last_sent = total_sent;
+                        log::info!(
+                            "{} in flight, {} uploaded, {} sent ({}/s)",
+                            uploads.len(),
+                            report.transferred.len(),
+                            format_size(total_sent),
+                            format_size(rate)
                         );
-                        failed += 1;
-                    }
-                }
-
-                if use_cache && completed.is_multiple_of(CACHE_FLUSH_INTERVAL) {
-                    let snapshot = new_cache.lock().unwrap().clone();
-                    if let Err(e) = snapshot.save(directory) {
-                        log::warn!("Cache flush failed at {} entries: {}", completed, e);
-                    } else {
-                        log::debug!("Flushed cache ({}/{} files hashed)", completed, total);
                     }
                 }
             }
 
-            // Final save before uploads — covers the last partial batch and
-            // any error paths that skipped the interval flush.
-            if use_cache {
+            // Final save — covers the last partial batch and any error
+            // paths that skipped the interval flush.
+            if save_cache {
                 let cache = Arc::try_unwrap(new_cache)
                     .expect("cache Arc should be unique now")
                     .into_inner()
@@ -399,138 +1036,952 @@ async fn main() {
                 }
             }
 
-            // Sort by filename for deterministic upload order + log output.
-            hashed.sort_by(|a, b| a.0.file_name().cmp(&b.0.file_name()));
+            if cli.dry_run {
+                log::info!(
+                    "Dry run: would upload {} files, {} already present, {} failed",
+                    would_upload,
+                    report.skipped.len(),
+                    report.failed.len()
+                );
+                return;
+            }
+            log::info!(
+                "Sync complete: {} uploaded, {} already present, {} failed",
+                report.transferred.len(),
+                report.skipped.len(),
+                report.failed.len()
+            );
+            report.finish(report_file.as_deref(), cli.notify);
+        }
 
-            let mut uploaded = 0usize;
-            let mut skipped = 0usize;
+        cli::Commands::UploadDir {
+            directory,
+            server,
+            parallel,
+            transfers,
+            report_file,
+        } => {
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s.with_upload_limit(cli.limit_rate),
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
+                    return;
+                }
+            };
+            let report =
+                upload_directory(&server, directory, *parallel, *transfers, cli.dry_run).await;
+            if cli.dry_run {
+                return;
+            }
+            log::info!(
+                "Upload complete: {} uploaded, {} already present, {} failed",
+                report.transferred.len(),
+                report.skipped.len(),
+                report.failed.len()
+            );
+            report.finish(report_file.as_deref(), cli.notify);
+        }
 
-            for (idx, (file, hash)) in hashed.iter().enumerate() {
-                let filename = file
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("<unknown>");
-                let upload_type = upload_type_for(file);
-                match server_has_hash(&client, server, upload_type, hash).await {
-                    Ok(true) => {
-                        log::info!(
-                            "[{}/{}] Server already has {} — skipping",
-                            idx + 1,
-                            hashed.len(),
-                            filename
-                        );
-                        skipped += 1;
-                        continue;
+        cli::Commands::Publish {
+            server,
+            wabbajack_file,
+            download_dirs,
+            transfers,
+            report_file,
+        } => {
+            let metadata = match WabbajackMetadata::load_quietly(wabbajack_file) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to load Wabbajack metadata: {}", e),
+                    );
+                    return;
+                }
+            };
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s.with_upload_limit(cli.limit_rate),
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
+                    return;
+                }
+            };
+            let (report, still_missing) = publish(
+                &server,
+                wabbajack_file,
+                &metadata,
+                download_dirs,
+                *transfers,
+                cli.dry_run,
+            )
+            .await;
+            if !cli.dry_run {
+                log::info!(
+                    "Publish complete: {} uploaded, {} already present, {} failed",
+                    report.transferred.len(),
+                    report.skipped.len(),
+                    report.failed.len()
+                );
+                report.finish(report_file.as_deref(), cli.notify);
+            }
+            if still_missing.is_empty() {
+                println!("The server has every archive {} needs", metadata.name);
+            } else {
+                println!(
+                    "Still missing on the server ({} archives, {}):",
+                    still_missing.len(),
+                    format_size(still_missing.iter().map(|archive| archive.size).sum())
+                );
+                for archive in &still_missing {
+                    match archive.state.source_url() {
+                        Some(url) => println!("  {} {}", archive.filename, url),
+                        None => println!("  {}", archive.filename),
                     }
-                    Ok(false) => {}
+                }
+            }
+        }
+
+        cli::Commands::Download {
+            server,
+            wabbajack_file,
+            download_dir,
+            transfers,
+            report_file,
+        } => {
+            let metadata = match WabbajackMetadata::load_quietly(wabbajack_file) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to load Wabbajack metadata: {}", e),
+                    );
+                    return;
+                }
+            };
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
+                    return;
+                }
+            };
+            if !cli.dry_run
+                && let Err(e) = std::fs::create_dir_all(download_dir)
+            {
+                notify_failure(
+                    cli.notify,
+                    &command,
+                    &format!("Failed to create {}: {}", download_dir.display(), e),
+                );
+                return;
+            }
+
+            let download_directory =
+                DownloadDirectory::new(download_dir).expect("Failed to open directory");
+            let present = if download_dir.is_dir() {
+                download_directory.files()
+            } else {
+                Vec::new()
+            };
+            let result = compare_file_lists(&metadata.required_files(), &present);
+            let missing: Vec<_> = metadata
+                .required_archives()
+                .into_iter()
+                .filter(|archive| result.missing_files.contains(&archive.filename))
+                .collect();
+            log::info!(
+                "{} of {} archives are already in {}",
+                result.satisfied_files.len(),
+                metadata.required_archives().len(),
+                download_dir.display()
+            );
+            let mut report = TransferReport::new("download");
+            for file in &result.satisfied_files {
+                report.skipped(file);
+            }
+            if missing.is_empty() {
+                report.finish(report_file.as_deref(), cli.notify);
+                return;
+            }
+
+            let available: HashSet<String> = match server
+                .check(
+                    ArchiveKind::Mod,
+                    missing.iter().map(|archive| archive.hash.clone()).collect(),
+                )
+                .await
+            {
+                Ok(availability) => availability.available.into_iter().collect(),
+                Err(e) => {
+                    notify_failure(cli.notify, &command, &format!("Hash check failed: {}", e));
+                    return;
+                }
+            };
+
+            let total = missing.len();
+            let mut pending = Vec::new();
+            for (idx, archive) in missing.iter().enumerate() {
+                if !available.contains(&archive.hash) {
+                    log::warn!("Server doesn't have {}", archive.filename);
+                    report.failed(&archive.filename, "not on the server");
+                    continue;
+                }
+                match safe_join(download_dir, &archive.filename) {
+                    Ok(path) => pending.push((idx, archive, path)),
                     Err(e) => {
-                        log::error!("Hash check failed for {}: {}", filename, e);
-                        failed += 1;
-                        continue;
+                        log::warn!("Not downloading {}: {}", archive.filename, e);
+                        report.failed(&archive.filename, e);
                     }
                 }
+            }
+            if cli.dry_run {
+                for (_, archive, path) in &pending {
+                    println!(
+                        "Would download {} ({})",
+                        path.display(),
+                        format_size(archive.size)
+                    );
+                }
+                log::info!(
+                    "Dry run: would download {} files ({}), {} can't be downloaded",
+                    pending.len(),
+                    format_size(pending.iter().map(|(_, archive, _)| archive.size).sum()),
+                    report.failed.len()
+                );
+                return;
+            }
 
-                log::info!("[{}/{}] Uploading {}", idx + 1, hashed.len(), filename);
-                match upload_file(&client, server, file, hash).await {
-                    Ok(UploadOutcome::Uploaded) => {
-                        log::info!("Uploaded {}", filename);
-                        uploaded += 1;
+            let server = &server;
+            let mut downloads = stream::iter(pending)
+                .map(|(idx, archive, path)| async move {
+                    log::info!("[{}/{}] Downloading {}", idx + 1, total, archive.filename);
+                    let result = server
+                        .download_mod(&archive.hash, archive.size, &path)
+                        .await;
+                    (archive, path, result)
+                })
+                .buffer_unordered((*transfers).max(1));
+            while let Some((archive, path, result)) = downloads.next().await {
+                match result {
+                    Ok(()) => {
+                        // Lets Wabbajack recognize the download as its own.
+                        if let Some(ini) = archive.meta_ini() {
+                            let mut meta_path = path.into_os_string();
+                            meta_path.push(".meta");
+                            if let Err(e) = std::fs::write(&meta_path, ini) {
+                                log::warn!("Failed to write .meta for {}: {}", archive.filename, e);
+                            }
+                        }
+                        report.transferred(&archive.filename, archive.size)
                     }
-                    Ok(UploadOutcome::AlreadyPresent) => {
-                        log::info!("Server reported {} already present", filename);
-                        skipped += 1;
+                    Err(e) => {
+                        log::error!("Download of {} failed: {}", archive.filename, e);
+                        report.failed(&archive.filename, e);
                     }
-                    Ok(UploadOutcome::Failed(code, body)) => {
-                        log::error!("Upload of {} failed: {} — {}", filename, code, body);
-                        failed += 1;
+                }
+            }
+
+            log::info!(
+                "Download complete: {} downloaded, {} failed",
+                report.transferred.len(),
+                report.failed.len()
+            );
+            report.finish(report_file.as_deref(), cli.notify);
+        }
+
+        cli::Commands::Fetch {
+            wabbajack_file,
+            download_dir,
+            nexus_api_key,
+            transfers,
+            report_file,
+        } => {
+            let metadata = match WabbajackMetadata::load_quietly(wabbajack_file) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to load Wabbajack metadata: {}", e),
+                    );
+                    return;
+                }
+            };
+            let fetcher = match Fetcher::new(nexus_api_key.clone()) {
+                Ok(fetcher) => fetcher,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to set up the HTTP client: {}", e),
+                    );
+                    return;
+                }
+            };
+            let report =
+                fetch_missing(&fetcher, &metadata, download_dir, *transfers, cli.dry_run).await;
+            if cli.dry_run {
+                return;
+            }
+            log::info!(
+                "Fetch complete: {} downloaded, {} failed",
+                report.transferred.len(),
+                report.failed.len()
+            );
+            report.finish(report_file.as_deref(), cli.notify);
+        }
+
+        cli::Commands::Meta {
+            wabbajack_file,
+            download_dir,
+            overwrite,
+        } => {
+            let metadata = match WabbajackMetadata::load_quietly(wabbajack_file) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to load Wabbajack metadata: {}", e),
+                    );
+                    return;
+                }
+            };
+            let download_directory =
+                DownloadDirectory::new(download_dir).expect("Failed to create download directory");
+            let paths: HashMap<String, PathBuf> = download_directory
+                .file_paths()
+                .into_iter()
+                .filter_map(|path| {
+                    let name = normalize_filename(path.file_name()?.to_str()?);
+                    Some((name, path))
+                })
+                .collect();
+
+            let (mut written, mut kept, mut unsupported) = (0, 0, 0);
+            for archive in metadata.required_archives() {
+                let Some(path) = paths.get(&normalize_filename(&archive.filename)) else {
+                    continue;
+                };
+                let Some(ini) = archive.meta_ini() else {
+                    log::debug!(
+                        "No .meta for {} ({} source)",
+                        archive.filename,
+                        archive.state.source_type()
+                    );
+                    unsupported += 1;
+                    continue;
+                };
+                let mut meta_path = path.clone().into_os_string();
+                meta_path.push(".meta");
+                let meta_path = PathBuf::from(meta_path);
+                if meta_path.exists() && !overwrite {
+                    kept += 1;
+                    continue;
+                }
+                if cli.dry_run {
+                    println!("Would write {}", meta_path.display());
+                    written += 1;
+                    continue;
+                }
+                match std::fs::write(&meta_path, ini) {
+                    Ok(()) => {
+                        log::debug!("Wrote {}", meta_path.display());
+                        written += 1;
                     }
+                    Err(e) => log::error!("Failed to write {}: {}", meta_path.display(), e),
+                }
+            }
+            log::info!(
+                "{} {} .meta files, kept {} existing, {} archives have no .meta",
+                if cli.dry_run { "Would write" } else { "Wrote" },
+                written,
+                kept,
+                unsupported
+            );
+        }
+
+        cli::Commands::Mirror {
+            server,
+            wabbajack_file,
+            output,
+            report_file,
+        } => {
+            let metadata = match WabbajackMetadata::load_quietly(wabbajack_file) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to load Wabbajack metadata: {}", e),
+                    );
+                    return;
+                }
+            };
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
+                    return;
+                }
+            };
+
+            let hashes: Vec<String> = metadata
+                .required_archives()
+                .iter()
+                .map(|archive| archive.hash.clone())
+                .collect();
+            let available: HashSet<String> = match server.check(ArchiveKind::Mod, hashes).await {
+                Ok(report) => report.available.into_iter().collect(),
+                Err(e) => {
+                    notify_failure(cli.notify, &command, &format!("Hash check failed: {}", e));
+                    return;
+                }
+            };
+
+            if cli.dry_run {
+                let mirrored: Vec<&Archive> = metadata
+                    .required_archives()
+                    .into_iter()
+                    .filter(|archive| {
+                        archive.state.requires_download() && available.contains(&archive.hash)
+                    })
+                    .collect();
+                for archive in &mirrored {
+                    println!(
+                        "Would point {} at {}",
+                        archive.filename,
+                        server.mod_download_url(&archive.hash)
+                    );
+                }
+                log::info!(
+                    "Dry run: would write {} with {} of {} archives mirrored",
+                    output.display(),
+                    mirrored.len(),
+                    metadata.required_archives().len()
+                );
+                return;
+            }
+
+            let mut report = TransferReport::new("mirror");
+            let result = patch_archive_states(wabbajack_file, output, |archive| {
+                if !archive.state.requires_download() {
+                    return None;
+                }
+                if !available.contains(&archive.hash) {
+                    report.skipped(&archive.filename);
+                    return None;
+                }
+                report.transferred(&archive.filename, 0);
+                Some(ArchiveState::HttpDownloader {
+                    url: server.mod_download_url(&archive.hash),
+                    headers: serde_json::json!([]),
+                })
+            });
+            match result {
+                Ok(patched) => log::info!(
+                    "Wrote {} with {} of {} archives mirrored",
+                    output.display(),
+                    patched,
+                    metadata.required_archives().len()
+                ),
+                Err(e) => {
+                    log::error!("Failed to write {}: {}", output.display(), e);
+                    report.failed(&output.display().to_string(), e);
+                }
+            }
+            report.finish(report_file.as_deref(), cli.notify);
+        }
+
+        cli::Commands::Prune {
+            download_dir,
+            wabbajack_files,
+            trash,
+            delete,
+            keep_meta,
+            yes,
+        } => {
+            let mut required_files = Vec::new();
+            for wabbajack_file in wabbajack_files {
+                match WabbajackMetadata::load_quietly(wabbajack_file) {
+                    Ok(metadata) => required_files.extend(metadata.required_files()),
                     Err(e) => {
-                        log::error!("Upload error for {}: {}", filename, e);
-                        failed += 1;
+                        log::error!(
+                            "Failed to load {}: {}; nothing pruned",
+                            wabbajack_file.display(),
+                            e
+                        );
+                        return;
                     }
                 }
             }
+            let download_directory =
+                DownloadDirectory::new(download_dir).expect("Failed to open directory");
+            let to_prune = prunable(download_directory.file_paths(), &required_files);
+            if to_prune.is_empty() {
+                log::info!("Nothing to prune in {}", download_dir.display());
+                return;
+            }
 
+            let total_size: u64 = to_prune
+                .iter()
+                .filter_map(|path| std::fs::metadata(path).ok())
+                .map(|m| m.len())
+                .sum();
+            for path in &to_prune {
+                println!("{}", path.display());
+            }
+            let trash = if *delete {
+                None
+            } else {
+                Some(
+                    trash
+                        .clone()
+                        .unwrap_or_else(|| download_dir.join("wabba-trash")),
+                )
+            };
+            let question = match &trash {
+                Some(trash) => format!(
+                    "Move these {} files ({}) to {}?",
+                    to_prune.len(),
+                    format_size(total_size),
+                    trash.display()
+                ),
+                None => format!(
+                    "Delete these {} files ({})?",
+                    to_prune.len(),
+                    format_size(total_size)
+                ),
+            };
+            if cli.dry_run {
+                match &trash {
+                    Some(trash) => log::info!(
+                        "Dry run: would move {} files ({}) to {}",
+                        to_prune.len(),
+                        format_size(total_size),
+                        trash.display()
+                    ),
+                    None => log::info!(
+                        "Dry run: would delete {} files ({})",
+                        to_prune.len(),
+                        format_size(total_size)
+                    ),
+                }
+                return;
+            }
+            if !*yes && !confirm(&question) {
+                log::info!("Nothing pruned");
+                return;
+            }
+            if let Some(trash) = &trash
+                && let Err(e) = std::fs::create_dir_all(trash)
+            {
+                notify_failure(
+                    cli.notify,
+                    &command,
+                    &format!("Failed to create {}: {}", trash.display(), e),
+                );
+                return;
+            }
+
+            let failed = prune(&to_prune, trash.as_deref(), *keep_meta);
+            for (path, e) in &failed {
+                log::error!("Failed to prune {}: {}", path.display(), e);
+            }
             log::info!(
-                "Sync complete: {} uploaded, {} already present, {} failed",
-                uploaded,
-                skipped,
-                failed
+                "Pruned {} of {} files",
+                to_prune.len()
+                    - failed
+                        .iter()
+                        .filter(|(path, _)| to_prune.contains(path))
+                        .count(),
+                to_prune.len()
             );
         }
-    }
 
-    // let result = compare_file_lists(&required_files, &files_in_download_dir);
-
-    // let potential_remote_dirs = vec![
-    //     "/mnt/users/prensox/WabbajackRepo/downloads",
-    //     "/mnt/users/prensox/WabbajackRepo/Wabbajack Backup",
-    // ]
-    // .into_iter()
-    // .map(PathBuf::from)
-    // .collect::<Vec<PathBuf>>();
-
-    // // for each file in result.missing_files, check if it exists in potential_remote_dirs
-    // for missing_file in &result.missing_files {
-    //     let mut found = false;
-    //     for dir in &potential_remote_dirs {
-    //         let file_path = dir.join(missing_file);
-    //         if file_path.exists() {
-    //             println!("Found missing file: {} in {}", missing_file, dir.display());
-    //             found = true;
-    //             break;
-    //         }
-    //     }
-    //     if !found {
-    //         println!("File still missing: {}", missing_file);
-    //     }
-    // }
-
-    // // for each file in result.missing_files, check if it exists in potential_remote_dirs
-    // let mut i = 0;
-    // let n = result.missing_files.len();
-    // for missing_file in &result.missing_files {
-    //     i = i + 1;
-    //     println!("{}/{}", i + 1, n);
-    //     for dir in &potential_remote_dirs {
-    //         let file_path = dir.join(missing_file);
-    //         let meta_file_path = file_path.with_meta_extension();
-    //         if file_path.exists() {
-    //             println!("Recovering: {}", missing_file);
-    //             let destination = PathBuf::from(download_dir).join(missing_file);
-    //             fs::copy(&file_path, &destination).expect("Failed to copy file");
-    //             println!("Recovered {} to {}", missing_file, destination.display());
-
-    //             if meta_file_path.exists() {
-    //                 let destination_meta = PathBuf::from(download_dir)
-    //                     .join(missing_file)
-    //                     .with_meta_extension();
-    //                 fs::copy(&meta_file_path, &destination_meta).expect("Failed to copy meta file");
-    //                 println!(
-    //                     "Recovered meta file for {} to {}",
-    //                     missing_file,
-    //                     destination_meta.display()
-    //                 );
-    //             } else {
-    //                 println!("No meta file found for {}", missing_file);
-    //             }
-
-    //             break;
-    //         }
-    //     }
-    // }
-
-    // println!("{:#?}", result);
-}
+        cli::Commands::Dedupe {
+            dirs,
+            link,
+            parallel,
+            no_cache,
+            yes,
+        } => {
+            let use_cache = !no_cache;
+            let sets = find_duplicates(dirs, *parallel, use_cache, use_cache && !cli.dry_run).await;
 
-// trait FileExt {
-//     fn with_meta_extension(&self) -> PathBuf;
-// }
-
-// impl FileExt for PathBuf {
-//     fn with_meta_extension(&self) -> PathBuf {
-//         let mut meta_extension = self.extension().unwrap_or_default().to_os_string();
-//         meta_extension.push(".meta");
-//         self.with_extension(meta_extension)
-//     }
-// }
+            // The safety report: every copy that would be replaced, under
+            // the file it would link to.
+            let mut replacements: Vec<(&Path, &Path)> = Vec::new();
+            let mut freed = 0u64;
+            for set in &sets {
+                println!("{} ({})", set.keep.display(), format_size(set.size));
+                for copy in &set.copies {
+                    if *link == LinkKind::Hard && !same_filesystem(&set.keep, copy) {
+                        println!("  = {} (on another filesystem, left alone)", copy.display());
+                        continue;
+                    }
+                    println!("  = {}", copy.display());
+                    replacements.push((&set.keep, copy));
+                    freed += set.size;
+                }
+            }
+            if replacements.is_empty() {
+                log::info!("No copies to replace");
+                return;
+            }
+            let kind = match link {
+                LinkKind::Hard => "hard links",
+                LinkKind::Sym => "symlinks",
+            };
+            if cli.dry_run {
+                log::info!(
+                    "Dry run: would replace {} copies with {}, freeing {}",
+                    replacements.len(),
+                    kind,
+                    format_size(freed)
+                );
+                return;
+            }
+            if !*yes
+                && !confirm(&format!(
+                    "Replace these {} copies with {}, freeing {}?",
+                    replacements.len(),
+                    kind,
+                    format_size(freed)
+                ))
+            {
+                log::info!("Nothing replaced");
+                return;
+            }
+
+            let mut replaced = 0usize;
+            for (keep, copy) in &replacements {
+                match replace_with_link(*link, keep, copy) {
+                    Ok(()) => {
+                        log::debug!("Linked {} to {}", copy.display(), keep.display());
+                        replaced += 1;
+                    }
+                    Err(e) => log::error!("Failed to replace {}: {}", copy.display(), e),
+                }
+            }
+            log::info!(
+                "Replaced {} of {} copies with {}",
+                replaced,
+                replacements.len(),
+                kind
+            );
+        }
+
+        cli::Commands::BackupDb { server, db, output } => {
+            if cli.dry_run {
+                let source = match db {
+                    Some(db) => db.display().to_string(),
+                    None => server.clone().unwrap_or_default(),
+                };
+                println!("Would back up {} to {}", source, output.display());
+                return;
+            }
+            if let Err(e) = backup_database(server.as_deref(), &client, db.as_deref(), output).await
+            {
+                log::error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+
+        cli::Commands::Verify {
+            download_dir,
+            manifest,
+            write_manifest,
+            server,
+            parallel,
+            format,
+        } => {
+            let manifest_path = manifest
+                .clone()
+                .unwrap_or_else(|| download_dir.join(MANIFEST_FILENAME));
+            let mut reference = match server {
+                Some(server) => {
+                    let server = match ServerClient::connect(server, &client).await {
+                        Ok(s) => s,
+                        Err(e) => {
+                            notify_failure(
+                                cli.notify,
+                                &command,
+                                &format!("Failed to reach server: {}", e),
+                            );
+                            return;
+                        }
+                    };
+                    match server.list_mods(false, None).await {
+                        Ok(mods) => Reference::from_server(mods),
+                        Err(e) => {
+                            notify_failure(
+                                cli.notify,
+                                &command,
+                                &format!("Failed to list mods: {}", e),
+                            );
+                            return;
+                        }
+                    }
+                }
+                None => match Manifest::load(&manifest_path) {
+                    Ok(manifest) => {
+                        if manifest.is_empty() && !*write_manifest {
+                            log::error!(
+                                "No manifest at {}; create one with --write-manifest or check against --server",
+                                manifest_path.display()
+                            );
+                            return;
+                        }
+                        Reference::Manifest(manifest)
+                    }
+                    Err(e) => {
+                        notify_failure(cli.notify, &command, &e);
+                        return;
+                    }
+                },
+            };
+            let report = verify_directory(
+                download_dir,
+                &mut reference,
+                *parallel,
+                write_manifest.then_some(manifest_path.as_path()),
+                cli.dry_run,
+            )
+            .await;
+            report.print(*format);
+            if cli.notify {
+                desktop_notification(
+                    if report.has_problems() {
+                        "wabba-tools verify found damaged files"
+                    } else {
+                        "wabba-tools verify finished"
+                    },
+                    &format!(
+                        "{} ok, {} corrupted, {} truncated, {} missing",
+                        report.ok.len(),
+                        report.corrupted.len(),
+                        report.truncated.len(),
+                        report.missing.len()
+                    ),
+                );
+            }
+            if report.has_problems() {
+                std::process::exit(1);
+            }
+        }
+
+        cli::Commands::Tui {
+            wabbajack_files,
+            download_dirs,
+            server,
+            fix_from,
+            parallel,
+            transfers,
+        } => {
+            let options = TuiOptions {
+                wabbajack_files: wabbajack_files.clone(),
+                download_dirs: download_dirs.clone(),
+                server: server.clone(),
+                fix_from: fix_from.clone(),
+                parallel: *parallel,
+                transfers: *transfers,
+                limit_rate: cli.limit_rate,
+                client: client.clone(),
+                dry_run: cli.dry_run,
+            };
+            if let Err(e) = tui::run(options, log_buffer).await {
+                log::error!("{}", e);
+            }
+        }
+
+        cli::Commands::Inspect {
+            wabbajack_file,
+            archives,
+            format,
+        } => match WabbajackMetadata::load_quietly(wabbajack_file) {
+            Ok(metadata) => ModlistInspection::new(&metadata, *archives).print(*format),
+            Err(e) => log::error!("Failed to load Wabbajack metadata: {}", e),
+        },
+
+        cli::Commands::Diff { old, new, format } => {
+            let (old, new) = match (
+                WabbajackMetadata::load_quietly(old),
+                WabbajackMetadata::load_quietly(new),
+            ) {
+                (Ok(old), Ok(new)) => (old, new),
+                (Err(e), _) | (_, Err(e)) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to load Wabbajack metadata: {}", e),
+                    );
+                    return;
+                }
+            };
+            ModlistDiff::compute(&old.required_archives(), &new.required_archives()).print(*format);
+        }
+
+        cli::Commands::List {
+            what,
+            server,
+            missing,
+            modlist,
+            format,
+        } => {
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
+                    return;
+                }
+            };
+            let modlists = match server.list_modlists().await {
+                Ok(modlists) => modlists,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to list modlists: {}", e),
+                    );
+                    return;
+                }
+            };
+
+            match what {
+                ListKind::Modlists => {
+                    if modlist.is_some() {
+                        log::warn!("--modlist only applies to `list mods`");
+                    }
+                    // A modlist is missing something when any required
+                    // archive (or the modlist file itself) is absent.
+                    let modlists: Vec<_> = modlists
+                        .into_iter()
+                        .filter(|m| !missing || !m.available || m.mods_available < m.mods_total)
+                        .collect();
+                    if *format == OutputFormat::Json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&modlists)
+                                .expect("Failed to serialize modlists")
+                        );
+                        return;
+                    }
+                    let mut rows = vec![
+                        ["ID", "NAME", "VERSION", "AVAILABLE", "SIZE", "FILENAME"]
+                            .map(str::to_string),
+                    ];
+                    rows.extend(modlists.iter().map(|m| {
+                        [
+                            m.id.to_string(),
+                            m.name.clone(),
+                            m.version.clone(),
+                            format!("{}/{}", m.mods_available, m.mods_total),
+                            m.size.to_string(),
+                            m.filename.clone(),
+                        ]
+                    }));
+                    print_table(&rows);
+                }
+                ListKind::Mods => {
+                    let modlist_id = match modlist {
+                        None => None,
+                        Some(wanted) => match wanted.parse::<u64>().ok().or_else(|| {
+                            modlists
+                                .iter()
+                                .find(|m| m.name.eq_ignore_ascii_case(wanted))
+                                .map(|m| m.id)
+                        }) {
+                            Some(id) => Some(id),
+                            None => {
+                                notify_failure(
+                                    cli.notify,
+                                    &command,
+                                    &format!("No modlist named {}", wanted),
+                                );
+                                return;
+                            }
+                        },
+                    };
+                    let mods = match server.list_mods(*missing, modlist_id).await {
+                        Ok(mods) => mods,
+                        Err(e) => {
+                            notify_failure(
+                                cli.notify,
+                                &command,
+                                &format!("Failed to list mods: {}", e),
+                            );
+                            return;
+                        }
+                    };
+                    if *format == OutputFormat::Json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&mods).expect("Failed to serialize mods")
+                        );
+                        return;
+                    }
+                    let mut rows =
+                        vec![["ID", "STATUS", "SIZE", "HASH", "FILENAME"].map(str::to_string)];
+                    rows.extend(mods.iter().map(|m| {
+                        let status = if m.available {
+                            "available"
+                        } else if m.lost_forever {
+                            "lost"
+                        } else {
+                            "missing"
+                        };
+                        [
+                            m.id.to_string(),
+                            status.to_string(),
+                            m.size.to_string(),
+                            m.hash.clone(),
+                            m.filename.clone(),
+                        ]
+                    }));
+                    print_table(&rows);
+                }
+            }
+        }
+
+        cli::Commands::DemoData { server } => {
+            let server = match ServerClient::connect(server, &client).await {
+                Ok(s) => s,
+                Err(e) => {
+                    notify_failure(
+                        cli.notify,
+                        &command,
+                        &format!("Failed to reach server: {}", e),
+                    );
+                    return;
+                }
+            };
+            match demo_data::seed(&server).await {
+                Ok(()) => log::info!("Demo data uploaded"),
+                Err(e) => log::error!("Failed to upload demo data: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// Shows a desktop notification for a finished command. Only called when
+/// `--notify` is given; failures are logged, never fatal.
+pub fn desktop_notification(summary: &str, body: &str) {
+    if let Err(e) = notify_rust::Notification::new()
+        .appname("wabba-tools")
+        .summary(summary)
+        .body(body)
+        .show()
+    {
+        log::warn!("Failed to show desktop notification: {}", e);
+    }
+}
+
+/// Logs why a command gave up before finishing, e.g. on a server it
+/// couldn't reach, and with `notify` says so on the desktop as well.
+pub fn notify_failure(notify: bool, command: &str, reason: &str) {
+    log::error!("{}", reason);
+    if notify {
+        desktop_notification(&format!("wabba-tools {} failed", command), reason);
+    }
+}
+
+/// Shows a failure notification for `command` if it panics, e.g. on a
+/// download directory it can't read, before the usual panic message.
+pub fn notify_on_panic(command: String) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        desktop_notification(
+            &format!("wabba-tools {} failed", command),
+            info.payload_as_str()
+                .unwrap_or("It stopped on an unexpected error"),
+        );
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,173 @@
+//! `prune`: clearing out archives no modlist needs any more.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use wabba_protocol::filename::normalize_filename;
+
+use crate::recovery::meta_path;
+
+/// The files among `paths` that none of `required_files` names. A `.part`
+/// of a required archive is an interrupted download and is kept.
+pub fn prunable(paths: Vec<PathBuf>, required_files: &[String]) -> Vec<PathBuf> {
+    let required: HashSet<String> = required_files
+        .iter()
+        .map(|f| normalize_filename(f))
+        .collect();
+    paths
+        .into_iter()
+        .filter(|path| {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                return false;
+            };
+            !required.contains(&normalize_filename(name))
+                && name
+                    .strip_suffix(".part")
+                    .is_none_or(|archive| !required.contains(&normalize_filename(archive)))
+        })
+        .collect()
+}
+
+/// Asks on stdin; anything but "y"/"yes" is a no.
+pub fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// A path in `trash` for `path` that neither a file nor its `.meta` is
+/// using yet: the same name, or with ` (2)`, ` (3)`, ... before the
+/// extension, so pruning a same-named archive twice keeps both copies.
+fn trash_destination(path: &Path, trash: &Path) -> std::io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::other("path has no file name"))?;
+    let is_free = |candidate: &Path| !candidate.exists() && !meta_path(candidate).exists();
+    let destination = trash.join(file_name);
+    if is_free(&destination) {
+        return Ok(destination);
+    }
+    let stem = path.file_stem().unwrap_or(file_name).to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| trash.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| is_free(candidate))
+        .ok_or_else(|| std::io::Error::other("no free name in the trash"))
+}
+
+/// Moves `path` to `destination`, falling back to copy-then-delete across
+/// filesystems.
+fn move_file(path: &Path, destination: &Path) -> std::io::Result<()> {
+    if std::fs::rename(path, destination).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(path, destination)?;
+    std::fs::remove_file(path)
+}
+
+/// Discards each of `files` (and its `.meta`, unless `keep_meta`): moves
+/// them into `trash` under a name nothing there uses yet, or deletes them
+/// when `trash` is `None`. Returns the files that couldn't be removed,
+/// with why.
+pub fn prune(files: &[PathBuf], trash: Option<&Path>, keep_meta: bool) -> Vec<(PathBuf, String)> {
+    let mut failed = Vec::new();
+    for path in files {
+        let destination = match trash
+            .map(|trash| trash_destination(path, trash))
+            .transpose()
+        {
+            Ok(destination) => destination,
+            Err(e) => {
+                failed.push((path.clone(), e.to_string()));
+                continue;
+            }
+        };
+        let discard = |path: &Path, destination: Option<&Path>| match destination {
+            Some(destination) => move_file(path, destination),
+            None => std::fs::remove_file(path),
+        };
+        if let Err(e) = discard(path, destination.as_deref()) {
+            failed.push((path.clone(), e.to_string()));
+            continue;
+        }
+        let meta = meta_path(path);
+        if !keep_meta
+            && meta.is_file()
+            && let Err(e) = discard(&meta, destination.as_deref().map(meta_path).as_deref())
+        {
+            failed.push((meta, e.to_string()));
+        }
+        log::debug!("Pruned {}", path.display());
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download_dir::DownloadDirectory;
+    use crate::sync_cache::CACHE_FILENAME;
+    use crate::verify::MANIFEST_FILENAME;
+
+    #[test]
+    fn pruning_a_name_already_in_the_trash_keeps_both() {
+        let dir = tempfile::tempdir().unwrap();
+        let trash = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Mod.7z");
+        for version in [b"version one", b"version two"] {
+            std::fs::write(&path, version).unwrap();
+            std::fs::write(meta_path(&path), version).unwrap();
+            assert!(prune(std::slice::from_ref(&path), Some(trash.path()), false).is_empty());
+        }
+
+        for (name, contents) in [("Mod.7z", "version one"), ("Mod (2).7z", "version two")] {
+            let trashed = trash.path().join(name);
+            assert_eq!(std::fs::read_to_string(&trashed).unwrap(), contents);
+            assert_eq!(
+                std::fs::read_to_string(meta_path(&trashed)).unwrap(),
+                contents
+            );
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn leaves_the_hash_cache_and_verify_manifest_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "Needed.7z",
+            "Needed2.7z.part",
+            "Stale.7z",
+            "Stale2.7z.part",
+            CACHE_FILENAME,
+            MANIFEST_FILENAME,
+        ] {
+            std::fs::write(dir.path().join(name), b"x").unwrap();
+        }
+        let directory = DownloadDirectory::new(&dir.path().to_path_buf()).unwrap();
+        let required = vec!["Needed.7z".to_string(), "Needed2.7z".to_string()];
+
+        let mut to_prune = prunable(directory.file_paths(), &required);
+        to_prune.sort();
+        assert_eq!(
+            to_prune,
+            vec![
+                dir.path().join("Stale.7z"),
+                dir.path().join("Stale2.7z.part")
+            ]
+        );
+
+        assert!(prune(&to_prune, None, false).is_empty());
+        assert!(dir.path().join(CACHE_FILENAME).is_file());
+        assert!(dir.path().join(MANIFEST_FILENAME).is_file());
+        assert!(dir.path().join("Needed.7z").is_file());
+    }
+}
@@ -0,0 +1,221 @@
+//! `publish`: uploads a `.wabbajack` file, then every archive it needs that
+//! the server doesn't have yet, taken from the download directories.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use futures_util::{StreamExt, stream};
+use wabba_protocol::api::ArchiveKind;
+use wabba_protocol::filename::normalize_filename;
+use wabba_protocol::hash::Hash;
+use wabba_protocol::wabbajack::{Archive, WabbajackMetadata};
+
+use crate::api_client::{ServerClient, UploadOutcome};
+use crate::download_dir::DownloadDirectory;
+use crate::report::{TransferReport, format_size};
+use crate::upload_dir::CHECK_BATCH_SIZE;
+
+/// The hashes of `files` (as `(hash, size)`) the server already has a file
+/// of that size for.
+async fn available_on_server(
+    server: &ServerClient,
+    kind: ArchiveKind,
+    files: &[(String, u64)],
+) -> Result<HashSet<String>, reqwest::Error> {
+    let mut available = HashSet::new();
+    for batch in files.chunks(CHECK_BATCH_SIZE) {
+        available.extend(server.check_files(kind, batch).await?.available);
+    }
+    Ok(available)
+}
+
+fn record_upload(
+    report: &mut TransferReport,
+    filename: &str,
+    outcome: Result<UploadOutcome, Box<dyn std::error::Error>>,
+) {
+    match outcome {
+        Ok(UploadOutcome::Uploaded(result)) => {
+            log::info!("Uploaded {} as {}", filename, result.filename);
+            report.transferred(filename, result.size);
+        }
+        Ok(UploadOutcome::AlreadyPresent) => {
+            log::info!("Server reported {} already present", filename);
+            report.skipped(filename);
+        }
+        Ok(UploadOutcome::Failed(code, body)) => {
+            log::error!("Upload of {} failed: {} — {}", filename, code, body);
+            report.failed(filename, format!("{}: {}", code, body));
+        }
+        Err(e) => {
+            log::error!("Upload error for {}: {}", filename, e);
+            report.failed(filename, e);
+        }
+    }
+}
+
+/// Uploads `wabbajack_file` unless the server has it, then looks up each of
+/// the required archives of `metadata` the server lacks in `download_dirs`
+/// (earlier directories win, as in `validate`) and uploads the ones found,
+/// `transfers` at a time. The modlist's own hashes are sent along, so the
+/// server rejects a file that has the right name but other contents.
+///
+/// Returns the report and the required archives the server is still
+/// missing afterwards.
+pub async fn publish<'a>(
+    server: &ServerClient,
+    wabbajack_file: &Path,
+    metadata: &'a WabbajackMetadata,
+    download_dirs: &[PathBuf],
+    transfers: usize,
+    dry_run: bool,
+) -> (TransferReport, Vec<&'a Archive>) {
+    let mut report = TransferReport::new("publish");
+    let required = metadata.required_archives();
+
+    let modlist_name = wabbajack_file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("<unknown>")
+        .to_string();
+    log::info!("Hashing {}", modlist_name);
+    let path = wabbajack_file.to_path_buf();
+    let hashed = tokio::task::spawn_blocking(move || {
+        Ok::<_, std::io::Error>((Hash::compute_file(&path)?, std::fs::metadata(&path)?.len()))
+    })
+    .await
+    .expect("blocking hash task panicked");
+    match hashed {
+        Ok((hash, size)) => match server
+            .check_files(ArchiveKind::Modlist, &[(hash.clone(), size)])
+            .await
+        {
+            Ok(check) if check.available.contains(&hash) => {
+                log::info!("Server already has {}", modlist_name);
+                report.skipped(&modlist_name);
+            }
+            Ok(_) if dry_run => println!("Would upload {}", modlist_name),
+            Ok(_) => {
+                log::info!("Uploading {}", modlist_name);
+                let outcome = server.submit(wabbajack_file, &hash).await;
+                record_upload(&mut report, &modlist_name, outcome);
+            }
+            Err(e) => {
+                log::error!("Hash check failed: {}", e);
+                report.failed(&modlist_name, format!("hash check failed: {}", e));
+            }
+        },
+        Err(e) => {
+            log::error!("Failed to hash {}: {}", modlist_name, e);
+            report.failed(&modlist_name, format!("hash: {}", e));
+        }
+    }
+
+    let files: Vec<(String, u64)> = required
+        .iter()
+        .map(|archive| (archive.hash.clone(), archive.size))
+        .collect();
+    let available = match available_on_server(server, ArchiveKind::Mod, &files).await {
+        Ok(available) => available,
+        Err(e) => {
+            log::error!("Hash check failed: {}", e);
+            return (report, required);
+        }
+    };
+
+    let mut paths: HashMap<String, PathBuf> = HashMap::new();
+    for dir in download_dirs {
+        if !dir.is_dir() {
+            log::warn!("{} is not a directory, skipping it", dir.display());
+            continue;
+        }
+        let Ok(download_directory) = DownloadDirectory::new(dir) else {
+            continue;
+        };
+        for path in download_directory.file_paths() {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                paths.entry(normalize_filename(name)).or_insert(path);
+            }
+        }
+    }
+
+    // A modlist can list the same archive under several names; send it once.
+    let mut seen = HashSet::new();
+    let mut needed: Vec<(&Archive, PathBuf)> = Vec::new();
+    let mut not_found = 0usize;
+    for archive in &required {
+        if available.contains(&archive.hash) || !seen.insert(archive.hash.as_str()) {
+            report.skipped(&archive.filename);
+            continue;
+        }
+        match paths.get(&normalize_filename(&archive.filename)) {
+            Some(path) if std::fs::metadata(path).is_ok_and(|m| m.len() == archive.size) => {
+                needed.push((*archive, path.clone()));
+            }
+            Some(path) => {
+                log::warn!(
+                    "{} is not the size the modlist lists, so it is not uploaded",
+                    path.display()
+                );
+                not_found += 1;
+            }
+            None => not_found += 1,
+        }
+    }
+    log::info!(
+        "{} of {} required archives are new to the server: {} found in the download directories ({}), {} not",
+        needed.len() + not_found,
+        required.len(),
+        needed.len(),
+        format_size(needed.iter().map(|(archive, _)| archive.size).sum()),
+        not_found
+    );
+
+    if dry_run {
+        for (archive, path) in &needed {
+            println!(
+                "Would upload {} ({})",
+                path.display(),
+                format_size(archive.size)
+            );
+        }
+        let uploadable: HashSet<&str> = needed
+            .iter()
+            .map(|(archive, _)| archive.hash.as_str())
+            .collect();
+        let still_missing = required
+            .into_iter()
+            .filter(|archive| {
+                !available.contains(&archive.hash) && !uploadable.contains(archive.hash.as_str())
+            })
+            .collect();
+        return (report, still_missing);
+    }
+
+    let count = needed.len();
+    let mut uploads = stream::iter(needed.into_iter().enumerate())
+        .map(|(idx, (archive, path))| async move {
+            log::info!("[{}/{}] Uploading {}", idx + 1, count, archive.filename);
+            let outcome = server.submit(&path, &archive.hash).await;
+            (archive, outcome)
+        })
+        .buffer_unordered(transfers.max(1));
+    while let Some((archive, outcome)) = uploads.next().await {
+        record_upload(&mut report, &archive.filename, outcome);
+    }
+
+    // Ask again rather than trusting the uploads, so the summary says what
+    // the server actually has now.
+    let available = match available_on_server(server, ArchiveKind::Mod, &files).await {
+        Ok(available) => available,
+        Err(e) => {
+            log::warn!("Failed to check the server again: {}", e);
+            available
+        }
+    };
+    let still_missing = required
+        .into_iter()
+        .filter(|archive| !available.contains(&archive.hash))
+        .collect();
+    (report, still_missing)
+}
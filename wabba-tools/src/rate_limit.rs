@@ -0,0 +1,61 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Caps the combined rate of every upload sharing it, so parallel `sync`
+/// transfers together stay under `--limit-rate`.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bytes_per_second: u64,
+    /// When the bytes reserved so far will have been "paid for".
+    next_free: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: u64) -> Self {
+        RateLimiter {
+            bytes_per_second,
+            next_free: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Waits until `bytes` more can be sent without exceeding the limit.
+    /// Call before sending each chunk.
+    pub async fn throttle(&self, bytes: usize) {
+        let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64);
+        let wait_until = {
+            let mut next_free = self.next_free.lock().unwrap();
+            let start = (*next_free).max(Instant::now());
+            *next_free = start + cost;
+            start
+        };
+        tokio::time::sleep_until(wait_until).await;
+    }
+}
+
+/// Parses a rate in bytes per second like `10MiB`, `500K` or `1048576`.
+/// Units are binary whether or not they're spelled with an `i`.
+pub fn parse_rate(input: &str) -> Result<u64, String> {
+    let input = input.trim().to_uppercase();
+    let input = input.strip_suffix("/S").unwrap_or(&input);
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a rate like 10MiB", input))?;
+    let multiplier = match unit.trim() {
+        "" | "B" => 1u64,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        other => return Err(format!("unknown unit '{}'", other)),
+    };
+    let rate = (number * multiplier as f64) as u64;
+    if rate == 0 {
+        return Err("the rate must be above zero".to_string());
+    }
+    Ok(rate)
+}
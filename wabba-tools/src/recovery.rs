@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use wabba_protocol::filename::{normalize_filename, safe_join};
+use wabba_protocol::{hash::Hash, wabbajack::Archive};
+
+use crate::download_dir::DownloadDirectory;
+
+/// `path` with `.meta` appended, e.g. `Mod.7z` -> `Mod.7z.meta`.
+pub fn meta_path(path: &Path) -> PathBuf {
+    let mut meta = path.as_os_str().to_os_string();
+    meta.push(".meta");
+    PathBuf::from(meta)
+}
+
+/// Hard links `source` to `destination`, copying instead when they are on
+/// different filesystems (or the filesystem can't link).
+fn link_or_copy(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if std::fs::hard_link(source, destination).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(source, destination).map(|_| ())
+}
+
+/// Looks for each archive missing from `paths` in `recovery_dirs`, first by
+/// filename and then by hash, and links or copies it (along with its `.meta`
+/// file, if any) into `destination` under the name the modlist expects.
+/// Recovered archives are added to `paths`; their filenames are returned.
+/// With `dry_run`, each recovery is only printed and `paths` is untouched.
+///
+/// A file found by name must also have the archive's size, and with
+/// `verify_hashes` its hash, so an older version under the same name isn't
+/// taken for it. Only files whose size matches a missing archive are
+/// hashed, so searching a large backup directory stays cheap.
+pub fn recover_missing(
+    archives: &[&Archive],
+    paths: &mut HashMap<String, PathBuf>,
+    recovery_dirs: &[PathBuf],
+    destination: &Path,
+    verify_hashes: bool,
+    dry_run: bool,
+) -> Vec<String> {
+    let missing: Vec<&Archive> = archives
+        .iter()
+        .copied()
+        .filter(|archive| !paths.contains_key(&normalize_filename(&archive.filename)))
+        .collect();
+    if missing.is_empty() {
+        return Vec::new();
+    }
+
+    let mut by_name: HashMap<String, PathBuf> = HashMap::new();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for dir in recovery_dirs {
+        if !dir.is_dir() {
+            log::warn!("{} is not a directory, skipping it", dir.display());
+            continue;
+        }
+        let Ok(directory) = DownloadDirectory::new(dir) else {
+            continue;
+        };
+        for path in directory.file_paths() {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                by_name
+                    .entry(normalize_filename(name))
+                    .or_insert(path.clone());
+            }
+            if let Ok(metadata) = std::fs::metadata(&path) {
+                by_size.entry(metadata.len()).or_default().push(path);
+            }
+        }
+    }
+
+    let mut hashes: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let mut recovered = Vec::new();
+    for archive in missing {
+        let target = match safe_join(destination, &archive.filename) {
+            Ok(target) => target,
+            Err(e) => {
+                log::warn!("Not recovering {}: {}", archive.filename, e);
+                continue;
+            }
+        };
+        let mut hash_matches = |path: &PathBuf| {
+            let hash = hashes
+                .entry(path.clone())
+                .or_insert_with(|| Hash::compute_file(path).ok());
+            hash.as_deref() == Some(archive.hash.as_str())
+        };
+        let named = by_name
+            .get(&normalize_filename(&archive.filename))
+            .filter(|path| {
+                let size = std::fs::metadata(path).map(|metadata| metadata.len()).ok();
+                if size != Some(archive.size) {
+                    log::warn!(
+                        "{} has the name of {} but not its size, looking for it by hash",
+                        path.display(),
+                        archive.filename
+                    );
+                    return false;
+                }
+                !verify_hashes || hash_matches(path)
+            })
+            .cloned();
+        let source = named.or_else(|| {
+            by_size
+                .get(&archive.size)?
+                .iter()
+                .find(|path| hash_matches(path))
+                .cloned()
+        });
+        let Some(source) = source else {
+            log::info!("{} not found in any recovery directory", archive.filename);
+            continue;
+        };
+
+        if dry_run {
+            println!(
+                "Would recover {} from {}",
+                archive.filename,
+                source.display()
+            );
+            recovered.push(archive.filename.clone());
+            continue;
+        }
+        if let Err(e) = link_or_copy(&source, &target) {
+            log::error!(
+                "Failed to recover {} from {}: {}",
+                archive.filename,
+                source.display(),
+                e
+            );
+            continue;
+        }
+        log::info!("Recovered {} from {}", archive.filename, source.display());
+
+        let source_meta = meta_path(&source);
+        let target_meta = meta_path(&target);
+        if source_meta.exists()
+            && !target_meta.exists()
+            && let Err(e) = link_or_copy(&source_meta, &target_meta)
+        {
+            log::warn!("Failed to recover .meta for {}: {}", archive.filename, e);
+        }
+
+        paths.insert(normalize_filename(&archive.filename), target);
+        recovered.push(archive.filename.clone());
+    }
+    recovered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wabba_protocol::test_util::TestArchive;
+
+    fn archive(test: &TestArchive) -> Archive {
+        Archive {
+            hash: test.hash(),
+            meta: test.meta.clone(),
+            filename: test.filename.clone(),
+            size: test.size(),
+            state: test.state.clone(),
+        }
+    }
+
+    #[test]
+    fn meta_path_appends_to_the_full_name() {
+        assert_eq!(
+            meta_path(Path::new("downloads/Mod-1.0.7z")),
+            PathBuf::from("downloads/Mod-1.0.7z.meta")
+        );
+    }
+
+    #[test]
+    fn recovers_by_decomposed_name_and_by_hash() {
+        let backup = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let by_name = TestArchive::new("Caf\u{e9}.7z", b"cafe archive");
+        let by_hash = TestArchive::new("Renamed.7z", b"renamed archive");
+        let lost = TestArchive::new("Lost.7z", b"nowhere to be found");
+        std::fs::write(backup.path().join("Cafe\u{301}.7z"), &by_name.contents).unwrap();
+        std::fs::write(backup.path().join("Cafe\u{301}.7z.meta"), "[General]\n").unwrap();
+        std::fs::write(backup.path().join("Old name.7z"), &by_hash.contents).unwrap();
+        let archives = [archive(&by_name), archive(&by_hash), archive(&lost)];
+        let archives: Vec<&Archive> = archives.iter().collect();
+
+        let mut paths = HashMap::new();
+        let mut recovered = recover_missing(
+            &archives,
+            &mut paths,
+            &[backup.path().to_path_buf()],
+            destination.path(),
+            false,
+            false,
+        );
+        recovered.sort();
+
+        assert_eq!(recovered, vec!["Café.7z", "Renamed.7z"]);
+        assert!(!paths.contains_key("Lost.7z"));
+        let target = &paths[&normalize_filename("Café.7z")];
+        assert_eq!(std::fs::read(target).unwrap(), by_name.contents);
+        assert!(meta_path(target).exists());
+        assert_eq!(
+            std::fs::read(destination.path().join("Renamed.7z")).unwrap(),
+            by_hash.contents
+        );
+    }
+
+    #[test]
+    fn skips_archives_already_present_and_dry_runs_touch_nothing() {
+        let backup = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let present = TestArchive::new("Present.7z", b"already here");
+        let missing = TestArchive::new("Missing.7z", b"in the backup");
+        std::fs::write(backup.path().join("Missing.7z"), &missing.contents).unwrap();
+        let archives = [archive(&present), archive(&missing)];
+        let archives: Vec<&Archive> = archives.iter().collect();
+        let mut paths = HashMap::from([(
+            "Present.7z".to_string(),
+            destination.path().join("Present.7z"),
+        )]);
+
+        let recovered = recover_missing(
+            &archives,
+            &mut paths,
+            &[backup.path().to_path_buf()],
+            destination.path(),
+            false,
+            true,
+        );
+
+        assert_eq!(recovered, vec!["Missing.7z"]);
+        assert_eq!(paths.len(), 1);
+        assert!(!destination.path().join("Missing.7z").exists());
+    }
+
+    #[test]
+    fn a_name_match_needs_the_right_size_and_with_verification_hash() {
+        let backup = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let resized = TestArchive::new("Resized.7z", b"version two");
+        let rehashed = TestArchive::new("Rehashed.7z", b"version two");
+        std::fs::write(backup.path().join("Resized.7z"), b"version one, longer").unwrap();
+        std::fs::write(backup.path().join("Rehashed.7z"), b"version one").unwrap();
+        let archives = [archive(&resized), archive(&rehashed)];
+        let archives: Vec<&Archive> = archives.iter().collect();
+        let recover = |verify_hashes| {
+            recover_missing(
+                &archives,
+                &mut HashMap::new(),
+                &[backup.path().to_path_buf()],
+                destination.path(),
+                verify_hashes,
+                true,
+            )
+        };
+
+        assert_eq!(recover(false), vec!["Rehashed.7z"]);
+        assert!(recover(true).is_empty());
+    }
+
+    #[test]
+    fn skips_recovery_dirs_that_dont_exist() {
+        let root = tempfile::tempdir().unwrap();
+        let missing = TestArchive::new("Missing.7z", b"in the backup");
+        let archives = [archive(&missing)];
+        let archives: Vec<&Archive> = archives.iter().collect();
+
+        let recovered = recover_missing(
+            &archives,
+            &mut HashMap::new(),
+            &[root.path().join("typo")],
+            root.path(),
+            false,
+            false,
+        );
+
+        assert!(recovered.is_empty());
+    }
+
+    #[test]
+    fn never_recovers_outside_the_destination() {
+        let backup = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let destination = root.path().join("downloads");
+        std::fs::create_dir(&destination).unwrap();
+        let escaping = TestArchive::new("../Escaped.7z", b"matched by hash");
+        std::fs::write(backup.path().join("Escaped.7z"), &escaping.contents).unwrap();
+        let archives = [archive(&escaping)];
+        let archives: Vec<&Archive> = archives.iter().collect();
+
+        let mut paths = HashMap::new();
+        let recovered = recover_missing(
+            &archives,
+            &mut paths,
+            &[backup.path().to_path_buf()],
+            &destination,
+            false,
+            false,
+        );
+
+        assert!(recovered.is_empty());
+        assert!(paths.is_empty());
+        assert!(!root.path().join("Escaped.7z").exists());
+    }
+}
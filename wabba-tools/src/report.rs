@@ -0,0 +1,328 @@
+//! Machine-readable output: `validate --format json|table` and the summary
+//! batch commands print when they finish.
+
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::notify::desktop_notification;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Log the results (the default)
+    Log,
+    /// Print one JSON document to stdout
+    Json,
+    /// Print a plain-text table to stdout
+    Table,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ArchiveEntry {
+    pub filename: String,
+    pub size: u64,
+    pub hash: String,
+    pub source_type: String,
+    /// Where the archive can be downloaded by hand, if the source has a
+    /// page for it.
+    pub source_url: Option<String>,
+    /// What to look for on `source_url`, for archives that must be
+    /// downloaded by hand.
+    pub prompt: Option<String>,
+    /// The download directory that holds the file, if any.
+    pub directory: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExtraneousFile {
+    pub filename: String,
+    pub size: u64,
+    pub directory: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ValidationReport {
+    pub missing: Vec<ArchiveEntry>,
+    pub satisfied: Vec<ArchiveEntry>,
+    pub extraneous: Vec<ExtraneousFile>,
+    /// Present files whose size or hash is wrong. Only filled in with
+    /// `--verify-hashes`; these are also listed under `satisfied`.
+    pub mismatched: Vec<ArchiveEntry>,
+}
+
+impl ValidationReport {
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Log => {}
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(self).expect("Failed to serialize report")
+            ),
+            OutputFormat::Table => self.print_table(),
+        }
+    }
+
+    fn print_table(&self) {
+        let mut rows: Vec<[String; 6]> = vec![[
+            "STATUS".to_string(),
+            "FILENAME".to_string(),
+            "SIZE".to_string(),
+            "HASH".to_string(),
+            "SOURCE".to_string(),
+            "DIRECTORY".to_string(),
+        ]];
+        let archive_rows = [
+            ("missing", &self.missing),
+            ("satisfied", &self.satisfied),
+            ("mismatched", &self.mismatched),
+        ];
+        for (status, entries) in archive_rows {
+            for entry in entries {
+                rows.push([
+                    status.to_string(),
+                    entry.filename.clone(),
+                    entry.size.to_string(),
+                    entry.hash.clone(),
+                    entry.source_type.clone(),
+                    entry.directory.clone().unwrap_or_default(),
+                ]);
+            }
+        }
+        for file in &self.extraneous {
+            rows.push([
+                "extraneous".to_string(),
+                file.filename.clone(),
+                file.size.to_string(),
+                String::new(),
+                String::new(),
+                file.directory.clone().unwrap_or_default(),
+            ]);
+        }
+
+        print_table(&rows);
+    }
+
+    /// Writes the missing archives to `path` as a list to hand to someone
+    /// who might have them: CSV for a `.csv` path, a Markdown table for
+    /// `.md`.
+    pub fn write_missing(&self, path: &Path) {
+        let header = [
+            "filename",
+            "size",
+            "hash",
+            "source type",
+            "source url",
+            "prompt",
+        ];
+        let rows: Vec<[String; 6]> = self
+            .missing
+            .iter()
+            .map(|entry| {
+                [
+                    entry.filename.clone(),
+                    entry.size.to_string(),
+                    entry.hash.clone(),
+                    entry.source_type.clone(),
+                    entry.source_url.clone().unwrap_or_default(),
+                    entry.prompt.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        let contents = match extension.as_deref() {
+            Some("csv") => {
+                let line = |fields: &[String]| {
+                    fields
+                        .iter()
+                        .map(|field| csv_field(field))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                };
+                std::iter::once(line(&header.map(str::to_string)))
+                    .chain(rows.iter().map(|row| line(row)))
+                    .map(|line| line + "\r\n")
+                    .collect::<String>()
+            }
+            Some("md") | Some("markdown") => {
+                let line = |fields: &[String]| {
+                    format!(
+                        "| {} |\n",
+                        fields
+                            .iter()
+                            .map(|field| field.replace('|', "\\|"))
+                            .collect::<Vec<_>>()
+                            .join(" | ")
+                    )
+                };
+                let mut table = line(&header.map(str::to_string));
+                table.push_str(&line(&header.map(|_| "---".to_string())));
+                for row in &rows {
+                    table.push_str(&line(row));
+                }
+                table
+            }
+            _ => {
+                log::error!(
+                    "Don't know how to write a report to {}, use a .csv or .md file",
+                    path.display()
+                );
+                return;
+            }
+        };
+        match std::fs::write(path, contents) {
+            Ok(()) => log::info!(
+                "Wrote {} missing archives to {}",
+                rows.len(),
+                path.display()
+            ),
+            Err(e) => log::error!("Failed to write report to {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// `field` quoted for CSV when it needs to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `bytes` in the largest unit that keeps it above 1.
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Prints `rows` (the first being the header) as left-aligned columns.
+pub fn print_table<const N: usize>(rows: &[[String; N]]) {
+    let mut widths = [0usize; N];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TransferredFile {
+    pub filename: String,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FailedFile {
+    pub filename: String,
+    pub reason: String,
+}
+
+/// What a batch command (`sync`, `download`, `mirror`) did, printed as a
+/// summary at the end and optionally written as JSON with `--report-file`.
+#[derive(Serialize, Debug)]
+pub struct TransferReport {
+    pub command: String,
+    pub transferred: Vec<TransferredFile>,
+    /// Files the other side already had, or that had nothing to transfer.
+    pub skipped: Vec<String>,
+    pub failed: Vec<FailedFile>,
+    pub bytes_transferred: u64,
+    pub elapsed_secs: f64,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl TransferReport {
+    pub fn new(command: &str) -> Self {
+        TransferReport {
+            command: command.to_string(),
+            transferred: Vec::new(),
+            skipped: Vec::new(),
+            failed: Vec::new(),
+            bytes_transferred: 0,
+            elapsed_secs: 0.0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn transferred(&mut self, filename: &str, bytes: u64) {
+        self.bytes_transferred += bytes;
+        self.transferred.push(TransferredFile {
+            filename: filename.to_string(),
+            bytes,
+        });
+    }
+
+    pub fn skipped(&mut self, filename: &str) {
+        self.skipped.push(filename.to_string());
+    }
+
+    pub fn failed(&mut self, filename: &str, reason: impl ToString) {
+        self.failed.push(FailedFile {
+            filename: filename.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Stops the clock, prints the summary, writes `report_file` if one
+    /// was given and, with `notify`, shows a desktop notification.
+    pub fn finish(mut self, report_file: Option<&Path>, notify: bool) {
+        self.elapsed_secs = self.started.elapsed().as_secs_f64();
+
+        println!("{:<12}{}", "Transferred", self.transferred.len());
+        println!("{:<12}{}", "Skipped", self.skipped.len());
+        println!("{:<12}{}", "Failed", self.failed.len());
+        println!(
+            "{:<12}{:.2} MB",
+            "Bytes",
+            self.bytes_transferred as f64 / (1024.0 * 1024.0)
+        );
+        println!("{:<12}{:.1}s", "Elapsed", self.elapsed_secs);
+        for failure in &self.failed {
+            println!("  {}: {}", failure.filename, failure.reason);
+        }
+
+        if notify {
+            desktop_notification(
+                &format!("wabba-tools {} finished", self.command),
+                &format!(
+                    "{} transferred, {} skipped, {} failed",
+                    self.transferred.len(),
+                    self.skipped.len(),
+                    self.failed.len()
+                ),
+            );
+        }
+
+        if let Some(path) = report_file {
+            let json = serde_json::to_string_pretty(&self).expect("Failed to serialize report");
+            match std::fs::write(path, json) {
+                Ok(()) => log::info!("Wrote report to {}", path.display()),
+                Err(e) => log::error!("Failed to write report to {}: {}", path.display(), e),
+            }
+        }
+    }
+}
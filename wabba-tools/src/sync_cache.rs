@@ -6,6 +6,8 @@ use std::time::UNIX_EPOCH;
 
 pub const CACHE_FILENAME: &str = ".wabba-sync-cache.json";
 
+/// Hashes of a directory's files keyed by name, size and mtime. Kept by
+/// `sync` and reused by `validate --verify-hashes`.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct SyncCache {
     entries: HashMap<String, CacheEntry>,
@@ -0,0 +1,1249 @@
+//! `tui`: a full-screen view of modlists' archives in a set of download
+//! directories. Every archive shows whether it is present, missing or
+//! corrupt and whether the server has it, and can be recovered, uploaded
+//! or downloaded on its own, with transfers shown as they run.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use futures_util::stream::FuturesUnordered;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{
+    Block, Cell, LineGauge, List, ListItem, ListState, Paragraph, Row, Table, TableState,
+};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::{Semaphore, mpsc};
+use wabba_protocol::api::ArchiveKind;
+use wabba_protocol::filename::{normalize_filename, safe_join};
+use wabba_protocol::hash::Hash;
+use wabba_protocol::wabbajack::{Archive, WabbajackMetadata};
+
+use crate::api_client::{ClientOptions, ServerClient, UploadOutcome};
+use crate::download_dir::DownloadDirectory;
+use crate::recovery::{meta_path, recover_missing};
+use crate::report::format_size;
+use crate::sync_cache::{SyncCache, file_fingerprint};
+use crate::upload_dir::CHECK_BATCH_SIZE;
+
+/// How often the screen is redrawn when nothing else happens, so transfer
+/// progress keeps moving.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Log lines kept for the log pane.
+const LOG_LINES: usize = 200;
+
+/// Rows in the transfer pane. Running transfers come first, then queued
+/// ones, then the most recently finished.
+const TRANSFER_ROWS: usize = 6;
+
+/// Rows PageUp and PageDown move by.
+const PAGE: isize = 20;
+
+const HELP: &str = "q quit · tab switch pane · f filter · v/V verify · r/R recover · \
+                    d/D download · u/U upload (lowercase: selected archive, uppercase: every \
+                    archive shown)";
+
+/// Where log records go. While the interface owns the terminal they are
+/// kept for the log pane instead of being written to stderr.
+#[derive(Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capturing: Arc<AtomicBool>,
+}
+
+impl LogBuffer {
+    fn last(&self, count: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if !self.capturing.load(Ordering::Relaxed) {
+            return std::io::stderr().write(buf);
+        }
+        let mut lines = self.lines.lock().unwrap();
+        for line in String::from_utf8_lossy(buf).lines() {
+            if lines.len() == LOG_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+pub struct TuiOptions {
+    pub wabbajack_files: Vec<PathBuf>,
+    /// Earlier directories win, like `validate`. The first one receives
+    /// downloads and recoveries.
+    pub download_dirs: Vec<PathBuf>,
+    pub server: Option<String>,
+    pub fix_from: Vec<PathBuf>,
+    pub parallel: usize,
+    pub transfers: usize,
+    pub limit_rate: Option<u64>,
+    pub client: ClientOptions,
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Missing,
+    /// On disk with the right size; the hash hasn't been checked.
+    Present,
+    Hashing,
+    Verified,
+    /// The wrong size or hash.
+    Corrupt,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Missing => "missing",
+            Status::Present => "present",
+            Status::Hashing => "hashing",
+            Status::Verified => "ok",
+            Status::Corrupt => "corrupt",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Status::Missing | Status::Corrupt => Color::Red,
+            Status::Present => Color::Reset,
+            Status::Hashing => Color::Yellow,
+            Status::Verified => Color::Green,
+        }
+    }
+}
+
+/// Normalized filename and hash. Modlists that share an archive share its
+/// state.
+type ArchiveKey = (String, String);
+
+fn archive_key(archive: &Archive) -> ArchiveKey {
+    (normalize_filename(&archive.filename), archive.hash.clone())
+}
+
+struct FileState {
+    path: Option<PathBuf>,
+    status: Status,
+    /// Unknown without a server.
+    on_server: Option<bool>,
+}
+
+struct ModlistView {
+    name: String,
+    archives: Vec<(ArchiveKey, Archive)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    All,
+    Problems,
+    Missing,
+    Corrupt,
+}
+
+impl Filter {
+    fn next(self) -> Self {
+        match self {
+            Filter::All => Filter::Problems,
+            Filter::Problems => Filter::Missing,
+            Filter::Missing => Filter::Corrupt,
+            Filter::Corrupt => Filter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Filter::All => "all archives",
+            Filter::Problems => "missing or corrupt",
+            Filter::Missing => "missing",
+            Filter::Corrupt => "corrupt",
+        }
+    }
+
+    fn matches(self, status: Status) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Problems => matches!(status, Status::Missing | Status::Corrupt),
+            Filter::Missing => status == Status::Missing,
+            Filter::Corrupt => status == Status::Corrupt,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Modlists,
+    Archives,
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Upload,
+    Download,
+}
+
+enum TransferState {
+    Queued,
+    Running,
+    Done,
+    Failed(String),
+}
+
+struct Transfer {
+    direction: Direction,
+    filename: String,
+    size: u64,
+    /// Bytes sent so far, for uploads.
+    sent: Arc<AtomicU64>,
+    /// The `.part` file a download grows into, for downloads.
+    part: Option<PathBuf>,
+    state: TransferState,
+}
+
+impl Transfer {
+    fn is_active(&self) -> bool {
+        matches!(self.state, TransferState::Queued | TransferState::Running)
+    }
+
+    fn progress(&self) -> u64 {
+        match (&self.state, &self.part) {
+            (TransferState::Done, _) => self.size,
+            (_, Some(part)) => std::fs::metadata(part).map_or(0, |m| m.len()),
+            (_, None) => self.sent.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// What the background tasks report back to the interface.
+enum Update {
+    Status(ArchiveKey, Status),
+    /// The archive is now at this path, after a recovery or a download.
+    Found(ArchiveKey, PathBuf, Status),
+    OnServer(ArchiveKey),
+    Transfer(usize, TransferState),
+}
+
+/// What the background tasks share. They all run on the interface's own
+/// task, so nothing here needs to be `Send`.
+struct Context {
+    server: Option<ServerClient>,
+    /// The first download directory.
+    destination: PathBuf,
+    fix_from: Vec<PathBuf>,
+    hashing: Semaphore,
+    transfers: Semaphore,
+    /// The hash caches `sync` and `validate` keep, by directory.
+    caches: RefCell<HashMap<PathBuf, SyncCache>>,
+    dry_run: bool,
+    updates: mpsc::UnboundedSender<Update>,
+}
+
+impl Context {
+    fn send(&self, update: Update) {
+        // Only fails once the interface has quit.
+        let _ = self.updates.send(update);
+    }
+
+    /// Hashes `path`, reusing the cached hash if the file hasn't changed
+    /// since it was last hashed.
+    async fn hash(&self, path: &Path) -> Result<String, String> {
+        let metadata = std::fs::metadata(path).map_err(|e| format!("stat: {}", e))?;
+        let (size, mtime_nanos) = file_fingerprint(&metadata);
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let filename = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let cached = self
+            .caches
+            .borrow_mut()
+            .entry(dir.clone())
+            .or_insert_with(|| SyncCache::load(&dir))
+            .lookup(&filename, size, mtime_nanos);
+        if let Some(hash) = cached {
+            return Ok(hash);
+        }
+
+        let _permit = self.hashing.acquire().await.expect("semaphore closed");
+        let owned = path.to_path_buf();
+        let hash = tokio::task::spawn_blocking(move || Hash::compute_file(&owned))
+            .await
+            .expect("blocking hash task panicked")
+            .map_err(|e| format!("hash: {}", e))?;
+        self.caches.borrow_mut().entry(dir).or_default().insert(
+            filename,
+            size,
+            mtime_nanos,
+            hash.clone(),
+        );
+        Ok(hash)
+    }
+
+    fn save_caches(&self) {
+        if self.dry_run {
+            return;
+        }
+        for (dir, cache) in self.caches.borrow().iter() {
+            if let Err(e) = cache.save(dir) {
+                log::warn!("Failed to save hash cache in {}: {}", dir.display(), e);
+            }
+        }
+    }
+}
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+async fn run_check(context: Rc<Context>, key: ArchiveKey, path: PathBuf) {
+    let status = match context.hash(&path).await {
+        Ok(hash) if hash == key.1 => Status::Verified,
+        Ok(_) => Status::Corrupt,
+        Err(e) => {
+            log::error!("Failed to verify {}: {}", path.display(), e);
+            Status::Present
+        }
+    };
+    context.send(Update::Status(key, status));
+}
+
+async fn run_recovery(context: Rc<Context>, archives: Vec<Archive>) {
+    let fix_from = context.fix_from.clone();
+    let destination = context.destination.clone();
+    let (archives, paths) = tokio::task::spawn_blocking(move || {
+        let mut paths = HashMap::new();
+        let missing: Vec<&Archive> = archives.iter().collect();
+        recover_missing(&missing, &mut paths, &fix_from, &destination, false, false);
+        (archives, paths)
+    })
+    .await
+    .expect("blocking recovery task panicked");
+    for archive in &archives {
+        if let Some(path) = paths.get(&normalize_filename(&archive.filename)) {
+            context.send(Update::Found(
+                archive_key(archive),
+                path.clone(),
+                Status::Present,
+            ));
+        }
+    }
+}
+
+async fn run_upload(
+    context: Rc<Context>,
+    id: usize,
+    sent: Arc<AtomicU64>,
+    key: ArchiveKey,
+    path: PathBuf,
+) {
+    let Some(server) = &context.server else {
+        return;
+    };
+    // The server files archives under the hash they are sent with, so a
+    // damaged copy must not go up under the one the modlist expects.
+    match context.hash(&path).await {
+        Ok(hash) if hash == key.1 => context.send(Update::Status(key.clone(), Status::Verified)),
+        Ok(_) => {
+            context.send(Update::Status(key, Status::Corrupt));
+            context.send(Update::Transfer(
+                id,
+                TransferState::Failed("corrupt".to_string()),
+            ));
+            return;
+        }
+        Err(e) => {
+            context.send(Update::Transfer(id, TransferState::Failed(e)));
+            return;
+        }
+    }
+
+    let _permit = context.transfers.acquire().await.expect("semaphore closed");
+    context.send(Update::Transfer(id, TransferState::Running));
+    log::info!("Uploading {}", path.display());
+    let state = match server.submit_tracked(&path, &key.1, Some(sent)).await {
+        Ok(UploadOutcome::Uploaded(_) | UploadOutcome::AlreadyPresent) => {
+            context.send(Update::OnServer(key));
+            TransferState::Done
+        }
+        Ok(UploadOutcome::Failed(status, message)) => {
+            log::error!(
+                "Upload of {} failed: {} {}",
+                path.display(),
+                status,
+                message
+            );
+            TransferState::Failed(format!("{}: {}", status, message))
+        }
+        Err(e) => {
+            log::error!("Upload of {} failed: {}", path.display(), e);
+            TransferState::Failed(e.to_string())
+        }
+    };
+    context.send(Update::Transfer(id, state));
+}
+
+async fn run_download(
+    context: Rc<Context>,
+    id: usize,
+    key: ArchiveKey,
+    archive: Archive,
+    dest: PathBuf,
+) {
+    let Some(server) = &context.server else {
+        return;
+    };
+    let _permit = context.transfers.acquire().await.expect("semaphore closed");
+    context.send(Update::Transfer(id, TransferState::Running));
+    log::info!("Downloading {}", archive.filename);
+    let state = match server
+        .download_mod(&archive.hash, archive.size, &dest)
+        .await
+    {
+        Ok(()) => {
+            // Lets Wabbajack recognize the download as its own.
+            if let Some(ini) = archive.meta_ini()
+                && let Err(e) = std::fs::write(meta_path(&dest), ini)
+            {
+                log::warn!("Failed to write .meta for {}: {}", archive.filename, e);
+            }
+            context.send(Update::Found(key, dest, Status::Verified));
+            TransferState::Done
+        }
+        Err(e) => {
+            log::error!("Download of {} failed: {}", archive.filename, e);
+            TransferState::Failed(e.to_string())
+        }
+    };
+    context.send(Update::Transfer(id, state));
+}
+
+struct App {
+    modlists: Vec<ModlistView>,
+    files: HashMap<ArchiveKey, FileState>,
+    transfers: Vec<Transfer>,
+    modlist_state: ListState,
+    archive_state: TableState,
+    focus: Focus,
+    filter: Filter,
+    log: LogBuffer,
+    context: Rc<Context>,
+    tasks: FuturesUnordered<Task>,
+    quit_warned: bool,
+}
+
+impl App {
+    fn archives(&self) -> &[(ArchiveKey, Archive)] {
+        self.modlist_state
+            .selected()
+            .and_then(|index| self.modlists.get(index))
+            .map_or(&[], |modlist| &modlist.archives)
+    }
+
+    /// The selected modlist's archives that pass the filter.
+    fn shown(&self) -> Vec<&(ArchiveKey, Archive)> {
+        self.archives()
+            .iter()
+            .filter(|(key, _)| self.filter.matches(self.files[key].status))
+            .collect()
+    }
+
+    fn selected(&self) -> Vec<(ArchiveKey, Archive)> {
+        self.archive_state
+            .selected()
+            .and_then(|index| self.shown().get(index).map(|entry| (*entry).clone()))
+            .into_iter()
+            .collect()
+    }
+
+    fn all_shown(&self) -> Vec<(ArchiveKey, Archive)> {
+        self.shown().into_iter().cloned().collect()
+    }
+
+    fn is_transferring(&self, archive: &Archive) -> bool {
+        self.transfers
+            .iter()
+            .any(|transfer| transfer.is_active() && transfer.filename == archive.filename)
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        updates: &mut mpsc::UnboundedReceiver<Update>,
+    ) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            while event::poll(Duration::ZERO)? {
+                if let Event::Key(key) = event::read()?
+                    && key.kind == KeyEventKind::Press
+                    && self.handle_key(key.code)
+                {
+                    return Ok(());
+                }
+            }
+            let update = tokio::select! {
+                update = updates.recv() => update,
+                Some(()) = self.tasks.next(), if !self.tasks.is_empty() => None,
+                _ = tokio::time::sleep(TICK) => None,
+            };
+            for update in update
+                .into_iter()
+                .chain(std::iter::from_fn(|| updates.try_recv().ok()))
+            {
+                self.apply(update);
+            }
+        }
+    }
+
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Status(key, status) => {
+                if let Some(file) = self.files.get_mut(&key) {
+                    file.status = status;
+                }
+            }
+            Update::Found(key, path, status) => {
+                if let Some(file) = self.files.get_mut(&key) {
+                    file.path = Some(path);
+                    file.status = status;
+                }
+            }
+            Update::OnServer(key) => {
+                if let Some(file) = self.files.get_mut(&key) {
+                    file.on_server = Some(true);
+                }
+            }
+            Update::Transfer(id, state) => self.transfers[id].state = state,
+        }
+    }
+
+    /// Returns whether to quit.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return self.confirm_quit(),
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Left | KeyCode::Right => {
+                self.focus = match self.focus {
+                    Focus::Modlists => Focus::Archives,
+                    Focus::Archives => Focus::Modlists,
+                };
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-PAGE),
+            KeyCode::PageDown => self.move_selection(PAGE),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            KeyCode::Char('f') => {
+                self.filter = self.filter.next();
+                self.archive_state.select(Some(0));
+            }
+            KeyCode::Char('v') => self.check(self.selected()),
+            KeyCode::Char('V') => self.check(self.all_shown()),
+            KeyCode::Char('r') => self.recover(self.selected()),
+            KeyCode::Char('R') => self.recover(self.all_shown()),
+            KeyCode::Char('d') => self.download(self.selected()),
+            KeyCode::Char('D') => self.download(self.all_shown()),
+            KeyCode::Char('u') => self.upload(self.selected()),
+            KeyCode::Char('U') => self.upload(self.all_shown()),
+            _ => {}
+        }
+        false
+    }
+
+    fn confirm_quit(&mut self) -> bool {
+        let active = self.transfers.iter().filter(|t| t.is_active()).count();
+        if active == 0 || self.quit_warned {
+            return true;
+        }
+        self.quit_warned = true;
+        log::warn!(
+            "{} transfers haven't finished; press q again to stop them. Interrupted downloads resume next time.",
+            active
+        );
+        false
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.focus {
+            Focus::Modlists => (self.modlist_state.selected(), self.modlists.len()),
+            Focus::Archives => (self.archive_state.selected(), self.shown().len()),
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.unwrap_or(0) as isize;
+        let next = current.saturating_add(delta).clamp(0, len as isize - 1) as usize;
+        match self.focus {
+            Focus::Modlists => {
+                self.modlist_state.select(Some(next));
+                self.archive_state.select(Some(0));
+            }
+            Focus::Archives => self.archive_state.select(Some(next)),
+        }
+    }
+
+    fn check(&mut self, archives: Vec<(ArchiveKey, Archive)>) {
+        let mut queued = 0;
+        for (key, _) in archives {
+            let file = self.files.get_mut(&key).expect("every archive has a state");
+            let Some(path) = file.path.clone() else {
+                continue;
+            };
+            if file.status == Status::Hashing {
+                continue;
+            }
+            file.status = Status::Hashing;
+            self.tasks
+                .push(Box::pin(run_check(Rc::clone(&self.context), key, path)));
+            queued += 1;
+        }
+        if queued == 0 {
+            log::warn!("Nothing to verify: no archive shown is on disk");
+        }
+    }
+
+    fn recover(&mut self, archives: Vec<(ArchiveKey, Archive)>) {
+        if self.context.fix_from.is_empty() {
+            log::warn!("Start with --fix-from to recover archives from other directories");
+            return;
+        }
+        let missing: Vec<Archive> = archives
+            .into_iter()
+            .filter(|(key, _)| self.files[key].status == Status::Missing)
+            .map(|(_, archive)| archive)
+            .collect();
+        if missing.is_empty() {
+            log::warn!("Nothing to recover: no archive shown is missing");
+            return;
+        }
+        if self.context.dry_run {
+            for archive in &missing {
+                log::info!(
+                    "Would look for {} in the recovery directories",
+                    archive.filename
+                );
+            }
+            return;
+        }
+        log::info!(
+            "Looking for {} archives in the recovery directories",
+            missing.len()
+        );
+        self.tasks
+            .push(Box::pin(run_recovery(Rc::clone(&self.context), missing)));
+    }
+
+    fn download(&mut self, archives: Vec<(ArchiveKey, Archive)>) {
+        if self.context.server.is_none() {
+            log::warn!("Start with --server to download archives");
+            return;
+        }
+        let wanted: Vec<(ArchiveKey, Archive)> = archives
+            .into_iter()
+            .filter(|(key, archive)| {
+                let file = &self.files[key];
+                matches!(file.status, Status::Missing | Status::Corrupt)
+                    && file.on_server != Some(false)
+                    && !self.is_transferring(archive)
+            })
+            .collect();
+        if wanted.is_empty() {
+            log::warn!(
+                "Nothing to download: the archives shown are on disk, already downloading or not on the server"
+            );
+            return;
+        }
+        for (key, archive) in wanted {
+            let dest = match safe_join(&self.context.destination, &archive.filename) {
+                Ok(dest) => dest,
+                Err(e) => {
+                    log::warn!("Not downloading {}: {}", archive.filename, e);
+                    continue;
+                }
+            };
+            if self.context.dry_run {
+                log::info!(
+                    "Would download {} ({})",
+                    dest.display(),
+                    format_size(archive.size)
+                );
+                continue;
+            }
+            let mut part = dest.clone().into_os_string();
+            part.push(".part");
+            let id = self.queue_transfer(Direction::Download, &archive, Some(part.into()));
+            self.tasks.push(Box::pin(run_download(
+                Rc::clone(&self.context),
+                id,
+                key,
+                archive,
+                dest,
+            )));
+        }
+    }
+
+    fn upload(&mut self, archives: Vec<(ArchiveKey, Archive)>) {
+        if self.context.server.is_none() {
+            log::warn!("Start with --server to upload archives");
+            return;
+        }
+        let wanted: Vec<(ArchiveKey, Archive, PathBuf)> = archives
+            .into_iter()
+            .filter_map(|(key, archive)| {
+                let file = &self.files[&key];
+                let path = file.path.clone()?;
+                (matches!(file.status, Status::Present | Status::Verified)
+                    && file.on_server != Some(true)
+                    && !self.is_transferring(&archive))
+                .then_some((key, archive, path))
+            })
+            .collect();
+        if wanted.is_empty() {
+            log::warn!(
+                "Nothing to upload: the archives shown are missing, corrupt, already uploading or on the server"
+            );
+            return;
+        }
+        for (key, archive, path) in wanted {
+            if self.context.dry_run {
+                log::info!("Would upload {}", path.display());
+                continue;
+            }
+            let id = self.queue_transfer(Direction::Upload, &archive, None);
+            let sent = Arc::clone(&self.transfers[id].sent);
+            self.tasks.push(Box::pin(run_upload(
+                Rc::clone(&self.context),
+                id,
+                sent,
+                key,
+                path,
+            )));
+        }
+    }
+
+    fn queue_transfer(
+        &mut self,
+        direction: Direction,
+        archive: &Archive,
+        part: Option<PathBuf>,
+    ) -> usize {
+        self.transfers.push(Transfer {
+            direction,
+            filename: archive.filename.clone(),
+            size: archive.size,
+            sent: Arc::new(AtomicU64::new(0)),
+            part,
+            state: TransferState::Queued,
+        });
+        self.transfers.len() - 1
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, transfers, log, help] = Layout::vertical([
+            Constraint::Min(8),
+            Constraint::Length(TRANSFER_ROWS as u16 + 2),
+            Constraint::Length(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [modlists, archives] =
+            Layout::horizontal([Constraint::Percentage(25), Constraint::Percentage(75)])
+                .areas(main);
+        self.draw_modlists(frame, modlists);
+        self.draw_archives(frame, archives);
+        self.draw_transfers(frame, transfers);
+
+        let lines: Vec<Line> = self
+            .log
+            .last(log.height.saturating_sub(2) as usize)
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Log ")),
+            log,
+        );
+        frame.render_widget(Paragraph::new(HELP).dim(), help);
+    }
+
+    fn pane(&self, focus: Focus, title: String) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.focus == focus {
+            block.border_style(Style::new().fg(Color::Yellow))
+        } else {
+            block
+        }
+    }
+
+    fn draw_modlists(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .modlists
+            .iter()
+            .map(|modlist| {
+                let count = |status| {
+                    modlist
+                        .archives
+                        .iter()
+                        .filter(|(key, _)| self.files[key].status == status)
+                        .count()
+                };
+                ListItem::new(format!(
+                    "{} ({} missing, {} corrupt)",
+                    modlist.name,
+                    count(Status::Missing),
+                    count(Status::Corrupt)
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(self.pane(Focus::Modlists, " Modlists ".to_string()))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, area, &mut self.modlist_state);
+    }
+
+    fn draw_archives(&mut self, frame: &mut Frame, area: Rect) {
+        let shown = self.shown();
+        let title = format!(
+            " {}: {} ({} of {}) ",
+            self.modlist_state
+                .selected()
+                .and_then(|index| self.modlists.get(index))
+                .map_or("", |modlist| modlist.name.as_str()),
+            self.filter.label(),
+            shown.len(),
+            self.archives().len()
+        );
+        let rows: Vec<Row> = shown
+            .into_iter()
+            .map(|(key, archive)| {
+                let file = &self.files[key];
+                Row::new(vec![
+                    Cell::from(file.status.label()).style(Style::new().fg(file.status.color())),
+                    Cell::from(match file.on_server {
+                        Some(true) => "yes",
+                        Some(false) => "no",
+                        None => "",
+                    }),
+                    Cell::from(format_size(archive.size)),
+                    Cell::from(archive.filename.clone()),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(6),
+                Constraint::Length(10),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["STATUS", "SERVER", "SIZE", "FILENAME"]).bold())
+        .block(self.pane(Focus::Archives, title))
+        .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(table, area, &mut self.archive_state);
+    }
+
+    fn draw_transfers(&self, frame: &mut Frame, area: Rect) {
+        let count = |running: bool| {
+            self.transfers
+                .iter()
+                .filter(|t| match t.state {
+                    TransferState::Running => running,
+                    TransferState::Queued => !running,
+                    _ => false,
+                })
+                .count()
+        };
+        let block = Block::bordered().title(format!(
+            " Transfers: {} running, {} queued ",
+            count(true),
+            count(false)
+        ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let running = self
+            .transfers
+            .iter()
+            .filter(|t| matches!(t.state, TransferState::Running));
+        let queued = self
+            .transfers
+            .iter()
+            .filter(|t| matches!(t.state, TransferState::Queued));
+        let finished = self.transfers.iter().rev().filter(|t| !t.is_active());
+        let shown: Vec<&Transfer> = running
+            .chain(queued)
+            .chain(finished)
+            .take(TRANSFER_ROWS)
+            .collect();
+        let rows = Layout::vertical(vec![Constraint::Length(1); shown.len()]).split(inner);
+        for (transfer, row) in shown.into_iter().zip(rows.iter()) {
+            let arrow = match transfer.direction {
+                Direction::Upload => "↑",
+                Direction::Download => "↓",
+            };
+            if let TransferState::Failed(reason) = &transfer.state {
+                let line = format!("{} {} failed: {}", arrow, transfer.filename, reason);
+                frame.render_widget(Line::from(line).red(), *row);
+                continue;
+            }
+            let done = transfer.progress().min(transfer.size);
+            let ratio = if transfer.size == 0 {
+                1.0
+            } else {
+                done as f64 / transfer.size as f64
+            };
+            let label = format!(
+                "{} {} {} / {}{}",
+                arrow,
+                transfer.filename,
+                format_size(done),
+                format_size(transfer.size),
+                if matches!(transfer.state, TransferState::Queued) {
+                    " (queued)"
+                } else {
+                    ""
+                }
+            );
+            frame.render_widget(
+                LineGauge::default()
+                    .ratio(ratio)
+                    .label(label)
+                    .filled_style(Style::new().fg(Color::Green)),
+                *row,
+            );
+        }
+    }
+}
+
+/// `tui`: loads the modlists, finds their archives in the download
+/// directories and asks the server which it has, then hands the terminal
+/// to the interface until it quits.
+pub async fn run(options: TuiOptions, log: LogBuffer) -> Result<(), String> {
+    let mut modlists = Vec::new();
+    for wabbajack_file in &options.wabbajack_files {
+        let metadata = WabbajackMetadata::load_quietly(wabbajack_file)
+            .map_err(|e| format!("Failed to load {}: {}", wabbajack_file.display(), e))?;
+        modlists.push(ModlistView {
+            name: metadata.name.clone(),
+            archives: metadata
+                .required_archives()
+                .into_iter()
+                .map(|archive| (archive_key(archive), archive.clone()))
+                .collect(),
+        });
+    }
+
+    let mut paths: HashMap<String, PathBuf> = HashMap::new();
+    for dir in &options.download_dirs {
+        let directory = DownloadDirectory::new(dir)
+            .map_err(|e| format!("Failed to open {}: {}", dir.display(), e))?;
+        for path in directory.file_paths() {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                paths.entry(normalize_filename(name)).or_insert(path);
+            }
+        }
+    }
+    let mut files: HashMap<ArchiveKey, FileState> = HashMap::new();
+    for (key, archive) in modlists.iter().flat_map(|modlist| &modlist.archives) {
+        files.entry(key.clone()).or_insert_with(|| {
+            let path = paths.get(&key.0).cloned();
+            // Sizes are cheap to check up front; hashes wait for `v`.
+            let status = match &path {
+                None => Status::Missing,
+                Some(path) if std::fs::metadata(path).is_ok_and(|m| m.len() != archive.size) => {
+                    Status::Corrupt
+                }
+                Some(_) => Status::Present,
+            };
+            FileState {
+                path,
+                status,
+                on_server: None,
+            }
+        });
+    }
+
+    let server = match &options.server {
+        Some(url) => Some(
+            ServerClient::connect(url, &options.client)
+                .await
+                .map_err(|e| format!("Failed to reach server: {}", e))?
+                .with_upload_limit(options.limit_rate),
+        ),
+        None => None,
+    };
+    if let Some(server) = &server {
+        let hashes: Vec<String> = files
+            .keys()
+            .map(|(_, hash)| hash.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let mut available = HashSet::new();
+        for batch in hashes.chunks(CHECK_BATCH_SIZE) {
+            let report = server
+                .check(ArchiveKind::Mod, batch.to_vec())
+                .await
+                .map_err(|e| format!("Hash check failed: {}", e))?;
+            available.extend(report.available);
+        }
+        for ((_, hash), file) in files.iter_mut() {
+            file.on_server = Some(available.contains(hash));
+        }
+    }
+
+    let (updates_tx, mut updates) = mpsc::unbounded_channel();
+    let mut app = App {
+        modlists,
+        files,
+        transfers: Vec::new(),
+        modlist_state: ListState::default().with_selected(Some(0)),
+        archive_state: TableState::default().with_selected(Some(0)),
+        focus: Focus::Archives,
+        filter: Filter::All,
+        log: log.clone(),
+        context: Rc::new(Context {
+            server,
+            destination: options.download_dirs[0].clone(),
+            fix_from: options.fix_from,
+            hashing: Semaphore::new(options.parallel.max(1)),
+            transfers: Semaphore::new(options.transfers.max(1)),
+            caches: RefCell::new(HashMap::new()),
+            dry_run: options.dry_run,
+            updates: updates_tx,
+        }),
+        tasks: FuturesUnordered::new(),
+        quit_warned: false,
+    };
+
+    log.capturing.store(true, Ordering::Relaxed);
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal, &mut updates).await;
+    ratatui::restore();
+    log.capturing.store(false, Ordering::Relaxed);
+
+    // Unfinished transfers stop here; downloads keep their `.part` file.
+    drop(app.tasks);
+    app.context.save_caches();
+    result.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wabba_protocol::test_util::TestArchive;
+
+    const STATUSES: [Status; 5] = [
+        Status::Missing,
+        Status::Present,
+        Status::Hashing,
+        Status::Verified,
+        Status::Corrupt,
+    ];
+
+    /// An app showing one modlist whose archives have `statuses`.
+    fn test_app(statuses: &[Status]) -> App {
+        let mut files = HashMap::new();
+        let archives = statuses
+            .iter()
+            .enumerate()
+            .map(|(index, status)| {
+                let test = TestArchive::new(&format!("Archive{}.7z", index), b"archive");
+                let archive = Archive {
+                    hash: test.hash(),
+                    meta: test.meta.clone(),
+                    filename: test.filename.clone(),
+                    size: test.size(),
+                    state: test.state.clone(),
+                };
+                let key = archive_key(&archive);
+                files.insert(
+                    key.clone(),
+                    FileState {
+                        path: None,
+                        status: *status,
+                        on_server: None,
+                    },
+                );
+                (key, archive)
+            })
+            .collect();
+        let (updates, _) = mpsc::unbounded_channel();
+        App {
+            modlists: vec![ModlistView {
+                name: "Modlist".to_string(),
+                archives,
+            }],
+            files,
+            transfers: Vec::new(),
+            modlist_state: ListState::default().with_selected(Some(0)),
+            archive_state: TableState::default().with_selected(Some(0)),
+            focus: Focus::Archives,
+            filter: Filter::All,
+            log: LogBuffer::default(),
+            context: Rc::new(Context {
+                server: None,
+                destination: PathBuf::from("downloads"),
+                fix_from: Vec::new(),
+                hashing: Semaphore::new(1),
+                transfers: Semaphore::new(1),
+                caches: RefCell::new(HashMap::new()),
+                dry_run: true,
+                updates,
+            }),
+            tasks: FuturesUnordered::new(),
+            quit_warned: false,
+        }
+    }
+
+    fn transfer(state: TransferState) -> Transfer {
+        Transfer {
+            direction: Direction::Upload,
+            filename: "Archive0.7z".to_string(),
+            size: 7,
+            sent: Arc::default(),
+            part: None,
+            state,
+        }
+    }
+
+    fn selected_filenames(app: &App) -> Vec<String> {
+        app.selected()
+            .into_iter()
+            .map(|(_, archive)| archive.filename)
+            .collect()
+    }
+
+    #[test]
+    fn filter_cycles_back_to_all() {
+        let mut filter = Filter::All;
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            seen.push(filter.label());
+            filter = filter.next();
+        }
+        assert!(filter == Filter::All);
+        assert_eq!(
+            seen,
+            vec!["all archives", "missing or corrupt", "missing", "corrupt"]
+        );
+    }
+
+    #[test]
+    fn filters_match_their_statuses() {
+        let matching = |filter: Filter| -> Vec<&'static str> {
+            STATUSES
+                .into_iter()
+                .filter(|status| filter.matches(*status))
+                .map(Status::label)
+                .collect()
+        };
+        assert_eq!(
+            matching(Filter::All),
+            vec!["missing", "present", "hashing", "ok", "corrupt"]
+        );
+        assert_eq!(matching(Filter::Problems), vec!["missing", "corrupt"]);
+        assert_eq!(matching(Filter::Missing), vec!["missing"]);
+        assert_eq!(matching(Filter::Corrupt), vec!["corrupt"]);
+    }
+
+    #[test]
+    fn moving_the_selection_stays_within_the_list() {
+        let mut app = test_app(&[Status::Present, Status::Present, Status::Present]);
+        app.move_selection(-1);
+        assert_eq!(app.archive_state.selected(), Some(0));
+        app.move_selection(isize::MAX);
+        assert_eq!(app.archive_state.selected(), Some(2));
+        app.move_selection(1);
+        assert_eq!(app.archive_state.selected(), Some(2));
+        app.move_selection(isize::MIN);
+        assert_eq!(app.archive_state.selected(), Some(0));
+
+        // Moving between modlists starts at the top of the new one.
+        app.move_selection(PAGE);
+        app.focus = Focus::Modlists;
+        app.move_selection(isize::MAX);
+        assert_eq!(app.modlist_state.selected(), Some(0));
+        assert_eq!(app.archive_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn moving_through_an_empty_list_does_nothing() {
+        let mut app = test_app(&[Status::Present]);
+        app.filter = Filter::Corrupt;
+        for delta in [1, -1, isize::MAX, isize::MIN] {
+            app.move_selection(delta);
+            assert_eq!(app.archive_state.selected(), Some(0));
+        }
+        assert!(app.selected().is_empty());
+
+        let mut app = test_app(&[]);
+        app.focus = Focus::Modlists;
+        app.modlists.clear();
+        app.move_selection(isize::MAX);
+        assert_eq!(app.modlist_state.selected(), Some(0));
+        assert!(app.selected().is_empty());
+    }
+
+    #[test]
+    fn selection_past_the_filtered_list_selects_nothing_until_moved() {
+        let mut app = test_app(&[Status::Present, Status::Missing, Status::Present]);
+        app.move_selection(isize::MAX);
+        assert_eq!(selected_filenames(&app), vec!["Archive2.7z"]);
+
+        // Only one archive is missing, so index 2 points past the list.
+        app.filter = Filter::Missing;
+        assert!(app.selected().is_empty());
+        app.move_selection(0);
+        assert_eq!(app.archive_state.selected(), Some(0));
+        assert_eq!(selected_filenames(&app), vec!["Archive1.7z"]);
+    }
+
+    #[test]
+    fn quitting_with_active_transfers_takes_a_second_press() {
+        let mut app = test_app(&[Status::Present]);
+        app.transfers.push(transfer(TransferState::Done));
+        app.transfers
+            .push(transfer(TransferState::Failed("corrupt".to_string())));
+        assert!(app.confirm_quit());
+
+        let mut app = test_app(&[Status::Present]);
+        app.transfers.push(transfer(TransferState::Running));
+        assert!(!app.confirm_quit());
+        assert!(app.confirm_quit());
+
+        let mut app = test_app(&[Status::Present]);
+        app.transfers.push(transfer(TransferState::Queued));
+        assert!(!app.confirm_quit());
+        // The transfer finishing doesn't take back the warning.
+        app.transfers[0].state = TransferState::Done;
+        assert!(app.confirm_quit());
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use futures_util::{StreamExt, stream};
+use wabba_protocol::api::ArchiveKind;
+use wabba_protocol::hash::Hash;
+
+use crate::api_client::{ServerClient, UploadOutcome};
+use crate::download_dir::DownloadDirectory;
+use crate::report::{TransferReport, format_size};
+
+/// Hashes per `POST /check`, so a huge folder doesn't become one huge
+/// request.
+pub const CHECK_BATCH_SIZE: usize = 1000;
+
+/// The files `sync` (top level only) and `upload-dir` (`recursive`) offer
+/// to the server. wabba-tools' own state files are never among them.
+pub fn upload_candidates(directory: &Path, recursive: bool) -> Vec<PathBuf> {
+    let download_directory =
+        DownloadDirectory::new(&directory.to_path_buf()).expect("Failed to open directory");
+    if recursive {
+        download_directory.file_paths_recursive()
+    } else {
+        download_directory.file_paths()
+    }
+}
+
+struct HashedFile {
+    path: PathBuf,
+    /// Path relative to the uploaded directory, for logs and the report.
+    display: String,
+    size: u64,
+    hash: String,
+}
+
+/// `upload-dir`: hashes every archive under `directory`, asks the server
+/// which it lacks and uploads those, `transfers` at a time. Unlike `sync`,
+/// subdirectories are included and nothing is cached between runs.
+pub async fn upload_directory(
+    server: &ServerClient,
+    directory: &Path,
+    parallel: usize,
+    transfers: usize,
+    dry_run: bool,
+) -> TransferReport {
+    let mut report = TransferReport::new("upload-dir");
+    let files = upload_candidates(directory, true);
+    let total = files.len();
+    log::info!(
+        "Hashing {} files under {} with parallelism={}",
+        total,
+        directory.display(),
+        parallel
+    );
+
+    let mut hashing = stream::iter(files)
+        .map(|path| {
+            let display = path
+                .strip_prefix(directory)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            tokio::task::spawn_blocking(move || {
+                let hashed = std::fs::metadata(&path)
+                    .map_err(|e| format!("stat: {}", e))
+                    .and_then(|metadata| {
+                        Hash::compute_file(&path)
+                            .map(|hash| (metadata.len(), hash))
+                            .map_err(|e| format!("hash: {}", e))
+                    });
+                (path, display, hashed)
+            })
+        })
+        .buffer_unordered(parallel.max(1));
+    let mut hashed = Vec::new();
+    let mut completed = 0usize;
+    while let Some(joined) = hashing.next().await {
+        let (path, display, result) = joined.expect("blocking hash task panicked");
+        completed += 1;
+        match result {
+            Ok((size, hash)) => {
+                log::info!("[{}/{}] Hashed {}", completed, total, display);
+                hashed.push(HashedFile {
+                    path,
+                    display,
+                    size,
+                    hash,
+                });
+            }
+            Err(e) => {
+                log::error!(
+                    "[{}/{}] Failed to hash {}: {}",
+                    completed,
+                    total,
+                    display,
+                    e
+                );
+                report.failed(&display, e);
+            }
+        }
+    }
+
+    let mut available = HashSet::new();
+    for kind in [ArchiveKind::Modlist, ArchiveKind::Mod] {
+        let files: Vec<(String, u64)> = hashed
+            .iter()
+            .filter(|file| ArchiveKind::for_filename(&file.display) == kind)
+            .map(|file| (file.hash.clone(), file.size))
+            .collect();
+        for batch in files.chunks(CHECK_BATCH_SIZE) {
+            match server.check_files(kind, batch).await {
+                Ok(batch_report) => available.extend(batch_report.available),
+                Err(e) => {
+                    log::error!("Hash check failed: {}", e);
+                    for file in &hashed {
+                        report.failed(&file.display, format!("hash check failed: {}", e));
+                    }
+                    return report;
+                }
+            }
+        }
+    }
+
+    // The same archive can sit in several subdirectories; send it once.
+    let mut seen = HashSet::new();
+    let mut needed = Vec::new();
+    for file in hashed {
+        if available.contains(&file.hash) || !seen.insert(file.hash.clone()) {
+            report.skipped(&file.display);
+        } else {
+            needed.push(file);
+        }
+    }
+    log::info!(
+        "{} of {} files are new to the server ({})",
+        needed.len(),
+        total,
+        format_size(needed.iter().map(|file| file.size).sum())
+    );
+
+    if dry_run {
+        for file in &needed {
+            println!("Would upload {} ({})", file.display, format_size(file.size));
+        }
+        return report;
+    }
+
+    let count = needed.len();
+    let mut uploads = stream::iter(needed.into_iter().enumerate())
+        .map(|(idx, file)| async move {
+            log::info!("[{}/{}] Uploading {}", idx + 1, count, file.display);
+            let outcome = server.submit(&file.path, &file.hash).await;
+            (file, outcome)
+        })
+        .buffer_unordered(transfers.max(1));
+    while let Some((file, outcome)) = uploads.next().await {
+        match outcome {
+            Ok(UploadOutcome::Uploaded(result)) => {
+                log::info!("Uploaded {} as {}", file.display, result.filename);
+                report.transferred(&file.display, result.size);
+            }
+            Ok(UploadOutcome::AlreadyPresent) => {
+                log::info!("Server reported {} already present", file.display);
+                report.skipped(&file.display);
+            }
+            Ok(UploadOutcome::Failed(code, body)) => {
+                log::error!("Upload of {} failed: {} — {}", file.display, code, body);
+                report.failed(&file.display, format!("{}: {}", code, body));
+            }
+            Err(e) => {
+                log::error!("Upload error for {}: {}", file.display, e);
+                report.failed(&file.display, e);
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync_cache::CACHE_FILENAME;
+    use crate::verify::MANIFEST_FILENAME;
+
+    #[test]
+    fn sync_and_upload_dir_skip_tool_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        for parent in [dir.path(), nested.as_path()] {
+            std::fs::write(parent.join("Mod.7z"), b"mod").unwrap();
+            std::fs::write(parent.join(CACHE_FILENAME), b"{}").unwrap();
+            std::fs::write(parent.join(MANIFEST_FILENAME), b"{}").unwrap();
+        }
+
+        assert_eq!(
+            upload_candidates(dir.path(), false),
+            vec![dir.path().join("Mod.7z")]
+        );
+        assert_eq!(
+            upload_candidates(dir.path(), true),
+            vec![dir.path().join("Mod.7z"), nested.join("Mod.7z")]
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! `verify`: rehashes every archive in a download directory to catch files
+//! that rotted on disk after they were last known good. Files are checked
+//! against a manifest written by an earlier run, or against the size and
+//! hash the server has recorded for archives of the same name.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use futures_util::{StreamExt, stream};
+use wabba_protocol::api::ModSummary;
+use wabba_protocol::filename::normalize_filename;
+use wabba_protocol::hash::Hash;
+
+use crate::download_dir::DownloadDirectory;
+use crate::report::{FailedFile, OutputFormat, format_size, print_table};
+use crate::sync_cache::file_fingerprint;
+
+pub const MANIFEST_FILENAME: &str = ".wabba-manifest.json";
+
+/// The size, modification time and hash of each file in a download
+/// directory when it was last verified.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub size: u64,
+    pub mtime_nanos: i128,
+    pub hash: String,
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`. A missing file is an empty manifest,
+    /// so the first `verify --write-manifest` can create it.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s)
+                .map_err(|e| format!("Manifest at {} is unreadable: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!(
+                "Failed to read manifest at {}: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    /// Writes through a temporary file, like the sync cache, so an
+    /// interrupted run never leaves half a manifest behind.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        let json = serde_json::to_string_pretty(self).expect("Manifest serializes");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// What the files are checked against.
+pub enum Reference {
+    Manifest(Manifest),
+    /// Every archive the server knows, keyed by normalized filename. One
+    /// name can belong to several versions of a mod.
+    Server(HashMap<String, Vec<ModSummary>>),
+}
+
+impl Reference {
+    pub fn from_server(mods: Vec<ModSummary>) -> Self {
+        let mut by_name: HashMap<String, Vec<ModSummary>> = HashMap::new();
+        for mod_summary in mods {
+            by_name
+                .entry(normalize_filename(&mod_summary.filename))
+                .or_default()
+                .push(mod_summary);
+        }
+        Reference::Server(by_name)
+    }
+
+    /// The (size, hash) pairs `filename` may have, and for manifests the
+    /// modification time it was recorded with.
+    fn expected(&self, filename: &str) -> Vec<(u64, &str, Option<i128>)> {
+        match self {
+            Reference::Manifest(manifest) => manifest
+                .files
+                .get(filename)
+                .map(|entry| (entry.size, entry.hash.as_str(), Some(entry.mtime_nanos)))
+                .into_iter()
+                .collect(),
+            Reference::Server(by_name) => by_name
+                .get(filename)
+                .into_iter()
+                .flatten()
+                .map(|mod_summary| (mod_summary.size, mod_summary.hash.as_str(), None))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct BadFile {
+    pub filename: String,
+    pub size: u64,
+    pub hash: String,
+    pub expected_size: u64,
+    pub expected_hash: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct VerifyReport {
+    pub ok: Vec<String>,
+    /// The right size but the wrong hash.
+    pub corrupted: Vec<BadFile>,
+    /// Shorter than expected.
+    pub truncated: Vec<BadFile>,
+    /// Different from the manifest but modified since it was written, so
+    /// replaced on purpose rather than damaged.
+    pub changed: Vec<String>,
+    /// In the manifest but no longer in the directory.
+    pub missing: Vec<String>,
+    /// Neither the manifest nor the server knows these files.
+    pub unknown: Vec<String>,
+    pub failed: Vec<FailedFile>,
+}
+
+impl VerifyReport {
+    pub fn has_problems(&self) -> bool {
+        !self.corrupted.is_empty()
+            || !self.truncated.is_empty()
+            || !self.missing.is_empty()
+            || !self.failed.is_empty()
+    }
+
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Log => {
+                for file in &self.corrupted {
+                    log::error!(
+                        "Corrupted: {} hashes to {}, expected {}",
+                        file.filename,
+                        file.hash,
+                        file.expected_hash
+                    );
+                }
+                for file in &self.truncated {
+                    log::error!(
+                        "Truncated: {} is {} of {} bytes",
+                        file.filename,
+                        file.size,
+                        file.expected_size
+                    );
+                }
+                for filename in &self.missing {
+                    log::error!("Missing: {}", filename);
+                }
+                for failure in &self.failed {
+                    log::error!("Failed to verify {}: {}", failure.filename, failure.reason);
+                }
+                log::info!(
+                    "{} ok, {} corrupted, {} truncated, {} changed, {} missing, {} unknown",
+                    self.ok.len(),
+                    self.corrupted.len(),
+                    self.truncated.len(),
+                    self.changed.len(),
+                    self.missing.len(),
+                    self.unknown.len()
+                );
+            }
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(self).expect("Failed to serialize report")
+            ),
+            OutputFormat::Table => self.print_table(),
+        }
+    }
+
+    fn print_table(&self) {
+        let mut rows: Vec<[String; 4]> = vec![[
+            "STATUS".to_string(),
+            "FILENAME".to_string(),
+            "SIZE".to_string(),
+            "EXPECTED".to_string(),
+        ]];
+        for (status, files) in [
+            ("corrupted", &self.corrupted),
+            ("truncated", &self.truncated),
+        ] {
+            for file in files {
+                rows.push([
+                    status.to_string(),
+                    file.filename.clone(),
+                    file.size.to_string(),
+                    format!("{} ({})", file.expected_hash, file.expected_size),
+                ]);
+            }
+        }
+        let name_rows = [
+            ("missing", &self.missing),
+            ("changed", &self.changed),
+            ("unknown", &self.unknown),
+            ("ok", &self.ok),
+        ];
+        for (status, filenames) in name_rows {
+            for filename in filenames {
+                rows.push([
+                    status.to_string(),
+                    filename.clone(),
+                    String::new(),
+                    String::new(),
+                ]);
+            }
+        }
+        for failure in &self.failed {
+            rows.push([
+                "failed".to_string(),
+                failure.filename.clone(),
+                String::new(),
+                failure.reason.clone(),
+            ]);
+        }
+        print_table(&rows);
+    }
+}
+
+struct HashedFile {
+    filename: String,
+    entry: ManifestEntry,
+}
+
+/// `verify`: hashes every top-level archive in `directory`, `parallel` at
+/// a time, and sorts them by how they compare to `reference`. The hash
+/// cache is deliberately not used, since a rotted file keeps its size and
+/// modification time. With `update` (and a manifest reference), the
+/// manifest is brought up to date afterwards: new and replaced files are
+/// recorded and vanished ones dropped, but damaged files keep their old
+/// entry so they stay flagged until they are replaced.
+pub async fn verify_directory(
+    directory: &Path,
+    reference: &mut Reference,
+    parallel: usize,
+    update: Option<&Path>,
+    dry_run: bool,
+) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    let files: Vec<PathBuf> = DownloadDirectory::new(&directory.to_path_buf())
+        .expect("Failed to open directory")
+        .file_paths()
+        .into_iter()
+        .filter(|path| {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            !name.ends_with(".part")
+        })
+        .collect();
+    let total = files.len();
+    log::info!(
+        "Verifying {} files in {} with parallelism={}",
+        total,
+        directory.display(),
+        parallel
+    );
+
+    let mut hashing = stream::iter(files)
+        .map(|path| {
+            tokio::task::spawn_blocking(move || {
+                let filename =
+                    normalize_filename(&path.file_name().unwrap_or_default().to_string_lossy());
+                let hashed = fs::metadata(&path)
+                    .map_err(|e| format!("stat: {}", e))
+                    .and_then(|metadata| {
+                        let (size, mtime_nanos) = file_fingerprint(&metadata);
+                        Hash::compute_file(&path)
+                            .map(|hash| ManifestEntry {
+                                size,
+                                mtime_nanos,
+                                hash,
+                            })
+                            .map_err(|e| format!("hash: {}", e))
+                    });
+                (filename, hashed)
+            })
+        })
+        .buffer_unordered(parallel.max(1));
+    let mut hashed = Vec::new();
+    let mut completed = 0usize;
+    while let Some(joined) = hashing.next().await {
+        let (filename, result) = joined.expect("blocking hash task panicked");
+        completed += 1;
+        match result {
+            Ok(entry) => {
+                log::debug!("[{}/{}] Hashed {}", completed, total, filename);
+                hashed.push(HashedFile { filename, entry });
+            }
+            Err(e) => {
+                log::error!(
+                    "[{}/{}] Failed to hash {}: {}",
+                    completed,
+                    total,
+                    filename,
+                    e
+                );
+                report.failed.push(FailedFile {
+                    filename,
+                    reason: e,
+                });
+            }
+        }
+    }
+    hashed.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    for file in &hashed {
+        let expected = reference.expected(&file.filename);
+        if expected.is_empty() {
+            report.unknown.push(file.filename.clone());
+            continue;
+        }
+        if expected.iter().any(|(_, hash, _)| *hash == file.entry.hash) {
+            report.ok.push(file.filename.clone());
+            continue;
+        }
+        let (expected_size, expected_hash, recorded_mtime) = expected
+            .iter()
+            .find(|(size, _, _)| *size == file.entry.size)
+            .unwrap_or(&expected[0]);
+        if recorded_mtime.is_some_and(|mtime| mtime != file.entry.mtime_nanos) {
+            report.changed.push(file.filename.clone());
+            continue;
+        }
+        let bad = BadFile {
+            filename: file.filename.clone(),
+            size: file.entry.size,
+            hash: file.entry.hash.clone(),
+            expected_size: *expected_size,
+            expected_hash: expected_hash.to_string(),
+        };
+        if file.entry.size < *expected_size {
+            report.truncated.push(bad);
+        } else {
+            report.corrupted.push(bad);
+        }
+    }
+
+    if let Reference::Manifest(manifest) = reference {
+        let on_disk: HashMap<&str, &ManifestEntry> = hashed
+            .iter()
+            .map(|file| (file.filename.as_str(), &file.entry))
+            .collect();
+        let unreadable: Vec<&str> = report.failed.iter().map(|f| f.filename.as_str()).collect();
+        report.missing = manifest
+            .files
+            .keys()
+            .filter(|name| {
+                !on_disk.contains_key(name.as_str()) && !unreadable.contains(&name.as_str())
+            })
+            .cloned()
+            .collect();
+
+        if let Some(path) = update {
+            let damaged = report.corrupted.len() + report.truncated.len();
+            for name in &report.missing {
+                manifest.files.remove(name);
+            }
+            for name in report
+                .ok
+                .iter()
+                .chain(&report.unknown)
+                .chain(&report.changed)
+            {
+                manifest
+                    .files
+                    .insert(name.clone(), on_disk[name.as_str()].clone());
+            }
+            if dry_run {
+                println!(
+                    "Would write {} entries to {} ({} damaged files keep their old entry)",
+                    manifest.len(),
+                    path.display(),
+                    damaged
+                );
+            } else {
+                match manifest.save(path) {
+                    Ok(()) => log::info!(
+                        "Wrote {} entries to {} ({})",
+                        manifest.len(),
+                        path.display(),
+                        format_size(manifest.files.values().map(|entry| entry.size).sum())
+                    ),
+                    Err(e) => log::error!("Failed to write manifest to {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+    report
+}
@@ -0,0 +1,130 @@
+//! `validate --watch`: keeps checking the download directories as files
+//! land in them, for while the last few archives are being downloaded by
+//! hand.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use wabba_protocol::filename::normalize_filename;
+use wabba_protocol::wabbajack::Archive;
+
+use crate::download_dir::DownloadDirectory;
+use crate::notify::desktop_notification;
+
+/// How long the directories have to stay quiet before they are rescanned.
+/// Browsers touch a file many times while saving it.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// The size of every top-level file in `download_dirs`, by normalized
+/// name. Earlier directories win, as in `validate`.
+fn scan(download_dirs: &[PathBuf]) -> HashMap<String, u64> {
+    let mut sizes = HashMap::new();
+    for dir in download_dirs {
+        let Ok(download_directory) = DownloadDirectory::new(dir) else {
+            continue;
+        };
+        for path in download_directory.file_paths() {
+            let (Some(name), Ok(metadata)) = (
+                path.file_name().and_then(|n| n.to_str()),
+                std::fs::metadata(&path),
+            ) else {
+                continue;
+            };
+            sizes
+                .entry(normalize_filename(name))
+                .or_insert(metadata.len());
+        }
+    }
+    sizes
+}
+
+/// Clears the status line so a message can be printed above it.
+fn clear_status() {
+    eprint!("\r\x1b[2K");
+}
+
+fn print_status(satisfied: usize, total: usize) {
+    clear_status();
+    eprint!("{} of {} archives satisfied", satisfied, total);
+    let _ = std::io::stderr().flush();
+}
+
+/// Rescans `download_dirs` whenever something in them changes and prints
+/// how many of `required` are there, until all of them are. An archive
+/// counts once a file of its name has its full size, so downloads still
+/// being written aren't counted early.
+pub async fn watch_download_dirs(required: &[&Archive], download_dirs: &[PathBuf], notify: bool) {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let handler = move |event: notify::Result<notify::Event>| {
+        let _ = tx.send(event);
+    };
+    let mut watcher = match notify::recommended_watcher(handler) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            log::error!("Failed to watch the download directories: {}", e);
+            return;
+        }
+    };
+    for dir in download_dirs {
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            log::error!("Failed to watch {}: {}", dir.display(), e);
+            return;
+        }
+    }
+    log::info!(
+        "Watching {} download directories, press Ctrl-C to stop",
+        download_dirs.len()
+    );
+
+    let present = |sizes: &HashMap<String, u64>, archive: &Archive| {
+        sizes.get(&normalize_filename(&archive.filename)) == Some(&archive.size)
+    };
+    let sizes = scan(download_dirs);
+    let mut satisfied: Vec<bool> = required
+        .iter()
+        .map(|archive| present(&sizes, archive))
+        .collect();
+    loop {
+        let count = satisfied.iter().filter(|s| **s).count();
+        print_status(count, required.len());
+        if count == required.len() {
+            eprintln!();
+            log::info!("Every required archive is present");
+            if notify {
+                desktop_notification(
+                    "wabba-tools validate finished",
+                    "Every required archive is present",
+                );
+            }
+            return;
+        }
+
+        match rx.recv().await {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => {
+                clear_status();
+                log::warn!("Error watching the download directories: {}", e);
+            }
+            None => return,
+        }
+        // Wait for the burst of events a single download makes to end.
+        while let Ok(Some(_)) = tokio::time::timeout(SETTLE_TIME, rx.recv()).await {}
+
+        let sizes = scan(download_dirs);
+        for (archive, satisfied) in required.iter().zip(satisfied.iter_mut()) {
+            let now = present(&sizes, archive);
+            if now != *satisfied {
+                clear_status();
+                if now {
+                    println!("Found {}", archive.filename);
+                } else {
+                    println!("Lost {}", archive.filename);
+                }
+                *satisfied = now;
+            }
+        }
+    }
+}